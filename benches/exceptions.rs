@@ -0,0 +1,99 @@
+//! Measures what the exception handling proposal costs to the calls made
+//! within a `try` block, which go through the `wasmer_invoke` builtin to
+//! catch what the callee throws, and to the exceptions themselves.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+use wasmer_engine_jit::JIT;
+
+static EXCEPTIONS_WAT: &str = r#"(module
+    (event $e (param i32))
+
+    (func $add (param i32) (result i32)
+        (i32.add (local.get 0) (i32.const 1)))
+
+    (func $throw (param i32) (result i32)
+        (throw $e (local.get 0)))
+
+    ;; Each of the functions below calls its callee 1000 times.
+    (func (export "call") (result i32)
+        (local $i i32)
+        (local $sum i32)
+        (loop $continue
+            (local.set $sum (call $add (local.get $sum)))
+            (br_if $continue
+                (i32.ne (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                        (i32.const 1000))))
+        (local.get $sum))
+
+    (func (export "call_in_try") (result i32)
+        (local $i i32)
+        (local $sum i32)
+        (loop $continue
+            (local.set $sum
+                (try (result i32)
+                    (do (call $add (local.get $sum)))
+                    (catch $e)))
+            (br_if $continue
+                (i32.ne (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                        (i32.const 1000))))
+        (local.get $sum))
+
+    (func (export "throw_and_catch") (result i32)
+        (local $i i32)
+        (local $sum i32)
+        (loop $continue
+            (local.set $sum
+                (try (result i32)
+                    (do (call $throw (local.get $sum)))
+                    (catch $e (i32.add (i32.const 1)))))
+            (br_if $continue
+                (i32.ne (local.tee $i (i32.add (local.get $i) (i32.const 1)))
+                        (i32.const 1000))))
+        (local.get $sum))
+)"#;
+
+pub fn run_exceptions(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(store, EXCEPTIONS_WAT).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+
+    for name in &["call", "call_in_try", "throw_and_catch"] {
+        let f: NativeFunc<(), i32> = instance.exports.get_native_function(name).unwrap();
+        c.bench_function(&format!("1000x {} {}", name, compiler_name), |b| {
+            b.iter(|| {
+                let result = black_box(f.call().unwrap());
+                assert_eq!(result, 1000);
+            })
+        });
+    }
+}
+
+fn run_exceptions_benchmarks(c: &mut Criterion) {
+    let mut features = Features::default();
+    features.exceptions(true);
+
+    #[cfg(feature = "llvm")]
+    {
+        let store = Store::new(
+            &JIT::new(wasmer_compiler_llvm::LLVM::new())
+                .features(features.clone())
+                .engine(),
+        );
+        run_exceptions(&store, "llvm", c);
+    }
+
+    #[cfg(feature = "cranelift")]
+    {
+        let store = Store::new(
+            &JIT::new(wasmer_compiler_cranelift::Cranelift::new())
+                .features(features.clone())
+                .engine(),
+        );
+        run_exceptions(&store, "cranelift", c);
+    }
+}
+
+criterion_group!(benches, run_exceptions_benchmarks);
+
+criterion_main!(benches);
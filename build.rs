@@ -45,6 +45,16 @@ fn main() -> anyhow::Result<()> {
                     wast_processor,
                 )?;
                 test_directory_module(spectests, "tests/wast/spec/proposals/simd", wast_processor)?;
+                test_directory_module(
+                    spectests,
+                    "tests/wast/spec/proposals/reference-types",
+                    wast_processor,
+                )?;
+                test_directory_module(
+                    spectests,
+                    "tests/wast/spec/proposals/tail-call",
+                    wast_processor,
+                )?;
                 // test_directory_module(spectests, "tests/wast/spec/proposals/bulk-memory-operations", wast_processor)?;
                 Ok(())
             })?;
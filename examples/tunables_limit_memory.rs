@@ -6,8 +6,8 @@ use loupe::{MemoryUsage, MemoryUsageTracker};
 use wasmer::{
     imports,
     vm::{self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition},
-    wat2wasm, BaseTunables, Instance, Memory, MemoryType, Module, Pages, Store, TableType, Target,
-    Tunables,
+    wat2wasm, BaseTunables, Instance, InstancePool, Memory, MemoryType, Module, Pages, Store,
+    TableType, Target, Tunables,
 };
 use wasmer_compiler_cranelift::Cranelift;
 use wasmer_engine_jit::JIT;
@@ -81,6 +81,27 @@ impl<T: Tunables> Tunables for LimitingTunables<T> {
         self.base.table_style(table)
     }
 
+    /// The maximum size of the stack WebAssembly code may use.
+    ///
+    /// Delegated to base.
+    fn max_wasm_stack(&self) -> Option<usize> {
+        self.base.max_wasm_stack()
+    }
+
+    /// The pool to allocate instances from.
+    ///
+    /// Delegated to base.
+    fn instance_pool(&self) -> Option<&Arc<InstancePool>> {
+        self.base.instance_pool()
+    }
+
+    /// Whether to initialize memories from memory images.
+    ///
+    /// Delegated to base.
+    fn use_memory_images(&self) -> bool {
+        self.base.use_memory_images()
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     ///
     /// The requested memory type is validated, adjusted to the limited and then passed to base.
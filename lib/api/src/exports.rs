@@ -1,5 +1,7 @@
-use crate::externals::{Extern, Function, Global, Memory, Table};
+use crate::externals::{Extern, Function, Global, Memory, Table, Tag};
 use crate::import_object::LikeNamespace;
+use crate::instance::Instance;
+use crate::module::Module;
 use crate::native::NativeFunc;
 use crate::WasmTypeList;
 use indexmap::IndexMap;
@@ -133,6 +135,21 @@ impl Exports {
         self.get(name)
     }
 
+    /// Get an export as a `Tag`.
+    pub fn get_tag(&self, name: &str) -> Result<&Tag, ExportError> {
+        self.get(name)
+    }
+
+    /// Get an export as an `Instance`.
+    pub fn get_instance(&self, name: &str) -> Result<&Instance, ExportError> {
+        self.get(name)
+    }
+
+    /// Get an export as a `Module`.
+    pub fn get_module(&self, name: &str) -> Result<&Module, ExportError> {
+        self.get(name)
+    }
+
     /// Get an export as a `Func`.
     pub fn get_function(&self, name: &str) -> Result<&Function, ExportError> {
         self.get(name)
@@ -255,6 +272,14 @@ where
             _ => None,
         })
     }
+
+    /// Get only the tags.
+    pub fn tags(self) -> impl Iterator<Item = (&'a String, &'a Tag)> + Sized {
+        self.iter.filter_map(|(name, export)| match export {
+            Extern::Tag(tag) => Some((name, tag)),
+            _ => None,
+        })
+    }
 }
 
 impl FromIterator<(String, Extern)> for Exports {
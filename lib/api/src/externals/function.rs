@@ -1,5 +1,6 @@
 use crate::exports::{ExportError, Exportable};
 use crate::externals::Extern;
+use crate::fiber::block_on;
 use crate::store::Store;
 use crate::types::Val;
 use crate::FunctionType;
@@ -13,6 +14,7 @@ pub use inner::{UnsafeMutableEnv, WithUnsafeMutableEnv};
 use std::cmp::max;
 use std::ffi::c_void;
 use std::fmt;
+use std::future::Future;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
@@ -203,6 +205,37 @@ impl Function {
         }
     }
 
+    /// Creates a new asynchronous host `Function` (dynamic) with the
+    /// provided signature.
+    ///
+    /// The function returns a future of its results. It can only be
+    /// called by WebAssembly code started with [`NativeFunc::call_async`]:
+    /// while the future is pending, the WebAssembly code is suspended and
+    /// the future returned by `call_async` is pending too. Calling it in
+    /// any other way fails with a `RuntimeError`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_async(&store, &signature, |args| {
+    ///     let value = args[0].unwrap_i32();
+    ///     async move { Ok(vec![Value::I32(value + 1)]) }
+    /// });
+    /// ```
+    pub fn new_async<FT, F, Fut>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<Vec<Val>, RuntimeError>> + 'static + Send,
+    {
+        Self::new(store, ty, move |args| block_on(func(args))?)
+    }
+
     /// Creates a new host `Function` (dynamic) with the provided signature and environment.
     ///
     /// If you know the signature of the host function at compile time,
@@ -529,14 +562,14 @@ impl Function {
         }
 
         // Call the trampoline.
-        if let Err(error) = unsafe {
+        if let Err(error) = self.store.interrupts().with_stack_limit(|| unsafe {
             wasmer_call_trampoline(
                 self.exported.vm_function.vmctx,
                 func.trampoline,
                 self.exported.vm_function.address,
                 values_vec.as_mut_ptr() as *mut u8,
             )
-        } {
+        }) {
             return Err(RuntimeError::from_trap(error));
         }
 
@@ -2,12 +2,11 @@ use crate::exports::{ExportError, Exportable};
 use crate::externals::Extern;
 use crate::store::Store;
 use crate::{MemoryType, MemoryView};
-use std::convert::TryInto;
 use std::slice;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportMemory};
 use wasmer_types::{Pages, ValueType};
-use wasmer_vm::{Memory as RuntimeMemory, MemoryError, VMExportMemory};
+use wasmer_vm::{LimitedMemory, Memory as RuntimeMemory, MemoryError, VMExportMemory};
 
 /// A WebAssembly `memory` instance.
 ///
@@ -46,7 +45,19 @@ impl Memory {
     pub fn new(store: &Store, ty: MemoryType) -> Result<Self, MemoryError> {
         let tunables = store.tunables();
         let style = tunables.memory_style(&ty);
-        let memory = tunables.create_host_memory(&ty, &style)?;
+        let limiter = store.resource_limiter();
+        if let Some(limiter) = &limiter {
+            LimitedMemory::check_creation(limiter.as_ref(), &ty)?;
+        }
+        let mut memory = tunables.create_host_memory(&ty, &style).map_err(|error| {
+            if let Some(limiter) = &limiter {
+                limiter.memory_grow_failed(&error);
+            }
+            error
+        })?;
+        if let Some(limiter) = limiter {
+            memory = Arc::new(LimitedMemory::new(memory, limiter));
+        }
 
         Ok(Self {
             store: store.clone(),
@@ -111,7 +122,7 @@ impl Memory {
     pub unsafe fn data_unchecked_mut(&self) -> &mut [u8] {
         let definition = self.memory.vmmemory();
         let def = definition.as_ref();
-        slice::from_raw_parts_mut(def.base, def.current_length.try_into().unwrap())
+        slice::from_raw_parts_mut(def.base, def.current_length)
     }
 
     /// Returns the pointer to the raw bytes of the `Memory`.
@@ -125,7 +136,7 @@ impl Memory {
     pub fn data_size(&self) -> u64 {
         let definition = self.memory.vmmemory();
         let def = unsafe { definition.as_ref() };
-        def.current_length.into()
+        def.current_length as u64
     }
 
     /// Returns the size (in [`Pages`]) of the `Memory`.
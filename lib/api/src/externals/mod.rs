@@ -2,6 +2,7 @@ pub(crate) mod function;
 mod global;
 mod memory;
 mod table;
+mod tag;
 
 pub use self::function::{
     FromToNativeWasmType, Function, HostFunction, WasmTypeList, WithEnv, WithoutEnv,
@@ -12,8 +13,11 @@ pub use self::function::{UnsafeMutableEnv, WithUnsafeMutableEnv};
 pub use self::global::Global;
 pub use self::memory::Memory;
 pub use self::table::Table;
+pub use self::tag::{Exception, Tag};
 
 use crate::exports::{ExportError, Exportable};
+use crate::instance::Instance;
+use crate::module::Module;
 use crate::store::{Store, StoreObject};
 use crate::ExternType;
 use std::fmt;
@@ -33,6 +37,12 @@ pub enum Extern {
     Table(Table),
     /// A external [`Memory`].
     Memory(Memory),
+    /// A external [`Tag`].
+    Tag(Tag),
+    /// A external [`Instance`], for the module linking proposal.
+    Instance(Instance),
+    /// A external [`Module`], for the module linking proposal.
+    Module(Module),
 }
 
 impl Extern {
@@ -43,6 +53,9 @@ impl Extern {
             Self::Memory(ft) => ExternType::Memory(*ft.ty()),
             Self::Table(tt) => ExternType::Table(*tt.ty()),
             Self::Global(gt) => ExternType::Global(*gt.ty()),
+            Self::Tag(tt) => ExternType::Tag(tt.ty().clone()),
+            Self::Instance(instance) => ExternType::Instance(instance.ty()),
+            Self::Module(module) => ExternType::Module(module.ty()),
         }
     }
 
@@ -53,6 +66,9 @@ impl Extern {
             Export::Memory(m) => Self::Memory(Memory::from_vm_export(store, m)),
             Export::Global(g) => Self::Global(Global::from_vm_export(store, g)),
             Export::Table(t) => Self::Table(Table::from_vm_export(store, t)),
+            Export::Tag(t) => Self::Tag(Tag::from_vm_export(store, t)),
+            Export::Instance(i) => Self::Instance(Instance::from_vm_export(i)),
+            Export::Module(m) => Self::Module(Module::from_vm_export(m)),
         }
     }
 }
@@ -64,6 +80,9 @@ impl<'a> Exportable<'a> for Extern {
             Self::Global(g) => g.to_export(),
             Self::Memory(m) => m.to_export(),
             Self::Table(t) => t.to_export(),
+            Self::Tag(t) => t.to_export(),
+            Self::Instance(i) => i.to_export(),
+            Self::Module(m) => m.to_export(),
         }
     }
 
@@ -80,6 +99,9 @@ impl StoreObject for Extern {
             Self::Global(g) => g.store(),
             Self::Memory(m) => m.store(),
             Self::Table(t) => t.store(),
+            Self::Tag(t) => t.store(),
+            Self::Instance(i) => i.store(),
+            Self::Module(m) => m.store(),
         };
         Store::same(my_store, store)
     }
@@ -95,6 +117,9 @@ impl fmt::Debug for Extern {
                 Self::Global(_) => "Global(...)",
                 Self::Memory(_) => "Memory(...)",
                 Self::Table(_) => "Table(...)",
+                Self::Tag(_) => "Tag(...)",
+                Self::Instance(_) => "Instance(...)",
+                Self::Module(_) => "Module(...)",
             }
         )
     }
@@ -123,3 +148,21 @@ impl From<Table> for Extern {
         Self::Table(r)
     }
 }
+
+impl From<Tag> for Extern {
+    fn from(r: Tag) -> Self {
+        Self::Tag(r)
+    }
+}
+
+impl From<Instance> for Extern {
+    fn from(r: Instance) -> Self {
+        Self::Instance(r)
+    }
+}
+
+impl From<Module> for Extern {
+    fn from(r: Module) -> Self {
+        Self::Module(r)
+    }
+}
@@ -6,7 +6,7 @@ use crate::RuntimeError;
 use crate::TableType;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportTable};
-use wasmer_vm::{Table as RuntimeTable, VMCallerCheckedAnyfunc, VMExportTable};
+use wasmer_vm::{LimitedTable, Table as RuntimeTable, VMCallerCheckedAnyfunc, VMExportTable};
 
 /// A WebAssembly `table` instance.
 ///
@@ -42,9 +42,19 @@ impl Table {
         let item = init.into_checked_anyfunc(store)?;
         let tunables = store.tunables();
         let style = tunables.table_style(&ty);
-        let table = tunables
-            .create_host_table(&ty, &style)
-            .map_err(RuntimeError::new)?;
+        let limiter = store.resource_limiter();
+        if let Some(limiter) = &limiter {
+            LimitedTable::check_creation(limiter.as_ref(), &ty).map_err(RuntimeError::new)?;
+        }
+        let mut table = tunables.create_host_table(&ty, &style).map_err(|error| {
+            if let Some(limiter) = &limiter {
+                limiter.table_grow_failed();
+            }
+            RuntimeError::new(error)
+        })?;
+        if let Some(limiter) = limiter {
+            table = Arc::new(LimitedTable::new(table, limiter));
+        }
 
         let num_elements = table.size();
         for i in 0..num_elements {
@@ -0,0 +1,224 @@
+use crate::exports::{ExportError, Exportable};
+use crate::externals::Extern;
+use crate::store::Store;
+use crate::types::Val;
+use crate::RuntimeError;
+use crate::TagType;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_engine::{Export, ExportTag};
+use wasmer_vm::{Tag as RuntimeTag, Trap, VMException, VMExportTag};
+
+/// A WebAssembly exception `tag` instance.
+///
+/// A tag identifies the exceptions thrown with it, and the types of the
+/// values they carry. Two tags with the same type are still distinct: an
+/// exception is only caught by the `catch` clauses referring to the tag it
+/// was thrown with.
+///
+/// Spec: <https://webassembly.github.io/exception-handling/core/exec/runtime.html#tag-instances>
+#[derive(Clone)]
+pub struct Tag {
+    store: Store,
+    tag: Arc<RuntimeTag>,
+}
+
+impl Tag {
+    /// Create a new `Tag` with the provided [`TagType`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType, Type};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new(vec![Type::I32]));
+    ///
+    /// assert_eq!(t.ty().params(), &[Type::I32]);
+    /// ```
+    pub fn new(store: &Store, ty: TagType) -> Self {
+        Self {
+            store: store.clone(),
+            tag: Arc::new(RuntimeTag::new(ty)),
+        }
+    }
+
+    /// Returns the [`TagType`] of the `Tag`.
+    pub fn ty(&self) -> &TagType {
+        self.tag.ty()
+    }
+
+    /// Returns the [`Store`] where the `Tag` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportTag) -> Self {
+        Self {
+            store: store.clone(),
+            tag: wasmer_export.vm_tag.from,
+        }
+    }
+
+    /// Returns whether or not these two tags are the same tag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, TagType};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new(vec![]));
+    /// let u = Tag::new(&store, TagType::new(vec![]));
+    ///
+    /// assert!(t.same(&t));
+    /// assert!(!t.same(&u));
+    /// ```
+    pub fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tag, &other.tag)
+    }
+}
+
+impl fmt::Debug for Tag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Tag")
+            .field("ty", &self.ty())
+            .finish()
+    }
+}
+
+impl<'a> Exportable<'a> for Tag {
+    fn to_export(&self) -> Export {
+        ExportTag {
+            vm_tag: VMExportTag {
+                from: self.tag.clone(),
+                instance_ref: None,
+            },
+        }
+        .into()
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Tag(tag) => Ok(tag),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+}
+
+/// A WebAssembly exception.
+///
+/// Exceptions thrown by WebAssembly and not caught by it surface as a
+/// [`RuntimeError`], from which they can be recovered with
+/// [`Exception::from_runtime_error`]. Host functions throw exceptions by
+/// returning them as their error, thanks to `From<Exception>` being
+/// implemented for [`RuntimeError`].
+///
+/// Only numeric values can be carried by exceptions for now.
+#[derive(Clone)]
+pub struct Exception {
+    store: Store,
+    exception: VMException,
+}
+
+impl Exception {
+    /// Create a new `Exception` thrown with `tag` and carrying `payload`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Exception, Store, Tag, TagType, Type, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let t = Tag::new(&store, TagType::new(vec![Type::I32]));
+    /// let e = Exception::new(&t, &[Value::I32(42)]).unwrap();
+    ///
+    /// assert!(e.tag().same(&t));
+    /// assert_eq!(e.payload(), vec![Value::I32(42)]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the values don't match the parameters of the
+    /// tag type, or when one of them is a reference.
+    pub fn new(tag: &Tag, payload: &[Val]) -> Result<Self, RuntimeError> {
+        let params = tag.ty().params();
+        if payload.len() != params.len()
+            || payload
+                .iter()
+                .zip(params.iter())
+                .any(|(v, ty)| v.ty() != *ty)
+        {
+            return Err(RuntimeError::new(format!(
+                "the exception payload doesn't match the tag type {}",
+                tag.ty()
+            )));
+        }
+        if params.iter().any(|ty| ty.is_ref()) {
+            return Err(RuntimeError::new(
+                "references can't be carried by exceptions",
+            ));
+        }
+        let mut values = vec![0u128; payload.len()].into_boxed_slice();
+        for (value, slot) in payload.iter().zip(values.iter_mut()) {
+            unsafe {
+                value.write_value_to(slot as *mut u128 as *mut i128);
+            }
+        }
+        Ok(Self {
+            store: tag.store.clone(),
+            exception: VMException::new(tag.tag.clone(), values),
+        })
+    }
+
+    /// Returns the [`Tag`] the `Exception` was thrown with.
+    pub fn tag(&self) -> Tag {
+        Tag {
+            store: self.store.clone(),
+            tag: self.exception.tag().clone(),
+        }
+    }
+
+    /// Returns the values carried by the `Exception`.
+    pub fn payload(&self) -> Vec<Val> {
+        self.exception
+            .tag()
+            .ty()
+            .params()
+            .iter()
+            .zip(self.exception.payload())
+            .map(|(ty, slot)| unsafe {
+                Val::read_value_from(slot as *const u128 as *const i128, *ty)
+            })
+            .collect()
+    }
+
+    /// Recover the `Exception` that caused `error`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `error` back when it wasn't caused by an uncaught exception.
+    pub fn from_runtime_error(store: &Store, error: RuntimeError) -> Result<Self, RuntimeError> {
+        error.downcast::<VMException>().map(|exception| Self {
+            store: store.clone(),
+            exception,
+        })
+    }
+}
+
+impl fmt::Debug for Exception {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Exception")
+            .field("tag", &self.exception.tag().ty())
+            .field("payload", &self.payload())
+            .finish()
+    }
+}
+
+impl From<Exception> for RuntimeError {
+    fn from(exception: Exception) -> Self {
+        Self::from_trap(Trap::User(Box::new(exception.exception)))
+    }
+}
@@ -0,0 +1,130 @@
+//! Running WebAssembly code asynchronously.
+//!
+//! [`NativeFunc::call_async`] runs the called function on a fiber, and
+//! the future it returns resumes the fiber each time it is polled. A
+//! host function created with [`Function::new_async`] polls its own
+//! future with the context of that poll, and suspends the fiber for as
+//! long as the future is pending.
+//!
+//! [`NativeFunc::call_async`]: crate::NativeFunc::call_async
+//! [`Function::new_async`]: crate::Function::new_async
+
+use crate::RuntimeError;
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll};
+use wasmer_vm::{suspend_current_fiber, Fiber, FiberError, DEFAULT_FIBER_STACK_SIZE};
+
+thread_local!(static POLL_CONTEXT: Cell<*mut Context<'static>> = Cell::new(ptr::null_mut()));
+
+// As with the fibers themselves, the thread-local accesses are never
+// inlined, because a fiber can be resumed on another thread.
+#[inline(never)]
+fn replace_poll_context(cx: *mut Context<'static>) -> *mut Context<'static> {
+    POLL_CONTEXT.with(|current| current.replace(cx))
+}
+
+#[inline(never)]
+fn poll_context() -> *mut Context<'static> {
+    POLL_CONTEXT.with(|current| current.get())
+}
+
+enum FiberFutureState<T> {
+    Running(Fiber<Result<T, RuntimeError>>),
+    Failed(RuntimeError),
+    Done,
+}
+
+/// The future returned by [`NativeFunc::call_async`], running a
+/// WebAssembly function on its own stack.
+///
+/// Dropping the future before it completes cancels the call: the host
+/// functions it is waiting on fail, and the call unwinds.
+///
+/// [`NativeFunc::call_async`]: crate::NativeFunc::call_async
+pub struct FiberFuture<T> {
+    state: FiberFutureState<T>,
+}
+
+impl<T> FiberFuture<T> {
+    /// Creates a future running `func` on a new fiber.
+    pub(crate) fn new<F>(func: F) -> Self
+    where
+        F: FnOnce() -> Result<T, RuntimeError> + Send + 'static,
+    {
+        let state = match Fiber::new(DEFAULT_FIBER_STACK_SIZE, func) {
+            Ok(fiber) => FiberFutureState::Running(fiber),
+            Err(message) => FiberFutureState::Failed(RuntimeError::new(message)),
+        };
+        Self { state }
+    }
+}
+
+impl<T> Future for FiberFuture<T> {
+    type Output = Result<T, RuntimeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fiber = match &mut self.state {
+            FiberFutureState::Running(fiber) => fiber,
+            FiberFutureState::Failed(_) => {
+                return match std::mem::replace(&mut self.state, FiberFutureState::Done) {
+                    FiberFutureState::Failed(error) => Poll::Ready(Err(error)),
+                    _ => unreachable!(),
+                };
+            }
+            FiberFutureState::Done => panic!("`FiberFuture` polled after completion"),
+        };
+
+        // The context only outlives this call on the fiber, which is
+        // suspended again before `resume` returns.
+        let cx = cx as *mut Context<'_> as *mut Context<'static>;
+        let prev = replace_poll_context(cx);
+        let result = fiber.resume();
+        replace_poll_context(prev);
+
+        let result = match result {
+            Ok(Poll::Pending) => return Poll::Pending,
+            Ok(Poll::Ready(result)) => result,
+            Err(trap) => Err(RuntimeError::from_trap(trap)),
+        };
+        self.state = FiberFutureState::Done;
+        Poll::Ready(result)
+    }
+}
+
+impl<T> fmt::Debug for FiberFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match &self.state {
+            FiberFutureState::Running(_) => "running",
+            FiberFutureState::Failed(_) => "failed",
+            FiberFutureState::Done => "done",
+        };
+        f.debug_struct("FiberFuture")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+/// Runs `future` to completion from a host function, suspending the
+/// fiber of the [`FiberFuture`] calling it while it is pending.
+pub(crate) fn block_on<F: Future>(future: F) -> Result<F::Output, RuntimeError> {
+    let mut future = Box::pin(future);
+    loop {
+        let cx = poll_context();
+        if cx.is_null() {
+            return Err(RuntimeError::new(
+                "async host functions can only be called through `call_async`",
+            ));
+        }
+        if let Poll::Ready(output) = future.as_mut().poll(unsafe { &mut *cx }) {
+            return Ok(output);
+        }
+        suspend_current_fiber().map_err(|error| match error {
+            FiberError::Cancelled => RuntimeError::new("the async call has been cancelled"),
+            error => RuntimeError::new(error.to_string()),
+        })?;
+    }
+}
@@ -1,13 +1,15 @@
-use crate::exports::Exports;
+use crate::exports::{ExportError, Exportable, Exports};
 use crate::externals::Extern;
 use crate::module::Module;
-use crate::store::Store;
+use crate::store::{InterruptHandle, Store};
+use crate::types::{ExportType, InstanceType};
 use crate::{HostEnvInitError, LinkError, RuntimeError};
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_engine::{Export, ExportInstance, Resolver};
+use wasmer_types::ExportIndex;
+use wasmer_vm::{InstanceHandle, InstanceSnapshot, SnapshotError, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -21,6 +23,9 @@ use wasmer_vm::{InstanceHandle, VMContext};
 pub struct Instance {
     handle: Arc<Mutex<InstanceHandle>>,
     module: Module,
+    /// The instances created by the module, for the module linking
+    /// proposal, which its exports may refer to.
+    _linked_instances: Arc<Vec<Instance>>,
     /// The exports for an instance.
     pub exports: Exports,
 }
@@ -112,21 +117,87 @@ impl Instance {
     ///  * Link errors that happen when plugging the imports into the instance
     ///  * Runtime errors that happen when running the module `start` function.
     pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
+        Self::new_with_snapshot(module, resolver, None)
+    }
+
+    /// Creates a new `Instance` of `module` in the state captured by
+    /// `snapshot` from an instance of the same module, with imports
+    /// resolved by the [`Resolver`].
+    ///
+    /// The instance isn't initialized: its memories, mutable globals and
+    /// tables get their contents from the snapshot, and the start function
+    /// of the module isn't called. The imports, including imported
+    /// memories, globals and tables, are used as they are.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Store, Module, Instance, Value};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, r#"
+    ///     (module
+    ///         (global $counter (export "counter") (mut i32) (i32.const 0))
+    ///         (func $start (global.set $counter (i32.const 42)))
+    ///         (start $start))
+    /// "#)?;
+    /// let instance = Instance::new(&module, &imports! {})?;
+    /// let snapshot = instance.snapshot()?;
+    ///
+    /// let restored = Instance::restore(&module, &snapshot, &imports! {})?;
+    /// let counter = restored.exports.get_global("counter")?;
+    /// assert_eq!(counter.get(), Value::I32(42));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// As [`Instance::new`], and link errors if the snapshot doesn't match
+    /// the module.
+    pub fn restore(
+        module: &Module,
+        snapshot: &InstanceSnapshot,
+        resolver: &dyn Resolver,
+    ) -> Result<Self, InstantiationError> {
+        Self::new_with_snapshot(module, resolver, Some(snapshot))
+    }
+
+    fn new_with_snapshot(
+        module: &Module,
+        resolver: &dyn Resolver,
+        snapshot: Option<&InstanceSnapshot>,
+    ) -> Result<Self, InstantiationError> {
         let store = module.store();
-        let handle = module.instantiate(resolver)?;
+        let linked = module.link(resolver)?;
+        let handle = module.instantiate(&linked.resolver(resolver), snapshot)?;
         let exports = module
-            .exports()
-            .map(|export| {
-                let name = export.name().to_string();
-                let export = handle.lookup(&name).expect("export");
-                let extern_ = Extern::from_vm_export(store, export.into());
-                (name, extern_)
+            .info()
+            .exports
+            .iter()
+            .map(|(name, index)| {
+                let extern_ = match index {
+                    ExportIndex::Module(index) => Extern::Module(linked.modules[index].clone()),
+                    ExportIndex::Instance(index) => {
+                        Extern::Instance(linked.instances[index].clone())
+                    }
+                    _ => {
+                        let export = handle.lookup(name).expect("export");
+                        Extern::from_vm_export(store, export.into())
+                    }
+                };
+                (name.clone(), extern_)
             })
             .collect::<Exports>();
 
         let instance = Self {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
+            _linked_instances: Arc::new(
+                linked
+                    .instances
+                    .into_iter()
+                    .map(|(_, instance)| instance)
+                    .collect(),
+            ),
             exports,
         };
 
@@ -159,12 +230,101 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns an [`InterruptHandle`] to interrupt the WebAssembly code
+    /// running in this instance.
+    ///
+    /// Interrupts apply to the whole [`Store`] of the instance.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.store().interrupt_handle()
+    }
+
+    /// Sets the epoch deadline of this instance `delta` epochs after the
+    /// current epoch of its [`Store`].
+    ///
+    /// Once the epoch of the store reaches the deadline, the WebAssembly
+    /// code of this instance calls the epoch deadline callback at its next
+    /// function entry or loop header, or traps with `TrapCode::EpochDeadline`
+    /// if there is none. Instances have no deadline by default.
+    ///
+    /// See [`Store::increment_epoch`] and
+    /// [`Instance::set_epoch_deadline_callback`].
+    pub fn set_epoch_deadline(&self, delta: u64) {
+        self.handle.lock().unwrap().set_epoch_deadline(delta)
+    }
+
+    /// Sets the callback run when the epoch deadline of this instance is
+    /// reached.
+    ///
+    /// The callback returns the number of epochs to extend the deadline by,
+    /// counted from the current epoch, or `None` to make the running code
+    /// trap with `TrapCode::EpochDeadline`.
+    pub fn set_epoch_deadline_callback<F>(&self, callback: F)
+    where
+        F: FnMut() -> Option<u64> + Send + 'static,
+    {
+        self.handle
+            .lock()
+            .unwrap()
+            .set_epoch_deadline_callback(callback)
+    }
+
+    /// Captures the state of the local memories, mutable globals and
+    /// tables of the instance, to restore it later with
+    /// [`Instance::restore`].
+    ///
+    /// The snapshot can be serialized with `serde`. It doesn't capture the
+    /// instances created for the module linking proposal, nor the state
+    /// of the host environments.
+    ///
+    /// ## Errors
+    ///
+    /// A table holding functions of other instances can't be captured.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        self.handle.lock().unwrap().snapshot()
+    }
+
+    /// Returns the type of the instance, as seen by the module linking
+    /// proposal: its exports.
+    pub fn ty(&self) -> InstanceType {
+        InstanceType::new(
+            self.exports
+                .iter()
+                .map(|(name, extern_)| ExportType::new(name, extern_.ty()))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    pub(crate) fn from_vm_export(export: ExportInstance) -> Self {
+        export
+            .handle
+            .downcast_ref::<Self>()
+            .expect("instance exports are created by `wasmer`")
+            .clone()
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
     }
 }
 
+impl<'a> Exportable<'a> for Instance {
+    fn to_export(&self) -> Export {
+        ExportInstance {
+            ty: self.ty(),
+            handle: Arc::new(self.clone()),
+        }
+        .into()
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Instance(instance) => Ok(instance),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+}
+
 impl fmt::Debug for Instance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Instance")
@@ -256,6 +256,7 @@
 mod env;
 mod exports;
 mod externals;
+mod fiber;
 mod import_object;
 mod instance;
 mod module;
@@ -286,18 +287,20 @@ pub mod internals {
 pub use crate::env::{HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use crate::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
+    Exception, Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, Tag,
+    WasmTypeList,
 };
+pub use crate::fiber::FiberFuture;
 pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
 pub use crate::instance::{Instance, InstantiationError};
 pub use crate::module::Module;
 pub use crate::native::NativeFunc;
 pub use crate::ptr::{Array, Item, WasmPtr};
-pub use crate::store::{Store, StoreObject};
+pub use crate::store::{InterruptHandle, Store, StoreObject};
 pub use crate::tunables::BaseTunables;
 pub use crate::types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
-    MemoryType, Mutability, TableType, Val, ValType,
+    InstanceType, MemoryType, ModuleType, Mutability, TableType, TagType, Val, ValType,
 };
 pub use crate::types::{Val as Value, ValType as Type};
 pub use crate::utils::is_wasm;
@@ -316,11 +319,14 @@ pub use wasmer_engine::{
 };
 pub use wasmer_types::{
     Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError, VMExport};
+pub use wasmer_vm::{
+    raise_user_trap, InstancePool, InstanceSnapshot, MemoryError, PoolingLimits, ResourceLimiter,
+    SnapshotError, VMExport,
+};
 pub mod vm {
     //! The vm module re-exports wasmer-vm types.
 
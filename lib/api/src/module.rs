@@ -1,6 +1,10 @@
+use crate::exports::{ExportError, Exportable};
+use crate::externals::Extern;
+use crate::instance::Instance;
 use crate::store::Store;
-use crate::types::{ExportType, ImportType};
+use crate::types::{ExportType, ExternType, ImportType, ModuleType};
 use crate::InstantiationError;
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::path::Path;
@@ -9,8 +13,15 @@ use thiserror::Error;
 use wasmer_compiler::CompileError;
 #[cfg(feature = "wat")]
 use wasmer_compiler::WasmError;
-use wasmer_engine::{Artifact, DeserializeError, Resolver, SerializeError};
-use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, ModuleInfo};
+use wasmer_engine::{
+    Artifact, DeserializeError, Export, ExportModule, ImportError, LinkError, Resolver,
+    SerializeError,
+};
+use wasmer_types::{ExportIndex, ImportIndex, InstanceIndex, ModuleIndex};
+use wasmer_vm::{
+    ExportsIterator, ImportsIterator, InstanceHandle, InstanceSnapshot, ModuleInfo,
+    ModuleInitializer,
+};
 
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -34,6 +45,9 @@ pub enum IoCompileError {
 pub struct Module {
     store: Store,
     artifact: Arc<dyn Artifact>,
+    /// The modules defined in the module section, for the module linking
+    /// proposal.
+    nested: Arc<HashMap<ModuleIndex, Module>>,
 }
 
 impl Module {
@@ -159,7 +173,7 @@ impl Module {
 
     fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
         let artifact = store.engine().compile(binary, store.tunables())?;
-        Ok(Self::from_artifact(store, artifact))
+        Self::from_artifact(store, artifact)
     }
 
     /// Serializes a module into a binary representation that the `Engine`
@@ -223,7 +237,7 @@ impl Module {
     /// ```
     pub unsafe fn deserialize(store: &Store, bytes: &[u8]) -> Result<Self, DeserializeError> {
         let artifact = store.engine().deserialize(bytes)?;
-        Ok(Self::from_artifact(store, artifact))
+        Self::from_artifact(store, artifact).map_err(DeserializeError::Compiler)
     }
 
     /// Deserializes a a serialized Module located in a `Path` into a `Module`.
@@ -248,31 +262,222 @@ impl Module {
         path: impl AsRef<Path>,
     ) -> Result<Self, DeserializeError> {
         let artifact = store.engine().deserialize_from_file(path.as_ref())?;
-        Ok(Self::from_artifact(store, artifact))
+        Self::from_artifact(store, artifact).map_err(DeserializeError::Compiler)
     }
 
-    fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
-        Self {
+    fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Result<Self, CompileError> {
+        // The nested modules have been validated along with the module
+        // they are defined in.
+        let nested = artifact
+            .module_ref()
+            .initializers
+            .iter()
+            .filter_map(|initializer| match initializer {
+                ModuleInitializer::DefineModule { index, binary } => Some(
+                    unsafe { Self::from_binary_unchecked(store, binary) }
+                        .map(|module| (*index, module)),
+                ),
+                _ => None,
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(Self {
             store: store.clone(),
             artifact,
+            nested: Arc::new(nested),
+        })
+    }
+
+    pub(crate) fn from_vm_export(export: ExportModule) -> Self {
+        export
+            .handle
+            .downcast_ref::<Self>()
+            .expect("module exports are created by `wasmer`")
+            .clone()
+    }
+
+    /// Resolves the imported modules and instances of a module using the
+    /// module linking proposal, and goes through its initializers to
+    /// define its nested modules, create its instances and resolve its
+    /// aliases.
+    pub(crate) fn link(&self, resolver: &dyn Resolver) -> Result<Linked, InstantiationError> {
+        let info = self.info();
+        let mut linked = Linked::default();
+        if !info.uses_module_linking() {
+            return Ok(linked);
+        }
+
+        for ((module_name, field, import_idx), import_index) in info.imports.iter() {
+            let expected = match import_index {
+                ImportIndex::Module(index) => ExternType::Module(info.modules[*index].clone()),
+                ImportIndex::Instance(index) => {
+                    ExternType::Instance(info.instances[*index].clone())
+                }
+                _ => continue,
+            };
+            let import_error = |error: ImportError| {
+                InstantiationError::Link(LinkError::Import(
+                    module_name.to_string(),
+                    field.to_string(),
+                    error,
+                ))
+            };
+            let extern_ = resolver
+                .resolve(*import_idx, module_name, field)
+                .map(|export| Extern::from_vm_export(&self.store, export))
+                .ok_or_else(|| import_error(ImportError::UnknownImport(expected.clone())))?;
+            let ty = extern_.ty();
+            if !ty.is_compatible_with(&expected) {
+                return Err(import_error(ImportError::IncompatibleType(expected, ty)));
+            }
+            match (import_index, extern_) {
+                (ImportIndex::Module(index), Extern::Module(module)) => {
+                    linked.modules.insert(*index, module);
+                }
+                (ImportIndex::Instance(index), Extern::Instance(instance)) => {
+                    linked.instances.insert(*index, instance);
+                }
+                _ => unreachable!("the type of the import has been checked"),
+            }
+        }
+
+        for initializer in info.initializers.iter() {
+            match initializer {
+                ModuleInitializer::DefineModule { index, .. } => {
+                    linked.modules.insert(*index, self.nested[index].clone());
+                }
+                ModuleInitializer::Instantiate {
+                    index,
+                    module,
+                    args,
+                } => {
+                    let mut arguments = HashMap::with_capacity(args.len());
+                    for (name, entity) in args.iter() {
+                        let argument = self.link_argument(name, entity, &mut linked, resolver)?;
+                        arguments.insert(name.clone(), argument);
+                    }
+                    let instance =
+                        Instance::new(&linked.modules[module], &ArgumentsResolver { arguments })?;
+                    linked.instances.insert(*index, instance);
+                }
+                ModuleInitializer::AliasInstanceExport {
+                    instance,
+                    export,
+                    index,
+                    import_idx,
+                } => {
+                    let extern_ = linked.instances[instance]
+                        .exports
+                        .get_extern(export)
+                        .cloned()
+                        .expect("the type of the instance has been checked");
+                    match (index, extern_) {
+                        (ImportIndex::Module(index), Extern::Module(module)) => {
+                            linked.modules.insert(*index, module);
+                        }
+                        (ImportIndex::Instance(index), Extern::Instance(instance)) => {
+                            linked.instances.insert(*index, instance);
+                        }
+                        (index, extern_) => {
+                            let export = extern_.to_export();
+                            linked.core.insert(index.clone(), export.clone());
+                            linked.aliases.insert(*import_idx, export);
+                        }
+                    }
+                }
+            }
         }
+
+        Ok(linked)
     }
 
+    /// Resolves an entity passed to a module instantiated by this one.
+    ///
+    /// As this module isn't instantiated yet, the entity is either a module
+    /// or an instance, or it is imported or aliased from an instance.
+    fn link_argument(
+        &self,
+        name: &str,
+        entity: &ExportIndex,
+        linked: &mut Linked,
+        resolver: &dyn Resolver,
+    ) -> Result<Extern, InstantiationError> {
+        let info = self.info();
+        let import_index = match entity {
+            ExportIndex::Module(index) => return Ok(Extern::Module(linked.modules[index].clone())),
+            ExportIndex::Instance(index) => {
+                return Ok(Extern::Instance(linked.instances[index].clone()))
+            }
+            ExportIndex::Function(index) => ImportIndex::Function(*index),
+            ExportIndex::Table(index) => ImportIndex::Table(*index),
+            ExportIndex::Memory(index) => ImportIndex::Memory(*index),
+            ExportIndex::Global(index) => ImportIndex::Global(*index),
+            ExportIndex::Tag(index) => ImportIndex::Tag(*index),
+        };
+        if !linked.core.contains_key(&import_index) {
+            let export = info
+                .imports
+                .iter()
+                .find(|(_, index)| **index == import_index)
+                .and_then(|((module_name, field, import_idx), _)| {
+                    resolver.resolve(*import_idx, module_name, field)
+                })
+                .ok_or_else(|| {
+                    let ty = match import_index {
+                        ImportIndex::Function(index) => {
+                            ExternType::Function(info.signatures[info.functions[index]].clone())
+                        }
+                        ImportIndex::Table(index) => ExternType::Table(info.tables[index]),
+                        ImportIndex::Memory(index) => ExternType::Memory(info.memories[index]),
+                        ImportIndex::Global(index) => ExternType::Global(info.globals[index]),
+                        ImportIndex::Tag(index) => ExternType::Tag(info.tag_type(index)),
+                        ImportIndex::Module(_) | ImportIndex::Instance(_) => unreachable!(),
+                    };
+                    InstantiationError::Link(LinkError::Import(
+                        name.to_string(),
+                        String::new(),
+                        ImportError::UnknownImport(ty),
+                    ))
+                })?;
+            linked.core.insert(import_index.clone(), export);
+        }
+        Ok(Extern::from_vm_export(
+            &self.store,
+            linked.core[&import_index].clone(),
+        ))
+    }
+
+    /// Instantiates the module, initializing the instance or restoring
+    /// it to the state captured by `snapshot`.
     pub(crate) fn instantiate(
         &self,
         resolver: &dyn Resolver,
+        snapshot: Option<&InstanceSnapshot>,
     ) -> Result<InstanceHandle, InstantiationError> {
         unsafe {
-            let instance_handle =
-                self.artifact
-                    .instantiate(self.store.tunables(), resolver, Box::new(()))?;
+            let instance_handle = self.artifact.instantiate(
+                self.store.tunables(),
+                resolver,
+                Box::new(()),
+                self.store.interrupts().clone(),
+                self.store.resource_limiter().as_ref(),
+            )?;
 
             // After the instance handle is created, we need to initialize
             // the data, call the start function and so. However, if any
             // of this steps traps, we still need to keep the instance alive
             // as some of the Instance elements may have placed in other
             // instance tables.
-            self.artifact.finish_instantiation(&instance_handle)?;
+            match snapshot {
+                Some(snapshot) => instance_handle.restore_snapshot(snapshot).map_err(|e| {
+                    InstantiationError::Link(LinkError::Resource(format!(
+                        "Failed to restore the snapshot: {}",
+                        e
+                    )))
+                })?,
+                None => self
+                    .artifact
+                    .finish_instantiation(self.store.tunables(), &instance_handle)?,
+            }
 
             Ok(instance_handle)
         }
@@ -385,6 +590,15 @@ impl Module {
         self.artifact.module_ref().exports()
     }
 
+    /// Returns the type of the module, as seen by the module linking
+    /// proposal: its imports and its exports.
+    pub fn ty(&self) -> ModuleType {
+        ModuleType::new(
+            self.imports().collect::<Vec<_>>(),
+            self.exports().collect::<Vec<_>>(),
+        )
+    }
+
     /// Get the custom sections of the module given a `name`.
     ///
     /// # Important
@@ -429,3 +643,84 @@ impl fmt::Debug for Module {
             .finish()
     }
 }
+
+impl<'a> Exportable<'a> for Module {
+    fn to_export(&self) -> Export {
+        ExportModule {
+            ty: self.ty(),
+            handle: Arc::new(self.clone()),
+        }
+        .into()
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Module(module) => Ok(module),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+}
+
+/// The modules and instances of a module using the module linking proposal,
+/// resolved for one of its instantiations.
+#[derive(Default)]
+pub(crate) struct Linked {
+    pub(crate) modules: HashMap<ModuleIndex, Module>,
+    pub(crate) instances: HashMap<InstanceIndex, Instance>,
+    /// The functions, tables, memories, globals and tags resolved so far,
+    /// either imported or aliased from instances.
+    core: HashMap<ImportIndex, Export>,
+    /// The aliased functions, tables, memories, globals and tags, by their
+    /// position among the imports of the module.
+    aliases: HashMap<u32, Export>,
+}
+
+impl Linked {
+    /// A resolver for the imports of the module, including the aliases
+    /// that the VM sees as imports.
+    pub(crate) fn resolver<'a>(&'a self, resolver: &'a dyn Resolver) -> LinkedResolver<'a> {
+        LinkedResolver {
+            linked: self,
+            resolver,
+        }
+    }
+}
+
+pub(crate) struct LinkedResolver<'a> {
+    linked: &'a Linked,
+    resolver: &'a dyn Resolver,
+}
+
+impl Resolver for LinkedResolver<'_> {
+    fn resolve(&self, index: u32, module: &str, field: &str) -> Option<Export> {
+        match self.linked.aliases.get(&index) {
+            Some(export) => Some(export.clone()),
+            None => self.resolver.resolve(index, module, field),
+        }
+    }
+}
+
+/// Resolves the imports of a module instantiated by another one from the
+/// arguments it is given, by import name.
+///
+/// Two-level imports are resolved from the exports of the instance passed
+/// for their module name.
+struct ArgumentsResolver {
+    arguments: HashMap<String, Extern>,
+}
+
+impl Resolver for ArgumentsResolver {
+    fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export> {
+        let argument = self.arguments.get(module)?;
+        if field.is_empty() {
+            return Some(argument.to_export());
+        }
+        match argument {
+            Extern::Instance(instance) => instance
+                .exports
+                .get_extern(field)
+                .map(|extern_| extern_.to_export()),
+            _ => None,
+        }
+    }
+}
@@ -13,6 +13,7 @@ use crate::externals::function::{
     DynamicFunctionWithEnv, DynamicFunctionWithoutEnv, FunctionDefinition, HostFunctionDefinition,
     VMDynamicFunction, WasmFunctionDefinition,
 };
+use crate::fiber::FiberFuture;
 use crate::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_engine::ExportFunction;
@@ -135,14 +136,14 @@ macro_rules! impl_native_traits {
                             }
                             rets_list.as_mut()
                         };
-                        unsafe {
+                        self.store.interrupts().with_stack_limit(|| unsafe {
                             wasmer_vm::wasmer_call_trampoline(
                                 self.vmctx(),
                                 trampoline,
                                 self.address(),
                                 args_rets.as_mut_ptr() as *mut u8,
                             )
-                        }?;
+                        })?;
                         let num_rets = rets_list.len();
                         if !using_rets_array && num_rets > 0 {
                             let src_pointer = params_list.as_ptr();
@@ -212,6 +213,28 @@ macro_rules! impl_native_traits {
             }
         }
 
+        #[allow(unused_parens, non_snake_case)]
+        impl<$( $x , )* Rets> NativeFunc<( $( $x ),* ), Rets>
+        where
+            $( $x: FromToNativeWasmType + Send + 'static, )*
+            Rets: WasmTypeList + Send + 'static,
+        {
+            /// Call the typed func on its own stack, returning a future of
+            /// its results.
+            ///
+            /// This is what allows the function to call the host functions
+            /// created with [`Function::new_async`].
+            pub fn call_async(&self, $( $x: $x, )* ) -> FiberFuture<Rets> {
+                // Not `self.clone()`, which needs `Rets: Clone`.
+                let func = Self::new(
+                    self.store.clone(),
+                    self.exported.clone(),
+                    self.definition.clone(),
+                );
+                FiberFuture::new(move || func.call($( $x, )*))
+            }
+        }
+
         #[allow(unused_parens)]
         impl<'a, $( $x, )* Rets> crate::exports::ExportableWithGenerics<'a, ($( $x ),*), Rets> for NativeFunc<( $( $x ),* ), Rets>
         where
@@ -1,10 +1,11 @@
 use crate::tunables::BaseTunables;
 use loupe::MemoryUsage;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 #[cfg(all(feature = "compiler", feature = "engine"))]
 use wasmer_compiler::CompilerConfig;
 use wasmer_engine::{Engine, Tunables};
+use wasmer_vm::{ResourceLimiter, VMInterrupts};
 
 /// The store represents all global state that can be manipulated by
 /// WebAssembly programs. It consists of the runtime representation
@@ -20,6 +21,12 @@ use wasmer_engine::{Engine, Tunables};
 pub struct Store {
     engine: Arc<dyn Engine + Send + Sync>,
     tunables: Arc<dyn Tunables + Send + Sync>,
+    #[loupe(skip)]
+    interrupts: Arc<VMInterrupts>,
+    /// The resource limiter, shared with the clones of the store such as
+    /// the ones held by its modules.
+    #[loupe(skip)]
+    limiter: Arc<Mutex<Option<Arc<dyn ResourceLimiter>>>>,
 }
 
 impl Store {
@@ -28,10 +35,7 @@ impl Store {
     where
         E: Engine + ?Sized,
     {
-        Self {
-            engine: engine.cloned(),
-            tunables: Arc::new(BaseTunables::for_target(engine.target())),
-        }
+        Self::new_with_tunables(engine, BaseTunables::for_target(engine.target()))
     }
 
     /// Creates a new `Store` with a specific [`Engine`] and [`Tunables`].
@@ -39,12 +43,31 @@ impl Store {
     where
         E: Engine + ?Sized,
     {
+        let interrupts = new_interrupts(&tunables);
         Self {
             engine: engine.cloned(),
             tunables: Arc::new(tunables),
+            interrupts: Arc::new(interrupts),
+            limiter: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Sets the [`ResourceLimiter`] consulted when the memories and tables
+    /// of this store are created and grow, whether they belong to
+    /// instances or to the host.
+    ///
+    /// The limiter is shared with the clones of this store, and applies to
+    /// what they create from now on, including the instances of modules
+    /// created before.
+    pub fn set_resource_limiter(&self, limiter: Arc<dyn ResourceLimiter>) {
+        *self.limiter.lock().unwrap() = Some(limiter);
+    }
+
+    /// Returns the [`ResourceLimiter`] of this store, if any.
+    pub fn resource_limiter(&self) -> Option<Arc<dyn ResourceLimiter>> {
+        self.limiter.lock().unwrap().clone()
+    }
+
     /// Returns the [`Tunables`].
     pub fn tunables(&self) -> &dyn Tunables {
         self.tunables.as_ref()
@@ -55,6 +78,30 @@ impl Store {
         &self.engine
     }
 
+    /// Returns an [`InterruptHandle`] to interrupt the WebAssembly code
+    /// running in the instances of this store.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            interrupts: self.interrupts.clone(),
+        }
+    }
+
+    /// Increments the epoch of this store, returning the new one.
+    ///
+    /// The epoch is compared against the deadlines set with
+    /// [`Instance::set_epoch_deadline`]. It is typically incremented at a
+    /// regular interval, from another thread with
+    /// [`InterruptHandle::increment_epoch`].
+    ///
+    /// [`Instance::set_epoch_deadline`]: crate::Instance::set_epoch_deadline
+    pub fn increment_epoch(&self) -> u64 {
+        self.interrupts.increment_epoch()
+    }
+
+    pub(crate) fn interrupts(&self) -> &Arc<VMInterrupts> {
+        &self.interrupts
+    }
+
     /// Checks whether two stores are identical. A store is considered
     /// equal to another store if both have the same engine. The
     /// tunables are excluded from the logic.
@@ -109,19 +156,78 @@ impl Default for Store {
         let config = get_config();
         let engine = get_engine(config);
         let tunables = BaseTunables::for_target(engine.target());
+        let interrupts = new_interrupts(&tunables);
         Store {
             engine: Arc::new(engine),
             tunables: Arc::new(tunables),
+            interrupts: Arc::new(interrupts),
+            limiter: Arc::new(Mutex::new(None)),
         }
     }
 }
 
+/// Creates the `VMInterrupts` of a store, with the stack limit of its
+/// tunables.
+fn new_interrupts(tunables: &dyn Tunables) -> VMInterrupts {
+    VMInterrupts::with_max_wasm_stack(tunables.max_wasm_stack().unwrap_or(0))
+}
+
 impl fmt::Debug for Store {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Store").finish()
     }
 }
 
+/// A handle to interrupt the WebAssembly code running in the instances of
+/// a [`Store`], and of its clones, from any thread.
+///
+/// Interrupted code traps with `TrapCode::Interrupt` at its next function
+/// entry or loop header. Host functions aren't interrupted, but the
+/// WebAssembly code they return to is. If no WebAssembly code is running
+/// when [`InterruptHandle::interrupt`] is called, the next code to run in
+/// the store is interrupted right away.
+///
+/// # Usage
+///
+/// ```
+/// # use wasmer::{imports, Instance, Module, Store};
+/// # use std::thread;
+/// # use std::time::Duration;
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, r#"(func (export "run") (loop (br 0)))"#)?;
+/// let instance = Instance::new(&module, &imports! {})?;
+/// let run = instance.exports.get_native_function::<(), ()>("run")?;
+///
+/// let handle = store.interrupt_handle();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_millis(10));
+///     handle.interrupt();
+/// });
+/// assert!(run.call().is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct InterruptHandle {
+    interrupts: Arc<VMInterrupts>,
+}
+
+impl InterruptHandle {
+    /// Interrupts the WebAssembly code running in the store, or the next
+    /// one to run in it.
+    pub fn interrupt(&self) {
+        self.interrupts.interrupt();
+    }
+
+    /// Increments the epoch of the store, returning the new one.
+    ///
+    /// See [`Store::increment_epoch`].
+    pub fn increment_epoch(&self) -> u64 {
+        self.interrupts.increment_epoch()
+    }
+}
+
 /// A trait represinting any object that lives in the `Store`.
 pub trait StoreObject {
     /// Return true if the object `Store` is the same as the provided `Store`.
@@ -1,6 +1,6 @@
 use crate::{MemoryType, Pages, TableType};
 use loupe::MemoryUsage;
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::ptr::NonNull;
 use std::sync::Arc;
 use target_lexicon::{OperatingSystem, PointerWidth};
@@ -8,8 +8,8 @@ use wasmer_compiler::Target;
 use wasmer_engine::Tunables;
 use wasmer_vm::MemoryError;
 use wasmer_vm::{
-    LinearMemory, LinearTable, Memory, MemoryStyle, Table, TableStyle, VMMemoryDefinition,
-    VMTableDefinition,
+    InstancePool, LinearMemory, LinearTable, Memory, MemoryStyle, PoolingLimits, Table, TableStyle,
+    VMMemoryDefinition, VMTableDefinition,
 };
 
 /// Tunable parameters for WebAssembly compilation.
@@ -30,6 +30,21 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// The maximum size in bytes of the stack WebAssembly code may use, so
+    /// that it overflows at the same depth whatever thread calls it, or
+    /// `None` (the default) to leave it to the size of the native stack.
+    pub max_wasm_stack: Option<usize>,
+
+    /// Whether to initialize the memories of new instances by mapping the
+    /// copy-on-write memory images of their module, when it has some.
+    /// `true` by default.
+    pub memory_images: bool,
+
+    /// The pool instances, and their memories and tables, are allocated
+    /// from, or `None` to allocate each of them separately.
+    #[loupe(skip)]
+    pub instance_pool: Option<Arc<InstancePool>>,
 }
 
 impl BaseTunables {
@@ -65,8 +80,27 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            max_wasm_stack: None,
+            memory_images: true,
+            instance_pool: None,
         }
     }
+
+    /// Allocates the instances, and their memories and tables, from a pool
+    /// preallocated within `limits`, rather than separately.
+    ///
+    /// The memory slots of the pool are sized for the static memory style
+    /// of these tunables, so it must be set up first.
+    pub fn enable_pooling(&mut self, limits: PoolingLimits) -> Result<(), String> {
+        let memory_reservation = self
+            .static_memory_bound
+            .bytes()
+            .0
+            .checked_add(self.static_memory_offset_guard_size as usize)
+            .ok_or_else(|| "the memory slots don't fit in the address space".to_string())?;
+        self.instance_pool = Some(Arc::new(InstancePool::new(limits, memory_reservation)?));
+        Ok(())
+    }
 }
 
 impl Tunables for BaseTunables {
@@ -77,7 +111,20 @@ impl Tunables for BaseTunables {
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if memory.shared && memory.maximum.is_some() {
+            // Shared memories can be accessed concurrently from several
+            // threads, so they must never move: reserve up to their maximum.
+            MemoryStyle::Static {
+                bound: max(maximum, self.static_memory_bound),
+                offset_guard_size: self.static_memory_offset_guard_size,
+            }
+        } else if memory.memory64 {
+            // 64-bit memories can't be covered by a guard region, so every
+            // access needs an explicit bounds check against the current size.
+            MemoryStyle::Dynamic {
+                offset_guard_size: self.dynamic_memory_offset_guard_size,
+            }
+        } else if maximum <= self.static_memory_bound {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
@@ -95,6 +142,21 @@ impl Tunables for BaseTunables {
         TableStyle::CallerChecksSignature
     }
 
+    /// The maximum size of the stack WebAssembly code may use.
+    fn max_wasm_stack(&self) -> Option<usize> {
+        self.max_wasm_stack
+    }
+
+    /// The pool to allocate instances from.
+    fn instance_pool(&self) -> Option<&Arc<InstancePool>> {
+        self.instance_pool.as_ref()
+    }
+
+    /// Whether to initialize memories from memory images.
+    fn use_memory_images(&self) -> bool {
+        self.memory_images
+    }
+
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
     fn create_host_memory(
         &self,
@@ -115,6 +177,9 @@ impl Tunables for BaseTunables {
         style: &MemoryStyle,
         vm_definition_location: NonNull<VMMemoryDefinition>,
     ) -> Result<Arc<dyn Memory>, MemoryError> {
+        if let Some(pool) = &self.instance_pool {
+            return pool.create_memory(&ty, &style, vm_definition_location);
+        }
         Ok(Arc::new(LinearMemory::from_definition(
             &ty,
             &style,
@@ -142,6 +207,9 @@ impl Tunables for BaseTunables {
         style: &TableStyle,
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String> {
+        if let Some(pool) = &self.instance_pool {
+            return pool.create_table(&ty, &style, vm_definition_location);
+        }
         Ok(Arc::new(LinearTable::from_definition(
             &ty,
             &style,
@@ -160,10 +228,13 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            max_wasm_stack: None,
+            memory_images: true,
+            instance_pool: None,
         };
 
         // No maximum
-        let requested = MemoryType::new(3, None, true);
+        let requested = MemoryType::new(3, None, false);
         let style = tunables.memory_style(&requested);
         match style {
             MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
@@ -171,7 +242,7 @@ mod tests {
         }
 
         // Large maximum
-        let requested = MemoryType::new(3, Some(5_000_000), true);
+        let requested = MemoryType::new(3, Some(5_000_000), false);
         let style = tunables.memory_style(&requested);
         match style {
             MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
@@ -179,7 +250,7 @@ mod tests {
         }
 
         // Small maximum
-        let requested = MemoryType::new(3, Some(16), true);
+        let requested = MemoryType::new(3, Some(16), false);
         let style = tunables.memory_style(&requested);
         match style {
             MemoryStyle::Static {
@@ -191,5 +262,27 @@ mod tests {
             }
             s => panic!("Unexpected memory style: {:?}", s),
         }
+
+        // Shared, with a large maximum
+        let requested = MemoryType::new(3, Some(4096), true);
+        let style = tunables.memory_style(&requested);
+        match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } => {
+                assert_eq!(bound, Pages(4096));
+                assert_eq!(offset_guard_size, 128);
+            }
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
+
+        // 64-bit, with a small maximum
+        let requested = MemoryType::new64(3, Some(16), false);
+        let style = tunables.memory_style(&requested);
+        match style {
+            MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
     }
 }
@@ -5,7 +5,7 @@ use std::ptr;
 use wasmer_types::Value;
 pub use wasmer_types::{
     ExportType, ExternRef, ExternType, FunctionType, GlobalType, HostInfo, HostRef, ImportType,
-    MemoryType, Mutability, TableType, Type as ValType,
+    InstanceType, MemoryType, ModuleType, Mutability, TableType, TagType, Type as ValType,
 };
 
 /// WebAssembly computations manipulate values of basic value types:
@@ -141,6 +141,7 @@ fn memory_new() -> Result<()> {
         shared: false,
         minimum: Pages(0),
         maximum: Some(Pages(10)),
+        memory64: false,
     };
     let memory = Memory::new(&store, memory_type)?;
     assert_eq!(memory.size(), Pages(0));
@@ -13,6 +13,7 @@ use crate::deprecated::{
 };
 use crate::error::{update_last_error, CApiError};
 use libc::{c_int, c_uint};
+use std::convert::{TryFrom, TryInto};
 use std::ptr::{self, NonNull};
 use std::slice;
 use wasmer::{ExportType, ExternType, Function, ImportType, Memory, Module, Val};
@@ -23,6 +24,9 @@ pub(crate) struct NamedExport {
     /// The export type and name.
     pub(crate) export_type: ExportType,
 
+    /// The export kind.
+    pub(crate) kind: wasmer_import_export_kind,
+
     /// The instance that holds the export.
     pub(crate) instance: NonNull<CAPIInstance>,
 }
@@ -133,6 +137,9 @@ impl std::convert::TryFrom<u32> for wasmer_import_export_kind {
 
 /// Gets export descriptors for the given module
 ///
+/// The exports that the C API can't represent, such as exception tags,
+/// are left out.
+///
 /// The caller owns the object and should call `wasmer_export_descriptors_destroy` to free it.
 #[allow(clippy::cast_ptr_alignment)]
 #[no_mangle]
@@ -143,7 +150,10 @@ pub unsafe extern "C" fn wasmer_export_descriptors(
     let module = &*(module as *const Module);
 
     let named_export_descriptors: Box<NamedExportDescriptors> = Box::new(NamedExportDescriptors(
-        module.exports().into_iter().map(|e| e.into()).collect(),
+        module
+            .exports()
+            .filter_map(|e| NamedExportDescriptor::try_from(e).ok())
+            .collect(),
     ));
     *export_descriptors =
         Box::into_raw(named_export_descriptors) as *mut wasmer_export_descriptors_t;
@@ -266,7 +276,7 @@ pub unsafe extern "C" fn wasmer_export_kind(
     export: *mut wasmer_export_t,
 ) -> wasmer_import_export_kind {
     let named_export = &*(export as *mut NamedExport);
-    (&named_export.export_type).into()
+    named_export.kind.clone()
 }
 
 /// Sets the result parameter to the arity of the params of the wasmer_export_func_t
@@ -528,52 +538,76 @@ pub unsafe extern "C" fn wasmer_export_func_call(
     }
 }
 
-impl From<ExportType> for NamedExportDescriptor {
-    fn from(et: ExportType) -> Self {
-        NamedExportDescriptor {
+impl TryFrom<ExportType> for NamedExportDescriptor {
+    type Error = CApiError;
+
+    fn try_from(et: ExportType) -> Result<Self, Self::Error> {
+        Ok(NamedExportDescriptor {
             name: et.name().to_string(),
-            kind: et.into(),
-        }
+            kind: et.try_into()?,
+        })
     }
 }
 
-impl From<&ImportType> for wasmer_import_export_kind {
-    fn from(it: &ImportType) -> Self {
-        it.ty().into()
+impl TryFrom<&ImportType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(it: &ImportType) -> Result<Self, Self::Error> {
+        it.ty().try_into()
     }
 }
 
-impl From<ImportType> for wasmer_import_export_kind {
-    fn from(it: ImportType) -> Self {
-        (&it).into()
+impl TryFrom<ImportType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(it: ImportType) -> Result<Self, Self::Error> {
+        (&it).try_into()
     }
 }
 
-impl From<&ExportType> for wasmer_import_export_kind {
-    fn from(et: &ExportType) -> Self {
-        et.ty().into()
+impl TryFrom<&ExportType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(et: &ExportType) -> Result<Self, Self::Error> {
+        et.ty().try_into()
     }
 }
 
-impl From<ExportType> for wasmer_import_export_kind {
-    fn from(et: ExportType) -> Self {
-        (&et).into()
+impl TryFrom<ExportType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(et: ExportType) -> Result<Self, Self::Error> {
+        (&et).try_into()
     }
 }
 
-impl From<&ExternType> for wasmer_import_export_kind {
-    fn from(et: &ExternType) -> Self {
-        match et {
+impl TryFrom<&ExternType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(et: &ExternType) -> Result<Self, Self::Error> {
+        Ok(match et {
             ExternType::Memory(_) => wasmer_import_export_kind::WASM_MEMORY,
             ExternType::Global(_) => wasmer_import_export_kind::WASM_GLOBAL,
             ExternType::Table(_) => wasmer_import_export_kind::WASM_TABLE,
             ExternType::Function(_) => wasmer_import_export_kind::WASM_FUNCTION,
-        }
+            ExternType::Tag(_) => {
+                return Err(CApiError {
+                    msg: "exception tags are not supported by the C API".to_string(),
+                })
+            }
+            ExternType::Instance(_) | ExternType::Module(_) => {
+                return Err(CApiError {
+                    msg: "instances and modules are not supported by the C API".to_string(),
+                })
+            }
+        })
     }
 }
 
-impl From<ExternType> for wasmer_import_export_kind {
-    fn from(et: ExternType) -> Self {
-        (&et).into()
+impl TryFrom<ExternType> for wasmer_import_export_kind {
+    type Error = CApiError;
+
+    fn try_from(et: ExternType) -> Result<Self, Self::Error> {
+        (&et).try_into()
     }
 }
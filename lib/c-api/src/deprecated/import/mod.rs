@@ -11,6 +11,7 @@ use crate::deprecated::{
 };
 use crate::error::{update_last_error, CApiError};
 use libc::c_uint;
+use std::convert::TryFrom;
 use std::ffi::{c_void, CStr};
 use std::os::raw::c_char;
 use std::ptr::{self, NonNull};
@@ -191,6 +192,24 @@ pub unsafe extern "C" fn wasmer_import_object_get_import(
                 let writer = import_export_value_out.func as *mut Global;
                 *writer = global.clone();
             }
+            Extern::Tag(_) => {
+                update_last_error(CApiError {
+                    msg: format!("Found tag, expected {}", tag.to_str()),
+                });
+                return wasmer_result_t::WASMER_ERROR;
+            }
+            Extern::Instance(_) => {
+                update_last_error(CApiError {
+                    msg: format!("Found instance, expected {}", tag.to_str()),
+                });
+                return wasmer_result_t::WASMER_ERROR;
+            }
+            Extern::Module(_) => {
+                update_last_error(CApiError {
+                    msg: format!("Found module, expected {}", tag.to_str()),
+                });
+                return wasmer_result_t::WASMER_ERROR;
+            }
         }
 
         import_out.value = *import_export_value;
@@ -508,6 +527,9 @@ pub unsafe extern "C" fn wasmer_import_object_extend(
 
 /// Gets import descriptors for the given module
 ///
+/// The imports that the C API can't represent, such as exception tags,
+/// are left out.
+///
 /// The caller owns the object and should call `wasmer_import_descriptors_destroy` to free it.
 #[allow(clippy::cast_ptr_alignment)]
 #[no_mangle]
@@ -520,7 +542,15 @@ pub unsafe extern "C" fn wasmer_import_descriptors(
     } else {
         return;
     };
-    let descriptors = module.imports().collect::<Vec<ImportType>>();
+    let descriptors = module
+        .imports()
+        .filter_map(|import_type| {
+            Some(NamedImportDescriptor {
+                kind: wasmer_import_export_kind::try_from(&import_type).ok()?,
+                import_type,
+            })
+        })
+        .collect::<Vec<NamedImportDescriptor>>();
 
     let named_import_descriptors: Box<NamedImportDescriptors> =
         Box::new(NamedImportDescriptors(descriptors));
@@ -528,7 +558,17 @@ pub unsafe extern "C" fn wasmer_import_descriptors(
         Box::into_raw(named_import_descriptors) as *mut wasmer_import_descriptors_t;
 }
 
-pub struct NamedImportDescriptors(Vec<ImportType>);
+/// Intermediate representation of an import descriptor that is
+/// exposed to C.
+pub(crate) struct NamedImportDescriptor {
+    /// The import type, module and name.
+    import_type: ImportType,
+
+    /// The import kind.
+    kind: wasmer_import_export_kind,
+}
+
+pub struct NamedImportDescriptors(Vec<NamedImportDescriptor>);
 
 /// Frees the memory for the given import descriptors
 #[allow(clippy::cast_ptr_alignment)]
@@ -576,10 +616,10 @@ pub unsafe extern "C" fn wasmer_import_descriptors_get(
 pub unsafe extern "C" fn wasmer_import_descriptor_name(
     import_descriptor: *mut wasmer_import_descriptor_t,
 ) -> wasmer_byte_array {
-    let named_import_descriptor = &*(import_descriptor as *mut ImportType);
+    let named_import_descriptor = &*(import_descriptor as *mut NamedImportDescriptor);
     wasmer_byte_array {
-        bytes: named_import_descriptor.name().as_ptr(),
-        bytes_len: named_import_descriptor.name().len() as u32,
+        bytes: named_import_descriptor.import_type.name().as_ptr(),
+        bytes_len: named_import_descriptor.import_type.name().len() as u32,
     }
 }
 
@@ -589,10 +629,10 @@ pub unsafe extern "C" fn wasmer_import_descriptor_name(
 pub unsafe extern "C" fn wasmer_import_descriptor_module_name(
     import_descriptor: *mut wasmer_import_descriptor_t,
 ) -> wasmer_byte_array {
-    let named_import_descriptor = &*(import_descriptor as *mut ImportType);
+    let named_import_descriptor = &*(import_descriptor as *mut NamedImportDescriptor);
     wasmer_byte_array {
-        bytes: named_import_descriptor.module().as_ptr(),
-        bytes_len: named_import_descriptor.module().len() as u32,
+        bytes: named_import_descriptor.import_type.module().as_ptr(),
+        bytes_len: named_import_descriptor.import_type.module().len() as u32,
     }
 }
 
@@ -602,8 +642,8 @@ pub unsafe extern "C" fn wasmer_import_descriptor_module_name(
 pub unsafe extern "C" fn wasmer_import_descriptor_kind(
     export: *mut wasmer_import_descriptor_t,
 ) -> wasmer_import_export_kind {
-    let named_import_descriptor = &*(export as *mut ImportType);
-    named_import_descriptor.ty().into()
+    let named_import_descriptor = &*(export as *mut NamedImportDescriptor);
+    named_import_descriptor.kind.clone()
 }
 
 /// Sets the result parameter to the arity of the params of the wasmer_import_func_t
@@ -11,6 +11,7 @@ use crate::deprecated::{
 use crate::error::{update_last_error, CApiError};
 use libc::{c_char, c_int, c_void};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::CStr;
 use std::ptr::NonNull;
 use std::slice;
@@ -412,7 +413,8 @@ pub unsafe extern "C" fn wasmer_instance_call(
 ///
 
 /// This function stores a Rust vector of exports into `exports` as an
-/// opaque pointer of kind `wasmer_exports_t`.
+/// opaque pointer of kind `wasmer_exports_t`. The exports that the C
+/// API can't represent, such as exception tags, are left out.
 ///
 /// As is, you can do anything with `exports` except using the
 /// companion functions, like `wasmer_exports_len()`,
@@ -469,9 +471,12 @@ pub unsafe extern "C" fn wasmer_instance_exports(
         .instance
         .module()
         .exports()
-        .map(|export_type| NamedExport {
-            export_type,
-            instance,
+        .filter_map(|export_type| {
+            Some(NamedExport {
+                kind: wasmer_import_export_kind::try_from(&export_type).ok()?,
+                export_type,
+                instance,
+            })
         })
         .collect();
 
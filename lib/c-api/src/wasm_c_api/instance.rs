@@ -2,7 +2,9 @@ use super::externals::{wasm_extern_t, wasm_extern_vec_t};
 use super::module::wasm_module_t;
 use super::store::wasm_store_t;
 use super::trap::wasm_trap_t;
+use super::types::wasm_externkind_enum;
 use crate::ordered_resolver::OrderedResolver;
+use std::convert::TryFrom;
 use std::mem;
 use std::sync::Arc;
 use wasmer::{Extern, Instance, InstantiationError};
@@ -94,6 +96,9 @@ pub unsafe extern "C" fn wasm_instance_delete(_instance: Option<Box<wasm_instanc
 
 /// Gets the exports of the instance.
 ///
+/// The exports that the C API can't represent, such as exception tags,
+/// are left out.
+///
 /// # Example
 ///
 /// ```rust
@@ -185,6 +190,7 @@ pub unsafe extern "C" fn wasm_instance_exports(
     let mut extern_vec = instance
         .exports
         .iter()
+        .filter(|(_, r#extern)| wasm_externkind_enum::try_from(r#extern.ty()).is_ok())
         .map(|(name, r#extern)| {
             let function = if let Extern::Function { .. } = r#extern {
                 instance.exports.get_function(&name).ok().cloned()
@@ -4,6 +4,7 @@ use super::types::{
     wasm_importtype_vec_t,
 };
 use crate::error::{update_last_error, CApiError};
+use std::convert::TryFrom;
 use std::ptr::NonNull;
 use std::sync::Arc;
 use wasmer::Module;
@@ -124,7 +125,8 @@ pub unsafe extern "C" fn wasm_module_validate(
 /// Returns an array of the exported types in the module.
 ///
 /// The order of the exports is guaranteed to be the same as in the
-/// WebAssembly bytecode.
+/// WebAssembly bytecode. The exports that the C API can't represent,
+/// such as exception tags, are left out.
 ///
 /// # Example
 ///
@@ -238,7 +240,7 @@ pub unsafe extern "C" fn wasm_module_exports(
     let exports = module
         .inner
         .exports()
-        .map(Into::into)
+        .filter_map(|export_type| wasm_exporttype_t::try_from(export_type).ok())
         .map(Box::new)
         .collect::<Vec<Box<wasm_exporttype_t>>>();
 
@@ -248,7 +250,8 @@ pub unsafe extern "C" fn wasm_module_exports(
 /// Returns an array of the imported types in the module.
 ///
 /// The order of the imports is guaranteed to be the same as in the
-/// WebAssembly bytecode.
+/// WebAssembly bytecode. The imports that the C API can't represent,
+/// such as exception tags, are left out.
 ///
 /// # Example
 ///
@@ -379,7 +382,7 @@ pub unsafe extern "C" fn wasm_module_imports(
     let imports = module
         .inner
         .imports()
-        .map(Into::into)
+        .filter_map(|import_type| wasm_importtype_t::try_from(import_type).ok())
         .map(Box::new)
         .collect::<Vec<Box<wasm_importtype_t>>>();
 
@@ -1,4 +1,6 @@
 use super::{wasm_externtype_t, wasm_name_t};
+use crate::error::CApiError;
+use std::convert::{TryFrom, TryInto};
 use wasmer::ExportType;
 
 #[allow(non_camel_case_types)]
@@ -34,17 +36,21 @@ pub extern "C" fn wasm_exporttype_type(export_type: &wasm_exporttype_t) -> &wasm
 #[no_mangle]
 pub extern "C" fn wasm_exporttype_delete(_export_type: Option<Box<wasm_exporttype_t>>) {}
 
-impl From<ExportType> for wasm_exporttype_t {
-    fn from(other: ExportType) -> Self {
-        (&other).into()
+impl TryFrom<ExportType> for wasm_exporttype_t {
+    type Error = CApiError;
+
+    fn try_from(other: ExportType) -> Result<Self, Self::Error> {
+        (&other).try_into()
     }
 }
 
-impl From<&ExportType> for wasm_exporttype_t {
-    fn from(other: &ExportType) -> Self {
+impl TryFrom<&ExportType> for wasm_exporttype_t {
+    type Error = CApiError;
+
+    fn try_from(other: &ExportType) -> Result<Self, Self::Error> {
         let name: Box<wasm_name_t> = Box::new(other.name().to_string().into());
-        let extern_type: Box<wasm_externtype_t> = Box::new(other.ty().into());
+        let extern_type: Box<wasm_externtype_t> = Box::new(other.ty().try_into()?);
 
-        wasm_exporttype_t { name, extern_type }
+        Ok(wasm_exporttype_t { name, extern_type })
     }
 }
@@ -3,6 +3,7 @@ use super::{
     wasm_functype_t, wasm_globaltype_t, wasm_memorytype_t, wasm_tabletype_t, WasmFunctionType,
     WasmGlobalType, WasmMemoryType, WasmTableType,
 };
+use crate::error::CApiError;
 use std::convert::{TryFrom, TryInto};
 use std::mem;
 use thiserror::Error;
@@ -20,19 +21,35 @@ pub enum wasm_externkind_enum {
     WASM_EXTERN_MEMORY = 3,
 }
 
-impl From<ExternType> for wasm_externkind_enum {
-    fn from(other: ExternType) -> Self {
-        (&other).into()
+impl TryFrom<ExternType> for wasm_externkind_enum {
+    type Error = CApiError;
+
+    fn try_from(other: ExternType) -> Result<Self, Self::Error> {
+        (&other).try_into()
     }
 }
-impl From<&ExternType> for wasm_externkind_enum {
-    fn from(other: &ExternType) -> Self {
-        match other {
+
+impl TryFrom<&ExternType> for wasm_externkind_enum {
+    type Error = CApiError;
+
+    fn try_from(other: &ExternType) -> Result<Self, Self::Error> {
+        Ok(match other {
             ExternType::Function(_) => Self::WASM_EXTERN_FUNC,
             ExternType::Global(_) => Self::WASM_EXTERN_GLOBAL,
             ExternType::Table(_) => Self::WASM_EXTERN_TABLE,
             ExternType::Memory(_) => Self::WASM_EXTERN_MEMORY,
-        }
+            ExternType::Tag(_) => return Err(unsupported_extern_type("exception tags")),
+            ExternType::Instance(_) | ExternType::Module(_) => {
+                return Err(unsupported_extern_type("instances and modules"))
+            }
+        })
+    }
+}
+
+/// The error returned for the externs that the C API can't represent.
+fn unsupported_extern_type(what: &str) -> CApiError {
+    CApiError {
+        msg: format!("{} are not supported by the C API", what),
     }
 }
 
@@ -51,46 +68,59 @@ pub struct wasm_externtype_t {
 }
 
 impl wasm_externtype_t {
-    pub(crate) fn new(extern_type: ExternType) -> Self {
-        Self {
-            inner: match extern_type {
-                ExternType::Function(function_type) => {
-                    WasmExternType::Function(WasmFunctionType::new(function_type))
-                }
-                ExternType::Global(global_type) => {
-                    WasmExternType::Global(WasmGlobalType::new(global_type))
-                }
-                ExternType::Table(table_type) => {
-                    WasmExternType::Table(WasmTableType::new(table_type))
-                }
-                ExternType::Memory(memory_type) => {
-                    WasmExternType::Memory(WasmMemoryType::new(memory_type))
-                }
-            },
-        }
+    pub(crate) fn new(inner: WasmExternType) -> Self {
+        Self { inner }
     }
 }
 
-impl From<ExternType> for wasm_externtype_t {
-    fn from(extern_type: ExternType) -> Self {
-        Self::new(extern_type)
+impl TryFrom<ExternType> for wasm_externtype_t {
+    type Error = CApiError;
+
+    fn try_from(extern_type: ExternType) -> Result<Self, Self::Error> {
+        Ok(Self::new(match extern_type {
+            ExternType::Function(function_type) => {
+                WasmExternType::Function(WasmFunctionType::new(function_type))
+            }
+            ExternType::Global(global_type) => {
+                WasmExternType::Global(WasmGlobalType::new(global_type))
+            }
+            ExternType::Table(table_type) => WasmExternType::Table(WasmTableType::new(table_type)),
+            ExternType::Memory(memory_type) => {
+                WasmExternType::Memory(WasmMemoryType::new(memory_type))
+            }
+            ExternType::Tag(_) => return Err(unsupported_extern_type("exception tags")),
+            ExternType::Instance(_) | ExternType::Module(_) => {
+                return Err(unsupported_extern_type("instances and modules"))
+            }
+        }))
     }
 }
 
-impl From<&ExternType> for wasm_externtype_t {
-    fn from(other: &ExternType) -> Self {
-        other.clone().into()
+impl TryFrom<&ExternType> for wasm_externtype_t {
+    type Error = CApiError;
+
+    fn try_from(other: &ExternType) -> Result<Self, Self::Error> {
+        other.clone().try_into()
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn wasm_extern_type(r#extern: &wasm_extern_t) -> Box<wasm_externtype_t> {
-    Box::new(wasm_externtype_t::new(r#extern.inner.ty()))
+pub unsafe extern "C" fn wasm_extern_type(
+    r#extern: &wasm_extern_t,
+) -> Option<Box<wasm_externtype_t>> {
+    Some(Box::new(c_try!(r#extern.inner.ty().try_into())))
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wasm_extern_kind(r#extern: &wasm_extern_t) -> wasm_externkind_t {
-    wasm_externkind_enum::from(r#extern.inner.ty()) as wasm_externkind_t
+    // `wasm_instance_exports` leaves out the externs without a kind, but
+    // don't panic across the FFI boundary if one shows up anyway
+    let kind = c_try!(
+        wasm_externkind_enum::try_from(r#extern.inner.ty());
+        otherwise wasm_externkind_t::MAX
+    );
+
+    kind as wasm_externkind_t
 }
 
 #[no_mangle]
@@ -1,5 +1,5 @@
 use super::{wasm_externtype_t, wasm_valtype_vec_delete, wasm_valtype_vec_t, WasmExternType};
-use wasmer::{ExternType, FunctionType, ValType};
+use wasmer::{FunctionType, ValType};
 
 #[derive(Debug)]
 pub(crate) struct WasmFunctionType {
@@ -36,8 +36,10 @@ pub struct wasm_functype_t {
 
 impl wasm_functype_t {
     pub(crate) fn new(function_type: FunctionType) -> Self {
+        let extern_type = WasmExternType::Function(WasmFunctionType::new(function_type));
+
         Self {
-            extern_type: wasm_externtype_t::new(ExternType::Function(function_type)),
+            extern_type: wasm_externtype_t::new(extern_type),
         }
     }
 
@@ -3,7 +3,7 @@ use super::{
     wasm_valtype_t, WasmExternType,
 };
 use std::convert::TryInto;
-use wasmer::{ExternType, GlobalType};
+use wasmer::GlobalType;
 
 #[derive(Debug, Clone)]
 pub(crate) struct WasmGlobalType {
@@ -31,8 +31,10 @@ pub struct wasm_globaltype_t {
 
 impl wasm_globaltype_t {
     pub(crate) fn new(global_type: GlobalType) -> Self {
+        let extern_type = WasmExternType::Global(WasmGlobalType::new(global_type));
+
         Self {
-            extern_type: wasm_externtype_t::new(ExternType::Global(global_type)),
+            extern_type: wasm_externtype_t::new(extern_type),
         }
     }
 
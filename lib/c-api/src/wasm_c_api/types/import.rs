@@ -1,4 +1,6 @@
 use super::{wasm_externtype_t, wasm_name_t};
+use crate::error::CApiError;
+use std::convert::{TryFrom, TryInto};
 use wasmer::ImportType;
 
 #[allow(non_camel_case_types)]
@@ -42,22 +44,26 @@ pub extern "C" fn wasm_importtype_type(import_type: &wasm_importtype_t) -> &wasm
 #[no_mangle]
 pub unsafe extern "C" fn wasm_importtype_delete(_import_type: Option<Box<wasm_importtype_t>>) {}
 
-impl From<ImportType> for wasm_importtype_t {
-    fn from(other: ImportType) -> Self {
-        (&other).into()
+impl TryFrom<ImportType> for wasm_importtype_t {
+    type Error = CApiError;
+
+    fn try_from(other: ImportType) -> Result<Self, Self::Error> {
+        (&other).try_into()
     }
 }
 
-impl From<&ImportType> for wasm_importtype_t {
-    fn from(other: &ImportType) -> Self {
+impl TryFrom<&ImportType> for wasm_importtype_t {
+    type Error = CApiError;
+
+    fn try_from(other: &ImportType) -> Result<Self, Self::Error> {
         let module: Box<wasm_name_t> = Box::new(other.module().to_string().into());
         let name: Box<wasm_name_t> = Box::new(other.name().to_string().into());
-        let extern_type: Box<wasm_externtype_t> = Box::new(other.ty().into());
+        let extern_type: Box<wasm_externtype_t> = Box::new(other.ty().try_into()?);
 
-        wasm_importtype_t {
+        Ok(wasm_importtype_t {
             module,
             name,
             extern_type,
-        }
+        })
     }
 }
@@ -1,5 +1,5 @@
 use super::{wasm_externtype_t, WasmExternType};
-use wasmer::{ExternType, MemoryType, Pages};
+use wasmer::{MemoryType, Pages};
 
 #[derive(Debug, Clone)]
 pub(crate) struct WasmMemoryType {
@@ -33,8 +33,10 @@ pub struct wasm_memorytype_t {
 
 impl wasm_memorytype_t {
     pub(crate) fn new(memory_type: MemoryType) -> Self {
+        let extern_type = WasmExternType::Memory(WasmMemoryType::new(memory_type));
+
         Self {
-            extern_type: wasm_externtype_t::new(ExternType::Memory(memory_type)),
+            extern_type: wasm_externtype_t::new(extern_type),
         }
     }
 
@@ -1,7 +1,7 @@
 use super::{
     wasm_externtype_t, wasm_limits_t, wasm_valtype_delete, wasm_valtype_t, WasmExternType,
 };
-use wasmer::{ExternType, TableType};
+use wasmer::TableType;
 
 #[allow(non_camel_case_types)]
 pub type wasm_table_size_t = u32;
@@ -40,8 +40,10 @@ pub struct wasm_tabletype_t {
 
 impl wasm_tabletype_t {
     pub(crate) fn new(table_type: TableType) -> Self {
+        let extern_type = WasmExternType::Table(WasmTableType::new(table_type));
+
         Self {
-            extern_type: wasm_externtype_t::new(ExternType::Table(table_type)),
+            extern_type: wasm_externtype_t::new(extern_type),
         }
     }
 
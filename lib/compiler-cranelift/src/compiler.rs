@@ -2,8 +2,6 @@
 
 use crate::address_map::get_function_address_map;
 use crate::config::Cranelift;
-#[cfg(feature = "unwind")]
-use crate::dwarf::WriterRelocate;
 use crate::func_environ::{get_function_name, FuncEnvironment};
 use crate::sink::{RelocSink, TrapSink};
 use crate::trampoline::{
@@ -21,13 +19,15 @@ use gimli::write::{Address, EhFrame, FrameTable};
 use loupe::MemoryUsage;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
-use wasmer_compiler::CompileError;
+#[cfg(feature = "unwind")]
+use wasmer_compiler::WriterRelocate;
 use wasmer_compiler::{CallingConvention, ModuleTranslationState, Target};
 use wasmer_compiler::{
     Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
     CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBody, FunctionBodyData,
     ModuleMiddlewareChain, SectionIndex,
 };
+use wasmer_compiler::{CompileError, WasmError};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
 
@@ -119,15 +119,24 @@ impl Compiler for CraneliftCompiler {
                 //     context.func.collect_debug_info();
                 // }
 
-                func_translator.translate(
-                    module_translation_state,
-                    input.data,
-                    input.module_offset,
-                    &mut context.func,
-                    &mut func_env,
-                    *i,
-                    &self.config,
-                )?;
+                func_translator
+                    .translate(
+                        module_translation_state,
+                        input.data,
+                        input.module_offset,
+                        &mut context.func,
+                        &mut func_env,
+                        *i,
+                        &self.config,
+                    )
+                    .map_err(|error| match error {
+                        // Operators that Cranelift can't lower yet are reported
+                        // as unsupported features of the compiler.
+                        WasmError::Unsupported(feature) => {
+                            CompileError::UnsupportedFeature(feature)
+                        }
+                        error => error.into(),
+                    })?;
 
                 let mut code_buf: Vec<u8> = Vec::new();
                 let mut reloc_sink = RelocSink::new(&module, func_index);
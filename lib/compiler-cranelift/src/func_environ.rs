@@ -2,7 +2,8 @@
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
 use crate::translator::{
-    type_to_irtype, FuncEnvironment as BaseFuncEnvironment, GlobalVariable, TargetEnvironment,
+    type_to_irtype, FuncEnvironment as BaseFuncEnvironment, FuncTranslationState, GlobalVariable,
+    TargetEnvironment,
 };
 use cranelift_codegen::cursor::FuncCursor;
 use cranelift_codegen::ir;
@@ -12,12 +13,13 @@ use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{AbiParam, ArgumentPurpose, Function, InstBuilder, Signature};
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::FunctionBuilder;
+use std::cmp;
 use std::convert::TryFrom;
 use wasmer_compiler::wasmparser::Type;
 use wasmer_compiler::{WasmError, WasmResult};
 use wasmer_types::entity::EntityRef;
 use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex};
 use wasmer_vm::VMBuiltinFunctionIndex;
 use wasmer_vm::VMOffsets;
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle};
@@ -27,11 +29,6 @@ pub fn get_function_name(func_index: FunctionIndex) -> ir::ExternalName {
     ir::ExternalName::user(0, func_index.as_u32())
 }
 
-/// The type of the `current_length` field.
-pub fn type_of_vmmemory_definition_current_length(vmoffsets: &VMOffsets) -> ir::Type {
-    ir::Type::int(u16::from(vmoffsets.size_of_vmmemory_definition_current_length()) * 8).unwrap()
-}
-
 /// The type of the `current_elements` field.
 pub fn type_of_vmtable_definition_current_elements(vmoffsets: &VMOffsets) -> ir::Type {
     ir::Type::int(u16::from(vmoffsets.size_of_vmtable_definition_current_elements()) * 8).unwrap()
@@ -59,6 +56,24 @@ pub struct FuncEnvironment<'module_environment> {
     /// for locally-defined memories.
     memory_grow_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `table.size`.
+    table_size_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `table.get`.
+    table_get_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `table.set`.
+    table_set_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `table.grow`.
+    table_grow_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `table.fill`.
+    table_fill_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `ref.func`.
+    func_ref_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `table.copy`
     /// (it's the same for both local and imported tables).
     table_copy_sig: Option<ir::SigRef>,
@@ -80,9 +95,63 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory.init`.
     memory_init_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// for 64-bit memories.
+    memory64_copy_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// between two different memories.
+    memory_copy_between_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.fill`
+    /// for 64-bit memories.
+    memory64_fill_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.init`
+    /// for 64-bit memories.
+    memory64_init_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `data.drop`.
     data_drop_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait32`.
+    memory_atomic_wait32_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.wait64`.
+    memory_atomic_wait64_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's
+    /// `memory.atomic.notify`.
+    memory_atomic_notify_sig: Option<ir::SigRef>,
+
+    /// The external function signature for creating the exception thrown by
+    /// wasm's `throw`.
+    exception_new_sig: Option<ir::SigRef>,
+
+    /// The external function signature for raising an exception not caught
+    /// by the function throwing it.
+    exception_raise_sig: Option<ir::SigRef>,
+
+    /// The external function signature for calling a function from the body
+    /// of wasm's `try`.
+    invoke_sig: Option<ir::SigRef>,
+
+    /// The external function signature for matching an exception against
+    /// wasm's `catch` clauses.
+    exception_matches_sig: Option<ir::SigRef>,
+
+    /// The external function signature for reading the values of a caught
+    /// exception.
+    exception_take_payload_sig: Option<ir::SigRef>,
+
+    /// The external function signature for checking for a pending interrupt.
+    interrupt_sig: Option<ir::SigRef>,
+
+    /// The external function signature for handling a reached epoch deadline.
+    epoch_deadline_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -108,13 +177,33 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             vmctx: None,
             memory32_size_sig: None,
             memory_grow_sig: None,
+            table_size_sig: None,
+            table_get_sig: None,
+            table_set_sig: None,
+            table_grow_sig: None,
+            table_fill_sig: None,
+            func_ref_sig: None,
             table_copy_sig: None,
             table_init_sig: None,
             elem_drop_sig: None,
             memory_copy_sig: None,
             memory_fill_sig: None,
             memory_init_sig: None,
+            memory64_copy_sig: None,
+            memory_copy_between_sig: None,
+            memory64_fill_sig: None,
+            memory64_init_sig: None,
             data_drop_sig: None,
+            memory_atomic_wait32_sig: None,
+            memory_atomic_wait64_sig: None,
+            memory_atomic_notify_sig: None,
+            exception_new_sig: None,
+            exception_raise_sig: None,
+            invoke_sig: None,
+            exception_matches_sig: None,
+            exception_take_payload_sig: None,
+            interrupt_sig: None,
+            epoch_deadline_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
@@ -208,6 +297,188 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    fn get_table_size_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_size_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Table index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_size_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_size_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_table_size_sig(func);
+        (
+            sig,
+            table_index.as_u32() as usize,
+            VMBuiltinFunctionIndex::get_table_size_index(),
+        )
+    }
+
+    fn get_table_get_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_get_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Table index.
+                    AbiParam::new(I32),
+                    // Index within table.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(self.pointer_type())],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_get_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_get_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_table_get_sig(func);
+        (
+            sig,
+            table_index.as_u32() as usize,
+            VMBuiltinFunctionIndex::get_table_get_index(),
+        )
+    }
+
+    fn get_table_set_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_set_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Table index.
+                    AbiParam::new(I32),
+                    // Index within table.
+                    AbiParam::new(I32),
+                    // Value to store.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_set_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_set_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_table_set_sig(func);
+        (
+            sig,
+            table_index.as_u32() as usize,
+            VMBuiltinFunctionIndex::get_table_set_index(),
+        )
+    }
+
+    fn get_table_grow_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_grow_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Value to initialize the new elements with.
+                    AbiParam::new(self.pointer_type()),
+                    // Number of elements to add.
+                    AbiParam::new(I32),
+                    // Table index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_grow_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_grow_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_table_grow_sig(func);
+        (
+            sig,
+            table_index.as_u32() as usize,
+            VMBuiltinFunctionIndex::get_table_grow_index(),
+        )
+    }
+
+    fn get_table_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.table_fill_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Table index.
+                    AbiParam::new(I32),
+                    // Index within table.
+                    AbiParam::new(I32),
+                    // Value to fill with.
+                    AbiParam::new(self.pointer_type()),
+                    // Number of elements to fill.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.table_fill_sig = Some(sig);
+        sig
+    }
+
+    fn get_table_fill_func(
+        &mut self,
+        func: &mut Function,
+        table_index: TableIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_table_fill_sig(func);
+        (
+            sig,
+            table_index.as_u32() as usize,
+            VMBuiltinFunctionIndex::get_table_fill_index(),
+        )
+    }
+
+    fn get_func_ref_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.func_ref_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Function index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(self.pointer_type())],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.func_ref_sig = Some(sig);
+        sig
+    }
+
+    fn get_func_ref_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_func_ref_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_func_ref_index())
+    }
+
     fn get_table_copy_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.table_copy_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -306,25 +577,32 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_elem_drop_index())
     }
 
-    fn get_memory_copy_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_copy_sig.unwrap_or_else(|| {
+    fn get_memory_copy_sig(&mut self, func: &mut Function, memory64: bool) -> ir::SigRef {
+        let (cached_sig, addr_ty) = if memory64 {
+            (&mut self.memory64_copy_sig, I64)
+        } else {
+            (&mut self.memory_copy_sig, I32)
+        };
+        let pointer_type = self.target_config.pointer_type();
+        let call_conv = self.target_config.default_call_conv;
+        let sig = cached_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    AbiParam::special(pointer_type, ArgumentPurpose::VMContext),
                     // Memory index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(addr_ty),
                     // Source address.
-                    AbiParam::new(I32),
+                    AbiParam::new(addr_ty),
                     // Length.
-                    AbiParam::new(I32),
+                    AbiParam::new(addr_ty),
                 ],
                 returns: vec![],
-                call_conv: self.target_config.default_call_conv,
+                call_conv,
             })
         });
-        self.memory_copy_sig = Some(sig);
+        *cached_sig = Some(sig);
         sig
     }
 
@@ -333,41 +611,81 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_copy_sig(func);
+        let memory64 = self.module.memories[memory_index].memory64;
+        let sig = self.get_memory_copy_sig(func, memory64);
         if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
             (
                 sig,
                 local_memory_index.index(),
-                VMBuiltinFunctionIndex::get_local_memory_copy_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_local_memory64_copy_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_local_memory_copy_index()
+                },
             )
         } else {
             (
                 sig,
                 memory_index.index(),
-                VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_imported_memory64_copy_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_imported_memory_copy_index()
+                },
             )
         }
     }
 
-    fn get_memory_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_fill_sig.unwrap_or_else(|| {
+    fn get_memory_copy_between_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_copy_between_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
                     AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
-                    // Memory index.
+                    // Destination memory index.
+                    AbiParam::new(I32),
+                    // Source memory index.
                     AbiParam::new(I32),
                     // Destination address.
+                    AbiParam::new(I64),
+                    // Source address.
+                    AbiParam::new(I64),
+                    // Length.
+                    AbiParam::new(I64),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_copy_between_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_fill_sig(&mut self, func: &mut Function, memory64: bool) -> ir::SigRef {
+        let (cached_sig, addr_ty) = if memory64 {
+            (&mut self.memory64_fill_sig, I64)
+        } else {
+            (&mut self.memory_fill_sig, I32)
+        };
+        let pointer_type = self.target_config.pointer_type();
+        let call_conv = self.target_config.default_call_conv;
+        let sig = cached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(pointer_type, ArgumentPurpose::VMContext),
+                    // Memory index.
                     AbiParam::new(I32),
+                    // Destination address.
+                    AbiParam::new(addr_ty),
                     // Value.
                     AbiParam::new(I32),
                     // Length.
-                    AbiParam::new(I32),
+                    AbiParam::new(addr_ty),
                 ],
                 returns: vec![],
-                call_conv: self.target_config.default_call_conv,
+                call_conv,
             })
         });
-        self.memory_fill_sig = Some(sig);
+        *cached_sig = Some(sig);
         sig
     }
 
@@ -376,73 +694,572 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_fill_sig(func);
+        let memory64 = self.module.memories[memory_index].memory64;
+        let sig = self.get_memory_fill_sig(func, memory64);
         if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
             (
                 sig,
                 local_memory_index.index(),
-                VMBuiltinFunctionIndex::get_memory_fill_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_memory64_fill_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_memory_fill_index()
+                },
             )
         } else {
             (
                 sig,
                 memory_index.index(),
-                VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_imported_memory64_fill_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_imported_memory_fill_index()
+                },
             )
         }
     }
 
-    fn get_memory_init_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_init_sig.unwrap_or_else(|| {
-            func.import_signature(Signature {
-                params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
-                    // Memory index.
-                    AbiParam::new(I32),
-                    // Data index.
-                    AbiParam::new(I32),
-                    // Destination address.
-                    AbiParam::new(I32),
-                    // Source index within the data segment.
-                    AbiParam::new(I32),
-                    // Length.
-                    AbiParam::new(I32),
-                ],
-                returns: vec![],
-                call_conv: self.target_config.default_call_conv,
-            })
-        });
-        self.memory_init_sig = Some(sig);
-        sig
+    fn get_memory_init_sig(&mut self, func: &mut Function, memory64: bool) -> ir::SigRef {
+        let (cached_sig, addr_ty) = if memory64 {
+            (&mut self.memory64_init_sig, I64)
+        } else {
+            (&mut self.memory_init_sig, I32)
+        };
+        let pointer_type = self.target_config.pointer_type();
+        let call_conv = self.target_config.default_call_conv;
+        let sig = cached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(pointer_type, ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Data index.
+                    AbiParam::new(I32),
+                    // Destination address.
+                    AbiParam::new(addr_ty),
+                    // Source index within the data segment.
+                    AbiParam::new(I32),
+                    // Length.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv,
+            })
+        });
+        *cached_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_init_func(
+        &mut self,
+        func: &mut Function,
+        memory_index: MemoryIndex,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        if self.module.memories[memory_index].memory64 {
+            let sig = self.get_memory_init_sig(func, true);
+            (sig, VMBuiltinFunctionIndex::get_memory64_init_index())
+        } else {
+            let sig = self.get_memory_init_sig(func, false);
+            (sig, VMBuiltinFunctionIndex::get_memory_init_index())
+        }
+    }
+
+    fn get_data_drop_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.data_drop_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Data index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.data_drop_sig = Some(sig);
+        sig
+    }
+
+    fn get_data_drop_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_data_drop_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_data_drop_index())
+    }
+
+    fn get_memory_atomic_wait_sig(&mut self, func: &mut Function, ty: ir::Type) -> ir::SigRef {
+        let cached_sig = if ty == I64 {
+            &mut self.memory_atomic_wait64_sig
+        } else {
+            &mut self.memory_atomic_wait32_sig
+        };
+        let pointer_type = self.target_config.pointer_type();
+        let call_conv = self.target_config.default_call_conv;
+        let sig = cached_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(pointer_type, ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Effective address.
+                    AbiParam::new(I64),
+                    // Expected value.
+                    AbiParam::new(ty),
+                    // Timeout in nanoseconds.
+                    AbiParam::new(I64),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv,
+            })
+        });
+        *cached_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_atomic_wait_func(
+        &mut self,
+        func: &mut Function,
+        memory_index: MemoryIndex,
+        ty: ir::Type,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_memory_atomic_wait_sig(func, ty);
+        let func_idx = if ty == I64 {
+            VMBuiltinFunctionIndex::get_memory_atomic_wait64_index()
+        } else {
+            VMBuiltinFunctionIndex::get_memory_atomic_wait32_index()
+        };
+        (sig, memory_index.index(), func_idx)
+    }
+
+    fn get_memory_atomic_notify_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.memory_atomic_notify_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Memory index.
+                    AbiParam::new(I32),
+                    // Effective address.
+                    AbiParam::new(I64),
+                    // Maximum number of waiters to wake up.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.memory_atomic_notify_sig = Some(sig);
+        sig
+    }
+
+    fn get_memory_atomic_notify_func(
+        &mut self,
+        func: &mut Function,
+        memory_index: MemoryIndex,
+    ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
+        let sig = self.get_memory_atomic_notify_sig(func);
+        (
+            sig,
+            memory_index.index(),
+            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+        )
+    }
+
+    fn get_exception_new_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_new_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Tag index.
+                    AbiParam::new(I32),
+                    // Address of the values carried by the exception.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![AbiParam::new(self.pointer_type())],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_new_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_new_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_new_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_new_index())
+    }
+
+    fn get_exception_raise_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_raise_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    // Exception.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_raise_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_raise_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_raise_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_raise_index())
+    }
+
+    fn get_invoke_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.invoke_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Signature index of the callee.
+                    AbiParam::new(I32),
+                    // Callee address.
+                    AbiParam::new(self.pointer_type()),
+                    // Callee vmctx.
+                    AbiParam::new(self.pointer_type()),
+                    // Address of the arguments and results.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![AbiParam::new(self.pointer_type())],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.invoke_sig = Some(sig);
+        sig
+    }
+
+    fn get_invoke_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_invoke_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_invoke_index())
+    }
+
+    fn get_exception_matches_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_matches_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(self.pointer_type()),
+                    // Tag index.
+                    AbiParam::new(I32),
+                ],
+                returns: vec![AbiParam::new(I32)],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_matches_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_matches_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_matches_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_matches_index())
+    }
+
+    fn get_exception_take_payload_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_take_payload_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    // Exception.
+                    AbiParam::new(self.pointer_type()),
+                    // Address where to store the values.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_take_payload_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_take_payload_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_take_payload_sig(func);
+        (
+            sig,
+            VMBuiltinFunctionIndex::get_exception_take_payload_index(),
+        )
+    }
+
+    fn get_interrupt_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.interrupt_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.interrupt_sig = Some(sig);
+        sig
+    }
+
+    fn get_interrupt_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_interrupt_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_interrupt_index())
+    }
+
+    fn get_epoch_deadline_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.epoch_deadline_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![AbiParam::special(
+                    self.pointer_type(),
+                    ArgumentPurpose::VMContext,
+                )],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.epoch_deadline_sig = Some(sig);
+        sig
+    }
+
+    fn get_epoch_deadline_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_epoch_deadline_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_epoch_deadline_index())
+    }
+
+    /// Emits a call to the builtin function `func_idx`, taking only vmctx,
+    /// when `condition` is non-zero.
+    fn translate_builtin_call_if(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        condition: ir::Value,
+        func_sig: ir::SigRef,
+        func_idx: VMBuiltinFunctionIndex,
+    ) {
+        let call_block = builder.create_block();
+        let continuation_block = builder.create_block();
+        builder.ins().brnz(condition, call_block, &[]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(call_block);
+
+        builder.switch_to_block(call_block);
+        let mut pos = builder.cursor();
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(func_sig, func_addr, &[vmctx]);
+        builder.ins().jump(continuation_block, &[]);
+        builder.seal_block(continuation_block);
+
+        builder.switch_to_block(continuation_block);
+    }
+
+    /// Emits the checks of the store's interrupt flag and of the epoch
+    /// deadline of the instance, calling into the runtime (which may trap)
+    /// when the former is set or the latter is reached.
+    fn translate_interrupt_checks(&mut self, builder: &mut FunctionBuilder) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(&mut builder.func);
+        let base = builder.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+        let interrupts_offset = i32::try_from(self.offsets.vmctx_interrupts()).unwrap();
+        let interrupts = builder
+            .ins()
+            .load(pointer_type, mem_flags, base, interrupts_offset);
+
+        // The flag is written by other threads, so the load must not be
+        // considered readonly.
+        let interrupt = builder.ins().load(
+            pointer_type,
+            ir::MemFlags::trusted(),
+            interrupts,
+            i32::from(self.offsets.vminterrupts_interrupt()),
+        );
+        let (func_sig, func_idx) = self.get_interrupt_func(&mut builder.func);
+        self.translate_builtin_call_if(builder, interrupt, func_sig, func_idx);
+
+        // The epoch is incremented by other threads and the deadline can be
+        // extended by the host, so these loads aren't readonly either.
+        let epoch = builder.ins().load(
+            I64,
+            ir::MemFlags::trusted(),
+            interrupts,
+            i32::from(self.offsets.vminterrupts_epoch()),
+        );
+        let deadline_offset = i32::try_from(self.offsets.vmctx_epoch_deadline()).unwrap();
+        let deadline = builder
+            .ins()
+            .load(I64, ir::MemFlags::trusted(), base, deadline_offset);
+        let reached = builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, epoch, deadline);
+        let (func_sig, func_idx) = self.get_epoch_deadline_func(&mut builder.func);
+        self.translate_builtin_call_if(builder, reached, func_sig, func_idx);
+    }
+
+    /// Creates a stack slot holding `len` values, each one in a 128-bit slot
+    /// like the values passed to trampolines, and stores `values` in it.
+    ///
+    /// Returns the stack slot and its address.
+    fn make_values_buffer(
+        &self,
+        pos: &mut FuncCursor<'_>,
+        len: usize,
+        values: &[ir::Value],
+    ) -> (ir::StackSlot, ir::Value) {
+        let slot = pos.func.create_stack_slot(ir::StackSlotData::new(
+            ir::StackSlotKind::ExplicitSlot,
+            (cmp::max(len, 1) * 16) as u32,
+        ));
+        for (i, value) in values.iter().enumerate() {
+            pos.ins().stack_store(*value, slot, (i * 16) as i32);
+        }
+        let addr = pos.ins().stack_addr(self.pointer_type(), slot, 0);
+        (slot, addr)
+    }
+
+    /// Loads the address and the vmctx of the imported function
+    /// `callee_index`.
+    fn load_imported_callee(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        callee_index: FunctionIndex,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(&mut pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mem_flags = ir::MemFlags::trusted();
+
+        // Load the callee address.
+        let body_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
+        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+
+        // Load the callee vmctx address.
+        let vmctx_offset =
+            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
+        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
+
+        (func_addr, vmctx)
+    }
+
+    /// Loads the address and the vmctx of the function `callee` in the table
+    /// `table_index`, checking that it has the signature `sig_index`.
+    fn load_indirect_callee(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+
+        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
+
+        // Dereference table_entry_addr to get the function address.
+        let mem_flags = ir::MemFlags::trusted();
+        let func_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
+        );
+
+        // Check whether `func_addr` is null.
+        pos.ins().trapz(func_addr, ir::TrapCode::IndirectCallToNull);
+
+        // If necessary, check the signature.
+        match self.table_styles[table_index] {
+            TableStyle::CallerChecksSignature => {
+                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
+                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
+                let vmctx = self.vmctx(pos.func);
+                let base = pos.ins().global_value(pointer_type, vmctx);
+                let offset =
+                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
+
+                // Load the caller ID.
+                let mut mem_flags = ir::MemFlags::trusted();
+                mem_flags.set_readonly();
+                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
+
+                // Load the callee ID.
+                let mem_flags = ir::MemFlags::trusted();
+                let callee_sig_id = pos.ins().load(
+                    sig_id_type,
+                    mem_flags,
+                    table_entry_addr,
+                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
+                );
+
+                // Check that they match.
+                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
+                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
+            }
+        }
+
+        // Load the callee vmctx address.
+        let vmctx = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
+        );
+
+        (func_addr, vmctx)
     }
 
-    fn get_memory_init_func(
+    /// Calls the function at `func_addr`, of signature `sig_index`, through
+    /// the `invoke` builtin so that the exception it may throw is caught.
+    ///
+    /// Returns the handle to the exception, null if the call returned
+    /// normally, and the results of the call.
+    fn invoke(
         &mut self,
-        func: &mut Function,
-    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_init_sig(func);
-        (sig, VMBuiltinFunctionIndex::get_memory_init_index())
-    }
-
-    fn get_data_drop_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.data_drop_sig.unwrap_or_else(|| {
-            func.import_signature(Signature {
-                params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
-                    // Data index.
-                    AbiParam::new(I32),
-                ],
-                returns: vec![],
-                call_conv: self.target_config.default_call_conv,
-            })
-        });
-        self.data_drop_sig = Some(sig);
-        sig
-    }
+        pos: &mut FuncCursor<'_>,
+        sig_index: SignatureIndex,
+        sig_ref: ir::SigRef,
+        func_addr: ir::Value,
+        callee_vmctx: ir::Value,
+        call_args: &[ir::Value],
+    ) -> (ir::Value, Vec<ir::Value>) {
+        let result_types = pos.func.dfg.signatures[sig_ref]
+            .returns
+            .iter()
+            .map(|param| param.value_type)
+            .collect::<Vec<_>>();
+        let (slot, values) = self.make_values_buffer(
+            pos,
+            cmp::max(call_args.len(), result_types.len()),
+            call_args,
+        );
 
-    fn get_data_drop_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
-        let sig = self.get_data_drop_sig(func);
-        (sig, VMBuiltinFunctionIndex::get_data_drop_index())
+        let (func_sig, func_idx) = self.get_invoke_func(&mut pos.func);
+        let sig_index_arg = pos.ins().iconst(I32, sig_index.as_u32() as i64);
+        let (vmctx, invoke_addr) = self.translate_load_builtin_function_address(pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            invoke_addr,
+            &[vmctx, sig_index_arg, func_addr, callee_vmctx, values],
+        );
+        let exn = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+
+        let results = result_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| pos.ins().stack_load(*ty, slot, (i * 16) as i32))
+            .collect();
+        (exn, results)
     }
 
     /// Translates load of builtin function and returns a pair of values `vmctx`
@@ -481,6 +1298,40 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         index >= 1
     }
 
+    fn before_translate_function(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        // Let Cranelift check the stack pointer against the store's stack
+        // limit in the prologue, trapping with `StackOverflow` below it. The
+        // limit changes as calls into WebAssembly code start and end, so
+        // its load isn't readonly.
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(&mut builder.func);
+        let interrupts = builder.func.create_global_value(ir::GlobalValueData::Load {
+            base: vmctx,
+            offset: Offset32::new(i32::try_from(self.offsets.vmctx_interrupts()).unwrap()),
+            global_type: pointer_type,
+            readonly: true,
+        });
+        let stack_limit = builder.func.create_global_value(ir::GlobalValueData::Load {
+            base: interrupts,
+            offset: Offset32::new(i32::from(self.offsets.vminterrupts_stack_limit())),
+            global_type: pointer_type,
+            readonly: false,
+        });
+        builder.func.stack_limit = Some(stack_limit);
+
+        self.translate_interrupt_checks(builder);
+        Ok(())
+    }
+
+    fn translate_loop_header(&mut self, builder: &mut FunctionBuilder) -> WasmResult<()> {
+        self.translate_interrupt_checks(builder);
+        Ok(())
+    }
+
     fn make_table(&mut self, func: &mut ir::Function, index: TableIndex) -> WasmResult<ir::Table> {
         let pointer_type = self.pointer_type();
 
@@ -540,53 +1391,78 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_table_grow(
         &mut self,
-        mut _pos: cranelift_codegen::cursor::FuncCursor<'_>,
-        _table_index: TableIndex,
+        mut pos: cranelift_codegen::cursor::FuncCursor<'_>,
+        table_index: TableIndex,
         _table: ir::Table,
-        _delta: ir::Value,
-        _init_value: ir::Value,
+        delta: ir::Value,
+        init_value: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "the `table.grow` instruction is not supported yet".into(),
-        ))
+        let (func_sig, table_index_arg, func_idx) =
+            self.get_table_grow_func(&mut pos.func, table_index);
+        let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, init_value, delta, table_index_arg],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_table_get(
         &mut self,
-        _builder: &mut FunctionBuilder,
-        _table_index: TableIndex,
+        builder: &mut FunctionBuilder,
+        table_index: TableIndex,
         _table: ir::Table,
-        _index: ir::Value,
+        index: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "the `table.get` instruction is not supported yet".into(),
-        ))
+        let mut pos = builder.cursor();
+        let (func_sig, table_index_arg, func_idx) =
+            self.get_table_get_func(&mut pos.func, table_index);
+        let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst =
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, table_index_arg, index]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_table_set(
         &mut self,
-        _builder: &mut FunctionBuilder,
-        _table_index: TableIndex,
+        builder: &mut FunctionBuilder,
+        table_index: TableIndex,
         _table: ir::Table,
-        _value: ir::Value,
-        _index: ir::Value,
+        value: ir::Value,
+        index: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "the `table.set` instruction is not supported yet".into(),
-        ))
+        let mut pos = builder.cursor();
+        let (func_sig, table_index_arg, func_idx) =
+            self.get_table_set_func(&mut pos.func, table_index);
+        let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, table_index_arg, index, value]);
+        Ok(())
     }
 
     fn translate_table_fill(
         &mut self,
-        mut _pos: cranelift_codegen::cursor::FuncCursor<'_>,
-        _table_index: TableIndex,
-        _dst: ir::Value,
-        _val: ir::Value,
-        _len: ir::Value,
+        mut pos: cranelift_codegen::cursor::FuncCursor<'_>,
+        table_index: TableIndex,
+        dst: ir::Value,
+        val: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "the `table.fill` instruction is not supported yet".into(),
-        ))
+        let (func_sig, table_index_arg, func_idx) =
+            self.get_table_fill_func(&mut pos.func, table_index);
+        let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, table_index_arg, dst, val, len],
+        );
+        Ok(())
     }
 
     fn translate_ref_null(
@@ -626,12 +1502,16 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_ref_func(
         &mut self,
-        mut _pos: cranelift_codegen::cursor::FuncCursor<'_>,
-        _func_index: FunctionIndex,
+        mut pos: cranelift_codegen::cursor::FuncCursor<'_>,
+        func_index: FunctionIndex,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "the `ref.func` instruction is not supported yet".into(),
-        ))
+        let (func_sig, func_idx) = self.get_func_ref_func(&mut pos.func);
+        let func_index_arg = pos.ins().iconst(I32, func_index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, func_index_arg]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_custom_global_get(
@@ -653,6 +1533,11 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn make_heap(&mut self, func: &mut ir::Function, index: MemoryIndex) -> WasmResult<ir::Heap> {
         let pointer_type = self.pointer_type();
+        let index_type = if self.module.memories[index].memory64 {
+            I64
+        } else {
+            I32
+        };
 
         let (ptr, base_offset, current_length_offset) = {
             let vmctx = self.vmctx(func);
@@ -684,10 +1569,14 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         // allocated up front and never moved.
         let (offset_guard_size, heap_style, readonly_base) = match self.memory_styles[index] {
             MemoryStyle::Dynamic { offset_guard_size } => {
+                // The bound is compared against addresses of the index type.
+                // The length of a 32-bit memory always fits in the low half
+                // of the `current_length` field, which comes first on the
+                // little-endian targets we support.
                 let heap_bound = func.create_global_value(ir::GlobalValueData::Load {
                     base: ptr,
                     offset: Offset32::new(current_length_offset),
-                    global_type: type_of_vmmemory_definition_current_length(&self.offsets),
+                    global_type: index_type,
                     readonly: false,
                 });
                 (
@@ -721,7 +1610,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type,
         }))
     }
 
@@ -791,61 +1680,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         callee: ir::Value,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        let pointer_type = self.pointer_type();
-
-        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
-
-        // Dereference table_entry_addr to get the function address.
-        let mem_flags = ir::MemFlags::trusted();
-        let func_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
-        );
-
-        // Check whether `func_addr` is null.
-        pos.ins().trapz(func_addr, ir::TrapCode::IndirectCallToNull);
-
-        // If necessary, check the signature.
-        match self.table_styles[table_index] {
-            TableStyle::CallerChecksSignature => {
-                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
-                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
-                let vmctx = self.vmctx(pos.func);
-                let base = pos.ins().global_value(pointer_type, vmctx);
-                let offset =
-                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
-
-                // Load the caller ID.
-                let mut mem_flags = ir::MemFlags::trusted();
-                mem_flags.set_readonly();
-                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
-
-                // Load the callee ID.
-                let mem_flags = ir::MemFlags::trusted();
-                let callee_sig_id = pos.ins().load(
-                    sig_id_type,
-                    mem_flags,
-                    table_entry_addr,
-                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
-                );
-
-                // Check that they match.
-                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
-                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
-            }
-        }
+        let (func_addr, vmctx) =
+            self.load_indirect_callee(&mut pos, table_index, table, sig_index, callee);
 
         let mut real_call_args = Vec::with_capacity(call_args.len() + 2);
 
         // First append the callee vmctx address.
-        let vmctx = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
-        );
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -879,22 +1719,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
         // Handle direct calls to imported functions. We use an indirect call
         // so that we don't have to patch the code at runtime.
-        let pointer_type = self.pointer_type();
         let sig_ref = pos.func.dfg.ext_funcs[callee].signature;
-        let vmctx = self.vmctx(&mut pos.func);
-        let base = pos.ins().global_value(pointer_type, vmctx);
-
-        let mem_flags = ir::MemFlags::trusted();
-
-        // Load the callee address.
-        let body_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_body(callee_index)).unwrap();
-        let func_addr = pos.ins().load(pointer_type, mem_flags, base, body_offset);
+        let (func_addr, vmctx) = self.load_imported_callee(&mut pos, callee_index);
 
         // First append the callee vmctx address.
-        let vmctx_offset =
-            i32::try_from(self.offsets.vmctx_vmfunction_import_vmctx(callee_index)).unwrap();
-        let vmctx = pos.ins().load(pointer_type, mem_flags, base, vmctx_offset);
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -910,13 +1738,35 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         _heap: ir::Heap,
         val: ir::Value,
     ) -> WasmResult<ir::Value> {
+        let memory64 = self.module.memories[index].memory64;
         let (func_sig, index_arg, func_idx) = self.get_memory_grow_func(&mut pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
+        let val = if memory64 {
+            // Deltas that don't fit in 32 bits can never succeed, so they are
+            // clamped to a delta that is guaranteed to fail.
+            let too_large =
+                pos.ins()
+                    .icmp_imm(IntCC::UnsignedGreaterThan, val, i64::from(u32::MAX));
+            let val = pos.ins().ireduce(I32, val);
+            let max = pos.ins().iconst(I32, -1);
+            pos.ins().select(too_large, max, val)
+        } else {
+            val
+        };
         let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        Ok(if memory64 {
+            // A failed grow returns -1, which must stay -1 as an `i64`.
+            let failed = pos.ins().icmp_imm(IntCC::Equal, result, -1);
+            let result = pos.ins().uextend(I64, result);
+            let minus_one = pos.ins().iconst(I64, -1);
+            pos.ins().select(failed, minus_one, result)
+        } else {
+            result
+        })
     }
 
     fn translate_memory_size(
@@ -931,7 +1781,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        Ok(if self.module.memories[index].memory64 {
+            pos.ins().uextend(I64, result)
+        } else {
+            result
+        })
     }
 
     fn translate_memory_copy(
@@ -939,12 +1794,42 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         mut pos: FuncCursor,
         src_index: MemoryIndex,
         _src_heap: ir::Heap,
-        _dst_index: MemoryIndex,
+        dst_index: MemoryIndex,
         _dst_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        if src_index != dst_index {
+            let func_sig = self.get_memory_copy_between_sig(&mut pos.func);
+            let func_idx = VMBuiltinFunctionIndex::get_memory_copy_between_index();
+            let dst_index_arg = pos.ins().iconst(I32, dst_index.index() as i64);
+            let src_index_arg = pos.ins().iconst(I32, src_index.index() as i64);
+            // The memories may have different index types, so the builtin
+            // takes zero-extended 64-bit operands.
+            let widen = |pos: &mut FuncCursor, value: ir::Value| {
+                if pos.func.dfg.value_type(value) == I64 {
+                    value
+                } else {
+                    pos.ins().uextend(I64, value)
+                }
+            };
+            let dst = widen(&mut pos, dst);
+            let src = widen(&mut pos, src);
+            let len = widen(&mut pos, len);
+
+            let (vmctx, func_addr) =
+                self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+            pos.ins().call_indirect(
+                func_sig,
+                func_addr,
+                &[vmctx, dst_index_arg, src_index_arg, dst, src, len],
+            );
+
+            return Ok(());
+        }
+
         let (func_sig, src_index, func_idx) = self.get_memory_copy_func(&mut pos.func, src_index);
 
         let src_index_arg = pos.ins().iconst(I32, src_index as i64);
@@ -992,7 +1877,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
-        let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func);
+        let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func, memory_index);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
         let seg_index_arg = pos.ins().iconst(I32, seg_index as i64);
@@ -1019,13 +1904,18 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_table_size(
         &mut self,
-        mut _pos: FuncCursor,
-        _table_index: TableIndex,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
         _table: ir::Table,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "bulk memory: `table.size`".to_string(),
-        ))
+        let (func_sig, table_index_arg, func_idx) =
+            self.get_table_size_func(&mut pos.func, table_index);
+        let table_index_arg = pos.ins().iconst(I32, table_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, table_index_arg]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_table_copy(
@@ -1105,28 +1995,153 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
 
     fn translate_atomic_wait(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _expected: ir::Value,
-        _timeout: ir::Value,
+        addr: ir::Value,
+        expected: ir::Value,
+        timeout: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_wait)".to_string(),
-        ))
+        let ty = pos.func.dfg.value_type(expected);
+        let (func_sig, memory_index_arg, func_idx) =
+            self.get_memory_atomic_wait_func(&mut pos.func, index, ty);
+        let memory_index_arg = pos.ins().iconst(I32, memory_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos.ins().call_indirect(
+            func_sig,
+            func_addr,
+            &[vmctx, memory_index_arg, addr, expected, timeout],
+        );
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
     }
 
     fn translate_atomic_notify(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _addr: ir::Value,
-        _count: ir::Value,
+        addr: ir::Value,
+        count: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        let (func_sig, memory_index_arg, func_idx) =
+            self.get_memory_atomic_notify_func(&mut pos.func, index);
+        let memory_index_arg = pos.ins().iconst(I32, memory_index_arg as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst =
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, memory_index_arg, addr, count]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn tag_param_arity(&self, tag_index: TagIndex) -> usize {
+        self.module.signatures[self.module.tags[tag_index]]
+            .params()
+            .len()
+    }
+
+    fn translate_exception_new(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<ir::Value> {
+        if self
+            .module
+            .tag_type(tag_index)
+            .params()
+            .iter()
+            .any(|ty| ty.is_ref())
+        {
+            return Err(WasmError::Unsupported(
+                "exceptions carrying references are not supported".to_string(),
+            ));
+        }
+        let (_, payload) = self.make_values_buffer(&mut pos, args.len(), args);
+        let (func_sig, func_idx) = self.get_exception_new_func(&mut pos.func);
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst =
+            pos.ins()
+                .call_indirect(func_sig, func_addr, &[vmctx, tag_index_arg, payload]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_raise(&mut self, mut pos: FuncCursor, exn: ir::Value) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_exception_raise_func(&mut pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(func_sig, func_addr, &[exn]);
+        Ok(())
+    }
+
+    fn translate_exception_matches(
+        &mut self,
+        mut pos: FuncCursor,
+        exn: ir::Value,
+        tag_index: TagIndex,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "wasm atomics (fn translate_atomic_notify)".to_string(),
-        ))
+        let (func_sig, func_idx) = self.get_exception_matches_func(&mut pos.func);
+        let tag_index_arg = pos.ins().iconst(I32, tag_index.as_u32() as i64);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        let call_inst = pos
+            .ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, exn, tag_index_arg]);
+        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+    }
+
+    fn translate_exception_take_payload(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+        exn: ir::Value,
+    ) -> WasmResult<Vec<ir::Value>> {
+        let types = self.module.signatures[self.module.tags[tag_index]]
+            .params()
+            .iter()
+            .map(|ty| type_to_irtype(*ty, self.target_config))
+            .collect::<WasmResult<Vec<_>>>()?;
+        let (slot, values) = self.make_values_buffer(&mut pos, types.len(), &[]);
+        let (func_sig, func_idx) = self.get_exception_take_payload_func(&mut pos.func);
+        let (_vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(func_sig, func_addr, &[exn, values]);
+        Ok(types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| pos.ins().stack_load(*ty, slot, (i * 16) as i32))
+            .collect())
+    }
+
+    fn translate_invoke(
+        &mut self,
+        mut pos: FuncCursor,
+        callee_index: FunctionIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<(ir::Value, Vec<ir::Value>)> {
+        let (func_addr, vmctx) = if self.module.is_imported_function(callee_index) {
+            self.load_imported_callee(&mut pos, callee_index)
+        } else {
+            // Locally-defined functions share the caller vmctx.
+            let func_addr = pos.ins().func_addr(self.pointer_type(), callee);
+            let caller_vmctx = pos.func.special_param(ArgumentPurpose::VMContext).unwrap();
+            (func_addr, caller_vmctx)
+        };
+        let sig_index = self.module.functions[callee_index];
+        let sig_ref = pos.func.dfg.ext_funcs[callee].signature;
+        Ok(self.invoke(&mut pos, sig_index, sig_ref, func_addr, vmctx, call_args))
+    }
+
+    fn translate_invoke_indirect(
+        &mut self,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        sig_ref: ir::SigRef,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> WasmResult<(ir::Value, Vec<ir::Value>)> {
+        let (func_addr, vmctx) =
+            self.load_indirect_callee(&mut pos, table_index, table, sig_index, callee);
+        Ok(self.invoke(&mut pos, sig_index, sig_ref, func_addr, vmctx, call_args))
     }
 }
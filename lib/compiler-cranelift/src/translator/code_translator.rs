@@ -47,7 +47,7 @@ use std::vec::Vec;
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator};
 use wasmer_compiler::WasmResult;
 use wasmer_compiler::{wasm_unsupported, ModuleTranslationState};
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex};
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
 #[cfg_attr(
@@ -203,7 +203,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 .extend_from_slice(builder.block_params(loop_body));
 
             builder.switch_to_block(loop_body);
-            environ.translate_loop_header(builder.cursor())?;
+            environ.translate_loop_header(builder)?;
         }
         Operator::If { ty } => {
             let val = state.pop1();
@@ -329,6 +329,10 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 // below.
             }
 
+            // All the `catch` clauses of a `try` are known now, so we can
+            // dispatch the exceptions thrown by its body.
+            translate_landing_pad(&frame, builder, state, environ)?;
+
             builder.switch_to_block(next_block);
             builder.seal_block(next_block);
 
@@ -464,29 +468,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             }
             state.reachable = false;
         }
-        Operator::Return => {
-            let (return_count, br_destination) = {
-                let frame = &mut state.control_stack[0];
-                frame.set_branched_to_exit();
-                let return_count = frame.num_return_values();
-                (return_count, frame.br_destination())
-            };
-            {
-                let return_args = state.peekn_mut(return_count);
-                let return_types = wasm_param_types(&builder.func.signature.returns, |i| {
-                    environ.is_wasm_return(&builder.func.signature, i)
-                });
-                bitcast_arguments(return_args, &return_types, builder);
-                match environ.return_mode() {
-                    ReturnMode::NormalReturns => builder.ins().return_(return_args),
-                    ReturnMode::FallthroughReturn => {
-                        canonicalise_then_jump(builder, br_destination, return_args)
-                    }
-                };
-            }
-            state.popn(return_count);
-            state.reachable = false;
-        }
+        Operator::Return => translate_return(builder, state, environ),
         /************************************ Calls ****************************************
          * The call instructions pop off their arguments from the stack and append their
          * return values to it. `call_indirect` needs environment support because there is an
@@ -494,6 +476,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
          ************************************************************************************/
         Operator::Call { function_index } => {
             let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
+            let landing_pad = innermost_landing_pad(builder, state, environ);
 
             // Bitcast any vector arguments to their default type, I8X16, before calling.
             let callee_signature =
@@ -504,6 +487,19 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             });
             bitcast_arguments(args, &types, builder);
 
+            if let Some(landing_pad) = landing_pad {
+                let (exn, results) = environ.translate_invoke(
+                    builder.cursor(),
+                    FunctionIndex::from_u32(*function_index),
+                    fref,
+                    args,
+                )?;
+                translate_catch_exception(builder, exn, landing_pad);
+                state.popn(num_args);
+                state.pushn(&results);
+                return Ok(());
+            }
+
             let call = environ.translate_call(
                 builder.cursor(),
                 FunctionIndex::from_u32(*function_index),
@@ -527,6 +523,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let (sigref, num_args) = state.get_indirect_sig(builder.func, *index, environ)?;
             let table = state.get_or_create_table(builder.func, *table_index, environ)?;
             let callee = state.pop1();
+            let landing_pad = innermost_landing_pad(builder, state, environ);
 
             // Bitcast any vector arguments to their default type, I8X16, before calling.
             let callee_signature = &builder.func.dfg.signatures[sigref];
@@ -536,6 +533,22 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             });
             bitcast_arguments(args, &types, builder);
 
+            if let Some(landing_pad) = landing_pad {
+                let (exn, results) = environ.translate_invoke_indirect(
+                    builder.cursor(),
+                    TableIndex::from_u32(*table_index),
+                    table,
+                    SignatureIndex::from_u32(*index),
+                    sigref,
+                    callee,
+                    state.peekn(num_args),
+                )?;
+                translate_catch_exception(builder, exn, landing_pad);
+                state.popn(num_args);
+                state.pushn(&results);
+                return Ok(());
+            }
+
             let call = environ.translate_call_indirect(
                 builder.cursor(),
                 TableIndex::from_u32(*table_index),
@@ -990,6 +1003,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let timeout = state.pop1(); // 64 (fixed)
             let expected = state.pop1(); // 32 or 64 (per the `Ixx` in `IxxAtomicWait`)
             let addr = state.pop1(); // 32 (fixed)
+            let addr = effective_atomic_addr(addr, memarg, builder);
             assert!(builder.func.dfg.value_type(expected) == implied_ty);
             // `fn translate_atomic_wait` can inspect the type of `expected` to figure out what
             // code it needs to generate, if it wants.
@@ -1008,6 +1022,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
             let count = state.pop1(); // 32 (fixed)
             let addr = state.pop1(); // 32 (fixed)
+            let addr = effective_atomic_addr(addr, memarg, builder);
             let res =
                 environ.translate_atomic_notify(builder.cursor(), heap_index, heap, addr, count)?;
             state.push1(res);
@@ -1557,20 +1572,25 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let bool_result = builder.ins().vany_true(a);
             state.push1(builder.ins().bint(I32, bool_result))
         }
-        Operator::I8x16AllTrue | Operator::I16x8AllTrue | Operator::I32x4AllTrue => {
+        Operator::I8x16AllTrue
+        | Operator::I16x8AllTrue
+        | Operator::I32x4AllTrue
+        | Operator::I64x2AllTrue => {
             let a = pop1_with_bitcast(state, type_of(op), builder);
             let bool_result = builder.ins().vall_true(a);
             state.push1(builder.ins().bint(I32, bool_result))
         }
-        Operator::I8x16Bitmask | Operator::I16x8Bitmask | Operator::I32x4Bitmask => {
-            unimplemented!("SIMD Operator {:?} not yet implemented", op);
-            // let a = pop1_with_bitcast(state, type_of(op), builder);
-            // state.push1(builder.ins().vhigh_bits(I32, a));
+        Operator::I8x16Bitmask
+        | Operator::I16x8Bitmask
+        | Operator::I32x4Bitmask
+        | Operator::I64x2Bitmask => {
+            let a = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().vhigh_bits(I32, a));
         }
-        Operator::I8x16Eq | Operator::I16x8Eq | Operator::I32x4Eq => {
+        Operator::I8x16Eq | Operator::I16x8Eq | Operator::I32x4Eq | Operator::I64x2Eq => {
             translate_vector_icmp(IntCC::Equal, type_of(op), builder, state)
         }
-        Operator::I8x16Ne | Operator::I16x8Ne | Operator::I32x4Ne => {
+        Operator::I8x16Ne | Operator::I16x8Ne | Operator::I32x4Ne | Operator::I64x2Ne => {
             translate_vector_icmp(IntCC::NotEqual, type_of(op), builder, state)
         }
         Operator::I8x16GtS | Operator::I16x8GtS | Operator::I32x4GtS => {
@@ -1643,19 +1663,52 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.push1(builder.ins().fmin(a, b))
         }
         Operator::F32x4PMax | Operator::F64x2PMax => {
-            unimplemented!("SIMD Operator {:?} not yet implemented", op);
-            // let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
-            // state.push1(builder.ins().fmax_pseudo(a, b))
+            let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().fmax_pseudo(a, b))
         }
         Operator::F32x4PMin | Operator::F64x2PMin => {
-            unimplemented!("SIMD Operator {:?} not yet implemented", op);
-            // let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
-            // state.push1(builder.ins().fmin_pseudo(a, b))
+            let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().fmin_pseudo(a, b))
         }
-        Operator::I32x4DotI16x8S
-        | Operator::V128Load32Zero { .. }
-        | Operator::V128Load64Zero { .. } => {
-            unimplemented!("SIMD Operator {:?} not yet implemented", op);
+        Operator::I32x4DotI16x8S => {
+            let (a, b) = pop2_with_bitcast(state, I16X8, builder);
+            state.push1(builder.ins().widening_pairwise_dot_product_s(a, b));
+        }
+        Operator::V128Load32Zero { memarg } | Operator::V128Load64Zero { memarg } => {
+            translate_load(
+                memarg,
+                ir::Opcode::Load,
+                type_of(op).lane_type(),
+                builder,
+                state,
+                environ,
+            )?;
+            let as_vector = builder.ins().scalar_to_vector(type_of(op), state.pop1());
+            state.push1(as_vector)
+        }
+        Operator::V128Load8Lane { memarg, lane }
+        | Operator::V128Load16Lane { memarg, lane }
+        | Operator::V128Load32Lane { memarg, lane }
+        | Operator::V128Load64Lane { memarg, lane } => {
+            let vector = pop1_with_bitcast(state, type_of(op), builder);
+            translate_load(
+                memarg,
+                ir::Opcode::Load,
+                type_of(op).lane_type(),
+                builder,
+                state,
+                environ,
+            )?;
+            let replacement = state.pop1();
+            state.push1(builder.ins().insertlane(vector, replacement, *lane))
+        }
+        Operator::V128Store8Lane { memarg, lane }
+        | Operator::V128Store16Lane { memarg, lane }
+        | Operator::V128Store32Lane { memarg, lane }
+        | Operator::V128Store64Lane { memarg, lane } => {
+            let vector = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().extractlane(vector, *lane));
+            translate_store(memarg, ir::Opcode::Store, builder, state, environ)?;
         }
         Operator::F32x4Sqrt | Operator::F64x2Sqrt => {
             let a = pop1_with_bitcast(state, type_of(op), builder);
@@ -1733,6 +1786,141 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let a = pop1_with_bitcast(state, I16X8, builder);
             state.push1(builder.ins().uwiden_high(a))
         }
+        Operator::I64x2WidenLowI32x4S | Operator::I64x2WidenHighI32x4S => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            let first = if let Operator::I64x2WidenLowI32x4S = op {
+                0
+            } else {
+                2
+            };
+            state.push1(map_two_lanes(a, first, I64X2, builder, |builder, lane| {
+                builder.ins().sextend(I64, lane)
+            }))
+        }
+        Operator::I64x2WidenLowI32x4U | Operator::I64x2WidenHighI32x4U => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            let first = if let Operator::I64x2WidenLowI32x4U = op {
+                0
+            } else {
+                2
+            };
+            state.push1(map_two_lanes(a, first, I64X2, builder, |builder, lane| {
+                builder.ins().uextend(I64, lane)
+            }))
+        }
+        Operator::I16x8ExtMulLowI8x16S | Operator::I32x4ExtMulLowI16x8S => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op).split_lanes().unwrap(), builder);
+            let a = builder.ins().swiden_low(a);
+            let b = builder.ins().swiden_low(b);
+            state.push1(builder.ins().imul(a, b))
+        }
+        Operator::I16x8ExtMulHighI8x16S | Operator::I32x4ExtMulHighI16x8S => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op).split_lanes().unwrap(), builder);
+            let a = builder.ins().swiden_high(a);
+            let b = builder.ins().swiden_high(b);
+            state.push1(builder.ins().imul(a, b))
+        }
+        Operator::I16x8ExtMulLowI8x16U | Operator::I32x4ExtMulLowI16x8U => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op).split_lanes().unwrap(), builder);
+            let a = builder.ins().uwiden_low(a);
+            let b = builder.ins().uwiden_low(b);
+            state.push1(builder.ins().imul(a, b))
+        }
+        Operator::I16x8ExtMulHighI8x16U | Operator::I32x4ExtMulHighI16x8U => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op).split_lanes().unwrap(), builder);
+            let a = builder.ins().uwiden_high(a);
+            let b = builder.ins().uwiden_high(b);
+            state.push1(builder.ins().imul(a, b))
+        }
+        Operator::I64x2ExtMulLowI32x4S
+        | Operator::I64x2ExtMulHighI32x4S
+        | Operator::I64x2ExtMulLowI32x4U
+        | Operator::I64x2ExtMulHighI32x4U => {
+            let (a, b) = pop2_with_bitcast(state, I32X4, builder);
+            let (first, signed) = match op {
+                Operator::I64x2ExtMulLowI32x4S => (0, true),
+                Operator::I64x2ExtMulHighI32x4S => (2, true),
+                Operator::I64x2ExtMulLowI32x4U => (0, false),
+                _ => (2, false),
+            };
+            // Cranelift has no 64-bit lane multiplication on every target, so the
+            // products are computed lane by lane.
+            let zero = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+            let mut result = builder.ins().vconst(I64X2, zero);
+            for i in 0..2 {
+                let lane_a = builder.ins().extractlane(a, first + i);
+                let lane_b = builder.ins().extractlane(b, first + i);
+                let (lane_a, lane_b) = if signed {
+                    (
+                        builder.ins().sextend(I64, lane_a),
+                        builder.ins().sextend(I64, lane_b),
+                    )
+                } else {
+                    (
+                        builder.ins().uextend(I64, lane_a),
+                        builder.ins().uextend(I64, lane_b),
+                    )
+                };
+                let product = builder.ins().imul(lane_a, lane_b);
+                result = builder.ins().insertlane(result, product, i);
+            }
+            state.push1(result)
+        }
+        Operator::I16x8Q15MulrSatS => {
+            let (a, b) = pop2_with_bitcast(state, I16X8, builder);
+            // Compute `(a * b + 0x4000) >> 15` on 32-bit lanes, and saturate the
+            // results back to 16 bits.
+            let rounding = builder.ins().iconst(I32, 0x4000);
+            let rounding = builder.ins().splat(I32X4, rounding);
+            let shift = builder.ins().iconst(I32, 15);
+            let a_low = builder.ins().swiden_low(a);
+            let b_low = builder.ins().swiden_low(b);
+            let low = builder.ins().imul(a_low, b_low);
+            let low = builder.ins().iadd(low, rounding);
+            let low = builder.ins().sshr(low, shift);
+            let a_high = builder.ins().swiden_high(a);
+            let b_high = builder.ins().swiden_high(b);
+            let high = builder.ins().imul(a_high, b_high);
+            let high = builder.ins().iadd(high, rounding);
+            let high = builder.ins().sshr(high, shift);
+            state.push1(builder.ins().snarrow(low, high))
+        }
+        Operator::F32x4DemoteF64x2Zero => {
+            let a = pop1_with_bitcast(state, F64X2, builder);
+            state.push1(map_two_lanes(a, 0, F32X4, builder, |builder, lane| {
+                builder.ins().fdemote(F32, lane)
+            }))
+        }
+        Operator::F64x2PromoteLowF32x4 => {
+            let a = pop1_with_bitcast(state, F32X4, builder);
+            state.push1(map_two_lanes(a, 0, F64X2, builder, |builder, lane| {
+                builder.ins().fpromote(F64, lane)
+            }))
+        }
+        Operator::F64x2ConvertLowI32x4S => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            state.push1(map_two_lanes(a, 0, F64X2, builder, |builder, lane| {
+                builder.ins().fcvt_from_sint(F64, lane)
+            }))
+        }
+        Operator::F64x2ConvertLowI32x4U => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            state.push1(map_two_lanes(a, 0, F64X2, builder, |builder, lane| {
+                builder.ins().fcvt_from_uint(F64, lane)
+            }))
+        }
+        Operator::I32x4TruncSatF64x2SZero => {
+            let a = pop1_with_bitcast(state, F64X2, builder);
+            state.push1(map_two_lanes(a, 0, I32X4, builder, |builder, lane| {
+                builder.ins().fcvt_to_sint_sat(I32, lane)
+            }))
+        }
+        Operator::I32x4TruncSatF64x2UZero => {
+            let a = pop1_with_bitcast(state, F64X2, builder);
+            state.push1(map_two_lanes(a, 0, I32X4, builder, |builder, lane| {
+                builder.ins().fcvt_to_uint_sat(I32, lane)
+            }))
+        }
 
         Operator::F32x4Ceil | Operator::F64x2Ceil => {
             // This is something of a misuse of `type_of`, because that produces the return type
@@ -1754,53 +1942,93 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.push1(builder.ins().nearest(arg));
         }
 
-        Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
-            return Err(wasm_unsupported!("proposed tail-call operator {:?}", op));
+        /******************************* Tail calls ***************************************
+         * Cranelift has no tail call instruction, so only `return_call`s to the function
+         * itself are supported: they reinitialize its locals and jump back to the start of its
+         * body. Other tail calls couldn't run in constant stack space, and are rejected.
+         ************************************************************************************/
+        Operator::ReturnCall { function_index } => {
+            let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
+            let body_block = match state.body_block {
+                Some(body_block) if builder.func.dfg.ext_funcs[fref].name == builder.func.name => {
+                    body_block
+                }
+                _ => {
+                    return Err(wasm_unsupported!(
+                        "tail call to function {} from another function",
+                        function_index
+                    ))
+                }
+            };
+
+            // Bitcast any vector arguments to their default type, I8X16, before defining
+            // the parameters with them.
+            let callee_signature =
+                &builder.func.dfg.signatures[builder.func.dfg.ext_funcs[fref].signature];
+            let args = state.peekn_mut(num_args);
+            let types = wasm_param_types(&callee_signature.params, |i| {
+                environ.is_wasm_parameter(&callee_signature, i)
+            });
+            bitcast_arguments(args, &types, builder);
+
+            for (local, arg) in state.peekn(num_args).iter().enumerate() {
+                builder.def_var(Variable::with_u32(local as u32), *arg);
+            }
+            for (local, init) in state.local_inits.iter().enumerate() {
+                builder.def_var(Variable::with_u32((num_args + local) as u32), *init);
+            }
+            builder.ins().jump(body_block, &[]);
+            state.popn(num_args);
+            state.reachable = false;
+        }
+        Operator::ReturnCallIndirect { .. } => {
+            return Err(wasm_unsupported!("indirect tail call"));
         }
 
-        Operator::I64x2Eq
-        | Operator::I64x2Ne
-        | Operator::I64x2AllTrue
-        | Operator::I64x2Bitmask
-        | Operator::I64x2WidenLowI32x4S
-        | Operator::I64x2WidenHighI32x4S
-        | Operator::I64x2WidenLowI32x4U
-        | Operator::I64x2WidenHighI32x4U
-        | Operator::I16x8ExtMulLowI8x16S
-        | Operator::I16x8ExtMulHighI8x16S
-        | Operator::I16x8ExtMulLowI8x16U
-        | Operator::I16x8ExtMulHighI8x16U
-        | Operator::I32x4ExtMulLowI16x8S
-        | Operator::I32x4ExtMulHighI16x8S
-        | Operator::I32x4ExtMulLowI16x8U
-        | Operator::I32x4ExtMulHighI16x8U
-        | Operator::I64x2ExtMulLowI32x4S
-        | Operator::I64x2ExtMulHighI32x4S
-        | Operator::I64x2ExtMulLowI32x4U
-        | Operator::I64x2ExtMulHighI32x4U
-        | Operator::V128Load8Lane { .. }
-        | Operator::V128Load16Lane { .. }
-        | Operator::V128Load32Lane { .. }
-        | Operator::V128Load64Lane { .. }
-        | Operator::V128Store8Lane { .. }
-        | Operator::V128Store16Lane { .. }
-        | Operator::V128Store32Lane { .. }
-        | Operator::V128Store64Lane { .. }
-        | Operator::I16x8Q15MulrSatS
-        | Operator::F32x4DemoteF64x2Zero
-        | Operator::F64x2PromoteLowF32x4
-        | Operator::F64x2ConvertLowI32x4S
-        | Operator::F64x2ConvertLowI32x4U
-        | Operator::I32x4TruncSatF64x2SZero
-        | Operator::I32x4TruncSatF64x2UZero => {
-            return Err(wasm_unsupported!("updated proposed simd operator {:?}", op));
-        }
-
-        Operator::Try { .. }
-        | Operator::Catch { .. }
-        | Operator::Throw { .. }
-        | Operator::Rethrow { .. }
-        | Operator::Unwind => {
+        /******************************* Exception handling ***********************************
+         * Exceptions are thrown by unwinding the stack up to the closest `invoke` builtin
+         * call, made instead of a regular call from the body of a `try` block. Within a
+         * function, thrown exceptions are passed directly to the landing pad of the innermost
+         * `try` block, which dispatches them to its `catch` clauses or throws them further.
+         ************************************************************************************/
+        Operator::Try { ty } => {
+            let (params, results) = module_translation_state.blocktype_params_results(*ty)?;
+            let next = block_with_params(builder, results, environ)?;
+            state.push_try(next, params.len(), results.len());
+        }
+        Operator::Catch { index } => {
+            let i = state.control_stack.len() - 1;
+            let (destination, return_count) = {
+                let frame = &mut state.control_stack[i];
+                frame.set_branched_to_exit();
+                (frame.following_code(), frame.num_return_values())
+            };
+            canonicalise_then_jump(builder, destination, state.peekn(return_count));
+            translate_catch(TagIndex::from_u32(*index), builder, state, environ)?;
+        }
+        Operator::Throw { index } => {
+            let tag_index = TagIndex::from_u32(*index);
+            let arity = environ.tag_param_arity(tag_index);
+            let exn =
+                environ.translate_exception_new(builder.cursor(), tag_index, state.peekn(arity))?;
+            state.popn(arity);
+            translate_throw(exn, builder, state, environ)?;
+            state.reachable = false;
+        }
+        Operator::Rethrow { relative_depth } => {
+            let i = state.control_stack.len() - 1 - (*relative_depth as usize);
+            let (tag_index, payload) = match state.control_stack[i] {
+                ControlStackFrame::Try {
+                    caught: Some((tag_index, ref payload)),
+                    ..
+                } => (tag_index, payload.clone()),
+                _ => unreachable!("rethrow must target a catch clause"),
+            };
+            let exn = environ.translate_exception_new(builder.cursor(), tag_index, &payload)?;
+            translate_throw(exn, builder, state, environ)?;
+            state.reachable = false;
+        }
+        Operator::Unwind => {
             return Err(wasm_unsupported!("proposed exception operator {:?}", op));
         }
     };
@@ -1837,6 +2065,14 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
         Operator::Loop { ty: _ } | Operator::Block { ty: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
+        Operator::Try { ty: _ } => {
+            // The body of the `try` can't throw, so its `catch` clauses are
+            // unreachable too.
+            state.push_try(ir::Block::reserved_value(), 0, 0);
+        }
+        Operator::Catch { index } => {
+            translate_catch(TagIndex::from_u32(index), builder, state, environ)?;
+        }
         Operator::Else => {
             let i = state.control_stack.len() - 1;
             match state.control_stack[i] {
@@ -1886,12 +2122,12 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
             }
         }
         Operator::End => {
-            let stack = &mut state.stack;
-            let control_stack = &mut state.control_stack;
-            let frame = control_stack.pop().unwrap();
+            let frame = state.control_stack.pop().unwrap();
 
             // Pop unused parameters from stack.
-            frame.truncate_value_stack_to_original_size(stack);
+            frame.truncate_value_stack_to_original_size(&mut state.stack);
+
+            translate_landing_pad(&frame, builder, state, environ)?;
 
             let reachable_anyway = match frame {
                 // If it is a loop we also have to seal the body loop block
@@ -1928,7 +2164,9 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
 
                 // And add the return values of the block but only if the next block is reachable
                 // (which corresponds to testing if the stack depth is 1)
-                stack.extend_from_slice(builder.block_params(frame.following_code()));
+                state
+                    .stack
+                    .extend_from_slice(builder.block_params(frame.following_code()));
                 state.reachable = true;
             }
         }
@@ -1940,6 +2178,169 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Returns the values on top of the stack from the function.
+fn translate_return<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) {
+    let (return_count, br_destination) = {
+        let frame = &mut state.control_stack[0];
+        frame.set_branched_to_exit();
+        let return_count = frame.num_return_values();
+        (return_count, frame.br_destination())
+    };
+    {
+        let return_args = state.peekn_mut(return_count);
+        let return_types = wasm_param_types(&builder.func.signature.returns, |i| {
+            environ.is_wasm_return(&builder.func.signature, i)
+        });
+        bitcast_arguments(return_args, &return_types, builder);
+        match environ.return_mode() {
+            ReturnMode::NormalReturns => builder.ins().return_(return_args),
+            ReturnMode::FallthroughReturn => {
+                canonicalise_then_jump(builder, br_destination, return_args)
+            }
+        };
+    }
+    state.popn(return_count);
+    state.reachable = false;
+}
+
+/// Returns the landing pad of the innermost `try` block whose body is being translated, if any,
+/// creating it if needed.
+fn innermost_landing_pad<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &FE,
+) -> Option<ir::Block> {
+    let frame = state
+        .control_stack
+        .iter_mut()
+        .rev()
+        .find(|frame| matches!(frame, ControlStackFrame::Try { in_body: true, .. }))?;
+    match frame {
+        ControlStackFrame::Try { landing_pad, .. } => Some(*landing_pad.get_or_insert_with(|| {
+            let block = builder.create_block();
+            builder.append_block_param(block, environ.pointer_type());
+            block
+        })),
+        _ => unreachable!(),
+    }
+}
+
+/// Branches to `landing_pad` if the exception `exn` returned by an `invoke` isn't null, and
+/// continues in a new block otherwise.
+fn translate_catch_exception(builder: &mut FunctionBuilder, exn: Value, landing_pad: ir::Block) {
+    builder.ins().brnz(exn, landing_pad, &[exn]);
+    let next_block = builder.create_block();
+    builder.ins().jump(next_block, &[]);
+    builder.seal_block(next_block); // Only predecessor is the current block.
+    builder.switch_to_block(next_block);
+}
+
+/// Throws the exception `exn`, either to the innermost `try` block of the function or to the
+/// caller.
+fn translate_throw<FE: FuncEnvironment + ?Sized>(
+    exn: Value,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    match innermost_landing_pad(builder, state, environ) {
+        Some(landing_pad) => {
+            builder.ins().jump(landing_pad, &[exn]);
+        }
+        None => {
+            environ.translate_exception_raise(builder.cursor(), exn)?;
+            builder.ins().trap(ir::TrapCode::UnreachableCodeReached);
+        }
+    }
+    Ok(())
+}
+
+/// Starts translating the `catch` clause of the `try` block on top of the control stack, catching
+/// the exceptions thrown with the tag `tag_index`.
+fn translate_catch<FE: FuncEnvironment + ?Sized>(
+    tag_index: TagIndex,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let frame = state.control_stack.last_mut().unwrap();
+    frame.truncate_value_stack_to_original_size(&mut state.stack);
+    match frame {
+        ControlStackFrame::Try {
+            in_body,
+            landing_pad,
+            catches,
+            caught,
+            ..
+        } => {
+            *in_body = false;
+            match landing_pad {
+                // Nothing in the body of the `try` throws.
+                None => {
+                    *caught = None;
+                    state.reachable = false;
+                }
+                Some(_) => {
+                    // The catch block is sealed once its landing pad is
+                    // filled, at the end of the `try`.
+                    let catch_block = builder.create_block();
+                    builder.append_block_param(catch_block, environ.pointer_type());
+                    catches.push((tag_index, catch_block));
+                    builder.switch_to_block(catch_block);
+
+                    let exn = builder.block_params(catch_block)[0];
+                    let payload = environ.translate_exception_take_payload(
+                        builder.cursor(),
+                        tag_index,
+                        exn,
+                    )?;
+                    state.stack.extend_from_slice(&payload);
+                    *caught = Some((tag_index, payload));
+                    state.reachable = true;
+                }
+            }
+        }
+        _ => unreachable!("catch must follow a try"),
+    }
+    Ok(())
+}
+
+/// Fills the landing pad of the `try` block `frame`, which has just ended, dispatching the
+/// exceptions thrown by its body to its `catch` clauses, or throwing them further.
+fn translate_landing_pad<FE: FuncEnvironment + ?Sized>(
+    frame: &ControlStackFrame,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let (landing_pad, catches) = match frame {
+        ControlStackFrame::Try {
+            landing_pad: Some(landing_pad),
+            catches,
+            ..
+        } => (*landing_pad, catches),
+        _ => return Ok(()),
+    };
+
+    builder.switch_to_block(landing_pad);
+    builder.seal_block(landing_pad);
+    let exn = builder.block_params(landing_pad)[0];
+    for (tag_index, catch_block) in catches {
+        let matches = environ.translate_exception_matches(builder.cursor(), exn, *tag_index)?;
+        builder.ins().brnz(matches, *catch_block, &[exn]);
+        let next_block = builder.create_block();
+        builder.ins().jump(next_block, &[]);
+        builder.seal_block(next_block); // Only predecessor is the current block.
+        builder.switch_to_block(next_block);
+        builder.seal_block(*catch_block);
+    }
+    translate_throw(exn, builder, state, environ)
+}
+
 /// Get the address+offset to use for a heap access.
 fn get_heap_addr(
     heap: ir::Heap,
@@ -2115,6 +2516,32 @@ fn translate_icmp(cc: IntCC, builder: &mut FunctionBuilder, state: &mut FuncTran
     state.push1(builder.ins().bint(I32, val));
 }
 
+/// Computes the 64-bit effective address of a `memory.atomic.wait32`,
+/// `memory.atomic.wait64` or `memory.atomic.notify`, whose bounds and alignment
+/// are checked by the runtime.
+fn effective_atomic_addr(
+    linear_mem_addr: Value,
+    memarg: &MemoryImmediate,
+    builder: &mut FunctionBuilder,
+) -> Value {
+    if builder.func.dfg.value_type(linear_mem_addr) == I64 {
+        // Addresses into 64-bit memories can overflow when adding the offset.
+        let addr = builder
+            .ins()
+            .iadd_imm(linear_mem_addr, i64::from(memarg.offset));
+        let overflow = builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, addr, linear_mem_addr);
+        builder
+            .ins()
+            .trapnz(overflow, ir::TrapCode::HeapOutOfBounds);
+        addr
+    } else {
+        let addr = builder.ins().uextend(I64, linear_mem_addr);
+        builder.ins().iadd_imm(addr, i64::from(memarg.offset))
+    }
+}
+
 // For an atomic memory operation, emit an alignment check for the linear memory address,
 // and then compute the final effective address.
 fn finalise_atomic_mem_addr<FE: FuncEnvironment + ?Sized>(
@@ -2456,7 +2883,9 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I8x16MaxS
         | Operator::I8x16MaxU
         | Operator::I8x16RoundingAverageU
-        | Operator::I8x16Bitmask => I8X16,
+        | Operator::I8x16Bitmask
+        | Operator::V128Load8Lane { .. }
+        | Operator::V128Store8Lane { .. } => I8X16,
 
         Operator::I16x8Splat
         | Operator::V128Load16Splat { .. }
@@ -2491,7 +2920,13 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I16x8MaxU
         | Operator::I16x8RoundingAverageU
         | Operator::I16x8Mul
-        | Operator::I16x8Bitmask => I16X8,
+        | Operator::I16x8Bitmask
+        | Operator::I16x8ExtMulLowI8x16S
+        | Operator::I16x8ExtMulHighI8x16S
+        | Operator::I16x8ExtMulLowI8x16U
+        | Operator::I16x8ExtMulHighI8x16U
+        | Operator::V128Load16Lane { .. }
+        | Operator::V128Store16Lane { .. } => I16X8,
 
         Operator::I32x4Splat
         | Operator::V128Load32Splat { .. }
@@ -2522,7 +2957,14 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I32x4MaxU
         | Operator::F32x4ConvertI32x4S
         | Operator::F32x4ConvertI32x4U
-        | Operator::I32x4Bitmask => I32X4,
+        | Operator::I32x4Bitmask
+        | Operator::I32x4ExtMulLowI16x8S
+        | Operator::I32x4ExtMulHighI16x8S
+        | Operator::I32x4ExtMulLowI16x8U
+        | Operator::I32x4ExtMulHighI16x8U
+        | Operator::V128Load32Zero { .. }
+        | Operator::V128Load32Lane { .. }
+        | Operator::V128Store32Lane { .. } => I32X4,
 
         Operator::I64x2Splat
         | Operator::V128Load64Splat { .. }
@@ -2534,7 +2976,14 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I64x2ShrU
         | Operator::I64x2Add
         | Operator::I64x2Sub
-        | Operator::I64x2Mul => I64X2,
+        | Operator::I64x2Mul
+        | Operator::I64x2Eq
+        | Operator::I64x2Ne
+        | Operator::I64x2AllTrue
+        | Operator::I64x2Bitmask
+        | Operator::V128Load64Zero { .. }
+        | Operator::V128Load64Lane { .. }
+        | Operator::V128Store64Lane { .. } => I64X2,
 
         Operator::F32x4Splat
         | Operator::F32x4ExtractLane { .. }
@@ -2695,6 +3144,25 @@ fn pop1_with_bitcast(
     optionally_bitcast_vector(state.pop1(), needed_type, builder)
 }
 
+/// Applies `f` to the two lanes of `vector` starting at lane `first`, and builds a vector of
+/// type `result_ty` out of the results; the other lanes of the result are zeroed.
+fn map_two_lanes(
+    vector: Value,
+    first: u8,
+    result_ty: Type,
+    builder: &mut FunctionBuilder,
+    f: impl Fn(&mut FunctionBuilder, Value) -> Value,
+) -> Value {
+    let zero = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+    let mut result = builder.ins().vconst(result_ty, zero);
+    for i in 0..2 {
+        let lane = builder.ins().extractlane(vector, first + i);
+        let mapped = f(builder, lane);
+        result = builder.ins().insertlane(result, mapped, i);
+    }
+    result
+}
+
 /// A helper for popping and bitcasting two values; since SIMD values can lose their type by
 /// using v128 (i.e. CLIF's I8x16) we must re-type the values using a bitcast to avoid CLIF
 /// typing issues.
@@ -14,7 +14,7 @@ use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::FunctionBuilder;
 use wasmer_compiler::wasmparser::{Operator, Type};
 use wasmer_compiler::WasmResult;
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex};
 
 /// The value of a WebAssembly global variable.
 #[derive(Clone, Copy)]
@@ -410,6 +410,8 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// to wait on, and `heap` is the heap reference returned by `make_heap`
     /// for the same index.  Whether the waited-on value is 32- or 64-bit can be
     /// determined by examining the type of `expected`, which must be only I32 or I64.
+    /// `addr` is the 64-bit effective address of the value, which hasn't been
+    /// bounds-checked yet.
     ///
     /// Returns an i32, which is negative if the helper call failed.
     fn translate_atomic_wait(
@@ -426,6 +428,8 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// The `index` provided identifies the linear memory containing the value
     /// to wait on, and `heap` is the heap reference returned by `make_heap`
     /// for the same index.
+    /// `addr` is the 64-bit effective address of the value, which hasn't been
+    /// bounds-checked yet.
     ///
     /// Returns an i64, which is negative if the helper call failed.
     fn translate_atomic_notify(
@@ -437,15 +441,98 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Returns the number of values carried by the exceptions thrown with
+    /// the tag `tag_index`.
+    fn tag_param_arity(&self, tag_index: TagIndex) -> usize;
+
+    /// Translate a `throw` WebAssembly instruction: create the exception
+    /// thrown with the tag `tag_index`, carrying `args`.
+    ///
+    /// Returns a pointer-sized handle to the exception, which must then be
+    /// either raised or caught.
+    fn translate_exception_new(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<ir::Value>;
+
+    /// Unwind the stack with the exception `exn`, which isn't caught by the
+    /// function being translated.
+    ///
+    /// The instructions inserted at `pos` don't return.
+    fn translate_exception_raise(&mut self, pos: FuncCursor, exn: ir::Value) -> WasmResult<()>;
+
+    /// Check whether the exception `exn` was thrown with the tag `tag_index`.
+    ///
+    /// Returns an i32, which is non-zero if it was.
+    fn translate_exception_matches(
+        &mut self,
+        pos: FuncCursor,
+        exn: ir::Value,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value>;
+
+    /// Free the caught exception `exn`, thrown with the tag `tag_index`.
+    ///
+    /// Returns the values carried by the exception.
+    fn translate_exception_take_payload(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        exn: ir::Value,
+    ) -> WasmResult<Vec<ir::Value>>;
+
+    /// Translate a `call` WebAssembly instruction at `pos` from the body of
+    /// a `try` block, so that the exception thrown by the callee is caught.
+    ///
+    /// Returns the handle to the exception, which is null if the call
+    /// returned normally, and the results of the call, which are meaningless
+    /// otherwise.
+    fn translate_invoke(
+        &mut self,
+        pos: FuncCursor,
+        callee_index: FunctionIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<(ir::Value, Vec<ir::Value>)>;
+
+    /// Translate a `call_indirect` WebAssembly instruction at `pos` from the
+    /// body of a `try` block, so that the exception thrown by the callee is
+    /// caught.
+    ///
+    /// Returns the same values as `translate_invoke`.
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+    fn translate_invoke_indirect(
+        &mut self,
+        pos: FuncCursor,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        sig_ref: ir::SigRef,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> WasmResult<(ir::Value, Vec<ir::Value>)>;
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
     /// the beginnings of loops.
-    fn translate_loop_header(&mut self, _pos: FuncCursor) -> WasmResult<()> {
+    fn translate_loop_header(&mut self, _builder: &mut FunctionBuilder) -> WasmResult<()> {
         // By default, don't emit anything.
         Ok(())
     }
 
+    /// Optional callback for the `FunctionEnvironment` performing this translation to emit code
+    /// at the beginning of the function body, after its locals are initialized.
+    fn before_translate_function(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     /// Optional callback for the `FunctionEnvironment` performing this translation to maintain
     /// internal state or prepare custom state for the operator to translate
     fn before_translate_operator(
@@ -14,7 +14,7 @@ use crate::{HashMap, Occupied, Vacant};
 use cranelift_codegen::ir::{self, Block, Inst, Value};
 use std::vec::Vec;
 use wasmer_compiler::WasmResult;
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex};
 
 /// Information about the presence of an associated `else` for an `if`, or the
 /// lack thereof.
@@ -43,8 +43,8 @@ pub enum ElseData {
     },
 }
 
-/// A control stack frame can be an `if`, a `block` or a `loop`, each one having the following
-/// fields:
+/// A control stack frame can be an `if`, a `block`, a `loop` or a `try`, each one having the
+/// following fields:
 ///
 /// - `destination`: reference to the `Block` that will hold the code after the control block;
 /// - `num_return_values`: number of values returned by the control block;
@@ -52,7 +52,8 @@ pub enum ElseData {
 ///
 /// Moreover, the `if` frame has the `branch_inst` field that points to the `brz` instruction
 /// separating the `true` and `false` branch. The `loop` frame has a `header` field that references
-/// the `Block` that contains the beginning of the body of the loop.
+/// the `Block` that contains the beginning of the body of the loop. The `try` frame keeps track
+/// of the `Block` receiving the exceptions thrown by its body, and of its `catch` clauses.
 #[derive(Debug)]
 pub enum ControlStackFrame {
     If {
@@ -89,6 +90,27 @@ pub enum ControlStackFrame {
         num_return_values: usize,
         original_stack_size: usize,
     },
+    Try {
+        destination: Block,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+        /// Are we still translating the body of the `try`, as opposed to one of its `catch`
+        /// clauses?
+        in_body: bool,
+        /// The block receiving the exceptions thrown by the body of the `try`, with the
+        /// exception as parameter.
+        ///
+        /// It is only created when something in the body may throw: if it is still `None` when
+        /// we are done with the body, the `catch` clauses are unreachable.
+        landing_pad: Option<Block>,
+        /// The `catch` clauses seen so far, with the tag they catch and the block handling them.
+        catches: Vec<(TagIndex, Block)>,
+        /// The tag and the values of the exception caught by the current `catch` clause, so
+        /// that `rethrow` can throw it again.
+        caught: Option<(TagIndex, Vec<Value>)>,
+    },
 }
 
 /// Helper methods for the control stack objects.
@@ -103,6 +125,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_return_values, ..
+            }
+            | Self::Try {
+                num_return_values, ..
             } => num_return_values,
         }
     }
@@ -116,6 +141,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_param_values, ..
+            }
+            | Self::Try {
+                num_param_values, ..
             } => num_param_values,
         }
     }
@@ -123,12 +151,15 @@ impl ControlStackFrame {
         match *self {
             Self::If { destination, .. }
             | Self::Block { destination, .. }
-            | Self::Loop { destination, .. } => destination,
+            | Self::Loop { destination, .. }
+            | Self::Try { destination, .. } => destination,
         }
     }
     pub fn br_destination(&self) -> Block {
         match *self {
-            Self::If { destination, .. } | Self::Block { destination, .. } => destination,
+            Self::If { destination, .. }
+            | Self::Block { destination, .. }
+            | Self::Try { destination, .. } => destination,
             Self::Loop { header, .. } => header,
         }
     }
@@ -147,12 +178,16 @@ impl ControlStackFrame {
             | Self::Loop {
                 original_stack_size,
                 ..
+            }
+            | Self::Try {
+                original_stack_size,
+                ..
             } => original_stack_size,
         }
     }
     pub fn is_loop(&self) -> bool {
         match *self {
-            Self::If { .. } | Self::Block { .. } => false,
+            Self::If { .. } | Self::Block { .. } | Self::Try { .. } => false,
             Self::Loop { .. } => true,
         }
     }
@@ -166,6 +201,10 @@ impl ControlStackFrame {
             | Self::Block {
                 exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                exit_is_branched_to,
+                ..
             } => exit_is_branched_to,
             Self::Loop { .. } => false,
         }
@@ -180,6 +219,10 @@ impl ControlStackFrame {
             | Self::Block {
                 ref mut exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                ref mut exit_is_branched_to,
+                ..
             } => *exit_is_branched_to = true,
             Self::Loop { .. } => {}
         }
@@ -246,6 +289,13 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FunctionIndex, (ir::FuncRef, usize)>,
+
+    /// The block starting the function body, once the locals are initialized.
+    /// `return_call`s to the function itself jump back to it.
+    pub(crate) body_block: Option<Block>,
+
+    /// The initial values of the locals that aren't parameters.
+    pub(crate) local_inits: Vec<Value>,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -270,6 +320,8 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            body_block: None,
+            local_inits: Vec::new(),
         }
     }
 
@@ -282,6 +334,8 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.body_block = None;
+        self.local_inits.clear();
     }
 
     /// Initialize the state for compiling a function with the given signature.
@@ -409,6 +463,27 @@ impl FuncTranslationState {
         });
     }
 
+    /// Push a try on the control stack.
+    pub(crate) fn push_try(
+        &mut self,
+        following_code: Block,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::Try {
+            destination: following_code,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+            in_body: true,
+            landing_pad: None,
+            catches: Vec::new(),
+            caught: None,
+        });
+    }
+
     /// Push an if on the control stack.
     pub(crate) fn push_if(
         &mut self,
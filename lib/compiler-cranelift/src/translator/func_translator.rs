@@ -118,7 +118,19 @@ impl FuncTranslator {
         builder.append_block_params_for_function_returns(exit_block);
         self.state.initialize(&builder.func.signature, exit_block);
 
-        parse_local_decls(&mut reader, &mut builder, num_params, environ)?;
+        let num_locals = parse_local_decls(&mut reader, &mut builder, num_params, environ)?;
+
+        // `return_call`s to the function itself reinitialize the locals and jump
+        // back to the start of the body, so that they run in constant stack space.
+        self.state.local_inits = (num_params..num_locals)
+            .map(|local| builder.use_var(Variable::new(local)))
+            .collect();
+        let body_block = builder.create_block();
+        builder.ins().jump(body_block, &[]);
+        builder.switch_to_block(body_block);
+        self.state.body_block = Some(body_block);
+        environ.before_translate_function(&mut builder, &self.state)?;
+
         parse_function_body(
             module_translation_state,
             reader,
@@ -127,6 +139,7 @@ impl FuncTranslator {
             environ,
         )?;
 
+        builder.seal_block(body_block);
         builder.finalize();
         Ok(())
     }
@@ -166,13 +179,14 @@ fn declare_wasm_parameters<FE: FuncEnvironment + ?Sized>(
 
 /// Parse the local variable declarations that precede the function body.
 ///
-/// Declare local variables, starting from `num_params`.
+/// Declare local variables, starting from `num_params`, and return the total
+/// number of local variables.
 fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
     reader: &mut MiddlewareBinaryReader,
     builder: &mut FunctionBuilder,
     num_params: usize,
     environ: &mut FE,
-) -> WasmResult<()> {
+) -> WasmResult<usize> {
     let mut next_local = num_params;
     let local_count = reader.read_local_count()?;
 
@@ -182,7 +196,7 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
         declare_locals(builder, count, ty, &mut next_local, environ)?;
     }
 
-    Ok(())
+    Ok(next_local)
 }
 
 /// Declare `count` local variables of the same type, starting from `next_local`.
@@ -207,7 +221,8 @@ fn declare_locals<FE: FuncEnvironment + ?Sized>(
             builder.ins().vconst(ir::types::I8X16, constant_handle)
         }
         ExternRef => builder.ins().null(environ.reference_type()),
-        FuncRef => builder.ins().null(environ.reference_type()),
+        // `funcref`s are represented as raw pointers, null being 0.
+        FuncRef => builder.ins().iconst(environ.pointer_type(), 0),
         ty => return Err(wasm_unsupported!("unsupported local type {:?}", ty)),
     };
 
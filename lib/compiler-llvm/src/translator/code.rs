@@ -1,12 +1,14 @@
 use super::{
     intrinsics::{
-        tbaa_label, type_to_llvm, CtxType, FunctionCache, GlobalCache, Intrinsics, MemoryCache,
+        tbaa_label, type_to_llvm, type_to_llvm_ptr, CtxType, FunctionCache, GlobalCache,
+        Intrinsics, MemoryCache,
     },
     // stackmap::{StackmapEntry, StackmapEntryKind, StackmapRegistry, ValueSemantic},
     state::{ControlFrame, ExtraInfo, IfElseState, State},
 };
 use inkwell::{
     attributes::AttributeLoc,
+    basic_block::BasicBlock,
     builder::Builder,
     context::Context,
     module::{Linkage, Module},
@@ -26,13 +28,13 @@ use crate::config::{CompiledKind, LLVM};
 use crate::object_file::{load_object_file, CompiledFunction};
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator};
 use wasmer_compiler::{
-    wptype_to_type, CompileError, FunctionBodyData, MiddlewareBinaryReader, ModuleMiddlewareChain,
-    ModuleTranslationState, RelocationTarget, Symbol, SymbolRegistry,
+    wasm_unsupported, wptype_to_type, CompileError, FunctionBodyData, MiddlewareBinaryReader,
+    ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget, Symbol, SymbolRegistry,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type,
+    TableIndex, TagIndex, Type,
 };
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, VMOffsets};
 
@@ -111,6 +113,7 @@ impl FuncTranslator {
         let entry = self.ctx.append_basic_block(func, "entry");
         let start_of_code = self.ctx.append_basic_block(func, "start_of_code");
         let return_ = self.ctx.append_basic_block(func, "return");
+        let body = self.ctx.append_basic_block(func, "body");
         let alloca_builder = self.ctx.create_builder();
         let cache_builder = self.ctx.create_builder();
         let builder = self.ctx.create_builder();
@@ -169,6 +172,7 @@ impl FuncTranslator {
         }
 
         let mut locals = vec![];
+        let mut local_inits = vec![];
         let num_locals = reader.read_local_count()?;
         for _ in 0..num_locals {
             let (count, ty) = reader.read_local_decl()?;
@@ -178,6 +182,7 @@ impl FuncTranslator {
                 let alloca = insert_alloca(ty, "local");
                 cache_builder.build_store(alloca, ty.const_zero());
                 locals.push(alloca);
+                local_inits.push(ty.const_zero());
             }
         }
 
@@ -192,6 +197,8 @@ impl FuncTranslator {
             state,
             function: func,
             locals: params_locals,
+            local_inits,
+            body_block: body,
             ctx: CtxType::new(wasm_module, &func, &cache_builder, &*self.abi),
             unreachable_depth: 0,
             memory_styles,
@@ -199,6 +206,8 @@ impl FuncTranslator {
             module: &module,
             module_translation,
             wasm_module,
+            wasm_fn_type,
+            function_index: func_index,
             symbol_registry,
             abi: &*self.abi,
         };
@@ -208,6 +217,10 @@ impl FuncTranslator {
             fcg.ctx.basic(),
             &func_attrs,
         );
+        fcg.builder.build_unconditional_branch(body);
+        fcg.builder.position_at_end(body);
+        fcg.build_stack_check();
+        fcg.build_interrupt_checks();
 
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
@@ -966,6 +979,82 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         Ok(())
     }
 
+    /// Computes the 64-bit effective address passed to the atomic wait and
+    /// notify builtins, which do their own bounds and alignment checks.
+    fn effective_atomic_addr(
+        &self,
+        memarg: &MemoryImmediate,
+        var_offset: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let imm_offset = self
+            .intrinsics
+            .i64_ty
+            .const_int(memarg.offset as u64, false);
+        if var_offset.get_type() == self.intrinsics.i64_ty {
+            // Addresses into 64-bit memories can overflow when adding the
+            // offset.
+            let addr = self.builder.build_int_add(var_offset, imm_offset, "");
+            self.trap_if_address_overflows(var_offset, addr);
+            addr
+        } else {
+            let var_offset =
+                self.builder
+                    .build_int_z_extend(var_offset, self.intrinsics.i64_ty, "");
+            self.builder.build_int_add(var_offset, imm_offset, "")
+        }
+    }
+
+    /// Traps if `end`, computed by adding constants to the address `start`,
+    /// wrapped around the 64-bit address space.
+    fn trap_if_address_overflows(&self, start: IntValue<'ctx>, end: IntValue<'ctx>) {
+        let should_trap =
+            self.builder
+                .build_int_compare(IntPredicate::ULT, end, start, "address_overflows");
+
+        let should_trap = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    should_trap.as_basic_value_enum(),
+                    self.intrinsics.i1_ty.const_zero().as_basic_value_enum(),
+                ],
+                "should_trap_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let shouldnt_trap_block = self
+            .context
+            .append_basic_block(self.function, "shouldnt_trap_block");
+        let should_trap_block = self
+            .context
+            .append_basic_block(self.function, "should_trap_block");
+        self.builder
+            .build_conditional_branch(should_trap, should_trap_block, shouldnt_trap_block);
+        self.builder.position_at_end(should_trap_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_memory_oob],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(shouldnt_trap_block);
+    }
+
+    /// Returns the index identifying `memory_index` to the `memory.size` and
+    /// `memory.grow` builtins: local memories are identified by their local
+    /// index, imported memories by their module index.
+    fn memory_index_arg(&self, memory_index: MemoryIndex) -> IntValue<'ctx> {
+        let index = match self.wasm_module.local_memory_index(memory_index) {
+            Some(local_memory_index) => local_memory_index.as_u32(),
+            None => memory_index.as_u32(),
+        };
+        self.intrinsics.i32_ty.const_int(index.into(), false)
+    }
+
     fn resolve_memory_ptr(
         &mut self,
         memory_index: MemoryIndex,
@@ -980,9 +1069,21 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         let function = &self.function;
 
         // Compute the offset into the storage.
+        let memory64 = self.wasm_module.memories[memory_index].memory64;
         let imm_offset = intrinsics.i64_ty.const_int(memarg.offset as u64, false);
-        let var_offset = builder.build_int_z_extend(var_offset, intrinsics.i64_ty, "");
+        let var_offset = if memory64 {
+            var_offset
+        } else {
+            builder.build_int_z_extend(var_offset, intrinsics.i64_ty, "")
+        };
         let offset = builder.build_int_add(var_offset, imm_offset, "");
+        if memory64 {
+            // The end of the access is checked against the memory's bounds
+            // below, but with 64-bit addresses it may have wrapped around.
+            let value_size_v = intrinsics.i64_ty.const_int(value_size as u64, false);
+            let load_offset_end = builder.build_int_add(offset, value_size_v, "");
+            self.trap_if_address_overflows(var_offset, load_offset_end);
+        }
 
         // Look up the memory base (as pointer) and bounds (as unsigned integer).
         let base_ptr =
@@ -997,7 +1098,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     // Bounds check it.
                     let minimum = self.wasm_module.memories[memory_index].minimum;
                     let value_size_v = intrinsics.i64_ty.const_int(value_size as u64, false);
-                    let ptr_in_bounds = if offset.is_const() {
+                    let ptr_in_bounds = if offset.is_const() && !memory64 {
                         // When the offset is constant, if it's below the minimum
                         // memory size, we've statically shown that it's safe.
                         let load_offset_end = offset.const_add(value_size_v);
@@ -1025,8 +1126,6 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                             format!("memory {} length", memory_index.as_u32()),
                             current_length.as_instruction_value().unwrap(),
                         );
-                        let current_length =
-                            builder.build_int_z_extend(current_length, intrinsics.i64_ty, "");
 
                         builder.build_int_compare(
                             IntPredicate::ULE,
@@ -1143,6 +1242,325 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
         self.builder.position_at_end(continue_block);
     }
 
+    /// Loads the 64-bit value at `ptr` with a volatile load, as it can be
+    /// written from other threads or by the host, and the load must stay
+    /// inside loops.
+    fn build_volatile_load_i64(&self, ptr: PointerValue<'ctx>, name: &str) -> IntValue<'ctx> {
+        let value = self.builder.build_load(ptr, name).into_int_value();
+        value
+            .as_instruction_value()
+            .unwrap()
+            .set_volatile(true)
+            .unwrap();
+        value
+    }
+
+    /// Calls the builtin function `fn_ptr`, taking only vmctx, when the
+    /// unlikely `condition` holds.
+    fn build_builtin_call_if(&self, condition: IntValue<'ctx>, fn_ptr: PointerValue<'ctx>) {
+        let condition = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    condition.as_basic_value_enum(),
+                    self.intrinsics
+                        .i1_ty
+                        .const_int(0, false)
+                        .as_basic_value_enum(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "builtin_call_continue_block");
+        let call_block = self
+            .context
+            .append_basic_block(self.function, "builtin_call_block");
+        self.builder
+            .build_conditional_branch(condition, call_block, continue_block);
+
+        self.builder.position_at_end(call_block);
+        self.builder.build_call(fn_ptr, &[self.ctx.basic()], "");
+        self.builder.build_unconditional_branch(continue_block);
+
+        self.builder.position_at_end(continue_block);
+    }
+
+    /// Traps with `StackOverflow` if the stack pointer is below the store's
+    /// stack limit. A limit of zero is never reached.
+    fn build_stack_check(&mut self) {
+        let stack_pointer = self
+            .builder
+            .build_call(self.intrinsics.stacksave, &[], "stack_pointer")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+        let stack_pointer =
+            self.builder
+                .build_ptr_to_int(stack_pointer, self.intrinsics.i64_ty, "");
+        let stack_limit_ptr = self.ctx.stack_limit(self.intrinsics);
+        let stack_limit = self.build_volatile_load_i64(stack_limit_ptr, "stack_limit");
+        let should_trap = self.builder.build_int_compare(
+            IntPredicate::ULT,
+            stack_pointer,
+            stack_limit,
+            "stack_overflows",
+        );
+
+        let should_trap = self
+            .builder
+            .build_call(
+                self.intrinsics.expect_i1,
+                &[
+                    should_trap.as_basic_value_enum(),
+                    self.intrinsics.i1_ty.const_zero().as_basic_value_enum(),
+                ],
+                "should_trap_expect",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+
+        let shouldnt_trap_block = self
+            .context
+            .append_basic_block(self.function, "shouldnt_trap_block");
+        let should_trap_block = self
+            .context
+            .append_basic_block(self.function, "should_trap_block");
+        self.builder
+            .build_conditional_branch(should_trap, should_trap_block, shouldnt_trap_block);
+        self.builder.position_at_end(should_trap_block);
+        self.builder.build_call(
+            self.intrinsics.throw_trap,
+            &[self.intrinsics.trap_stack_overflow],
+            "throw",
+        );
+        self.builder.build_unreachable();
+        self.builder.position_at_end(shouldnt_trap_block);
+    }
+
+    /// Checks the store's interrupt flag and the epoch deadline of the
+    /// instance, and calls into the runtime, which may trap, when the former
+    /// is set or the latter is reached.
+    fn build_interrupt_checks(&mut self) {
+        let interrupt_flag = self.ctx.interrupt_flag(self.intrinsics);
+        let interrupt = self.build_volatile_load_i64(interrupt_flag, "interrupt");
+        let interrupted = self.builder.build_int_compare(
+            IntPredicate::NE,
+            interrupt,
+            self.intrinsics.i64_zero,
+            "",
+        );
+        let interrupt_fn = self.ctx.interrupt(self.intrinsics);
+        self.build_builtin_call_if(interrupted, interrupt_fn);
+
+        let epoch_ptr = self.ctx.epoch(self.intrinsics);
+        let epoch = self.build_volatile_load_i64(epoch_ptr, "epoch");
+        let deadline_ptr = self.ctx.epoch_deadline_ptr(self.intrinsics);
+        let deadline = self.build_volatile_load_i64(deadline_ptr, "epoch_deadline");
+        let reached = self
+            .builder
+            .build_int_compare(IntPredicate::UGE, epoch, deadline, "");
+        let epoch_deadline_fn = self.ctx.epoch_deadline(self.intrinsics);
+        self.build_builtin_call_if(reached, epoch_deadline_fn);
+    }
+
+    /// Allocates a buffer of `len` 128-bit slots, laid out like the values
+    /// passed to trampolines, to exchange values with the exception builtins.
+    fn values_buffer(&self, len: usize) -> PointerValue<'ctx> {
+        let array_ty = self.intrinsics.i128_ty.array_type(len.max(1) as u32);
+        let buffer = self.alloca_builder.build_alloca(array_ty, "values");
+        self.builder
+            .build_pointer_cast(buffer, self.intrinsics.i128_ptr_ty, "")
+    }
+
+    fn values_buffer_slot(
+        &self,
+        buffer: PointerValue<'ctx>,
+        index: usize,
+        ty: Type,
+    ) -> Result<PointerValue<'ctx>, CompileError> {
+        let index = self.intrinsics.i32_ty.const_int(index as u64, false);
+        let slot = unsafe { self.builder.build_in_bounds_gep(buffer, &[index], "") };
+        Ok(self
+            .builder
+            .build_pointer_cast(slot, type_to_llvm_ptr(self.intrinsics, ty)?, ""))
+    }
+
+    /// Stores `values`, of the given `types`, in the consecutive slots of
+    /// `buffer`.
+    fn store_values(
+        &self,
+        buffer: PointerValue<'ctx>,
+        types: &[Type],
+        values: &[(BasicValueEnum<'ctx>, ExtraInfo)],
+    ) -> Result<(), CompileError> {
+        for (index, (&ty, &(value, info))) in types.iter().zip(values.iter()).enumerate() {
+            let value = self.apply_pending_canonicalization(value, info);
+            let value = self
+                .builder
+                .build_bitcast(value, type_to_llvm(self.intrinsics, ty)?, "");
+            let slot = self.values_buffer_slot(buffer, index, ty)?;
+            self.builder.build_store(slot, value);
+        }
+        Ok(())
+    }
+
+    /// Loads values of the given `types` from the consecutive slots of
+    /// `buffer`.
+    fn load_values(
+        &self,
+        buffer: PointerValue<'ctx>,
+        types: &[Type],
+    ) -> Result<Vec<BasicValueEnum<'ctx>>, CompileError> {
+        types
+            .iter()
+            .enumerate()
+            .map(|(index, &ty)| {
+                let slot = self.values_buffer_slot(buffer, index, ty)?;
+                Ok(self.builder.build_load(slot, ""))
+            })
+            .collect()
+    }
+
+    /// Returns the landing pad of the innermost `try` block whose body is
+    /// being translated, if any, creating it if needed.
+    fn innermost_landing_pad(&mut self) -> Option<(BasicBlock<'ctx>, PhiValue<'ctx>)> {
+        let (context, function, builder, intrinsics) =
+            (self.context, self.function, &self.builder, self.intrinsics);
+        match self.state.innermost_try_body_mut()? {
+            ControlFrame::Try { landing_pad, .. } => Some(*landing_pad.get_or_insert_with(|| {
+                let current_block = builder.get_insert_block().unwrap();
+                let landing_pad = context.append_basic_block(function, "landing_pad");
+                builder.position_at_end(landing_pad);
+                let exn = builder.build_phi(intrinsics.i8_ptr_ty, "exn");
+                builder.position_at_end(current_block);
+                (landing_pad, exn)
+            })),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Creates the exception thrown with the tag `tag_index`, carrying
+    /// `payload`.
+    fn build_exception_new(
+        &mut self,
+        tag_index: TagIndex,
+        payload: &[(BasicValueEnum<'ctx>, ExtraInfo)],
+    ) -> Result<PointerValue<'ctx>, CompileError> {
+        let wasm_module = self.wasm_module;
+        let tag_type = &wasm_module.signatures[wasm_module.tags[tag_index]];
+        let buffer = self.values_buffer(tag_type.params().len());
+        self.store_values(buffer, tag_type.params(), payload)?;
+        let exception_new = self.ctx.exception_new(self.intrinsics);
+        Ok(self
+            .builder
+            .build_call(
+                exception_new,
+                &[
+                    self.ctx.basic(),
+                    self.intrinsics
+                        .i32_ty
+                        .const_int(tag_index.as_u32().into(), false)
+                        .as_basic_value_enum(),
+                    buffer.as_basic_value_enum(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value())
+    }
+
+    /// Throws the exception `exn`, either to the innermost `try` block of the
+    /// function or to the caller.
+    fn build_throw(&mut self, exn: PointerValue<'ctx>) -> Result<(), CompileError> {
+        match self.innermost_landing_pad() {
+            Some((landing_pad, landing_pad_exn)) => {
+                let current_block = self
+                    .builder
+                    .get_insert_block()
+                    .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+                landing_pad_exn.add_incoming(&[(&exn, current_block)]);
+                self.builder.build_unconditional_branch(landing_pad);
+            }
+            None => {
+                let exception_raise = self.ctx.exception_raise(self.intrinsics);
+                self.builder
+                    .build_call(exception_raise, &[exn.as_basic_value_enum()], "");
+                self.builder.build_unreachable();
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `callee`, of the signature `sigindex`, from the body of a `try`
+    /// block. The call goes through the `invoke` builtin, which returns the
+    /// exception thrown by the callee, if any, so it can be passed to
+    /// `landing_pad`.
+    fn build_invoke(
+        &mut self,
+        sigindex: SignatureIndex,
+        callee: PointerValue<'ctx>,
+        callee_vmctx: PointerValue<'ctx>,
+        params: &[(BasicValueEnum<'ctx>, ExtraInfo)],
+        (landing_pad, landing_pad_exn): (BasicBlock<'ctx>, PhiValue<'ctx>),
+    ) -> Result<Vec<BasicValueEnum<'ctx>>, CompileError> {
+        let wasm_module = self.wasm_module;
+        let func_type = &wasm_module.signatures[sigindex];
+        let values = self.values_buffer(func_type.params().len().max(func_type.results().len()));
+        self.store_values(values, func_type.params(), params)?;
+
+        let callee = self
+            .builder
+            .build_pointer_cast(callee, self.intrinsics.i8_ptr_ty, "");
+        let invoke = self.ctx.invoke(self.intrinsics);
+        let exn = self
+            .builder
+            .build_call(
+                invoke,
+                &[
+                    self.ctx.basic(),
+                    self.intrinsics
+                        .i32_ty
+                        .const_int(sigindex.as_u32().into(), false)
+                        .as_basic_value_enum(),
+                    callee.as_basic_value_enum(),
+                    callee_vmctx.as_basic_value_enum(),
+                    values.as_basic_value_enum(),
+                ],
+                "",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+
+        let current_block = self
+            .builder
+            .get_insert_block()
+            .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+        landing_pad_exn.add_incoming(&[(&exn, current_block)]);
+        let continue_block = self
+            .context
+            .append_basic_block(self.function, "invoke_continue");
+        let thrown = self.builder.build_is_not_null(exn, "thrown");
+        self.builder
+            .build_conditional_branch(thrown, landing_pad, continue_block);
+        self.builder.position_at_end(continue_block);
+
+        self.load_values(values, func_type.results())
+    }
+
     fn finalize(&mut self, wasm_fn_type: &FunctionType) -> Result<(), CompileError> {
         let func_type = self.function.get_type();
 
@@ -1283,6 +1701,11 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
     state: State<'ctx>,
     function: FunctionValue<'ctx>,
     locals: Vec<PointerValue<'ctx>>, // Contains params and locals
+    /// The initial values of the locals that aren't parameters.
+    local_inits: Vec<BasicValueEnum<'ctx>>,
+    /// The block starting the function body, once the locals are
+    /// initialized. `return_call`s to the function itself jump back to it.
+    body_block: BasicBlock<'ctx>,
     ctx: CtxType<'ctx, 'a>,
     unreachable_depth: usize,
     memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
@@ -1298,6 +1721,8 @@ pub struct LLVMFunctionCodeGenerator<'ctx, 'a> {
     module: &'a Module<'ctx>,
     module_translation: &'a ModuleTranslationState,
     wasm_module: &'a ModuleInfo,
+    wasm_fn_type: &'a FunctionType,
+    function_index: FunctionIndex,
     symbol_registry: &'a dyn SymbolRegistry,
     abi: &'a dyn Abi,
 }
@@ -1312,11 +1737,14 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
         if !self.state.reachable {
             match op {
-                Operator::Block { ty: _ } | Operator::Loop { ty: _ } | Operator::If { ty: _ } => {
+                Operator::Block { ty: _ }
+                | Operator::Loop { ty: _ }
+                | Operator::If { ty: _ }
+                | Operator::Try { ty: _ } => {
                     self.unreachable_depth += 1;
                     return Ok(());
                 }
-                Operator::Else => {
+                Operator::Else | Operator::Catch { .. } => {
                     if self.unreachable_depth != 0 {
                         return Ok(());
                     }
@@ -1440,6 +1868,8 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 }
                 */
 
+                self.build_interrupt_checks();
+
                 self.state.push_loop(loop_body, loop_next, loop_phis, phis);
             }
             Operator::Br { relative_depth } => {
@@ -1722,6 +2152,56 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     }
                 }
 
+                if let ControlFrame::Try {
+                    landing_pad: Some((landing_pad, exn)),
+                    catches,
+                    ..
+                } = &frame
+                {
+                    // All the `catch` clauses are known now, so the
+                    // exceptions thrown by the body can be dispatched to
+                    // them, or thrown further.
+                    self.builder.position_at_end(*landing_pad);
+                    let exn = exn.as_basic_value().into_pointer_value();
+                    for (tag_index, catch_block, catch_exn) in catches {
+                        let exception_matches = self.ctx.exception_matches(self.intrinsics);
+                        let matches = self
+                            .builder
+                            .build_call(
+                                exception_matches,
+                                &[
+                                    vmctx.as_basic_value_enum(),
+                                    exn.as_basic_value_enum(),
+                                    self.intrinsics
+                                        .i32_ty
+                                        .const_int(tag_index.as_u32().into(), false)
+                                        .as_basic_value_enum(),
+                                ],
+                                "",
+                            )
+                            .try_as_basic_value()
+                            .left()
+                            .unwrap()
+                            .into_int_value();
+                        let matches = self.builder.build_int_compare(
+                            IntPredicate::NE,
+                            matches,
+                            self.intrinsics.i32_zero,
+                            "",
+                        );
+                        let current_block = self.builder.get_insert_block().ok_or_else(|| {
+                            CompileError::Codegen("not currently in a block".to_string())
+                        })?;
+                        catch_exn.add_incoming(&[(&exn, current_block)]);
+                        let next_block =
+                            self.context.append_basic_block(self.function, "catch_next");
+                        self.builder
+                            .build_conditional_branch(matches, *catch_block, next_block);
+                        self.builder.position_at_end(next_block);
+                    }
+                    self.build_throw(exn)?;
+                }
+
                 self.builder.position_at_end(*frame.code_after());
                 self.state.reset_stack(&frame);
 
@@ -1739,6 +2219,131 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     }
                 }
             }
+            Operator::Try { ty } => {
+                let current_block = self
+                    .builder
+                    .get_insert_block()
+                    .ok_or_else(|| CompileError::Codegen("not currently in a block".to_string()))?;
+
+                let end_block = self.context.append_basic_block(self.function, "try_end");
+                self.builder.position_at_end(end_block);
+
+                let (params, results) = self.module_translation.blocktype_params_results(ty)?;
+                let phis: SmallVec<[PhiValue<'ctx>; 1]> = results
+                    .iter()
+                    .map(|&wp_ty| {
+                        wptype_to_type(wp_ty)
+                            .map_err(to_compile_error)
+                            .and_then(|wasm_ty| {
+                                type_to_llvm(self.intrinsics, wasm_ty)
+                                    .map(|ty| self.builder.build_phi(ty, ""))
+                            })
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                self.state.push_try(end_block, phis, params.len());
+                self.builder.position_at_end(current_block);
+            }
+            Operator::Catch { index } => {
+                if self.state.reachable {
+                    let frame = self.state.frame_at_depth(0)?;
+                    let current_block = self.builder.get_insert_block().ok_or_else(|| {
+                        CompileError::Codegen("not currently in a block".to_string())
+                    })?;
+
+                    for phi in frame.phis().to_vec().iter().rev() {
+                        let (value, info) = self.state.pop1_extra()?;
+                        let value = self.apply_pending_canonicalization(value, info);
+                        phi.add_incoming(&[(&value, current_block)])
+                    }
+
+                    let frame = self.state.frame_at_depth(0)?;
+                    self.builder.build_unconditional_branch(*frame.code_after());
+                }
+
+                let tag_index = TagIndex::from_u32(index);
+                let (landing_pad, stack_size_snapshot) = match self.state.frame_at_depth_mut(0)? {
+                    ControlFrame::Try {
+                        in_body,
+                        landing_pad,
+                        caught,
+                        stack_size_snapshot,
+                        ..
+                    } => {
+                        *in_body = false;
+                        *caught = None;
+                        (*landing_pad, *stack_size_snapshot)
+                    }
+                    _ => return Err(CompileError::Codegen("catch must follow a try".to_string())),
+                };
+                self.state.stack.truncate(stack_size_snapshot);
+
+                if landing_pad.is_none() {
+                    // Nothing in the body of the `try` throws.
+                    self.state.reachable = false;
+                    return Ok(());
+                }
+
+                let catch_block = self.context.append_basic_block(self.function, "catch");
+                self.builder.position_at_end(catch_block);
+                let exn = self.builder.build_phi(self.intrinsics.i8_ptr_ty, "exn");
+
+                let wasm_module = self.wasm_module;
+                let tag_type = &wasm_module.signatures[wasm_module.tags[tag_index]];
+                let payload = self.values_buffer(tag_type.params().len());
+                let exception_take_payload = self.ctx.exception_take_payload(self.intrinsics);
+                self.builder.build_call(
+                    exception_take_payload,
+                    &[exn.as_basic_value(), payload.as_basic_value_enum()],
+                    "",
+                );
+                let payload = self.load_values(payload, tag_type.params())?;
+                for value in payload.iter() {
+                    self.state.push1(*value);
+                }
+
+                if let ControlFrame::Try {
+                    catches, caught, ..
+                } = self.state.frame_at_depth_mut(0)?
+                {
+                    catches.push((tag_index, catch_block, exn));
+                    *caught = Some((tag_index, payload));
+                }
+                self.state.reachable = true;
+            }
+            Operator::Throw { index } => {
+                let tag_index = TagIndex::from_u32(index);
+                let wasm_module = self.wasm_module;
+                let arity = wasm_module.signatures[wasm_module.tags[tag_index]]
+                    .params()
+                    .len();
+                let payload = self.state.popn_save_extra(arity)?;
+                let exn = self.build_exception_new(tag_index, &payload)?;
+                self.build_throw(exn)?;
+                self.state.reachable = false;
+            }
+            Operator::Rethrow { relative_depth } => {
+                let (tag_index, payload) = match self.state.frame_at_depth(relative_depth)? {
+                    ControlFrame::Try {
+                        caught: Some((tag_index, payload)),
+                        ..
+                    } => (
+                        *tag_index,
+                        payload
+                            .iter()
+                            .map(|value| (*value, ExtraInfo::default()))
+                            .collect::<Vec<_>>(),
+                    ),
+                    _ => {
+                        return Err(CompileError::Codegen(
+                            "rethrow must target a catch clause".to_string(),
+                        ))
+                    }
+                };
+                let exn = self.build_exception_new(tag_index, &payload)?;
+                self.build_throw(exn)?;
+                self.state.reachable = false;
+            }
             Operator::Return => {
                 let current_block = self
                     .builder
@@ -2101,6 +2706,22 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 */
                 let params = self.state.popn_save_extra(func_type.params().len())?;
 
+                // Calls made from the body of a `try` block go through the
+                // `invoke` builtin, to catch the exceptions they throw.
+                if let Some(landing_pad) = self.innermost_landing_pad() {
+                    let results = self.build_invoke(
+                        *sigindex,
+                        func,
+                        callee_vmctx.into_pointer_value(),
+                        &params,
+                        landing_pad,
+                    )?;
+                    for result in results {
+                        self.state.push1(result);
+                    }
+                    return Ok(());
+                }
+
                 // Apply pending canonicalizations.
                 let params =
                     params
@@ -2174,6 +2795,38 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                     .iter()
                     .for_each(|ret| self.state.push1(*ret));
             }
+            // LLVM 11's C API can't mark calls `musttail`, so only the tail
+            // calls to the function itself, which become a jump, are
+            // guaranteed to run in constant stack space. The other ones are
+            // rejected.
+            Operator::ReturnCall { function_index } => {
+                let func_index = FunctionIndex::from_u32(function_index);
+                if func_index != self.function_index {
+                    return Err(CompileError::Wasm(wasm_unsupported!(
+                        "tail call to function {} from another function",
+                        function_index
+                    )));
+                }
+                let params = self
+                    .state
+                    .popn_save_extra(self.wasm_fn_type.params().len())?;
+                for (pointer_value, (v, i)) in self.locals.iter().zip(params) {
+                    let v = self.apply_pending_canonicalization(v, i);
+                    self.builder.build_store(*pointer_value, v);
+                }
+                let num_params = self.wasm_fn_type.params().len();
+                for (pointer_value, init) in self.locals[num_params..]
+                    .iter()
+                    .zip(self.local_inits.iter())
+                {
+                    self.builder.build_store(*pointer_value, *init);
+                }
+                self.builder.build_unconditional_branch(self.body_block);
+                self.state.reachable = false;
+            }
+            Operator::ReturnCallIndirect { .. } => {
+                return Err(CompileError::Wasm(wasm_unsupported!("indirect tail call")));
+            }
             Operator::CallIndirect { index, table_index } => {
                 let sigindex = SignatureIndex::from_u32(index);
                 let func_type = &self.wasm_module.signatures[sigindex];
@@ -2349,6 +3002,20 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
                 let params = self.state.popn_save_extra(func_type.params().len())?;
 
+                if let Some(landing_pad) = self.innermost_landing_pad() {
+                    let results = self.build_invoke(
+                        sigindex,
+                        func_ptr,
+                        ctx_ptr.into_pointer_value(),
+                        &params,
+                        landing_pad,
+                    )?;
+                    for result in results {
+                        self.state.push1(result);
+                    }
+                    return Ok(());
+                }
+
                 // Apply pending canonicalizations.
                 let params =
                     params
@@ -6212,7 +6879,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
              ***************************/
             Operator::I32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6231,7 +6898,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6250,7 +6917,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F32Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6269,7 +6936,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::F64Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6288,7 +6955,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6309,7 +6976,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6330,7 +6997,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6352,7 +7019,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6374,7 +7041,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6396,7 +7063,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let (v, i) = self.state.pop1_extra()?;
                 let v = self.apply_pending_canonicalization(v, i);
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6416,7 +7083,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6440,7 +7107,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6464,7 +7131,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6489,7 +7156,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6514,7 +7181,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6539,7 +7206,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
             Operator::I32Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6563,7 +7230,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6587,7 +7254,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6611,7 +7278,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6635,7 +7302,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64Load32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6661,7 +7328,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store8 { ref memarg } | Operator::I64Store8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6685,7 +7352,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32Store16 { ref memarg } | Operator::I64Store16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -6709,7 +7376,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64Store32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7089,7 +7756,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7110,7 +7777,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8x8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7131,7 +7798,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7152,7 +7819,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16x4U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7173,7 +7840,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2S { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7194,7 +7861,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32x2U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7215,7 +7882,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7239,7 +7906,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Zero { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7263,7 +7930,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load8Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7284,7 +7951,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load16Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7305,7 +7972,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load32Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7326,7 +7993,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::V128Load64Splat { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7356,7 +8023,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7374,7 +8041,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7392,7 +8059,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7416,7 +8083,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I32AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7440,7 +8107,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad8U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7464,7 +8131,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad16U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7488,7 +8155,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             }
             Operator::I64AtomicLoad32U { ref memarg } => {
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7513,7 +8180,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7531,7 +8198,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore { ref memarg } => {
                 let value = self.state.pop1()?;
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7549,7 +8216,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicStore8 { ref memarg } | Operator::I64AtomicStore8 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7571,7 +8238,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             | Operator::I64AtomicStore16 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7592,7 +8259,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicStore32 { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7613,7 +8280,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7648,7 +8315,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7683,7 +8350,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7712,7 +8379,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7747,7 +8414,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7782,7 +8449,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AddU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7817,7 +8484,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAdd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7846,7 +8513,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7881,7 +8548,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7916,7 +8583,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7945,7 +8612,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -7980,7 +8647,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8015,7 +8682,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32SubU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8050,7 +8717,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwSub { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8079,7 +8746,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8114,7 +8781,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8149,7 +8816,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8178,7 +8845,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8213,7 +8880,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8248,7 +8915,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32AndU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8283,7 +8950,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwAnd { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8312,7 +8979,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8347,7 +9014,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8382,7 +9049,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8414,7 +9081,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8449,7 +9116,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8484,7 +9151,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32OrU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8519,7 +9186,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwOr { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8548,7 +9215,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8583,7 +9250,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8618,7 +9285,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8647,7 +9314,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8682,7 +9349,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8717,7 +9384,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XorU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8752,7 +9419,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXor { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8781,7 +9448,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8816,7 +9483,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8851,7 +9518,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I32AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8880,7 +9547,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw8XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8915,7 +9582,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw16XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8950,7 +9617,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmw32XchgU { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -8985,7 +9652,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
             Operator::I64AtomicRmwXchg { ref memarg } => {
                 let value = self.state.pop1()?.into_int_value();
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9017,7 +9684,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9064,7 +9731,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9111,7 +9778,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9145,7 +9812,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9192,7 +9859,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9239,7 +9906,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9286,7 +9953,7 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 let new = self.apply_pending_canonicalization(new, new_info);
                 let (cmp, new) = (cmp.into_int_value(), new.into_int_value());
                 let offset = self.state.pop1()?.into_int_value();
-                let memory_index = MemoryIndex::from_u32(0);
+                let memory_index = MemoryIndex::from_u32(memarg.memory);
                 let effective_address = self.resolve_memory_ptr(
                     memory_index,
                     memarg,
@@ -9317,38 +9984,144 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
 
             Operator::MemoryGrow { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::from_u32(mem);
+                let memory64 = self.wasm_module.memories[memory_index].memory64;
                 let delta = self.state.pop1()?;
+                let delta = if memory64 {
+                    // Deltas that don't fit in 32 bits can never succeed, so
+                    // they are clamped to a delta that is guaranteed to fail.
+                    let delta = delta.into_int_value();
+                    let too_large = self.builder.build_int_compare(
+                        IntPredicate::UGT,
+                        delta,
+                        self.intrinsics.i64_ty.const_int(u32::MAX.into(), false),
+                        "",
+                    );
+                    let delta = self
+                        .builder
+                        .build_int_truncate(delta, self.intrinsics.i32_ty, "");
+                    self.builder.build_select(
+                        too_large,
+                        self.intrinsics.i32_ty.const_all_ones(),
+                        delta,
+                        "",
+                    )
+                } else {
+                    delta
+                };
                 let grow_fn_ptr = self.ctx.memory_grow(memory_index, self.intrinsics);
                 let grow = self.builder.build_call(
                     grow_fn_ptr,
                     &[
                         vmctx.as_basic_value_enum(),
                         delta,
-                        self.intrinsics
-                            .i32_ty
-                            .const_int(mem.into(), false)
-                            .as_basic_value_enum(),
+                        self.memory_index_arg(memory_index).as_basic_value_enum(),
                     ],
                     "",
                 );
-                self.state.push1(grow.try_as_basic_value().left().unwrap());
+                let grow = grow.try_as_basic_value().left().unwrap();
+                let grow = if memory64 {
+                    // A failed grow returns -1, which must stay -1 as an `i64`.
+                    let grow = grow.into_int_value();
+                    let failed = self.builder.build_int_compare(
+                        IntPredicate::EQ,
+                        grow,
+                        self.intrinsics.i32_ty.const_all_ones(),
+                        "",
+                    );
+                    let grow = self
+                        .builder
+                        .build_int_z_extend(grow, self.intrinsics.i64_ty, "");
+                    self.builder.build_select(
+                        failed,
+                        self.intrinsics.i64_ty.const_all_ones(),
+                        grow,
+                        "",
+                    )
+                } else {
+                    grow
+                };
+                self.state.push1(grow);
             }
             Operator::MemorySize { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::from_u32(mem);
                 let size_fn_ptr = self.ctx.memory_size(memory_index, self.intrinsics);
                 let size = self.builder.build_call(
                     size_fn_ptr,
+                    &[
+                        vmctx.as_basic_value_enum(),
+                        self.memory_index_arg(memory_index).as_basic_value_enum(),
+                    ],
+                    "",
+                );
+                size.add_attribute(AttributeLoc::Function, self.intrinsics.readonly);
+                let size = size.try_as_basic_value().left().unwrap();
+                let size = if self.wasm_module.memories[memory_index].memory64 {
+                    self.builder
+                        .build_int_z_extend(size.into_int_value(), self.intrinsics.i64_ty, "")
+                        .as_basic_value_enum()
+                } else {
+                    size
+                };
+                self.state.push1(size);
+            }
+            Operator::MemoryAtomicWait32 { ref memarg } => {
+                let ((addr, _), (expected, _), (timeout, _)) = self.state.pop3_extra()?;
+                let addr = self.effective_atomic_addr(memarg, addr.into_int_value());
+                let wait_fn_ptr = self.ctx.memory_atomic_wait32(self.intrinsics);
+                let ret = self.builder.build_call(
+                    wait_fn_ptr,
                     &[
                         vmctx.as_basic_value_enum(),
                         self.intrinsics
                             .i32_ty
-                            .const_int(mem.into(), false)
+                            .const_int(memarg.memory.into(), false)
                             .as_basic_value_enum(),
+                        addr.as_basic_value_enum(),
+                        expected,
+                        timeout,
                     ],
                     "",
                 );
-                size.add_attribute(AttributeLoc::Function, self.intrinsics.readonly);
-                self.state.push1(size.try_as_basic_value().left().unwrap());
+                self.state.push1(ret.try_as_basic_value().left().unwrap());
+            }
+            Operator::MemoryAtomicWait64 { ref memarg } => {
+                let ((addr, _), (expected, _), (timeout, _)) = self.state.pop3_extra()?;
+                let addr = self.effective_atomic_addr(memarg, addr.into_int_value());
+                let wait_fn_ptr = self.ctx.memory_atomic_wait64(self.intrinsics);
+                let ret = self.builder.build_call(
+                    wait_fn_ptr,
+                    &[
+                        vmctx.as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.memory.into(), false)
+                            .as_basic_value_enum(),
+                        addr.as_basic_value_enum(),
+                        expected,
+                        timeout,
+                    ],
+                    "",
+                );
+                self.state.push1(ret.try_as_basic_value().left().unwrap());
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let (addr, count) = self.state.pop2()?;
+                let addr = self.effective_atomic_addr(memarg, addr.into_int_value());
+                let notify_fn_ptr = self.ctx.memory_atomic_notify(self.intrinsics);
+                let ret = self.builder.build_call(
+                    notify_fn_ptr,
+                    &[
+                        vmctx.as_basic_value_enum(),
+                        self.intrinsics
+                            .i32_ty
+                            .const_int(memarg.memory.into(), false)
+                            .as_basic_value_enum(),
+                        addr.as_basic_value_enum(),
+                        count,
+                    ],
+                    "",
+                );
+                self.state.push1(ret.try_as_basic_value().left().unwrap());
             }
             _ => {
                 return Err(CompileError::Codegen(format!(
@@ -113,6 +113,7 @@ pub struct Intrinsics<'ctx> {
 
     pub expect_i1: FunctionValue<'ctx>,
     pub trap: FunctionValue<'ctx>,
+    pub stacksave: FunctionValue<'ctx>,
     pub debug_trap: FunctionValue<'ctx>,
 
     pub personality: FunctionValue<'ctx>,
@@ -166,6 +167,7 @@ pub struct Intrinsics<'ctx> {
     pub trap_bad_conversion_to_integer: BasicValueEnum<'ctx>,
     pub trap_unaligned_atomic: BasicValueEnum<'ctx>,
     pub trap_table_access_oob: BasicValueEnum<'ctx>,
+    pub trap_stack_overflow: BasicValueEnum<'ctx>,
 
     // VM intrinsics.
     pub throw_trap: FunctionValue<'ctx>,
@@ -184,6 +186,16 @@ pub struct Intrinsics<'ctx> {
     pub imported_memory32_grow_ptr_ty: PointerType<'ctx>,
     pub memory32_size_ptr_ty: PointerType<'ctx>,
     pub imported_memory32_size_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_wait32_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_wait64_ptr_ty: PointerType<'ctx>,
+    pub memory_atomic_notify_ptr_ty: PointerType<'ctx>,
+    pub exception_new_ptr_ty: PointerType<'ctx>,
+    pub exception_raise_ptr_ty: PointerType<'ctx>,
+    pub invoke_ptr_ty: PointerType<'ctx>,
+    pub exception_matches_ptr_ty: PointerType<'ctx>,
+    pub exception_take_payload_ptr_ty: PointerType<'ctx>,
+    pub interrupt_ptr_ty: PointerType<'ctx>,
+    pub epoch_deadline_ptr_ty: PointerType<'ctx>,
 
     pub ctx_ptr_ty: PointerType<'ctx>,
 }
@@ -361,6 +373,7 @@ impl<'ctx> Intrinsics<'ctx> {
 
             expect_i1: module.add_function("llvm.expect.i1", ret_i1_take_i1_i1, None),
             trap: module.add_function("llvm.trap", void_ty.fn_type(&[], false), None),
+            stacksave: module.add_function("llvm.stacksave", i8_ptr_ty.fn_type(&[], false), None),
             debug_trap: module.add_function("llvm.debugtrap", void_ty.fn_type(&[], false), None),
             personality: module.add_function(
                 "__gxx_personality_v0",
@@ -436,6 +449,9 @@ impl<'ctx> Intrinsics<'ctx> {
             trap_table_access_oob: i32_ty
                 .const_int(TrapCode::TableAccessOutOfBounds as _, false)
                 .as_basic_value_enum(),
+            trap_stack_overflow: i32_ty
+                .const_int(TrapCode::StackOverflow as _, false)
+                .as_basic_value_enum(),
 
             // VM intrinsics.
             throw_trap: module.add_function(
@@ -463,7 +479,7 @@ impl<'ctx> Intrinsics<'ctx> {
 
             // TODO: this i64 is actually a rust usize
             vmmemory_definition_ptr_ty: context
-                .struct_type(&[i8_ptr_ty_basic, i32_ty_basic], false)
+                .struct_type(&[i8_ptr_ty_basic, i64_ty_basic], false)
                 .ptr_type(AddressSpace::Generic),
             vmmemory_definition_base_element: 0,
             vmmemory_definition_current_length_element: 1,
@@ -486,6 +502,85 @@ impl<'ctx> Intrinsics<'ctx> {
             imported_memory32_size_ptr_ty: i32_ty
                 .fn_type(&[ctx_ptr_ty.as_basic_type_enum(), i32_ty_basic], false)
                 .ptr_type(AddressSpace::Generic),
+            memory_atomic_wait32_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i32_ty_basic,
+                        i64_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            memory_atomic_wait64_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i64_ty_basic,
+                        i64_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            memory_atomic_notify_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i64_ty_basic,
+                        i32_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_new_ptr_ty: i8_ptr_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i128_ptr_ty.as_basic_type_enum(),
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_raise_ptr_ty: void_ty
+                .fn_type(&[i8_ptr_ty_basic], false)
+                .ptr_type(AddressSpace::Generic),
+            invoke_ptr_ty: i8_ptr_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i32_ty_basic,
+                        i8_ptr_ty_basic,
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i128_ptr_ty.as_basic_type_enum(),
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_matches_ptr_ty: i32_ty
+                .fn_type(
+                    &[
+                        ctx_ptr_ty.as_basic_type_enum(),
+                        i8_ptr_ty_basic,
+                        i32_ty_basic,
+                    ],
+                    false,
+                )
+                .ptr_type(AddressSpace::Generic),
+            exception_take_payload_ptr_ty: void_ty
+                .fn_type(&[i8_ptr_ty_basic, i128_ptr_ty.as_basic_type_enum()], false)
+                .ptr_type(AddressSpace::Generic),
+            interrupt_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty.as_basic_type_enum()], false)
+                .ptr_type(AddressSpace::Generic),
+            epoch_deadline_ptr_ty: void_ty
+                .fn_type(&[ctx_ptr_ty.as_basic_type_enum()], false)
+                .ptr_type(AddressSpace::Generic),
 
             ctx_ptr_ty,
         };
@@ -545,6 +640,8 @@ pub struct CtxType<'ctx, 'a> {
     cached_functions: HashMap<FunctionIndex, FunctionCache<'ctx>>,
     cached_memory_grow: HashMap<MemoryIndex, PointerValue<'ctx>>,
     cached_memory_size: HashMap<MemoryIndex, PointerValue<'ctx>>,
+    cached_builtin_functions: HashMap<u32, PointerValue<'ctx>>,
+    cached_interrupts: Option<PointerValue<'ctx>>,
 
     offsets: VMOffsets,
 }
@@ -570,6 +667,8 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
             cached_functions: HashMap::new(),
             cached_memory_grow: HashMap::new(),
             cached_memory_size: HashMap::new(),
+            cached_builtin_functions: HashMap::new(),
+            cached_interrupts: None,
 
             // TODO: pointer width
             offsets: VMOffsets::new(8, &wasm_module),
@@ -638,7 +737,11 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
                     "",
                 )
                 .unwrap();
-            if let MemoryStyle::Dynamic { .. } = memory_style {
+            // Accesses to 64-bit memories are always bounds checked, even
+            // when the memory is reserved up front.
+            if wasm_module.memories[index].memory64
+                || matches!(memory_style, MemoryStyle::Dynamic { .. })
+            {
                 let current_length_ptr = cache_builder
                     .build_struct_gep(
                         memory_definition_ptr,
@@ -1104,6 +1207,192 @@ impl<'ctx, 'a> CtxType<'ctx, 'a> {
         })
     }
 
+    /// Returns a pointer to the builtin function at `index`, with the
+    /// function pointer type `fn_ty`.
+    fn builtin_function(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        fn_ty: PointerType<'ctx>,
+        intrinsics: &Intrinsics<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let (cached_builtin_functions, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_builtin_functions,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_builtin_functions
+            .entry(index.index())
+            .or_insert_with(|| {
+                let offset = offsets.vmctx_builtin_function(index);
+                let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+                let fn_ptr_ptr = unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+
+                let fn_ptr_ptr = cache_builder
+                    .build_bitcast(fn_ptr_ptr, fn_ty.ptr_type(AddressSpace::Generic), "")
+                    .into_pointer_value();
+                cache_builder
+                    .build_load(fn_ptr_ptr, "")
+                    .into_pointer_value()
+            })
+    }
+
+    pub fn memory_atomic_wait32(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+            intrinsics.memory_atomic_wait32_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn memory_atomic_wait64(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+            intrinsics.memory_atomic_wait64_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn memory_atomic_notify(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+            intrinsics.memory_atomic_notify_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn exception_new(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_exception_new_index(),
+            intrinsics.exception_new_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn exception_raise(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_exception_raise_index(),
+            intrinsics.exception_raise_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn invoke(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_invoke_index(),
+            intrinsics.invoke_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn exception_matches(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_exception_matches_index(),
+            intrinsics.exception_matches_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn exception_take_payload(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_exception_take_payload_index(),
+            intrinsics.exception_take_payload_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn interrupt(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_interrupt_index(),
+            intrinsics.interrupt_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    pub fn epoch_deadline(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        self.builtin_function(
+            VMBuiltinFunctionIndex::get_epoch_deadline_index(),
+            intrinsics.epoch_deadline_ptr_ty,
+            intrinsics,
+        )
+    }
+
+    /// Returns a pointer to the `VMInterrupts` shared by all the instances
+    /// of the store.
+    fn interrupts(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let (cached_interrupts, offsets, cache_builder, ctx_ptr_value) = (
+            &mut self.cached_interrupts,
+            &self.offsets,
+            &self.cache_builder,
+            &self.ctx_ptr_value,
+        );
+        *cached_interrupts.get_or_insert_with(|| {
+            let offset = offsets.vmctx_interrupts();
+            let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+            let interrupts_ptr_ptr =
+                unsafe { cache_builder.build_gep(*ctx_ptr_value, &[offset], "") };
+            let interrupts_ptr_ptr = cache_builder
+                .build_bitcast(
+                    interrupts_ptr_ptr,
+                    intrinsics.i8_ptr_ty.ptr_type(AddressSpace::Generic),
+                    "",
+                )
+                .into_pointer_value();
+            cache_builder
+                .build_load(interrupts_ptr_ptr, "interrupts_ptr")
+                .into_pointer_value()
+        })
+    }
+
+    /// Returns a pointer to the interrupt flag shared by all the instances
+    /// of the store.
+    pub fn interrupt_flag(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let interrupts = self.interrupts(intrinsics);
+        let offset = self.offsets.vminterrupts_interrupt();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let interrupt_ptr = unsafe { self.cache_builder.build_gep(interrupts, &[offset], "") };
+        self.cache_builder
+            .build_bitcast(interrupt_ptr, intrinsics.i64_ptr_ty, "interrupt_ptr")
+            .into_pointer_value()
+    }
+
+    /// Returns a pointer to the epoch shared by all the instances of the
+    /// store.
+    pub fn epoch(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let interrupts = self.interrupts(intrinsics);
+        let offset = self.offsets.vminterrupts_epoch();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let epoch_ptr = unsafe { self.cache_builder.build_gep(interrupts, &[offset], "") };
+        self.cache_builder
+            .build_bitcast(epoch_ptr, intrinsics.i64_ptr_ty, "epoch_ptr")
+            .into_pointer_value()
+    }
+
+    /// Returns a pointer to the stack limit shared by all the instances of
+    /// the store.
+    pub fn stack_limit(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let interrupts = self.interrupts(intrinsics);
+        let offset = self.offsets.vminterrupts_stack_limit();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let stack_limit_ptr = unsafe { self.cache_builder.build_gep(interrupts, &[offset], "") };
+        self.cache_builder
+            .build_bitcast(stack_limit_ptr, intrinsics.i64_ptr_ty, "stack_limit_ptr")
+            .into_pointer_value()
+    }
+
+    /// Returns a pointer to the epoch deadline of the instance.
+    pub fn epoch_deadline_ptr(&mut self, intrinsics: &Intrinsics<'ctx>) -> PointerValue<'ctx> {
+        let offset = self.offsets.vmctx_epoch_deadline();
+        let offset = intrinsics.i32_ty.const_int(offset.into(), false);
+        let deadline_ptr = unsafe {
+            self.cache_builder
+                .build_gep(self.ctx_ptr_value, &[offset], "")
+        };
+        self.cache_builder
+            .build_bitcast(deadline_ptr, intrinsics.i64_ptr_ty, "epoch_deadline_ptr")
+            .into_pointer_value()
+    }
+
     pub fn get_offsets(&self) -> &VMOffsets {
         &self.offsets
     }
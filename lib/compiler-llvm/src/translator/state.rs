@@ -5,6 +5,7 @@ use inkwell::{
 use smallvec::SmallVec;
 use std::ops::{BitAnd, BitOr, BitOrAssign};
 use wasmer_compiler::CompileError;
+use wasmer_types::TagIndex;
 
 #[derive(Debug)]
 pub enum ControlFrame<'ctx> {
@@ -30,6 +31,24 @@ pub enum ControlFrame<'ctx> {
         stack_size_snapshot: usize,
         if_else_state: IfElseState,
     },
+    Try {
+        next: BasicBlock<'ctx>,
+        phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        stack_size_snapshot: usize,
+        /// Whether the body of the `try` is being translated, as opposed to
+        /// one of its `catch` clauses.
+        in_body: bool,
+        /// The block the exceptions thrown by the body are passed to, along
+        /// with the phi receiving them. It is created by the first
+        /// instruction of the body which may throw.
+        landing_pad: Option<(BasicBlock<'ctx>, PhiValue<'ctx>)>,
+        /// The `catch` clauses seen so far, with the phi receiving the
+        /// exception in each of them.
+        catches: Vec<(TagIndex, BasicBlock<'ctx>, PhiValue<'ctx>)>,
+        /// The tag and payload of the exception caught by the current `catch`
+        /// clause, for `rethrow`.
+        caught: Option<(TagIndex, Vec<BasicValueEnum<'ctx>>)>,
+    },
 }
 
 #[derive(Debug)]
@@ -43,22 +62,25 @@ impl<'ctx> ControlFrame<'ctx> {
         match self {
             ControlFrame::Block { ref next, .. }
             | ControlFrame::Loop { ref next, .. }
-            | ControlFrame::IfElse { ref next, .. } => next,
+            | ControlFrame::IfElse { ref next, .. }
+            | ControlFrame::Try { ref next, .. } => next,
         }
     }
 
     pub fn br_dest(&self) -> &BasicBlock<'ctx> {
         match self {
-            ControlFrame::Block { ref next, .. } | ControlFrame::IfElse { ref next, .. } => next,
+            ControlFrame::Block { ref next, .. }
+            | ControlFrame::IfElse { ref next, .. }
+            | ControlFrame::Try { ref next, .. } => next,
             ControlFrame::Loop { ref body, .. } => body,
         }
     }
 
     pub fn phis(&self) -> &[PhiValue<'ctx>] {
         match self {
-            ControlFrame::Block { ref phis, .. } | ControlFrame::Loop { ref phis, .. } => {
-                phis.as_slice()
-            }
+            ControlFrame::Block { ref phis, .. }
+            | ControlFrame::Loop { ref phis, .. }
+            | ControlFrame::Try { ref phis, .. } => phis.as_slice(),
             ControlFrame::IfElse { ref next_phis, .. } => next_phis.as_slice(),
         }
     }
@@ -66,7 +88,9 @@ impl<'ctx> ControlFrame<'ctx> {
     /// PHI nodes for stack values in the loop body.
     pub fn loop_body_phis(&self) -> &[PhiValue<'ctx>] {
         match self {
-            ControlFrame::Block { .. } | ControlFrame::IfElse { .. } => &[],
+            ControlFrame::Block { .. } | ControlFrame::IfElse { .. } | ControlFrame::Try { .. } => {
+                &[]
+            }
             ControlFrame::Loop {
                 ref loop_body_phis, ..
             } => loop_body_phis.as_slice(),
@@ -235,6 +259,10 @@ impl<'ctx> State<'ctx> {
             | ControlFrame::IfElse {
                 stack_size_snapshot,
                 ..
+            }
+            | ControlFrame::Try {
+                stack_size_snapshot,
+                ..
             } => *stack_size_snapshot,
         };
         self.stack.truncate(stack_size_snapshot);
@@ -271,6 +299,15 @@ impl<'ctx> State<'ctx> {
         Ok(&mut self.control_stack[index])
     }
 
+    /// Returns the innermost `try` block whose body is being translated, if
+    /// any.
+    pub fn innermost_try_body_mut(&mut self) -> Option<&mut ControlFrame<'ctx>> {
+        self.control_stack
+            .iter_mut()
+            .rev()
+            .find(|frame| matches!(frame, ControlFrame::Try { in_body: true, .. }))
+    }
+
     pub fn pop_frame(&mut self) -> Result<ControlFrame<'ctx>, CompileError> {
         self.control_stack.pop().ok_or_else(|| {
             CompileError::Codegen("pop_frame: cannot pop from control stack".to_string())
@@ -418,4 +455,23 @@ impl<'ctx> State<'ctx> {
             if_else_state: IfElseState::If,
         });
     }
+
+    /// Pushes a `try` block, whose `num_params` parameters are already on
+    /// the value stack.
+    pub fn push_try(
+        &mut self,
+        next: BasicBlock<'ctx>,
+        phis: SmallVec<[PhiValue<'ctx>; 1]>,
+        num_params: usize,
+    ) {
+        self.control_stack.push(ControlFrame::Try {
+            next,
+            phis,
+            stack_size_snapshot: self.stack.len() - num_params,
+            in_body: true,
+            landing_pad: None,
+            catches: vec![],
+            caught: None,
+        });
+    }
 }
@@ -0,0 +1,286 @@
+//! ARM64 structures.
+
+use wasmer_types::Type;
+
+/// General-purpose registers.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(dead_code)]
+pub enum GPR {
+    /// X0 register
+    X0,
+    /// X1 register
+    X1,
+    /// X2 register
+    X2,
+    /// X3 register
+    X3,
+    /// X4 register
+    X4,
+    /// X5 register
+    X5,
+    /// X6 register
+    X6,
+    /// X7 register
+    X7,
+    /// X8 register
+    X8,
+    /// X9 register
+    X9,
+    /// X10 register
+    X10,
+    /// X11 register
+    X11,
+    /// X12 register
+    X12,
+    /// X13 register
+    X13,
+    /// X14 register
+    X14,
+    /// X15 register
+    X15,
+    /// X16 register
+    X16,
+    /// X17 register
+    X17,
+    /// X18 register
+    X18,
+    /// X19 register
+    X19,
+    /// X20 register
+    X20,
+    /// X21 register
+    X21,
+    /// X22 register
+    X22,
+    /// X23 register
+    X23,
+    /// X24 register
+    X24,
+    /// X25 register
+    X25,
+    /// X26 register
+    X26,
+    /// X27 register
+    X27,
+    /// X28 register
+    X28,
+    /// X29 register, the frame pointer
+    X29,
+    /// X30 register, the link register
+    X30,
+    /// Register 31, which is the zero register or the stack pointer depending
+    /// on the instruction
+    XzrSp,
+}
+
+/// NEON (floating point/SIMD) registers.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(dead_code)]
+pub enum NEON {
+    /// V0 register
+    V0,
+    /// V1 register
+    V1,
+    /// V2 register
+    V2,
+    /// V3 register
+    V3,
+    /// V4 register
+    V4,
+    /// V5 register
+    V5,
+    /// V6 register
+    V6,
+    /// V7 register
+    V7,
+    /// V8 register
+    V8,
+    /// V9 register
+    V9,
+    /// V10 register
+    V10,
+    /// V11 register
+    V11,
+    /// V12 register
+    V12,
+    /// V13 register
+    V13,
+    /// V14 register
+    V14,
+    /// V15 register
+    V15,
+    /// V16 register
+    V16,
+    /// V17 register
+    V17,
+    /// V18 register
+    V18,
+    /// V19 register
+    V19,
+    /// V20 register
+    V20,
+    /// V21 register
+    V21,
+    /// V22 register
+    V22,
+    /// V23 register
+    V23,
+    /// V24 register
+    V24,
+    /// V25 register
+    V25,
+    /// V26 register
+    V26,
+    /// V27 register
+    V27,
+    /// V28 register
+    V28,
+    /// V29 register
+    V29,
+    /// V30 register
+    V30,
+    /// V31 register
+    V31,
+}
+
+impl GPR {
+    /// Returns the register number used in instruction encodings.
+    pub fn into_index(self) -> usize {
+        self as usize
+    }
+}
+
+impl NEON {
+    /// Returns the register number used in instruction encodings.
+    pub fn into_index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A machine register under the AArch64 architecture.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ARM64Register {
+    /// General-purpose registers.
+    GPR(GPR),
+    /// NEON (floating point/SIMD) registers.
+    NEON(NEON),
+}
+
+impl ARM64Register {
+    /// Returns the DWARF register number of the register.
+    #[cfg(feature = "unwind")]
+    pub fn dwarf_regnum(&self) -> u16 {
+        match *self {
+            ARM64Register::GPR(x) => x as u16,
+            ARM64Register::NEON(x) => x as u16 + 64,
+        }
+    }
+}
+
+/// An operation that changes how to unwind the current frame, recorded by
+/// the code generator and turned into DWARF call frame instructions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnwindOp {
+    /// The frame pointer and the link register were pushed, moving the stack
+    /// pointer down by `size` bytes.
+    PushFrameRecord { size: u32 },
+    /// The frame pointer was set to the stack pointer, and is used to compute
+    /// the canonical frame address from now on.
+    DefineNewFrame,
+    /// `reg` was saved `bp_neg_offset` bytes below the frame pointer.
+    SaveRegister {
+        reg: ARM64Register,
+        bp_neg_offset: i32,
+    },
+}
+
+/// An allocator that allocates registers for function arguments according to
+/// the AArch64 Procedure Call Standard.
+#[derive(Default)]
+pub struct ArgumentRegisterAllocator {
+    n_gprs: usize,
+    n_neons: usize,
+}
+
+impl ArgumentRegisterAllocator {
+    /// Allocates a register for argument type `ty`. Returns `None` if no register is available for this type.
+    pub fn next(&mut self, ty: Type) -> Option<ARM64Register> {
+        static GPR_SEQ: &[GPR] = &[
+            GPR::X0,
+            GPR::X1,
+            GPR::X2,
+            GPR::X3,
+            GPR::X4,
+            GPR::X5,
+            GPR::X6,
+            GPR::X7,
+        ];
+        static NEON_SEQ: &[NEON] = &[
+            NEON::V0,
+            NEON::V1,
+            NEON::V2,
+            NEON::V3,
+            NEON::V4,
+            NEON::V5,
+            NEON::V6,
+            NEON::V7,
+        ];
+        match ty {
+            Type::I32 | Type::I64 | Type::ExternRef | Type::FuncRef => {
+                if self.n_gprs < GPR_SEQ.len() {
+                    let gpr = GPR_SEQ[self.n_gprs];
+                    self.n_gprs += 1;
+                    Some(ARM64Register::GPR(gpr))
+                } else {
+                    None
+                }
+            }
+            // 128-bit vectors are passed in the same registers as floats.
+            Type::F32 | Type::F64 | Type::V128 => {
+                if self.n_neons < NEON_SEQ.len() {
+                    let neon = NEON_SEQ[self.n_neons];
+                    self.n_neons += 1;
+                    Some(ARM64Register::NEON(neon))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_argument_register_allocator() {
+        let mut argalloc = ArgumentRegisterAllocator::default();
+        assert_eq!(argalloc.next(Type::I64), Some(ARM64Register::GPR(GPR::X0)));
+        assert_eq!(
+            argalloc.next(Type::F32),
+            Some(ARM64Register::NEON(NEON::V0))
+        );
+        assert_eq!(argalloc.next(Type::I32), Some(ARM64Register::GPR(GPR::X1)));
+        assert_eq!(
+            argalloc.next(Type::F64),
+            Some(ARM64Register::NEON(NEON::V1))
+        );
+
+        // Integer and floating point arguments are counted separately.
+        for _ in 2..8 {
+            assert!(argalloc.next(Type::I32).is_some());
+        }
+        assert_eq!(argalloc.next(Type::I32), None);
+        assert_eq!(
+            argalloc.next(Type::F32),
+            Some(ARM64Register::NEON(NEON::V2))
+        );
+        assert_eq!(
+            argalloc.next(Type::V128),
+            Some(ARM64Register::NEON(NEON::V3))
+        );
+    }
+}
@@ -0,0 +1,2166 @@
+//! The singlepass code generator for AArch64.
+//!
+//! This follows the structure of `codegen_x64`, but uses the standard AArch64
+//! Procedure Call Standard for all calls, so that no argument translation is
+//! needed at the boundaries with host functions.
+
+use crate::address_map::get_function_address_map;
+use crate::codegen_x64::{
+    type_to_wp_type, CodegenError, IfElseState, TrapTable, WpTypeExt, GEF32_LT_I32_MIN,
+    GEF32_LT_I64_MIN, GEF32_LT_U32_MIN, GEF32_LT_U64_MIN, GEF64_LT_I32_MIN, GEF64_LT_I64_MIN,
+    GEF64_LT_U32_MIN, GEF64_LT_U64_MIN, LEF32_GT_I32_MAX, LEF32_GT_I64_MAX, LEF32_GT_U32_MAX,
+    LEF32_GT_U64_MAX, LEF64_GT_I32_MAX, LEF64_GT_I64_MAX, LEF64_GT_U32_MAX, LEF64_GT_U64_MAX,
+};
+use crate::{
+    arm64_decl::UnwindOp, config::Singlepass, emitter_arm64::*, machine_arm64::MachineARM64,
+};
+use dynasmrt::{aarch64::Assembler, DynamicLabel};
+use smallvec::{smallvec, SmallVec};
+use wasmer_compiler::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer_compiler::{
+    CompiledFunction, CompiledFunctionFrameInfo, CustomSection, CustomSectionProtection,
+    FunctionBody, FunctionBodyData, InstructionAddressMap, Relocation, RelocationKind,
+    RelocationTarget, SectionBody, SectionIndex, SourceLoc, TrapInformation,
+};
+use wasmer_types::{
+    entity::{EntityRef, PrimaryMap, SecondaryMap},
+    FunctionType,
+};
+use wasmer_types::{
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex, TableIndex, Type,
+};
+use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
+
+/// The singlepass per-function code generator for AArch64.
+pub struct FuncGenARM64<'a> {
+    // Immutable properties assigned at creation time.
+    /// Static module information.
+    module: &'a ModuleInfo,
+
+    /// ModuleInfo compilation config.
+    config: &'a Singlepass,
+
+    /// Offsets of vmctx fields.
+    vmoffsets: &'a VMOffsets,
+
+    // Memory plans.
+    memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+
+    /// Function signature.
+    signature: FunctionType,
+
+    // Working storage.
+    /// The assembler.
+    assembler: Assembler,
+
+    /// Memory locations of local variables.
+    locals: Vec<Location>,
+
+    /// Types of local variables, including arguments.
+    local_types: Vec<WpType>,
+
+    /// Value stack.
+    value_stack: Vec<Location>,
+
+    /// A list of frames describing the current control stack.
+    control_stack: Vec<ControlFrame>,
+
+    /// Low-level machine state.
+    machine: MachineARM64,
+
+    /// Nesting level of unreachable code.
+    unreachable_depth: usize,
+
+    /// Trap table.
+    trap_table: TrapTable,
+
+    /// Relocation information.
+    relocations: Vec<Relocation>,
+
+    /// A set of special labels for trapping.
+    special_labels: SpecialLabelSet,
+
+    /// The source location for the current operator.
+    src_loc: u32,
+
+    /// Map from byte offset into wasm function to range of native instructions.
+    ///
+    // Ordered by increasing InstructionAddressMap::srcloc.
+    instructions_address_map: Vec<InstructionAddressMap>,
+
+    /// Operations needed to unwind the frame of this function.
+    unwind_ops: Vec<(usize, UnwindOp)>,
+}
+
+struct SpecialLabelSet {
+    integer_division_by_zero: DynamicLabel,
+    integer_overflow: DynamicLabel,
+    bad_conversion_to_integer: DynamicLabel,
+    heap_access_oob: DynamicLabel,
+    table_access_oob: DynamicLabel,
+    indirect_call_null: DynamicLabel,
+    bad_signature: DynamicLabel,
+    stack_overflow: DynamicLabel,
+}
+
+/// A control frame. Unlike on x86-64, no machine state is tracked.
+#[derive(Debug)]
+struct ControlFrame {
+    label: DynamicLabel,
+    loop_like: bool,
+    if_else: IfElseState,
+    returns: SmallVec<[WpType; 1]>,
+    value_stack_depth: usize,
+}
+
+/// Abstraction for a 2-input, 1-output operator. Can be an integer/floating-point
+/// binop/cmpop.
+struct I2O1 {
+    loc_a: Location,
+    loc_b: Location,
+    ret: Location,
+}
+
+impl<'a> FuncGenARM64<'a> {
+    /// Set the source location of the Wasm to the given offset.
+    pub fn set_srcloc(&mut self, offset: u32) {
+        self.src_loc = offset;
+    }
+
+    fn get_location_released(&mut self, loc: Location) -> Location {
+        self.machine.release_locations(&mut self.assembler, &[loc]);
+        loc
+    }
+
+    fn pop_value_released(&mut self) -> Location {
+        let loc = self
+            .value_stack
+            .pop()
+            .expect("pop_value_released: value stack is empty");
+        self.get_location_released(loc)
+    }
+
+    /// Acquires a location for a new value of type `ty` and pushes it to the value stack.
+    fn push_new_value(&mut self, ty: WpType) -> Location {
+        let loc = self
+            .machine
+            .acquire_locations(&mut self.assembler, &[ty], false)[0];
+        self.value_stack.push(loc);
+        loc
+    }
+
+    /// Prepare data for binary operator with 2 inputs and 1 output.
+    fn i2o1_prepare(&mut self, ty: WpType) -> I2O1 {
+        let loc_b = self.pop_value_released();
+        let loc_a = self.pop_value_released();
+        let ret = self.push_new_value(ty);
+        I2O1 { loc_a, loc_b, ret }
+    }
+
+    /// Marks each address in the code range emitted by `f` with the trap code `code`.
+    fn mark_range_with_trap_code<F: FnOnce(&mut Self) -> R, R>(
+        &mut self,
+        code: TrapCode,
+        f: F,
+    ) -> R {
+        let begin = self.assembler.get_offset().0;
+        let ret = f(self);
+        let end = self.assembler.get_offset().0;
+        for i in begin..end {
+            self.trap_table.offset_to_code.insert(i, code);
+        }
+        self.mark_instruction_address_end(begin);
+        ret
+    }
+
+    /// Marks one address as trappable with trap code `code`.
+    fn mark_address_with_trap_code(&mut self, code: TrapCode) {
+        let offset = self.assembler.get_offset().0;
+        self.trap_table.offset_to_code.insert(offset, code);
+        self.mark_instruction_address_end(offset);
+    }
+
+    /// Returns a general purpose register holding the value at `loc`,
+    /// moving it into `tmp` if needed.
+    fn location_to_gpr(&mut self, sz: Size, loc: Location, tmp: GPR) -> GPR {
+        match loc {
+            Location::GPR(x) => x,
+            _ => {
+                self.assembler.emit_mov(sz, loc, Location::GPR(tmp));
+                tmp
+            }
+        }
+    }
+
+    /// Returns a NEON register holding the value at `loc`, moving it into
+    /// `tmp` if needed.
+    fn location_to_neon(&mut self, sz: Size, loc: Location, tmp: NEON) -> NEON {
+        match loc {
+            Location::NEON(x) => x,
+            _ => {
+                self.assembler.emit_mov(sz, loc, Location::NEON(tmp));
+                tmp
+            }
+        }
+    }
+
+    /// Returns `loc` if it can be used as the second operand of an integer
+    /// instruction, or moves it into `tmp`.
+    fn location_to_operand(&mut self, sz: Size, loc: Location, tmp: GPR) -> Location {
+        match loc {
+            Location::GPR(_) | Location::Imm8(_) | Location::Imm32(_) | Location::Imm64(_) => loc,
+            _ => Location::GPR(self.location_to_gpr(sz, loc, tmp)),
+        }
+    }
+
+    /// Moves the value computed into `reg` to `ret`, if they differ.
+    fn move_gpr_to(&mut self, sz: Size, reg: GPR, ret: Location) {
+        if Location::GPR(reg) != ret {
+            self.assembler.emit_mov(sz, Location::GPR(reg), ret);
+        }
+    }
+
+    /// Moves the value computed into `reg` to `ret`, if they differ.
+    fn move_neon_to(&mut self, sz: Size, reg: NEON, ret: Location) {
+        if Location::NEON(reg) != ret {
+            self.assembler.emit_mov(sz, Location::NEON(reg), ret);
+        }
+    }
+
+    /// Returns the register to compute a general purpose result for `ret` into.
+    fn gpr_for_result(ret: Location, tmp: GPR) -> GPR {
+        match ret {
+            Location::GPR(x) => x,
+            _ => tmp,
+        }
+    }
+
+    /// Returns the register to compute a floating point result for `ret` into.
+    fn neon_for_result(ret: Location, tmp: NEON) -> NEON {
+        match ret {
+            Location::NEON(x) => x,
+            _ => tmp,
+        }
+    }
+
+    /// Emits an integer binary operator.
+    fn emit_binop(
+        &mut self,
+        ty: WpType,
+        sz: Size,
+        f: fn(&mut Assembler, Size, GPR, Location, GPR),
+    ) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let tmp_a = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_b = self.machine.acquire_temp_gpr().unwrap();
+        let src1 = self.location_to_gpr(sz, loc_a, tmp_a);
+        let src2 = self.location_to_operand(sz, loc_b, tmp_b);
+        let dst = Self::gpr_for_result(ret, tmp_a);
+        f(&mut self.assembler, sz, src1, src2, dst);
+        self.move_gpr_to(sz, dst, ret);
+        self.machine.release_temp_gpr(tmp_b);
+        self.machine.release_temp_gpr(tmp_a);
+    }
+
+    /// Emits an integer rotation to the left, as a rotation to the right by
+    /// the negated count.
+    fn emit_rotl(&mut self, ty: WpType, sz: Size) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let tmp_a = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_b = self.machine.acquire_temp_gpr().unwrap();
+        let src1 = self.location_to_gpr(sz, loc_a, tmp_a);
+        let src2 = self.location_to_gpr(sz, loc_b, tmp_b);
+        self.assembler.emit_neg(sz, src2, tmp_b);
+        let dst = Self::gpr_for_result(ret, tmp_a);
+        self.assembler.emit_ror(sz, src1, Location::GPR(tmp_b), dst);
+        self.move_gpr_to(sz, dst, ret);
+        self.machine.release_temp_gpr(tmp_b);
+        self.machine.release_temp_gpr(tmp_a);
+    }
+
+    /// Emits an integer comparison.
+    fn emit_cmpop(&mut self, sz: Size, c: Condition) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+        let tmp_a = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_b = self.machine.acquire_temp_gpr().unwrap();
+        let src1 = self.location_to_gpr(sz, loc_a, tmp_a);
+        let src2 = self.location_to_operand(sz, loc_b, tmp_b);
+        self.assembler.emit_cmp(sz, src1, src2);
+        let dst = Self::gpr_for_result(ret, tmp_a);
+        self.assembler.emit_cset(c, dst);
+        self.move_gpr_to(Size::S32, dst, ret);
+        self.machine.release_temp_gpr(tmp_b);
+        self.machine.release_temp_gpr(tmp_a);
+    }
+
+    /// Emits an integer unary operator.
+    fn emit_unop(&mut self, ty: WpType, sz: Size, f: fn(&mut Assembler, Size, GPR, GPR)) {
+        let loc = self.pop_value_released();
+        let ret = self.push_new_value(ty);
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let src = self.location_to_gpr(sz, loc, tmp);
+        let dst = Self::gpr_for_result(ret, tmp);
+        f(&mut self.assembler, sz, src, dst);
+        self.move_gpr_to(sz, dst, ret);
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Emits an integer division or remainder, trapping on division by zero
+    /// and, for signed division, on overflow.
+    fn emit_div(&mut self, ty: WpType, sz: Size, signed: bool, rem: bool) {
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let tmp_a = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_b = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_q = self.machine.acquire_temp_gpr().unwrap();
+        let src1 = self.location_to_gpr(sz, loc_a, tmp_a);
+        let src2 = self.location_to_gpr(sz, loc_b, tmp_b);
+
+        self.assembler
+            .emit_cbz_label(sz, src2, self.special_labels.integer_division_by_zero);
+        if signed && !rem {
+            // `INT_MIN / -1` overflows. The remainder is 0, which `msub` computes.
+            let (minus_one, int_min) = match sz {
+                Size::S32 => (std::u32::MAX as u64, std::i32::MIN as u32 as u64),
+                _ => (std::u64::MAX, std::i64::MIN as u64),
+            };
+            let no_overflow = self.assembler.get_label();
+            self.assembler
+                .emit_cmp(sz, src2, Location::Imm64(minus_one));
+            self.assembler.emit_bcond_label(Condition::Ne, no_overflow);
+            self.assembler.emit_cmp(sz, src1, Location::Imm64(int_min));
+            self.assembler
+                .emit_bcond_label(Condition::Eq, self.special_labels.integer_overflow);
+            self.assembler.emit_label(no_overflow);
+        }
+
+        let dst = Self::gpr_for_result(ret, tmp_a);
+        let div = if rem { tmp_q } else { dst };
+        if signed {
+            self.assembler.emit_sdiv(sz, src1, Location::GPR(src2), div);
+        } else {
+            self.assembler.emit_udiv(sz, src1, Location::GPR(src2), div);
+        }
+        if rem {
+            self.assembler.emit_msub(sz, tmp_q, src2, src1, dst);
+        }
+        self.move_gpr_to(sz, dst, ret);
+
+        self.machine.release_temp_gpr(tmp_q);
+        self.machine.release_temp_gpr(tmp_b);
+        self.machine.release_temp_gpr(tmp_a);
+    }
+
+    /// Canonicalizes the NaN in `reg`, if it holds one.
+    fn canonicalize_nan(&mut self, sz: Size, reg: NEON) {
+        let canonical_nan = match sz {
+            Size::S32 => 0x7FC0_0000,
+            _ => 0x7FF8_0000_0000_0000,
+        };
+        let end = self.assembler.get_label();
+        self.assembler.emit_fcmp(sz, reg, reg);
+        self.assembler.emit_bcond_label(Condition::Vc, end);
+        self.assembler
+            .emit_mov(sz, Location::Imm64(canonical_nan), Location::NEON(reg));
+        self.assembler.emit_label(end);
+    }
+
+    /// Emits a floating point arithmetic binary operator.
+    fn emit_fp_binop(&mut self, ty: WpType, f: fn(&mut Assembler, Size, NEON, NEON, NEON)) {
+        let sz = fp_size(ty);
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let tmp_a = self.machine.acquire_temp_neon().unwrap();
+        let tmp_b = self.machine.acquire_temp_neon().unwrap();
+        let src1 = self.location_to_neon(sz, loc_a, tmp_a);
+        let src2 = self.location_to_neon(sz, loc_b, tmp_b);
+        let dst = Self::neon_for_result(ret, tmp_a);
+        f(&mut self.assembler, sz, src1, src2, dst);
+        if self.config.enable_nan_canonicalization {
+            self.canonicalize_nan(sz, dst);
+        }
+        self.move_neon_to(sz, dst, ret);
+        self.machine.release_temp_neon(tmp_b);
+        self.machine.release_temp_neon(tmp_a);
+    }
+
+    /// Emits a floating point unary operator. Only arithmetic operators
+    /// canonicalize NaNs, as `neg` and `abs` are bit operations.
+    fn emit_fp_unop(
+        &mut self,
+        ty: WpType,
+        f: fn(&mut Assembler, Size, NEON, NEON),
+        arithmetic: bool,
+    ) {
+        let sz = fp_size(ty);
+        let loc = self.pop_value_released();
+        let ret = self.push_new_value(ty);
+        let tmp = self.machine.acquire_temp_neon().unwrap();
+        let src = self.location_to_neon(sz, loc, tmp);
+        let dst = Self::neon_for_result(ret, tmp);
+        f(&mut self.assembler, sz, src, dst);
+        if arithmetic && self.config.enable_nan_canonicalization {
+            self.canonicalize_nan(sz, dst);
+        }
+        self.move_neon_to(sz, dst, ret);
+        self.machine.release_temp_neon(tmp);
+    }
+
+    /// Emits a floating point comparison.
+    fn emit_fp_cmpop(&mut self, ty: WpType, c: Condition) {
+        let sz = fp_size(ty);
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(WpType::I32);
+        let tmp_a = self.machine.acquire_temp_neon().unwrap();
+        let tmp_b = self.machine.acquire_temp_neon().unwrap();
+        let src1 = self.location_to_neon(sz, loc_a, tmp_a);
+        let src2 = self.location_to_neon(sz, loc_b, tmp_b);
+        self.assembler.emit_fcmp(sz, src1, src2);
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let dst = Self::gpr_for_result(ret, tmp);
+        self.assembler.emit_cset(c, dst);
+        self.move_gpr_to(Size::S32, dst, ret);
+        self.machine.release_temp_gpr(tmp);
+        self.machine.release_temp_neon(tmp_b);
+        self.machine.release_temp_neon(tmp_a);
+    }
+
+    /// Emits `copysign`, operating on the bits of the values.
+    fn emit_fp_copysign(&mut self, ty: WpType) {
+        let sz = fp_size(ty);
+        let (sign_mask, magnitude_mask) = match sz {
+            Size::S32 => (0x8000_0000, 0x7FFF_FFFF),
+            _ => (0x8000_0000_0000_0000, 0x7FFF_FFFF_FFFF_FFFF),
+        };
+        let I2O1 { loc_a, loc_b, ret } = self.i2o1_prepare(ty);
+        let tmp_a = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_b = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler.emit_mov(sz, loc_a, Location::GPR(tmp_a));
+        self.assembler.emit_mov(sz, loc_b, Location::GPR(tmp_b));
+        self.assembler
+            .emit_and(sz, tmp_a, Location::Imm64(magnitude_mask), tmp_a);
+        self.assembler
+            .emit_and(sz, tmp_b, Location::Imm64(sign_mask), tmp_b);
+        self.assembler
+            .emit_orr(sz, tmp_a, Location::GPR(tmp_b), tmp_a);
+        self.assembler.emit_mov(sz, Location::GPR(tmp_a), ret);
+        self.machine.release_temp_gpr(tmp_b);
+        self.machine.release_temp_gpr(tmp_a);
+    }
+
+    /// Emits a conversion from an integer of size `sz_src` to a float of type `ty`.
+    fn emit_int_to_fp(&mut self, sz_src: Size, signed: bool, ty: WpType) {
+        let sz_dst = fp_size(ty);
+        let loc = self.pop_value_released();
+        let ret = self.push_new_value(ty);
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_out = self.machine.acquire_temp_neon().unwrap();
+        let src = self.location_to_gpr(sz_src, loc, tmp);
+        let dst = Self::neon_for_result(ret, tmp_out);
+        if signed {
+            self.assembler.emit_scvtf(sz_src, src, sz_dst, dst);
+        } else {
+            self.assembler.emit_ucvtf(sz_src, src, sz_dst, dst);
+        }
+        self.move_neon_to(sz_dst, dst, ret);
+        self.machine.release_temp_neon(tmp_out);
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Emits a conversion from a float of type `ty` to an integer of size
+    /// `sz_dst`, rounding towards zero.
+    ///
+    /// If `bounds` is given, the conversion traps on NaN and on values outside
+    /// of the exclusive range, given as bit patterns. Otherwise it saturates,
+    /// which is what the AArch64 instructions do.
+    fn emit_fp_to_int(
+        &mut self,
+        ty: WpType,
+        sz_dst: Size,
+        signed: bool,
+        bounds: Option<(u64, u64)>,
+    ) {
+        let sz_src = fp_size(ty);
+        let loc = self.pop_value_released();
+        let ret = self.push_new_value(if sz_dst == Size::S32 {
+            WpType::I32
+        } else {
+            WpType::I64
+        });
+        let tmp_in = self.machine.acquire_temp_neon().unwrap();
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let src = self.location_to_neon(sz_src, loc, tmp_in);
+
+        if let Some((lower_bound, upper_bound)) = bounds {
+            let tmp_bound = self.machine.acquire_temp_neon().unwrap();
+
+            // NaN.
+            self.assembler.emit_fcmp(sz_src, src, src);
+            self.assembler
+                .emit_bcond_label(Condition::Vs, self.special_labels.bad_conversion_to_integer);
+
+            // Underflow.
+            self.assembler.emit_mov(
+                sz_src,
+                Location::Imm64(lower_bound),
+                Location::NEON(tmp_bound),
+            );
+            self.assembler.emit_fcmp(sz_src, src, tmp_bound);
+            self.assembler
+                .emit_bcond_label(Condition::Ls, self.special_labels.integer_overflow);
+
+            // Overflow.
+            self.assembler.emit_mov(
+                sz_src,
+                Location::Imm64(upper_bound),
+                Location::NEON(tmp_bound),
+            );
+            self.assembler.emit_fcmp(sz_src, src, tmp_bound);
+            self.assembler
+                .emit_bcond_label(Condition::Ge, self.special_labels.integer_overflow);
+
+            self.machine.release_temp_neon(tmp_bound);
+        }
+
+        let dst = Self::gpr_for_result(ret, tmp);
+        if signed {
+            self.assembler.emit_fcvtzs(sz_src, src, sz_dst, dst);
+        } else {
+            self.assembler.emit_fcvtzu(sz_src, src, sz_dst, dst);
+        }
+        self.move_gpr_to(sz_dst, dst, ret);
+        self.machine.release_temp_gpr(tmp);
+        self.machine.release_temp_neon(tmp_in);
+    }
+
+    /// Emits the check of the stack pointer against the store's stack
+    /// limit, trapping with `TrapCode::StackOverflow` below it.
+    fn emit_stack_check(&mut self) {
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_stack_limit(), GPR::X16);
+        self.assembler.emit_mov_from_sp(GPR::X17);
+        self.assembler
+            .emit_cmp(Size::S64, GPR::X17, Location::GPR(GPR::X16));
+        self.assembler
+            .emit_bcond_label(Condition::Cc, self.special_labels.stack_overflow);
+    }
+
+    /// Emits the checks of the store's interrupt flag and of the epoch
+    /// deadline of the instance, calling into the runtime (which may trap)
+    /// when the former is set or the latter is reached.
+    fn emit_interrupt_checks(&mut self) {
+        let not_interrupted = self.assembler.get_label();
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_interrupt(), GPR::X16);
+        self.assembler
+            .emit_cbz_label(Size::S64, GPR::X16, not_interrupted);
+        self.emit_vmctx_builtin_call(VMBuiltinFunctionIndex::get_interrupt_index());
+        self.assembler.emit_label(not_interrupted);
+
+        let before_deadline = self.assembler.get_label();
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_epoch(), GPR::X16);
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                MachineARM64::get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_deadline() as i32,
+            ),
+            Location::GPR(GPR::X17),
+        );
+        self.assembler
+            .emit_cmp(Size::S64, GPR::X16, Location::GPR(GPR::X17));
+        self.assembler
+            .emit_bcond_label(Condition::Cc, before_deadline);
+        self.emit_vmctx_builtin_call(VMBuiltinFunctionIndex::get_epoch_deadline_index());
+        self.assembler.emit_label(before_deadline);
+    }
+
+    /// Loads the 64-bit field at `offset` in the store's `VMInterrupts` to
+    /// `dst`.
+    fn emit_load_interrupts_field(&mut self, offset: u8, dst: GPR) {
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                MachineARM64::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(dst),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(dst, offset as i32),
+            Location::GPR(dst),
+        );
+    }
+
+    /// Emits a call to the builtin function `index`, taking only vmctx.
+    fn emit_vmctx_builtin_call(&mut self, index: VMBuiltinFunctionIndex) {
+        let func_offset = self.vmoffsets.vmctx_builtin_function(index) as i32;
+        self.emit_call_aapcs64(
+            |this| {
+                this.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(MachineARM64::get_vmctx_reg(), func_offset),
+                    Location::GPR(GPR::X16),
+                );
+                this.assembler.emit_call_register(GPR::X16);
+            },
+            // [vmctx]
+            &[],
+            &[],
+        );
+    }
+
+    /// Emits a call following the AArch64 Procedure Call Standard.
+    ///
+    /// The caller-saved registers holding values are saved around the call,
+    /// `params` of types `param_types` are moved to their argument locations,
+    /// and vmctx is passed in X0. `cb` then emits the actual call, and may
+    /// override X0. X9 is preserved until `cb` is called.
+    fn emit_call_aapcs64<F: FnOnce(&mut Self)>(
+        &mut self,
+        cb: F,
+        params: &[Location],
+        param_types: &[Type],
+    ) {
+        // Save used caller-saved registers.
+        let used_gprs = self.machine.get_used_gprs();
+        let used_neons = self.machine.get_used_neons();
+        let saved: Vec<Location> = used_gprs
+            .iter()
+            .map(|&x| Location::GPR(x))
+            .chain(used_neons.iter().map(|&x| Location::NEON(x)))
+            .collect();
+        let save_area_size = (saved.len() * 8 + 15) & !15;
+        if save_area_size != 0 {
+            self.assembler.emit_sub_sp(save_area_size as u32);
+        }
+        for (i, loc) in saved.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                *loc,
+                Location::Memory(GPR::XzrSp, (i * 8) as i32),
+            );
+        }
+
+        // Prepare the arguments passed on the stack first, since the
+        // registers holding values are not argument registers.
+        let param_locations = MachineARM64::get_param_locations(param_types);
+        let stack_args_size = MachineARM64::get_param_stack_size(param_types);
+        if stack_args_size != 0 {
+            self.assembler.emit_sub_sp(stack_args_size as u32);
+        }
+        for (param, loc) in params.iter().zip(param_locations.iter()) {
+            if let Location::Memory(_, _) = loc {
+                self.assembler.emit_mov(Size::S64, *param, *loc);
+            }
+        }
+        for (param, loc) in params.iter().zip(param_locations.iter()) {
+            match loc {
+                Location::Memory(_, _) => {}
+                _ => self.assembler.emit_mov(Size::S64, *param, *loc),
+            }
+        }
+
+        // Put vmctx as the first parameter.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(MachineARM64::get_vmctx_reg()),
+            Location::GPR(GPR::X0),
+        );
+
+        cb(self);
+
+        if stack_args_size != 0 {
+            self.assembler.emit_add_sp(stack_args_size as u32);
+        }
+
+        // Restore the saved registers.
+        for (i, loc) in saved.iter().enumerate() {
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::XzrSp, (i * 8) as i32),
+                *loc,
+            );
+        }
+        if save_area_size != 0 {
+            self.assembler.emit_add_sp(save_area_size as u32);
+        }
+    }
+
+    /// Emits the call instruction to a function pointer in `reg`.
+    fn emit_call_register_with_trap(&mut self, reg: GPR) {
+        let offset = self.assembler.get_offset().0;
+        self.trap_table
+            .offset_to_code
+            .insert(offset, TrapCode::StackOverflow);
+        self.assembler.emit_call_register(reg);
+        self.mark_instruction_address_end(offset);
+    }
+
+    /// Pushes the result of a call, returned in X0 or V0, to the value stack.
+    fn push_call_result(&mut self, return_types: &[WpType]) {
+        if let Some(&ty) = return_types.first() {
+            let ret = self.push_new_value(ty);
+            let src = if ty.is_float() {
+                Location::NEON(NEON::V0)
+            } else {
+                Location::GPR(GPR::X0)
+            };
+            self.assembler.emit_mov(Size::S64, src, ret);
+        }
+    }
+
+    /// Returns the index that the memory libcalls expect for `memory_index`:
+    /// the local index for a locally defined memory, the global one otherwise.
+    fn memory_index_arg(&self, memory_index: MemoryIndex) -> u32 {
+        match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => local_memory_index.index() as u32,
+            None => memory_index.index() as u32,
+        }
+    }
+
+    /// Emits a memory operation.
+    fn emit_memory_op<F: FnOnce(&mut Self, GPR)>(
+        &mut self,
+        addr: Location,
+        memarg: &MemoryImmediate,
+        value_size: usize,
+        cb: F,
+    ) {
+        let memory_index = MemoryIndex::new(memarg.memory as usize);
+        let need_check = match self.memory_styles[memory_index] {
+            MemoryStyle::Static { .. } => false,
+            MemoryStyle::Dynamic { .. } => true,
+        };
+        let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
+
+        // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
+        let (base_loc, bound_loc) = if self.module.is_imported_memory(memory_index) {
+            // Imported memories require one level of indirection.
+            let offset = self
+                .vmoffsets
+                .vmctx_vmmemory_import_definition(memory_index);
+            self.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(MachineARM64::get_vmctx_reg(), offset as i32),
+                Location::GPR(tmp_addr),
+            );
+            (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
+        } else {
+            let local_memory_index = self.module.local_memory_index(memory_index).unwrap();
+            let offset = self.vmoffsets.vmctx_vmmemory_definition(local_memory_index);
+            (
+                Location::Memory(MachineARM64::get_vmctx_reg(), offset as i32),
+                Location::Memory(MachineARM64::get_vmctx_reg(), (offset + 8) as i32),
+            )
+        };
+
+        let tmp_base = self.machine.acquire_temp_gpr().unwrap();
+        let tmp_bound = self.machine.acquire_temp_gpr().unwrap();
+
+        // Load base into temporary register.
+        self.assembler
+            .emit_mov(Size::S64, base_loc, Location::GPR(tmp_base));
+
+        // Load bound into temporary register, if needed.
+        if need_check {
+            self.assembler
+                .emit_mov(Size::S64, bound_loc, Location::GPR(tmp_bound));
+
+            // The maximum allowed beginning of the word is (inclusively)
+            // `tmp_bound + tmp_base - value_size`.
+            self.assembler
+                .emit_add(Size::S64, tmp_bound, Location::GPR(tmp_base), tmp_bound);
+            self.assembler.emit_sub(
+                Size::S64,
+                tmp_bound,
+                Location::Imm32(value_size as u32),
+                tmp_bound,
+            );
+        }
+
+        // Load effective address.
+        // `base_loc` and `bound_loc` becomes INVALID after this line, because `tmp_addr`
+        // might be reused.
+        self.assembler
+            .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
+
+        // Add offset to memory address.
+        if memarg.offset != 0 {
+            self.assembler.emit_adds(
+                Size::S32,
+                tmp_addr,
+                Location::Imm32(memarg.offset),
+                tmp_addr,
+            );
+
+            // Trap if offset calculation overflowed.
+            self.assembler
+                .emit_bcond_label(Condition::Cs, self.special_labels.heap_access_oob);
+        }
+
+        // Wasm linear memory -> real memory
+        self.assembler
+            .emit_add(Size::S64, tmp_base, Location::GPR(tmp_addr), tmp_addr);
+
+        if need_check {
+            // `tmp_bound` is inclusive. So trap only if `tmp_addr > tmp_bound`.
+            self.assembler
+                .emit_cmp(Size::S64, tmp_addr, Location::GPR(tmp_bound));
+            self.assembler
+                .emit_bcond_label(Condition::Hi, self.special_labels.heap_access_oob);
+        }
+
+        self.machine.release_temp_gpr(tmp_bound);
+        self.machine.release_temp_gpr(tmp_base);
+
+        self.mark_range_with_trap_code(TrapCode::HeapAccessOutOfBounds, |this| cb(this, tmp_addr));
+
+        self.machine.release_temp_gpr(tmp_addr);
+    }
+
+    /// Emits a load of `sz_src` bytes from memory, extended to `ty`.
+    fn emit_load(&mut self, memarg: &MemoryImmediate, ty: WpType, sz_src: Size, signed: bool) {
+        let target = self.pop_value_released();
+        let ret = self.push_new_value(ty);
+        let sz_dst = match ty {
+            WpType::I32 | WpType::F32 => Size::S32,
+            _ => Size::S64,
+        };
+        self.emit_memory_op(target, memarg, size_in_bytes(sz_src), |this, addr| {
+            if sz_src == sz_dst {
+                this.assembler
+                    .emit_mov(sz_dst, Location::Memory(addr, 0), ret);
+            } else {
+                let dst = Self::gpr_for_result(ret, addr);
+                this.assembler
+                    .emit_load(sz_src, signed, sz_dst, Location::Memory(addr, 0), dst);
+                this.move_gpr_to(sz_dst, dst, ret);
+            }
+        });
+    }
+
+    /// Emits a store of the low `sz` bytes of a value to memory.
+    fn emit_store(&mut self, memarg: &MemoryImmediate, sz: Size, full_size: bool) {
+        let target_value = self.pop_value_released();
+        let target_addr = self.pop_value_released();
+        self.emit_memory_op(target_addr, memarg, size_in_bytes(sz), |this, addr| {
+            if full_size {
+                this.assembler
+                    .emit_mov(sz, target_value, Location::Memory(addr, 0));
+            } else {
+                let tmp = this.machine.acquire_temp_gpr().unwrap();
+                let src = this.location_to_gpr(Size::S64, target_value, tmp);
+                this.assembler
+                    .emit_store(sz, src, Location::Memory(addr, 0));
+                this.machine.release_temp_gpr(tmp);
+            }
+        });
+    }
+
+    /// Returns the location of the global at `global_index`.
+    ///
+    /// The pointer to the global is loaded into `tmp`.
+    fn global_location(&mut self, global_index: GlobalIndex, tmp: GPR) -> Location {
+        let offset = if let Some(local_global_index) = self.module.local_global_index(global_index)
+        {
+            self.vmoffsets.vmctx_vmglobal_definition(local_global_index)
+        } else {
+            // Imported globals require one level of indirection.
+            self.vmoffsets
+                .vmctx_vmglobal_import_definition(global_index)
+        };
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(MachineARM64::get_vmctx_reg(), offset as i32),
+            Location::GPR(tmp),
+        );
+        Location::Memory(tmp, 0)
+    }
+
+    /// Moves the value returned by a branch to `frame` to X0.
+    fn emit_branch_value(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        let frame = &self.control_stack[frame_index];
+        if !frame.loop_like && !frame.returns.is_empty() {
+            if frame.returns.len() != 1 {
+                return Err(CodegenError {
+                    message: "Br: incorrect frame.returns".to_string(),
+                });
+            }
+            let loc = *self.value_stack.last().unwrap();
+            self.assembler
+                .emit_mov(Size::S64, loc, Location::GPR(GPR::X0));
+        }
+        Ok(())
+    }
+
+    /// Emits a branch to the frame at `frame_index` in the control stack.
+    fn emit_branch(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        self.emit_branch_value(frame_index)?;
+        let frame = &self.control_stack[frame_index];
+        let released = &self.value_stack[frame.value_stack_depth..];
+        self.machine
+            .release_locations_keep_state(&mut self.assembler, released);
+        self.assembler.emit_b_label(frame.label);
+        Ok(())
+    }
+
+    fn block_returns(ty: WpTypeOrFuncType) -> Result<SmallVec<[WpType; 1]>, CodegenError> {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => Ok(smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => Ok(smallvec![inner_ty]),
+            _ => Err(CodegenError {
+                message: "multi-value blocks not yet implemented on AArch64".to_string(),
+            }),
+        }
+    }
+
+    fn emit_head(&mut self) -> Result<(), CodegenError> {
+        self.mark_range_with_trap_code(TrapCode::StackOverflow, |this| {
+            // Frame record.
+            this.assembler.emit_push_pair(GPR::X29, GPR::X30);
+            this.unwind_ops.push((
+                this.assembler.get_offset().0,
+                UnwindOp::PushFrameRecord { size: 16 },
+            ));
+            this.assembler.emit_mov_from_sp(GPR::X29);
+            this.unwind_ops
+                .push((this.assembler.get_offset().0, UnwindOp::DefineNewFrame));
+
+            // Initialize locals.
+            this.locals = this.machine.init_locals(
+                &mut this.assembler,
+                &this.local_types,
+                this.signature.params(),
+            );
+        });
+        self.unwind_ops.append(&mut self.machine.unwind_ops);
+
+        // Keep some space below the stack pointer, as values are read right
+        // after their stack slot is released.
+        self.assembler.emit_sub_sp(32);
+
+        self.control_stack.push(ControlFrame {
+            label: self.assembler.get_label(),
+            loop_like: false,
+            if_else: IfElseState::None,
+            returns: self
+                .signature
+                .results()
+                .iter()
+                .map(|&x| type_to_wp_type(x))
+                .collect(),
+            value_stack_depth: 0,
+        });
+
+        self.emit_stack_check();
+        self.emit_interrupt_checks();
+        Ok(())
+    }
+
+    /// Pushes the instruction to the address map, calculating the offset from a
+    /// provided beginning address.
+    fn mark_instruction_address_end(&mut self, begin: usize) {
+        self.instructions_address_map.push(InstructionAddressMap {
+            srcloc: SourceLoc::new(self.src_loc),
+            code_offset: begin,
+            code_len: self.assembler.get_offset().0 - begin,
+        });
+    }
+
+    pub fn new(
+        module: &'a ModuleInfo,
+        config: &'a Singlepass,
+        vmoffsets: &'a VMOffsets,
+        memory_styles: &'a PrimaryMap<MemoryIndex, MemoryStyle>,
+        _table_styles: &'a PrimaryMap<TableIndex, TableStyle>,
+        local_func_index: LocalFunctionIndex,
+        local_types_excluding_arguments: &[WpType],
+    ) -> Result<FuncGenARM64<'a>, CodegenError> {
+        let func_index = module.func_index(local_func_index);
+        let sig_index = module.functions[func_index];
+        let signature = module.signatures[sig_index].clone();
+
+        let mut local_types: Vec<_> = signature
+            .params()
+            .iter()
+            .map(|&x| type_to_wp_type(x))
+            .collect();
+        local_types.extend_from_slice(&local_types_excluding_arguments);
+
+        let mut assembler = Assembler::new().unwrap();
+        let special_labels = SpecialLabelSet {
+            integer_division_by_zero: assembler.get_label(),
+            integer_overflow: assembler.get_label(),
+            bad_conversion_to_integer: assembler.get_label(),
+            heap_access_oob: assembler.get_label(),
+            table_access_oob: assembler.get_label(),
+            indirect_call_null: assembler.get_label(),
+            bad_signature: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
+        };
+
+        let mut fg = FuncGenARM64 {
+            module,
+            config,
+            vmoffsets,
+            memory_styles,
+            signature,
+            assembler,
+            locals: vec![], // initialization deferred to emit_head
+            local_types,
+            value_stack: vec![],
+            control_stack: vec![],
+            machine: MachineARM64::new(),
+            unreachable_depth: 0,
+            trap_table: TrapTable::default(),
+            relocations: vec![],
+            special_labels,
+            src_loc: 0,
+            instructions_address_map: vec![],
+            unwind_ops: vec![],
+        };
+        fg.emit_head()?;
+        Ok(fg)
+    }
+
+    pub fn has_control_frames(&self) -> bool {
+        !self.control_stack.is_empty()
+    }
+
+    pub fn feed_operator(&mut self, op: Operator) -> Result<(), CodegenError> {
+        let was_unreachable;
+
+        if self.unreachable_depth > 0 {
+            was_unreachable = true;
+
+            match op {
+                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                    self.unreachable_depth += 1;
+                }
+                Operator::End => {
+                    self.unreachable_depth -= 1;
+                }
+                Operator::Else => {
+                    // We are in a reachable true branch
+                    if self.unreachable_depth == 1 {
+                        if let Some(IfElseState::If(_)) =
+                            self.control_stack.last().map(|x| x.if_else)
+                        {
+                            self.unreachable_depth -= 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if self.unreachable_depth > 0 {
+                return Ok(());
+            }
+        } else {
+            was_unreachable = false;
+        }
+
+        match op {
+            Operator::GlobalGet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+                let ty = type_to_wp_type(self.module.globals[global_index].ty);
+                let loc = self.push_new_value(ty);
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let src = self.global_location(global_index, tmp);
+                self.assembler.emit_mov(Size::S64, src, loc);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::GlobalSet { global_index } => {
+                let global_index = GlobalIndex::from_u32(global_index);
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let dst = self.global_location(global_index, tmp);
+                let loc = self.pop_value_released();
+                self.assembler.emit_mov(Size::S64, loc, dst);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::LocalGet { local_index } => {
+                let local_index = local_index as usize;
+                let ret = self.push_new_value(self.local_types[local_index]);
+                self.assembler
+                    .emit_mov(Size::S64, self.locals[local_index], ret);
+            }
+            Operator::LocalSet { local_index } => {
+                let local_index = local_index as usize;
+                let loc = self.pop_value_released();
+                self.assembler
+                    .emit_mov(Size::S64, loc, self.locals[local_index]);
+            }
+            Operator::LocalTee { local_index } => {
+                let local_index = local_index as usize;
+                let loc = *self.value_stack.last().unwrap();
+                self.assembler
+                    .emit_mov(Size::S64, loc, self.locals[local_index]);
+            }
+            Operator::I32Const { value } => {
+                self.value_stack.push(Location::Imm32(value as u32));
+            }
+            Operator::I32Add => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_add),
+            Operator::I32Sub => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_sub),
+            Operator::I32Mul => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_mul),
+            Operator::I32DivU => self.emit_div(WpType::I32, Size::S32, false, false),
+            Operator::I32DivS => self.emit_div(WpType::I32, Size::S32, true, false),
+            Operator::I32RemU => self.emit_div(WpType::I32, Size::S32, false, true),
+            Operator::I32RemS => self.emit_div(WpType::I32, Size::S32, true, true),
+            Operator::I32And => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_and),
+            Operator::I32Or => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_orr),
+            Operator::I32Xor => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_eor),
+            Operator::I32Eq => self.emit_cmpop(Size::S32, Condition::Eq),
+            Operator::I32Ne => self.emit_cmpop(Size::S32, Condition::Ne),
+            Operator::I32Eqz => {
+                self.value_stack.push(Location::Imm32(0));
+                self.emit_cmpop(Size::S32, Condition::Eq);
+            }
+            Operator::I32Clz => self.emit_unop(WpType::I32, Size::S32, Assembler::emit_clz),
+            Operator::I32Ctz => self.emit_unop(WpType::I32, Size::S32, emit_ctz),
+            Operator::I32Popcnt => self.emit_unop(WpType::I32, Size::S32, Assembler::emit_cnt),
+            Operator::I32Shl => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_lsl),
+            Operator::I32ShrU => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_lsr),
+            Operator::I32ShrS => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_asr),
+            Operator::I32Rotl => self.emit_rotl(WpType::I32, Size::S32),
+            Operator::I32Rotr => self.emit_binop(WpType::I32, Size::S32, Assembler::emit_ror),
+            Operator::I32LtU => self.emit_cmpop(Size::S32, Condition::Cc),
+            Operator::I32LeU => self.emit_cmpop(Size::S32, Condition::Ls),
+            Operator::I32GtU => self.emit_cmpop(Size::S32, Condition::Hi),
+            Operator::I32GeU => self.emit_cmpop(Size::S32, Condition::Cs),
+            Operator::I32LtS => self.emit_cmpop(Size::S32, Condition::Lt),
+            Operator::I32LeS => self.emit_cmpop(Size::S32, Condition::Le),
+            Operator::I32GtS => self.emit_cmpop(Size::S32, Condition::Gt),
+            Operator::I32GeS => self.emit_cmpop(Size::S32, Condition::Ge),
+            Operator::I64Const { value } => {
+                self.value_stack.push(Location::Imm64(value as u64));
+            }
+            Operator::I64Add => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_add),
+            Operator::I64Sub => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_sub),
+            Operator::I64Mul => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_mul),
+            Operator::I64DivU => self.emit_div(WpType::I64, Size::S64, false, false),
+            Operator::I64DivS => self.emit_div(WpType::I64, Size::S64, true, false),
+            Operator::I64RemU => self.emit_div(WpType::I64, Size::S64, false, true),
+            Operator::I64RemS => self.emit_div(WpType::I64, Size::S64, true, true),
+            Operator::I64And => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_and),
+            Operator::I64Or => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_orr),
+            Operator::I64Xor => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_eor),
+            Operator::I64Eq => self.emit_cmpop(Size::S64, Condition::Eq),
+            Operator::I64Ne => self.emit_cmpop(Size::S64, Condition::Ne),
+            Operator::I64Eqz => {
+                self.value_stack.push(Location::Imm64(0));
+                self.emit_cmpop(Size::S64, Condition::Eq);
+            }
+            Operator::I64Clz => self.emit_unop(WpType::I64, Size::S64, Assembler::emit_clz),
+            Operator::I64Ctz => self.emit_unop(WpType::I64, Size::S64, emit_ctz),
+            Operator::I64Popcnt => self.emit_unop(WpType::I64, Size::S64, Assembler::emit_cnt),
+            Operator::I64Shl => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_lsl),
+            Operator::I64ShrU => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_lsr),
+            Operator::I64ShrS => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_asr),
+            Operator::I64Rotl => self.emit_rotl(WpType::I64, Size::S64),
+            Operator::I64Rotr => self.emit_binop(WpType::I64, Size::S64, Assembler::emit_ror),
+            Operator::I64LtU => self.emit_cmpop(Size::S64, Condition::Cc),
+            Operator::I64LeU => self.emit_cmpop(Size::S64, Condition::Ls),
+            Operator::I64GtU => self.emit_cmpop(Size::S64, Condition::Hi),
+            Operator::I64GeU => self.emit_cmpop(Size::S64, Condition::Cs),
+            Operator::I64LtS => self.emit_cmpop(Size::S64, Condition::Lt),
+            Operator::I64LeS => self.emit_cmpop(Size::S64, Condition::Le),
+            Operator::I64GtS => self.emit_cmpop(Size::S64, Condition::Gt),
+            Operator::I64GeS => self.emit_cmpop(Size::S64, Condition::Ge),
+            Operator::I64ExtendI32U | Operator::I32WrapI64 => {
+                let ty = match op {
+                    Operator::I64ExtendI32U => WpType::I64,
+                    _ => WpType::I32,
+                };
+                let loc = self.pop_value_released();
+                let ret = self.push_new_value(ty);
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                // A 32-bit move zeroes the upper half of the register.
+                let dst = Self::gpr_for_result(ret, tmp);
+                self.assembler.emit_mov(Size::S32, loc, Location::GPR(dst));
+                self.move_gpr_to(Size::S64, dst, ret);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::I64ExtendI32S => self.emit_unop(WpType::I64, Size::S64, |a, _, src, dst| {
+                a.emit_sext(Size::S32, Size::S64, src, dst)
+            }),
+            Operator::I32Extend8S => self.emit_unop(WpType::I32, Size::S32, |a, _, src, dst| {
+                a.emit_sext(Size::S8, Size::S32, src, dst)
+            }),
+            Operator::I32Extend16S => self.emit_unop(WpType::I32, Size::S32, |a, _, src, dst| {
+                a.emit_sext(Size::S16, Size::S32, src, dst)
+            }),
+            Operator::I64Extend8S => self.emit_unop(WpType::I64, Size::S64, |a, _, src, dst| {
+                a.emit_sext(Size::S8, Size::S64, src, dst)
+            }),
+            Operator::I64Extend16S => self.emit_unop(WpType::I64, Size::S64, |a, _, src, dst| {
+                a.emit_sext(Size::S16, Size::S64, src, dst)
+            }),
+            Operator::I64Extend32S => self.emit_unop(WpType::I64, Size::S64, |a, _, src, dst| {
+                a.emit_sext(Size::S32, Size::S64, src, dst)
+            }),
+
+            Operator::F32Const { value } => {
+                self.value_stack.push(Location::Imm32(value.bits()));
+            }
+            Operator::F32Add => self.emit_fp_binop(WpType::F32, Assembler::emit_fadd),
+            Operator::F32Sub => self.emit_fp_binop(WpType::F32, Assembler::emit_fsub),
+            Operator::F32Mul => self.emit_fp_binop(WpType::F32, Assembler::emit_fmul),
+            Operator::F32Div => self.emit_fp_binop(WpType::F32, Assembler::emit_fdiv),
+            Operator::F32Max => self.emit_fp_binop(WpType::F32, Assembler::emit_fmax),
+            Operator::F32Min => self.emit_fp_binop(WpType::F32, Assembler::emit_fmin),
+            Operator::F32Eq => self.emit_fp_cmpop(WpType::F32, Condition::Eq),
+            Operator::F32Ne => self.emit_fp_cmpop(WpType::F32, Condition::Ne),
+            Operator::F32Lt => self.emit_fp_cmpop(WpType::F32, Condition::Mi),
+            Operator::F32Le => self.emit_fp_cmpop(WpType::F32, Condition::Ls),
+            Operator::F32Gt => self.emit_fp_cmpop(WpType::F32, Condition::Gt),
+            Operator::F32Ge => self.emit_fp_cmpop(WpType::F32, Condition::Ge),
+            Operator::F32Nearest => self.emit_fp_unop(WpType::F32, Assembler::emit_frintn, true),
+            Operator::F32Floor => self.emit_fp_unop(WpType::F32, Assembler::emit_frintm, true),
+            Operator::F32Ceil => self.emit_fp_unop(WpType::F32, Assembler::emit_frintp, true),
+            Operator::F32Trunc => self.emit_fp_unop(WpType::F32, Assembler::emit_frintz, true),
+            Operator::F32Sqrt => self.emit_fp_unop(WpType::F32, Assembler::emit_fsqrt, true),
+            Operator::F32Copysign => self.emit_fp_copysign(WpType::F32),
+            Operator::F32Abs => self.emit_fp_unop(WpType::F32, Assembler::emit_fabs, false),
+            Operator::F32Neg => self.emit_fp_unop(WpType::F32, Assembler::emit_fneg, false),
+
+            Operator::F64Const { value } => {
+                self.value_stack.push(Location::Imm64(value.bits()));
+            }
+            Operator::F64Add => self.emit_fp_binop(WpType::F64, Assembler::emit_fadd),
+            Operator::F64Sub => self.emit_fp_binop(WpType::F64, Assembler::emit_fsub),
+            Operator::F64Mul => self.emit_fp_binop(WpType::F64, Assembler::emit_fmul),
+            Operator::F64Div => self.emit_fp_binop(WpType::F64, Assembler::emit_fdiv),
+            Operator::F64Max => self.emit_fp_binop(WpType::F64, Assembler::emit_fmax),
+            Operator::F64Min => self.emit_fp_binop(WpType::F64, Assembler::emit_fmin),
+            Operator::F64Eq => self.emit_fp_cmpop(WpType::F64, Condition::Eq),
+            Operator::F64Ne => self.emit_fp_cmpop(WpType::F64, Condition::Ne),
+            Operator::F64Lt => self.emit_fp_cmpop(WpType::F64, Condition::Mi),
+            Operator::F64Le => self.emit_fp_cmpop(WpType::F64, Condition::Ls),
+            Operator::F64Gt => self.emit_fp_cmpop(WpType::F64, Condition::Gt),
+            Operator::F64Ge => self.emit_fp_cmpop(WpType::F64, Condition::Ge),
+            Operator::F64Nearest => self.emit_fp_unop(WpType::F64, Assembler::emit_frintn, true),
+            Operator::F64Floor => self.emit_fp_unop(WpType::F64, Assembler::emit_frintm, true),
+            Operator::F64Ceil => self.emit_fp_unop(WpType::F64, Assembler::emit_frintp, true),
+            Operator::F64Trunc => self.emit_fp_unop(WpType::F64, Assembler::emit_frintz, true),
+            Operator::F64Sqrt => self.emit_fp_unop(WpType::F64, Assembler::emit_fsqrt, true),
+            Operator::F64Copysign => self.emit_fp_copysign(WpType::F64),
+            Operator::F64Abs => self.emit_fp_unop(WpType::F64, Assembler::emit_fabs, false),
+            Operator::F64Neg => self.emit_fp_unop(WpType::F64, Assembler::emit_fneg, false),
+
+            Operator::F64PromoteF32 | Operator::F32DemoteF64 => {
+                let (sz_src, ty) = match op {
+                    Operator::F64PromoteF32 => (Size::S32, WpType::F64),
+                    _ => (Size::S64, WpType::F32),
+                };
+                let loc = self.pop_value_released();
+                let ret = self.push_new_value(ty);
+                let tmp = self.machine.acquire_temp_neon().unwrap();
+                let src = self.location_to_neon(sz_src, loc, tmp);
+                let dst = Self::neon_for_result(ret, tmp);
+                self.assembler.emit_fcvt(sz_src, src, dst);
+                if self.config.enable_nan_canonicalization {
+                    self.canonicalize_nan(fp_size(ty), dst);
+                }
+                self.move_neon_to(fp_size(ty), dst, ret);
+                self.machine.release_temp_neon(tmp);
+            }
+            Operator::I32ReinterpretF32
+            | Operator::F32ReinterpretI32
+            | Operator::I64ReinterpretF64
+            | Operator::F64ReinterpretI64 => {
+                let (sz, ty) = match op {
+                    Operator::I32ReinterpretF32 => (Size::S32, WpType::I32),
+                    Operator::F32ReinterpretI32 => (Size::S32, WpType::F32),
+                    Operator::I64ReinterpretF64 => (Size::S64, WpType::I64),
+                    _ => (Size::S64, WpType::F64),
+                };
+                let loc = self.pop_value_released();
+                let ret = self.push_new_value(ty);
+                if loc != ret {
+                    self.assembler.emit_mov(sz, loc, ret);
+                }
+            }
+
+            Operator::I32TruncF32U => self.emit_fp_to_int(
+                WpType::F32,
+                Size::S32,
+                false,
+                Some(f32_bounds(GEF32_LT_U32_MIN, LEF32_GT_U32_MAX)),
+            ),
+            Operator::I32TruncF32S => self.emit_fp_to_int(
+                WpType::F32,
+                Size::S32,
+                true,
+                Some(f32_bounds(GEF32_LT_I32_MIN, LEF32_GT_I32_MAX)),
+            ),
+            Operator::I64TruncF32U => self.emit_fp_to_int(
+                WpType::F32,
+                Size::S64,
+                false,
+                Some(f32_bounds(GEF32_LT_U64_MIN, LEF32_GT_U64_MAX)),
+            ),
+            Operator::I64TruncF32S => self.emit_fp_to_int(
+                WpType::F32,
+                Size::S64,
+                true,
+                Some(f32_bounds(GEF32_LT_I64_MIN, LEF32_GT_I64_MAX)),
+            ),
+            Operator::I32TruncF64U => self.emit_fp_to_int(
+                WpType::F64,
+                Size::S32,
+                false,
+                Some(f64_bounds(GEF64_LT_U32_MIN, LEF64_GT_U32_MAX)),
+            ),
+            Operator::I32TruncF64S => self.emit_fp_to_int(
+                WpType::F64,
+                Size::S32,
+                true,
+                Some(f64_bounds(GEF64_LT_I32_MIN, LEF64_GT_I32_MAX)),
+            ),
+            Operator::I64TruncF64U => self.emit_fp_to_int(
+                WpType::F64,
+                Size::S64,
+                false,
+                Some(f64_bounds(GEF64_LT_U64_MIN, LEF64_GT_U64_MAX)),
+            ),
+            Operator::I64TruncF64S => self.emit_fp_to_int(
+                WpType::F64,
+                Size::S64,
+                true,
+                Some(f64_bounds(GEF64_LT_I64_MIN, LEF64_GT_I64_MAX)),
+            ),
+            Operator::I32TruncSatF32U => self.emit_fp_to_int(WpType::F32, Size::S32, false, None),
+            Operator::I32TruncSatF32S => self.emit_fp_to_int(WpType::F32, Size::S32, true, None),
+            Operator::I64TruncSatF32U => self.emit_fp_to_int(WpType::F32, Size::S64, false, None),
+            Operator::I64TruncSatF32S => self.emit_fp_to_int(WpType::F32, Size::S64, true, None),
+            Operator::I32TruncSatF64U => self.emit_fp_to_int(WpType::F64, Size::S32, false, None),
+            Operator::I32TruncSatF64S => self.emit_fp_to_int(WpType::F64, Size::S32, true, None),
+            Operator::I64TruncSatF64U => self.emit_fp_to_int(WpType::F64, Size::S64, false, None),
+            Operator::I64TruncSatF64S => self.emit_fp_to_int(WpType::F64, Size::S64, true, None),
+
+            Operator::F32ConvertI32S => self.emit_int_to_fp(Size::S32, true, WpType::F32),
+            Operator::F32ConvertI32U => self.emit_int_to_fp(Size::S32, false, WpType::F32),
+            Operator::F32ConvertI64S => self.emit_int_to_fp(Size::S64, true, WpType::F32),
+            Operator::F32ConvertI64U => self.emit_int_to_fp(Size::S64, false, WpType::F32),
+            Operator::F64ConvertI32S => self.emit_int_to_fp(Size::S32, true, WpType::F64),
+            Operator::F64ConvertI32U => self.emit_int_to_fp(Size::S32, false, WpType::F64),
+            Operator::F64ConvertI64S => self.emit_int_to_fp(Size::S64, true, WpType::F64),
+            Operator::F64ConvertI64U => self.emit_int_to_fp(Size::S64, false, WpType::F64),
+
+            Operator::Call { function_index } => {
+                let function_index = function_index as usize;
+
+                let sig_index = *self
+                    .module
+                    .functions
+                    .get(FunctionIndex::new(function_index))
+                    .unwrap();
+                let sig = self.module.signatures.get(sig_index).unwrap();
+                let param_types: SmallVec<[Type; 8]> = sig.params().iter().cloned().collect();
+                let return_types: SmallVec<[WpType; 1]> =
+                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+
+                let params: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - param_types.len()..)
+                    .collect();
+                self.machine.release_locations_only_regs(&params);
+
+                // Imported functions are called through trampolines placed as custom sections.
+                let reloc_target = if function_index < self.module.num_imported_functions {
+                    RelocationTarget::CustomSection(SectionIndex::new(function_index))
+                } else {
+                    RelocationTarget::LocalFunc(LocalFunctionIndex::new(
+                        function_index - self.module.num_imported_functions,
+                    ))
+                };
+
+                self.emit_call_aapcs64(
+                    |this| {
+                        // The address is relocated by the JIT linker.
+                        let reloc_at = this.assembler.emit_ldr_literal(std::u64::MAX, GPR::X16);
+                        this.relocations.push(Relocation {
+                            kind: RelocationKind::Abs8,
+                            reloc_target,
+                            offset: reloc_at as u32,
+                            addend: 0,
+                        });
+                        this.emit_call_register_with_trap(GPR::X16);
+                    },
+                    &params,
+                    &param_types,
+                );
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &params);
+                self.push_call_result(&return_types);
+            }
+            Operator::CallIndirect { index, table_index } => {
+                if table_index != 0 {
+                    return Err(CodegenError {
+                        message: "CallIndirect: table_index is not 0".to_string(),
+                    });
+                }
+                let table_index = TableIndex::new(table_index as _);
+                let index = SignatureIndex::new(index as usize);
+                let sig = self.module.signatures.get(index).unwrap();
+                let param_types: SmallVec<[Type; 8]> = sig.params().iter().cloned().collect();
+                let return_types: SmallVec<[WpType; 1]> =
+                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+
+                let func_index = self.pop_value_released();
+
+                let params: SmallVec<[_; 8]> = self
+                    .value_stack
+                    .drain(self.value_stack.len() - param_types.len()..)
+                    .collect();
+                self.machine.release_locations_only_regs(&params);
+
+                // The address of the table entry is kept in X9 until the call.
+                let table_base = self.machine.reserve_unused_temp_gpr(GPR::X9);
+                let table_count = self.machine.acquire_temp_gpr().unwrap();
+                let sigidx = self.machine.acquire_temp_gpr().unwrap();
+
+                let vmctx = MachineARM64::get_vmctx_reg();
+                if let Some(local_table_index) = self.module.local_table_index(table_index) {
+                    let (vmctx_offset_base, vmctx_offset_len) = (
+                        self.vmoffsets.vmctx_vmtable_definition(local_table_index),
+                        self.vmoffsets
+                            .vmctx_vmtable_definition_current_elements(local_table_index),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(vmctx, vmctx_offset_base as i32),
+                        Location::GPR(table_base),
+                    );
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(vmctx, vmctx_offset_len as i32),
+                        Location::GPR(table_count),
+                    );
+                } else {
+                    // Do an indirection.
+                    let import_offset = self.vmoffsets.vmctx_vmtable_import(table_index);
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(vmctx, import_offset as i32),
+                        Location::GPR(table_base),
+                    );
+
+                    // Load len.
+                    self.assembler.emit_mov(
+                        Size::S32,
+                        Location::Memory(
+                            table_base,
+                            self.vmoffsets.vmtable_definition_current_elements() as _,
+                        ),
+                        Location::GPR(table_count),
+                    );
+
+                    // Load base.
+                    self.assembler.emit_mov(
+                        Size::S64,
+                        Location::Memory(table_base, self.vmoffsets.vmtable_definition_base() as _),
+                        Location::GPR(table_base),
+                    );
+                }
+
+                self.assembler
+                    .emit_mov(Size::S32, func_index, Location::GPR(sigidx));
+                self.assembler
+                    .emit_cmp(Size::S32, sigidx, Location::GPR(table_count));
+                self.assembler
+                    .emit_bcond_label(Condition::Cs, self.special_labels.table_access_oob);
+                self.assembler.emit_mov_imm(
+                    Size::S64,
+                    self.vmoffsets.size_of_vmcaller_checked_anyfunc() as u64,
+                    table_count,
+                );
+                self.assembler
+                    .emit_mul(Size::S64, sigidx, Location::GPR(table_count), table_count);
+                self.assembler.emit_add(
+                    Size::S64,
+                    table_base,
+                    Location::GPR(table_count),
+                    table_base,
+                );
+
+                // Trap if the current table entry is null.
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Memory(
+                        table_base,
+                        self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as i32,
+                    ),
+                    Location::GPR(table_count),
+                );
+                self.assembler.emit_cbz_label(
+                    Size::S64,
+                    table_count,
+                    self.special_labels.indirect_call_null,
+                );
+
+                // Trap if signature mismatches.
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Memory(
+                        vmctx,
+                        self.vmoffsets.vmctx_vmshared_signature_id(index) as i32,
+                    ),
+                    Location::GPR(sigidx),
+                );
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Memory(
+                        table_base,
+                        self.vmoffsets.vmcaller_checked_anyfunc_type_index() as i32,
+                    ),
+                    Location::GPR(table_count),
+                );
+                self.assembler
+                    .emit_cmp(Size::S32, table_count, Location::GPR(sigidx));
+                self.assembler
+                    .emit_bcond_label(Condition::Ne, self.special_labels.bad_signature);
+
+                self.machine.release_temp_gpr(sigidx);
+                self.machine.release_temp_gpr(table_count);
+
+                let vmcaller_checked_anyfunc_func_ptr =
+                    self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as i32;
+                let vmcaller_checked_anyfunc_vmctx =
+                    self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as i32;
+
+                self.emit_call_aapcs64(
+                    |this| {
+                        // The callee may belong to another instance.
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(table_base, vmcaller_checked_anyfunc_vmctx),
+                            Location::GPR(GPR::X0),
+                        );
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(table_base, vmcaller_checked_anyfunc_func_ptr),
+                            Location::GPR(GPR::X16),
+                        );
+                        this.emit_call_register_with_trap(GPR::X16);
+                    },
+                    &params,
+                    &param_types,
+                );
+                self.machine.release_temp_gpr(table_base);
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &params);
+                self.push_call_result(&return_types);
+            }
+            Operator::If { ty } => {
+                let label_end = self.assembler.get_label();
+                let label_else = self.assembler.get_label();
+
+                let cond = self.pop_value_released();
+
+                let frame = ControlFrame {
+                    label: label_end,
+                    loop_like: false,
+                    if_else: IfElseState::If(label_else),
+                    returns: Self::block_returns(ty)?,
+                    value_stack_depth: self.value_stack.len(),
+                };
+                self.control_stack.push(frame);
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let cond = self.location_to_gpr(Size::S32, cond, tmp);
+                self.assembler.emit_cbz_label(Size::S32, cond, label_else);
+                self.machine.release_temp_gpr(tmp);
+            }
+            Operator::Else => {
+                let frame = self.control_stack.last_mut().unwrap();
+
+                if !was_unreachable && !frame.returns.is_empty() {
+                    let loc = *self.value_stack.last().unwrap();
+                    self.assembler
+                        .emit_mov(Size::S64, loc, Location::GPR(GPR::X0));
+                }
+
+                let frame = self.control_stack.last_mut().unwrap();
+
+                let released: &[Location] = &self.value_stack[frame.value_stack_depth..];
+                self.machine
+                    .release_locations(&mut self.assembler, released);
+                self.value_stack.truncate(frame.value_stack_depth);
+
+                match frame.if_else {
+                    IfElseState::If(label) => {
+                        self.assembler.emit_b_label(frame.label);
+                        self.assembler.emit_label(label);
+                        frame.if_else = IfElseState::Else;
+                    }
+                    _ => {
+                        return Err(CodegenError {
+                            message: "Else: frame.if_else unreachable code".to_string(),
+                        })
+                    }
+                }
+            }
+            Operator::Select => {
+                let cond = self.pop_value_released();
+                let v_b = self.pop_value_released();
+                let v_a = self.pop_value_released();
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let cond = self.location_to_gpr(Size::S32, cond, tmp);
+                let ret = self.push_new_value(WpType::I64);
+
+                let end_label = self.assembler.get_label();
+                let zero_label = self.assembler.get_label();
+
+                self.assembler.emit_cbz_label(Size::S32, cond, zero_label);
+                self.machine.release_temp_gpr(tmp);
+                if v_a != ret {
+                    self.assembler.emit_mov(Size::S64, v_a, ret);
+                }
+                self.assembler.emit_b_label(end_label);
+                self.assembler.emit_label(zero_label);
+                if v_b != ret {
+                    self.assembler.emit_mov(Size::S64, v_b, ret);
+                }
+                self.assembler.emit_label(end_label);
+            }
+            Operator::Block { ty } => {
+                let frame = ControlFrame {
+                    label: self.assembler.get_label(),
+                    loop_like: false,
+                    if_else: IfElseState::None,
+                    returns: Self::block_returns(ty)?,
+                    value_stack_depth: self.value_stack.len(),
+                };
+                self.control_stack.push(frame);
+            }
+            Operator::Loop { ty } => {
+                let label = self.assembler.get_label();
+                self.control_stack.push(ControlFrame {
+                    label,
+                    loop_like: true,
+                    if_else: IfElseState::None,
+                    returns: Self::block_returns(ty)?,
+                    value_stack_depth: self.value_stack.len(),
+                });
+                self.assembler.emit_label(label);
+
+                self.emit_interrupt_checks();
+            }
+            Operator::Nop => {}
+            Operator::MemorySize { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                // Local memories are addressed by their local index.
+                let memory_index_arg = self.memory_index_arg(memory_index);
+                let func_offset = self.vmoffsets.vmctx_builtin_function(
+                    if self.module.local_memory_index(memory_index).is_some() {
+                        VMBuiltinFunctionIndex::get_memory32_size_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_memory32_size_index()
+                    },
+                ) as i32;
+                self.emit_call_aapcs64(
+                    |this| {
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(MachineARM64::get_vmctx_reg(), func_offset),
+                            Location::GPR(GPR::X16),
+                        );
+                        this.assembler.emit_call_register(GPR::X16);
+                    },
+                    // [vmctx, memory_index]
+                    &[Location::Imm32(memory_index_arg)],
+                    &[Type::I32],
+                );
+                self.push_call_result(&[WpType::I32]);
+            }
+            Operator::MemoryGrow { mem, mem_byte: _ } => {
+                let memory_index = MemoryIndex::new(mem as usize);
+                // Local memories are addressed by their local index.
+                let memory_index_arg = self.memory_index_arg(memory_index);
+                let param_pages = self.value_stack.pop().unwrap();
+
+                self.machine.release_locations_only_regs(&[param_pages]);
+
+                let func_offset = self.vmoffsets.vmctx_builtin_function(
+                    if self.module.local_memory_index(memory_index).is_some() {
+                        VMBuiltinFunctionIndex::get_memory32_grow_index()
+                    } else {
+                        VMBuiltinFunctionIndex::get_imported_memory32_grow_index()
+                    },
+                ) as i32;
+                self.emit_call_aapcs64(
+                    |this| {
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(MachineARM64::get_vmctx_reg(), func_offset),
+                            Location::GPR(GPR::X16),
+                        );
+                        this.assembler.emit_call_register(GPR::X16);
+                    },
+                    // [vmctx, val, memory_index]
+                    &[param_pages, Location::Imm32(memory_index_arg)],
+                    &[Type::I32, Type::I32],
+                );
+
+                self.machine
+                    .release_locations_only_stack(&mut self.assembler, &[param_pages]);
+                self.push_call_result(&[WpType::I32]);
+            }
+            Operator::I32Load { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S32, false)
+            }
+            Operator::F32Load { ref memarg } => {
+                self.emit_load(memarg, WpType::F32, Size::S32, false)
+            }
+            Operator::I32Load8U { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S8, false)
+            }
+            Operator::I32Load8S { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S8, true)
+            }
+            Operator::I32Load16U { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S16, false)
+            }
+            Operator::I32Load16S { ref memarg } => {
+                self.emit_load(memarg, WpType::I32, Size::S16, true)
+            }
+            Operator::I32Store { ref memarg } => self.emit_store(memarg, Size::S32, true),
+            Operator::F32Store { ref memarg } => self.emit_store(memarg, Size::S32, true),
+            Operator::I32Store8 { ref memarg } => self.emit_store(memarg, Size::S8, false),
+            Operator::I32Store16 { ref memarg } => self.emit_store(memarg, Size::S16, false),
+            Operator::I64Load { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S64, false)
+            }
+            Operator::F64Load { ref memarg } => {
+                self.emit_load(memarg, WpType::F64, Size::S64, false)
+            }
+            Operator::I64Load8U { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S8, false)
+            }
+            Operator::I64Load8S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S8, true)
+            }
+            Operator::I64Load16U { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S16, false)
+            }
+            Operator::I64Load16S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S16, true)
+            }
+            Operator::I64Load32U { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S32, false)
+            }
+            Operator::I64Load32S { ref memarg } => {
+                self.emit_load(memarg, WpType::I64, Size::S32, true)
+            }
+            Operator::I64Store { ref memarg } => self.emit_store(memarg, Size::S64, true),
+            Operator::F64Store { ref memarg } => self.emit_store(memarg, Size::S64, true),
+            Operator::I64Store8 { ref memarg } => self.emit_store(memarg, Size::S8, false),
+            Operator::I64Store16 { ref memarg } => self.emit_store(memarg, Size::S16, false),
+            Operator::I64Store32 { ref memarg } => self.emit_store(memarg, Size::S32, false),
+            Operator::Unreachable => {
+                self.mark_address_with_trap_code(TrapCode::UnreachableCodeReached);
+                self.assembler.emit_udf();
+                self.unreachable_depth = 1;
+            }
+            Operator::Return => {
+                let frame = &self.control_stack[0];
+                if frame.returns.len() > 1 {
+                    return Err(CodegenError {
+                        message: "Return: incorrect frame.returns".to_string(),
+                    });
+                }
+                self.emit_branch(0)?;
+                self.unreachable_depth = 1;
+            }
+            Operator::Br { relative_depth } => {
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
+                self.unreachable_depth = 1;
+            }
+            Operator::BrIf { relative_depth } => {
+                let after = self.assembler.get_label();
+                let cond = self.pop_value_released();
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let cond = self.location_to_gpr(Size::S32, cond, tmp);
+                self.assembler.emit_cbz_label(Size::S32, cond, after);
+                self.machine.release_temp_gpr(tmp);
+
+                self.emit_branch(self.control_stack.len() - 1 - (relative_depth as usize))?;
+
+                self.assembler.emit_label(after);
+            }
+            Operator::BrTable { ref table } => {
+                let mut targets = table
+                    .targets()
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| CodegenError {
+                        message: format!("BrTable read_table: {:?}", e),
+                    })?;
+                let default_target = targets.pop().unwrap().0;
+                let cond = self.pop_value_released();
+                let table_label = self.assembler.get_label();
+                let mut table: Vec<DynamicLabel> = vec![];
+                let default_br = self.assembler.get_label();
+
+                let tmp_cond = self.machine.acquire_temp_gpr().unwrap();
+                let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
+                self.assembler
+                    .emit_mov(Size::S32, cond, Location::GPR(tmp_cond));
+                self.assembler
+                    .emit_cmp(Size::S32, tmp_cond, Location::Imm32(targets.len() as u32));
+                self.assembler.emit_bcond_label(Condition::Cs, default_br);
+
+                // Every entry of the table is a 4-byte branch instruction.
+                self.assembler.emit_adr_label(table_label, tmp_addr);
+                self.assembler.emit_add_lsl(tmp_addr, tmp_cond, 2, tmp_addr);
+                self.assembler.emit_b_register(tmp_addr);
+                self.machine.release_temp_gpr(tmp_addr);
+                self.machine.release_temp_gpr(tmp_cond);
+
+                for (target, _) in targets.iter() {
+                    let label = self.assembler.get_label();
+                    self.assembler.emit_label(label);
+                    table.push(label);
+                    self.emit_branch(self.control_stack.len() - 1 - (*target as usize))?;
+                }
+                self.assembler.emit_label(default_br);
+                self.emit_branch(self.control_stack.len() - 1 - (default_target as usize))?;
+
+                self.assembler.emit_label(table_label);
+                for x in table {
+                    self.assembler.emit_b_label(x);
+                }
+                self.unreachable_depth = 1;
+            }
+            Operator::Drop => {
+                self.pop_value_released();
+            }
+            Operator::End => {
+                let frame = self.control_stack.pop().unwrap();
+
+                if !was_unreachable && !frame.returns.is_empty() {
+                    let loc = *self.value_stack.last().unwrap();
+                    self.assembler
+                        .emit_mov(Size::S64, loc, Location::GPR(GPR::X0));
+                }
+
+                if self.control_stack.is_empty() {
+                    self.assembler.emit_label(frame.label);
+
+                    // Make a copy of the return value in V0, as required by the AAPCS64.
+                    match self.signature.results() {
+                        [x] if *x == Type::F32 || *x == Type::F64 => {
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::X0),
+                                Location::NEON(NEON::V0),
+                            );
+                        }
+                        _ => {}
+                    }
+
+                    self.machine.finalize_locals(&mut self.assembler);
+                    self.assembler.emit_mov_to_sp(GPR::X29);
+                    self.assembler.emit_pop_pair(GPR::X29, GPR::X30);
+                    self.assembler.emit_ret();
+                } else {
+                    let released = &self.value_stack[frame.value_stack_depth..];
+                    self.machine
+                        .release_locations(&mut self.assembler, released);
+                    self.value_stack.truncate(frame.value_stack_depth);
+
+                    if !frame.loop_like {
+                        self.assembler.emit_label(frame.label);
+                    }
+
+                    if let IfElseState::If(label) = frame.if_else {
+                        self.assembler.emit_label(label);
+                    }
+
+                    if !frame.returns.is_empty() {
+                        if frame.returns.len() != 1 {
+                            return Err(CodegenError {
+                                message: "End: incorrect frame.returns".to_string(),
+                            });
+                        }
+                        let loc = self.push_new_value(frame.returns[0]);
+                        self.assembler
+                            .emit_mov(Size::S64, Location::GPR(GPR::X0), loc);
+                    }
+                }
+            }
+            _ => {
+                return Err(CodegenError {
+                    message: format!("not yet implemented on AArch64: {:?}", op),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finalizes the function, returning it with the operations needed to
+    /// unwind its frame.
+    pub fn finalize(
+        mut self,
+        data: &FunctionBodyData,
+    ) -> (CompiledFunction, Vec<(usize, UnwindOp)>) {
+        // Generate actual code for special labels.
+        let special_labels = [
+            (
+                self.special_labels.integer_division_by_zero,
+                TrapCode::IntegerDivisionByZero,
+            ),
+            (
+                self.special_labels.integer_overflow,
+                TrapCode::IntegerOverflow,
+            ),
+            (
+                self.special_labels.bad_conversion_to_integer,
+                TrapCode::BadConversionToInteger,
+            ),
+            (
+                self.special_labels.heap_access_oob,
+                TrapCode::HeapAccessOutOfBounds,
+            ),
+            (
+                self.special_labels.table_access_oob,
+                TrapCode::TableAccessOutOfBounds,
+            ),
+            (
+                self.special_labels.indirect_call_null,
+                TrapCode::IndirectCallToNull,
+            ),
+            (self.special_labels.bad_signature, TrapCode::BadSignature),
+            (self.special_labels.stack_overflow, TrapCode::StackOverflow),
+        ];
+        for (label, code) in special_labels.iter() {
+            self.assembler.emit_label(*label);
+            self.mark_address_with_trap_code(*code);
+            self.assembler.emit_udf();
+        }
+
+        let body_len = self.assembler.get_offset().0;
+        let instructions_address_map = self.instructions_address_map;
+        let address_map = get_function_address_map(instructions_address_map, data, body_len);
+
+        let function = CompiledFunction {
+            body: FunctionBody {
+                body: self.assembler.finalize().unwrap().to_vec(),
+                unwind_info: None,
+            },
+            relocations: self.relocations,
+            jt_offsets: SecondaryMap::new(),
+            frame_info: CompiledFunctionFrameInfo {
+                traps: self
+                    .trap_table
+                    .offset_to_code
+                    .into_iter()
+                    .map(|(offset, code)| TrapInformation {
+                        code_offset: offset as u32,
+                        trap_code: code,
+                    })
+                    .collect(),
+                address_map,
+            },
+        };
+        (function, self.unwind_ops)
+    }
+}
+
+/// Counts the trailing zeros of `src`, by counting the leading zeros of its
+/// reversed bits.
+fn emit_ctz(a: &mut Assembler, sz: Size, src: GPR, dst: GPR) {
+    a.emit_rbit(sz, src, dst);
+    a.emit_clz(sz, dst, dst);
+}
+
+fn fp_size(ty: WpType) -> Size {
+    match ty {
+        WpType::F32 => Size::S32,
+        WpType::F64 => Size::S64,
+        _ => unreachable!(),
+    }
+}
+
+fn size_in_bytes(sz: Size) -> usize {
+    match sz {
+        Size::S8 => 1,
+        Size::S16 => 2,
+        Size::S32 => 4,
+        Size::S64 => 8,
+    }
+}
+
+fn f32_bounds(lower_bound: f32, upper_bound: f32) -> (u64, u64) {
+    (lower_bound.to_bits() as u64, upper_bound.to_bits() as u64)
+}
+
+fn f64_bounds(lower_bound: f64, upper_bound: f64) -> (u64, u64) {
+    (lower_bound.to_bits(), upper_bound.to_bits())
+}
+
+// Standard entry trampoline.
+pub fn gen_std_trampoline_arm64(sig: &FunctionType) -> FunctionBody {
+    let mut a = Assembler::new().unwrap();
+
+    // The frame record, and the callee-saved registers holding the function
+    // pointer and the values vector.
+    a.emit_push_pair(GPR::X29, GPR::X30);
+    a.emit_mov_from_sp(GPR::X29);
+    a.emit_push_pair(GPR::X19, GPR::X20);
+    a.emit_mov(Size::S64, Location::GPR(GPR::X1), Location::GPR(GPR::X19));
+    a.emit_mov(Size::S64, Location::GPR(GPR::X2), Location::GPR(GPR::X20));
+
+    // Prepare stack space.
+    let stack_args_size = MachineARM64::get_param_stack_size(sig.params());
+    if stack_args_size != 0 {
+        a.emit_sub_sp(stack_args_size as u32);
+    }
+
+    // Move arguments to their locations.
+    // `callee_vmctx` is already in the first argument register, so no need to move.
+    for (i, loc) in MachineARM64::get_param_locations(sig.params())
+        .into_iter()
+        .enumerate()
+    {
+        let src_loc = Location::Memory(GPR::X20, (i * 16) as _); // args_rets[i]
+        a.emit_mov(Size::S64, src_loc, loc);
+    }
+
+    // Call.
+    a.emit_call_register(GPR::X19);
+
+    // Restore stack.
+    if stack_args_size != 0 {
+        a.emit_add_sp(stack_args_size as u32);
+    }
+
+    // Write return value.
+    if let Some(&ty) = sig.results().first() {
+        let src = match ty {
+            Type::F32 | Type::F64 => Location::NEON(NEON::V0),
+            _ => Location::GPR(GPR::X0),
+        };
+        a.emit_mov(Size::S64, src, Location::Memory(GPR::X20, 0));
+    }
+
+    // Restore callee-saved registers.
+    a.emit_pop_pair(GPR::X19, GPR::X20);
+    a.emit_pop_pair(GPR::X29, GPR::X30);
+    a.emit_ret();
+
+    FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    }
+}
+
+/// Generates dynamic import function call trampoline for a function type.
+pub fn gen_std_dynamic_import_trampoline_arm64(
+    vmoffsets: &VMOffsets,
+    sig: &FunctionType,
+) -> FunctionBody {
+    let mut a = Assembler::new().unwrap();
+
+    a.emit_push_pair(GPR::X29, GPR::X30);
+    a.emit_mov_from_sp(GPR::X29);
+
+    // Allocate argument array, 16 bytes each.
+    let stack_offset =
+        16 * std::cmp::max(1, std::cmp::max(sig.params().len(), sig.results().len()));
+    a.emit_sub_sp(stack_offset as u32);
+
+    // Copy arguments.
+    for (i, loc) in MachineARM64::get_param_locations(sig.params())
+        .into_iter()
+        .enumerate()
+    {
+        let source_loc = match loc {
+            // Stack arguments are above the frame record.
+            Location::Memory(_, offset) => Location::Memory(GPR::X29, 16 + offset),
+            _ => loc,
+        };
+        a.emit_mov(
+            Size::S64,
+            source_loc,
+            Location::Memory(GPR::XzrSp, (i * 16) as _),
+        );
+
+        // Zero upper 64 bits.
+        a.emit_mov(
+            Size::S64,
+            Location::Imm32(0),
+            Location::Memory(GPR::XzrSp, (i * 16 + 8) as _),
+        );
+    }
+
+    // Load target address.
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(
+            GPR::X0,
+            vmoffsets.vmdynamicfunction_import_context_address() as i32,
+        ),
+        Location::GPR(GPR::X16),
+    );
+
+    // Load values array.
+    a.emit_mov_from_sp(GPR::X1);
+
+    // Call target.
+    a.emit_call_register(GPR::X16);
+
+    // Fetch return value.
+    if let Some(&ty) = sig.results().first() {
+        assert_eq!(sig.results().len(), 1);
+        let dst = match ty {
+            Type::F32 | Type::F64 => Location::NEON(NEON::V0),
+            _ => Location::GPR(GPR::X0),
+        };
+        a.emit_mov(Size::S64, Location::Memory(GPR::XzrSp, 0), dst);
+    }
+
+    // Release values array and return.
+    a.emit_mov_to_sp(GPR::X29);
+    a.emit_pop_pair(GPR::X29, GPR::X30);
+    a.emit_ret();
+
+    FunctionBody {
+        body: a.finalize().unwrap().to_vec(),
+        unwind_info: None,
+    }
+}
+
+// Singlepass calls import functions through a trampoline.
+//
+// Since functions compiled for AArch64 use the standard calling convention,
+// the trampoline only has to swap vmctx and jump to the target.
+pub fn gen_import_call_trampoline_arm64(
+    vmoffsets: &VMOffsets,
+    index: FunctionIndex,
+    _sig: &FunctionType,
+) -> CustomSection {
+    let mut a = Assembler::new().unwrap();
+
+    let offset = vmoffsets.vmctx_vmfunction_import(index);
+
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(GPR::X0, offset as i32), // function pointer
+        Location::GPR(GPR::X16),
+    );
+    a.emit_mov(
+        Size::S64,
+        Location::Memory(GPR::X0, offset as i32 + 8), // target vmctx
+        Location::GPR(GPR::X0),
+    );
+    a.emit_b_register(GPR::X16);
+
+    let section_body = SectionBody::new_with_vec(a.finalize().unwrap().to_vec());
+
+    CustomSection {
+        protection: CustomSectionProtection::ReadExecute,
+        bytes: section_body,
+        relocations: vec![],
+    }
+}
@@ -17,8 +17,7 @@ use wasmer_types::{
     FunctionType,
 };
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, LocalFunctionIndex, LocalMemoryIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type,
+    FunctionIndex, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex, TableIndex, Type,
 };
 use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMBuiltinFunctionIndex, VMOffsets};
 
@@ -89,6 +88,14 @@ pub struct FuncGen<'a> {
     ///
     // Ordered by increasing InstructionAddressMap::srcloc.
     instructions_address_map: Vec<InstructionAddressMap>,
+
+    /// Base of the frame area through which block parameters and multiple
+    /// results are passed to a label, and where multiple call results are
+    /// assembled. Slot `i` is `16 * i` bytes above the base, so that any slot
+    /// can hold a `V128`.
+    ///
+    /// `None` if no signature in the module needs it.
+    multi_value_scratch: Option<Location>,
 }
 
 struct SpecialLabelSet {
@@ -97,6 +104,7 @@ struct SpecialLabelSet {
     table_access_oob: DynamicLabel,
     indirect_call_null: DynamicLabel,
     bad_signature: DynamicLabel,
+    stack_overflow: DynamicLabel,
 }
 
 /// A trap table for a `RunnableModuleInfo`.
@@ -214,7 +222,7 @@ impl<T> PopMany<T> for Vec<T> {
     }
 }
 
-trait WpTypeExt {
+pub(crate) trait WpTypeExt {
     fn is_float(&self) -> bool;
 }
 
@@ -232,6 +240,7 @@ pub struct ControlFrame {
     pub label: DynamicLabel,
     pub loop_like: bool,
     pub if_else: IfElseState,
+    pub params: SmallVec<[WpType; 1]>,
     pub returns: SmallVec<[WpType; 1]>,
     pub value_stack_depth: usize,
     pub fp_stack_depth: usize,
@@ -239,6 +248,23 @@ pub struct ControlFrame {
     pub state_diff_id: usize,
 }
 
+impl ControlFrame {
+    /// The types of the values carried by a branch to this frame.
+    pub fn branch_types(&self) -> &[WpType] {
+        if self.loop_like {
+            &self.params
+        } else {
+            &self.returns
+        }
+    }
+
+    /// Whether values flowing into this frame's label are passed through the
+    /// multi-value scratch area rather than in RAX, or XMM8 for a `V128`.
+    pub fn uses_multi_value_scratch(&self) -> bool {
+        !self.params.is_empty() || self.returns.len() > 1
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum IfElseState {
     None,
@@ -956,203 +982,875 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
-    /// Emits a System V call sequence.
-    ///
-    /// This function will not use RAX before `cb` is called.
-    ///
-    /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
-    /// this function.
-    fn emit_call_sysv<I: Iterator<Item = Location>, F: FnOnce(&mut Self)>(
-        &mut self,
-        cb: F,
-        params: I,
-    ) -> Result<(), CodegenError> {
-        // Values pushed in this function are above the shadow region.
-        self.machine
-            .state
-            .stack_values
-            .push(MachineValue::ExplicitShadow);
+    // SIMD operations keep `V128` values in memory, and load their operands
+    // into XMM8 and XMM9 and use XMM10 to XMM15 as scratch registers. The
+    // register allocator never hands out XMM8 to XMM15, so nothing live is
+    // clobbered.
 
-        let params: Vec<_> = params.collect();
+    /// Acquires a stack slot for a new `V128` value and pushes it onto the value stack.
+    fn acquire_v128(&mut self) -> Location {
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(
+                WpType::V128,
+                MachineValue::WasmStack(self.value_stack.len()),
+            )],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        ret
+    }
 
-        // Save used GPRs.
-        let used_gprs = self.machine.get_used_gprs();
-        for r in used_gprs.iter() {
-            self.assembler.emit_push(Size::S64, Location::GPR(*r));
-            let content =
-                self.machine.state.register_values[X64Register::GPR(*r).to_index().0].clone();
-            if content == MachineValue::Undefined {
-                return Err(CodegenError {
-                    message: "emit_call_sysv: Undefined used_gprs content".to_string(),
-                });
-            }
-            self.machine.state.stack_values.push(content);
-        }
+    /// Pops a `V128` value and loads it into `dst`.
+    fn pop_v128_into(&mut self, dst: XMM) {
+        let loc = self.pop_value_released();
+        self.assembler
+            .emit_vmovdqu(xmm_or_memory(loc), XMMOrMemory::XMM(dst));
+    }
 
-        // Save used XMM registers.
-        let used_xmms = self.machine.get_used_xmms();
-        if used_xmms.len() > 0 {
-            self.assembler.emit_sub(
-                Size::S64,
-                Location::Imm32((used_xmms.len() * 8) as u32),
-                Location::GPR(GPR::RSP),
-            );
+    /// Pushes a new `V128` value holding the contents of `src`.
+    fn push_v128_from(&mut self, src: XMM) -> Location {
+        let ret = self.acquire_v128();
+        self.assembler
+            .emit_vmovdqu(XMMOrMemory::XMM(src), xmm_or_memory(ret));
+        ret
+    }
 
-            for (i, r) in used_xmms.iter().enumerate() {
-                self.assembler.emit_mov(
-                    Size::S64,
-                    Location::XMM(*r),
-                    Location::Memory(GPR::RSP, (i * 8) as i32),
-                );
+    /// Loads the constant `value` into `dst`. Clobbers XMM15.
+    fn emit_load_v128_const(&mut self, value: u128, dst: XMM) {
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(value as u64), Location::GPR(tmp));
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::XMM(dst));
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Imm64((value >> 64) as u64),
+            Location::GPR(tmp),
+        );
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(tmp), Location::XMM(XMM::XMM15));
+        self.assembler
+            .emit_vpunpcklqdq(dst, XMMOrMemory::XMM(XMM::XMM15), dst);
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Clears all bits of `dst`.
+    fn emit_v128_zero(&mut self, dst: XMM) {
+        self.assembler.emit_vpxor(dst, XMMOrMemory::XMM(dst), dst);
+    }
+
+    /// Inverts all bits of `x`. Clobbers XMM15.
+    fn emit_v128_not(&mut self, x: XMM) {
+        self.assembler
+            .emit_vpcmpeqd(XMM::XMM15, XMMOrMemory::XMM(XMM::XMM15), XMM::XMM15);
+        self.assembler
+            .emit_vpxor(x, XMMOrMemory::XMM(XMM::XMM15), x);
+    }
+
+    /// Loads the shift count `count` into `dst`.
+    fn emit_load_shift_count(&mut self, count: u32, dst: XMM) {
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler
+            .emit_mov(Size::S32, Location::Imm32(count), Location::GPR(tmp));
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(tmp), Location::XMM(dst));
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Replicates the low `sz` bits of `gpr` over all of its 64 bits.
+    fn emit_broadcast_gpr(&mut self, sz: Size, gpr: GPR) {
+        let pattern: u64 = match sz {
+            Size::S8 => 0x0101_0101_0101_0101,
+            Size::S16 => 0x0001_0001_0001_0001,
+            Size::S32 => 0x0000_0001_0000_0001,
+            Size::S64 => return,
+        };
+        match sz {
+            Size::S8 | Size::S16 => {
+                self.assembler
+                    .emit_movzx(sz, Location::GPR(gpr), Size::S32, Location::GPR(gpr));
             }
-            for r in used_xmms.iter().rev() {
-                let content =
-                    self.machine.state.register_values[X64Register::XMM(*r).to_index().0].clone();
-                if content == MachineValue::Undefined {
-                    return Err(CodegenError {
-                        message: "emit_call_sysv: Undefined used_xmms content".to_string(),
-                    });
-                }
-                self.machine.state.stack_values.push(content);
+            _ => {
+                self.assembler
+                    .emit_mov(Size::S32, Location::GPR(gpr), Location::GPR(gpr));
             }
         }
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.assembler
+            .emit_mov(Size::S64, Location::Imm64(pattern), Location::GPR(tmp));
+        self.assembler
+            .emit_imul(Size::S64, Location::GPR(tmp), Location::GPR(gpr));
+        self.machine.release_temp_gpr(tmp);
+    }
 
-        let mut stack_offset: usize = 0;
+    /// Stores `gpr` to both halves of the `V128` at `dst`.
+    fn emit_store_v128_halves(&mut self, gpr: GPR, dst: Location) {
+        self.assembler.emit_mov(Size::S64, Location::GPR(gpr), dst);
+        self.assembler
+            .emit_mov(Size::S64, Location::GPR(gpr), v128_high_half(dst));
+    }
 
-        // Calculate stack offset.
-        for (i, _param) in params.iter().enumerate() {
-            if let Location::Memory(_, _) = Machine::get_param_location(1 + i) {
-                stack_offset += 8;
+    /// Pops a lane value of type `ty` into `dst`, canonicalizing it if it is a
+    /// float that needs it.
+    fn emit_pop_lane_value(&mut self, ty: WpType, dst: GPR) -> Result<(), CodegenError> {
+        let loc = self.pop_value_released();
+        if ty.is_float() {
+            let fp = self.fp_stack.pop1()?;
+            if self.assembler.arch_supports_canonicalize_nan()
+                && self.config.enable_nan_canonicalization
+                && fp.canonicalization.is_some()
+            {
+                let sz = match ty {
+                    WpType::F32 => Size::S32,
+                    _ => Size::S64,
+                };
+                self.canonicalize_nan(sz, loc, Location::GPR(dst));
+                return Ok(());
             }
         }
+        self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, Location::GPR(dst));
+        Ok(())
+    }
 
-        // Align stack to 16 bytes.
-        if (self.machine.get_stack_offset()
-            + used_gprs.len() * 8
-            + used_xmms.len() * 8
-            + stack_offset)
-            % 16
-            != 0
-        {
-            self.assembler
-                .emit_sub(Size::S64, Location::Imm32(8), Location::GPR(GPR::RSP));
-            stack_offset += 8;
-            self.machine
-                .state
-                .stack_values
-                .push(MachineValue::Undefined);
-        }
+    /// `V128` unary operation. The operand is loaded into XMM8, where `f`
+    /// leaves the result.
+    fn emit_simd_unop_with<F: FnOnce(&mut Self, XMM)>(&mut self, f: F) {
+        self.pop_v128_into(XMM::XMM8);
+        f(self, XMM::XMM8);
+        self.push_v128_from(XMM::XMM8);
+    }
 
-        let mut call_movs: Vec<(Location, GPR)> = vec![];
+    /// `V128` unary operation with a single instruction.
+    fn emit_simd_unop(&mut self, f: fn(&mut Assembler, XMMOrMemory, XMM)) {
+        self.emit_simd_unop_with(|this, x| f(&mut this.assembler, XMMOrMemory::XMM(x), x));
+    }
 
-        // Prepare register & stack parameters.
-        for (i, param) in params.iter().enumerate().rev() {
-            let loc = Machine::get_param_location(1 + i);
-            match loc {
-                Location::GPR(x) => {
-                    call_movs.push((*param, x));
-                }
-                Location::Memory(_, _) => {
-                    match *param {
-                        Location::GPR(x) => {
-                            let content = self.machine.state.register_values
-                                [X64Register::GPR(x).to_index().0]
-                                .clone();
-                            // FIXME: There might be some corner cases (release -> emit_call_sysv -> acquire?) that cause this assertion to fail.
-                            // Hopefully nothing would be incorrect at runtime.
+    /// `V128` binary operation. The operands are loaded into XMM8 and XMM9,
+    /// and `f` leaves the result in XMM8.
+    fn emit_simd_binop_with<F: FnOnce(&mut Self, XMM, XMM)>(&mut self, f: F) {
+        self.pop_v128_into(XMM::XMM9);
+        self.pop_v128_into(XMM::XMM8);
+        f(self, XMM::XMM8, XMM::XMM9);
+        self.push_v128_from(XMM::XMM8);
+    }
 
-                            //assert!(content != MachineValue::Undefined);
-                            self.machine.state.stack_values.push(content);
-                        }
-                        Location::XMM(x) => {
-                            let content = self.machine.state.register_values
-                                [X64Register::XMM(x).to_index().0]
-                                .clone();
-                            //assert!(content != MachineValue::Undefined);
-                            self.machine.state.stack_values.push(content);
-                        }
-                        Location::Memory(reg, offset) => {
-                            if reg != GPR::RBP {
-                                return Err(CodegenError {
-                                    message: "emit_call_sysv loc param: unreachable code"
-                                        .to_string(),
-                                });
-                            }
-                            self.machine
-                                .state
-                                .stack_values
-                                .push(MachineValue::CopyStackBPRelative(offset));
-                            // TODO: Read value at this offset
-                        }
-                        _ => {
-                            self.machine
-                                .state
-                                .stack_values
-                                .push(MachineValue::Undefined);
-                        }
-                    }
-                    match *param {
-                        Location::Imm64(_) => {
-                            // Dummy value slot to be filled with `mov`.
-                            self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
+    /// `V128` binary operation with a single instruction.
+    fn emit_simd_binop(&mut self, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_binop_with(|this, a, b| f(&mut this.assembler, a, XMMOrMemory::XMM(b), a));
+    }
 
-                            // Use RCX as the temporary register here, since:
-                            // - It is a temporary register that is not used for any persistent value.
-                            // - This register as an argument location is only written to after `sort_call_movs`.'
-                            self.machine.reserve_unused_temp_gpr(GPR::RCX);
-                            self.assembler
-                                .emit_mov(Size::S64, *param, Location::GPR(GPR::RCX));
-                            self.assembler.emit_mov(
-                                Size::S64,
-                                Location::GPR(GPR::RCX),
-                                Location::Memory(GPR::RSP, 0),
-                            );
-                            self.machine.release_temp_gpr(GPR::RCX);
-                        }
-                        Location::XMM(_) => {
-                            // Dummy value slot to be filled with `mov`.
-                            self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
+    /// `V128` binary operation with a single instruction taking its operands
+    /// in reverse order.
+    fn emit_simd_binop_swapped(&mut self, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_binop_with(|this, a, b| f(&mut this.assembler, b, XMMOrMemory::XMM(a), a));
+    }
 
-                            // XMM registers can be directly stored to memory.
-                            self.assembler.emit_mov(
-                                Size::S64,
-                                *param,
-                                Location::Memory(GPR::RSP, 0),
-                            );
-                        }
-                        _ => self.assembler.emit_push(Size::S64, *param),
-                    }
-                }
-                _ => {
-                    return Err(CodegenError {
-                        message: "emit_call_sysv loc: unreachable code".to_string(),
-                    })
-                }
+    /// `V128` comparison computed as the negation of `f`, possibly with its
+    /// operands in reverse order.
+    fn emit_simd_cmpop_not(&mut self, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM), swap: bool) {
+        self.emit_simd_binop_with(|this, a, b| {
+            if swap {
+                f(&mut this.assembler, b, XMMOrMemory::XMM(a), a);
+            } else {
+                f(&mut this.assembler, a, XMMOrMemory::XMM(b), a);
             }
-        }
-
-        // Sort register moves so that register are not overwritten before read.
-        sort_call_movs(&mut call_movs);
+            this.emit_v128_not(a);
+        });
+    }
 
-        // Emit register moves.
-        for (loc, gpr) in call_movs {
-            if loc != Location::GPR(gpr) {
-                self.assembler.emit_mov(Size::S64, loc, Location::GPR(gpr));
+    /// Unsigned `V128` comparison. The lanes of `a` are compared as greater or
+    /// equal (or less or equal) to `b` by checking that the unsigned maximum
+    /// (or minimum) `minmax` of both is `a`, and negated if `negate` is set.
+    fn emit_simd_cmpop_unsigned(
+        &mut self,
+        minmax: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        eq: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        negate: bool,
+    ) {
+        self.emit_simd_binop_with(|this, a, b| {
+            minmax(&mut this.assembler, a, XMMOrMemory::XMM(b), XMM::XMM10);
+            eq(&mut this.assembler, a, XMMOrMemory::XMM(XMM::XMM10), a);
+            if negate {
+                this.emit_v128_not(a);
             }
-        }
+        });
+    }
 
-        // Put vmctx as the first parameter.
-        self.assembler.emit_mov(
-            Size::S64,
-            Location::GPR(Machine::get_vmctx_reg()),
-            Machine::get_param_location(0),
-        ); // vmctx
+    /// `V128` operation with an `I32` result. The operand is loaded into XMM8,
+    /// and `f` computes the result into a temporary GPR.
+    fn emit_simd_to_i32<F: FnOnce(&mut Self, XMM, GPR)>(&mut self, f: F) {
+        self.pop_v128_into(XMM::XMM8);
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        f(self, XMM::XMM8, tmp);
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(WpType::I32, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        self.assembler.emit_mov(Size::S32, Location::GPR(tmp), ret);
+        self.machine.release_temp_gpr(tmp);
+    }
 
-        if (self.machine.state.stack_values.len() % 2) != 1 {
-            return Err(CodegenError {
-                message: "emit_call_sysv: explicit shadow takes one slot".to_string(),
-            });
-        }
+    /// Computes whether all lanes of a `V128` are non-zero, using `eq` to
+    /// compare them with zero.
+    fn emit_simd_all_true(&mut self, eq: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_to_i32(|this, x, tmp| {
+            this.emit_v128_zero(XMM::XMM10);
+            eq(
+                &mut this.assembler,
+                x,
+                XMMOrMemory::XMM(XMM::XMM10),
+                XMM::XMM10,
+            );
+            this.assembler
+                .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp));
+            this.assembler
+                .emit_vptest(XMMOrMemory::XMM(XMM::XMM10), XMM::XMM10);
+            this.assembler.emit_set(Condition::Equal, tmp);
+        });
+    }
+
+    /// Pops a shift count, reduces it modulo the lane width `lane_bits` into
+    /// RCX, and copies it to XMM9. The caller must release RCX.
+    fn emit_simd_shift_count(&mut self, lane_bits: u32) {
+        let count = self.pop_value_released();
+        self.machine.reserve_unused_temp_gpr(GPR::RCX);
+        self.emit_relaxed_binop(
+            Assembler::emit_mov,
+            Size::S32,
+            count,
+            Location::GPR(GPR::RCX),
+        );
+        self.assembler.emit_and(
+            Size::S32,
+            Location::Imm32(lane_bits - 1),
+            Location::GPR(GPR::RCX),
+        );
+        self.assembler
+            .emit_mov(Size::S32, Location::GPR(GPR::RCX), Location::XMM(XMM::XMM9));
+    }
+
+    /// `V128` shift by the count popped from the stack.
+    fn emit_simd_shift(&mut self, lane_bits: u32, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_shift_count(lane_bits);
+        self.emit_simd_unop_with(|this, x| {
+            f(&mut this.assembler, x, XMMOrMemory::XMM(XMM::XMM9), x)
+        });
+        self.machine.release_temp_gpr(GPR::RCX);
+    }
+
+    /// `i8x16` logical shift by the count popped from the stack. The bytes
+    /// are shifted as words with `f`, and the bits crossing into the
+    /// neighbouring byte are then masked off.
+    fn emit_simd_i8x16_shift(
+        &mut self,
+        f: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+        mask_shift: fn(&mut Assembler, Size, Location, Location),
+    ) {
+        self.emit_simd_shift_count(8);
+        self.emit_simd_unop_with(|this, x| {
+            f(&mut this.assembler, x, XMMOrMemory::XMM(XMM::XMM9), x);
+
+            // Broadcast `0xff` shifted by the count to all bytes.
+            let tmp = this.machine.acquire_temp_gpr().unwrap();
+            this.assembler
+                .emit_mov(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
+            mask_shift(
+                &mut this.assembler,
+                Size::S32,
+                Location::GPR(GPR::RCX),
+                Location::GPR(tmp),
+            );
+            this.assembler
+                .emit_mov(Size::S32, Location::GPR(tmp), Location::XMM(XMM::XMM10));
+            this.machine.release_temp_gpr(tmp);
+            this.emit_v128_zero(XMM::XMM11);
+            this.assembler
+                .emit_vpshufb(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM11), XMM::XMM10);
+
+            this.assembler
+                .emit_vpand(x, XMMOrMemory::XMM(XMM::XMM10), x);
+        });
+        self.machine.release_temp_gpr(GPR::RCX);
+    }
+
+    /// `V128` widening of the low (or high, if `high` is set) half of the lanes with `extend`.
+    fn emit_simd_widen(&mut self, high: bool, extend: fn(&mut Assembler, XMMOrMemory, XMM)) {
+        self.emit_simd_unop_with(|this, x| {
+            if high {
+                this.assembler.emit_vpunpckhqdq(x, XMMOrMemory::XMM(x), x);
+            }
+            extend(&mut this.assembler, XMMOrMemory::XMM(x), x);
+        });
+    }
+
+    /// `V128` extended multiplication of the low (or high, if `high` is set)
+    /// half of the lanes, widened with `extend` and multiplied with `mul`.
+    fn emit_simd_extmul(
+        &mut self,
+        high: bool,
+        extend: fn(&mut Assembler, XMMOrMemory, XMM),
+        mul: fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+    ) {
+        self.emit_simd_binop_with(|this, a, b| {
+            if high {
+                this.assembler.emit_vpunpckhqdq(a, XMMOrMemory::XMM(a), a);
+                this.assembler.emit_vpunpckhqdq(b, XMMOrMemory::XMM(b), b);
+            }
+            extend(&mut this.assembler, XMMOrMemory::XMM(a), a);
+            extend(&mut this.assembler, XMMOrMemory::XMM(b), b);
+            mul(&mut this.assembler, a, XMMOrMemory::XMM(b), a);
+        });
+    }
+
+    /// Floating point `V128` operation on each lane with a constant mask
+    /// `mask` combined with `f`.
+    fn emit_simd_fp_mask(&mut self, mask: u128, f: fn(&mut Assembler, XMM, XMMOrMemory, XMM)) {
+        self.emit_simd_unop_with(|this, x| {
+            this.emit_load_v128_const(mask, XMM::XMM10);
+            f(&mut this.assembler, x, XMMOrMemory::XMM(XMM::XMM10), x);
+        });
+    }
+
+    /// `f32x4.min` or `f64x2.min`, propagating NaNs and ordering -0 below +0.
+    fn emit_simd_fp_min(&mut self, f64x2: bool) {
+        let (min, or, unord, shift, andn, nan_shift): (
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            u32,
+        ) = if f64x2 {
+            (
+                Assembler::emit_vminpd,
+                Assembler::emit_vpor,
+                Assembler::emit_vcmpunordpd,
+                Assembler::emit_vpsrlq,
+                Assembler::emit_vpandn,
+                13,
+            )
+        } else {
+            (
+                Assembler::emit_vminps,
+                Assembler::emit_vpor,
+                Assembler::emit_vcmpunordps,
+                Assembler::emit_vpsrld,
+                Assembler::emit_vpandn,
+                10,
+            )
+        };
+        self.emit_simd_binop_with(|this, a, b| {
+            // `min` returns its second operand if either is NaN or both are
+            // zero, so compute it both ways and merge the results.
+            min(&mut this.assembler, b, XMMOrMemory::XMM(a), XMM::XMM10);
+            min(&mut this.assembler, a, XMMOrMemory::XMM(b), XMM::XMM11);
+            or(
+                &mut this.assembler,
+                XMM::XMM10,
+                XMMOrMemory::XMM(XMM::XMM11),
+                XMM::XMM10,
+            );
+            // Canonicalize NaNs by clearing their payload.
+            unord(
+                &mut this.assembler,
+                XMM::XMM11,
+                XMMOrMemory::XMM(XMM::XMM10),
+                XMM::XMM11,
+            );
+            or(
+                &mut this.assembler,
+                XMM::XMM10,
+                XMMOrMemory::XMM(XMM::XMM11),
+                XMM::XMM10,
+            );
+            this.emit_load_shift_count(nan_shift, XMM::XMM12);
+            shift(
+                &mut this.assembler,
+                XMM::XMM11,
+                XMMOrMemory::XMM(XMM::XMM12),
+                XMM::XMM11,
+            );
+            andn(
+                &mut this.assembler,
+                XMM::XMM11,
+                XMMOrMemory::XMM(XMM::XMM10),
+                a,
+            );
+        });
+    }
+
+    /// `f32x4.max` or `f64x2.max`, propagating NaNs and ordering +0 above -0.
+    fn emit_simd_fp_max(&mut self, f64x2: bool) {
+        let (max, sub, unord, shift, nan_shift): (
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            fn(&mut Assembler, XMM, XMMOrMemory, XMM),
+            u32,
+        ) = if f64x2 {
+            (
+                Assembler::emit_vmaxpd,
+                Assembler::emit_vsubpd,
+                Assembler::emit_vcmpunordpd,
+                Assembler::emit_vpsrlq,
+                13,
+            )
+        } else {
+            (
+                Assembler::emit_vmaxps,
+                Assembler::emit_vsubps,
+                Assembler::emit_vcmpunordps,
+                Assembler::emit_vpsrld,
+                10,
+            )
+        };
+        self.emit_simd_binop_with(|this, a, b| {
+            // `max` returns its second operand if either is NaN or both are
+            // zero, so compute it both ways and find the discrepancies.
+            max(&mut this.assembler, b, XMMOrMemory::XMM(a), XMM::XMM10);
+            max(&mut this.assembler, a, XMMOrMemory::XMM(b), XMM::XMM11);
+            this.assembler
+                .emit_vpxor(XMM::XMM11, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM11);
+            // Propagate NaNs and the sign discrepancy of zeros.
+            this.assembler
+                .emit_vpor(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM11), XMM::XMM10);
+            sub(
+                &mut this.assembler,
+                XMM::XMM10,
+                XMMOrMemory::XMM(XMM::XMM11),
+                XMM::XMM10,
+            );
+            // Canonicalize NaNs by clearing their payload.
+            unord(
+                &mut this.assembler,
+                XMM::XMM11,
+                XMMOrMemory::XMM(XMM::XMM10),
+                XMM::XMM11,
+            );
+            this.emit_load_shift_count(nan_shift, XMM::XMM12);
+            shift(
+                &mut this.assembler,
+                XMM::XMM11,
+                XMMOrMemory::XMM(XMM::XMM12),
+                XMM::XMM11,
+            );
+            this.assembler
+                .emit_vpandn(XMM::XMM11, XMMOrMemory::XMM(XMM::XMM10), a);
+        });
+    }
+
+    /// Pushes a `V128` with every lane set to the value of type `ty` popped
+    /// from the stack, whose lanes are `sz` wide.
+    fn emit_simd_splat(&mut self, ty: WpType, sz: Size) -> Result<(), CodegenError> {
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_pop_lane_value(ty, tmp)?;
+        self.emit_broadcast_gpr(sz, tmp);
+        let ret = self.acquire_v128();
+        self.emit_store_v128_halves(tmp, ret);
+        self.machine.release_temp_gpr(tmp);
+        Ok(())
+    }
+
+    /// Pushes the lane `offset` bytes into the `V128` popped from the stack as
+    /// a value of type `ty`, reading it with `f`.
+    fn emit_simd_extract_lane(
+        &mut self,
+        ty: WpType,
+        offset: i32,
+        f: fn(&mut Assembler, Location, Location),
+    ) {
+        let v = self.pop_value_released();
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        let lane = match v {
+            Location::Memory(base, disp) => Location::Memory(base, disp + offset),
+            _ => unreachable!(),
+        };
+        f(&mut self.assembler, lane, Location::GPR(tmp));
+        let ret = self.machine.acquire_locations(
+            &mut self.assembler,
+            &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+            false,
+        )[0];
+        self.value_stack.push(ret);
+        if ty.is_float() {
+            self.fp_stack
+                .push(FloatValue::new(self.value_stack.len() - 1));
+        }
+        self.assembler.emit_mov(Size::S64, Location::GPR(tmp), ret);
+        self.machine.release_temp_gpr(tmp);
+    }
+
+    /// Replaces the lane `offset` bytes into a `V128` with the value of type
+    /// `ty` popped from the stack, whose lanes are `sz` wide.
+    fn emit_simd_replace_lane(
+        &mut self,
+        ty: WpType,
+        sz: Size,
+        offset: i32,
+    ) -> Result<(), CodegenError> {
+        let tmp = self.machine.acquire_temp_gpr().unwrap();
+        self.emit_pop_lane_value(ty, tmp)?;
+        self.pop_v128_into(XMM::XMM8);
+        let ret = self.push_v128_from(XMM::XMM8);
+        let lane = match ret {
+            Location::Memory(base, disp) => Location::Memory(base, disp + offset),
+            _ => unreachable!(),
+        };
+        self.assembler.emit_mov(sz, Location::GPR(tmp), lane);
+        self.machine.release_temp_gpr(tmp);
+        Ok(())
+    }
+
+    /// Loads a `V128` from memory with `f`, which reads `value_size` bytes.
+    fn emit_simd_load<F: FnOnce(&mut Self, GPR, Location)>(
+        &mut self,
+        memarg: &MemoryImmediate,
+        value_size: usize,
+        f: F,
+    ) -> Result<(), CodegenError> {
+        let target = self.pop_value_released();
+        let ret = self.acquire_v128();
+        self.emit_memory_op(target, memarg, false, value_size, |this, addr| {
+            f(this, addr, ret);
+            Ok(())
+        })
+    }
+
+    /// Loads 64 bits from memory and widens their lanes with `extend`.
+    fn emit_simd_load_extend(
+        &mut self,
+        memarg: &MemoryImmediate,
+        extend: fn(&mut Assembler, XMMOrMemory, XMM),
+    ) -> Result<(), CodegenError> {
+        self.emit_simd_load(memarg, 8, |this, addr, ret| {
+            this.assembler.emit_mov(
+                Size::S64,
+                Location::Memory(addr, 0),
+                Location::XMM(XMM::XMM8),
+            );
+            extend(&mut this.assembler, XMMOrMemory::XMM(XMM::XMM8), XMM::XMM8);
+            this.assembler
+                .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM8), xmm_or_memory(ret));
+        })
+    }
+
+    /// Loads a lane `sz` wide from memory and replicates it to all lanes.
+    fn emit_simd_load_splat(
+        &mut self,
+        memarg: &MemoryImmediate,
+        sz: Size,
+    ) -> Result<(), CodegenError> {
+        self.emit_simd_load(memarg, lane_bytes(sz), |this, addr, ret| {
+            let tmp = this.machine.acquire_temp_gpr().unwrap();
+            this.emit_load_lane(sz, Location::Memory(addr, 0), tmp);
+            this.emit_broadcast_gpr(sz, tmp);
+            this.emit_store_v128_halves(tmp, ret);
+            this.machine.release_temp_gpr(tmp);
+        })
+    }
+
+    /// Loads a lane `sz` wide from memory into the lowest lane, zeroing the others.
+    fn emit_simd_load_zero(
+        &mut self,
+        memarg: &MemoryImmediate,
+        sz: Size,
+    ) -> Result<(), CodegenError> {
+        self.emit_simd_load(memarg, lane_bytes(sz), |this, addr, ret| {
+            let tmp = this.machine.acquire_temp_gpr().unwrap();
+            this.emit_load_lane(sz, Location::Memory(addr, 0), tmp);
+            this.assembler.emit_mov(Size::S64, Location::GPR(tmp), ret);
+            this.assembler
+                .emit_mov(Size::S64, Location::Imm32(0), v128_high_half(ret));
+            this.machine.release_temp_gpr(tmp);
+        })
+    }
+
+    /// Loads a lane `sz` wide from memory into the lane `lane` of the `V128`
+    /// popped from the stack.
+    fn emit_simd_load_lane(
+        &mut self,
+        memarg: &MemoryImmediate,
+        sz: Size,
+        lane: u8,
+    ) -> Result<(), CodegenError> {
+        self.pop_v128_into(XMM::XMM8);
+        self.emit_simd_load(memarg, lane_bytes(sz), |this, addr, ret| {
+            this.assembler
+                .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM8), xmm_or_memory(ret));
+            let tmp = this.machine.acquire_temp_gpr().unwrap();
+            this.emit_load_lane(sz, Location::Memory(addr, 0), tmp);
+            let offset = (lane as usize * lane_bytes(sz)) as i32;
+            let dst = match ret {
+                Location::Memory(base, disp) => Location::Memory(base, disp + offset),
+                _ => unreachable!(),
+            };
+            this.assembler.emit_mov(sz, Location::GPR(tmp), dst);
+            this.machine.release_temp_gpr(tmp);
+        })
+    }
+
+    /// Stores the lane `lane`, `sz` wide, of the `V128` popped from the stack to memory.
+    fn emit_simd_store_lane(
+        &mut self,
+        memarg: &MemoryImmediate,
+        sz: Size,
+        lane: u8,
+    ) -> Result<(), CodegenError> {
+        let v = self.pop_value_released();
+        let target = self.pop_value_released();
+        let offset = (lane as usize * lane_bytes(sz)) as i32;
+        let src = match v {
+            Location::Memory(base, disp) => Location::Memory(base, disp + offset),
+            _ => unreachable!(),
+        };
+        self.emit_memory_op(target, memarg, false, lane_bytes(sz), |this, addr| {
+            let tmp = this.machine.acquire_temp_gpr().unwrap();
+            this.emit_load_lane(sz, src, tmp);
+            this.assembler
+                .emit_mov(sz, Location::GPR(tmp), Location::Memory(addr, 0));
+            this.machine.release_temp_gpr(tmp);
+            Ok(())
+        })
+    }
+
+    /// Zero-extends the lane `sz` wide at `src` into `dst`.
+    fn emit_load_lane(&mut self, sz: Size, src: Location, dst: GPR) {
+        match sz {
+            Size::S8 | Size::S16 => {
+                self.assembler
+                    .emit_movzx(sz, src, Size::S32, Location::GPR(dst));
+            }
+            _ => {
+                self.assembler.emit_mov(sz, src, Location::GPR(dst));
+            }
+        }
+    }
+
+    /// Emits a System V call sequence.
+    ///
+    /// This function will not use RAX before `cb` is called.
+    ///
+    /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
+    /// this function.
+    fn emit_call_sysv<I: Iterator<Item = Location>, F: FnOnce(&mut Self)>(
+        &mut self,
+        cb: F,
+        params: I,
+    ) -> Result<(), CodegenError> {
+        self.emit_call_sysv_with_sret(cb, params, None)
+    }
+
+    /// Emits a System V call sequence, passing the address of `sret` as the
+    /// hidden struct-return pointer ahead of vmctx if it is given.
+    ///
+    /// This function will not use RAX before `cb` is called.
+    ///
+    /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
+    /// this function.
+    fn emit_call_sysv_with_sret<I: Iterator<Item = Location>, F: FnOnce(&mut Self)>(
+        &mut self,
+        cb: F,
+        params: I,
+        sret: Option<Location>,
+    ) -> Result<(), CodegenError> {
+        // The struct-return pointer, if any, shifts vmctx and the parameters by one.
+        let vmctx_param = if sret.is_some() { 1 } else { 0 };
+
+        // Values pushed in this function are above the shadow region.
+        self.machine
+            .state
+            .stack_values
+            .push(MachineValue::ExplicitShadow);
+
+        let params: Vec<_> = params.collect();
+
+        // Save used GPRs.
+        let used_gprs = self.machine.get_used_gprs();
+        for r in used_gprs.iter() {
+            self.assembler.emit_push(Size::S64, Location::GPR(*r));
+            let content =
+                self.machine.state.register_values[X64Register::GPR(*r).to_index().0].clone();
+            if content == MachineValue::Undefined {
+                return Err(CodegenError {
+                    message: "emit_call_sysv: Undefined used_gprs content".to_string(),
+                });
+            }
+            self.machine.state.stack_values.push(content);
+        }
+
+        // Save used XMM registers.
+        let used_xmms = self.machine.get_used_xmms();
+        if used_xmms.len() > 0 {
+            self.assembler.emit_sub(
+                Size::S64,
+                Location::Imm32((used_xmms.len() * 8) as u32),
+                Location::GPR(GPR::RSP),
+            );
+
+            for (i, r) in used_xmms.iter().enumerate() {
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::XMM(*r),
+                    Location::Memory(GPR::RSP, (i * 8) as i32),
+                );
+            }
+            for r in used_xmms.iter().rev() {
+                let content =
+                    self.machine.state.register_values[X64Register::XMM(*r).to_index().0].clone();
+                if content == MachineValue::Undefined {
+                    return Err(CodegenError {
+                        message: "emit_call_sysv: Undefined used_xmms content".to_string(),
+                    });
+                }
+                self.machine.state.stack_values.push(content);
+            }
+        }
+
+        let mut stack_offset: usize = 0;
+
+        // Calculate stack offset.
+        for (i, _param) in params.iter().enumerate() {
+            if let Location::Memory(_, _) = Machine::get_param_location(vmctx_param + 1 + i) {
+                stack_offset += 8;
+            }
+        }
+
+        // Align stack to 16 bytes.
+        if (self.machine.get_stack_offset()
+            + used_gprs.len() * 8
+            + used_xmms.len() * 8
+            + stack_offset)
+            % 16
+            != 0
+        {
+            self.assembler
+                .emit_sub(Size::S64, Location::Imm32(8), Location::GPR(GPR::RSP));
+            stack_offset += 8;
+            self.machine
+                .state
+                .stack_values
+                .push(MachineValue::Undefined);
+        }
+
+        let mut call_movs: Vec<(Location, GPR)> = vec![];
+
+        // Prepare register & stack parameters.
+        for (i, param) in params.iter().enumerate().rev() {
+            let loc = Machine::get_param_location(vmctx_param + 1 + i);
+            match loc {
+                Location::GPR(x) => {
+                    call_movs.push((*param, x));
+                }
+                Location::Memory(_, _) => {
+                    match *param {
+                        Location::GPR(x) => {
+                            let content = self.machine.state.register_values
+                                [X64Register::GPR(x).to_index().0]
+                                .clone();
+                            // FIXME: There might be some corner cases (release -> emit_call_sysv -> acquire?) that cause this assertion to fail.
+                            // Hopefully nothing would be incorrect at runtime.
+
+                            //assert!(content != MachineValue::Undefined);
+                            self.machine.state.stack_values.push(content);
+                        }
+                        Location::XMM(x) => {
+                            let content = self.machine.state.register_values
+                                [X64Register::XMM(x).to_index().0]
+                                .clone();
+                            //assert!(content != MachineValue::Undefined);
+                            self.machine.state.stack_values.push(content);
+                        }
+                        Location::Memory(reg, offset) => {
+                            if reg != GPR::RBP {
+                                return Err(CodegenError {
+                                    message: "emit_call_sysv loc param: unreachable code"
+                                        .to_string(),
+                                });
+                            }
+                            self.machine
+                                .state
+                                .stack_values
+                                .push(MachineValue::CopyStackBPRelative(offset));
+                            // TODO: Read value at this offset
+                        }
+                        _ => {
+                            self.machine
+                                .state
+                                .stack_values
+                                .push(MachineValue::Undefined);
+                        }
+                    }
+                    match *param {
+                        Location::Imm64(_) => {
+                            // Dummy value slot to be filled with `mov`.
+                            self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
+
+                            // Use RCX as the temporary register here, since:
+                            // - It is a temporary register that is not used for any persistent value.
+                            // - This register as an argument location is only written to after `sort_call_movs`.'
+                            self.machine.reserve_unused_temp_gpr(GPR::RCX);
+                            self.assembler
+                                .emit_mov(Size::S64, *param, Location::GPR(GPR::RCX));
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                Location::GPR(GPR::RCX),
+                                Location::Memory(GPR::RSP, 0),
+                            );
+                            self.machine.release_temp_gpr(GPR::RCX);
+                        }
+                        Location::XMM(_) => {
+                            // Dummy value slot to be filled with `mov`.
+                            self.assembler.emit_push(Size::S64, Location::GPR(GPR::RAX));
+
+                            // XMM registers can be directly stored to memory.
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                *param,
+                                Location::Memory(GPR::RSP, 0),
+                            );
+                        }
+                        _ => self.assembler.emit_push(Size::S64, *param),
+                    }
+                }
+                _ => {
+                    return Err(CodegenError {
+                        message: "emit_call_sysv loc: unreachable code".to_string(),
+                    })
+                }
+            }
+        }
+
+        // Sort register moves so that register are not overwritten before read.
+        sort_call_movs(&mut call_movs);
+
+        // Emit register moves.
+        for (loc, gpr) in call_movs {
+            if loc != Location::GPR(gpr) {
+                self.assembler.emit_mov(Size::S64, loc, Location::GPR(gpr));
+            }
+        }
+
+        // Put vmctx as the first parameter.
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::GPR(Machine::get_vmctx_reg()),
+            Machine::get_param_location(vmctx_param),
+        ); // vmctx
+
+        // The struct-return pointer goes before it.
+        if let Some(sret) = sret {
+            self.assembler
+                .emit_lea(Size::S64, sret, Machine::get_param_location(0));
+        }
+
+        if (self.machine.state.stack_values.len() % 2) != 1 {
+            return Err(CodegenError {
+                message: "emit_call_sysv: explicit shadow takes one slot".to_string(),
+            });
+        }
 
         cb(self);
 
@@ -1235,6 +1933,164 @@ impl<'a> FuncGen<'a> {
         Ok(())
     }
 
+    /// Returns the index that the memory libcalls expect for `memory_index`:
+    /// the local index for a locally defined memory, the global one otherwise.
+    fn memory_index_arg(&self, memory_index: MemoryIndex) -> u32 {
+        match self.module.local_memory_index(memory_index) {
+            Some(local_memory_index) => local_memory_index.index() as u32,
+            None => memory_index.index() as u32,
+        }
+    }
+
+    /// Emits the checks of the store's interrupt flag and of the epoch
+    /// deadline of the instance, calling into the runtime (which may trap)
+    /// when the former is set or the latter is reached.
+    ///
+    /// Must not be called while temporary registers are held.
+    fn emit_interrupt_checks(&mut self) -> Result<(), CodegenError> {
+        let not_interrupted = self.assembler.get_label();
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_interrupt());
+        self.assembler
+            .emit_cmp(Size::S64, Location::Imm32(0), Location::GPR(GPR::RAX));
+        self.assembler.emit_jmp(Condition::Equal, not_interrupted);
+        self.emit_builtin_call(
+            VMBuiltinFunctionIndex::get_interrupt_index(),
+            &[],
+            &[],
+            None,
+        )?;
+        self.assembler.emit_label(not_interrupted);
+
+        let before_deadline = self.assembler.get_label();
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_epoch());
+        self.assembler.emit_cmp(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_epoch_deadline() as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_jmp(Condition::Below, before_deadline);
+        self.emit_builtin_call(
+            VMBuiltinFunctionIndex::get_epoch_deadline_index(),
+            &[],
+            &[],
+            None,
+        )?;
+        self.assembler.emit_label(before_deadline);
+        Ok(())
+    }
+
+    /// Emits the check of the stack pointer against the store's stack
+    /// limit, trapping with `TrapCode::StackOverflow` below it.
+    ///
+    /// A limit of zero is never reached, so no check is needed to tell
+    /// whether it is set.
+    fn emit_stack_check(&mut self) {
+        self.emit_load_interrupts_field(self.vmoffsets.vminterrupts_stack_limit());
+        self.assembler
+            .emit_cmp(Size::S64, Location::GPR(GPR::RAX), Location::GPR(GPR::RSP));
+        self.assembler
+            .emit_jmp(Condition::Below, self.special_labels.stack_overflow);
+    }
+
+    /// Loads the 64-bit field at `offset` in the store's `VMInterrupts` to RAX.
+    fn emit_load_interrupts_field(&mut self, offset: u8) {
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_interrupts() as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(GPR::RAX, offset as i32),
+            Location::GPR(GPR::RAX),
+        );
+    }
+
+    /// Emits a call to the builtin function `index`, passing vmctx and `params`.
+    ///
+    /// `popped` are the values among `params` that were popped off the value
+    /// stack, in stack order, and are released around the call. If `ret_ty`
+    /// is given, the result is pushed to the value stack.
+    fn emit_builtin_call(
+        &mut self,
+        index: VMBuiltinFunctionIndex,
+        params: &[Location],
+        popped: &[Location],
+        ret_ty: Option<WpType>,
+    ) -> Result<(), CodegenError> {
+        self.machine.release_locations_only_regs(popped);
+
+        self.assembler.emit_mov(
+            Size::S64,
+            Location::Memory(
+                Machine::get_vmctx_reg(),
+                self.vmoffsets.vmctx_builtin_function(index) as i32,
+            ),
+            Location::GPR(GPR::RAX),
+        );
+
+        self.machine.release_locations_only_osr_state(popped.len());
+
+        self.emit_call_sysv(
+            |this| {
+                this.assembler.emit_call_register(GPR::RAX);
+            },
+            params.iter().cloned(),
+        )?;
+
+        self.machine
+            .release_locations_only_stack(&mut self.assembler, popped);
+
+        if let Some(ty) = ret_ty {
+            let ret = self.machine.acquire_locations(
+                &mut self.assembler,
+                &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+                false,
+            )[0];
+            self.value_stack.push(ret);
+            self.assembler
+                .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+        }
+        Ok(())
+    }
+
+    /// Replaces the 32-bit address popped into `addr` with the 64-bit
+    /// effective address of `memarg`, as expected by the atomic wait and
+    /// notify builtins, and returns its location.
+    fn emit_effective_atomic_addr(&mut self, addr: Location, memarg: &MemoryImmediate) -> Location {
+        match addr {
+            Location::Imm32(x) => Location::Imm64(x as u64 + memarg.offset as u64),
+            _ => {
+                let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
+                let tmp_offset = self.machine.acquire_temp_gpr().unwrap();
+                // 32-bit moves zero-extend the value into the full register.
+                self.assembler
+                    .emit_mov(Size::S32, addr, Location::GPR(tmp_addr));
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Imm32(memarg.offset),
+                    Location::GPR(tmp_offset),
+                );
+                self.assembler.emit_add(
+                    Size::S64,
+                    Location::GPR(tmp_offset),
+                    Location::GPR(tmp_addr),
+                );
+                self.assembler
+                    .emit_mov(Size::S64, Location::GPR(tmp_addr), addr);
+                self.machine.release_temp_gpr(tmp_offset);
+                self.machine.release_temp_gpr(tmp_addr);
+                addr
+            }
+        }
+    }
+
     /// Emits a memory operation.
     fn emit_memory_op<F: FnOnce(&mut Self, GPR) -> Result<(), CodegenError>>(
         &mut self,
@@ -1244,18 +2100,19 @@ impl<'a> FuncGen<'a> {
         value_size: usize,
         cb: F,
     ) -> Result<(), CodegenError> {
-        let need_check = match self.memory_styles[MemoryIndex::new(0)] {
+        let memory_index = MemoryIndex::new(memarg.memory as usize);
+        let need_check = match self.memory_styles[memory_index] {
             MemoryStyle::Static { .. } => false,
             MemoryStyle::Dynamic { .. } => true,
         };
         let tmp_addr = self.machine.acquire_temp_gpr().unwrap();
 
         // Reusing `tmp_addr` for temporary indirection here, since it's not used before the last reference to `{base,bound}_loc`.
-        let (base_loc, bound_loc) = if self.module.num_imported_memories != 0 {
+        let (base_loc, bound_loc) = if self.module.is_imported_memory(memory_index) {
             // Imported memories require one level of indirection.
             let offset = self
                 .vmoffsets
-                .vmctx_vmmemory_import_definition(MemoryIndex::new(0));
+                .vmctx_vmmemory_import_definition(memory_index);
             self.emit_relaxed_binop(
                 Assembler::emit_mov,
                 Size::S64,
@@ -1264,9 +2121,8 @@ impl<'a> FuncGen<'a> {
             );
             (Location::Memory(tmp_addr, 0), Location::Memory(tmp_addr, 8))
         } else {
-            let offset = self
-                .vmoffsets
-                .vmctx_vmmemory_definition(LocalMemoryIndex::new(0));
+            let local_memory_index = self.module.local_memory_index(memory_index).unwrap();
+            let offset = self.vmoffsets.vmctx_vmmemory_definition(local_memory_index);
             (
                 Location::Memory(Machine::get_vmctx_reg(), offset as i32),
                 Location::Memory(Machine::get_vmctx_reg(), (offset + 8) as i32),
@@ -1283,7 +2139,7 @@ impl<'a> FuncGen<'a> {
         // Load bound into temporary register, if needed.
         if need_check {
             self.assembler
-                .emit_mov(Size::S32, bound_loc, Location::GPR(tmp_bound));
+                .emit_mov(Size::S64, bound_loc, Location::GPR(tmp_bound));
 
             // Wasm -> Effective.
             // Assuming we never underflow - should always be true on Linux/macOS and Windows >=8,
@@ -1745,17 +2601,40 @@ impl<'a> FuncGen<'a> {
             .emit_mov(Size::S64, Location::GPR(GPR::RSP), Location::GPR(GPR::RBP));
 
         // Initialize locals.
+        let sret = multi_value_return_layout(self.signature.results())
+            .map_or(false, |layout| layout.is_sret());
         self.locals = self.machine.init_locals(
             &mut self.assembler,
-            self.local_types.len(),
+            &self.local_types,
             self.signature.params().len(),
+            sret,
         );
 
+        // Reserve the multi-value scratch area. It must hold the widest block
+        // type in the module, with two eightbytes per value, and at least the
+        // two eightbytes of a struct returned in registers.
+        let multi_value_slots = self
+            .module
+            .signatures
+            .values()
+            .filter(|sig| sig.results().len() > 1 || !sig.params().is_empty())
+            .map(|sig| {
+                let struct_slots = multi_value_return_layout(sig.results())
+                    .map_or(0, |layout| (layout.size + 7) / 8);
+                (2 * sig.params().len().max(sig.results().len())).max(struct_slots)
+            })
+            .max();
+        if let Some(slots) = multi_value_slots {
+            self.multi_value_scratch = Some(
+                self.machine
+                    .reserve_stack_slots(&mut self.assembler, std::cmp::max(slots, 2)),
+            );
+        }
+
         // Mark vmctx register. The actual loading of the vmctx value is handled by init_local.
         self.machine.state.register_values
             [X64Register::GPR(Machine::get_vmctx_reg()).to_index().0] = MachineValue::Vmctx;
 
-        // TODO: Explicit stack check is not supported for now.
         let diff = self.machine.state.diff(&new_machine_state());
         let state_diff_id = self.fsm.diffs.len();
         self.fsm.diffs.push(diff);
@@ -1767,6 +2646,7 @@ impl<'a> FuncGen<'a> {
             label: self.assembler.get_label(),
             loop_like: false,
             if_else: IfElseState::None,
+            params: smallvec![],
             returns: self
                 .signature
                 .results()
@@ -1779,8 +2659,6 @@ impl<'a> FuncGen<'a> {
             state_diff_id,
         });
 
-        // TODO: Full preemption by explicit signal checking
-
         // We insert set StackOverflow as the default trap that can happen
         // anywhere in the function prologue.
         let offset = 0;
@@ -1789,12 +2667,337 @@ impl<'a> FuncGen<'a> {
             .insert(offset, TrapCode::StackOverflow);
         self.mark_instruction_address_end(offset);
 
+        self.emit_stack_check();
+        self.emit_interrupt_checks()?;
+
         if self.machine.state.wasm_inst_offset != std::usize::MAX {
             return Err(CodegenError {
                 message: "emit_head: wasm_inst_offset not std::usize::MAX".to_string(),
             });
         }
-        Ok(())
+        Ok(())
+    }
+
+    /// Returns the location `offset` bytes above the base of the multi-value scratch area.
+    fn multi_value_scratch_at(&self, offset: usize) -> Location {
+        match self.multi_value_scratch {
+            Some(Location::Memory(base, disp)) => Location::Memory(base, disp + offset as i32),
+            _ => unreachable!("the multi-value scratch area is not allocated"),
+        }
+    }
+
+    /// Returns the parameter and result types of a block of type `ty`.
+    fn block_signature(
+        &self,
+        ty: WpTypeOrFuncType,
+    ) -> (SmallVec<[WpType; 1]>, SmallVec<[WpType; 1]>) {
+        match ty {
+            WpTypeOrFuncType::Type(WpType::EmptyBlockType) => (smallvec![], smallvec![]),
+            WpTypeOrFuncType::Type(inner_ty) => (smallvec![], smallvec![inner_ty]),
+            WpTypeOrFuncType::FuncType(sig_index) => {
+                let sig = &self.module.signatures[SignatureIndex::from_u32(sig_index)];
+                (
+                    sig.params().iter().cloned().map(type_to_wp_type).collect(),
+                    sig.results().iter().cloned().map(type_to_wp_type).collect(),
+                )
+            }
+        }
+    }
+
+    /// Returns the length of the FP stack when the value stack was `depth` values deep.
+    fn fp_stack_depth_at(&self, depth: usize) -> usize {
+        self.fp_stack
+            .iter()
+            .take_while(|fp| fp.depth < depth)
+            .count()
+    }
+
+    /// Moves the value at `depth` in the value stack, of type `ty`, to `dst`,
+    /// canonicalizing it on the way if it is a float that needs it.
+    fn emit_move_value_canonicalized(&mut self, ty: WpType, depth: usize, dst: Location) {
+        let loc = self.value_stack[depth];
+        let canonicalize = ty.is_float()
+            && self.assembler.arch_supports_canonicalize_nan()
+            && self.config.enable_nan_canonicalization
+            && self
+                .fp_stack
+                .iter()
+                .rev()
+                .find(|fp| fp.depth == depth)
+                .map_or(false, |fp| fp.canonicalization.is_some());
+        if canonicalize {
+            self.canonicalize_nan(
+                match ty {
+                    WpType::F32 => Size::S32,
+                    WpType::F64 => Size::S64,
+                    _ => unreachable!(),
+                },
+                loc,
+                dst,
+            );
+        } else if ty == WpType::V128 {
+            self.emit_move_v128(loc, dst);
+        } else {
+            self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, dst);
+        }
+    }
+
+    /// Copies the `V128` value at `src` to `dst` through XMM8, which the
+    /// register allocator never hands out. Either location may be XMM8 itself.
+    fn emit_move_v128(&mut self, src: Location, dst: Location) {
+        if src != Location::XMM(XMM::XMM8) {
+            self.assembler
+                .emit_vmovdqu(xmm_or_memory(src), XMMOrMemory::XMM(XMM::XMM8));
+        }
+        if dst != Location::XMM(XMM::XMM8) {
+            self.assembler
+                .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM8), xmm_or_memory(dst));
+        }
+    }
+
+    /// Returns where a label expects the `i`-th of the values of types `types`.
+    fn label_value_location(&self, types: &[WpType], i: usize, through_scratch: bool) -> Location {
+        if through_scratch {
+            self.multi_value_scratch_at(i * 16)
+        } else if types[i] == WpType::V128 {
+            Location::XMM(XMM::XMM8)
+        } else {
+            Location::GPR(GPR::RAX)
+        }
+    }
+
+    /// Moves the values of types `types` at the top of the value stack to where
+    /// a label expects them: the multi-value scratch area if `through_scratch`
+    /// is set, or RAX (XMM8 for a `V128`) for a single value otherwise.
+    ///
+    /// The value stack itself is left untouched.
+    fn emit_move_values_to_label(&mut self, types: &[WpType], through_scratch: bool) {
+        let base = self.value_stack.len() - types.len();
+        for (i, ty) in types.iter().enumerate() {
+            let dst = self.label_value_location(types, i, through_scratch);
+            self.emit_move_value_canonicalized(*ty, base + i, dst);
+        }
+    }
+
+    /// Pushes the values of types `types` passed to a label by
+    /// `emit_move_values_to_label` onto the value stack.
+    fn emit_receive_label_values(&mut self, types: &[WpType], through_scratch: bool) {
+        let tys: SmallVec<[(WpType, MachineValue); 1]> = types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (*ty, MachineValue::WasmStack(self.value_stack.len() + i)))
+            .collect();
+        let locs = self
+            .machine
+            .acquire_locations(&mut self.assembler, &tys, false);
+        for (i, (ty, loc)) in types.iter().zip(locs.into_iter()).enumerate() {
+            let src = self.label_value_location(types, i, through_scratch);
+            if *ty == WpType::V128 {
+                self.emit_move_v128(src, loc);
+            } else {
+                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+            }
+            self.value_stack.push(loc);
+            if ty.is_float() {
+                // We already canonicalized at the `Br*` instruction or at the end of the block.
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+        }
+    }
+
+    /// Moves the values carried by a branch to the frame at `frame_index` in
+    /// the control stack to where the label of that frame expects them.
+    fn emit_branch_values(&mut self, frame_index: usize) -> Result<(), CodegenError> {
+        let frame = &self.control_stack[frame_index];
+        let types: SmallVec<[WpType; 1]> = frame.branch_types().iter().cloned().collect();
+        let through_scratch = frame.uses_multi_value_scratch();
+        if types.len()
+            > self.value_stack.len() - self.control_stack.last().unwrap().value_stack_depth
+        {
+            return Err(CodegenError {
+                message: "emit_branch_values: not enough values on the stack".to_string(),
+            });
+        }
+        self.emit_move_values_to_label(&types, through_scratch);
+        Ok(())
+    }
+
+    /// Pushes copies of the block parameters of types `params` at the top of
+    /// the value stack, leaving the originals in place below them.
+    fn emit_copy_block_params(&mut self, params: &[WpType]) {
+        let base = self.value_stack.len() - params.len();
+        let tys: SmallVec<[(WpType, MachineValue); 1]> = params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (*ty, MachineValue::WasmStack(self.value_stack.len() + i)))
+            .collect();
+        let locs = self
+            .machine
+            .acquire_locations(&mut self.assembler, &tys, false);
+        for (i, (ty, loc)) in params.iter().zip(locs.into_iter()).enumerate() {
+            let src = self.value_stack[base + i];
+            if *ty == WpType::V128 {
+                self.emit_move_v128(src, loc);
+            } else {
+                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+            }
+            self.value_stack.push(loc);
+            if ty.is_float() {
+                let canonicalization = self
+                    .fp_stack
+                    .iter()
+                    .rev()
+                    .find(|fp| fp.depth == base + i)
+                    .and_then(|fp| fp.canonicalization);
+                self.fp_stack.push(FloatValue {
+                    canonicalization,
+                    depth: self.value_stack.len() - 1,
+                });
+            }
+        }
+    }
+
+    /// Pops the `n` values at the top of the value stack and releases their locations.
+    fn release_top_values(&mut self, n: usize) {
+        let depth = self.value_stack.len() - n;
+        let released: SmallVec<[Location; 1]> = self.value_stack.drain(depth..).collect();
+        self.machine
+            .release_locations(&mut self.assembler, &released);
+        let fp_depth = self.fp_stack_depth_at(depth);
+        self.fp_stack.truncate(fp_depth);
+    }
+
+    /// Pushes the results of a call to a function returning `results` onto the
+    /// value stack.
+    fn emit_call_results(&mut self, results: &[Type]) {
+        let layout = match multi_value_return_layout(results) {
+            Some(layout) => layout,
+            None => {
+                if let Some(&ty) = results.first() {
+                    let ty = type_to_wp_type(ty);
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(ty, MachineValue::WasmStack(self.value_stack.len()))],
+                        false,
+                    )[0];
+                    self.value_stack.push(ret);
+                    if ty.is_float() {
+                        self.assembler
+                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    } else if ty == WpType::V128 {
+                        // A `V128` is returned in RAX (low half) and RDX (high half).
+                        self.assembler
+                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+                        self.assembler.emit_mov(
+                            Size::S64,
+                            Location::GPR(GPR::RDX),
+                            v128_high_half(ret),
+                        );
+                    } else {
+                        self.assembler
+                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
+                    }
+                }
+                return;
+            }
+        };
+
+        // Spill a struct returned in registers to the scratch area, where a
+        // struct returned in memory has already been written by the callee.
+        if let Some(registers) = &layout.registers {
+            for (i, reg) in registers.iter().enumerate() {
+                let src = match *reg {
+                    X64Register::GPR(x) => Location::GPR(x),
+                    X64Register::XMM(x) => Location::XMM(x),
+                };
+                let dst = self.multi_value_scratch_at(i * 8);
+                self.assembler.emit_mov(Size::S64, src, dst);
+            }
+        }
+
+        let tys: SmallVec<[(WpType, MachineValue); 1]> = results
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                (
+                    type_to_wp_type(*ty),
+                    MachineValue::WasmStack(self.value_stack.len() + i),
+                )
+            })
+            .collect();
+        let locs = self
+            .machine
+            .acquire_locations(&mut self.assembler, &tys, false);
+        for (i, (ty, loc)) in results.iter().zip(locs.into_iter()).enumerate() {
+            let src = self.multi_value_scratch_at(layout.offsets[i]);
+            if *ty == Type::V128 {
+                self.emit_move_v128(src, loc);
+            } else {
+                self.emit_relaxed_binop(Assembler::emit_mov, value_size(*ty), src, loc);
+            }
+            self.value_stack.push(loc);
+            if *ty == Type::F32 || *ty == Type::F64 {
+                self.fp_stack
+                    .push(FloatValue::new(self.value_stack.len() - 1));
+            }
+        }
+    }
+
+    /// Returns the struct-return buffer to pass to a call to a function
+    /// returning `results`, if it returns them in memory.
+    fn call_sret_location(&self, results: &[Type]) -> Option<Location> {
+        match multi_value_return_layout(results) {
+            Some(ref layout) if layout.is_sret() => Some(self.multi_value_scratch_at(0)),
+            _ => None,
+        }
+    }
+
+    /// Hands the results of the function, left in the multi-value scratch
+    /// area by the branches to its end, back to the caller as described by
+    /// `layout`.
+    fn emit_multi_value_return(&mut self, layout: &MultiValueReturnLayout) {
+        let results = self.signature.results().to_vec();
+        if layout.is_sret() {
+            // The struct-return pointer is also returned in RAX.
+            let sret = self.machine.get_sret_location().unwrap();
+            self.assembler
+                .emit_mov(Size::S64, sret, Location::GPR(GPR::RAX));
+            for (i, ty) in results.iter().enumerate() {
+                for half in 0..value_eightbytes(*ty) {
+                    let src = self.multi_value_scratch_at(i * 16 + half * 8);
+                    self.assembler
+                        .emit_mov(Size::S64, src, Location::GPR(GPR::RCX));
+                    self.assembler.emit_mov(
+                        value_size(*ty),
+                        Location::GPR(GPR::RCX),
+                        Location::Memory(GPR::RAX, (layout.offsets[i] + half * 8) as i32),
+                    );
+                }
+            }
+        } else {
+            // Pack the values into the struct layout in place. A field never
+            // starts after the slot its value comes from, so every slot is
+            // read before a field overwrites it.
+            for (i, ty) in results.iter().enumerate() {
+                let src = self.multi_value_scratch_at(i * 16);
+                let dst = self.multi_value_scratch_at(layout.offsets[i]);
+                self.assembler
+                    .emit_mov(Size::S64, src, Location::GPR(GPR::RCX));
+                self.assembler
+                    .emit_mov(value_size(*ty), Location::GPR(GPR::RCX), dst);
+            }
+            for (i, reg) in layout.registers.as_ref().unwrap().iter().enumerate() {
+                let dst = match *reg {
+                    X64Register::GPR(x) => Location::GPR(x),
+                    X64Register::XMM(x) => Location::XMM(x),
+                };
+                let src = self.multi_value_scratch_at(i * 8);
+                self.assembler.emit_mov(Size::S64, src, dst);
+            }
+        }
     }
 
     /// Pushes the instruction to the address map, calculating the offset from a
@@ -1843,6 +3046,7 @@ impl<'a> FuncGen<'a> {
             table_access_oob: assembler.get_label(),
             indirect_call_null: assembler.get_label(),
             bad_signature: assembler.get_label(),
+            stack_overflow: assembler.get_label(),
         };
 
         let mut fg = FuncGen {
@@ -1866,6 +3070,7 @@ impl<'a> FuncGen<'a> {
             special_labels,
             src_loc: 0,
             instructions_address_map: vec![],
+            multi_value_scratch: None,
         };
         fg.emit_head()?;
         Ok(fg)
@@ -1954,7 +3159,11 @@ impl<'a> FuncGen<'a> {
                     Location::Memory(tmp, 0)
                 };
 
-                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+                if ty == WpType::V128 {
+                    self.emit_move_v128(src, loc);
+                } else {
+                    self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, src, loc);
+                }
 
                 self.machine.release_temp_gpr(tmp);
             }
@@ -2005,6 +3214,8 @@ impl<'a> FuncGen<'a> {
                     } else {
                         self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, dst);
                     }
+                } else if ty == WpType::V128 {
+                    self.emit_move_v128(loc, dst);
                 } else {
                     self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, loc, dst);
                 }
@@ -2012,28 +3223,43 @@ impl<'a> FuncGen<'a> {
             }
             Operator::LocalGet { local_index } => {
                 let local_index = local_index as usize;
-                let ret = self.machine.acquire_locations(
-                    &mut self.assembler,
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
-                    false,
-                )[0];
-                self.emit_relaxed_binop(
-                    Assembler::emit_mov,
-                    Size::S64,
-                    self.locals[local_index],
-                    ret,
-                );
-                self.value_stack.push(ret);
-                if self.local_types[local_index].is_float() {
-                    self.fp_stack
-                        .push(FloatValue::new(self.value_stack.len() - 1));
+                if self.local_types[local_index] == WpType::V128 {
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(
+                            WpType::V128,
+                            MachineValue::WasmStack(self.value_stack.len()),
+                        )],
+                        false,
+                    )[0];
+                    self.emit_move_v128(self.locals[local_index], ret);
+                    self.value_stack.push(ret);
+                } else {
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                        false,
+                    )[0];
+                    self.emit_relaxed_binop(
+                        Assembler::emit_mov,
+                        Size::S64,
+                        self.locals[local_index],
+                        ret,
+                    );
+                    self.value_stack.push(ret);
+                    if self.local_types[local_index].is_float() {
+                        self.fp_stack
+                            .push(FloatValue::new(self.value_stack.len() - 1));
+                    }
                 }
             }
             Operator::LocalSet { local_index } => {
                 let local_index = local_index as usize;
                 let loc = self.pop_value_released();
 
-                if self.local_types[local_index].is_float() {
+                if self.local_types[local_index] == WpType::V128 {
+                    self.emit_move_v128(loc, self.locals[local_index]);
+                } else if self.local_types[local_index].is_float() {
                     let fp = self.fp_stack.pop1()?;
                     if self.assembler.arch_supports_canonicalize_nan()
                         && self.config.enable_nan_canonicalization
@@ -2069,7 +3295,9 @@ impl<'a> FuncGen<'a> {
                 let local_index = local_index as usize;
                 let loc = *self.value_stack.last().unwrap();
 
-                if self.local_types[local_index].is_float() {
+                if self.local_types[local_index] == WpType::V128 {
+                    self.emit_move_v128(loc, self.locals[local_index]);
+                } else if self.local_types[local_index].is_float() {
                     let fp = self.fp_stack.peek1()?;
                     if self.assembler.arch_supports_canonicalize_nan()
                         && self.config.enable_nan_canonicalization
@@ -5154,8 +6382,7 @@ impl<'a> FuncGen<'a> {
                 let sig = self.module.signatures.get(sig_index).unwrap();
                 let param_types: SmallVec<[WpType; 8]> =
                     sig.params().iter().cloned().map(type_to_wp_type).collect();
-                let return_types: SmallVec<[WpType; 1]> =
-                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+                let return_types: SmallVec<[Type; 1]> = sig.results().iter().cloned().collect();
 
                 let params: SmallVec<[_; 8]> = self
                     .value_stack
@@ -5210,7 +6437,8 @@ impl<'a> FuncGen<'a> {
                     Location::GPR(GPR::RAX),
                 );
 
-                self.emit_call_sysv(
+                let sret = self.call_sret_location(&return_types);
+                self.emit_call_sysv_with_sret(
                     |this| {
                         let offset = this.assembler.get_offset().0;
                         this.trap_table
@@ -5219,32 +6447,14 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_location(Location::GPR(GPR::RAX));
                         this.mark_instruction_address_end(offset);
                     },
-                    params.iter().copied(),
+                    param_slot_locations(&param_types, &params).into_iter(),
+                    sret,
                 )?;
 
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &params);
 
-                if !return_types.is_empty() {
-                    let ret = self.machine.acquire_locations(
-                        &mut self.assembler,
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )[0];
-                    self.value_stack.push(ret);
-                    if return_types[0].is_float() {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
-                        self.fp_stack
-                            .push(FloatValue::new(self.value_stack.len() - 1));
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-                    }
-                }
+                self.emit_call_results(&return_types);
             }
             Operator::CallIndirect { index, table_index } => {
                 if table_index != 0 {
@@ -5257,8 +6467,7 @@ impl<'a> FuncGen<'a> {
                 let sig = self.module.signatures.get(index).unwrap();
                 let param_types: SmallVec<[WpType; 8]> =
                     sig.params().iter().cloned().map(type_to_wp_type).collect();
-                let return_types: SmallVec<[WpType; 1]> =
-                    sig.results().iter().cloned().map(type_to_wp_type).collect();
+                let return_types: SmallVec<[Type; 1]> = sig.results().iter().cloned().collect();
 
                 let func_index = self.pop_value_released();
 
@@ -5399,9 +6608,20 @@ impl<'a> FuncGen<'a> {
 
                 let vmcaller_checked_anyfunc_func_ptr =
                     self.vmoffsets.vmcaller_checked_anyfunc_func_ptr() as usize;
+                let vmcaller_checked_anyfunc_vmctx =
+                    self.vmoffsets.vmcaller_checked_anyfunc_vmctx() as usize;
 
-                self.emit_call_sysv(
+                let sret = self.call_sret_location(&return_types);
+                let vmctx_param = if sret.is_some() { 1 } else { 0 };
+                self.emit_call_sysv_with_sret(
                     |this| {
+                        // The callee may belong to another instance: pass it
+                        // its own vmctx.
+                        this.assembler.emit_mov(
+                            Size::S64,
+                            Location::Memory(GPR::RAX, vmcaller_checked_anyfunc_vmctx as i32),
+                            Machine::get_param_location(vmctx_param),
+                        );
                         if this.assembler.arch_requires_indirect_call_trampoline() {
                             this.assembler.arch_emit_indirect_call_with_trampoline(
                                 Location::Memory(
@@ -5421,98 +6641,54 @@ impl<'a> FuncGen<'a> {
                             this.mark_instruction_address_end(offset);
                         }
                     },
-                    params.iter().copied(),
+                    param_slot_locations(&param_types, &params).into_iter(),
+                    sret,
                 )?;
 
                 self.machine
                     .release_locations_only_stack(&mut self.assembler, &params);
 
-                if !return_types.is_empty() {
-                    let ret = self.machine.acquire_locations(
-                        &mut self.assembler,
-                        &[(
-                            return_types[0],
-                            MachineValue::WasmStack(self.value_stack.len()),
-                        )],
-                        false,
-                    )[0];
-                    self.value_stack.push(ret);
-                    if return_types[0].is_float() {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::XMM(XMM::XMM0), ret);
-                        self.fp_stack
-                            .push(FloatValue::new(self.value_stack.len() - 1));
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
-                    }
-                }
+                self.emit_call_results(&return_types);
             }
             Operator::If { ty } => {
                 let label_end = self.assembler.get_label();
                 let label_else = self.assembler.get_label();
 
                 let cond = self.pop_value_released();
+                let (params, returns) = self.block_signature(ty);
+                let value_stack_depth = self.value_stack.len() - params.len();
 
                 let frame = ControlFrame {
                     label: label_end,
                     loop_like: false,
                     if_else: IfElseState::If(label_else),
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "If: multi-value returns not yet implemented".to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    value_stack_depth,
+                    fp_stack_depth: self.fp_stack_depth_at(value_stack_depth),
                     state: self.machine.state.clone(),
                     state_diff_id: self.get_state_diff(),
                 };
                 self.control_stack.push(frame);
                 self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
                 self.assembler.emit_jmp(Condition::Equal, label_else);
+
+                // Both arms consume the block parameters, so the then arm works
+                // on a copy and the originals are kept below the frame for the
+                // else arm.
+                let params = self.control_stack.last().unwrap().params.clone();
+                if !params.is_empty() {
+                    self.emit_copy_block_params(&params);
+                    let value_stack_depth = self.value_stack.len() - params.len();
+                    let fp_stack_depth = self.fp_stack_depth_at(value_stack_depth);
+                    let frame = self.control_stack.last_mut().unwrap();
+                    frame.value_stack_depth = value_stack_depth;
+                    frame.fp_stack_depth = fp_stack_depth;
+                }
             }
             Operator::Else => {
-                let frame = self.control_stack.last_mut().unwrap();
-
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
+                if !was_unreachable {
+                    self.emit_branch_values(self.control_stack.len() - 1)?;
                 }
 
                 let mut frame = self.control_stack.last_mut().unwrap();
@@ -5535,88 +6711,135 @@ impl<'a> FuncGen<'a> {
                         })
                     }
                 }
+
+                let params = frame.params.clone();
+                self.emit_copy_block_params(&params);
             }
-            Operator::Select => {
-                let cond = self.pop_value_released();
-                let v_b = self.pop_value_released();
-                let v_a = self.pop_value_released();
-                let cncl: Option<(Option<CanonicalizeType>, Option<CanonicalizeType>)> =
-                    if self.fp_stack.len() >= 2
-                        && self.fp_stack[self.fp_stack.len() - 2].depth == self.value_stack.len()
-                        && self.fp_stack[self.fp_stack.len() - 1].depth
-                            == self.value_stack.len() + 1
-                    {
-                        let (left, right) = self.fp_stack.pop2()?;
-                        self.fp_stack.push(FloatValue::new(self.value_stack.len()));
-                        Some((left.canonicalization, right.canonicalization))
-                    } else {
-                        None
-                    };
-                let ret = self.machine.acquire_locations(
-                    &mut self.assembler,
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
-                    false,
-                )[0];
-                self.value_stack.push(ret);
+            Operator::Select | Operator::TypedSelect { .. } => {
+                let v_b = self.value_stack[self.value_stack.len() - 2];
+                if self.machine.is_v128_stack_value(v_b) {
+                    let cond = self.pop_value_released();
+                    let v_b = self.pop_value_released();
+                    let v_a = self.pop_value_released();
+                    self.assembler
+                        .emit_vmovdqu(xmm_or_memory(v_a), XMMOrMemory::XMM(XMM::XMM8));
+                    self.assembler
+                        .emit_vmovdqu(xmm_or_memory(v_b), XMMOrMemory::XMM(XMM::XMM9));
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(
+                            WpType::V128,
+                            MachineValue::WasmStack(self.value_stack.len()),
+                        )],
+                        false,
+                    )[0];
+                    self.value_stack.push(ret);
 
-                let end_label = self.assembler.get_label();
-                let zero_label = self.assembler.get_label();
+                    let end_label = self.assembler.get_label();
+                    let zero_label = self.assembler.get_label();
+                    self.emit_relaxed_binop(
+                        Assembler::emit_cmp,
+                        Size::S32,
+                        Location::Imm32(0),
+                        cond,
+                    );
+                    self.assembler.emit_jmp(Condition::Equal, zero_label);
+                    self.emit_move_v128(Location::XMM(XMM::XMM8), ret);
+                    self.assembler.emit_jmp(Condition::None, end_label);
+                    self.assembler.emit_label(zero_label);
+                    self.emit_move_v128(Location::XMM(XMM::XMM9), ret);
+                    self.assembler.emit_label(end_label);
+                } else {
+                    let cond = self.pop_value_released();
+                    let v_b = self.pop_value_released();
+                    let v_a = self.pop_value_released();
+                    let cncl: Option<(Option<CanonicalizeType>, Option<CanonicalizeType>)> =
+                        if self.fp_stack.len() >= 2
+                            && self.fp_stack[self.fp_stack.len() - 2].depth
+                                == self.value_stack.len()
+                            && self.fp_stack[self.fp_stack.len() - 1].depth
+                                == self.value_stack.len() + 1
+                        {
+                            let (left, right) = self.fp_stack.pop2()?;
+                            self.fp_stack.push(FloatValue::new(self.value_stack.len()));
+                            Some((left.canonicalization, right.canonicalization))
+                        } else {
+                            None
+                        };
+                    let ret = self.machine.acquire_locations(
+                        &mut self.assembler,
+                        &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                        false,
+                    )[0];
+                    self.value_stack.push(ret);
 
-                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
-                self.assembler.emit_jmp(Condition::Equal, zero_label);
-                match cncl {
-                    Some((Some(fp), _))
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization =>
-                    {
-                        self.canonicalize_nan(fp.to_size(), v_a, ret);
-                    }
-                    _ => {
-                        if v_a != ret {
-                            self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, v_a, ret);
+                    let end_label = self.assembler.get_label();
+                    let zero_label = self.assembler.get_label();
+
+                    self.emit_relaxed_binop(
+                        Assembler::emit_cmp,
+                        Size::S32,
+                        Location::Imm32(0),
+                        cond,
+                    );
+                    self.assembler.emit_jmp(Condition::Equal, zero_label);
+                    match cncl {
+                        Some((Some(fp), _))
+                            if self.assembler.arch_supports_canonicalize_nan()
+                                && self.config.enable_nan_canonicalization =>
+                        {
+                            self.canonicalize_nan(fp.to_size(), v_a, ret);
+                        }
+                        _ => {
+                            if v_a != ret {
+                                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, v_a, ret);
+                            }
                         }
                     }
-                }
-                self.assembler.emit_jmp(Condition::None, end_label);
-                self.assembler.emit_label(zero_label);
-                match cncl {
-                    Some((_, Some(fp)))
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization =>
-                    {
-                        self.canonicalize_nan(fp.to_size(), v_b, ret);
-                    }
-                    _ => {
-                        if v_b != ret {
-                            self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, v_b, ret);
+                    self.assembler.emit_jmp(Condition::None, end_label);
+                    self.assembler.emit_label(zero_label);
+                    match cncl {
+                        Some((_, Some(fp)))
+                            if self.assembler.arch_supports_canonicalize_nan()
+                                && self.config.enable_nan_canonicalization =>
+                        {
+                            self.canonicalize_nan(fp.to_size(), v_b, ret);
+                        }
+                        _ => {
+                            if v_b != ret {
+                                self.emit_relaxed_binop(Assembler::emit_mov, Size::S64, v_b, ret);
+                            }
                         }
                     }
+                    self.assembler.emit_label(end_label);
                 }
-                self.assembler.emit_label(end_label);
             }
             Operator::Block { ty } => {
+                let (params, returns) = self.block_signature(ty);
+                let value_stack_depth = self.value_stack.len() - params.len();
                 let frame = ControlFrame {
                     label: self.assembler.get_label(),
                     loop_like: false,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Block: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
-                        }
-                    },
-                    value_stack_depth: self.value_stack.len(),
-                    fp_stack_depth: self.fp_stack.len(),
+                    params,
+                    returns,
+                    value_stack_depth,
+                    fp_stack_depth: self.fp_stack_depth_at(value_stack_depth),
                     state: self.machine.state.clone(),
                     state_diff_id: self.get_state_diff(),
                 };
                 self.control_stack.push(frame);
             }
             Operator::Loop { ty } => {
+                let (params, returns) = self.block_signature(ty);
+
+                // Branches back to the loop pass its parameters through the
+                // multi-value scratch area, so entering the loop does too.
+                if !params.is_empty() {
+                    self.emit_move_values_to_label(&params, true);
+                    self.release_top_values(params.len());
+                }
+
                 // Pad with NOPs to the next 16-byte boundary.
                 // Here we don't use the dynasm `.align 16` attribute because it pads the alignment with single-byte nops
                 // which may lead to efficiency problems.
@@ -5636,16 +6859,8 @@ impl<'a> FuncGen<'a> {
                     label,
                     loop_like: true,
                     if_else: IfElseState::None,
-                    returns: match ty {
-                        WpTypeOrFuncType::Type(WpType::EmptyBlockType) => smallvec![],
-                        WpTypeOrFuncType::Type(inner_ty) => smallvec![inner_ty],
-                        _ => {
-                            return Err(CodegenError {
-                                message: "Loop: multi-value returns not yet implemented"
-                                    .to_string(),
-                            })
-                        }
-                    },
+                    params: params.clone(),
+                    returns,
                     value_stack_depth: self.value_stack.len(),
                     fp_stack_depth: self.fp_stack.len(),
                     state: self.machine.state.clone(),
@@ -5653,11 +6868,17 @@ impl<'a> FuncGen<'a> {
                 });
                 self.assembler.emit_label(label);
 
-                // TODO: Re-enable interrupt signal check without branching
+                if !params.is_empty() {
+                    self.emit_receive_label_values(&params, true);
+                }
+
+                self.emit_interrupt_checks()?;
             }
             Operator::Nop => {}
             Operator::MemorySize { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::new(mem as usize);
+                // Local memories are addressed by their local index.
+                let memory_index_arg = self.memory_index_arg(memory_index);
                 self.assembler.emit_mov(
                     Size::S64,
                     Location::Memory(
@@ -5677,7 +6898,7 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
                     // [vmctx, memory_index]
-                    iter::once(Location::Imm32(memory_index.index() as u32)),
+                    iter::once(Location::Imm32(memory_index_arg)),
                 )?;
                 let ret = self.machine.acquire_locations(
                     &mut self.assembler,
@@ -5690,6 +6911,8 @@ impl<'a> FuncGen<'a> {
             }
             Operator::MemoryGrow { mem, mem_byte: _ } => {
                 let memory_index = MemoryIndex::new(mem as usize);
+                // Local memories are addressed by their local index.
+                let memory_index_arg = self.memory_index_arg(memory_index);
                 let param_pages = self.value_stack.pop().unwrap();
 
                 self.machine.release_locations_only_regs(&[param_pages]);
@@ -5716,8 +6939,7 @@ impl<'a> FuncGen<'a> {
                         this.assembler.emit_call_register(GPR::RAX);
                     },
                     // [vmctx, val, memory_index]
-                    iter::once(param_pages)
-                        .chain(iter::once(Location::Imm32(memory_index.index() as u32))),
+                    iter::once(param_pages).chain(iter::once(Location::Imm32(memory_index_arg))),
                 )?;
 
                 self.machine
@@ -5732,6 +6954,117 @@ impl<'a> FuncGen<'a> {
                 self.assembler
                     .emit_mov(Size::S64, Location::GPR(GPR::RAX), ret);
             }
+            Operator::TableSize { table } => {
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_size_index(),
+                    // [vmctx, table_index]
+                    &[Location::Imm32(table)],
+                    &[],
+                    Some(WpType::I32),
+                )?;
+            }
+            Operator::TableGet { table } => {
+                let index = self.value_stack.pop().unwrap();
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_get_index(),
+                    // [vmctx, table_index, elem_index]
+                    &[Location::Imm32(table), index],
+                    &[index],
+                    Some(WpType::FuncRef),
+                )?;
+            }
+            Operator::TableSet { table } => {
+                let value = self.value_stack.pop().unwrap();
+                let index = self.value_stack.pop().unwrap();
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_set_index(),
+                    // [vmctx, table_index, elem_index, value]
+                    &[Location::Imm32(table), index, value],
+                    &[index, value],
+                    None,
+                )?;
+            }
+            Operator::TableGrow { table } => {
+                let delta = self.value_stack.pop().unwrap();
+                let init_value = self.value_stack.pop().unwrap();
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_grow_index(),
+                    // [vmctx, init_value, delta, table_index]
+                    &[init_value, delta, Location::Imm32(table)],
+                    &[init_value, delta],
+                    Some(WpType::I32),
+                )?;
+            }
+            Operator::TableFill { table } => {
+                let len = self.value_stack.pop().unwrap();
+                let value = self.value_stack.pop().unwrap();
+                let dst = self.value_stack.pop().unwrap();
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_table_fill_index(),
+                    // [vmctx, table_index, dst, value, len]
+                    &[Location::Imm32(table), dst, value, len],
+                    &[dst, value, len],
+                    None,
+                )?;
+            }
+            Operator::MemoryAtomicWait32 { ref memarg } => {
+                let timeout = self.value_stack.pop().unwrap();
+                let expected = self.value_stack.pop().unwrap();
+                let addr = self.value_stack.pop().unwrap();
+                let addr = self.emit_effective_atomic_addr(addr, memarg);
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait32_index(),
+                    // [vmctx, memory_index, addr, expected, timeout]
+                    &[Location::Imm32(memarg.memory), addr, expected, timeout],
+                    &[addr, expected, timeout],
+                    Some(WpType::I32),
+                )?;
+            }
+            Operator::MemoryAtomicWait64 { ref memarg } => {
+                let timeout = self.value_stack.pop().unwrap();
+                let expected = self.value_stack.pop().unwrap();
+                let addr = self.value_stack.pop().unwrap();
+                let addr = self.emit_effective_atomic_addr(addr, memarg);
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_atomic_wait64_index(),
+                    // [vmctx, memory_index, addr, expected, timeout]
+                    &[Location::Imm32(memarg.memory), addr, expected, timeout],
+                    &[addr, expected, timeout],
+                    Some(WpType::I32),
+                )?;
+            }
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                let count = self.value_stack.pop().unwrap();
+                let addr = self.value_stack.pop().unwrap();
+                let addr = self.emit_effective_atomic_addr(addr, memarg);
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_memory_atomic_notify_index(),
+                    // [vmctx, memory_index, addr, count]
+                    &[Location::Imm32(memarg.memory), addr, count],
+                    &[addr, count],
+                    Some(WpType::I32),
+                )?;
+            }
+            Operator::RefNull { .. } => {
+                // Reference values are pointers, and null is 0.
+                self.value_stack.push(Location::Imm64(0));
+                self.machine
+                    .state
+                    .wasm_stack
+                    .push(WasmAbstractValue::Const(0));
+            }
+            Operator::RefIsNull => {
+                self.emit_cmpop_i64_dynamic_b(Condition::Equal, Location::Imm64(0))?
+            }
+            Operator::RefFunc { function_index } => {
+                self.emit_builtin_call(
+                    VMBuiltinFunctionIndex::get_func_ref_index(),
+                    // [vmctx, function_index]
+                    &[Location::Imm32(function_index)],
+                    &[],
+                    Some(WpType::FuncRef),
+                )?;
+            }
             Operator::I32Load { ref memarg } => {
                 let target = self.pop_value_released();
                 let ret = self.machine.acquire_locations(
@@ -6182,47 +7515,7 @@ impl<'a> FuncGen<'a> {
                 self.unreachable_depth = 1;
             }
             Operator::Return => {
-                let frame = &self.control_stack[0];
-                if !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Return: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
-                }
+                self.emit_branch_values(0)?;
                 let frame = &self.control_stack[0];
                 let released = &self.value_stack[frame.value_stack_depth..];
                 self.machine
@@ -6231,45 +7524,7 @@ impl<'a> FuncGen<'a> {
                 self.unreachable_depth = 1;
             }
             Operator::Br { relative_depth } => {
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "Br: incorrect frame.returns".to_string(),
-                        });
-                    }
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
+                self.emit_branch_values(self.control_stack.len() - 1 - (relative_depth as usize))?;
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
 
@@ -6282,48 +7537,10 @@ impl<'a> FuncGen<'a> {
             Operator::BrIf { relative_depth } => {
                 let after = self.assembler.get_label();
                 let cond = self.pop_value_released();
-                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
-                self.assembler.emit_jmp(Condition::Equal, after);
-
-                let frame =
-                    &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
-                if !frame.loop_like && !frame.returns.is_empty() {
-                    if frame.returns.len() != 1 {
-                        return Err(CodegenError {
-                            message: "BrIf: incorrect frame.returns".to_string(),
-                        });
-                    }
+                self.emit_relaxed_binop(Assembler::emit_cmp, Size::S32, Location::Imm32(0), cond);
+                self.assembler.emit_jmp(Condition::Equal, after);
 
-                    let first_return = frame.returns[0];
-                    let loc = *self.value_stack.last().unwrap();
-                    if first_return.is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match first_return {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.assembler
-                            .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                    }
-                }
+                self.emit_branch_values(self.control_stack.len() - 1 - (relative_depth as usize))?;
                 let frame =
                     &self.control_stack[self.control_stack.len() - 1 - (relative_depth as usize)];
                 let released = &self.value_stack[frame.value_stack_depth..];
@@ -6372,48 +7589,7 @@ impl<'a> FuncGen<'a> {
                     let label = self.assembler.get_label();
                     self.assembler.emit_label(label);
                     table.push(label);
-                    let frame =
-                        &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: format!(
-                                    "BrTable: incorrect frame.returns for {:?}",
-                                    target
-                                ),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
+                    self.emit_branch_values(self.control_stack.len() - 1 - (*target as usize))?;
                     let frame =
                         &self.control_stack[self.control_stack.len() - 1 - (*target as usize)];
                     let released = &self.value_stack[frame.value_stack_depth..];
@@ -6424,45 +7600,9 @@ impl<'a> FuncGen<'a> {
                 self.assembler.emit_label(default_br);
 
                 {
-                    let frame = &self.control_stack
-                        [self.control_stack.len() - 1 - (default_target as usize)];
-                    if !frame.loop_like && !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "BrTable: incorrect frame.returns".to_string(),
-                            });
-                        }
-
-                        let first_return = frame.returns[0];
-                        let loc = *self.value_stack.last().unwrap();
-                        if first_return.is_float() {
-                            let fp = self.fp_stack.peek1()?;
-                            if self.assembler.arch_supports_canonicalize_nan()
-                                && self.config.enable_nan_canonicalization
-                                && fp.canonicalization.is_some()
-                            {
-                                self.canonicalize_nan(
-                                    match first_return {
-                                        WpType::F32 => Size::S32,
-                                        WpType::F64 => Size::S64,
-                                        _ => unreachable!(),
-                                    },
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            } else {
-                                self.emit_relaxed_binop(
-                                    Assembler::emit_mov,
-                                    Size::S64,
-                                    loc,
-                                    Location::GPR(GPR::RAX),
-                                );
-                            }
-                        } else {
-                            self.assembler
-                                .emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        }
-                    }
+                    self.emit_branch_values(
+                        self.control_stack.len() - 1 - (default_target as usize),
+                    )?;
                     let frame = &self.control_stack
                         [self.control_stack.len() - 1 - (default_target as usize)];
                     let released = &self.value_stack[frame.value_stack_depth..];
@@ -6487,44 +7627,17 @@ impl<'a> FuncGen<'a> {
             }
             Operator::End => {
                 let frame = self.control_stack.pop().unwrap();
+                let through_scratch = frame.uses_multi_value_scratch();
 
-                if !was_unreachable && !frame.returns.is_empty() {
-                    let loc = *self.value_stack.last().unwrap();
-                    if frame.returns[0].is_float() {
-                        let fp = self.fp_stack.peek1()?;
-                        if self.assembler.arch_supports_canonicalize_nan()
-                            && self.config.enable_nan_canonicalization
-                            && fp.canonicalization.is_some()
-                        {
-                            self.canonicalize_nan(
-                                match frame.returns[0] {
-                                    WpType::F32 => Size::S32,
-                                    WpType::F64 => Size::S64,
-                                    _ => unreachable!(),
-                                },
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        } else {
-                            self.emit_relaxed_binop(
-                                Assembler::emit_mov,
-                                Size::S64,
-                                loc,
-                                Location::GPR(GPR::RAX),
-                            );
-                        }
-                    } else {
-                        self.emit_relaxed_binop(
-                            Assembler::emit_mov,
-                            Size::S64,
-                            loc,
-                            Location::GPR(GPR::RAX),
-                        );
-                    }
+                if !was_unreachable {
+                    self.emit_move_values_to_label(&frame.returns, through_scratch);
                 }
 
                 if self.control_stack.is_empty() {
                     self.assembler.emit_label(frame.label);
+                    if let Some(layout) = multi_value_return_layout(self.signature.results()) {
+                        self.emit_multi_value_return(&layout);
+                    }
                     self.machine
                         .finalize_locals(&mut self.assembler, &self.locals);
                     self.assembler.emit_mov(
@@ -6543,6 +7656,24 @@ impl<'a> FuncGen<'a> {
                                 Location::XMM(XMM::XMM0),
                             );
                         }
+                        // A `V128` is returned in RAX (low half) and RDX (high half).
+                        [Type::V128] => {
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                Location::XMM(XMM::XMM8),
+                                Location::GPR(GPR::RAX),
+                            );
+                            self.assembler.emit_vpunpckhqdq(
+                                XMM::XMM8,
+                                XMMOrMemory::XMM(XMM::XMM8),
+                                XMM::XMM8,
+                            );
+                            self.assembler.emit_mov(
+                                Size::S64,
+                                Location::XMM(XMM::XMM8),
+                                Location::GPR(GPR::RDX),
+                            );
+                        }
                         _ => {}
                     }
                     self.assembler.emit_ret();
@@ -6553,37 +7684,28 @@ impl<'a> FuncGen<'a> {
                     self.value_stack.truncate(frame.value_stack_depth);
                     self.fp_stack.truncate(frame.fp_stack_depth);
 
+                    if let IfElseState::If(label) = frame.if_else {
+                        if frame.params.is_empty() {
+                            self.assembler.emit_label(label);
+                        } else {
+                            // Without an else arm, the block parameters are
+                            // passed through as the results.
+                            self.assembler.emit_jmp(Condition::None, frame.label);
+                            self.assembler.emit_label(label);
+                            self.emit_move_values_to_label(&frame.params, true);
+                        }
+                    }
+
                     if !frame.loop_like {
                         self.assembler.emit_label(frame.label);
                     }
 
-                    if let IfElseState::If(label) = frame.if_else {
-                        self.assembler.emit_label(label);
+                    // Release the block parameters kept for the else arm.
+                    if !matches!(frame.if_else, IfElseState::None) && !frame.params.is_empty() {
+                        self.release_top_values(frame.params.len());
                     }
 
-                    if !frame.returns.is_empty() {
-                        if frame.returns.len() != 1 {
-                            return Err(CodegenError {
-                                message: "End: incorrect frame.returns".to_string(),
-                            });
-                        }
-                        let loc = self.machine.acquire_locations(
-                            &mut self.assembler,
-                            &[(
-                                frame.returns[0],
-                                MachineValue::WasmStack(self.value_stack.len()),
-                            )],
-                            false,
-                        )[0];
-                        self.assembler
-                            .emit_mov(Size::S64, Location::GPR(GPR::RAX), loc);
-                        self.value_stack.push(loc);
-                        if frame.returns[0].is_float() {
-                            self.fp_stack
-                                .push(FloatValue::new(self.value_stack.len() - 1));
-                            // we already canonicalized at the `Br*` instruction or here previously.
-                        }
-                    }
+                    self.emit_receive_label_values(&frame.returns, through_scratch);
                 }
             }
             Operator::AtomicFence { flags: _ } => {
@@ -8104,63 +9226,777 @@ impl<'a> FuncGen<'a> {
                 };
                 self.assembler.emit_push(Size::S64, Location::GPR(value));
                 self.assembler
-                    .emit_mov(Size::S64, cmp, Location::GPR(compare));
+                    .emit_mov(Size::S64, cmp, Location::GPR(compare));
+                self.assembler
+                    .emit_mov(Size::S64, new, Location::GPR(value));
+
+                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
+                    this.assembler.emit_lock_cmpxchg(
+                        Size::S16,
+                        Location::GPR(value),
+                        Location::Memory(addr, 0),
+                    );
+                    this.assembler
+                        .emit_movzx(Size::S16, Location::GPR(compare), Size::S64, ret);
+                    Ok(())
+                })?;
+                self.assembler.emit_pop(Size::S64, Location::GPR(value));
+                self.machine.release_temp_gpr(compare);
+            }
+            Operator::I64AtomicRmw32CmpxchgU { ref memarg } => {
+                let new = self.pop_value_released();
+                let cmp = self.pop_value_released();
+                let target = self.pop_value_released();
+                let ret = self.machine.acquire_locations(
+                    &mut self.assembler,
+                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
+                    false,
+                )[0];
+                self.value_stack.push(ret);
+
+                let compare = self.machine.reserve_unused_temp_gpr(GPR::RAX);
+                let value = if cmp == Location::GPR(GPR::R14) {
+                    if new == Location::GPR(GPR::R13) {
+                        GPR::R12
+                    } else {
+                        GPR::R13
+                    }
+                } else {
+                    GPR::R14
+                };
+                self.assembler.emit_push(Size::S64, Location::GPR(value));
+                self.assembler
+                    .emit_mov(Size::S64, cmp, Location::GPR(compare));
+                self.assembler
+                    .emit_mov(Size::S64, new, Location::GPR(value));
+
+                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
+                    this.assembler.emit_lock_cmpxchg(
+                        Size::S32,
+                        Location::GPR(value),
+                        Location::Memory(addr, 0),
+                    );
+                    this.assembler
+                        .emit_mov(Size::S32, Location::GPR(compare), ret);
+                    Ok(())
+                })?;
+                self.assembler.emit_pop(Size::S64, Location::GPR(value));
+                self.machine.release_temp_gpr(compare);
+            }
+            Operator::V128Const { value } => {
+                let value = u128::from_le_bytes(*value.bytes());
+                self.emit_load_v128_const(value, XMM::XMM8);
+                self.push_v128_from(XMM::XMM8);
+            }
+            Operator::V128Load { ref memarg } => {
+                self.emit_simd_load(memarg, 16, |this, addr, ret| {
+                    this.assembler
+                        .emit_vmovdqu(XMMOrMemory::Memory(addr, 0), XMMOrMemory::XMM(XMM::XMM8));
+                    this.assembler
+                        .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM8), xmm_or_memory(ret));
+                })?;
+            }
+            Operator::V128Load8x8S { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovsxbw)?;
+            }
+            Operator::V128Load8x8U { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovzxbw)?;
+            }
+            Operator::V128Load16x4S { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovsxwd)?;
+            }
+            Operator::V128Load16x4U { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovzxwd)?;
+            }
+            Operator::V128Load32x2S { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovsxdq)?;
+            }
+            Operator::V128Load32x2U { ref memarg } => {
+                self.emit_simd_load_extend(memarg, Assembler::emit_vpmovzxdq)?;
+            }
+            Operator::V128Load8Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S8)?;
+            }
+            Operator::V128Load16Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S16)?;
+            }
+            Operator::V128Load32Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S32)?;
+            }
+            Operator::V128Load64Splat { ref memarg } => {
+                self.emit_simd_load_splat(memarg, Size::S64)?;
+            }
+            Operator::V128Load32Zero { ref memarg } => {
+                self.emit_simd_load_zero(memarg, Size::S32)?;
+            }
+            Operator::V128Load64Zero { ref memarg } => {
+                self.emit_simd_load_zero(memarg, Size::S64)?;
+            }
+            Operator::V128Load8Lane { ref memarg, lane } => {
+                self.emit_simd_load_lane(memarg, Size::S8, lane)?;
+            }
+            Operator::V128Load16Lane { ref memarg, lane } => {
+                self.emit_simd_load_lane(memarg, Size::S16, lane)?;
+            }
+            Operator::V128Load32Lane { ref memarg, lane } => {
+                self.emit_simd_load_lane(memarg, Size::S32, lane)?;
+            }
+            Operator::V128Load64Lane { ref memarg, lane } => {
+                self.emit_simd_load_lane(memarg, Size::S64, lane)?;
+            }
+            Operator::V128Store { ref memarg } => {
+                self.pop_v128_into(XMM::XMM8);
+                let target_addr = self.pop_value_released();
+
+                self.emit_memory_op(target_addr, memarg, false, 16, |this, addr| {
+                    this.assembler
+                        .emit_vmovdqu(XMMOrMemory::XMM(XMM::XMM8), XMMOrMemory::Memory(addr, 0));
+                    Ok(())
+                })?;
+            }
+            Operator::V128Store8Lane { ref memarg, lane } => {
+                self.emit_simd_store_lane(memarg, Size::S8, lane)?;
+            }
+            Operator::V128Store16Lane { ref memarg, lane } => {
+                self.emit_simd_store_lane(memarg, Size::S16, lane)?;
+            }
+            Operator::V128Store32Lane { ref memarg, lane } => {
+                self.emit_simd_store_lane(memarg, Size::S32, lane)?;
+            }
+            Operator::V128Store64Lane { ref memarg, lane } => {
+                self.emit_simd_store_lane(memarg, Size::S64, lane)?;
+            }
+
+            Operator::I8x16Splat => self.emit_simd_splat(WpType::I32, Size::S8)?,
+            Operator::I16x8Splat => self.emit_simd_splat(WpType::I32, Size::S16)?,
+            Operator::I32x4Splat => self.emit_simd_splat(WpType::I32, Size::S32)?,
+            Operator::I64x2Splat => self.emit_simd_splat(WpType::I64, Size::S64)?,
+            Operator::F32x4Splat => self.emit_simd_splat(WpType::F32, Size::S32)?,
+            Operator::F64x2Splat => self.emit_simd_splat(WpType::F64, Size::S64)?,
+
+            Operator::I8x16ExtractLaneS { lane } => {
+                self.emit_simd_extract_lane(WpType::I32, lane as i32, |a, src, dst| {
+                    a.emit_movsx(Size::S8, src, Size::S32, dst)
+                });
+            }
+            Operator::I8x16ExtractLaneU { lane } => {
+                self.emit_simd_extract_lane(WpType::I32, lane as i32, |a, src, dst| {
+                    a.emit_movzx(Size::S8, src, Size::S32, dst)
+                });
+            }
+            Operator::I16x8ExtractLaneS { lane } => {
+                self.emit_simd_extract_lane(WpType::I32, lane as i32 * 2, |a, src, dst| {
+                    a.emit_movsx(Size::S16, src, Size::S32, dst)
+                });
+            }
+            Operator::I16x8ExtractLaneU { lane } => {
+                self.emit_simd_extract_lane(WpType::I32, lane as i32 * 2, |a, src, dst| {
+                    a.emit_movzx(Size::S16, src, Size::S32, dst)
+                });
+            }
+            Operator::I32x4ExtractLane { lane } => {
+                self.emit_simd_extract_lane(WpType::I32, lane as i32 * 4, |a, src, dst| {
+                    a.emit_mov(Size::S32, src, dst)
+                });
+            }
+            Operator::I64x2ExtractLane { lane } => {
+                self.emit_simd_extract_lane(WpType::I64, lane as i32 * 8, |a, src, dst| {
+                    a.emit_mov(Size::S64, src, dst)
+                });
+            }
+            Operator::F32x4ExtractLane { lane } => {
+                self.emit_simd_extract_lane(WpType::F32, lane as i32 * 4, |a, src, dst| {
+                    a.emit_mov(Size::S32, src, dst)
+                });
+            }
+            Operator::F64x2ExtractLane { lane } => {
+                self.emit_simd_extract_lane(WpType::F64, lane as i32 * 8, |a, src, dst| {
+                    a.emit_mov(Size::S64, src, dst)
+                });
+            }
+
+            Operator::I8x16ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::I32, Size::S8, lane as i32)?;
+            }
+            Operator::I16x8ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::I32, Size::S16, lane as i32 * 2)?;
+            }
+            Operator::I32x4ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::I32, Size::S32, lane as i32 * 4)?;
+            }
+            Operator::I64x2ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::I64, Size::S64, lane as i32 * 8)?;
+            }
+            Operator::F32x4ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::F32, Size::S32, lane as i32 * 4)?;
+            }
+            Operator::F64x2ReplaceLane { lane } => {
+                self.emit_simd_replace_lane(WpType::F64, Size::S64, lane as i32 * 8)?;
+            }
+
+            Operator::I8x16Shuffle { lanes } => {
+                let mut mask_a = [0x80u8; 16];
+                let mut mask_b = [0x80u8; 16];
+                for (i, &lane) in lanes.iter().enumerate() {
+                    if lane < 16 {
+                        mask_a[i] = lane;
+                    } else {
+                        mask_b[i] = lane - 16;
+                    }
+                }
+                self.emit_simd_binop_with(|this, a, b| {
+                    // Pick the lanes from each operand, zeroing the others.
+                    this.emit_load_v128_const(u128::from_le_bytes(mask_a), XMM::XMM10);
+                    this.assembler
+                        .emit_vpshufb(a, XMMOrMemory::XMM(XMM::XMM10), a);
+                    this.emit_load_v128_const(u128::from_le_bytes(mask_b), XMM::XMM10);
+                    this.assembler
+                        .emit_vpshufb(b, XMMOrMemory::XMM(XMM::XMM10), b);
+                    this.assembler.emit_vpor(a, XMMOrMemory::XMM(b), a);
+                });
+            }
+            Operator::I8x16Swizzle => {
+                self.emit_simd_binop_with(|this, a, b| {
+                    // `pshufb` zeroes the lanes whose index has its top bit
+                    // set, so push the indices above 15 there.
+                    this.emit_load_v128_const(v128_splat(0x70, 8), XMM::XMM10);
+                    this.assembler
+                        .emit_vpaddusb(b, XMMOrMemory::XMM(XMM::XMM10), b);
+                    this.assembler.emit_vpshufb(a, XMMOrMemory::XMM(b), a);
+                });
+            }
+
+            Operator::V128Not => self.emit_simd_unop_with(|this, x| this.emit_v128_not(x)),
+            Operator::V128And => self.emit_simd_binop(Assembler::emit_vpand),
+            Operator::V128AndNot => self.emit_simd_binop_swapped(Assembler::emit_vpandn),
+            Operator::V128Or => self.emit_simd_binop(Assembler::emit_vpor),
+            Operator::V128Xor => self.emit_simd_binop(Assembler::emit_vpxor),
+            Operator::V128Bitselect => {
+                self.pop_v128_into(XMM::XMM10);
+                self.pop_v128_into(XMM::XMM9);
+                self.pop_v128_into(XMM::XMM8);
+                self.assembler
+                    .emit_vpand(XMM::XMM8, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM8);
+                self.assembler
+                    .emit_vpandn(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM9), XMM::XMM9);
+                self.assembler
+                    .emit_vpor(XMM::XMM8, XMMOrMemory::XMM(XMM::XMM9), XMM::XMM8);
+                self.push_v128_from(XMM::XMM8);
+            }
+            Operator::V128AnyTrue => {
+                self.emit_simd_to_i32(|this, x, tmp| {
+                    this.assembler
+                        .emit_mov(Size::S32, Location::Imm32(0), Location::GPR(tmp));
+                    this.assembler.emit_vptest(XMMOrMemory::XMM(x), x);
+                    this.assembler.emit_set(Condition::NotEqual, tmp);
+                });
+            }
+
+            Operator::I8x16AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqb),
+            Operator::I16x8AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqw),
+            Operator::I32x4AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqd),
+            Operator::I64x2AllTrue => self.emit_simd_all_true(Assembler::emit_vpcmpeqq),
+            Operator::I8x16Bitmask => {
+                self.emit_simd_to_i32(|this, x, tmp| this.assembler.emit_vpmovmskb(x, tmp));
+            }
+            Operator::I16x8Bitmask => {
+                self.emit_simd_to_i32(|this, x, tmp| {
+                    // Saturating the words to bytes keeps their sign.
+                    this.assembler.emit_vpacksswb(x, XMMOrMemory::XMM(x), x);
+                    this.assembler.emit_vpmovmskb(x, tmp);
+                    this.assembler
+                        .emit_and(Size::S32, Location::Imm32(0xff), Location::GPR(tmp));
+                });
+            }
+            Operator::I32x4Bitmask => {
+                self.emit_simd_to_i32(|this, x, tmp| this.assembler.emit_vmovmskps(x, tmp));
+            }
+            Operator::I64x2Bitmask => {
+                self.emit_simd_to_i32(|this, x, tmp| this.assembler.emit_vmovmskpd(x, tmp));
+            }
+
+            Operator::I8x16Eq => self.emit_simd_binop(Assembler::emit_vpcmpeqb),
+            Operator::I8x16Ne => self.emit_simd_cmpop_not(Assembler::emit_vpcmpeqb, false),
+            Operator::I8x16LtS => self.emit_simd_binop_swapped(Assembler::emit_vpcmpgtb),
+            Operator::I8x16GtS => self.emit_simd_binop(Assembler::emit_vpcmpgtb),
+            Operator::I8x16LeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtb, false),
+            Operator::I8x16GeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtb, true),
+            Operator::I8x16LtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxub,
+                Assembler::emit_vpcmpeqb,
+                true,
+            ),
+            Operator::I8x16GtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminub,
+                Assembler::emit_vpcmpeqb,
+                true,
+            ),
+            Operator::I8x16LeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminub,
+                Assembler::emit_vpcmpeqb,
+                false,
+            ),
+            Operator::I8x16GeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxub,
+                Assembler::emit_vpcmpeqb,
+                false,
+            ),
+            Operator::I16x8Eq => self.emit_simd_binop(Assembler::emit_vpcmpeqw),
+            Operator::I16x8Ne => self.emit_simd_cmpop_not(Assembler::emit_vpcmpeqw, false),
+            Operator::I16x8LtS => self.emit_simd_binop_swapped(Assembler::emit_vpcmpgtw),
+            Operator::I16x8GtS => self.emit_simd_binop(Assembler::emit_vpcmpgtw),
+            Operator::I16x8LeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtw, false),
+            Operator::I16x8GeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtw, true),
+            Operator::I16x8LtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxuw,
+                Assembler::emit_vpcmpeqw,
+                true,
+            ),
+            Operator::I16x8GtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminuw,
+                Assembler::emit_vpcmpeqw,
+                true,
+            ),
+            Operator::I16x8LeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminuw,
+                Assembler::emit_vpcmpeqw,
+                false,
+            ),
+            Operator::I16x8GeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxuw,
+                Assembler::emit_vpcmpeqw,
+                false,
+            ),
+            Operator::I32x4Eq => self.emit_simd_binop(Assembler::emit_vpcmpeqd),
+            Operator::I32x4Ne => self.emit_simd_cmpop_not(Assembler::emit_vpcmpeqd, false),
+            Operator::I32x4LtS => self.emit_simd_binop_swapped(Assembler::emit_vpcmpgtd),
+            Operator::I32x4GtS => self.emit_simd_binop(Assembler::emit_vpcmpgtd),
+            Operator::I32x4LeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtd, false),
+            Operator::I32x4GeS => self.emit_simd_cmpop_not(Assembler::emit_vpcmpgtd, true),
+            Operator::I32x4LtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxud,
+                Assembler::emit_vpcmpeqd,
+                true,
+            ),
+            Operator::I32x4GtU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminud,
+                Assembler::emit_vpcmpeqd,
+                true,
+            ),
+            Operator::I32x4LeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpminud,
+                Assembler::emit_vpcmpeqd,
+                false,
+            ),
+            Operator::I32x4GeU => self.emit_simd_cmpop_unsigned(
+                Assembler::emit_vpmaxud,
+                Assembler::emit_vpcmpeqd,
+                false,
+            ),
+            Operator::I64x2Eq => self.emit_simd_binop(Assembler::emit_vpcmpeqq),
+            Operator::I64x2Ne => self.emit_simd_cmpop_not(Assembler::emit_vpcmpeqq, false),
+
+            Operator::F32x4Eq => self.emit_simd_binop(Assembler::emit_vcmpeqps),
+            Operator::F32x4Ne => self.emit_simd_binop(Assembler::emit_vcmpneqps),
+            Operator::F32x4Lt => self.emit_simd_binop(Assembler::emit_vcmpltps),
+            Operator::F32x4Gt => self.emit_simd_binop_swapped(Assembler::emit_vcmpltps),
+            Operator::F32x4Le => self.emit_simd_binop(Assembler::emit_vcmpleps),
+            Operator::F32x4Ge => self.emit_simd_binop_swapped(Assembler::emit_vcmpleps),
+            Operator::F64x2Eq => self.emit_simd_binop(Assembler::emit_vcmpeqpd),
+            Operator::F64x2Ne => self.emit_simd_binop(Assembler::emit_vcmpneqpd),
+            Operator::F64x2Lt => self.emit_simd_binop(Assembler::emit_vcmpltpd),
+            Operator::F64x2Gt => self.emit_simd_binop_swapped(Assembler::emit_vcmpltpd),
+            Operator::F64x2Le => self.emit_simd_binop(Assembler::emit_vcmplepd),
+            Operator::F64x2Ge => self.emit_simd_binop_swapped(Assembler::emit_vcmplepd),
+
+            Operator::I8x16Abs => self.emit_simd_unop(Assembler::emit_vpabsb),
+            Operator::I16x8Abs => self.emit_simd_unop(Assembler::emit_vpabsw),
+            Operator::I32x4Abs => self.emit_simd_unop(Assembler::emit_vpabsd),
+            Operator::I8x16Neg => self.emit_simd_unop_with(|this, x| {
+                this.emit_v128_zero(XMM::XMM10);
+                this.assembler
+                    .emit_vpsubb(XMM::XMM10, XMMOrMemory::XMM(x), x);
+            }),
+            Operator::I16x8Neg => self.emit_simd_unop_with(|this, x| {
+                this.emit_v128_zero(XMM::XMM10);
+                this.assembler
+                    .emit_vpsubw(XMM::XMM10, XMMOrMemory::XMM(x), x);
+            }),
+            Operator::I32x4Neg => self.emit_simd_unop_with(|this, x| {
+                this.emit_v128_zero(XMM::XMM10);
+                this.assembler
+                    .emit_vpsubd(XMM::XMM10, XMMOrMemory::XMM(x), x);
+            }),
+            Operator::I64x2Neg => self.emit_simd_unop_with(|this, x| {
+                this.emit_v128_zero(XMM::XMM10);
+                this.assembler
+                    .emit_vpsubq(XMM::XMM10, XMMOrMemory::XMM(x), x);
+            }),
+
+            Operator::I8x16Add => self.emit_simd_binop(Assembler::emit_vpaddb),
+            Operator::I8x16AddSatS => self.emit_simd_binop(Assembler::emit_vpaddsb),
+            Operator::I8x16AddSatU => self.emit_simd_binop(Assembler::emit_vpaddusb),
+            Operator::I8x16Sub => self.emit_simd_binop(Assembler::emit_vpsubb),
+            Operator::I8x16SubSatS => self.emit_simd_binop(Assembler::emit_vpsubsb),
+            Operator::I8x16SubSatU => self.emit_simd_binop(Assembler::emit_vpsubusb),
+            Operator::I8x16MinS => self.emit_simd_binop(Assembler::emit_vpminsb),
+            Operator::I8x16MinU => self.emit_simd_binop(Assembler::emit_vpminub),
+            Operator::I8x16MaxS => self.emit_simd_binop(Assembler::emit_vpmaxsb),
+            Operator::I8x16MaxU => self.emit_simd_binop(Assembler::emit_vpmaxub),
+            Operator::I8x16RoundingAverageU => self.emit_simd_binop(Assembler::emit_vpavgb),
+            Operator::I8x16NarrowI16x8S => self.emit_simd_binop(Assembler::emit_vpacksswb),
+            Operator::I8x16NarrowI16x8U => self.emit_simd_binop(Assembler::emit_vpackuswb),
+            Operator::I16x8Add => self.emit_simd_binop(Assembler::emit_vpaddw),
+            Operator::I16x8AddSatS => self.emit_simd_binop(Assembler::emit_vpaddsw),
+            Operator::I16x8AddSatU => self.emit_simd_binop(Assembler::emit_vpaddusw),
+            Operator::I16x8Sub => self.emit_simd_binop(Assembler::emit_vpsubw),
+            Operator::I16x8SubSatS => self.emit_simd_binop(Assembler::emit_vpsubsw),
+            Operator::I16x8SubSatU => self.emit_simd_binop(Assembler::emit_vpsubusw),
+            Operator::I16x8Mul => self.emit_simd_binop(Assembler::emit_vpmullw),
+            Operator::I16x8MinS => self.emit_simd_binop(Assembler::emit_vpminsw),
+            Operator::I16x8MinU => self.emit_simd_binop(Assembler::emit_vpminuw),
+            Operator::I16x8MaxS => self.emit_simd_binop(Assembler::emit_vpmaxsw),
+            Operator::I16x8MaxU => self.emit_simd_binop(Assembler::emit_vpmaxuw),
+            Operator::I16x8RoundingAverageU => self.emit_simd_binop(Assembler::emit_vpavgw),
+            Operator::I16x8NarrowI32x4S => self.emit_simd_binop(Assembler::emit_vpackssdw),
+            Operator::I16x8NarrowI32x4U => self.emit_simd_binop(Assembler::emit_vpackusdw),
+            Operator::I16x8Q15MulrSatS => {
+                self.emit_simd_binop_with(|this, a, b| {
+                    // `pmulhrsw` only overflows for `0x8000 * 0x8000`, which
+                    // yields `0x8000` instead of `0x7fff`.
+                    this.assembler.emit_vpmulhrsw(a, XMMOrMemory::XMM(b), a);
+                    this.emit_load_v128_const(v128_splat(0x8000, 16), XMM::XMM10);
+                    this.assembler
+                        .emit_vpcmpeqw(a, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM10);
+                    this.assembler
+                        .emit_vpxor(a, XMMOrMemory::XMM(XMM::XMM10), a);
+                });
+            }
+            Operator::I32x4Add => self.emit_simd_binop(Assembler::emit_vpaddd),
+            Operator::I32x4Sub => self.emit_simd_binop(Assembler::emit_vpsubd),
+            Operator::I32x4Mul => self.emit_simd_binop(Assembler::emit_vpmulld),
+            Operator::I32x4MinS => self.emit_simd_binop(Assembler::emit_vpminsd),
+            Operator::I32x4MinU => self.emit_simd_binop(Assembler::emit_vpminud),
+            Operator::I32x4MaxS => self.emit_simd_binop(Assembler::emit_vpmaxsd),
+            Operator::I32x4MaxU => self.emit_simd_binop(Assembler::emit_vpmaxud),
+            Operator::I32x4DotI16x8S => self.emit_simd_binop(Assembler::emit_vpmaddwd),
+            Operator::I64x2Add => self.emit_simd_binop(Assembler::emit_vpaddq),
+            Operator::I64x2Sub => self.emit_simd_binop(Assembler::emit_vpsubq),
+            Operator::I64x2Mul => {
+                self.emit_simd_binop_with(|this, a, b| {
+                    // a * b = lo(a) * lo(b) + ((hi(a) * lo(b) + lo(a) * hi(b)) << 32)
+                    this.emit_load_shift_count(32, XMM::XMM12);
+                    this.assembler
+                        .emit_vpsrlq(a, XMMOrMemory::XMM(XMM::XMM12), XMM::XMM10);
+                    this.assembler
+                        .emit_vpmuludq(XMM::XMM10, XMMOrMemory::XMM(b), XMM::XMM10);
+                    this.assembler
+                        .emit_vpsrlq(b, XMMOrMemory::XMM(XMM::XMM12), XMM::XMM11);
+                    this.assembler
+                        .emit_vpmuludq(XMM::XMM11, XMMOrMemory::XMM(a), XMM::XMM11);
+                    this.assembler.emit_vpaddq(
+                        XMM::XMM10,
+                        XMMOrMemory::XMM(XMM::XMM11),
+                        XMM::XMM10,
+                    );
+                    this.assembler.emit_vpsllq(
+                        XMM::XMM10,
+                        XMMOrMemory::XMM(XMM::XMM12),
+                        XMM::XMM10,
+                    );
+                    this.assembler.emit_vpmuludq(a, XMMOrMemory::XMM(b), a);
+                    this.assembler
+                        .emit_vpaddq(a, XMMOrMemory::XMM(XMM::XMM10), a);
+                });
+            }
+
+            Operator::I16x8WidenLowI8x16S => self.emit_simd_widen(false, Assembler::emit_vpmovsxbw),
+            Operator::I16x8WidenHighI8x16S => self.emit_simd_widen(true, Assembler::emit_vpmovsxbw),
+            Operator::I16x8WidenLowI8x16U => self.emit_simd_widen(false, Assembler::emit_vpmovzxbw),
+            Operator::I16x8WidenHighI8x16U => self.emit_simd_widen(true, Assembler::emit_vpmovzxbw),
+            Operator::I32x4WidenLowI16x8S => self.emit_simd_widen(false, Assembler::emit_vpmovsxwd),
+            Operator::I32x4WidenHighI16x8S => self.emit_simd_widen(true, Assembler::emit_vpmovsxwd),
+            Operator::I32x4WidenLowI16x8U => self.emit_simd_widen(false, Assembler::emit_vpmovzxwd),
+            Operator::I32x4WidenHighI16x8U => self.emit_simd_widen(true, Assembler::emit_vpmovzxwd),
+            Operator::I64x2WidenLowI32x4S => self.emit_simd_widen(false, Assembler::emit_vpmovsxdq),
+            Operator::I64x2WidenHighI32x4S => self.emit_simd_widen(true, Assembler::emit_vpmovsxdq),
+            Operator::I64x2WidenLowI32x4U => self.emit_simd_widen(false, Assembler::emit_vpmovzxdq),
+            Operator::I64x2WidenHighI32x4U => self.emit_simd_widen(true, Assembler::emit_vpmovzxdq),
+
+            Operator::I16x8ExtMulLowI8x16S => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovsxbw, Assembler::emit_vpmullw)
+            }
+            Operator::I16x8ExtMulHighI8x16S => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovsxbw, Assembler::emit_vpmullw)
+            }
+            Operator::I16x8ExtMulLowI8x16U => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovzxbw, Assembler::emit_vpmullw)
+            }
+            Operator::I16x8ExtMulHighI8x16U => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovzxbw, Assembler::emit_vpmullw)
+            }
+            Operator::I32x4ExtMulLowI16x8S => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovsxwd, Assembler::emit_vpmulld)
+            }
+            Operator::I32x4ExtMulHighI16x8S => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovsxwd, Assembler::emit_vpmulld)
+            }
+            Operator::I32x4ExtMulLowI16x8U => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovzxwd, Assembler::emit_vpmulld)
+            }
+            Operator::I32x4ExtMulHighI16x8U => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovzxwd, Assembler::emit_vpmulld)
+            }
+            Operator::I64x2ExtMulLowI32x4S => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovsxdq, Assembler::emit_vpmuldq)
+            }
+            Operator::I64x2ExtMulHighI32x4S => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovsxdq, Assembler::emit_vpmuldq)
+            }
+            Operator::I64x2ExtMulLowI32x4U => {
+                self.emit_simd_extmul(false, Assembler::emit_vpmovzxdq, Assembler::emit_vpmuludq)
+            }
+            Operator::I64x2ExtMulHighI32x4U => {
+                self.emit_simd_extmul(true, Assembler::emit_vpmovzxdq, Assembler::emit_vpmuludq)
+            }
+
+            Operator::I8x16Shl => {
+                self.emit_simd_i8x16_shift(Assembler::emit_vpsllw, Assembler::emit_shl)
+            }
+            Operator::I8x16ShrU => {
+                self.emit_simd_i8x16_shift(Assembler::emit_vpsrlw, Assembler::emit_shr)
+            }
+            Operator::I8x16ShrS => {
+                self.emit_simd_shift_count(8);
+                self.assembler
+                    .emit_add(Size::S32, Location::Imm32(8), Location::GPR(GPR::RCX));
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::GPR(GPR::RCX),
+                    Location::XMM(XMM::XMM9),
+                );
+                self.emit_simd_unop_with(|this, x| {
+                    // Sign-extend the bytes to words by placing them in the
+                    // high byte, shift them, and saturate them back to bytes.
+                    this.assembler
+                        .emit_vpunpcklbw(x, XMMOrMemory::XMM(x), XMM::XMM10);
+                    this.assembler
+                        .emit_vpunpckhbw(x, XMMOrMemory::XMM(x), XMM::XMM11);
+                    this.assembler
+                        .emit_vpsraw(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM9), XMM::XMM10);
+                    this.assembler
+                        .emit_vpsraw(XMM::XMM11, XMMOrMemory::XMM(XMM::XMM9), XMM::XMM11);
+                    this.assembler
+                        .emit_vpacksswb(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM11), x);
+                });
+                self.machine.release_temp_gpr(GPR::RCX);
+            }
+            Operator::I16x8Shl => self.emit_simd_shift(16, Assembler::emit_vpsllw),
+            Operator::I16x8ShrS => self.emit_simd_shift(16, Assembler::emit_vpsraw),
+            Operator::I16x8ShrU => self.emit_simd_shift(16, Assembler::emit_vpsrlw),
+            Operator::I32x4Shl => self.emit_simd_shift(32, Assembler::emit_vpslld),
+            Operator::I32x4ShrS => self.emit_simd_shift(32, Assembler::emit_vpsrad),
+            Operator::I32x4ShrU => self.emit_simd_shift(32, Assembler::emit_vpsrld),
+            Operator::I64x2Shl => self.emit_simd_shift(64, Assembler::emit_vpsllq),
+            Operator::I64x2ShrU => self.emit_simd_shift(64, Assembler::emit_vpsrlq),
+            Operator::I64x2ShrS => {
+                // There is no arithmetic right shift of quadwords before
+                // AVX-512, so shift both halves in memory.
+                self.emit_simd_shift_count(64);
+                self.pop_v128_into(XMM::XMM8);
+                let ret = self.push_v128_from(XMM::XMM8);
                 self.assembler
-                    .emit_mov(Size::S64, new, Location::GPR(value));
-
-                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
-                    this.assembler.emit_lock_cmpxchg(
-                        Size::S16,
-                        Location::GPR(value),
-                        Location::Memory(addr, 0),
+                    .emit_sar(Size::S64, Location::GPR(GPR::RCX), ret);
+                self.assembler
+                    .emit_sar(Size::S64, Location::GPR(GPR::RCX), v128_high_half(ret));
+                self.machine.release_temp_gpr(GPR::RCX);
+            }
+
+            Operator::F32x4Add => self.emit_simd_binop(Assembler::emit_vaddps),
+            Operator::F32x4Sub => self.emit_simd_binop(Assembler::emit_vsubps),
+            Operator::F32x4Mul => self.emit_simd_binop(Assembler::emit_vmulps),
+            Operator::F32x4Div => self.emit_simd_binop(Assembler::emit_vdivps),
+            Operator::F32x4Min => self.emit_simd_fp_min(false),
+            Operator::F32x4Max => self.emit_simd_fp_max(false),
+            Operator::F32x4PMin => self.emit_simd_binop_swapped(Assembler::emit_vminps),
+            Operator::F32x4PMax => self.emit_simd_binop_swapped(Assembler::emit_vmaxps),
+            Operator::F64x2Add => self.emit_simd_binop(Assembler::emit_vaddpd),
+            Operator::F64x2Sub => self.emit_simd_binop(Assembler::emit_vsubpd),
+            Operator::F64x2Mul => self.emit_simd_binop(Assembler::emit_vmulpd),
+            Operator::F64x2Div => self.emit_simd_binop(Assembler::emit_vdivpd),
+            Operator::F64x2Min => self.emit_simd_fp_min(true),
+            Operator::F64x2Max => self.emit_simd_fp_max(true),
+            Operator::F64x2PMin => self.emit_simd_binop_swapped(Assembler::emit_vminpd),
+            Operator::F64x2PMax => self.emit_simd_binop_swapped(Assembler::emit_vmaxpd),
+
+            Operator::F32x4Abs => {
+                self.emit_simd_fp_mask(v128_splat(0x7fff_ffff, 32), Assembler::emit_vpand)
+            }
+            Operator::F32x4Neg => {
+                self.emit_simd_fp_mask(v128_splat(0x8000_0000, 32), Assembler::emit_vpxor)
+            }
+            Operator::F64x2Abs => {
+                self.emit_simd_fp_mask(v128_splat(0x7fff_ffff_ffff_ffff, 64), Assembler::emit_vpand)
+            }
+            Operator::F64x2Neg => {
+                self.emit_simd_fp_mask(v128_splat(0x8000_0000_0000_0000, 64), Assembler::emit_vpxor)
+            }
+            Operator::F32x4Sqrt => self.emit_simd_unop(Assembler::emit_vsqrtps),
+            Operator::F32x4Ceil => self.emit_simd_unop(Assembler::emit_vroundps_ceil),
+            Operator::F32x4Floor => self.emit_simd_unop(Assembler::emit_vroundps_floor),
+            Operator::F32x4Trunc => self.emit_simd_unop(Assembler::emit_vroundps_trunc),
+            Operator::F32x4Nearest => self.emit_simd_unop(Assembler::emit_vroundps_nearest),
+            Operator::F64x2Sqrt => self.emit_simd_unop(Assembler::emit_vsqrtpd),
+            Operator::F64x2Ceil => self.emit_simd_unop(Assembler::emit_vroundpd_ceil),
+            Operator::F64x2Floor => self.emit_simd_unop(Assembler::emit_vroundpd_floor),
+            Operator::F64x2Trunc => self.emit_simd_unop(Assembler::emit_vroundpd_trunc),
+            Operator::F64x2Nearest => self.emit_simd_unop(Assembler::emit_vroundpd_nearest),
+
+            Operator::F32x4ConvertI32x4S => self.emit_simd_unop(Assembler::emit_vcvtdq2ps),
+            Operator::F32x4ConvertI32x4U => {
+                self.emit_simd_unop_with(|this, x| {
+                    // Convert the low and high 16 bits separately, since
+                    // `cvtdq2ps` only handles signed integers. The high bits
+                    // are halved first so that they stay positive.
+                    this.emit_load_v128_const(v128_splat(0xffff, 32), XMM::XMM10);
+                    this.assembler
+                        .emit_vpand(x, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM10);
+                    this.assembler
+                        .emit_vpsubd(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                    this.assembler
+                        .emit_vcvtdq2ps(XMMOrMemory::XMM(XMM::XMM10), XMM::XMM10);
+                    this.emit_load_shift_count(1, XMM::XMM12);
+                    this.assembler
+                        .emit_vpsrld(x, XMMOrMemory::XMM(XMM::XMM12), x);
+                    this.assembler.emit_vcvtdq2ps(XMMOrMemory::XMM(x), x);
+                    this.assembler.emit_vaddps(x, XMMOrMemory::XMM(x), x);
+                    this.assembler
+                        .emit_vaddps(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                });
+            }
+            Operator::I32x4TruncSatF32x4S => {
+                self.emit_simd_unop_with(|this, x| {
+                    // Zero the NaN lanes, and fix up the lanes that
+                    // overflowed positively, for which `cvttps2dq` returns
+                    // `0x80000000`.
+                    this.assembler
+                        .emit_vcmpeqps(x, XMMOrMemory::XMM(x), XMM::XMM10);
+                    this.assembler
+                        .emit_vpand(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                    this.assembler
+                        .emit_vpxor(XMM::XMM10, XMMOrMemory::XMM(x), XMM::XMM10);
+                    this.assembler.emit_vcvttps2dq(XMMOrMemory::XMM(x), x);
+                    this.assembler
+                        .emit_vpand(XMM::XMM10, XMMOrMemory::XMM(x), XMM::XMM10);
+                    this.emit_load_shift_count(31, XMM::XMM12);
+                    this.assembler.emit_vpsrad(
+                        XMM::XMM10,
+                        XMMOrMemory::XMM(XMM::XMM12),
+                        XMM::XMM10,
                     );
                     this.assembler
-                        .emit_movzx(Size::S16, Location::GPR(compare), Size::S64, ret);
-                    Ok(())
-                })?;
-                self.assembler.emit_pop(Size::S64, Location::GPR(value));
-                self.machine.release_temp_gpr(compare);
+                        .emit_vpxor(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                });
             }
-            Operator::I64AtomicRmw32CmpxchgU { ref memarg } => {
-                let new = self.pop_value_released();
-                let cmp = self.pop_value_released();
-                let target = self.pop_value_released();
-                let ret = self.machine.acquire_locations(
-                    &mut self.assembler,
-                    &[(WpType::I64, MachineValue::WasmStack(self.value_stack.len()))],
-                    false,
-                )[0];
-                self.value_stack.push(ret);
-
-                let compare = self.machine.reserve_unused_temp_gpr(GPR::RAX);
-                let value = if cmp == Location::GPR(GPR::R14) {
-                    if new == Location::GPR(GPR::R13) {
-                        GPR::R12
-                    } else {
-                        GPR::R13
-                    }
-                } else {
-                    GPR::R14
-                };
-                self.assembler.emit_push(Size::S64, Location::GPR(value));
+            Operator::I32x4TruncSatF32x4U => {
+                self.emit_simd_unop_with(|this, x| {
+                    // Clamp the negative and NaN lanes to zero, then convert
+                    // the lanes at or above 2^31 after subtracting 2^31.
+                    this.emit_v128_zero(XMM::XMM10);
+                    this.assembler
+                        .emit_vmaxps(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                    this.emit_load_v128_const(v128_splat(0x4f00_0000, 32), XMM::XMM10);
+                    this.assembler
+                        .emit_vsubps(x, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM11);
+                    this.assembler.emit_vcmpleps(
+                        XMM::XMM10,
+                        XMMOrMemory::XMM(XMM::XMM11),
+                        XMM::XMM10,
+                    );
+                    this.assembler
+                        .emit_vcvttps2dq(XMMOrMemory::XMM(XMM::XMM11), XMM::XMM11);
+                    this.assembler
+                        .emit_vpxor(XMM::XMM11, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM11);
+                    this.emit_v128_zero(XMM::XMM10);
+                    this.assembler.emit_vpmaxsd(
+                        XMM::XMM11,
+                        XMMOrMemory::XMM(XMM::XMM10),
+                        XMM::XMM11,
+                    );
+                    this.assembler.emit_vcvttps2dq(XMMOrMemory::XMM(x), x);
+                    this.assembler
+                        .emit_vpaddd(x, XMMOrMemory::XMM(XMM::XMM11), x);
+                });
+            }
+            Operator::F64x2ConvertLowI32x4S => self.emit_simd_unop(Assembler::emit_vcvtdq2pd),
+            Operator::F64x2ConvertLowI32x4U => {
+                self.emit_simd_unop_with(|this, x| {
+                    // Build the doubles `2^52 + x` by using the integers as
+                    // their low mantissa bits, and subtract `2^52`.
+                    this.emit_load_v128_const(v128_splat(0x4330_0000, 32), XMM::XMM10);
+                    this.assembler
+                        .emit_vpunpckldq(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                    this.emit_load_v128_const(v128_splat(0x4330_0000_0000_0000, 64), XMM::XMM10);
+                    this.assembler
+                        .emit_vsubpd(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                });
+            }
+            Operator::I32x4TruncSatF64x2SZero => {
+                self.emit_simd_unop_with(|this, x| {
+                    // Clamp the lanes to `i32::MAX`, and the NaN lanes to zero.
+                    this.assembler
+                        .emit_vcmpeqpd(x, XMMOrMemory::XMM(x), XMM::XMM10);
+                    this.emit_load_v128_const(v128_splat(2147483647f64.to_bits(), 64), XMM::XMM11);
+                    this.assembler
+                        .emit_vpand(XMM::XMM10, XMMOrMemory::XMM(XMM::XMM11), XMM::XMM10);
+                    this.assembler
+                        .emit_vminpd(x, XMMOrMemory::XMM(XMM::XMM10), x);
+                    this.assembler.emit_vcvttpd2dq(XMMOrMemory::XMM(x), x);
+                });
+            }
+            Operator::I32x4TruncSatF64x2UZero => {
+                // Clamp the lanes, truncate them, and add `2^52` so that the
+                // low 32 bits of each double hold the integer.
+                self.pop_v128_into(XMM::XMM8);
+                self.emit_v128_zero(XMM::XMM10);
                 self.assembler
-                    .emit_mov(Size::S64, cmp, Location::GPR(compare));
+                    .emit_vmaxpd(XMM::XMM8, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM8);
+                self.emit_load_v128_const(v128_splat(4294967295f64.to_bits(), 64), XMM::XMM10);
                 self.assembler
-                    .emit_mov(Size::S64, new, Location::GPR(value));
+                    .emit_vminpd(XMM::XMM8, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM8);
+                self.assembler
+                    .emit_vroundpd_trunc(XMMOrMemory::XMM(XMM::XMM8), XMM::XMM8);
+                self.emit_load_v128_const(v128_splat(0x4330_0000_0000_0000, 64), XMM::XMM10);
+                self.assembler
+                    .emit_vaddpd(XMM::XMM8, XMMOrMemory::XMM(XMM::XMM10), XMM::XMM8);
+                let ret = self.push_v128_from(XMM::XMM8);
 
-                self.emit_memory_op(target, memarg, true, 1, |this, addr| {
-                    this.assembler.emit_lock_cmpxchg(
-                        Size::S32,
-                        Location::GPR(value),
-                        Location::Memory(addr, 0),
-                    );
-                    this.assembler
-                        .emit_mov(Size::S32, Location::GPR(compare), ret);
-                    Ok(())
-                })?;
-                self.assembler.emit_pop(Size::S64, Location::GPR(value));
-                self.machine.release_temp_gpr(compare);
+                // Pack the two integers into the low half, and zero the high half.
+                let tmp = self.machine.acquire_temp_gpr().unwrap();
+                let (base, disp) = match ret {
+                    Location::Memory(base, disp) => (base, disp),
+                    _ => unreachable!(),
+                };
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::Memory(base, disp + 8),
+                    Location::GPR(tmp),
+                );
+                self.assembler.emit_mov(
+                    Size::S32,
+                    Location::GPR(tmp),
+                    Location::Memory(base, disp + 4),
+                );
+                self.assembler.emit_mov(
+                    Size::S64,
+                    Location::Imm32(0),
+                    Location::Memory(base, disp + 8),
+                );
+                self.machine.release_temp_gpr(tmp);
             }
+            Operator::F32x4DemoteF64x2Zero => self.emit_simd_unop(Assembler::emit_vcvtpd2ps),
+            Operator::F64x2PromoteLowF32x4 => self.emit_simd_unop(Assembler::emit_vcvtps2pd),
+
             _ => {
                 return Err(CodegenError {
                     message: format!("not yet implemented: {:?}", op),
@@ -8197,6 +10033,11 @@ impl<'a> FuncGen<'a> {
         self.mark_address_with_trap_code(TrapCode::BadSignature);
         self.assembler.emit_ud2();
 
+        self.assembler
+            .emit_label(self.special_labels.stack_overflow);
+        self.mark_address_with_trap_code(TrapCode::StackOverflow);
+        self.assembler.emit_ud2();
+
         // Notify the assembler backend to generate necessary code at end of function.
         self.assembler.finalize_function();
 
@@ -8227,7 +10068,7 @@ impl<'a> FuncGen<'a> {
     }
 }
 
-fn type_to_wp_type(ty: Type) -> WpType {
+pub(crate) fn type_to_wp_type(ty: Type) -> WpType {
     match ty {
         Type::I32 => WpType::I32,
         Type::I64 => WpType::I64,
@@ -8239,6 +10080,87 @@ fn type_to_wp_type(ty: Type) -> WpType {
     }
 }
 
+/// Returns the size of a value of type `ty` inside a multi-value return struct.
+///
+/// A `V128` is moved as two 8-byte halves.
+fn value_size(ty: Type) -> Size {
+    match ty {
+        Type::I32 | Type::F32 => Size::S32,
+        _ => Size::S64,
+    }
+}
+
+/// Returns the number of eightbytes a value of type `ty` takes.
+fn value_eightbytes(ty: Type) -> usize {
+    match ty {
+        Type::V128 => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the location of the high half of the `V128` in the stack slot `loc`.
+fn v128_high_half(loc: Location) -> Location {
+    match loc {
+        Location::Memory(base, disp) => Location::Memory(base, disp + 8),
+        _ => unreachable!("a V128 value is not in a stack slot: {:?}", loc),
+    }
+}
+
+/// Returns the number of bytes in a lane of size `sz`.
+fn lane_bytes(sz: Size) -> usize {
+    match sz {
+        Size::S8 => 1,
+        Size::S16 => 2,
+        Size::S32 => 4,
+        Size::S64 => 8,
+    }
+}
+
+/// Returns a `V128` with every lane `lane_bits` wide set to `lane`.
+fn v128_splat(lane: u64, lane_bits: u32) -> u128 {
+    let mut value = 0u128;
+    let mut shift = 0;
+    while shift < 128 {
+        value |= (lane as u128) << shift;
+        shift += lane_bits;
+    }
+    value
+}
+
+/// Converts a location holding a `V128` to an operand of a vector instruction.
+fn xmm_or_memory(loc: Location) -> XMMOrMemory {
+    match loc {
+        Location::XMM(x) => XMMOrMemory::XMM(x),
+        Location::Memory(base, disp) => XMMOrMemory::Memory(base, disp),
+        _ => unreachable!("a V128 value cannot be at {:?}", loc),
+    }
+}
+
+/// Returns the types of the 8-byte parameter slots taken by parameters of
+/// types `params`. A `V128` takes two `I64` slots, low half first.
+fn param_slot_types(params: &[Type]) -> Vec<Type> {
+    params
+        .iter()
+        .flat_map(|&ty| match ty {
+            Type::V128 => vec![Type::I64, Type::I64],
+            ty => vec![ty],
+        })
+        .collect()
+}
+
+/// Splits the locations of call arguments of types `param_types` into one
+/// location per 8-byte parameter slot.
+fn param_slot_locations(param_types: &[WpType], params: &[Location]) -> Vec<Location> {
+    let mut slots = Vec::with_capacity(params.len());
+    for (ty, loc) in param_types.iter().zip(params.iter()) {
+        slots.push(*loc);
+        if *ty == WpType::V128 {
+            slots.push(v128_high_half(*loc));
+        }
+    }
+    slots
+}
+
 // FIXME: This implementation seems to be not enough to resolve all kinds of register dependencies
 // at call place.
 fn sort_call_movs(movs: &mut [(Location, GPR)]) {
@@ -8292,14 +10214,28 @@ fn sort_call_movs(movs: &mut [(Location, GPR)]) {
 pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     let mut a = Assembler::new().unwrap();
 
+    // A function returning multiple values takes a struct-return pointer
+    // before `vmctx` if the results do not fit in registers.
+    let layout = multi_value_return_layout(sig.results());
+    let vmctx_param = match layout {
+        Some(ref layout) if layout.is_sret() => 1,
+        _ => 0,
+    };
+
     // Calculate stack offset.
     let mut stack_offset: u32 = 0;
-    for (i, _param) in sig.params().iter().enumerate() {
-        if let Location::Memory(_, _) = Machine::get_param_location(1 + i) {
+    for (i, _param) in param_slot_types(sig.params()).iter().enumerate() {
+        if let Location::Memory(_, _) = Machine::get_param_location(vmctx_param + 1 + i) {
             stack_offset += 8;
         }
     }
 
+    // Multiple values are returned through a buffer above the stack arguments.
+    let results_offset = stack_offset;
+    if let Some(ref layout) = layout {
+        stack_offset += ((layout.size + 15) / 16 * 16) as u32;
+    }
+
     // Align to 16 bytes. We push two 8-byte registers below, so here we need to ensure stack_offset % 16 == 8.
     if stack_offset % 16 != 8 {
         stack_offset += 8;
@@ -8329,29 +10265,47 @@ pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     ); // args_rets
 
     // Move arguments to their locations.
-    // `callee_vmctx` is already in the first argument register, so no need to move.
+    // `callee_vmctx` is already in the first argument register, so no need to move
+    // unless the struct-return pointer takes its place.
+    if vmctx_param == 1 {
+        a.emit_mov(
+            Size::S64,
+            Machine::get_param_location(0),
+            Machine::get_param_location(1),
+        );
+        a.emit_lea(
+            Size::S64,
+            Location::Memory(GPR::RSP, results_offset as i32),
+            Machine::get_param_location(0),
+        );
+    }
     {
         let mut n_stack_args: usize = 0;
-        for (i, _param) in sig.params().iter().enumerate() {
-            let src_loc = Location::Memory(GPR::R14, (i * 16) as _); // args_rets[i]
-            let dst_loc = Machine::get_param_location(1 + i);
-
-            match dst_loc {
-                Location::GPR(_) => {
-                    a.emit_mov(Size::S64, src_loc, dst_loc);
-                }
-                Location::Memory(_, _) => {
-                    // This location is for reading arguments but we are writing arguments here.
-                    // So recalculate it.
-                    a.emit_mov(Size::S64, src_loc, Location::GPR(GPR::RAX));
-                    a.emit_mov(
-                        Size::S64,
-                        Location::GPR(GPR::RAX),
-                        Location::Memory(GPR::RSP, (n_stack_args * 8) as _),
-                    );
-                    n_stack_args += 1;
+        let mut slot: usize = 0;
+        for (i, ty) in sig.params().iter().enumerate() {
+            // A `V128` takes two parameter slots.
+            for half in 0..value_eightbytes(*ty) {
+                let src_loc = Location::Memory(GPR::R14, (i * 16 + half * 8) as _); // args_rets[i]
+                let dst_loc = Machine::get_param_location(vmctx_param + 1 + slot);
+                slot += 1;
+
+                match dst_loc {
+                    Location::GPR(_) => {
+                        a.emit_mov(Size::S64, src_loc, dst_loc);
+                    }
+                    Location::Memory(_, _) => {
+                        // This location is for reading arguments but we are writing arguments here.
+                        // So recalculate it.
+                        a.emit_mov(Size::S64, src_loc, Location::GPR(GPR::RAX));
+                        a.emit_mov(
+                            Size::S64,
+                            Location::GPR(GPR::RAX),
+                            Location::Memory(GPR::RSP, (n_stack_args * 8) as _),
+                        );
+                        n_stack_args += 1;
+                    }
+                    _ => unreachable!(),
                 }
-                _ => unreachable!(),
             }
         }
     }
@@ -8359,6 +10313,40 @@ pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     // Call.
     a.emit_call_location(Location::GPR(GPR::R15));
 
+    // Unpack multiple return values into `args_rets`.
+    if let Some(ref layout) = layout {
+        if let Some(ref registers) = layout.registers {
+            for (i, reg) in registers.iter().enumerate() {
+                let src = match *reg {
+                    X64Register::GPR(x) => Location::GPR(x),
+                    X64Register::XMM(x) => Location::XMM(x),
+                };
+                a.emit_mov(
+                    Size::S64,
+                    src,
+                    Location::Memory(GPR::RSP, (results_offset as usize + i * 8) as i32),
+                );
+            }
+        }
+        for (i, ty) in sig.results().iter().enumerate() {
+            for half in 0..value_eightbytes(*ty) {
+                a.emit_mov(
+                    value_size(*ty),
+                    Location::Memory(
+                        GPR::RSP,
+                        (results_offset as usize + layout.offsets[i] + half * 8) as i32,
+                    ),
+                    Location::GPR(GPR::RAX),
+                );
+                a.emit_mov(
+                    Size::S64,
+                    Location::GPR(GPR::RAX),
+                    Location::Memory(GPR::R14, (i * 16 + half * 8) as i32),
+                );
+            }
+        }
+    }
+
     // Restore stack.
     a.emit_add(
         Size::S64,
@@ -8367,12 +10355,20 @@ pub fn gen_std_trampoline(sig: &FunctionType) -> FunctionBody {
     );
 
     // Write return value.
-    if !sig.results().is_empty() {
+    if sig.results().len() == 1 {
         a.emit_mov(
             Size::S64,
             Location::GPR(GPR::RAX),
             Location::Memory(GPR::R14, 0),
         );
+        // The high half of a `V128` is returned in RDX.
+        if sig.results()[0] == Type::V128 {
+            a.emit_mov(
+                Size::S64,
+                Location::GPR(GPR::RDX),
+                Location::Memory(GPR::R14, 8),
+            );
+        }
     }
 
     // Restore callee-saved registers.
@@ -8394,47 +10390,82 @@ pub fn gen_std_dynamic_import_trampoline(
 ) -> FunctionBody {
     let mut a = Assembler::new().unwrap();
 
+    // Multiple return values that do not fit in registers are written through
+    // a struct-return pointer passed before `vmctx`.
+    let layout = multi_value_return_layout(sig.results());
+    let sret = layout.as_ref().map_or(false, |layout| layout.is_sret());
+
     // Allocate argument array.
-    let stack_offset: usize = 16 * std::cmp::max(sig.params().len(), sig.results().len()) + 8; // 16 bytes each + 8 bytes sysv call padding
+    let values_size = 16 * std::cmp::max(sig.params().len(), sig.results().len());
+    let stack_offset: usize = if sret {
+        values_size + 16 + 8 // 16 bytes each + 16 bytes for the struct-return pointer + 8 bytes sysv call padding
+    } else {
+        values_size + 8 // 16 bytes each + 8 bytes sysv call padding
+    };
     a.emit_sub(
         Size::S64,
         Location::Imm32(stack_offset as _),
         Location::GPR(GPR::RSP),
     );
 
+    // Save the struct-return pointer and move `vmctx` to the first argument register.
+    if sret {
+        a.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::RDI),
+            Location::Memory(GPR::RSP, values_size as i32),
+        );
+        a.emit_mov(Size::S64, Location::GPR(GPR::RSI), Location::GPR(GPR::RDI));
+    }
+
     // Copy arguments.
     if !sig.params().is_empty() {
         let mut argalloc = ArgumentRegisterAllocator::default();
+        if sret {
+            argalloc.next(Type::I64).unwrap(); // skip struct-return pointer
+        }
         argalloc.next(Type::I64).unwrap(); // skip VMContext
 
         let mut stack_param_count: usize = 0;
 
         for (i, ty) in sig.params().iter().enumerate() {
-            let source_loc = match argalloc.next(*ty) {
-                Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
-                Some(X64Register::XMM(xmm)) => Location::XMM(xmm),
-                None => {
-                    a.emit_mov(
-                        Size::S64,
-                        Location::Memory(GPR::RSP, (stack_offset + 8 + stack_param_count * 8) as _),
-                        Location::GPR(GPR::RAX),
-                    );
-                    stack_param_count += 1;
-                    Location::GPR(GPR::RAX)
-                }
+            // A `V128` is passed as two `I64` halves.
+            let slot_types: &[Type] = match ty {
+                Type::V128 => &[Type::I64, Type::I64],
+                _ => std::slice::from_ref(ty),
             };
-            a.emit_mov(
-                Size::S64,
-                source_loc,
-                Location::Memory(GPR::RSP, (i * 16) as _),
-            );
+            for (half, slot_ty) in slot_types.iter().enumerate() {
+                let source_loc = match argalloc.next(*slot_ty) {
+                    Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
+                    Some(X64Register::XMM(xmm)) => Location::XMM(xmm),
+                    None => {
+                        a.emit_mov(
+                            Size::S64,
+                            Location::Memory(
+                                GPR::RSP,
+                                (stack_offset + 8 + stack_param_count * 8) as _,
+                            ),
+                            Location::GPR(GPR::RAX),
+                        );
+                        stack_param_count += 1;
+                        Location::GPR(GPR::RAX)
+                    }
+                };
+                a.emit_mov(
+                    Size::S64,
+                    source_loc,
+                    Location::Memory(GPR::RSP, (i * 16 + half * 8) as _),
+                );
+            }
 
             // Zero upper 64 bits.
-            a.emit_mov(
-                Size::S64,
-                Location::Imm32(0),
-                Location::Memory(GPR::RSP, (i * 16 + 8) as _),
-            );
+            if *ty != Type::V128 {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Imm32(0),
+                    Location::Memory(GPR::RSP, (i * 16 + 8) as _),
+                );
+            }
         }
     }
 
@@ -8455,13 +10486,69 @@ pub fn gen_std_dynamic_import_trampoline(
     a.emit_call_location(Location::GPR(GPR::RAX));
 
     // Fetch return value.
-    if !sig.results().is_empty() {
-        assert_eq!(sig.results().len(), 1);
-        a.emit_mov(
-            Size::S64,
-            Location::Memory(GPR::RSP, 0),
-            Location::GPR(GPR::RAX),
-        );
+    match layout {
+        Some(ref layout) if layout.is_sret() => {
+            // The struct-return pointer is also returned in RAX.
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::RSP, values_size as i32),
+                Location::GPR(GPR::RAX),
+            );
+            for (i, ty) in sig.results().iter().enumerate() {
+                for half in 0..value_eightbytes(*ty) {
+                    a.emit_mov(
+                        Size::S64,
+                        Location::Memory(GPR::RSP, (i * 16 + half * 8) as i32),
+                        Location::GPR(GPR::RCX),
+                    );
+                    a.emit_mov(
+                        value_size(*ty),
+                        Location::GPR(GPR::RCX),
+                        Location::Memory(GPR::RAX, (layout.offsets[i] + half * 8) as i32),
+                    );
+                }
+            }
+        }
+        Some(ref layout) => {
+            // Pack the values array into the struct layout in place. A field
+            // never starts after the entry its value comes from.
+            for (i, ty) in sig.results().iter().enumerate() {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(GPR::RSP, (i * 16) as i32),
+                    Location::GPR(GPR::RCX),
+                );
+                a.emit_mov(
+                    value_size(*ty),
+                    Location::GPR(GPR::RCX),
+                    Location::Memory(GPR::RSP, layout.offsets[i] as i32),
+                );
+            }
+            for (i, reg) in layout.registers.as_ref().unwrap().iter().enumerate() {
+                let dst = match *reg {
+                    X64Register::GPR(x) => Location::GPR(x),
+                    X64Register::XMM(x) => Location::XMM(x),
+                };
+                a.emit_mov(Size::S64, Location::Memory(GPR::RSP, (i * 8) as i32), dst);
+            }
+        }
+        None => {
+            if !sig.results().is_empty() {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(GPR::RSP, 0),
+                    Location::GPR(GPR::RAX),
+                );
+            }
+            // The high half of a `V128` is returned in RDX.
+            if let [Type::V128] = sig.results() {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Memory(GPR::RSP, 8),
+                    Location::GPR(GPR::RDX),
+                );
+            }
+        }
     }
 
     // Release values array.
@@ -8495,19 +10582,31 @@ pub fn gen_import_call_trampoline(
     //
     // FIXME: This is only a workaround. We should fix singlepass to use the standard CC.
 
+    // A struct-return pointer, if any, comes first and shifts `vmctx` and the
+    // arguments by one register.
+    let sret = multi_value_return_layout(sig.results()).map_or(false, |layout| layout.is_sret());
+    let (vmctx_reg, param_regs): (GPR, &[GPR]) = if sret {
+        (GPR::RSI, &[GPR::RDX, GPR::RCX, GPR::R8, GPR::R9])
+    } else {
+        (GPR::RDI, &[GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9])
+    };
+    let n_param_regs = param_regs.len();
+
+    // A `V128` argument takes two integer parameter slots.
+    let param_slots = param_slot_types(sig.params());
+
     // Translation is expensive, so only do it if needed.
-    if sig
-        .params()
+    if param_slots
         .iter()
         .any(|&x| x == Type::F32 || x == Type::F64)
     {
         let mut param_locations: Vec<Location> = vec![];
 
         // Allocate stack space for arguments.
-        let stack_offset: i32 = if sig.params().len() > 5 {
-            5 * 8
+        let stack_offset: i32 = if param_slots.len() > n_param_regs {
+            (n_param_regs as i32) * 8
         } else {
-            (sig.params().len() as i32) * 8
+            (param_slots.len() as i32) * 8
         };
         if stack_offset > 0 {
             a.emit_sub(
@@ -8518,24 +10617,25 @@ pub fn gen_import_call_trampoline(
         }
 
         // Store all arguments to the stack to prevent overwrite.
-        for i in 0..sig.params().len() {
-            let loc = match i {
-                0..=4 => {
-                    static PARAM_REGS: &[GPR] = &[GPR::RSI, GPR::RDX, GPR::RCX, GPR::R8, GPR::R9];
-                    let loc = Location::Memory(GPR::RSP, (i * 8) as i32);
-                    a.emit_mov(Size::S64, Location::GPR(PARAM_REGS[i]), loc);
-                    loc
-                }
-                _ => Location::Memory(GPR::RSP, stack_offset + 8 + ((i - 5) * 8) as i32),
+        for i in 0..param_slots.len() {
+            let loc = if i < n_param_regs {
+                let loc = Location::Memory(GPR::RSP, (i * 8) as i32);
+                a.emit_mov(Size::S64, Location::GPR(param_regs[i]), loc);
+                loc
+            } else {
+                Location::Memory(GPR::RSP, stack_offset + 8 + ((i - n_param_regs) * 8) as i32)
             };
             param_locations.push(loc);
         }
 
         // Copy arguments.
         let mut argalloc = ArgumentRegisterAllocator::default();
+        if sret {
+            argalloc.next(Type::I64).unwrap(); // skip struct-return pointer
+        }
         argalloc.next(Type::I64).unwrap(); // skip VMContext
         let mut caller_stack_offset: i32 = 0;
-        for (i, ty) in sig.params().iter().enumerate() {
+        for (i, ty) in param_slots.iter().enumerate() {
             let prev_loc = param_locations[i];
             let target = match argalloc.next(*ty) {
                 Some(X64Register::GPR(gpr)) => Location::GPR(gpr),
@@ -8576,13 +10676,13 @@ pub fn gen_import_call_trampoline(
 
     a.emit_mov(
         Size::S64,
-        Location::Memory(GPR::RDI, offset as i32), // function pointer
+        Location::Memory(vmctx_reg, offset as i32), // function pointer
         Location::GPR(GPR::RAX),
     );
     a.emit_mov(
         Size::S64,
-        Location::Memory(GPR::RDI, offset as i32 + 8), // target vmctx
-        Location::GPR(GPR::RDI),
+        Location::Memory(vmctx_reg, offset as i32 + 8), // target vmctx
+        Location::GPR(vmctx_reg),
     );
     a.emit_host_redirection(GPR::RAX);
 
@@ -8601,35 +10701,35 @@ pub fn gen_import_call_trampoline(
 // min (for least) or max (for greatest), when rounding towards zero.
 
 /// Greatest Exact Float (32 bits) less-than i32::MIN when rounding towards zero.
-const GEF32_LT_I32_MIN: f32 = -2147483904.0;
+pub(crate) const GEF32_LT_I32_MIN: f32 = -2147483904.0;
 /// Least Exact Float (32 bits) greater-than i32::MAX when rounding towards zero.
-const LEF32_GT_I32_MAX: f32 = 2147483648.0;
+pub(crate) const LEF32_GT_I32_MAX: f32 = 2147483648.0;
 /// Greatest Exact Float (32 bits) less-than i64::MIN when rounding towards zero.
-const GEF32_LT_I64_MIN: f32 = -9223373136366403584.0;
+pub(crate) const GEF32_LT_I64_MIN: f32 = -9223373136366403584.0;
 /// Least Exact Float (32 bits) greater-than i64::MAX when rounding towards zero.
-const LEF32_GT_I64_MAX: f32 = 9223372036854775808.0;
+pub(crate) const LEF32_GT_I64_MAX: f32 = 9223372036854775808.0;
 /// Greatest Exact Float (32 bits) less-than u32::MIN when rounding towards zero.
-const GEF32_LT_U32_MIN: f32 = -1.0;
+pub(crate) const GEF32_LT_U32_MIN: f32 = -1.0;
 /// Least Exact Float (32 bits) greater-than u32::MAX when rounding towards zero.
-const LEF32_GT_U32_MAX: f32 = 4294967296.0;
+pub(crate) const LEF32_GT_U32_MAX: f32 = 4294967296.0;
 /// Greatest Exact Float (32 bits) less-than u64::MIN when rounding towards zero.
-const GEF32_LT_U64_MIN: f32 = -1.0;
+pub(crate) const GEF32_LT_U64_MIN: f32 = -1.0;
 /// Least Exact Float (32 bits) greater-than u64::MAX when rounding towards zero.
-const LEF32_GT_U64_MAX: f32 = 18446744073709551616.0;
+pub(crate) const LEF32_GT_U64_MAX: f32 = 18446744073709551616.0;
 
 /// Greatest Exact Float (64 bits) less-than i32::MIN when rounding towards zero.
-const GEF64_LT_I32_MIN: f64 = -2147483649.0;
+pub(crate) const GEF64_LT_I32_MIN: f64 = -2147483649.0;
 /// Least Exact Float (64 bits) greater-than i32::MAX when rounding towards zero.
-const LEF64_GT_I32_MAX: f64 = 2147483648.0;
+pub(crate) const LEF64_GT_I32_MAX: f64 = 2147483648.0;
 /// Greatest Exact Float (64 bits) less-than i64::MIN when rounding towards zero.
-const GEF64_LT_I64_MIN: f64 = -9223372036854777856.0;
+pub(crate) const GEF64_LT_I64_MIN: f64 = -9223372036854777856.0;
 /// Least Exact Float (64 bits) greater-than i64::MAX when rounding towards zero.
-const LEF64_GT_I64_MAX: f64 = 9223372036854775808.0;
+pub(crate) const LEF64_GT_I64_MAX: f64 = 9223372036854775808.0;
 /// Greatest Exact Float (64 bits) less-than u32::MIN when rounding towards zero.
-const GEF64_LT_U32_MIN: f64 = -1.0;
+pub(crate) const GEF64_LT_U32_MIN: f64 = -1.0;
 /// Least Exact Float (64 bits) greater-than u32::MAX when rounding towards zero.
-const LEF64_GT_U32_MAX: f64 = 4294967296.0;
+pub(crate) const LEF64_GT_U32_MAX: f64 = 4294967296.0;
 /// Greatest Exact Float (64 bits) less-than u64::MIN when rounding towards zero.
-const GEF64_LT_U64_MIN: f64 = -1.0;
+pub(crate) const GEF64_LT_U64_MIN: f64 = -1.0;
 /// Least Exact Float (64 bits) greater-than u64::MAX when rounding towards zero.
-const LEF64_GT_U64_MAX: f64 = 18446744073709551616.0;
+pub(crate) const LEF64_GT_U64_MAX: f64 = 18446744073709551616.0;
@@ -2,24 +2,39 @@
 // Allow unused imports while developing.
 #![allow(unused_imports, dead_code)]
 
+use crate::codegen_arm64::{
+    gen_import_call_trampoline_arm64, gen_std_dynamic_import_trampoline_arm64,
+    gen_std_trampoline_arm64, FuncGenARM64,
+};
 use crate::codegen_x64::{
     gen_import_call_trampoline, gen_std_dynamic_import_trampoline, gen_std_trampoline,
     CodegenError, FuncGen,
 };
 use crate::config::Singlepass;
+#[cfg(feature = "unwind")]
+use crate::unwind::{create_systemv_cie, create_unwind_info_from_insts};
+#[cfg(feature = "unwind")]
+use gimli::write::{Address, EhFrame, FrameTable};
 use loupe::MemoryUsage;
 use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::TrapInformation;
+#[cfg(feature = "unwind")]
+use wasmer_compiler::WriterRelocate;
+use wasmer_compiler::{
+    Architecture, CallingConvention, CompileModuleInfo, CompilerConfig, CpuFeature,
+    MiddlewareBinaryReader, ModuleMiddlewareChain, ModuleTranslationState, OperatingSystem, Target,
+};
 use wasmer_compiler::{
-    Architecture, CompileModuleInfo, CompilerConfig, MiddlewareBinaryReader, ModuleMiddlewareChain,
-    ModuleTranslationState, OperatingSystem, Target,
+    Compilation, CompileError, CompiledFunction, CompiledFunctionUnwindInfo, Compiler,
+    CustomSection, Dwarf, SectionIndex,
 };
-use wasmer_compiler::{Compilation, CompileError, CompiledFunction, Compiler, SectionIndex};
 use wasmer_compiler::{FunctionBody, FunctionBodyData};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
-use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex};
-use wasmer_vm::{ModuleInfo, TrapCode, VMOffsets};
+use wasmer_types::{
+    FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex, Type,
+};
+use wasmer_vm::{MemoryStyle, ModuleInfo, TableStyle, TrapCode, VMOffsets};
 
 /// A compiler that compiles a WebAssembly module with Singlepass.
 /// It does the compilation in one pass
@@ -55,11 +70,27 @@ impl Compiler for SinglepassCompiler {
                 OperatingSystem::Windows.to_string(),
             ));
         }
-        if let Architecture::X86_32(arch) = target.triple().architecture {
-            return Err(CompileError::UnsupportedTarget(arch.to_string()));
-        }
-        if compile_info.features.multi_value {
-            return Err(CompileError::UnsupportedFeature("multivalue".to_string()));
+        match target.triple().architecture {
+            Architecture::X86_64 => {
+                if compile_info.features.simd
+                    && !(target.cpu_features().contains(CpuFeature::AVX)
+                        && target.cpu_features().contains(CpuFeature::SSE41))
+                {
+                    // The SIMD operators are lowered to AVX instructions, some of
+                    // which come from SSE4.1.
+                    return Err(CompileError::UnsupportedFeature(
+                        "simd without AVX and SSE4.1".to_string(),
+                    ));
+                }
+            }
+            Architecture::Aarch64(_) => {
+                if compile_info.features.simd {
+                    return Err(CompileError::UnsupportedFeature(
+                        "simd on aarch64".to_string(),
+                    ));
+                }
+            }
+            arch => return Err(CompileError::UnsupportedTarget(arch.to_string())),
         }
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
@@ -68,6 +99,22 @@ impl Compiler for SinglepassCompiler {
         compile_info.module = Arc::new(module);
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
+        if module.memories.values().any(|memory| memory.memory64) {
+            return Err(CompileError::UnsupportedFeature(
+                "64-bit memories".to_string(),
+            ));
+        }
+        if let Architecture::Aarch64(_) = target.triple().architecture {
+            return compile_module_arm64(
+                &self.config,
+                target,
+                module,
+                &vmoffsets,
+                memory_styles,
+                table_styles,
+                function_body_inputs,
+            );
+        }
         let import_trampolines: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
             .map(FunctionIndex::new)
             .collect::<Vec<_>>()
@@ -154,6 +201,156 @@ impl Compiler for SinglepassCompiler {
     }
 }
 
+/// Compiles a module for AArch64.
+fn compile_module_arm64(
+    config: &Singlepass,
+    target: &Target,
+    module: &ModuleInfo,
+    vmoffsets: &VMOffsets,
+    memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
+    table_styles: &PrimaryMap<TableIndex, TableStyle>,
+    function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+) -> Result<Compilation, CompileError> {
+    if module
+        .signatures
+        .values()
+        .any(|sig| sig.results().len() > 1)
+    {
+        return Err(CompileError::UnsupportedFeature(
+            "multi-value on aarch64".to_string(),
+        ));
+    }
+    // The arguments and results are moved 64 bits at a time.
+    if module.signatures.values().any(|sig| {
+        sig.params()
+            .iter()
+            .chain(sig.results())
+            .any(|ty| *ty == Type::V128)
+    }) {
+        return Err(CompileError::UnsupportedFeature(
+            "v128 parameters and results on aarch64".to_string(),
+        ));
+    }
+
+    let mut custom_sections: PrimaryMap<SectionIndex, CustomSection> = (0..module
+        .num_imported_functions)
+        .map(FunctionIndex::new)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|i| {
+            gen_import_call_trampoline_arm64(vmoffsets, i, &module.signatures[module.functions[i]])
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect();
+    #[cfg_attr(not(feature = "unwind"), allow(unused_variables))]
+    let (functions, unwind_ops): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
+        .iter()
+        .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
+        .par_iter()
+        .map(|(i, input)| {
+            let middleware_chain = config.middlewares.generate_function_middleware_chain(*i);
+            let mut reader =
+                MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+            reader.set_middleware_chain(middleware_chain);
+
+            // This local list excludes arguments.
+            let mut locals = vec![];
+            let num_locals = reader.read_local_count()?;
+            for _ in 0..num_locals {
+                let (count, ty) = reader.read_local_decl()?;
+                for _ in 0..count {
+                    locals.push(ty);
+                }
+            }
+
+            let mut generator = FuncGenARM64::new(
+                module,
+                config,
+                vmoffsets,
+                memory_styles,
+                table_styles,
+                *i,
+                &locals,
+            )
+            .map_err(to_compile_error)?;
+
+            while generator.has_control_frames() {
+                generator.set_srcloc(reader.original_position() as u32);
+                let op = reader.read_operator()?;
+                generator.feed_operator(op).map_err(to_compile_error)?;
+            }
+
+            Ok(generator.finalize(&input))
+        })
+        .collect::<Result<Vec<_>, CompileError>>()?
+        .into_iter()
+        .unzip();
+    #[cfg_attr(not(feature = "unwind"), allow(unused_mut))]
+    let mut functions = functions
+        .into_iter()
+        .collect::<PrimaryMap<LocalFunctionIndex, CompiledFunction>>();
+
+    // Describe how to unwind the frames of the functions in an `.eh_frame`
+    // section, placed after the import trampolines.
+    #[cfg(feature = "unwind")]
+    let dwarf = if !functions.is_empty()
+        && target.triple().default_calling_convention() == Ok(CallingConvention::SystemV)
+    {
+        let mut dwarf_frametable = FrameTable::default();
+        let cie_id = dwarf_frametable.add_cie(create_systemv_cie());
+        for ((i, function), ops) in functions.iter_mut().zip(unwind_ops.iter()) {
+            let body_len = function.body.body.len();
+            if let Some(unwind_info) = create_unwind_info_from_insts(ops, body_len) {
+                dwarf_frametable.add_fde(
+                    cie_id,
+                    unwind_info.to_fde(Address::Symbol {
+                        symbol: WriterRelocate::FUNCTION_SYMBOL,
+                        addend: i.index() as _,
+                    }),
+                );
+                function.body.unwind_info = Some(CompiledFunctionUnwindInfo::Dwarf);
+            }
+        }
+        let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
+        dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+        let eh_frame_section_index = custom_sections.push(eh_frame.0.into_section());
+        Some(Dwarf::new(eh_frame_section_index))
+    } else {
+        None
+    };
+    #[cfg(not(feature = "unwind"))]
+    let dwarf = None;
+
+    let function_call_trampolines = module
+        .signatures
+        .values()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .cloned()
+        .map(gen_std_trampoline_arm64)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect::<PrimaryMap<_, _>>();
+
+    let dynamic_function_trampolines = module
+        .imported_function_types()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|func_type| gen_std_dynamic_import_trampoline_arm64(vmoffsets, &func_type))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
+
+    Ok(Compilation::new(
+        functions,
+        custom_sections,
+        function_call_trampolines,
+        dynamic_function_trampolines,
+        dwarf,
+    ))
+}
+
 trait ToCompileError {
     fn to_compile_error(self) -> CompileError;
 }
@@ -173,7 +370,7 @@ mod tests {
     use super::*;
     use std::str::FromStr;
     use target_lexicon::triple;
-    use wasmer_compiler::{CpuFeature, Features, Triple};
+    use wasmer_compiler::{Features, ModuleEnvironment, RelocationKind, RelocationTarget, Triple};
     use wasmer_vm::{MemoryStyle, TableStyle};
 
     fn dummy_compilation_ingredients<'a>() -> (
@@ -223,4 +420,86 @@ mod tests {
             error => panic!("Unexpected error: {:?}", error),
         };
     }
+
+    #[test]
+    fn compiles_for_aarch64() {
+        let compiler = SinglepassCompiler::new(Singlepass::default());
+
+        // (module
+        //   (func $f (call $g))
+        //   (func $g))
+        let wasm: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+            0x03, 0x03, 0x02, 0x00, 0x00, // function section
+            0x0a, 0x09, 0x02, 0x04, 0x00, 0x10, 0x01, 0x0b, 0x02, 0x00, 0x0b, // code section
+        ];
+        let translation = ModuleEnvironment::new().translate(wasm).unwrap();
+        let mut info = CompileModuleInfo {
+            features: Features::new(),
+            module: Arc::new(translation.module),
+            memory_styles: PrimaryMap::<MemoryIndex, MemoryStyle>::new(),
+            table_styles: PrimaryMap::<TableIndex, TableStyle>::new(),
+        };
+
+        let aarch64 = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let compilation = compiler
+            .compile_module(
+                &aarch64,
+                &mut info,
+                translation.module_translation_state.as_ref().unwrap(),
+                translation.function_body_inputs,
+            )
+            .unwrap();
+
+        // The call from `$f` to `$g` is relocated.
+        let relocations = compilation.get_relocations();
+        assert!(relocations[LocalFunctionIndex::new(0)]
+            .iter()
+            .any(|r| r.kind == RelocationKind::Abs8
+                && r.reloc_target == RelocationTarget::LocalFunc(LocalFunctionIndex::new(1))));
+
+        // Both functions are described in the `.eh_frame` section.
+        if cfg!(feature = "unwind") {
+            assert!(compilation.get_debug().is_some());
+            for (_, body) in compilation.get_function_bodies().iter() {
+                match body.unwind_info {
+                    Some(CompiledFunctionUnwindInfo::Dwarf) => {}
+                    ref other => panic!("Unexpected unwind info: {:?}", other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors_for_v128_signatures_on_aarch64() {
+        let compiler = SinglepassCompiler::new(Singlepass::default());
+
+        let aarch64 = Target::new(triple!("aarch64-unknown-linux-gnu"), CpuFeature::set());
+        let (mut info, translation, inputs) = dummy_compilation_ingredients();
+        let mut module = ModuleInfo::new();
+        module
+            .signatures
+            .push(FunctionType::new(vec![Type::V128], vec![]));
+        info.module = Arc::new(module);
+        let result = compiler.compile_module(&aarch64, &mut info, &translation, inputs);
+        match result.unwrap_err() {
+            CompileError::UnsupportedFeature(_) => {}
+            error => panic!("Unexpected error: {:?}", error),
+        };
+    }
+
+    #[test]
+    fn errors_for_simd_without_avx() {
+        let compiler = SinglepassCompiler::new(Singlepass::default());
+
+        let linux64 = Target::new(triple!("x86_64-unknown-linux-gnu"), CpuFeature::SSE2.into());
+        let (mut info, translation, inputs) = dummy_compilation_ingredients();
+        info.features.simd(true);
+        let result = compiler.compile_module(&linux64, &mut info, &translation, inputs);
+        match result.unwrap_err() {
+            CompileError::UnsupportedFeature(_) => {}
+            error => panic!("Unexpected error: {:?}", error),
+        };
+    }
 }
@@ -0,0 +1,967 @@
+pub use crate::arm64_decl::{GPR, NEON};
+use dynasmrt::{aarch64::Assembler, AssemblyOffset, DynamicLabel, DynasmApi, DynasmLabelApi};
+
+/// Dynasm proc-macro checks for an `.arch` expression in a source file to
+/// determine the architecture it should use. Since `emitter_x64` already sets
+/// it to x64, every invocation in this file sets it again.
+macro_rules! dynasm {
+    ($a:expr ; $($tt:tt)*) => {
+        dynasm::dynasm!(
+            $a
+            ; .arch aarch64
+            ; $($tt)*
+        )
+    };
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Location {
+    Imm8(u8),
+    Imm32(u32),
+    Imm64(u64),
+    GPR(GPR),
+    NEON(NEON),
+    Memory(GPR, i32),
+}
+
+impl Location {
+    /// Returns the value of an immediate location.
+    pub fn imm_value(&self) -> Option<u64> {
+        match *self {
+            Location::Imm8(x) => Some(x as u64),
+            Location::Imm32(x) => Some(x as u64),
+            Location::Imm64(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[allow(dead_code)]
+pub enum Condition {
+    /// Equal.
+    Eq,
+    /// Not equal.
+    Ne,
+    /// Carry set, unsigned higher or same.
+    Cs,
+    /// Carry clear, unsigned lower.
+    Cc,
+    /// Negative, or less than after a floating point comparison.
+    Mi,
+    /// Positive or zero.
+    Pl,
+    /// Overflow, or unordered after a floating point comparison.
+    Vs,
+    /// No overflow.
+    Vc,
+    /// Unsigned higher.
+    Hi,
+    /// Unsigned lower or same.
+    Ls,
+    /// Signed greater than or equal.
+    Ge,
+    /// Signed less than.
+    Lt,
+    /// Signed greater than.
+    Gt,
+    /// Signed less than or equal.
+    Le,
+    /// Always.
+    Al,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Size {
+    S8,
+    S16,
+    S32,
+    S64,
+}
+
+/// Emits AArch64 instructions.
+///
+/// X16 and X17 are reserved as scratch registers for the emitter itself: X17
+/// holds addresses that don't fit the immediate offset of a load or store and
+/// immediates that don't fit an instruction, and X16 holds values moved
+/// between two memory locations. V31 is reserved as a scratch NEON register.
+/// None of them is ever allocated by the code generator.
+pub trait EmitterARM64 {
+    type Label: Copy;
+    type Offset;
+
+    fn get_label(&mut self) -> Self::Label;
+    fn get_offset(&self) -> Self::Offset;
+
+    fn emit_u64(&mut self, x: u64);
+
+    fn emit_label(&mut self, label: Self::Label);
+
+    fn emit_nop(&mut self);
+
+    /// Moves a 32-bit or 64-bit value between any two locations. A 32-bit
+    /// move into a general purpose register zeroes its upper half.
+    fn emit_mov(&mut self, sz: Size, src: Location, dst: Location);
+    fn emit_mov_imm(&mut self, sz: Size, val: u64, dst: GPR);
+    /// Loads a value of size `sz_src` from memory, extending it to `sz_dst`.
+    fn emit_load(&mut self, sz_src: Size, signed: bool, sz_dst: Size, src: Location, dst: GPR);
+    /// Stores the low `sz` bytes of `src` to memory.
+    fn emit_store(&mut self, sz: Size, src: GPR, dst: Location);
+    /// Loads a 64-bit value stored inline into `dst`, returning the offset of
+    /// the value so that it can be relocated.
+    fn emit_ldr_literal(&mut self, val: u64, dst: GPR) -> usize;
+
+    fn emit_add(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_adds(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_sub(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    /// Computes `src1 + (src2 << lsl)` on 64 bits.
+    fn emit_add_lsl(&mut self, src1: GPR, src2: GPR, lsl: u32, dst: GPR);
+    fn emit_mul(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_sdiv(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_udiv(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    /// Computes `src3 - src1 * src2`.
+    fn emit_msub(&mut self, sz: Size, src1: GPR, src2: GPR, src3: GPR, dst: GPR);
+    fn emit_and(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_orr(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_eor(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_lsl(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_lsr(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_asr(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_ror(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR);
+    fn emit_neg(&mut self, sz: Size, src: GPR, dst: GPR);
+    fn emit_clz(&mut self, sz: Size, src: GPR, dst: GPR);
+    fn emit_rbit(&mut self, sz: Size, src: GPR, dst: GPR);
+    /// Counts the bits set in `src`, through V31.
+    fn emit_cnt(&mut self, sz: Size, src: GPR, dst: GPR);
+    /// Sign-extends the low `sz_src` bits of `src`.
+    fn emit_sext(&mut self, sz_src: Size, sz_dst: Size, src: GPR, dst: GPR);
+
+    fn emit_cmp(&mut self, sz: Size, left: GPR, right: Location);
+    /// Sets `dst` to 1 if `condition` holds and to 0 otherwise.
+    fn emit_cset(&mut self, condition: Condition, dst: GPR);
+
+    fn emit_b_label(&mut self, label: Self::Label);
+    fn emit_bcond_label(&mut self, condition: Condition, label: Self::Label);
+    fn emit_cbz_label(&mut self, sz: Size, reg: GPR, label: Self::Label);
+    fn emit_cbnz_label(&mut self, sz: Size, reg: GPR, label: Self::Label);
+    fn emit_adr_label(&mut self, label: Self::Label, dst: GPR);
+    fn emit_b_register(&mut self, reg: GPR);
+    fn emit_call_register(&mut self, reg: GPR);
+    fn emit_ret(&mut self);
+    fn emit_udf(&mut self);
+
+    /// Pushes two registers, keeping the stack pointer 16-byte aligned.
+    fn emit_push_pair(&mut self, reg1: GPR, reg2: GPR);
+    /// Pops two registers pushed by `emit_push_pair`.
+    fn emit_pop_pair(&mut self, reg1: GPR, reg2: GPR);
+    fn emit_mov_from_sp(&mut self, dst: GPR);
+    fn emit_mov_to_sp(&mut self, src: GPR);
+    fn emit_add_sp(&mut self, imm: u32);
+    fn emit_sub_sp(&mut self, imm: u32);
+
+    fn emit_fadd(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fsub(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmul(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fdiv(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmin(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fmax(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON);
+    fn emit_fneg(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fabs(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fsqrt(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_frintn(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_frintm(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_frintp(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_frintz(&mut self, sz: Size, src: NEON, dst: NEON);
+    fn emit_fcmp(&mut self, sz: Size, left: NEON, right: NEON);
+    /// Converts between single and double precision.
+    fn emit_fcvt(&mut self, sz_src: Size, src: NEON, dst: NEON);
+    fn emit_scvtf(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON);
+    fn emit_ucvtf(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON);
+    fn emit_fcvtzs(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR);
+    fn emit_fcvtzu(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR);
+}
+
+/// A load or store instruction.
+#[derive(Copy, Clone, Debug)]
+enum MemOp {
+    Ldrb,
+    Ldrh,
+    LdrW,
+    LdrX,
+    Ldrsb32,
+    Ldrsb64,
+    Ldrsh32,
+    Ldrsh64,
+    Ldrsw,
+    LdrS,
+    LdrD,
+    Strb,
+    Strh,
+    StrW,
+    StrX,
+    StrS,
+    StrD,
+}
+
+impl MemOp {
+    /// The size in bytes of the accessed memory.
+    fn size(self) -> i32 {
+        match self {
+            MemOp::Ldrb | MemOp::Ldrsb32 | MemOp::Ldrsb64 | MemOp::Strb => 1,
+            MemOp::Ldrh | MemOp::Ldrsh32 | MemOp::Ldrsh64 | MemOp::Strh => 2,
+            MemOp::LdrW | MemOp::Ldrsw | MemOp::LdrS | MemOp::StrW | MemOp::StrS => 4,
+            MemOp::LdrX | MemOp::LdrD | MemOp::StrX | MemOp::StrD => 8,
+        }
+    }
+}
+
+/// Whether `[base, disp]` can be encoded with a scaled unsigned offset.
+fn is_scaled_offset(disp: i32, size: i32) -> bool {
+    disp >= 0 && disp % size == 0 && disp / size < 4096
+}
+
+/// Whether `[base, disp]` can be encoded with an unscaled signed offset.
+fn is_unscaled_offset(disp: i32) -> bool {
+    disp >= -256 && disp < 256
+}
+
+/// Emits the load or store `op` of register `rt` at `[base, disp]`.
+///
+/// If the offset cannot be encoded, the address is computed into X17 first.
+/// The stack pointer can only be used as base with an offset that can be
+/// encoded.
+fn emit_mem_op(a: &mut Assembler, op: MemOp, rt: u32, base: GPR, disp: i32) {
+    let size = op.size();
+    let (rn, disp) = if is_scaled_offset(disp, size) || is_unscaled_offset(disp) {
+        (base.into_index() as u32, disp)
+    } else if disp < 0 && disp > -4096 {
+        a.emit_sub(Size::S64, base, Location::Imm32((-disp) as u32), GPR::X17);
+        (17, 0)
+    } else {
+        a.emit_mov_imm(Size::S64, disp as i64 as u64, GPR::X17);
+        a.emit_add(Size::S64, base, Location::GPR(GPR::X17), GPR::X17);
+        (17, 0)
+    };
+    if is_scaled_offset(disp, size) {
+        let disp = disp as u32;
+        match op {
+            MemOp::Ldrb => dynasm!(a ; ldrb W(rt), [X(rn), disp]),
+            MemOp::Ldrh => dynasm!(a ; ldrh W(rt), [X(rn), disp]),
+            MemOp::LdrW => dynasm!(a ; ldr W(rt), [X(rn), disp]),
+            MemOp::LdrX => dynasm!(a ; ldr X(rt), [X(rn), disp]),
+            MemOp::Ldrsb32 => dynasm!(a ; ldrsb W(rt), [X(rn), disp]),
+            MemOp::Ldrsb64 => dynasm!(a ; ldrsb X(rt), [X(rn), disp]),
+            MemOp::Ldrsh32 => dynasm!(a ; ldrsh W(rt), [X(rn), disp]),
+            MemOp::Ldrsh64 => dynasm!(a ; ldrsh X(rt), [X(rn), disp]),
+            MemOp::Ldrsw => dynasm!(a ; ldrsw X(rt), [X(rn), disp]),
+            MemOp::LdrS => dynasm!(a ; ldr S(rt), [X(rn), disp]),
+            MemOp::LdrD => dynasm!(a ; ldr D(rt), [X(rn), disp]),
+            MemOp::Strb => dynasm!(a ; strb W(rt), [X(rn), disp]),
+            MemOp::Strh => dynasm!(a ; strh W(rt), [X(rn), disp]),
+            MemOp::StrW => dynasm!(a ; str W(rt), [X(rn), disp]),
+            MemOp::StrX => dynasm!(a ; str X(rt), [X(rn), disp]),
+            MemOp::StrS => dynasm!(a ; str S(rt), [X(rn), disp]),
+            MemOp::StrD => dynasm!(a ; str D(rt), [X(rn), disp]),
+        }
+    } else {
+        match op {
+            MemOp::Ldrb => dynasm!(a ; ldurb W(rt), [X(rn), disp]),
+            MemOp::Ldrh => dynasm!(a ; ldurh W(rt), [X(rn), disp]),
+            MemOp::LdrW => dynasm!(a ; ldur W(rt), [X(rn), disp]),
+            MemOp::LdrX => dynasm!(a ; ldur X(rt), [X(rn), disp]),
+            MemOp::Ldrsb32 => dynasm!(a ; ldursb W(rt), [X(rn), disp]),
+            MemOp::Ldrsb64 => dynasm!(a ; ldursb X(rt), [X(rn), disp]),
+            MemOp::Ldrsh32 => dynasm!(a ; ldursh W(rt), [X(rn), disp]),
+            MemOp::Ldrsh64 => dynasm!(a ; ldursh X(rt), [X(rn), disp]),
+            MemOp::Ldrsw => dynasm!(a ; ldursw X(rt), [X(rn), disp]),
+            MemOp::LdrS => dynasm!(a ; ldur S(rt), [X(rn), disp]),
+            MemOp::LdrD => dynasm!(a ; ldur D(rt), [X(rn), disp]),
+            MemOp::Strb => dynasm!(a ; sturb W(rt), [X(rn), disp]),
+            MemOp::Strh => dynasm!(a ; sturh W(rt), [X(rn), disp]),
+            MemOp::StrW => dynasm!(a ; stur W(rt), [X(rn), disp]),
+            MemOp::StrX => dynasm!(a ; stur X(rt), [X(rn), disp]),
+            MemOp::StrS => dynasm!(a ; stur S(rt), [X(rn), disp]),
+            MemOp::StrD => dynasm!(a ; stur D(rt), [X(rn), disp]),
+        }
+    }
+}
+
+/// Returns the number of the register holding `loc`, loading an immediate
+/// into X17 first.
+fn gpr_or_x17(a: &mut Assembler, sz: Size, loc: Location) -> u32 {
+    match loc {
+        Location::GPR(x) => x.into_index() as u32,
+        _ => match loc.imm_value() {
+            Some(val) => {
+                a.emit_mov_imm(sz, val, GPR::X17);
+                17
+            }
+            None => panic!("singlepass can't use {:?} as a register operand", loc),
+        },
+    }
+}
+
+/// Returns the value of `loc` if it is an immediate that fits the 12-bit
+/// immediate of arithmetic instructions.
+fn imm12(loc: Location) -> Option<u32> {
+    match loc.imm_value() {
+        Some(val) if val < 4096 => Some(val as u32),
+        _ => None,
+    }
+}
+
+macro_rules! binop_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR) {
+            let src1 = src1.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            let src2 = gpr_or_x17(self, sz, src2);
+            match sz {
+                Size::S32 => dynasm!(self ; $ins W(dst), W(src1), W(src2)),
+                Size::S64 => dynasm!(self ; $ins X(dst), X(src1), X(src2)),
+                _ => panic!("singlepass can't emit {} {:?}", stringify!($ins), sz),
+            }
+        }
+    };
+}
+
+macro_rules! unop_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src: GPR, dst: GPR) {
+            let src = src.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            match sz {
+                Size::S32 => dynasm!(self ; $ins W(dst), W(src)),
+                Size::S64 => dynasm!(self ; $ins X(dst), X(src)),
+                _ => panic!("singlepass can't emit {} {:?}", stringify!($ins), sz),
+            }
+        }
+    };
+}
+
+macro_rules! fp_binop_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src1: NEON, src2: NEON, dst: NEON) {
+            let src1 = src1.into_index() as u32;
+            let src2 = src2.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            match sz {
+                Size::S32 => dynasm!(self ; $ins S(dst), S(src1), S(src2)),
+                Size::S64 => dynasm!(self ; $ins D(dst), D(src1), D(src2)),
+                _ => panic!("singlepass can't emit {} {:?}", stringify!($ins), sz),
+            }
+        }
+    };
+}
+
+macro_rules! fp_unop_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz: Size, src: NEON, dst: NEON) {
+            let src = src.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            match sz {
+                Size::S32 => dynasm!(self ; $ins S(dst), S(src)),
+                Size::S64 => dynasm!(self ; $ins D(dst), D(src)),
+                _ => panic!("singlepass can't emit {} {:?}", stringify!($ins), sz),
+            }
+        }
+    };
+}
+
+macro_rules! int_fp_conv_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz_src: Size, src: GPR, sz_dst: Size, dst: NEON) {
+            let src = src.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            match (sz_src, sz_dst) {
+                (Size::S32, Size::S32) => dynasm!(self ; $ins S(dst), W(src)),
+                (Size::S64, Size::S32) => dynasm!(self ; $ins S(dst), X(src)),
+                (Size::S32, Size::S64) => dynasm!(self ; $ins D(dst), W(src)),
+                (Size::S64, Size::S64) => dynasm!(self ; $ins D(dst), X(src)),
+                _ => panic!(
+                    "singlepass can't emit {} {:?} {:?}",
+                    stringify!($ins),
+                    sz_src,
+                    sz_dst
+                ),
+            }
+        }
+    };
+}
+
+macro_rules! fp_int_conv_fn {
+    ($ins:ident, $name:ident) => {
+        fn $name(&mut self, sz_src: Size, src: NEON, sz_dst: Size, dst: GPR) {
+            let src = src.into_index() as u32;
+            let dst = dst.into_index() as u32;
+            match (sz_src, sz_dst) {
+                (Size::S32, Size::S32) => dynasm!(self ; $ins W(dst), S(src)),
+                (Size::S64, Size::S32) => dynasm!(self ; $ins W(dst), D(src)),
+                (Size::S32, Size::S64) => dynasm!(self ; $ins X(dst), S(src)),
+                (Size::S64, Size::S64) => dynasm!(self ; $ins X(dst), D(src)),
+                _ => panic!(
+                    "singlepass can't emit {} {:?} {:?}",
+                    stringify!($ins),
+                    sz_src,
+                    sz_dst
+                ),
+            }
+        }
+    };
+}
+
+impl EmitterARM64 for Assembler {
+    type Label = DynamicLabel;
+    type Offset = AssemblyOffset;
+
+    fn get_label(&mut self) -> DynamicLabel {
+        self.new_dynamic_label()
+    }
+
+    fn get_offset(&self) -> AssemblyOffset {
+        self.offset()
+    }
+
+    fn emit_u64(&mut self, x: u64) {
+        self.push_u64(x);
+    }
+
+    fn emit_label(&mut self, label: Self::Label) {
+        dynasm!(self ; => label);
+    }
+
+    fn emit_nop(&mut self) {
+        dynasm!(self ; nop);
+    }
+
+    fn emit_mov(&mut self, sz: Size, src: Location, dst: Location) {
+        if let Some(val) = src.imm_value() {
+            match dst {
+                Location::GPR(dst) => self.emit_mov_imm(sz, val, dst),
+                Location::NEON(_) => {
+                    self.emit_mov_imm(sz, val, GPR::X16);
+                    self.emit_mov(sz, Location::GPR(GPR::X16), dst);
+                }
+                Location::Memory(_, _) => {
+                    // Zero is stored straight from the zero register.
+                    let reg = if val == 0 {
+                        GPR::XzrSp
+                    } else {
+                        self.emit_mov_imm(sz, val, GPR::X16);
+                        GPR::X16
+                    };
+                    self.emit_mov(sz, Location::GPR(reg), dst);
+                }
+                _ => panic!("singlepass can't emit MOV {:?} {:?} {:?}", sz, src, dst),
+            }
+            return;
+        }
+        match (sz, src, dst) {
+            (Size::S32, Location::GPR(src), Location::GPR(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; mov W(dst), W(src));
+            }
+            (Size::S64, Location::GPR(src), Location::GPR(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; mov X(dst), X(src));
+            }
+            (Size::S32, Location::GPR(src), Location::NEON(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov S(dst), W(src));
+            }
+            (Size::S64, Location::GPR(src), Location::NEON(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov D(dst), X(src));
+            }
+            (Size::S32, Location::NEON(src), Location::GPR(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov W(dst), S(src));
+            }
+            (Size::S64, Location::NEON(src), Location::GPR(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov X(dst), D(src));
+            }
+            (Size::S32, Location::NEON(src), Location::NEON(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov S(dst), S(src));
+            }
+            (Size::S64, Location::NEON(src), Location::NEON(dst)) => {
+                let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+                dynasm!(self ; fmov D(dst), D(src));
+            }
+            (Size::S32, Location::GPR(src), Location::Memory(base, disp)) => {
+                emit_mem_op(self, MemOp::StrW, src.into_index() as u32, base, disp);
+            }
+            (Size::S64, Location::GPR(src), Location::Memory(base, disp)) => {
+                emit_mem_op(self, MemOp::StrX, src.into_index() as u32, base, disp);
+            }
+            (Size::S32, Location::NEON(src), Location::Memory(base, disp)) => {
+                emit_mem_op(self, MemOp::StrS, src.into_index() as u32, base, disp);
+            }
+            (Size::S64, Location::NEON(src), Location::Memory(base, disp)) => {
+                emit_mem_op(self, MemOp::StrD, src.into_index() as u32, base, disp);
+            }
+            (Size::S32, Location::Memory(base, disp), Location::GPR(dst)) => {
+                emit_mem_op(self, MemOp::LdrW, dst.into_index() as u32, base, disp);
+            }
+            (Size::S64, Location::Memory(base, disp), Location::GPR(dst)) => {
+                emit_mem_op(self, MemOp::LdrX, dst.into_index() as u32, base, disp);
+            }
+            (Size::S32, Location::Memory(base, disp), Location::NEON(dst)) => {
+                emit_mem_op(self, MemOp::LdrS, dst.into_index() as u32, base, disp);
+            }
+            (Size::S64, Location::Memory(base, disp), Location::NEON(dst)) => {
+                emit_mem_op(self, MemOp::LdrD, dst.into_index() as u32, base, disp);
+            }
+            (_, Location::Memory(_, _), Location::Memory(_, _)) => {
+                self.emit_mov(sz, src, Location::GPR(GPR::X16));
+                self.emit_mov(sz, Location::GPR(GPR::X16), dst);
+            }
+            _ => panic!("singlepass can't emit MOV {:?} {:?} {:?}", sz, src, dst),
+        }
+    }
+
+    fn emit_mov_imm(&mut self, sz: Size, val: u64, dst: GPR) {
+        let dst = dst.into_index() as u32;
+        match sz {
+            Size::S64 => {
+                if !val < 0x10000 {
+                    dynasm!(self ; movn X(dst), (!val) as u32);
+                } else {
+                    dynasm!(self ; movz X(dst), (val & 0xffff) as u32);
+                    let part = ((val >> 16) & 0xffff) as u32;
+                    if part != 0 {
+                        dynasm!(self ; movk X(dst), part, LSL 16);
+                    }
+                    let part = ((val >> 32) & 0xffff) as u32;
+                    if part != 0 {
+                        dynasm!(self ; movk X(dst), part, LSL 32);
+                    }
+                    let part = ((val >> 48) & 0xffff) as u32;
+                    if part != 0 {
+                        dynasm!(self ; movk X(dst), part, LSL 48);
+                    }
+                }
+            }
+            _ => {
+                let val = val as u32;
+                if !val < 0x10000 {
+                    dynasm!(self ; movn W(dst), !val);
+                } else {
+                    dynasm!(self ; movz W(dst), val & 0xffff);
+                    if val >> 16 != 0 {
+                        dynasm!(self ; movk W(dst), val >> 16, LSL 16);
+                    }
+                }
+            }
+        }
+    }
+
+    fn emit_load(&mut self, sz_src: Size, signed: bool, sz_dst: Size, src: Location, dst: GPR) {
+        let op = match (sz_src, signed, sz_dst) {
+            (Size::S8, false, _) => MemOp::Ldrb,
+            (Size::S16, false, _) => MemOp::Ldrh,
+            (Size::S32, false, _) => MemOp::LdrW,
+            (Size::S64, _, Size::S64) => MemOp::LdrX,
+            (Size::S8, true, Size::S32) => MemOp::Ldrsb32,
+            (Size::S8, true, Size::S64) => MemOp::Ldrsb64,
+            (Size::S16, true, Size::S32) => MemOp::Ldrsh32,
+            (Size::S16, true, Size::S64) => MemOp::Ldrsh64,
+            (Size::S32, true, Size::S32) => MemOp::LdrW,
+            (Size::S32, true, Size::S64) => MemOp::Ldrsw,
+            _ => panic!(
+                "singlepass can't emit LDR {:?} {:?} {:?}",
+                sz_src, signed, sz_dst
+            ),
+        };
+        match src {
+            Location::Memory(base, disp) => {
+                emit_mem_op(self, op, dst.into_index() as u32, base, disp)
+            }
+            _ => panic!("singlepass can't emit LDR {:?}", src),
+        }
+    }
+
+    fn emit_store(&mut self, sz: Size, src: GPR, dst: Location) {
+        let op = match sz {
+            Size::S8 => MemOp::Strb,
+            Size::S16 => MemOp::Strh,
+            Size::S32 => MemOp::StrW,
+            Size::S64 => MemOp::StrX,
+        };
+        match dst {
+            Location::Memory(base, disp) => {
+                emit_mem_op(self, op, src.into_index() as u32, base, disp)
+            }
+            _ => panic!("singlepass can't emit STR {:?}", dst),
+        }
+    }
+
+    fn emit_ldr_literal(&mut self, val: u64, dst: GPR) -> usize {
+        // Keep the literal 8-byte aligned.
+        if self.get_offset().0 % 8 != 0 {
+            self.emit_nop();
+        }
+        let dst = dst.into_index() as u32;
+        let literal = self.new_dynamic_label();
+        let after = self.new_dynamic_label();
+        dynasm!(self
+            ; ldr X(dst), =>literal
+            ; b =>after
+            ; =>literal
+        );
+        let offset = self.get_offset().0;
+        self.push_u64(val);
+        dynasm!(self ; =>after);
+        offset
+    }
+
+    fn emit_add(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR) {
+        let (s1, d) = (src1.into_index() as u32, dst.into_index() as u32);
+        match (sz, imm12(src2)) {
+            (Size::S32, Some(imm)) => dynasm!(self ; add W(d), W(s1), imm),
+            (Size::S64, Some(imm)) => dynasm!(self ; add X(d), X(s1), imm),
+            _ => {
+                let s2 = gpr_or_x17(self, sz, src2);
+                match sz {
+                    Size::S32 => dynasm!(self ; add W(d), W(s1), W(s2)),
+                    _ => dynasm!(self ; add X(d), X(s1), X(s2)),
+                }
+            }
+        }
+    }
+
+    fn emit_adds(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR) {
+        let (s1, d) = (src1.into_index() as u32, dst.into_index() as u32);
+        match (sz, imm12(src2)) {
+            (Size::S32, Some(imm)) => dynasm!(self ; adds W(d), W(s1), imm),
+            (Size::S64, Some(imm)) => dynasm!(self ; adds X(d), X(s1), imm),
+            _ => {
+                let s2 = gpr_or_x17(self, sz, src2);
+                match sz {
+                    Size::S32 => dynasm!(self ; adds W(d), W(s1), W(s2)),
+                    _ => dynasm!(self ; adds X(d), X(s1), X(s2)),
+                }
+            }
+        }
+    }
+
+    fn emit_sub(&mut self, sz: Size, src1: GPR, src2: Location, dst: GPR) {
+        let (s1, d) = (src1.into_index() as u32, dst.into_index() as u32);
+        match (sz, imm12(src2)) {
+            (Size::S32, Some(imm)) => dynasm!(self ; sub W(d), W(s1), imm),
+            (Size::S64, Some(imm)) => dynasm!(self ; sub X(d), X(s1), imm),
+            _ => {
+                let s2 = gpr_or_x17(self, sz, src2);
+                match sz {
+                    Size::S32 => dynasm!(self ; sub W(d), W(s1), W(s2)),
+                    _ => dynasm!(self ; sub X(d), X(s1), X(s2)),
+                }
+            }
+        }
+    }
+
+    fn emit_add_lsl(&mut self, src1: GPR, src2: GPR, lsl: u32, dst: GPR) {
+        let (s1, s2, d) = (
+            src1.into_index() as u32,
+            src2.into_index() as u32,
+            dst.into_index() as u32,
+        );
+        dynasm!(self ; add X(d), X(s1), X(s2), LSL lsl);
+    }
+
+    binop_fn!(mul, emit_mul);
+    binop_fn!(sdiv, emit_sdiv);
+    binop_fn!(udiv, emit_udiv);
+    binop_fn!(and, emit_and);
+    binop_fn!(orr, emit_orr);
+    binop_fn!(eor, emit_eor);
+    binop_fn!(lslv, emit_lsl);
+    binop_fn!(lsrv, emit_lsr);
+    binop_fn!(asrv, emit_asr);
+    binop_fn!(rorv, emit_ror);
+
+    fn emit_msub(&mut self, sz: Size, src1: GPR, src2: GPR, src3: GPR, dst: GPR) {
+        let (s1, s2, s3, d) = (
+            src1.into_index() as u32,
+            src2.into_index() as u32,
+            src3.into_index() as u32,
+            dst.into_index() as u32,
+        );
+        match sz {
+            Size::S32 => dynasm!(self ; msub W(d), W(s1), W(s2), W(s3)),
+            _ => dynasm!(self ; msub X(d), X(s1), X(s2), X(s3)),
+        }
+    }
+
+    fn emit_neg(&mut self, sz: Size, src: GPR, dst: GPR) {
+        let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+        match sz {
+            Size::S32 => dynasm!(self ; sub W(dst), wzr, W(src)),
+            _ => dynasm!(self ; sub X(dst), xzr, X(src)),
+        }
+    }
+
+    unop_fn!(clz, emit_clz);
+    unop_fn!(rbit, emit_rbit);
+
+    fn emit_cnt(&mut self, sz: Size, src: GPR, dst: GPR) {
+        self.emit_mov(sz, Location::GPR(src), Location::NEON(NEON::V31));
+        let dst = dst.into_index() as u32;
+        dynasm!(self
+            ; cnt v31.b8, v31.b8
+            ; addv b31, v31.b8
+            ; fmov W(dst), s31
+        );
+    }
+
+    fn emit_sext(&mut self, sz_src: Size, sz_dst: Size, src: GPR, dst: GPR) {
+        let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+        match (sz_src, sz_dst) {
+            (Size::S8, Size::S32) => dynasm!(self ; sxtb W(dst), W(src)),
+            (Size::S16, Size::S32) => dynasm!(self ; sxth W(dst), W(src)),
+            (Size::S8, Size::S64) => dynasm!(self ; sxtb X(dst), W(src)),
+            (Size::S16, Size::S64) => dynasm!(self ; sxth X(dst), W(src)),
+            (Size::S32, Size::S64) => dynasm!(self ; sxtw X(dst), W(src)),
+            _ => panic!("singlepass can't emit SXT {:?} {:?}", sz_src, sz_dst),
+        }
+    }
+
+    fn emit_cmp(&mut self, sz: Size, left: GPR, right: Location) {
+        let left = left.into_index() as u32;
+        match (sz, imm12(right)) {
+            (Size::S32, Some(imm)) => dynasm!(self ; cmp W(left), imm),
+            (Size::S64, Some(imm)) => dynasm!(self ; cmp X(left), imm),
+            _ => {
+                let right = gpr_or_x17(self, sz, right);
+                match sz {
+                    Size::S32 => dynasm!(self ; cmp W(left), W(right)),
+                    _ => dynasm!(self ; cmp X(left), X(right)),
+                }
+            }
+        }
+    }
+
+    fn emit_cset(&mut self, condition: Condition, dst: GPR) {
+        let dst = dst.into_index() as u32;
+        match condition {
+            Condition::Eq => dynasm!(self ; cset W(dst), eq),
+            Condition::Ne => dynasm!(self ; cset W(dst), ne),
+            Condition::Cs => dynasm!(self ; cset W(dst), cs),
+            Condition::Cc => dynasm!(self ; cset W(dst), cc),
+            Condition::Mi => dynasm!(self ; cset W(dst), mi),
+            Condition::Pl => dynasm!(self ; cset W(dst), pl),
+            Condition::Vs => dynasm!(self ; cset W(dst), vs),
+            Condition::Vc => dynasm!(self ; cset W(dst), vc),
+            Condition::Hi => dynasm!(self ; cset W(dst), hi),
+            Condition::Ls => dynasm!(self ; cset W(dst), ls),
+            Condition::Ge => dynasm!(self ; cset W(dst), ge),
+            Condition::Lt => dynasm!(self ; cset W(dst), lt),
+            Condition::Gt => dynasm!(self ; cset W(dst), gt),
+            Condition::Le => dynasm!(self ; cset W(dst), le),
+            Condition::Al => dynasm!(self ; movz W(dst), 1),
+        }
+    }
+
+    fn emit_b_label(&mut self, label: Self::Label) {
+        dynasm!(self ; b =>label);
+    }
+
+    fn emit_bcond_label(&mut self, condition: Condition, label: Self::Label) {
+        match condition {
+            Condition::Eq => dynasm!(self ; b.eq =>label),
+            Condition::Ne => dynasm!(self ; b.ne =>label),
+            Condition::Cs => dynasm!(self ; b.cs =>label),
+            Condition::Cc => dynasm!(self ; b.cc =>label),
+            Condition::Mi => dynasm!(self ; b.mi =>label),
+            Condition::Pl => dynasm!(self ; b.pl =>label),
+            Condition::Vs => dynasm!(self ; b.vs =>label),
+            Condition::Vc => dynasm!(self ; b.vc =>label),
+            Condition::Hi => dynasm!(self ; b.hi =>label),
+            Condition::Ls => dynasm!(self ; b.ls =>label),
+            Condition::Ge => dynasm!(self ; b.ge =>label),
+            Condition::Lt => dynasm!(self ; b.lt =>label),
+            Condition::Gt => dynasm!(self ; b.gt =>label),
+            Condition::Le => dynasm!(self ; b.le =>label),
+            Condition::Al => dynasm!(self ; b =>label),
+        }
+    }
+
+    fn emit_cbz_label(&mut self, sz: Size, reg: GPR, label: Self::Label) {
+        let reg = reg.into_index() as u32;
+        match sz {
+            Size::S32 => dynasm!(self ; cbz W(reg), =>label),
+            _ => dynasm!(self ; cbz X(reg), =>label),
+        }
+    }
+
+    fn emit_cbnz_label(&mut self, sz: Size, reg: GPR, label: Self::Label) {
+        let reg = reg.into_index() as u32;
+        match sz {
+            Size::S32 => dynasm!(self ; cbnz W(reg), =>label),
+            _ => dynasm!(self ; cbnz X(reg), =>label),
+        }
+    }
+
+    fn emit_adr_label(&mut self, label: Self::Label, dst: GPR) {
+        let dst = dst.into_index() as u32;
+        dynasm!(self ; adr X(dst), =>label);
+    }
+
+    fn emit_b_register(&mut self, reg: GPR) {
+        let reg = reg.into_index() as u32;
+        dynasm!(self ; br X(reg));
+    }
+
+    fn emit_call_register(&mut self, reg: GPR) {
+        let reg = reg.into_index() as u32;
+        dynasm!(self ; blr X(reg));
+    }
+
+    fn emit_ret(&mut self) {
+        dynasm!(self ; ret);
+    }
+
+    fn emit_udf(&mut self) {
+        // The all-zero word is the permanently undefined `udf #0`, which
+        // raises SIGILL like `ud2` on x86-64.
+        self.push_u32(0);
+    }
+
+    fn emit_push_pair(&mut self, reg1: GPR, reg2: GPR) {
+        let (reg1, reg2) = (reg1.into_index() as u32, reg2.into_index() as u32);
+        dynasm!(self ; stp X(reg1), X(reg2), [sp, -16]!);
+    }
+
+    fn emit_pop_pair(&mut self, reg1: GPR, reg2: GPR) {
+        let (reg1, reg2) = (reg1.into_index() as u32, reg2.into_index() as u32);
+        dynasm!(self ; ldp X(reg1), X(reg2), [sp], 16);
+    }
+
+    fn emit_mov_from_sp(&mut self, dst: GPR) {
+        let dst = dst.into_index() as u32;
+        dynasm!(self ; mov XSP(dst), sp);
+    }
+
+    fn emit_mov_to_sp(&mut self, src: GPR) {
+        let src = src.into_index() as u32;
+        dynasm!(self ; mov sp, XSP(src));
+    }
+
+    fn emit_add_sp(&mut self, imm: u32) {
+        assert!(imm < 1 << 24, "singlepass can't adjust SP by {}", imm);
+        let (low, high) = (imm & 0xfff, imm >> 12);
+        if high != 0 {
+            dynasm!(self ; add sp, sp, high, LSL 12);
+        }
+        if low != 0 {
+            dynasm!(self ; add sp, sp, low);
+        }
+    }
+
+    fn emit_sub_sp(&mut self, imm: u32) {
+        assert!(imm < 1 << 24, "singlepass can't adjust SP by {}", imm);
+        let (low, high) = (imm & 0xfff, imm >> 12);
+        if high != 0 {
+            dynasm!(self ; sub sp, sp, high, LSL 12);
+        }
+        if low != 0 {
+            dynasm!(self ; sub sp, sp, low);
+        }
+    }
+
+    fp_binop_fn!(fadd, emit_fadd);
+    fp_binop_fn!(fsub, emit_fsub);
+    fp_binop_fn!(fmul, emit_fmul);
+    fp_binop_fn!(fdiv, emit_fdiv);
+    fp_binop_fn!(fmin, emit_fmin);
+    fp_binop_fn!(fmax, emit_fmax);
+
+    fp_unop_fn!(fneg, emit_fneg);
+    fp_unop_fn!(fabs, emit_fabs);
+    fp_unop_fn!(fsqrt, emit_fsqrt);
+    fp_unop_fn!(frintn, emit_frintn);
+    fp_unop_fn!(frintm, emit_frintm);
+    fp_unop_fn!(frintp, emit_frintp);
+    fp_unop_fn!(frintz, emit_frintz);
+
+    fn emit_fcmp(&mut self, sz: Size, left: NEON, right: NEON) {
+        let (left, right) = (left.into_index() as u32, right.into_index() as u32);
+        match sz {
+            Size::S32 => dynasm!(self ; fcmp S(left), S(right)),
+            _ => dynasm!(self ; fcmp D(left), D(right)),
+        }
+    }
+
+    fn emit_fcvt(&mut self, sz_src: Size, src: NEON, dst: NEON) {
+        let (src, dst) = (src.into_index() as u32, dst.into_index() as u32);
+        match sz_src {
+            Size::S32 => dynasm!(self ; fcvt D(dst), S(src)),
+            _ => dynasm!(self ; fcvt S(dst), D(src)),
+        }
+    }
+
+    int_fp_conv_fn!(scvtf, emit_scvtf);
+    int_fp_conv_fn!(ucvtf, emit_ucvtf);
+    fp_int_conv_fn!(fcvtzs, emit_fcvtzs);
+    fp_int_conv_fn!(fcvtzu, emit_fcvtzu);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assemble<F: FnOnce(&mut Assembler)>(f: F) -> Vec<u32> {
+        let mut a = Assembler::new().unwrap();
+        f(&mut a);
+        a.finalize()
+            .unwrap()
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_mem_op_offsets() {
+        // Scaled and unscaled offsets are encoded in the instruction.
+        let code = assemble(|a| {
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, 16),
+                Location::GPR(GPR::X0),
+            )
+        });
+        assert_eq!(code, vec![0xf9400ba0]); // ldr x0, [x29, #16]
+        let code = assemble(|a| {
+            a.emit_mov(
+                Size::S64,
+                Location::GPR(GPR::X0),
+                Location::Memory(GPR::X29, -8),
+            )
+        });
+        assert_eq!(code, vec![0xf81f83a0]); // stur x0, [x29, #-8]
+
+        // Other offsets go through X17.
+        let code = assemble(|a| {
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, -1024),
+                Location::GPR(GPR::X0),
+            )
+        });
+        assert_eq!(code, vec![0xd11003b1, 0xf9400220]); // sub x17, x29, #1024; ldr x0, [x17]
+    }
+
+    #[test]
+    fn test_ldr_literal() {
+        let mut a = Assembler::new().unwrap();
+        a.emit_nop();
+        let offset = a.emit_ldr_literal(0x1122334455667788, GPR::X16);
+        assert_eq!(offset % 8, 0);
+        let code = a.finalize().unwrap();
+        assert_eq!(
+            &code[offset..offset + 8],
+            &0x1122334455667788u64.to_le_bytes()
+        );
+    }
+}
@@ -198,6 +198,143 @@ pub trait Emitter {
     fn emit_vblendvps(&mut self, src1: XMM, src2: XMMOrMemory, mask: XMM, dst: XMM);
     fn emit_vblendvpd(&mut self, src1: XMM, src2: XMMOrMemory, mask: XMM, dst: XMM);
 
+    fn emit_vmovdqu(&mut self, src: XMMOrMemory, dst: XMMOrMemory);
+
+    fn emit_vpand(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpandn(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpor(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpxor(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpaddb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpaddsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddusb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpaddusw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubusb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsubusw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpmullw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmulld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmuludq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmuldq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaddwd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmulhrsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpminsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminsd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminub(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminuw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpminud(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpmaxsb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxsw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxsd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxub(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxuw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpmaxud(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpavgb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpavgw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpcmpeqb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpeqq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpcmpgtd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpsllw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpslld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsllq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrlw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrld(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrlq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsraw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpsrad(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpacksswb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackssdw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackuswb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpackusdw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpunpcklbw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpckhbw(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpckldq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpcklqdq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vpunpckhqdq(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpshufb(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vaddps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vaddpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vsubps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vsubpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmulps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmulpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vdivps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vdivpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vminps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vminpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmaxps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vmaxpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vcmpeqps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpeqpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpneqps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpneqpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpltps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpltpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpleps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmplepd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vcmpunordps(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+    fn emit_vcmpunordpd(&mut self, src1: XMM, src2: XMMOrMemory, dst: XMM);
+
+    fn emit_vpabsb(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpabsw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpabsd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovsxbw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxbw(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovsxwd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxwd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovsxdq(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovzxdq(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvtdq2ps(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvttps2dq(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvtdq2pd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvtps2pd(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvtpd2ps(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vcvttpd2dq(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vsqrtps(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vsqrtpd(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vroundps_nearest(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_floor(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_ceil(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundps_trunc(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_nearest(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_floor(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_ceil(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vroundpd_trunc(&mut self, src: XMMOrMemory, dst: XMM);
+
+    fn emit_vptest(&mut self, src: XMMOrMemory, dst: XMM);
+    fn emit_vpmovmskb(&mut self, src: XMM, dst: GPR);
+    fn emit_vmovmskps(&mut self, src: XMM, dst: GPR);
+    fn emit_vmovmskpd(&mut self, src: XMM, dst: GPR);
+
     fn emit_test_gpr_64(&mut self, reg: GPR);
 
     fn emit_ud2(&mut self);
@@ -625,6 +762,28 @@ macro_rules! avx_round_fn {
     }
 }
 
+macro_rules! avx_unop_fn {
+    ($ins:ident, $name:ident, $mem_size:ident) => {
+        fn $name(&mut self, src: XMMOrMemory, dst: XMM) {
+            match src {
+                XMMOrMemory::XMM(x) => dynasm!(self ; $ins Rx((dst as u8)), Rx((x as u8))),
+                XMMOrMemory::Memory(base, disp) => dynasm!(self ; $ins Rx((dst as u8)), $mem_size [Rq((base as u8)) + disp]),
+            }
+        }
+    }
+}
+
+macro_rules! avx_round_packed_fn {
+    ($ins:ident, $name:ident, $mode:expr) => {
+        fn $name(&mut self, src: XMMOrMemory, dst: XMM) {
+            match src {
+                XMMOrMemory::XMM(x) => dynasm!(self ; $ins Rx((dst as u8)), Rx((x as u8)), $mode),
+                XMMOrMemory::Memory(base, disp) => dynasm!(self ; $ins Rx((dst as u8)), OWORD [Rq((base as u8)) + disp], $mode),
+            }
+        }
+    }
+}
+
 impl Emitter for Assembler {
     type Label = DynamicLabel;
     type Offset = AssemblyOffset;
@@ -1293,6 +1452,172 @@ impl Emitter for Assembler {
     avx_i2f_64_fn!(vcvtsi2ss, emit_vcvtsi2ss_64);
     avx_i2f_64_fn!(vcvtsi2sd, emit_vcvtsi2sd_64);
 
+    avx_fn!(vpand, emit_vpand);
+    avx_fn!(vpandn, emit_vpandn);
+    avx_fn!(vpor, emit_vpor);
+    avx_fn!(vpxor, emit_vpxor);
+
+    avx_fn!(vpaddb, emit_vpaddb);
+    avx_fn!(vpaddw, emit_vpaddw);
+    avx_fn!(vpaddd, emit_vpaddd);
+    avx_fn!(vpaddq, emit_vpaddq);
+    avx_fn!(vpsubb, emit_vpsubb);
+    avx_fn!(vpsubw, emit_vpsubw);
+    avx_fn!(vpsubd, emit_vpsubd);
+    avx_fn!(vpsubq, emit_vpsubq);
+
+    avx_fn!(vpaddsb, emit_vpaddsb);
+    avx_fn!(vpaddsw, emit_vpaddsw);
+    avx_fn!(vpaddusb, emit_vpaddusb);
+    avx_fn!(vpaddusw, emit_vpaddusw);
+    avx_fn!(vpsubsb, emit_vpsubsb);
+    avx_fn!(vpsubsw, emit_vpsubsw);
+    avx_fn!(vpsubusb, emit_vpsubusb);
+    avx_fn!(vpsubusw, emit_vpsubusw);
+
+    avx_fn!(vpmullw, emit_vpmullw);
+    avx_fn!(vpmulld, emit_vpmulld);
+    avx_fn!(vpmuludq, emit_vpmuludq);
+    avx_fn!(vpmuldq, emit_vpmuldq);
+    avx_fn!(vpmaddwd, emit_vpmaddwd);
+    avx_fn!(vpmulhrsw, emit_vpmulhrsw);
+
+    avx_fn!(vpminsb, emit_vpminsb);
+    avx_fn!(vpminsw, emit_vpminsw);
+    avx_fn!(vpminsd, emit_vpminsd);
+    avx_fn!(vpminub, emit_vpminub);
+    avx_fn!(vpminuw, emit_vpminuw);
+    avx_fn!(vpminud, emit_vpminud);
+
+    avx_fn!(vpmaxsb, emit_vpmaxsb);
+    avx_fn!(vpmaxsw, emit_vpmaxsw);
+    avx_fn!(vpmaxsd, emit_vpmaxsd);
+    avx_fn!(vpmaxub, emit_vpmaxub);
+    avx_fn!(vpmaxuw, emit_vpmaxuw);
+    avx_fn!(vpmaxud, emit_vpmaxud);
+
+    avx_fn!(vpavgb, emit_vpavgb);
+    avx_fn!(vpavgw, emit_vpavgw);
+
+    avx_fn!(vpcmpeqb, emit_vpcmpeqb);
+    avx_fn!(vpcmpeqw, emit_vpcmpeqw);
+    avx_fn!(vpcmpeqd, emit_vpcmpeqd);
+    avx_fn!(vpcmpeqq, emit_vpcmpeqq);
+    avx_fn!(vpcmpgtb, emit_vpcmpgtb);
+    avx_fn!(vpcmpgtw, emit_vpcmpgtw);
+    avx_fn!(vpcmpgtd, emit_vpcmpgtd);
+
+    avx_fn!(vpsllw, emit_vpsllw);
+    avx_fn!(vpslld, emit_vpslld);
+    avx_fn!(vpsllq, emit_vpsllq);
+    avx_fn!(vpsrlw, emit_vpsrlw);
+    avx_fn!(vpsrld, emit_vpsrld);
+    avx_fn!(vpsrlq, emit_vpsrlq);
+    avx_fn!(vpsraw, emit_vpsraw);
+    avx_fn!(vpsrad, emit_vpsrad);
+
+    avx_fn!(vpacksswb, emit_vpacksswb);
+    avx_fn!(vpackssdw, emit_vpackssdw);
+    avx_fn!(vpackuswb, emit_vpackuswb);
+    avx_fn!(vpackusdw, emit_vpackusdw);
+
+    avx_fn!(vpunpcklbw, emit_vpunpcklbw);
+    avx_fn!(vpunpckhbw, emit_vpunpckhbw);
+    avx_fn!(vpunpckldq, emit_vpunpckldq);
+    avx_fn!(vpunpcklqdq, emit_vpunpcklqdq);
+    avx_fn!(vpunpckhqdq, emit_vpunpckhqdq);
+
+    avx_fn!(vpshufb, emit_vpshufb);
+
+    avx_fn!(vaddps, emit_vaddps);
+    avx_fn!(vaddpd, emit_vaddpd);
+    avx_fn!(vsubps, emit_vsubps);
+    avx_fn!(vsubpd, emit_vsubpd);
+    avx_fn!(vmulps, emit_vmulps);
+    avx_fn!(vmulpd, emit_vmulpd);
+    avx_fn!(vdivps, emit_vdivps);
+    avx_fn!(vdivpd, emit_vdivpd);
+
+    avx_fn!(vminps, emit_vminps);
+    avx_fn!(vminpd, emit_vminpd);
+    avx_fn!(vmaxps, emit_vmaxps);
+    avx_fn!(vmaxpd, emit_vmaxpd);
+
+    avx_fn!(vcmpeqps, emit_vcmpeqps);
+    avx_fn!(vcmpeqpd, emit_vcmpeqpd);
+    avx_fn!(vcmpneqps, emit_vcmpneqps);
+    avx_fn!(vcmpneqpd, emit_vcmpneqpd);
+    avx_fn!(vcmpltps, emit_vcmpltps);
+    avx_fn!(vcmpltpd, emit_vcmpltpd);
+    avx_fn!(vcmpleps, emit_vcmpleps);
+    avx_fn!(vcmplepd, emit_vcmplepd);
+
+    avx_fn!(vcmpunordps, emit_vcmpunordps);
+    avx_fn!(vcmpunordpd, emit_vcmpunordpd);
+
+    avx_unop_fn!(vpabsb, emit_vpabsb, OWORD);
+    avx_unop_fn!(vpabsw, emit_vpabsw, OWORD);
+    avx_unop_fn!(vpabsd, emit_vpabsd, OWORD);
+    avx_unop_fn!(vpmovsxbw, emit_vpmovsxbw, QWORD);
+    avx_unop_fn!(vpmovzxbw, emit_vpmovzxbw, QWORD);
+    avx_unop_fn!(vpmovsxwd, emit_vpmovsxwd, QWORD);
+    avx_unop_fn!(vpmovzxwd, emit_vpmovzxwd, QWORD);
+    avx_unop_fn!(vpmovsxdq, emit_vpmovsxdq, QWORD);
+    avx_unop_fn!(vpmovzxdq, emit_vpmovzxdq, QWORD);
+    avx_unop_fn!(vcvtdq2ps, emit_vcvtdq2ps, OWORD);
+    avx_unop_fn!(vcvttps2dq, emit_vcvttps2dq, OWORD);
+    avx_unop_fn!(vcvtdq2pd, emit_vcvtdq2pd, QWORD);
+    avx_unop_fn!(vcvtps2pd, emit_vcvtps2pd, QWORD);
+    avx_unop_fn!(vcvtpd2ps, emit_vcvtpd2ps, OWORD);
+    avx_unop_fn!(vcvttpd2dq, emit_vcvttpd2dq, OWORD);
+    avx_unop_fn!(vsqrtps, emit_vsqrtps, OWORD);
+    avx_unop_fn!(vsqrtpd, emit_vsqrtpd, OWORD);
+
+    avx_round_packed_fn!(vroundps, emit_vroundps_nearest, 0);
+    avx_round_packed_fn!(vroundps, emit_vroundps_floor, 1);
+    avx_round_packed_fn!(vroundps, emit_vroundps_ceil, 2);
+    avx_round_packed_fn!(vroundps, emit_vroundps_trunc, 3);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_nearest, 0);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_floor, 1);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_ceil, 2);
+    avx_round_packed_fn!(vroundpd, emit_vroundpd_trunc, 3);
+
+    fn emit_vmovdqu(&mut self, src: XMMOrMemory, dst: XMMOrMemory) {
+        match (src, dst) {
+            (XMMOrMemory::XMM(src), XMMOrMemory::XMM(dst)) => {
+                dynasm!(self ; vmovdqu Rx(dst as u8), Rx(src as u8))
+            }
+            (XMMOrMemory::Memory(base, disp), XMMOrMemory::XMM(dst)) => {
+                dynasm!(self ; vmovdqu Rx(dst as u8), OWORD [Rq(base as u8) + disp])
+            }
+            (XMMOrMemory::XMM(src), XMMOrMemory::Memory(base, disp)) => {
+                dynasm!(self ; vmovdqu OWORD [Rq(base as u8) + disp], Rx(src as u8))
+            }
+            _ => panic!("singlepass can't emit VMOVDQU {:?} {:?}", src, dst),
+        };
+    }
+
+    fn emit_vptest(&mut self, src: XMMOrMemory, dst: XMM) {
+        match src {
+            XMMOrMemory::XMM(x) => dynasm!(self ; vptest Rx(dst as u8), Rx(x as u8)),
+            XMMOrMemory::Memory(base, disp) => {
+                dynasm!(self ; vptest Rx(dst as u8), OWORD [Rq(base as u8) + disp])
+            }
+        }
+    }
+
+    fn emit_vpmovmskb(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; vpmovmskb Rd(dst as u8), Rx(src as u8));
+    }
+
+    fn emit_vmovmskps(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; vmovmskps Rd(dst as u8), Rx(src as u8));
+    }
+
+    fn emit_vmovmskpd(&mut self, src: XMM, dst: GPR) {
+        dynasm!(self ; vmovmskpd Rd(dst as u8), Rx(src as u8));
+    }
+
     fn emit_vblendvps(&mut self, src1: XMM, src2: XMMOrMemory, mask: XMM, dst: XMM) {
         match src2 {
             XMMOrMemory::XMM(src2) => {
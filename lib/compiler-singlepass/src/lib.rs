@@ -9,12 +9,18 @@
 //! runtime performance.
 
 mod address_map;
+mod arm64_decl;
+mod codegen_arm64;
 mod codegen_x64;
 mod common_decl;
 mod compiler;
 mod config;
+mod emitter_arm64;
 mod emitter_x64;
 mod machine;
+mod machine_arm64;
+#[cfg(feature = "unwind")]
+mod unwind;
 mod x64_decl;
 
 pub use crate::compiler::SinglepassCompiler;
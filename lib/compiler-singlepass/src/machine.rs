@@ -16,6 +16,9 @@ pub struct Machine {
     used_xmms: HashSet<XMM>,
     stack_offset: MachineStackOffset,
     save_area_offset: Option<MachineStackOffset>,
+    sret_location: Option<Location>,
+    /// Stack offsets of the 16-byte slots holding `V128` stack values.
+    v128_stack_slots: HashSet<usize>,
     pub state: MachineState,
     pub(crate) track_state: bool,
 }
@@ -27,6 +30,8 @@ impl Machine {
             used_xmms: HashSet::new(),
             stack_offset: MachineStackOffset(0),
             save_area_offset: None,
+            sret_location: None,
+            v128_stack_slots: HashSet::new(),
             state: new_machine_state(),
             track_state: true,
         }
@@ -48,6 +53,33 @@ impl Machine {
         GPR::R15
     }
 
+    /// Returns the stack slot holding the struct-return pointer passed by the
+    /// caller, if the function being compiled returns its results in memory.
+    pub fn get_sret_location(&self) -> Option<Location> {
+        self.sret_location
+    }
+
+    /// Returns whether `loc` is a stack slot holding a `V128` stack value.
+    ///
+    /// `V128` values never live in registers, so this identifies them on the value stack.
+    pub fn is_v128_stack_value(&self, loc: Location) -> bool {
+        match loc {
+            Location::Memory(GPR::RBP, x) if x < 0 => {
+                self.v128_stack_slots.contains(&((-x) as usize))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the size in bytes of the stack slot at `offset`.
+    fn stack_slot_size(&self, offset: usize) -> usize {
+        if self.v128_stack_slots.contains(&offset) {
+            16
+        } else {
+            8
+        }
+    }
+
     /// Picks an unused general purpose register for local/stack/argument use.
     ///
     /// This method does not mark the register as used.
@@ -155,12 +187,23 @@ impl Machine {
         for (ty, mv) in tys {
             let loc = match *ty {
                 WpType::F32 | WpType::F64 => self.pick_xmm().map(Location::XMM),
-                WpType::I32 | WpType::I64 => self.pick_gpr().map(Location::GPR),
+                // Reference values are pointers.
+                WpType::I32 | WpType::I64 | WpType::FuncRef | WpType::ExternRef => {
+                    self.pick_gpr().map(Location::GPR)
+                }
+                // `V128` values always live in a 16-byte stack slot.
+                WpType::V128 => None,
                 _ => unreachable!(),
             };
 
             let loc = if let Some(x) = loc {
                 x
+            } else if *ty == WpType::V128 {
+                self.stack_offset.0 += 16;
+                delta_stack_offset += 16;
+                self.v128_stack_slots.insert(self.stack_offset.0);
+                self.state.stack_values.push(MachineValue::Undefined);
+                Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32))
             } else {
                 self.stack_offset.0 += 8;
                 delta_stack_offset += 8;
@@ -189,6 +232,13 @@ impl Machine {
         if zeroed {
             for i in 0..tys.len() {
                 assembler.emit_mov(Size::S64, Location::Imm32(0), ret[i]);
+                if let (WpType::V128, Location::Memory(base, disp)) = (tys[i].0, ret[i]) {
+                    assembler.emit_mov(
+                        Size::S64,
+                        Location::Imm32(0),
+                        Location::Memory(base, disp + 8),
+                    );
+                }
             }
         }
         ret
@@ -218,9 +268,13 @@ impl Machine {
                     if offset != self.stack_offset.0 {
                         unreachable!();
                     }
-                    self.stack_offset.0 -= 8;
-                    delta_stack_offset += 8;
-                    self.state.stack_values.pop().unwrap();
+                    let size = self.stack_slot_size(offset);
+                    self.v128_stack_slots.remove(&offset);
+                    self.stack_offset.0 -= size;
+                    delta_stack_offset += size;
+                    for _ in 0..size / 8 {
+                        self.state.stack_values.pop().unwrap();
+                    }
                 }
                 _ => {}
             }
@@ -271,9 +325,13 @@ impl Machine {
                 if offset != self.stack_offset.0 {
                     unreachable!();
                 }
-                self.stack_offset.0 -= 8;
-                delta_stack_offset += 8;
-                self.state.stack_values.pop().unwrap();
+                let size = self.stack_slot_size(offset);
+                self.v128_stack_slots.remove(&offset);
+                self.stack_offset.0 -= size;
+                delta_stack_offset += size;
+                for _ in 0..size / 8 {
+                    self.state.stack_values.pop().unwrap();
+                }
             }
             // Wasm state popping is deferred to `release_locations_only_osr_state`.
         }
@@ -310,8 +368,9 @@ impl Machine {
                 if offset != stack_offset {
                     unreachable!();
                 }
-                stack_offset -= 8;
-                delta_stack_offset += 8;
+                let size = self.stack_slot_size(offset);
+                stack_offset -= size;
+                delta_stack_offset += size;
             }
         }
 
@@ -324,31 +383,53 @@ impl Machine {
         }
     }
 
+    /// Sets up the function frame, loading the parameters into the locals.
+    ///
+    /// `local_types` lists the types of all locals, starting with the
+    /// `n_params` parameters. A `V128` parameter is passed in two consecutive
+    /// parameter slots, low half first.
+    ///
+    /// If `sret` is true, the first argument is a struct-return pointer that
+    /// precedes vmctx. It is saved to a stack slot available afterwards
+    /// through `get_sret_location`.
     pub fn init_locals<E: Emitter>(
         &mut self,
         a: &mut E,
-        n: usize,
+        local_types: &[WpType],
         n_params: usize,
+        sret: bool,
     ) -> Vec<Location> {
+        let n = local_types.len();
+
         // Determine whether a local should be allocated on the stack.
-        fn is_local_on_stack(idx: usize) -> bool {
-            idx > 3
-        }
+        // `V128` locals never live in registers.
+        let is_local_on_stack = |idx: usize| idx > 3 || local_types[idx] == WpType::V128;
+
+        // The size in bytes of a local.
+        let local_size = |idx: usize| {
+            if local_types[idx] == WpType::V128 {
+                16
+            } else {
+                8
+            }
+        };
 
-        // Determine a local's location.
-        fn get_local_location(idx: usize, callee_saved_regs_size: usize) -> Location {
-            // Use callee-saved registers for the first locals.
+        // Use callee-saved registers for the first locals.
+        fn get_local_register(idx: usize) -> Location {
             match idx {
                 0 => Location::GPR(GPR::R12),
                 1 => Location::GPR(GPR::R13),
                 2 => Location::GPR(GPR::R14),
                 3 => Location::GPR(GPR::RBX),
-                _ => Location::Memory(GPR::RBP, -(((idx - 3) * 8 + callee_saved_regs_size) as i32)),
+                _ => unreachable!(),
             }
         }
 
-        // How many machine stack slots will all the locals use?
-        let num_mem_slots = (0..n).filter(|&x| is_local_on_stack(x)).count();
+        // How many bytes of machine stack will all the locals use?
+        let mem_size: usize = (0..n)
+            .filter(|&x| is_local_on_stack(x))
+            .map(|x| local_size(x))
+            .sum();
 
         // Total size (in bytes) of the pre-allocated "static area" for this function's
         // locals and callee-saved registers.
@@ -366,16 +447,29 @@ impl Machine {
         // Callee-saved R15 for vmctx.
         static_area_size += 8;
 
+        // The struct-return pointer.
+        if sret {
+            static_area_size += 8;
+        }
+
         // Total size of callee saved registers.
         let callee_saved_regs_size = static_area_size;
 
         // Now we can determine concrete locations for locals.
+        let mut mem_offset = callee_saved_regs_size;
         let locations: Vec<Location> = (0..n)
-            .map(|i| get_local_location(i, callee_saved_regs_size))
+            .map(|i| {
+                if is_local_on_stack(i) {
+                    mem_offset += local_size(i);
+                    Location::Memory(GPR::RBP, -(mem_offset as i32))
+                } else {
+                    get_local_register(i)
+                }
+            })
             .collect();
 
         // Add size of locals on stack.
-        static_area_size += num_mem_slots * 8;
+        static_area_size += mem_size;
 
         // Allocate save area, without actually writing to it.
         a.emit_sub(
@@ -413,6 +507,18 @@ impl Machine {
         // Save the offset of register save area.
         self.save_area_offset = Some(MachineStackOffset(self.stack_offset.0));
 
+        // Save the struct-return pointer, since RDI is clobbered below.
+        if sret {
+            self.stack_offset.0 += 8;
+            let loc = Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32));
+            a.emit_mov(Size::S64, Self::get_param_location(0), loc);
+            self.state.stack_values.push(MachineValue::Undefined);
+            self.sret_location = Some(loc);
+        }
+
+        // With a struct-return pointer, vmctx and the parameters are shifted by one.
+        let vmctx_param = if sret { 1 } else { 0 };
+
         // Save location information for locals.
         for (i, loc) in locations.iter().enumerate() {
             match *loc {
@@ -421,7 +527,9 @@ impl Machine {
                         MachineValue::WasmLocal(i);
                 }
                 Location::Memory(_, _) => {
-                    self.state.stack_values.push(MachineValue::WasmLocal(i));
+                    for _ in 0..local_size(i) / 8 {
+                        self.state.stack_values.push(MachineValue::WasmLocal(i));
+                    }
                 }
                 _ => unreachable!(),
             }
@@ -430,38 +538,46 @@ impl Machine {
         // Load in-register parameters into the allocated locations.
         // Locals are allocated on the stack from higher address to lower address,
         // so we won't skip the stack guard page here.
+        let mut param_slot = vmctx_param + 1;
         for i in 0..n_params {
-            let loc = Self::get_param_location(i + 1);
-            match loc {
-                Location::GPR(_) => {
-                    a.emit_mov(Size::S64, loc, locations[i]);
-                }
-                Location::Memory(_, _) => match locations[i] {
+            for half in 0..local_size(i) / 8 {
+                let loc = Self::get_param_location(param_slot);
+                param_slot += 1;
+                let dst = match locations[i] {
+                    Location::Memory(base, disp) => Location::Memory(base, disp + half as i32 * 8),
+                    x => x,
+                };
+                match loc {
                     Location::GPR(_) => {
-                        a.emit_mov(Size::S64, loc, locations[i]);
-                    }
-                    Location::Memory(_, _) => {
-                        a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
-                        a.emit_mov(Size::S64, Location::GPR(GPR::RAX), locations[i]);
+                        a.emit_mov(Size::S64, loc, dst);
                     }
+                    Location::Memory(_, _) => match dst {
+                        Location::GPR(_) => {
+                            a.emit_mov(Size::S64, loc, dst);
+                        }
+                        Location::Memory(_, _) => {
+                            a.emit_mov(Size::S64, loc, Location::GPR(GPR::RAX));
+                            a.emit_mov(Size::S64, Location::GPR(GPR::RAX), dst);
+                        }
+                        _ => unreachable!(),
+                    },
                     _ => unreachable!(),
-                },
-                _ => unreachable!(),
+                }
             }
         }
 
         // Load vmctx into R15.
         a.emit_mov(
             Size::S64,
-            Self::get_param_location(0),
+            Self::get_param_location(vmctx_param),
             Location::GPR(GPR::R15),
         );
 
         // Stack probe.
         //
         // `rep stosq` writes data from low address to high address and may skip the stack guard page.
-        // so here we probe it explicitly when needed.
-        for i in (n_params..n).step_by(NATIVE_PAGE_SIZE / 8).skip(1) {
+        // so here we probe it explicitly when needed. A local takes at most 16 bytes.
+        for i in (n_params..n).step_by(NATIVE_PAGE_SIZE / 16).skip(1) {
             a.emit_mov(Size::S64, Location::Imm32(0), locations[i]);
         }
 
@@ -471,7 +587,7 @@ impl Machine {
         for i in n_params..n {
             match locations[i] {
                 Location::Memory(_, _) => {
-                    init_stack_loc_cnt += 1;
+                    init_stack_loc_cnt += local_size(i) / 8;
                     last_stack_loc = cmp::min(last_stack_loc, locations[i]);
                 }
                 Location::GPR(_) => {
@@ -498,6 +614,31 @@ impl Machine {
         locations
     }
 
+    /// Reserves `n` stack slots that are not tied to any Wasm value and stay
+    /// allocated for the rest of the function.
+    ///
+    /// Returns the location of the lowest slot; slot `i` is `8 * i` bytes above it.
+    pub fn reserve_stack_slots<E: Emitter>(&mut self, a: &mut E, n: usize) -> Location {
+        a.emit_sub(
+            Size::S64,
+            Location::Imm32((n * 8) as u32),
+            Location::GPR(GPR::RSP),
+        );
+        for i in 0..n {
+            self.stack_offset.0 += 8;
+            self.state.stack_values.push(MachineValue::Undefined);
+            // Probe the stack from higher to lower addresses so that the guard page is hit.
+            if i > 0 && i % (NATIVE_PAGE_SIZE / 8) == 0 {
+                a.emit_mov(
+                    Size::S64,
+                    Location::Imm32(0),
+                    Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32)),
+                );
+            }
+        }
+        Location::Memory(GPR::RBP, -(self.stack_offset.0 as i32))
+    }
+
     pub fn finalize_locals<E: Emitter>(&mut self, a: &mut E, locations: &[Location]) {
         // Unwind stack to the "save area".
         a.emit_lea(
@@ -0,0 +1,457 @@
+use crate::arm64_decl::UnwindOp;
+use crate::arm64_decl::{ARM64Register, ArgumentRegisterAllocator};
+use crate::emitter_arm64::*;
+use dynasmrt::AssemblyOffset;
+use smallvec::smallvec;
+use smallvec::SmallVec;
+use std::collections::HashSet;
+use wasmer_compiler::wasmparser::Type as WpType;
+use wasmer_types::Type;
+
+/// Rounds `x` up to the stack alignment.
+fn align16(x: usize) -> usize {
+    (x + 15) & !15
+}
+
+/// The register allocator and stack frame of a function compiled for AArch64.
+///
+/// The frame record (X29 and X30) is at the top of the frame, and everything
+/// else is addressed below the frame pointer X29. The stack pointer is kept
+/// 16-byte aligned, as required by the architecture, by always reserving the
+/// used stack size rounded up to 16 bytes.
+///
+/// Unlike the x86-64 `Machine`, no `MachineState` is tracked, so functions
+/// compiled for AArch64 don't support on-stack replacement.
+pub struct MachineARM64 {
+    used_gprs: HashSet<GPR>,
+    used_neons: HashSet<NEON>,
+    stack_offset: usize,
+    /// Callee-saved registers stored on function entry, with the offset of
+    /// their slot below the frame pointer.
+    saved_registers: Vec<(GPR, usize)>,
+    /// Unwind operations recorded while setting up the frame.
+    pub(crate) unwind_ops: Vec<(usize, UnwindOp)>,
+}
+
+impl MachineARM64 {
+    pub fn new() -> Self {
+        MachineARM64 {
+            used_gprs: HashSet::new(),
+            used_neons: HashSet::new(),
+            stack_offset: 0,
+            saved_registers: vec![],
+            unwind_ops: vec![],
+        }
+    }
+
+    pub fn get_used_gprs(&self) -> Vec<GPR> {
+        let mut gprs: Vec<GPR> = self.used_gprs.iter().cloned().collect();
+        gprs.sort();
+        gprs
+    }
+
+    pub fn get_used_neons(&self) -> Vec<NEON> {
+        let mut neons: Vec<NEON> = self.used_neons.iter().cloned().collect();
+        neons.sort();
+        neons
+    }
+
+    pub fn get_vmctx_reg() -> GPR {
+        GPR::X28
+    }
+
+    /// Picks an unused general purpose register for local/stack/argument use.
+    ///
+    /// This method does not mark the register as used.
+    pub fn pick_gpr(&self) -> Option<GPR> {
+        use GPR::*;
+        static REGS: &[GPR] = &[X12, X13, X14, X15];
+        for r in REGS {
+            if !self.used_gprs.contains(r) {
+                return Some(*r);
+            }
+        }
+        None
+    }
+
+    /// Picks an unused general purpose register for internal temporary use.
+    ///
+    /// This method does not mark the register as used.
+    pub fn pick_temp_gpr(&self) -> Option<GPR> {
+        use GPR::*;
+        static REGS: &[GPR] = &[X9, X10, X11];
+        for r in REGS {
+            if !self.used_gprs.contains(r) {
+                return Some(*r);
+            }
+        }
+        None
+    }
+
+    /// Acquires a temporary GPR.
+    pub fn acquire_temp_gpr(&mut self) -> Option<GPR> {
+        let gpr = self.pick_temp_gpr();
+        if let Some(x) = gpr {
+            self.used_gprs.insert(x);
+        }
+        gpr
+    }
+
+    /// Releases a temporary GPR.
+    pub fn release_temp_gpr(&mut self, gpr: GPR) {
+        assert!(self.used_gprs.remove(&gpr));
+    }
+
+    /// Specify that a given register is in use.
+    pub fn reserve_unused_temp_gpr(&mut self, gpr: GPR) -> GPR {
+        assert!(!self.used_gprs.contains(&gpr));
+        self.used_gprs.insert(gpr);
+        gpr
+    }
+
+    /// Picks an unused NEON register.
+    ///
+    /// This method does not mark the register as used.
+    pub fn pick_neon(&self) -> Option<NEON> {
+        use NEON::*;
+        static REGS: &[NEON] = &[V16, V17, V18, V19, V20, V21, V22, V23];
+        for r in REGS {
+            if !self.used_neons.contains(r) {
+                return Some(*r);
+            }
+        }
+        None
+    }
+
+    /// Picks an unused NEON register for internal temporary use.
+    ///
+    /// This method does not mark the register as used.
+    pub fn pick_temp_neon(&self) -> Option<NEON> {
+        use NEON::*;
+        static REGS: &[NEON] = &[V24, V25, V26, V27];
+        for r in REGS {
+            if !self.used_neons.contains(r) {
+                return Some(*r);
+            }
+        }
+        None
+    }
+
+    /// Acquires a temporary NEON register.
+    pub fn acquire_temp_neon(&mut self) -> Option<NEON> {
+        let neon = self.pick_temp_neon();
+        if let Some(x) = neon {
+            self.used_neons.insert(x);
+        }
+        neon
+    }
+
+    /// Releases a temporary NEON register.
+    pub fn release_temp_neon(&mut self, neon: NEON) {
+        assert!(self.used_neons.remove(&neon));
+    }
+
+    /// Moves the stack pointer from the frame of `old_offset` bytes to the
+    /// frame of `new_offset` bytes.
+    fn adjust_sp<E: EmitterARM64>(a: &mut E, old_offset: usize, new_offset: usize) {
+        let (old, new) = (align16(old_offset), align16(new_offset));
+        if new > old {
+            a.emit_sub_sp((new - old) as u32);
+        } else if old > new {
+            a.emit_add_sp((old - new) as u32);
+        }
+    }
+
+    /// Acquires locations from the machine state.
+    ///
+    /// If the returned locations are used for stack value, `release_location` needs to be called on them;
+    /// Otherwise, if the returned locations are used for locals, `release_location` does not need to be called on them.
+    pub fn acquire_locations<E: EmitterARM64>(
+        &mut self,
+        assembler: &mut E,
+        tys: &[WpType],
+        zeroed: bool,
+    ) -> SmallVec<[Location; 1]> {
+        let mut ret = smallvec![];
+        let old_offset = self.stack_offset;
+
+        for ty in tys {
+            let loc = match *ty {
+                WpType::F32 | WpType::F64 => self.pick_neon().map(Location::NEON),
+                WpType::I32 | WpType::I64 => self.pick_gpr().map(Location::GPR),
+                _ => unreachable!(),
+            };
+
+            let loc = if let Some(x) = loc {
+                x
+            } else {
+                self.stack_offset += 8;
+                Location::Memory(GPR::X29, -(self.stack_offset as i32))
+            };
+            if let Location::GPR(x) = loc {
+                self.used_gprs.insert(x);
+            } else if let Location::NEON(x) = loc {
+                self.used_neons.insert(x);
+            }
+            ret.push(loc);
+        }
+
+        Self::adjust_sp(assembler, old_offset, self.stack_offset);
+        if zeroed {
+            for loc in ret.iter() {
+                assembler.emit_mov(Size::S64, Location::Imm32(0), *loc);
+            }
+        }
+        ret
+    }
+
+    /// Releases locations used for stack value.
+    pub fn release_locations<E: EmitterARM64>(&mut self, assembler: &mut E, locs: &[Location]) {
+        self.release_locations_only_regs(locs);
+        self.release_locations_only_stack(assembler, locs);
+    }
+
+    pub fn release_locations_only_regs(&mut self, locs: &[Location]) {
+        for loc in locs.iter().rev() {
+            match *loc {
+                Location::GPR(ref x) => {
+                    assert!(self.used_gprs.remove(x));
+                }
+                Location::NEON(ref x) => {
+                    assert!(self.used_neons.remove(x));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn release_locations_only_stack<E: EmitterARM64>(
+        &mut self,
+        assembler: &mut E,
+        locs: &[Location],
+    ) {
+        let old_offset = self.stack_offset;
+        self.stack_offset = self.stack_offset_after_release(locs);
+        Self::adjust_sp(assembler, old_offset, self.stack_offset);
+    }
+
+    /// Emits the stack pointer adjustment of releasing `locs`, without
+    /// actually releasing them.
+    pub fn release_locations_keep_state<E: EmitterARM64>(
+        &self,
+        assembler: &mut E,
+        locs: &[Location],
+    ) {
+        Self::adjust_sp(
+            assembler,
+            self.stack_offset,
+            self.stack_offset_after_release(locs),
+        );
+    }
+
+    /// Returns the stack offset after releasing the stack slots of `locs`.
+    fn stack_offset_after_release(&self, locs: &[Location]) -> usize {
+        let mut stack_offset = self.stack_offset;
+        for loc in locs.iter().rev() {
+            if let Location::Memory(GPR::X29, x) = *loc {
+                if x >= 0 {
+                    unreachable!();
+                }
+                if (-x) as usize != stack_offset {
+                    unreachable!();
+                }
+                stack_offset -= 8;
+            }
+        }
+        stack_offset
+    }
+
+    /// Sets up the function frame, loading the parameters into the locals.
+    ///
+    /// This must be called right after the frame record is pushed and X29 is
+    /// set. `local_types` lists the types of all locals, starting with the
+    /// parameters, whose types are `params`.
+    pub fn init_locals<E: EmitterARM64<Offset = AssemblyOffset>>(
+        &mut self,
+        a: &mut E,
+        local_types: &[WpType],
+        params: &[Type],
+    ) -> Vec<Location> {
+        let n = local_types.len();
+        let n_params = params.len();
+
+        // Use callee-saved registers for the first locals, whatever their
+        // type. Floating point locals are kept as bits.
+        fn get_local_register(idx: usize) -> Option<GPR> {
+            use GPR::*;
+            static REGS: &[GPR] = &[X19, X20, X21, X22, X23, X24, X25, X26, X27];
+            REGS.get(idx).cloned()
+        }
+
+        // The callee-saved registers used for locals, and X28 for vmctx.
+        let saved_gprs: Vec<GPR> = (0..n)
+            .filter_map(get_local_register)
+            .chain(std::iter::once(Self::get_vmctx_reg()))
+            .collect();
+        let callee_saved_regs_size = saved_gprs.len() * 8;
+
+        // Now we can determine concrete locations for locals.
+        let mut mem_offset = callee_saved_regs_size;
+        let locations: Vec<Location> = (0..n)
+            .map(|i| match get_local_register(i) {
+                Some(x) => Location::GPR(x),
+                None => {
+                    mem_offset += 8;
+                    Location::Memory(GPR::X29, -(mem_offset as i32))
+                }
+            })
+            .collect();
+
+        // Allocate the whole static area, without actually writing to it.
+        Self::adjust_sp(a, 0, mem_offset);
+
+        // Save callee-saved registers.
+        for gpr in saved_gprs {
+            self.stack_offset += 8;
+            a.emit_mov(
+                Size::S64,
+                Location::GPR(gpr),
+                Location::Memory(GPR::X29, -(self.stack_offset as i32)),
+            );
+            self.saved_registers.push((gpr, self.stack_offset));
+            self.unwind_ops.push((
+                a.get_offset().0,
+                UnwindOp::SaveRegister {
+                    reg: ARM64Register::GPR(gpr),
+                    bp_neg_offset: self.stack_offset as i32,
+                },
+            ));
+        }
+
+        // Load parameters into the allocated locations. vmctx is in X0, so it
+        // is loaded last.
+        let param_locations = Self::get_param_locations(params);
+        for i in 0..n_params {
+            let loc = match param_locations[i] {
+                Location::Memory(GPR::XzrSp, disp) => Location::Memory(GPR::X29, 16 + disp),
+                x => x,
+            };
+            a.emit_mov(Size::S64, loc, locations[i]);
+        }
+
+        // Load vmctx into X28.
+        a.emit_mov(
+            Size::S64,
+            Location::GPR(GPR::X0),
+            Location::GPR(Self::get_vmctx_reg()),
+        );
+
+        // Initialize all normal locals to zero.
+        //
+        // Locals are zeroed from higher address to lower address, so that
+        // the stack guard page can't be skipped.
+        let mut stack_locals = vec![];
+        for i in n_params..n {
+            match locations[i] {
+                Location::GPR(_) => a.emit_mov(Size::S64, Location::Imm32(0), locations[i]),
+                loc => stack_locals.push(loc),
+            }
+        }
+        if stack_locals.len() <= 8 {
+            for loc in stack_locals {
+                a.emit_mov(Size::S64, Location::Imm32(0), loc);
+            }
+        } else if let Location::Memory(_, disp) = stack_locals[0] {
+            let label = a.get_label();
+            a.emit_sub(
+                Size::S64,
+                GPR::X29,
+                Location::Imm64((-disp) as u64),
+                GPR::X9,
+            );
+            a.emit_mov_imm(Size::S64, stack_locals.len() as u64, GPR::X10);
+            a.emit_label(label);
+            a.emit_mov(Size::S64, Location::Imm32(0), Location::Memory(GPR::X9, 0));
+            a.emit_sub(Size::S64, GPR::X9, Location::Imm32(8), GPR::X9);
+            a.emit_sub(Size::S64, GPR::X10, Location::Imm32(1), GPR::X10);
+            a.emit_cbnz_label(Size::S64, GPR::X10, label);
+        }
+
+        // Add the size of all locals allocated to stack.
+        self.stack_offset = mem_offset;
+
+        locations
+    }
+
+    /// Restores the callee-saved registers. The frame is popped by the caller.
+    pub fn finalize_locals<E: EmitterARM64>(&mut self, a: &mut E) {
+        for (gpr, offset) in self.saved_registers.iter() {
+            a.emit_mov(
+                Size::S64,
+                Location::Memory(GPR::X29, -(*offset as i32)),
+                Location::GPR(*gpr),
+            );
+        }
+    }
+
+    /// Returns where the arguments of a call with parameters `params` are
+    /// passed, following the AArch64 Procedure Call Standard, without vmctx
+    /// which is always in X0.
+    ///
+    /// Arguments passed on the stack are returned as `Memory(XzrSp, offset)`
+    /// relative to the stack pointer of the caller. Every argument takes 8
+    /// bytes, which doesn't match the packed layout of Apple platforms.
+    pub fn get_param_locations(params: &[Type]) -> Vec<Location> {
+        let mut allocator = ArgumentRegisterAllocator::default();
+        // vmctx.
+        allocator.next(Type::I64);
+        let mut stack_offset = 0;
+        params
+            .iter()
+            .map(|ty| match allocator.next(*ty) {
+                Some(ARM64Register::GPR(x)) => Location::GPR(x),
+                Some(ARM64Register::NEON(x)) => Location::NEON(x),
+                None => {
+                    stack_offset += 8;
+                    Location::Memory(GPR::XzrSp, stack_offset - 8)
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the size of the stack area for the arguments of a call with
+    /// parameters `params`, rounded up to the stack alignment.
+    pub fn get_param_stack_size(params: &[Type]) -> usize {
+        let n = Self::get_param_locations(params)
+            .iter()
+            .filter(|loc| matches!(loc, Location::Memory(_, _)))
+            .count();
+        align16(n * 8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dynasmrt::aarch64::Assembler;
+
+    #[test]
+    fn test_release_locations_keep_state_nopanic() {
+        let mut machine = MachineARM64::new();
+        let mut assembler = Assembler::new().unwrap();
+        let locs = machine.acquire_locations(&mut assembler, &[WpType::I32; 10], false);
+
+        machine.release_locations_keep_state(&mut assembler, &locs);
+    }
+
+    #[test]
+    fn test_param_locations() {
+        let params = [Type::I32; 9];
+        let locs = MachineARM64::get_param_locations(&params);
+        assert_eq!(locs[0], Location::GPR(GPR::X1));
+        assert_eq!(locs[6], Location::GPR(GPR::X7));
+        assert_eq!(locs[7], Location::Memory(GPR::XzrSp, 0));
+        assert_eq!(locs[8], Location::Memory(GPR::XzrSp, 8));
+        assert_eq!(MachineARM64::get_param_stack_size(&params), 16);
+    }
+}
@@ -0,0 +1,91 @@
+//! Unwind information for the functions generated by Singlepass.
+//!
+//! The code generator records an `UnwindOp` at the code offset following each
+//! instruction that changes how the caller's frame can be recovered. These
+//! operations are then turned into a DWARF frame description entry.
+
+use crate::arm64_decl::UnwindOp;
+use gimli::write::{Address, CallFrameInstruction, CommonInformationEntry, FrameDescriptionEntry};
+
+/// The call frame instructions of a function, not yet tied to an address.
+pub struct UnwindInstructions {
+    instructions: Vec<(u32, CallFrameInstruction)>,
+    len: u32,
+}
+
+impl UnwindInstructions {
+    /// Creates the frame description entry of a function starting at `address`.
+    pub fn to_fde(&self, address: Address) -> FrameDescriptionEntry {
+        let mut fde = FrameDescriptionEntry::new(address, self.len);
+        for (offset, instruction) in &self.instructions {
+            fde.add_instruction(*offset, instruction.clone());
+        }
+        fde
+    }
+}
+
+/// Returns the common information entry shared by all the functions of a
+/// module, which describes the state on function entry.
+pub fn create_systemv_cie() -> CommonInformationEntry {
+    use gimli::{Encoding, Format, Register};
+
+    let mut entry = CommonInformationEntry::new(
+        Encoding {
+            address_size: 8,
+            format: Format::Dwarf32,
+            version: 1,
+        },
+        4,
+        -8,
+        // The return address is in the link register.
+        Register(30),
+    );
+    // On entry, the canonical frame address is the stack pointer.
+    entry.add_instruction(CallFrameInstruction::Cfa(Register(31), 0));
+    entry
+}
+
+/// Creates the call frame instructions of a function from the operations
+/// recorded while generating its `code_len` bytes of code.
+pub fn create_unwind_info_from_insts(
+    insts: &[(usize, UnwindOp)],
+    code_len: usize,
+) -> Option<UnwindInstructions> {
+    use gimli::Register;
+
+    let mut instructions = vec![];
+    for &(offset, op) in insts {
+        let offset = offset as u32;
+        match op {
+            UnwindOp::PushFrameRecord { size } => {
+                instructions.push((offset, CallFrameInstruction::CfaOffset(size as i32)));
+                instructions.push((
+                    offset,
+                    CallFrameInstruction::Offset(Register(29), -(size as i32)),
+                ));
+                instructions.push((
+                    offset,
+                    CallFrameInstruction::Offset(Register(30), -(size as i32) + 8),
+                ));
+            }
+            UnwindOp::DefineNewFrame => {
+                instructions.push((offset, CallFrameInstruction::CfaRegister(Register(29))));
+            }
+            UnwindOp::SaveRegister { reg, bp_neg_offset } => {
+                // The frame pointer is 16 bytes below the canonical frame
+                // address, right under the saved frame record.
+                instructions.push((
+                    offset,
+                    CallFrameInstruction::Offset(Register(reg.dwarf_regnum()), -16 - bp_neg_offset),
+                ));
+            }
+        }
+    }
+    if instructions.is_empty() {
+        return None;
+    }
+    Some(UnwindInstructions {
+        instructions,
+        len: code_len as u32,
+    })
+}
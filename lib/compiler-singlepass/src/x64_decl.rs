@@ -184,7 +184,7 @@ impl ArgumentRegisterAllocator {
             XMM::XMM7,
         ];
         match ty {
-            Type::I32 | Type::I64 => {
+            Type::I32 | Type::I64 | Type::ExternRef | Type::FuncRef => {
                 if self.n_gprs < GPR_SEQ.len() {
                     let gpr = GPR_SEQ[self.n_gprs];
                     self.n_gprs += 1;
@@ -210,6 +210,97 @@ impl ArgumentRegisterAllocator {
     }
 }
 
+/// Describes how a function returning more than one value hands its results
+/// back to the caller.
+///
+/// The results are laid out like a `#[repr(C)]` struct with one field per
+/// value, and the struct is returned as the System V ABI prescribes. This is
+/// also how host functions defined with `Function::new_native` return
+/// multiple values, so both can be called the same way.
+#[derive(Debug)]
+pub struct MultiValueReturnLayout {
+    /// The byte offset of each value inside the struct.
+    pub offsets: Vec<usize>,
+    /// The size in bytes of the struct, including trailing padding.
+    pub size: usize,
+    /// The registers holding each eightbyte of the struct, or `None` if the
+    /// struct is returned in memory through a hidden pointer passed as the
+    /// first argument.
+    pub registers: Option<Vec<X64Register>>,
+}
+
+impl MultiValueReturnLayout {
+    /// Computes the layout for the given result types.
+    pub fn new(results: &[Type]) -> Self {
+        let mut offsets = Vec::with_capacity(results.len());
+        let mut size = 0;
+        let mut align = 1;
+        for ty in results {
+            let ty_size = match ty {
+                Type::I32 | Type::F32 => 4,
+                Type::I64 | Type::F64 | Type::ExternRef | Type::FuncRef => 8,
+                Type::V128 => 16,
+            };
+            // Every field is naturally aligned.
+            size = (size + ty_size - 1) / ty_size * ty_size;
+            offsets.push(size);
+            size += ty_size;
+            align = std::cmp::max(align, ty_size);
+        }
+        size = (size + align - 1) / align * align;
+
+        let in_memory = size > 16 || results.iter().any(|ty| *ty == Type::V128);
+        let registers = if in_memory {
+            None
+        } else {
+            static GPR_SEQ: &[GPR] = &[GPR::RAX, GPR::RDX];
+            static XMM_SEQ: &[XMM] = &[XMM::XMM0, XMM::XMM1];
+            let (mut n_gprs, mut n_xmms) = (0, 0);
+            Some(
+                (0..(size + 7) / 8)
+                    .map(|eightbyte| {
+                        // An eightbyte is passed in an XMM register only if
+                        // every value it contains is a float.
+                        let is_sse = results
+                            .iter()
+                            .zip(offsets.iter())
+                            .filter(|(_, offset)| **offset / 8 == eightbyte)
+                            .all(|(ty, _)| *ty == Type::F32 || *ty == Type::F64);
+                        if is_sse {
+                            n_xmms += 1;
+                            X64Register::XMM(XMM_SEQ[n_xmms - 1])
+                        } else {
+                            n_gprs += 1;
+                            X64Register::GPR(GPR_SEQ[n_gprs - 1])
+                        }
+                    })
+                    .collect(),
+            )
+        };
+
+        Self {
+            offsets,
+            size,
+            registers,
+        }
+    }
+
+    /// Whether the results are written through a hidden struct-return pointer.
+    pub fn is_sret(&self) -> bool {
+        self.registers.is_none()
+    }
+}
+
+/// Returns the return layout for a signature, or `None` if it returns at most
+/// one value.
+pub fn multi_value_return_layout(results: &[Type]) -> Option<MultiValueReturnLayout> {
+    if results.len() > 1 {
+        Some(MultiValueReturnLayout::new(results))
+    } else {
+        None
+    }
+}
+
 /// Create a new `MachineState` with default values.
 pub fn new_machine_state() -> MachineState {
     MachineState {
@@ -220,3 +311,29 @@ pub fn new_machine_state() -> MachineState {
         wasm_inst_offset: std::usize::MAX,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multi_value_return_layout() {
+        let layout = MultiValueReturnLayout::new(&[Type::I32, Type::F32, Type::F64]);
+        assert_eq!(layout.offsets, vec![0, 4, 8]);
+        assert_eq!(layout.size, 16);
+        assert_eq!(
+            layout.registers,
+            Some(vec![
+                X64Register::GPR(GPR::RAX),
+                X64Register::XMM(XMM::XMM0)
+            ])
+        );
+
+        let layout = MultiValueReturnLayout::new(&[Type::I32, Type::I64, Type::I32]);
+        assert_eq!(layout.offsets, vec![0, 8, 16]);
+        assert_eq!(layout.size, 24);
+        assert!(layout.is_sret());
+
+        assert!(multi_value_return_layout(&[Type::I64]).is_none());
+    }
+}
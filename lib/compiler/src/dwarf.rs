@@ -0,0 +1,168 @@
+//! A DWARF writer that records the relocations of the addresses it
+//! writes, so the compilers can emit an `.eh_frame` as a `CustomSection`
+//! that is patched later by the engine (native or JIT).
+
+use crate::lib::std::vec::Vec;
+use crate::{
+    CustomSection, CustomSectionProtection, Endianness, Relocation, RelocationKind,
+    RelocationTarget, SectionBody,
+};
+use gimli::write::{Address, EndianVec, Error, Result, Writer};
+use gimli::{RunTimeEndian, SectionId};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::LocalFunctionIndex;
+
+/// A `gimli` writer that turns the addresses of the local functions into
+/// relocations.
+#[derive(Clone, Debug)]
+pub struct WriterRelocate {
+    /// The relocations of the addresses written so far.
+    pub relocs: Vec<Relocation>,
+    writer: EndianVec<RunTimeEndian>,
+}
+
+impl WriterRelocate {
+    /// The symbol of the `Address::Symbol`s pointing to a local function,
+    /// whose index is given by the addend.
+    pub const FUNCTION_SYMBOL: usize = 0;
+
+    /// Creates a new writer with the given endianness, or the host's one.
+    pub fn new(endianness: Option<Endianness>) -> Self {
+        let endianness = match endianness {
+            Some(Endianness::Little) => RunTimeEndian::Little,
+            Some(Endianness::Big) => RunTimeEndian::Big,
+            // We autodetect it, based on the host
+            None => RunTimeEndian::default(),
+        };
+        WriterRelocate {
+            relocs: Vec::new(),
+            writer: EndianVec::new(endianness),
+        }
+    }
+
+    /// Turns what was written into a read-only `CustomSection`.
+    pub fn into_section(mut self) -> CustomSection {
+        // GCC expects a terminating "empty" length, so write a 0 length at the end of the table.
+        self.writer.write_u32(0).unwrap();
+        let data = self.writer.into_vec();
+        CustomSection {
+            protection: CustomSectionProtection::Read,
+            bytes: SectionBody::new_with_vec(data),
+            relocations: self.relocs,
+        }
+    }
+}
+
+impl Writer for WriterRelocate {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> Self::Endian {
+        self.writer.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.writer.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        self.writer.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> Result<()> {
+        match address {
+            Address::Constant(val) => self.write_udata(val, size),
+            Address::Symbol { symbol, addend } => {
+                // Is a function relocation
+                if symbol != Self::FUNCTION_SYMBOL {
+                    return Err(Error::InvalidAddress);
+                }
+                // We use the addend to detect the function index
+                let function_index = LocalFunctionIndex::new(addend as _);
+                let reloc_target = RelocationTarget::LocalFunc(function_index);
+                let offset = self.len() as u32;
+                let kind = match size {
+                    8 => RelocationKind::Abs8,
+                    _ => return Err(Error::UnsupportedWordSize(size)),
+                };
+                let addend = 0;
+                self.relocs.push(Relocation {
+                    kind,
+                    reloc_target,
+                    offset,
+                    addend,
+                });
+                self.write_udata(addend as u64, size)
+            }
+        }
+    }
+
+    // The offsets are relative to the start of `section`. Only `.eh_frame`
+    // is written, and its offsets point into itself, so unlike the addresses
+    // of the functions they don't need to be relocated.
+    fn write_offset(&mut self, val: usize, _section: SectionId, size: u8) -> Result<()> {
+        self.write_udata(val as u64, size)
+    }
+
+    fn write_offset_at(
+        &mut self,
+        offset: usize,
+        val: usize,
+        _section: SectionId,
+        size: u8,
+    ) -> Result<()> {
+        self.write_udata_at(offset, val as u64, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocates_the_addresses_of_functions() {
+        let mut writer = WriterRelocate::new(Some(Endianness::Little));
+        writer.write_u32(0).unwrap();
+        writer
+            .write_address(
+                Address::Symbol {
+                    symbol: WriterRelocate::FUNCTION_SYMBOL,
+                    addend: 3,
+                },
+                8,
+            )
+            .unwrap();
+        assert_eq!(writer.len(), 12);
+        assert_eq!(
+            writer.relocs,
+            vec![Relocation {
+                kind: RelocationKind::Abs8,
+                reloc_target: RelocationTarget::LocalFunc(LocalFunctionIndex::new(3)),
+                offset: 4,
+                addend: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_addresses() {
+        let mut writer = WriterRelocate::new(None);
+        let function = Address::Symbol {
+            symbol: WriterRelocate::FUNCTION_SYMBOL,
+            addend: 0,
+        };
+        assert_eq!(
+            writer.write_address(function, 4),
+            Err(Error::UnsupportedWordSize(4))
+        );
+        let other = Address::Symbol {
+            symbol: 1,
+            addend: 0,
+        };
+        assert_eq!(writer.write_address(other, 8), Err(Error::InvalidAddress));
+        assert!(writer.relocs.is_empty());
+    }
+}
@@ -52,6 +52,8 @@ mod lib {
 mod address_map;
 #[cfg(feature = "translator")]
 mod compiler;
+#[cfg(feature = "unwind")]
+mod dwarf;
 mod error;
 mod function;
 mod jump_table;
@@ -69,6 +71,8 @@ mod sourceloc;
 pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
 #[cfg(feature = "translator")]
 pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
+#[cfg(feature = "unwind")]
+pub use crate::dwarf::WriterRelocate;
 pub use crate::error::{
     CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
 };
@@ -3,21 +3,23 @@
 
 use super::module::translate_module;
 use super::state::ModuleTranslationState;
-use crate::lib::std::borrow::ToOwned;
 use crate::lib::std::string::ToString;
 use crate::lib::std::{boxed::Box, string::String, vec::Vec};
+use crate::wasm_unsupported;
 use crate::{WasmError, WasmResult};
+use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
+use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::FunctionType;
 use wasmer_types::{
     CustomSectionIndex, DataIndex, DataInitializer, DataInitializerLocation, ElemIndex,
-    ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
-    LocalFunctionIndex, MemoryIndex, MemoryType, SignatureIndex, TableIndex, TableInitializer,
-    TableType,
+    ExportIndex, ExternType, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
+    InstanceIndex, InstanceType, LocalFunctionIndex, MemoryIndex, MemoryType, ModuleIndex,
+    ModuleType, SignatureIndex, TableIndex, TableInitializer, TableType, TagIndex,
 };
-use wasmer_vm::ModuleInfo;
+use wasmer_vm::{ModuleInfo, ModuleInitializer};
 
 /// Contains function data: bytecode and its offset in the module.
 #[derive(Hash)]
@@ -52,6 +54,20 @@ pub struct ModuleEnvironment<'data> {
     /// The result to be filled in.
     pub result: ModuleInfoTranslation<'data>,
     imports: u32,
+    /// The instance and module types of the type section, by type index.
+    instance_types: HashMap<SignatureIndex, InstanceType>,
+    module_types: HashMap<SignatureIndex, ModuleType>,
+    /// The signatures of the functions and tags aliased from instances,
+    /// which are only added to the signatures once the type section is done.
+    aliased_signatures: Vec<(ImportIndex, FunctionType)>,
+    /// The instance index space of the binary, mapped to the instances of
+    /// the module.
+    ///
+    /// With the module linking proposal, the first two-level import from a
+    /// module name implicitly defines an instance as well. Those instances
+    /// aren't supported, and are `None` here.
+    instance_indices: Vec<Option<InstanceIndex>>,
+    implicit_instances: HashSet<String>,
 }
 
 impl<'data> ModuleEnvironment<'data> {
@@ -65,6 +81,11 @@ impl<'data> ModuleEnvironment<'data> {
                 module_translation_state: None,
             },
             imports: 0,
+            instance_types: HashMap::new(),
+            module_types: HashMap::new(),
+            aliased_signatures: Vec::new(),
+            instance_indices: Vec::new(),
+            implicit_instances: HashSet::new(),
         }
     }
 
@@ -73,10 +94,32 @@ impl<'data> ModuleEnvironment<'data> {
     pub fn translate(mut self, data: &'data [u8]) -> WasmResult<ModuleInfoTranslation<'data>> {
         assert!(self.result.module_translation_state.is_none());
         let module_translation_state = translate_module(data, &mut self)?;
+        self.declare_aliased_signatures();
         self.result.module_translation_state = Some(module_translation_state);
         Ok(self.result)
     }
 
+    /// Gives the functions and tags aliased from instances their signature.
+    ///
+    /// Type sections may follow alias sections with the module linking
+    /// proposal, so the signatures missing from the module are only added
+    /// after all of them, to keep the signature indices of the module as they
+    /// are in the binary.
+    fn declare_aliased_signatures(&mut self) {
+        let module = &mut self.result.module;
+        for (index, sig) in self.aliased_signatures.drain(..) {
+            let sig_index = match module.signatures.iter().find(|(_, s)| **s == sig) {
+                Some((sig_index, _)) => sig_index,
+                None => module.signatures.push(sig),
+            };
+            match index {
+                ImportIndex::Function(index) => module.functions[index] = sig_index,
+                ImportIndex::Tag(index) => module.tags[index] = sig_index,
+                _ => unreachable!("only functions and tags have a signature"),
+            }
+        }
+    }
+
     pub(crate) fn declare_export(&mut self, export: ExportIndex, name: &str) -> WasmResult<()> {
         self.result
             .module
@@ -112,6 +155,48 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_instance_type(&mut self, ty: InstanceType) -> WasmResult<()> {
+        let index = self.declare_placeholder_signature();
+        self.instance_types.insert(index, ty);
+        Ok(())
+    }
+
+    pub(crate) fn declare_module_type(&mut self, ty: ModuleType) -> WasmResult<()> {
+        let index = self.declare_placeholder_signature();
+        self.module_types.insert(index, ty);
+        Ok(())
+    }
+
+    /// Instance and module types take up a type index, so they get a
+    /// signature that nothing refers to, to keep the signatures of the
+    /// function types at their index.
+    fn declare_placeholder_signature(&mut self) -> SignatureIndex {
+        self.result
+            .module
+            .signatures
+            .push(FunctionType::new(Vec::new(), Vec::new()))
+    }
+
+    pub(crate) fn signature(&self, index: SignatureIndex) -> WasmResult<&FunctionType> {
+        self.result
+            .module
+            .signatures
+            .get(index)
+            .ok_or_else(|| WasmError::Generic(format!("unknown type {}", index.as_u32())))
+    }
+
+    pub(crate) fn instance_type(&self, index: SignatureIndex) -> WasmResult<&InstanceType> {
+        self.instance_types.get(&index).ok_or_else(|| {
+            WasmError::Generic(format!("type {} is not an instance type", index.as_u32()))
+        })
+    }
+
+    pub(crate) fn module_type(&self, index: SignatureIndex) -> WasmResult<&ModuleType> {
+        self.module_types.get(&index).ok_or_else(|| {
+            WasmError::Generic(format!("type {} is not a module type", index.as_u32()))
+        })
+    }
+
     pub(crate) fn declare_func_import(
         &mut self,
         sig_index: SignatureIndex,
@@ -208,10 +293,241 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_tag_import(
+        &mut self,
+        sig_index: SignatureIndex,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        debug_assert_eq!(
+            self.result.module.tags.len(),
+            self.result.module.num_imported_tags,
+            "Imported tags must be declared first"
+        );
+        self.declare_import(
+            ImportIndex::Tag(TagIndex::from_u32(
+                self.result.module.num_imported_tags as _,
+            )),
+            module,
+            field,
+        )?;
+        self.result.module.tags.push(sig_index);
+        self.result.module.num_imported_tags += 1;
+        self.imports += 1;
+        Ok(())
+    }
+
+    pub(crate) fn declare_module_import(
+        &mut self,
+        ty: ModuleType,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        let index = self.result.module.modules.push(ty);
+        self.declare_import(ImportIndex::Module(index), module, field)?;
+        self.imports += 1;
+        Ok(())
+    }
+
+    pub(crate) fn declare_instance_import(
+        &mut self,
+        ty: InstanceType,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        let index = self.result.module.instances.push(ty);
+        self.instance_indices.push(Some(index));
+        self.declare_import(ImportIndex::Instance(index), module, field)?;
+        self.imports += 1;
+        Ok(())
+    }
+
+    /// Declares the instance implicitly defined by a two-level import from
+    /// `module`, if it's the first one.
+    pub(crate) fn declare_implicit_instance(&mut self, module: &str) -> WasmResult<()> {
+        if self.implicit_instances.insert(module.to_string()) {
+            self.instance_indices.push(None);
+        }
+        Ok(())
+    }
+
+    /// Maps an index of the instance index space of the binary to an
+    /// instance of the module.
+    fn instance_index(&self, index: InstanceIndex) -> WasmResult<InstanceIndex> {
+        match self.instance_indices.get(index.as_u32() as usize) {
+            Some(Some(index)) => Ok(*index),
+            Some(None) => Err(wasm_unsupported!(
+                "instance {} implicitly defined by two-level imports",
+                index.as_u32()
+            )),
+            None => Err(WasmError::Generic(format!(
+                "unknown instance {}",
+                index.as_u32()
+            ))),
+        }
+    }
+
     pub(crate) fn finish_imports(&mut self) -> WasmResult<()> {
         Ok(())
     }
 
+    /// Declares a module of the module section, given its binary.
+    ///
+    /// The nested module is translated on its own to know its type, and it
+    /// is compiled when it is instantiated.
+    pub(crate) fn declare_module(&mut self, binary: &'data [u8]) -> WasmResult<()> {
+        let nested = ModuleEnvironment::new().translate(binary)?.module;
+        let ty = ModuleType::new(
+            nested.imports().collect::<Vec<_>>(),
+            nested.exports().collect::<Vec<_>>(),
+        );
+        let index = self.result.module.modules.push(ty);
+        self.result
+            .module
+            .initializers
+            .push(ModuleInitializer::DefineModule {
+                index,
+                binary: Arc::from(binary),
+            });
+        Ok(())
+    }
+
+    pub(crate) fn declare_instance(
+        &mut self,
+        module: ModuleIndex,
+        args: Vec<(String, ExportIndex)>,
+    ) -> WasmResult<()> {
+        let args = args
+            .into_iter()
+            .map(|(name, entity)| match entity {
+                ExportIndex::Instance(index) => {
+                    Ok((name, ExportIndex::Instance(self.instance_index(index)?)))
+                }
+                entity => Ok((name, entity)),
+            })
+            .collect::<WasmResult<Vec<_>>>()?;
+        let ty = self
+            .result
+            .module
+            .modules
+            .get(module)
+            .ok_or_else(|| WasmError::Generic(format!("unknown module {}", module.as_u32())))?
+            .instance_type()
+            .clone();
+        let index = self.result.module.instances.push(ty);
+        self.instance_indices.push(Some(index));
+        self.result
+            .module
+            .initializers
+            .push(ModuleInitializer::Instantiate {
+                index,
+                module,
+                args,
+            });
+        Ok(())
+    }
+
+    /// Declares an alias of the export of an instance.
+    ///
+    /// Aliased functions, tables, memories, globals and tags are imported
+    /// ones for the VM, so they must be declared before any local ones, like
+    /// imports.
+    pub(crate) fn declare_instance_export_alias(
+        &mut self,
+        instance: InstanceIndex,
+        export: &str,
+    ) -> WasmResult<()> {
+        let instance = self.instance_index(instance)?;
+        let ty = self
+            .result
+            .module
+            .instances
+            .get(instance)
+            .and_then(|ty| ty.export(export))
+            .cloned()
+            .ok_or_else(|| {
+                WasmError::Generic(format!(
+                    "unknown export {} of instance {}",
+                    export,
+                    instance.as_u32()
+                ))
+            })?;
+        let module = &mut self.result.module;
+        let index = match ty {
+            ExternType::Function(sig) => {
+                debug_assert_eq!(
+                    module.functions.len(),
+                    module.num_imported_functions,
+                    "Aliased functions must be declared before local ones"
+                );
+                let index = module.functions.push(SignatureIndex::reserved_value());
+                module.num_imported_functions += 1;
+                self.aliased_signatures
+                    .push((ImportIndex::Function(index), sig));
+                ImportIndex::Function(index)
+            }
+            ExternType::Table(table) => {
+                debug_assert_eq!(
+                    module.tables.len(),
+                    module.num_imported_tables,
+                    "Aliased tables must be declared before local ones"
+                );
+                module.num_imported_tables += 1;
+                ImportIndex::Table(module.tables.push(table))
+            }
+            ExternType::Memory(memory) => {
+                debug_assert_eq!(
+                    module.memories.len(),
+                    module.num_imported_memories,
+                    "Aliased memories must be declared before local ones"
+                );
+                module.num_imported_memories += 1;
+                ImportIndex::Memory(module.memories.push(memory))
+            }
+            ExternType::Global(global) => {
+                debug_assert_eq!(
+                    module.globals.len(),
+                    module.num_imported_globals,
+                    "Aliased globals must be declared before local ones"
+                );
+                module.num_imported_globals += 1;
+                ImportIndex::Global(module.globals.push(global))
+            }
+            ExternType::Tag(tag) => {
+                debug_assert_eq!(
+                    module.tags.len(),
+                    module.num_imported_tags,
+                    "Aliased tags must be declared before local ones"
+                );
+                let index = module.tags.push(SignatureIndex::reserved_value());
+                module.num_imported_tags += 1;
+                self.aliased_signatures.push((
+                    ImportIndex::Tag(index),
+                    FunctionType::new(tag.params(), Vec::new()),
+                ));
+                ImportIndex::Tag(index)
+            }
+            ExternType::Instance(instance_type) => {
+                let index = module.instances.push(instance_type);
+                self.instance_indices.push(Some(index));
+                ImportIndex::Instance(index)
+            }
+            ExternType::Module(module_type) => {
+                ImportIndex::Module(module.modules.push(module_type))
+            }
+        };
+        module
+            .initializers
+            .push(ModuleInitializer::AliasInstanceExport {
+                instance,
+                export: String::from(export),
+                index,
+                import_idx: self.imports,
+            });
+        self.imports += 1;
+        Ok(())
+    }
+
     pub(crate) fn reserve_func_types(&mut self, num: u32) -> WasmResult<()> {
         self.result
             .module
@@ -250,15 +566,23 @@ impl<'data> ModuleEnvironment<'data> {
     }
 
     pub(crate) fn declare_memory(&mut self, memory: MemoryType) -> WasmResult<()> {
-        if memory.shared {
-            return Err(WasmError::Unsupported(
-                "shared memories are not supported yet".to_owned(),
-            ));
-        }
         self.result.module.memories.push(memory);
         Ok(())
     }
 
+    pub(crate) fn reserve_tags(&mut self, num: u32) -> WasmResult<()> {
+        self.result
+            .module
+            .tags
+            .reserve_exact(usize::try_from(num).unwrap());
+        Ok(())
+    }
+
+    pub(crate) fn declare_tag(&mut self, sig_index: SignatureIndex) -> WasmResult<()> {
+        self.result.module.tags.push(sig_index);
+        Ok(())
+    }
+
     pub(crate) fn reserve_globals(&mut self, num: u32) -> WasmResult<()> {
         self.result
             .module
@@ -317,6 +641,27 @@ impl<'data> ModuleEnvironment<'data> {
         self.declare_export(ExportIndex::Global(global_index), name)
     }
 
+    pub(crate) fn declare_tag_export(&mut self, tag_index: TagIndex, name: &str) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Tag(tag_index), name)
+    }
+
+    pub(crate) fn declare_module_export(
+        &mut self,
+        module_index: ModuleIndex,
+        name: &str,
+    ) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Module(module_index), name)
+    }
+
+    pub(crate) fn declare_instance_export(
+        &mut self,
+        instance_index: InstanceIndex,
+        name: &str,
+    ) -> WasmResult<()> {
+        let instance_index = self.instance_index(instance_index)?;
+        self.declare_export(ExportIndex::Instance(instance_index), name)
+    }
+
     pub(crate) fn declare_start_function(&mut self, func_index: FunctionIndex) -> WasmResult<()> {
         debug_assert!(self.result.module.start_function.is_none());
         self.result.module.start_function = Some(func_index);
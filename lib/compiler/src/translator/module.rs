@@ -5,9 +5,10 @@
 //! to deal with each part of it.
 use super::environ::ModuleEnvironment;
 use super::sections::{
-    parse_data_section, parse_element_section, parse_export_section, parse_function_section,
-    parse_global_section, parse_import_section, parse_memory_section, parse_name_section,
-    parse_start_section, parse_table_section, parse_type_section,
+    parse_alias_section, parse_data_section, parse_element_section, parse_export_section,
+    parse_function_section, parse_global_section, parse_import_section, parse_instance_section,
+    parse_memory_section, parse_name_section, parse_start_section, parse_table_section,
+    parse_tag_section, parse_type_section,
 };
 use super::state::ModuleTranslationState;
 use crate::WasmResult;
@@ -21,8 +22,23 @@ pub fn translate_module<'data>(
 ) -> WasmResult<ModuleTranslationState> {
     let mut module_translation_state = ModuleTranslationState::new();
 
+    // `parse_all` also goes through the nested modules, right after their
+    // entry in the module section. They are translated on their own when
+    // they are declared, so their payloads are skipped here.
+    let mut nested_depth = 0;
+
     for payload in Parser::new(0).parse_all(data) {
-        match payload? {
+        let payload = payload?;
+        if nested_depth > 0 {
+            match payload {
+                Payload::ModuleSectionEntry { .. } => nested_depth += 1,
+                Payload::End => nested_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        match payload {
             Payload::Version { .. } | Payload::End => {}
 
             Payload::TypeSection(types) => {
@@ -45,6 +61,10 @@ pub fn translate_module<'data>(
                 parse_memory_section(memories, environ)?;
             }
 
+            Payload::EventSection(tags) => {
+                parse_tag_section(tags, environ)?;
+            }
+
             Payload::GlobalSection(globals) => {
                 parse_global_section(globals, environ)?;
             }
@@ -81,12 +101,18 @@ pub fn translate_module<'data>(
                 environ.reserve_passive_data(count)?;
             }
 
-            Payload::InstanceSection(_)
-            | Payload::AliasSection(_)
-            | Payload::EventSection(_)
-            | Payload::ModuleSectionStart { .. }
-            | Payload::ModuleSectionEntry { .. } => {
-                unimplemented!("module linking not implemented yet")
+            Payload::InstanceSection(instances) => {
+                parse_instance_section(instances, environ)?;
+            }
+
+            Payload::AliasSection(aliases) => {
+                parse_alias_section(aliases, environ)?;
+            }
+
+            Payload::ModuleSectionStart { .. } => {}
+            Payload::ModuleSectionEntry { range, .. } => {
+                environ.declare_module(&data[range.start..range.end])?;
+                nested_depth = 1;
             }
 
             Payload::CustomSection {
@@ -21,15 +21,20 @@ use std::vec::Vec;
 use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex, FunctionType,
+    GlobalIndex, GlobalInit, GlobalType, ImportType, InstanceIndex, InstanceType, MemoryIndex,
+    MemoryType, ModuleIndex, ModuleType, Pages, SignatureIndex, TableIndex, TableType, TagIndex,
+    TagType, Type, V128, WASM64_MAX_PAGES,
 };
 use wasmparser::{
-    self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
-    ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType as WPFunctionType,
-    FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionEntryType,
-    ImportSectionReader, MemorySectionReader, MemoryType as WPMemoryType, NameSectionReader,
-    Naming, NamingReader, Operator, TableSectionReader, TypeDef, TypeSectionReader,
+    self, Alias, AliasSectionReader, Data, DataKind, DataSectionReader, Element, ElementItem,
+    ElementItems, ElementKind, ElementSectionReader, EventSectionReader, EventType, Export,
+    ExportSectionReader, ExportType as WPExportType, ExternalKind, FuncType as WPFunctionType,
+    FunctionSectionReader, GlobalSectionReader, GlobalType as WPGlobalType, Import,
+    ImportSectionEntryType, ImportSectionReader, InstanceSectionReader,
+    InstanceType as WPInstanceType, MemorySectionReader, MemoryType as WPMemoryType,
+    ModuleType as WPModuleType, NameSectionReader, Naming, NamingReader, Operator,
+    TableSectionReader, TypeDef, TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -59,32 +64,111 @@ pub fn parse_type_section(
     environ.reserve_signatures(count)?;
 
     for entry in types {
-        if let Ok(TypeDef::Func(WPFunctionType { params, returns })) = entry {
-            let sig_params: Vec<Type> = params
-                .iter()
-                .map(|ty| {
-                    wptype_to_type(*ty)
-                        .expect("only numeric types are supported in function signatures")
-                })
-                .collect();
-            let sig_returns: Vec<Type> = returns
-                .iter()
-                .map(|ty| {
-                    wptype_to_type(*ty)
-                        .expect("only numeric types are supported in function signatures")
-                })
-                .collect();
-            let sig = FunctionType::new(sig_params, sig_returns);
-            environ.declare_signature(sig)?;
-            module_translation_state.wasm_types.push((params, returns));
-        } else {
-            unimplemented!("module linking not implemented yet")
+        match entry? {
+            TypeDef::Func(WPFunctionType { params, returns }) => {
+                let sig_params: Vec<Type> = params
+                    .iter()
+                    .map(|ty| {
+                        wptype_to_type(*ty)
+                            .expect("only numeric types are supported in function signatures")
+                    })
+                    .collect();
+                let sig_returns: Vec<Type> = returns
+                    .iter()
+                    .map(|ty| {
+                        wptype_to_type(*ty)
+                            .expect("only numeric types are supported in function signatures")
+                    })
+                    .collect();
+                let sig = FunctionType::new(sig_params, sig_returns);
+                environ.declare_signature(sig)?;
+                module_translation_state.wasm_types.push((params, returns));
+            }
+            TypeDef::Instance(WPInstanceType { exports }) => {
+                let ty = InstanceType::new(export_types(&exports, environ)?);
+                environ.declare_instance_type(ty)?;
+                module_translation_state
+                    .wasm_types
+                    .push((Vec::new().into_boxed_slice(), Vec::new().into_boxed_slice()));
+            }
+            TypeDef::Module(WPModuleType { imports, exports }) => {
+                let ty = ModuleType::new(
+                    import_types(&imports, environ)?,
+                    export_types(&exports, environ)?,
+                );
+                environ.declare_module_type(ty)?;
+                module_translation_state
+                    .wasm_types
+                    .push((Vec::new().into_boxed_slice(), Vec::new().into_boxed_slice()));
+            }
         }
     }
 
     Ok(())
 }
 
+/// Translates the type of an import, or of an export of an instance or
+/// module type, to an `ExternType`.
+fn extern_type(ty: &ImportSectionEntryType, environ: &ModuleEnvironment) -> WasmResult<ExternType> {
+    Ok(match *ty {
+        ImportSectionEntryType::Function(sig) => {
+            ExternType::Function(environ.signature(SignatureIndex::from_u32(sig))?.clone())
+        }
+        ImportSectionEntryType::Event(EventType { type_index }) => {
+            let sig = environ.signature(SignatureIndex::from_u32(type_index))?;
+            ExternType::Tag(TagType::new(sig.params()))
+        }
+        ImportSectionEntryType::Memory(ref memory) => ExternType::Memory(memory_type(memory)?),
+        ImportSectionEntryType::Global(ref ty) => ExternType::Global(GlobalType {
+            ty: wptype_to_type(ty.content_type)?,
+            mutability: ty.mutable.into(),
+        }),
+        ImportSectionEntryType::Table(ref tab) => ExternType::Table(TableType {
+            ty: wptype_to_type(tab.element_type)?,
+            minimum: tab.limits.initial,
+            maximum: tab.limits.maximum,
+        }),
+        ImportSectionEntryType::Module(type_index) => ExternType::Module(
+            environ
+                .module_type(SignatureIndex::from_u32(type_index))?
+                .clone(),
+        ),
+        ImportSectionEntryType::Instance(type_index) => ExternType::Instance(
+            environ
+                .instance_type(SignatureIndex::from_u32(type_index))?
+                .clone(),
+        ),
+    })
+}
+
+fn import_types(imports: &[Import], environ: &ModuleEnvironment) -> WasmResult<Vec<ImportType>> {
+    imports
+        .iter()
+        .map(|import| {
+            Ok(ImportType::new(
+                import.module,
+                import.field.unwrap_or_default(),
+                extern_type(&import.ty, environ)?,
+            ))
+        })
+        .collect()
+}
+
+fn export_types(
+    exports: &[WPExportType],
+    environ: &ModuleEnvironment,
+) -> WasmResult<Vec<ExportType>> {
+    exports
+        .iter()
+        .map(|export| {
+            Ok(ExportType::new(
+                export.name,
+                extern_type(&export.ty, environ)?,
+            ))
+        })
+        .collect()
+}
+
 /// Parses the Import section of the wasm module.
 pub fn parse_import_section<'data>(
     imports: ImportSectionReader<'data>,
@@ -97,6 +181,10 @@ pub fn parse_import_section<'data>(
         let module_name = import.module;
         let field_name = import.field;
 
+        if field_name.is_some() {
+            environ.declare_implicit_instance(module_name)?;
+        }
+
         match import.ty {
             ImportSectionEntryType::Function(sig) => {
                 environ.declare_func_import(
@@ -105,28 +193,32 @@ pub fn parse_import_section<'data>(
                     field_name.unwrap_or_default(),
                 )?;
             }
-            ImportSectionEntryType::Module(_)
-            | ImportSectionEntryType::Instance(_)
-            | ImportSectionEntryType::Event(_) => {
-                unimplemented!("module linking not implemented yet")
+            ImportSectionEntryType::Event(EventType { type_index }) => {
+                environ.declare_tag_import(
+                    SignatureIndex::from_u32(type_index),
+                    module_name,
+                    field_name.unwrap_or_default(),
+                )?;
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M32 {
-                limits: ref memlimits,
-                shared,
-            }) => {
+            ImportSectionEntryType::Module(type_index) => {
+                let ty = environ
+                    .module_type(SignatureIndex::from_u32(type_index))?
+                    .clone();
+                environ.declare_module_import(ty, module_name, field_name.unwrap_or_default())?;
+            }
+            ImportSectionEntryType::Instance(type_index) => {
+                let ty = environ
+                    .instance_type(SignatureIndex::from_u32(type_index))?
+                    .clone();
+                environ.declare_instance_import(ty, module_name, field_name.unwrap_or_default())?;
+            }
+            ImportSectionEntryType::Memory(ref memory) => {
                 environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(memlimits.initial),
-                        maximum: memlimits.maximum.map(Pages),
-                        shared,
-                    },
+                    memory_type(memory)?,
                     module_name,
                     field_name.unwrap_or_default(),
                 )?;
             }
-            ImportSectionEntryType::Memory(WPMemoryType::M64 { .. }) => {
-                unimplemented!("64bit memory not implemented yet")
-            }
             ImportSectionEntryType::Global(ref ty) => {
                 environ.declare_global_import(
                     GlobalType {
@@ -204,16 +296,51 @@ pub fn parse_memory_section(
 
     for entry in memories {
         let memory = entry?;
-        match memory {
-            WPMemoryType::M32 { limits, shared } => {
-                environ.declare_memory(MemoryType {
-                    minimum: Pages(limits.initial),
-                    maximum: limits.maximum.map(Pages),
-                    shared,
-                })?;
+        environ.declare_memory(memory_type(&memory)?)?;
+    }
+
+    Ok(())
+}
+
+/// Converts a wasmparser memory type into a `MemoryType`.
+fn memory_type(memory: &WPMemoryType) -> WasmResult<MemoryType> {
+    Ok(match *memory {
+        WPMemoryType::M32 { ref limits, shared } => MemoryType {
+            minimum: Pages(limits.initial),
+            maximum: limits.maximum.map(Pages),
+            shared,
+            memory64: false,
+        },
+        WPMemoryType::M64 { ref limits, shared } => {
+            let pages = |count: u64| {
+                u32::try_from(count).map(Pages).map_err(|_| {
+                    wasm_unsupported!(
+                        "64-bit memories with more than {} pages are not supported",
+                        WASM64_MAX_PAGES
+                    )
+                })
+            };
+            MemoryType {
+                minimum: pages(limits.initial)?,
+                maximum: limits.maximum.map(pages).transpose()?,
+                shared,
+                memory64: true,
             }
-            WPMemoryType::M64 { .. } => unimplemented!("64bit memory not implemented yet"),
         }
+    })
+}
+
+/// Parses the Event section of the wasm module, which declares the exception
+/// tags of the module.
+pub fn parse_tag_section(
+    tags: EventSectionReader,
+    environ: &mut ModuleEnvironment,
+) -> WasmResult<()> {
+    environ.reserve_tags(tags.get_count())?;
+
+    for entry in tags {
+        let EventType { type_index } = entry?;
+        environ.declare_tag(SignatureIndex::from_u32(type_index))?;
     }
 
     Ok(())
@@ -294,12 +421,14 @@ pub fn parse_export_section<'data>(
             ExternalKind::Global => {
                 environ.declare_global_export(GlobalIndex::new(index), field)?
             }
-            ExternalKind::Type
-            | ExternalKind::Module
-            | ExternalKind::Instance
-            | ExternalKind::Event => {
-                unimplemented!("module linking not implemented yet")
+            ExternalKind::Event => environ.declare_tag_export(TagIndex::new(index), field)?,
+            ExternalKind::Module => {
+                environ.declare_module_export(ModuleIndex::new(index), field)?
             }
+            ExternalKind::Instance => {
+                environ.declare_instance_export(InstanceIndex::new(index), field)?
+            }
+            ExternalKind::Type => return Err(wasm_unsupported!("type exports")),
         }
     }
 
@@ -307,6 +436,58 @@ pub fn parse_export_section<'data>(
     Ok(())
 }
 
+/// Parses the Instance section of the wasm module.
+pub fn parse_instance_section<'data>(
+    instances: InstanceSectionReader<'data>,
+    environ: &mut ModuleEnvironment<'data>,
+) -> WasmResult<()> {
+    for entry in instances {
+        let instance = entry?;
+        let mut args = Vec::new();
+        for arg in instance.args()? {
+            let arg = arg?;
+            let index = arg.index as usize;
+            let entity = match arg.kind {
+                ExternalKind::Function => ExportIndex::Function(FunctionIndex::new(index)),
+                ExternalKind::Table => ExportIndex::Table(TableIndex::new(index)),
+                ExternalKind::Memory => ExportIndex::Memory(MemoryIndex::new(index)),
+                ExternalKind::Global => ExportIndex::Global(GlobalIndex::new(index)),
+                ExternalKind::Event => ExportIndex::Tag(TagIndex::new(index)),
+                ExternalKind::Module => ExportIndex::Module(ModuleIndex::new(index)),
+                ExternalKind::Instance => ExportIndex::Instance(InstanceIndex::new(index)),
+                ExternalKind::Type => return Err(wasm_unsupported!("type arguments")),
+            };
+            args.push((arg.name.to_string(), entity));
+        }
+        environ.declare_instance(ModuleIndex::from_u32(instance.module()), args)?;
+    }
+
+    Ok(())
+}
+
+/// Parses the Alias section of the wasm module.
+pub fn parse_alias_section<'data>(
+    aliases: AliasSectionReader<'data>,
+    environ: &mut ModuleEnvironment<'data>,
+) -> WasmResult<()> {
+    for entry in aliases {
+        match entry? {
+            Alias::InstanceExport {
+                instance, export, ..
+            } => {
+                environ.declare_instance_export_alias(InstanceIndex::from_u32(instance), export)?;
+            }
+            // Nested modules are compiled on their own, without the types
+            // and modules of the modules around them.
+            Alias::OuterType { .. } | Alias::OuterModule { .. } => {
+                return Err(wasm_unsupported!("outer aliases"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Parses the Start section of the wasm module.
 pub fn parse_start_section(index: u32, environ: &mut ModuleEnvironment) -> WasmResult<()> {
     environ.declare_start_function(FunctionIndex::from_u32(index))?;
@@ -395,6 +576,13 @@ pub fn parse_data_section<'data>(
                 let mut init_expr_reader = init_expr.get_binary_reader();
                 let (base, offset) = match init_expr_reader.read_operator()? {
                     Operator::I32Const { value } => (None, value as u32 as usize),
+                    // Active segments of 64-bit memories have `i64` offsets.
+                    Operator::I64Const { value } => (
+                        None,
+                        usize::try_from(value as u64).map_err(|_| {
+                            wasm_unsupported!("data segment offset {} is too large", value as u64)
+                        })?,
+                    ),
                     Operator::GlobalGet { global_index } => {
                         (Some(GlobalIndex::from_u32(global_index)), 0)
                     }
@@ -22,7 +22,8 @@ use wasmer_types::{
     TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, MemoryImages, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex,
+    VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -34,6 +35,7 @@ pub struct JITArtifact {
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    memory_images: MemoryImages,
 }
 
 impl JITArtifact {
@@ -223,6 +225,10 @@ impl JITArtifact {
         let finished_dynamic_function_trampolines =
             finished_dynamic_function_trampolines.into_boxed_slice();
         let signatures = signatures.into_boxed_slice();
+        let memory_images = MemoryImages::new(
+            &serializable.compile_info.module,
+            &serializable.data_initializers,
+        );
 
         Ok(Self {
             serializable,
@@ -232,6 +238,7 @@ impl JITArtifact {
             signatures,
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
+            memory_images,
         })
     }
 
@@ -287,6 +294,10 @@ impl Artifact for JITArtifact {
         &*self.serializable.data_initializers
     }
 
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        Some(&self.memory_images)
+    }
+
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
         &self.serializable.compile_info.memory_styles
     }
@@ -32,8 +32,8 @@ use wasmer_types::{
     TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMFunctionBody, VMSharedSignatureIndex,
-    VMTrampoline,
+    FunctionBodyPtr, MemoryImages, MemoryStyle, ModuleInfo, TableStyle, VMFunctionBody,
+    VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -44,6 +44,7 @@ pub struct NativeArtifact {
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+    memory_images: MemoryImages,
 }
 
 fn to_compile_error(err: impl Error) -> CompileError {
@@ -353,6 +354,8 @@ impl NativeArtifact {
             finished_dynamic_function_trampolines: finished_dynamic_function_trampolines
                 .into_boxed_slice(),
             signatures: signatures.into_boxed_slice(),
+            // A cross-compiled artifact can't be instantiated.
+            memory_images: MemoryImages::default(),
         })
     }
 
@@ -447,6 +450,9 @@ impl NativeArtifact {
 
         engine_inner.add_library(lib);
 
+        let memory_images =
+            MemoryImages::new(&metadata.compile_info.module, &metadata.data_initializers);
+
         Ok(Self {
             sharedobject_path,
             metadata,
@@ -456,6 +462,7 @@ impl NativeArtifact {
             finished_dynamic_function_trampolines: finished_dynamic_function_trampolines
                 .into_boxed_slice(),
             signatures: signatures.into_boxed_slice(),
+            memory_images,
         })
     }
 
@@ -579,6 +586,10 @@ impl Artifact for NativeArtifact {
         &*self.metadata.data_initializers
     }
 
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        Some(&self.memory_images)
+    }
+
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
         &self.metadata.compile_info.memory_styles
     }
@@ -26,7 +26,8 @@ use wasmer_types::{
     TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, MemoryImages, MemoryStyle, ModuleInfo, TableStyle, VMSharedSignatureIndex,
+    VMTrampoline,
 };
 
 /// A compiled wasm module, ready to be instantiated.
@@ -40,6 +41,7 @@ pub struct ObjectFileArtifact {
     /// Length of the serialized metadata
     metadata_length: usize,
     symbol_registry: ModuleMetadataSymbolRegistry,
+    memory_images: MemoryImages,
 }
 
 #[allow(dead_code)]
@@ -277,6 +279,9 @@ impl ObjectFileArtifact {
             signatures: signatures.into_boxed_slice(),
             metadata_length,
             symbol_registry,
+            // The compiled code is in the object file, so the artifact
+            // can't be instantiated.
+            memory_images: MemoryImages::default(),
         })
     }
 
@@ -374,6 +379,8 @@ impl ObjectFileArtifact {
         }
 
         let symbol_registry = metadata.get_symbol_registry();
+        let memory_images =
+            MemoryImages::new(&metadata.compile_info.module, &metadata.data_initializers);
         Ok(Self {
             metadata,
             module_bytes: bytes.to_owned(),
@@ -385,6 +392,7 @@ impl ObjectFileArtifact {
             signatures: signatures.into_boxed_slice(),
             metadata_length: 0,
             symbol_registry,
+            memory_images,
         })
     }
 
@@ -424,6 +432,10 @@ impl Artifact for ObjectFileArtifact {
         &*self.metadata.data_initializers
     }
 
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        Some(&self.memory_images)
+    }
+
     fn memory_styles(&self) -> &PrimaryMap<MemoryIndex, MemoryStyle> {
         &self.metadata.compile_info.memory_styles
     }
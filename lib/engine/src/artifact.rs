@@ -1,5 +1,6 @@
 use crate::{
-    resolve_imports, InstantiationError, Resolver, RuntimeError, SerializeError, Tunables,
+    resolve_imports, InstantiationError, LinkError, Resolver, RuntimeError, SerializeError,
+    Tunables,
 };
 use std::any::Any;
 use std::fs;
@@ -12,7 +13,8 @@ use wasmer_types::{
     SignatureIndex, TableIndex,
 };
 use wasmer_vm::{
-    FunctionBodyPtr, InstanceAllocator, InstanceHandle, MemoryStyle, ModuleInfo, TableStyle,
+    FunctionBodyPtr, InstanceAllocator, InstanceHandle, LimitedMemory, LimitedTable, MemoryError,
+    MemoryImages, MemoryStyle, ModuleInfo, ResourceLimiter, TableStyle, VMInterrupts,
     VMSharedSignatureIndex, VMTrampoline,
 };
 
@@ -51,6 +53,19 @@ pub trait Artifact: Send + Sync + Upcastable {
     /// Returns data initializers to pass to `InstanceHandle::initialize`
     fn data_initializers(&self) -> &[OwnedDataInitializer];
 
+    /// Returns the copy-on-write images of the local memories, precomputed
+    /// from the data initializers, if this `Artifact` has any.
+    fn memory_images(&self) -> Option<&MemoryImages> {
+        None
+    }
+
+    /// Returns whether a memory image is available to initialize at least
+    /// one local memory of new instances.
+    fn has_memory_image(&self) -> bool {
+        self.memory_images()
+            .map_or(false, |memory_images| !memory_images.is_empty())
+    }
+
     /// Returns the functions allocated in memory or this `Artifact`
     /// ready to be run.
     fn finished_functions(&self) -> &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>;
@@ -81,7 +96,11 @@ pub trait Artifact: Send + Sync + Upcastable {
         Ok(())
     }
 
-    /// Crate an `Instance` from this `Artifact`.
+    /// Crate an `Instance` from this `Artifact`, whose compiled code checks
+    /// `interrupts` on function entry and loop headers.
+    ///
+    /// The growth of the local memories and tables of the instance is
+    /// limited by `limiter`, if any.
     ///
     /// # Safety
     ///
@@ -91,6 +110,8 @@ pub trait Artifact: Send + Sync + Upcastable {
         tunables: &dyn Tunables,
         resolver: &dyn Resolver,
         host_state: Box<dyn Any>,
+        interrupts: Arc<VMInterrupts>,
+        limiter: Option<&Arc<dyn ResourceLimiter>>,
     ) -> Result<InstanceHandle, InstantiationError> {
         self.preinstantiate()?;
 
@@ -116,15 +137,50 @@ pub trait Artifact: Send + Sync + Upcastable {
         // Get pointers to where metadata about local tables should live in VM memory.
 
         let (allocator, memory_definition_locations, table_definition_locations) =
-            InstanceAllocator::new(&*module);
-        let finished_memories = tunables
+            match tunables.instance_pool() {
+                Some(pool) => InstanceAllocator::new_in_pool(&*module, pool).map_err(|e| {
+                    InstantiationError::Link(LinkError::Resource(format!(
+                        "Failed to allocate the instance: {}",
+                        e
+                    )))
+                })?,
+                None => InstanceAllocator::new(&*module),
+            };
+        // The limiter decides whether the memories and tables can be
+        // created before they are allocated.
+        if let Some(limiter) = limiter {
+            check_resources_creation(limiter.as_ref(), &module)?;
+        }
+        let mut finished_memories = tunables
             .create_memories(&module, self.memory_styles(), &memory_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
-        let finished_tables = tunables
+            .map_err(|error| {
+                if let Some(limiter) = limiter {
+                    let memory_error = MemoryError::Generic(error.to_string());
+                    memories_creation_failed(limiter.as_ref(), &module, &memory_error);
+                    tables_creation_failed(limiter.as_ref(), &module);
+                }
+                InstantiationError::Link(error)
+            })?;
+        if let Some(limiter) = limiter {
+            for memory in finished_memories.values_mut() {
+                *memory = Arc::new(LimitedMemory::new(memory.clone(), limiter.clone()));
+            }
+        }
+        let mut finished_tables = tunables
             .create_tables(&module, self.table_styles(), &table_definition_locations)
-            .map_err(InstantiationError::Link)?
-            .into_boxed_slice();
+            .map_err(|error| {
+                if let Some(limiter) = limiter {
+                    tables_creation_failed(limiter.as_ref(), &module);
+                }
+                InstantiationError::Link(error)
+            })?;
+        if let Some(limiter) = limiter {
+            for table in finished_tables.values_mut() {
+                *table = Arc::new(LimitedTable::new(table.clone(), limiter.clone()));
+            }
+        }
+        let finished_memories = finished_memories.into_boxed_slice();
+        let finished_tables = finished_tables.into_boxed_slice();
         let finished_globals = tunables
             .create_globals(&module)
             .map_err(InstantiationError::Link)?
@@ -144,18 +200,22 @@ pub trait Artifact: Send + Sync + Upcastable {
             self.signatures().clone(),
             host_state,
             import_function_envs,
+            interrupts,
         )
         .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))?;
         Ok(handle)
     }
 
-    /// Finishes the instantiation of a just created `InstanceHandle`.
+    /// Finishes the instantiation of a just created `InstanceHandle`,
+    /// initializing its memories from the memory images of this `Artifact`
+    /// unless `tunables` opt out of them.
     ///
     /// # Safety
     ///
     /// See [`InstanceHandle::finish_instantiation`].
     unsafe fn finish_instantiation(
         &self,
+        tunables: &dyn Tunables,
         handle: &InstanceHandle,
     ) -> Result<(), InstantiationError> {
         let data_initializers = self
@@ -166,12 +226,68 @@ pub trait Artifact: Send + Sync + Upcastable {
                 data: &*init.data,
             })
             .collect::<Vec<_>>();
+        let memory_images = if tunables.use_memory_images() {
+            self.memory_images()
+        } else {
+            None
+        };
         handle
-            .finish_instantiation(&data_initializers)
+            .finish_instantiation(&data_initializers, memory_images)
             .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))
     }
 }
 
+/// Asks `limiter` whether the local memories and tables of `module` can be
+/// created. If one of them can't, the limiter is told that the ones it
+/// allowed failed to be created.
+fn check_resources_creation(
+    limiter: &dyn ResourceLimiter,
+    module: &ModuleInfo,
+) -> Result<(), InstantiationError> {
+    let memories = module.memories.values().skip(module.num_imported_memories);
+    let tables = module.tables.values().skip(module.num_imported_tables);
+    for (allowed, ty) in memories.clone().enumerate() {
+        if let Err(error) = LimitedMemory::check_creation(limiter, ty) {
+            for _ in 0..allowed {
+                limiter.memory_grow_failed(&error);
+            }
+            return Err(InstantiationError::Link(LinkError::Resource(format!(
+                "Failed to create memory: {}",
+                error
+            ))));
+        }
+    }
+    for (allowed, ty) in tables.enumerate() {
+        if let Err(error) = LimitedTable::check_creation(limiter, ty) {
+            memories_creation_failed(limiter, module, &MemoryError::Generic(error.clone()));
+            for _ in 0..allowed {
+                limiter.table_grow_failed();
+            }
+            return Err(InstantiationError::Link(LinkError::Resource(error)));
+        }
+    }
+    Ok(())
+}
+
+/// Tells `limiter` that the local memories of `module` failed to be
+/// created with `error`.
+fn memories_creation_failed(
+    limiter: &dyn ResourceLimiter,
+    module: &ModuleInfo,
+    error: &MemoryError,
+) {
+    for _ in module.num_imported_memories..module.memories.len() {
+        limiter.memory_grow_failed(error);
+    }
+}
+
+/// Tells `limiter` that the local tables of `module` failed to be created.
+fn tables_creation_failed(limiter: &dyn ResourceLimiter, module: &ModuleInfo) {
+    for _ in module.num_imported_tables..module.tables.len() {
+        limiter.table_grow_failed();
+    }
+}
+
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
 /// Trait needed to get downcasting from `WasiFile` to work.
 pub trait Upcastable {
@@ -1,9 +1,11 @@
 use wasmer_vm::{
     ImportInitializerFuncPtr, VMExport, VMExportFunction, VMExportGlobal, VMExportMemory,
-    VMExportTable,
+    VMExportTable, VMExportTag,
 };
 
+use std::any::Any;
 use std::sync::Arc;
+use wasmer_types::{InstanceType, ModuleType};
 
 /// The value of an export passed from one instance to another.
 #[derive(Debug, Clone)]
@@ -19,6 +21,15 @@ pub enum Export {
 
     /// A global export value.
     Global(ExportGlobal),
+
+    /// An exception tag export value.
+    Tag(ExportTag),
+
+    /// An instance export value.
+    Instance(ExportInstance),
+
+    /// A module export value.
+    Module(ExportModule),
 }
 
 impl From<Export> for VMExport {
@@ -28,6 +39,10 @@ impl From<Export> for VMExport {
             Export::Memory(ExportMemory { vm_memory }) => Self::Memory(vm_memory),
             Export::Table(ExportTable { vm_table }) => Self::Table(vm_table),
             Export::Global(ExportGlobal { vm_global }) => Self::Global(vm_global),
+            Export::Tag(ExportTag { vm_tag }) => Self::Tag(vm_tag),
+            Export::Instance(_) | Export::Module(_) => {
+                panic!("modules and instances are not exported by the VM")
+            }
         }
     }
 }
@@ -42,6 +57,7 @@ impl From<VMExport> for Export {
             VMExport::Memory(vm_memory) => Self::Memory(ExportMemory { vm_memory }),
             VMExport::Table(vm_table) => Self::Table(ExportTable { vm_table }),
             VMExport::Global(vm_global) => Self::Global(ExportGlobal { vm_global }),
+            VMExport::Tag(vm_tag) => Self::Tag(ExportTag { vm_tag }),
         }
     }
 }
@@ -186,3 +202,52 @@ impl From<ExportGlobal> for Export {
         Self::Global(global)
     }
 }
+
+/// An exception tag export value.
+#[derive(Debug, Clone)]
+pub struct ExportTag {
+    /// The VM tag, containing info about the tag.
+    pub vm_tag: VMExportTag,
+}
+
+impl From<ExportTag> for Export {
+    fn from(tag: ExportTag) -> Self {
+        Self::Tag(tag)
+    }
+}
+
+/// An instance export value, for the module linking proposal.
+///
+/// Instances are created by the embedder rather than by the VM, so the
+/// engine only keeps their type along with an opaque handle to them.
+#[derive(Debug, Clone)]
+pub struct ExportInstance {
+    /// The type of the instance.
+    pub ty: InstanceType,
+    /// The embedder's representation of the instance.
+    pub handle: Arc<dyn Any + Send + Sync>,
+}
+
+impl From<ExportInstance> for Export {
+    fn from(instance: ExportInstance) -> Self {
+        Self::Instance(instance)
+    }
+}
+
+/// A module export value, for the module linking proposal.
+///
+/// Like instances, modules are kept as an opaque handle to the embedder's
+/// representation of them.
+#[derive(Debug, Clone)]
+pub struct ExportModule {
+    /// The type of the module.
+    pub ty: ModuleType,
+    /// The embedder's representation of the module.
+    pub handle: Arc<dyn Any + Send + Sync>,
+}
+
+impl From<ExportModule> for Export {
+    fn from(module: ExportModule) -> Self {
+        Self::Module(module)
+    }
+}
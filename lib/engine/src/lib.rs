@@ -35,7 +35,8 @@ pub use crate::error::{
     DeserializeError, ImportError, InstantiationError, LinkError, SerializeError,
 };
 pub use crate::export::{
-    Export, ExportFunction, ExportFunctionMetadata, ExportGlobal, ExportMemory, ExportTable,
+    Export, ExportFunction, ExportFunctionMetadata, ExportGlobal, ExportInstance, ExportMemory,
+    ExportModule, ExportTable, ExportTag,
 };
 pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
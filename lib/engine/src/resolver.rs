@@ -7,9 +7,9 @@ use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{ExternType, FunctionIndex, ImportIndex, MemoryIndex, TableIndex};
 
 use wasmer_vm::{
-    FunctionBodyPtr, ImportFunctionEnv, Imports, MemoryStyle, ModuleInfo, TableStyle,
-    VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalImport,
-    VMMemoryImport, VMTableImport,
+    FunctionBodyPtr, ImportFunctionEnv, Imports, MemoryStyle, ModuleInfo, ModuleInitializer,
+    TableStyle, VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind,
+    VMGlobalImport, VMMemoryImport, VMTableImport,
 };
 
 /// Import resolver connects imports with available exported values.
@@ -98,6 +98,9 @@ fn get_extern_from_import(module: &ModuleInfo, import_index: &ImportIndex) -> Ex
             let global = module.globals[*index];
             ExternType::Global(global)
         }
+        ImportIndex::Tag(index) => ExternType::Tag(module.tag_type(*index)),
+        ImportIndex::Module(index) => ExternType::Module(module.modules[*index].clone()),
+        ImportIndex::Instance(index) => ExternType::Instance(module.instances[*index].clone()),
     }
 }
 
@@ -111,6 +114,9 @@ fn get_extern_from_export(_module: &ModuleInfo, export: &Export) -> ExternType {
             let global = g.vm_global.from.ty();
             ExternType::Global(*global)
         }
+        Export::Tag(ref t) => ExternType::Tag(t.vm_tag.ty().clone()),
+        Export::Instance(ref i) => ExternType::Instance(i.ty.clone()),
+        Export::Module(ref m) => ExternType::Module(m.ty.clone()),
     }
 }
 
@@ -131,9 +137,48 @@ pub fn resolve_imports(
     let mut table_imports = PrimaryMap::with_capacity(module.num_imported_tables);
     let mut memory_imports = PrimaryMap::with_capacity(module.num_imported_memories);
     let mut global_imports = PrimaryMap::with_capacity(module.num_imported_globals);
+    let mut tag_imports = PrimaryMap::with_capacity(module.num_imported_tags);
 
-    for ((module_name, field, import_idx), import_index) in module.imports.iter() {
-        let resolved = resolver.resolve(*import_idx, module_name, field);
+    // Imported modules and instances are handled by the embedder. On the
+    // other hand, the functions, tables, memories, globals and tags aliased
+    // from instances are imports as far as the VM is concerned, so they are
+    // resolved along with the regular imports, in the order of their indices.
+    let mut core_imports = module
+        .imports
+        .iter()
+        .map(|((module_name, field, import_idx), import_index)| {
+            (
+                *import_idx,
+                module_name.as_str(),
+                field.as_str(),
+                import_index,
+            )
+        })
+        .chain(
+            module
+                .initializers
+                .iter()
+                .filter_map(|initializer| match initializer {
+                    ModuleInitializer::AliasInstanceExport {
+                        export,
+                        index,
+                        import_idx,
+                        ..
+                    } => Some((*import_idx, "", export.as_str(), index)),
+                    _ => None,
+                }),
+        )
+        .filter(|(_, _, _, import_index)| {
+            !matches!(
+                import_index,
+                ImportIndex::Module(_) | ImportIndex::Instance(_)
+            )
+        })
+        .collect::<Vec<_>>();
+    core_imports.sort_by_key(|(_, _, _, import_index)| *import_index);
+
+    for (import_idx, module_name, field, import_index) in core_imports {
+        let resolved = resolver.resolve(import_idx, module_name, field);
         let import_extern = get_extern_from_import(module, import_index);
         let resolved = match resolved {
             None => {
@@ -268,6 +313,16 @@ pub fn resolve_imports(
                     from: g.vm_global.from.clone(),
                 });
             }
+
+            Export::Tag(ref t) => {
+                tag_imports.push(t.vm_tag.from.clone());
+            }
+
+            Export::Instance(_) | Export::Module(_) => {
+                // This should never be reached, as we filtered out the
+                // module and instance imports before
+                panic!("Module or instance resolved as a core import");
+            }
         }
     }
 
@@ -277,6 +332,7 @@ pub fn resolve_imports(
         table_imports,
         memory_imports,
         global_imports,
+        tag_imports,
     ))
 }
 
@@ -8,7 +8,7 @@ use wasmer_types::{
     TableIndex, TableType,
 };
 use wasmer_vm::MemoryError;
-use wasmer_vm::{Global, Memory, ModuleInfo, Table};
+use wasmer_vm::{Global, InstancePool, Memory, ModuleInfo, Table};
 use wasmer_vm::{MemoryStyle, TableStyle};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -57,6 +57,32 @@ pub trait Tunables: MemoryUsage {
         vm_definition_location: NonNull<VMTableDefinition>,
     ) -> Result<Arc<dyn Table>, String>;
 
+    /// The maximum number of bytes of stack WebAssembly code may use
+    /// when called from the host, after which it traps with
+    /// `TrapCode::StackOverflow`.
+    ///
+    /// `None` leaves it to the size of the native stack.
+    fn max_wasm_stack(&self) -> Option<usize> {
+        None
+    }
+
+    /// The pool to allocate instances from, or `None` to allocate each
+    /// of them separately.
+    ///
+    /// The memories and tables of the instances are created by
+    /// [`Tunables::create_vm_memory`] and [`Tunables::create_vm_table`],
+    /// which should take them from the pool too.
+    fn instance_pool(&self) -> Option<&Arc<InstancePool>> {
+        None
+    }
+
+    /// Whether new instances initialize their memories by mapping the
+    /// copy-on-write memory images of their artifact, when it has some,
+    /// rather than by copying their data initializers.
+    fn use_memory_images(&self) -> bool {
+        true
+    }
+
     /// Create a global with an unset value.
     fn create_global(&self, ty: GlobalType) -> Result<Arc<Global>, String> {
         Ok(Arc::new(Global::new(ty)))
@@ -76,7 +102,7 @@ pub trait Tunables: MemoryUsage {
             let mi = MemoryIndex::new(index);
             let ty = &module.memories[mi];
             let style = &memory_styles[mi];
-            let mdl = memory_definition_locations[index];
+            let mdl = memory_definition_locations[index - num_imports];
             memories.push(
                 self.create_vm_memory(ty, style, mdl)
                     .map_err(|e| LinkError::Resource(format!("Failed to create memory: {}", e)))?,
@@ -99,7 +125,7 @@ pub trait Tunables: MemoryUsage {
             let ti = TableIndex::new(index);
             let ty = &module.tables[ti];
             let style = &table_styles[ti];
-            let tdl = table_definition_locations[index];
+            let tdl = table_definition_locations[index - num_imports];
             tables.push(
                 self.create_vm_table(ty, style, tdl)
                     .map_err(LinkError::Resource)?,
@@ -1,4 +1,5 @@
-//! Runtime build script compiles C code using setjmp for trap handling.
+//! Runtime build script compiles C code using setjmp for trap handling,
+//! and ucontext for fibers.
 
 fn main() {
     println!("cargo:rerun-if-changed=src/trap/helpers.c");
@@ -5,9 +5,10 @@ use crate::global::Global;
 use crate::instance::InstanceRef;
 use crate::memory::{Memory, MemoryStyle};
 use crate::table::{Table, TableStyle};
+use crate::tag::Tag;
 use crate::vmcontext::{VMFunctionBody, VMFunctionEnvironment, VMFunctionKind, VMTrampoline};
 use std::sync::Arc;
-use wasmer_types::{FunctionType, MemoryType, TableType};
+use wasmer_types::{FunctionType, MemoryType, TableType, TagType};
 
 /// The value of an export passed from one instance to another.
 #[derive(Debug)]
@@ -23,6 +24,9 @@ pub enum VMExport {
 
     /// A global export value.
     Global(VMExportGlobal),
+
+    /// An exception tag export value.
+    Tag(VMExportTag),
 }
 
 /// A function export value.
@@ -194,3 +198,41 @@ impl From<VMExportGlobal> for VMExport {
         Self::Global(global)
     }
 }
+
+/// An exception tag export value.
+#[derive(Debug, Clone)]
+pub struct VMExportTag {
+    /// Pointer to the containing `Tag`.
+    pub from: Arc<Tag>,
+
+    /// A “reference” to the instance through the
+    /// `InstanceRef`. `None` if it is a host tag.
+    pub instance_ref: Option<InstanceRef>,
+}
+
+/// # Safety
+/// This is correct because there is no non-threadsafe logic directly in this type,
+/// and tags are immutable.
+unsafe impl Send for VMExportTag {}
+
+/// # Safety
+/// This is correct because tags are immutable and the type is both `Send` and `Clone`.
+unsafe impl Sync for VMExportTag {}
+
+impl VMExportTag {
+    /// Get the type for this exported tag
+    pub fn ty(&self) -> &TagType {
+        self.from.ty()
+    }
+
+    /// Returns whether or not the two `VMExportTag`s refer to the same Tag.
+    pub fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.from, &other.from)
+    }
+}
+
+impl From<VMExportTag> for VMExport {
+    fn from(tag: VMExportTag) -> Self {
+        Self::Tag(tag)
+    }
+}
@@ -0,0 +1,395 @@
+//! Fibers: functions running on their own, separately allocated stack,
+//! which can suspend themselves and be resumed later, possibly from
+//! another thread.
+//!
+//! Fibers are what allow WebAssembly code to call asynchronous host
+//! functions: the WebAssembly code runs on a fiber, and when a host
+//! future is not ready yet the fiber is suspended, giving control back
+//! to the async executor polling it.
+
+use crate::mmap::Mmap;
+use crate::trap::traphandlers::{tls, CallThreadState};
+use crate::trap::Trap;
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::task::Poll;
+use thiserror::Error;
+
+/// The default size of a fiber stack, not including its guard page.
+pub const DEFAULT_FIBER_STACK_SIZE: usize = 2 * 1024 * 1024;
+
+cfg_if::cfg_if! {
+    if #[cfg(any(
+        target_os = "macos",
+        target_os = "freebsd",
+        all(target_os = "linux", target_env = "gnu")
+    ))] {
+        use std::os::raw::{c_int, c_void};
+
+        // Implemented with the ucontext routines in `helpers.c`.
+        extern "C" {
+            fn WasmerFiberSize() -> usize;
+            fn WasmerFiberInit(
+                storage: *mut c_void,
+                stack: *mut c_void,
+                stack_size: usize,
+                entry: extern "C" fn(*mut c_void),
+                payload: *mut c_void,
+            ) -> c_int;
+            fn WasmerFiberResume(storage: *mut c_void) -> c_int;
+            fn WasmerFiberSuspend(storage: *mut c_void) -> c_int;
+        }
+
+        /// Whether fibers are supported on the current platform.
+        pub const FIBERS_SUPPORTED: bool = true;
+    } else {
+        use std::os::raw::{c_int, c_void};
+
+        // Fibers are never created on this platform, so these are never
+        // called.
+        #[allow(non_snake_case)]
+        unsafe fn WasmerFiberSize() -> usize {
+            0
+        }
+        #[allow(non_snake_case)]
+        unsafe fn WasmerFiberInit(
+            _storage: *mut c_void,
+            _stack: *mut c_void,
+            _stack_size: usize,
+            _entry: extern "C" fn(*mut c_void),
+            _payload: *mut c_void,
+        ) -> c_int {
+            -1
+        }
+        #[allow(non_snake_case)]
+        unsafe fn WasmerFiberResume(_storage: *mut c_void) -> c_int {
+            -1
+        }
+        #[allow(non_snake_case)]
+        unsafe fn WasmerFiberSuspend(_storage: *mut c_void) -> c_int {
+            -1
+        }
+
+        /// Whether fibers are supported on the current platform.
+        pub const FIBERS_SUPPORTED: bool = false;
+    }
+}
+
+/// An error returned when suspending the current fiber.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FiberError {
+    /// The caller is not running on a fiber.
+    #[error("not running on a fiber")]
+    NotOnFiber,
+    /// The fiber has been dropped before completing, and must unwind.
+    #[error("the fiber has been cancelled")]
+    Cancelled,
+}
+
+/// A stack for a fiber, with a guard page below it so that overflowing
+/// it faults instead of corrupting memory.
+pub struct FiberStack {
+    mmap: Mmap,
+}
+
+impl FiberStack {
+    /// Allocates a new stack of at least `size` bytes.
+    pub fn new(size: usize) -> Result<Self, String> {
+        let page_size = region::page::size();
+        let size = (size + (page_size - 1)) & !(page_size - 1);
+        let mmap = Mmap::with_at_least(page_size + size)?;
+        unsafe { region::protect(mmap.as_ptr(), page_size, region::Protection::NONE) }
+            .map_err(|e| e.to_string())?;
+        Ok(Self { mmap })
+    }
+
+    /// The range of addresses of the guard page.
+    pub fn guard_range(&self) -> (usize, usize) {
+        let start = self.mmap.as_ptr() as usize;
+        (start, start + region::page::size())
+    }
+
+    /// The lowest usable address of the stack, and its size.
+    fn usable(&mut self) -> (*mut u8, usize) {
+        let page_size = region::page::size();
+        unsafe {
+            (
+                self.mmap.as_mut_ptr().add(page_size),
+                self.mmap.len() - page_size,
+            )
+        }
+    }
+}
+
+impl fmt::Debug for FiberStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FiberStack")
+            .field("guard_range", &self.guard_range())
+            .field("len", &self.mmap.len())
+            .finish()
+    }
+}
+
+/// The state of a fiber which doesn't depend on the type of its result,
+/// reachable through `CURRENT` while the fiber runs.
+struct FiberContext {
+    /// The platform context of the fiber. `u128` gives the alignment
+    /// `ucontext_t` needs.
+    storage: Box<[u128]>,
+    guard_range: (usize, usize),
+    /// The trap handling state of the calls running on the fiber, saved
+    /// while it is suspended.
+    call_state: Cell<*const CallThreadState>,
+    cancelled: Cell<bool>,
+}
+
+impl FiberContext {
+    fn storage_ptr(&self) -> *mut c_void {
+        self.storage.as_ptr() as *mut c_void
+    }
+}
+
+thread_local!(static CURRENT: Cell<*const FiberContext> = Cell::new(ptr::null()));
+
+// The thread-local accesses below are never inlined: a fiber may be
+// suspended on a thread and resumed on another one, and the address of
+// a thread-local must not be reused across the switch.
+#[inline(never)]
+fn replace_current(context: *const FiberContext) -> *const FiberContext {
+    CURRENT.with(|current| current.replace(context))
+}
+
+#[inline(never)]
+fn current() -> *const FiberContext {
+    CURRENT.with(|current| current.get())
+}
+
+struct FiberInner<R> {
+    context: FiberContext,
+    stack: FiberStack,
+    func: Option<Box<dyn FnOnce() -> R + Send>>,
+    result: Option<Result<R, Box<dyn Any + Send>>>,
+    done: bool,
+}
+
+/// A function running on its own stack, which can suspend itself with
+/// [`suspend_current_fiber`].
+pub struct Fiber<R> {
+    inner: Box<FiberInner<R>>,
+}
+
+// The function and its result are `Send`, and a suspended fiber only holds
+// its own stack.
+unsafe impl<R: Send> Send for Fiber<R> {}
+
+impl<R> Fiber<R> {
+    /// Creates a new fiber, running `func` on a stack of `stack_size`
+    /// bytes when it is first resumed.
+    pub fn new<F>(stack_size: usize, func: F) -> Result<Self, String>
+    where
+        F: FnOnce() -> R + Send + 'static,
+    {
+        if !FIBERS_SUPPORTED {
+            return Err("fibers are not supported on this platform".to_string());
+        }
+        let stack = FiberStack::new(stack_size)?;
+        let storage_len = unsafe { WasmerFiberSize() };
+        let storage_len = (storage_len + 15) / 16;
+        let mut inner = Box::new(FiberInner {
+            context: FiberContext {
+                storage: vec![0u128; storage_len].into_boxed_slice(),
+                guard_range: stack.guard_range(),
+                call_state: Cell::new(ptr::null()),
+                cancelled: Cell::new(false),
+            },
+            stack,
+            func: Some(Box::new(func)),
+            result: None,
+            done: false,
+        });
+        let (stack_ptr, stack_len) = inner.stack.usable();
+        let payload = &mut *inner as *mut FiberInner<R> as *mut c_void;
+        let r = unsafe {
+            WasmerFiberInit(
+                inner.context.storage_ptr(),
+                stack_ptr as *mut c_void,
+                stack_len,
+                fiber_entry::<R>,
+                payload,
+            )
+        };
+        if r != 0 {
+            return Err(format!(
+                "failed to initialize the fiber: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(Self { inner })
+    }
+
+    /// Whether the fiber ran to completion.
+    pub fn is_done(&self) -> bool {
+        self.inner.done
+    }
+
+    /// Runs the fiber until it suspends itself, returning `Poll::Pending`,
+    /// or until it completes, returning `Poll::Ready` with its result.
+    ///
+    /// If the fiber panics, the panic is resumed on the caller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fiber is already done.
+    pub fn resume(&mut self) -> Result<Poll<R>, Trap> {
+        assert!(!self.is_done(), "cannot resume a finished fiber");
+        // Traps on the fiber are handled on the sigaltstack of the thread
+        // resuming it.
+        #[cfg(unix)]
+        crate::trap::traphandlers::setup_unix_sigaltstack()?;
+
+        self.switch();
+        match self.inner.result.take() {
+            None => Ok(Poll::Pending),
+            Some(Ok(result)) => Ok(Poll::Ready(result)),
+            Some(Err(panic)) => panic::resume_unwind(panic),
+        }
+    }
+
+    /// Switches to the fiber until it suspends or completes.
+    fn switch(&mut self) {
+        let inner: *mut FiberInner<R> = &mut *self.inner;
+        unsafe {
+            let context = &(*inner).context;
+            let prev_fiber = replace_current(context);
+            let prev_state = tls::replace(context.call_state.get());
+            let r = WasmerFiberResume(context.storage_ptr());
+            context.call_state.set(tls::replace(prev_state));
+            replace_current(prev_fiber);
+            assert_eq!(r, 0, "failed to switch to the fiber");
+        }
+    }
+}
+
+impl<R> Drop for Fiber<R> {
+    fn drop(&mut self) {
+        // A fiber which never ran has nothing to unwind.
+        if self.inner.func.is_some() || self.is_done() {
+            return;
+        }
+        // Otherwise let it unwind its stack: every suspension point now
+        // returns `FiberError::Cancelled`.
+        self.inner.context.cancelled.set(true);
+        while !self.is_done() {
+            self.switch();
+        }
+    }
+}
+
+impl<R> fmt::Debug for Fiber<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Fiber")
+            .field("stack", &self.inner.stack)
+            .field("done", &self.inner.done)
+            .finish()
+    }
+}
+
+extern "C" fn fiber_entry<R>(payload: *mut c_void) {
+    unsafe {
+        let inner = payload as *mut FiberInner<R>;
+        let func = (*inner).func.take().unwrap();
+        // Unwinding must not cross the fiber boundary: the panic is
+        // carried over to the caller of `resume` instead.
+        let result = panic::catch_unwind(AssertUnwindSafe(func));
+        (*inner).result = Some(result);
+        (*inner).done = true;
+    }
+}
+
+/// Suspends the fiber the caller is running on, returning to the caller
+/// of [`Fiber::resume`]. Returns once the fiber is resumed again.
+///
+/// Returns `FiberError::Cancelled` if the fiber is being dropped: the
+/// caller must then unwind back to the start of the fiber as soon as
+/// possible.
+pub fn suspend_current_fiber() -> Result<(), FiberError> {
+    let context = current();
+    if context.is_null() {
+        return Err(FiberError::NotOnFiber);
+    }
+    unsafe {
+        if (*context).cancelled.get() {
+            return Err(FiberError::Cancelled);
+        }
+        let r = WasmerFiberSuspend((*context).storage_ptr());
+        assert_eq!(r, 0, "failed to suspend the fiber");
+        if (*context).cancelled.get() {
+            return Err(FiberError::Cancelled);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `addr` is in the guard page of the fiber running on the
+/// current thread, meaning that the fiber overflowed its stack.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn is_fiber_guard_page(addr: usize) -> bool {
+    let context = current();
+    if context.is_null() {
+        return false;
+    }
+    let (start, end) = unsafe { (*context).guard_range };
+    start <= addr && addr < end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn fiber_suspend_and_resume() {
+        if !FIBERS_SUPPORTED {
+            return;
+        }
+        let mut fiber = Fiber::new(DEFAULT_FIBER_STACK_SIZE, || {
+            let mut count = 0;
+            while count < 3 {
+                suspend_current_fiber().unwrap();
+                count += 1;
+            }
+            count
+        })
+        .unwrap();
+        for _ in 0..3 {
+            assert_eq!(fiber.resume().unwrap(), Poll::Pending);
+        }
+        assert_eq!(fiber.resume().unwrap(), Poll::Ready(3));
+        assert!(fiber.is_done());
+        assert_eq!(suspend_current_fiber(), Err(FiberError::NotOnFiber));
+    }
+
+    #[test]
+    fn fiber_cancelled_on_drop() {
+        if !FIBERS_SUPPORTED {
+            return;
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut fiber = Fiber::new(DEFAULT_FIBER_STACK_SIZE, {
+            let cancelled = cancelled.clone();
+            move || {
+                while suspend_current_fiber().is_ok() {}
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        })
+        .unwrap();
+        assert_eq!(fiber.resume().unwrap(), Poll::Pending);
+        assert_eq!(fiber.resume().unwrap(), Poll::Pending);
+        drop(fiber);
+        assert!(cancelled.load(Ordering::SeqCst));
+    }
+}
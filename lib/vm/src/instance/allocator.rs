@@ -1,10 +1,12 @@
 use super::{Instance, InstanceRef};
+use crate::pool::InstancePool;
 use crate::vmcontext::{VMMemoryDefinition, VMTableDefinition};
 use crate::{ModuleInfo, VMOffsets};
 use std::alloc::{self, Layout};
 use std::convert::TryFrom;
 use std::mem;
 use std::ptr::{self, NonNull};
+use std::sync::Arc;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{LocalMemoryIndex, LocalTableIndex};
 
@@ -33,6 +35,9 @@ pub struct InstanceAllocator {
     /// The layout of the `instance_ptr` buffer.
     instance_layout: Layout,
 
+    /// Where the `instance_ptr` buffer comes from.
+    storage: InstanceStorage,
+
     /// Information about the offsets into the `instance_ptr` buffer for
     /// the dynamic fields.
     offsets: VMOffsets,
@@ -48,15 +53,39 @@ impl Drop for InstanceAllocator {
         if !self.consumed {
             // If `consumed` has not been set, then we still have ownership
             // over the buffer and must free it.
-            let instance_ptr = self.instance_ptr.as_ptr();
-
             unsafe {
-                std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+                self.storage
+                    .free(self.instance_ptr.cast(), self.instance_layout);
             }
         }
     }
 }
 
+/// Where the buffer of an [`Instance`] comes from.
+#[derive(Debug, Clone)]
+pub(crate) enum InstanceStorage {
+    /// The buffer was allocated with the global allocator.
+    Heap,
+
+    /// The buffer is the slot of the given index in an instance pool.
+    Pool(Arc<InstancePool>, usize),
+}
+
+impl InstanceStorage {
+    /// Frees the buffer at `ptr`, of the given `layout`.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must come from `self`, and any `Instance` in it must
+    /// have been dropped.
+    pub(crate) unsafe fn free(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self {
+            Self::Heap => alloc::dealloc(ptr.as_ptr(), layout),
+            Self::Pool(pool, index) => pool.give_back_instance(*index, layout),
+        }
+    }
+}
+
 impl InstanceAllocator {
     /// Allocates instance data for use with [`InstanceHandle::new`].
     ///
@@ -86,9 +115,56 @@ impl InstanceAllocator {
             alloc::handle_alloc_error(instance_layout);
         };
 
+        Self::with_storage(
+            instance_ptr,
+            instance_layout,
+            InstanceStorage::Heap,
+            offsets,
+        )
+    }
+
+    /// Allocates instance data in a slot of `pool`, like
+    /// [`InstanceAllocator::new`] does with the global allocator.
+    ///
+    /// Returns an error if the pool has no free instance slot, or if
+    /// instances of `module` don't fit in its slots.
+    pub fn new_in_pool(
+        module: &ModuleInfo,
+        pool: &Arc<InstancePool>,
+    ) -> Result<
+        (
+            Self,
+            Vec<NonNull<VMMemoryDefinition>>,
+            Vec<NonNull<VMTableDefinition>>,
+        ),
+        String,
+    > {
+        let offsets = VMOffsets::new(mem::size_of::<usize>() as u8, module);
+        let instance_layout = Self::instance_layout(&offsets);
+        let (index, instance_ptr) = pool.take_instance(instance_layout)?;
+        let storage = InstanceStorage::Pool(pool.clone(), index);
+        Ok(Self::with_storage(
+            instance_ptr.cast(),
+            instance_layout,
+            storage,
+            offsets,
+        ))
+    }
+
+    fn with_storage(
+        instance_ptr: NonNull<Instance>,
+        instance_layout: Layout,
+        storage: InstanceStorage,
+        offsets: VMOffsets,
+    ) -> (
+        Self,
+        Vec<NonNull<VMMemoryDefinition>>,
+        Vec<NonNull<VMTableDefinition>>,
+    ) {
         let allocator = Self {
             instance_ptr,
             instance_layout,
+            storage,
             offsets,
             consumed: false,
         };
@@ -201,10 +277,11 @@ impl InstanceAllocator {
         }
         let instance = self.instance_ptr;
         let instance_layout = self.instance_layout;
+        let storage = self.storage.clone();
 
         // This is correct because of the invariants of `Self` and
         // because we write `Instance` to the pointer in this function.
-        unsafe { InstanceRef::new(instance, instance_layout) }
+        unsafe { InstanceRef::new(instance, instance_layout, storage) }
     }
 
     /// Get the [`VMOffsets`] for the allocated buffer.
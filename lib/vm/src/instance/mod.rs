@@ -9,39 +9,46 @@
 
 mod allocator;
 mod r#ref;
+mod snapshot;
 
 pub use allocator::InstanceAllocator;
 pub use r#ref::InstanceRef;
+pub use snapshot::{InstanceSnapshot, SnapshotError};
 
 use crate::export::VMExport;
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
+use crate::memory_image::MemoryImages;
+use crate::parking_spot::ParkingSpot;
 use crate::table::Table;
+use crate::tag::{Tag, VMException};
 use crate::trap::{catch_traps, init_traps, Trap, TrapCode};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
     VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport,
-    VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport,
-    VMTrampoline,
+    VMInterrupts, VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition,
+    VMTableImport, VMTrampoline,
 };
 use crate::{FunctionBodyPtr, ModuleInfo, VMOffsets};
-use crate::{VMExportFunction, VMExportGlobal, VMExportMemory, VMExportTable};
+use crate::{VMExportFunction, VMExportGlobal, VMExportMemory, VMExportTable, VMExportTag};
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::any::Any;
 use std::cell::{Cell, RefCell};
-use std::convert::{TryFrom, TryInto};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::ffi;
 use std::fmt;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{mem, ptr, slice};
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
     DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
     LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, Pages,
-    SignatureIndex, TableIndex, TableInitializer,
+    SignatureIndex, TableIndex, TableInitializer, TagIndex,
 };
 
 /// The function pointer to call with data and an [`Instance`] pointer to
@@ -72,6 +79,9 @@ pub(crate) struct Instance {
     /// WebAssembly global data.
     globals: BoxedSlice<LocalGlobalIndex, Arc<Global>>,
 
+    /// WebAssembly exception tags, imported and local.
+    tags: BoxedSlice<TagIndex, Arc<Tag>>,
+
     /// Pointers to functions in executable memory.
     functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
 
@@ -86,9 +96,21 @@ pub(crate) struct Instance {
     /// get removed. A missing entry is considered equivalent to an empty slice.
     passive_data: RefCell<PrimaryMap<DataIndex, Option<Arc<[u8]>>>>,
 
+    /// The functions referenced by `funcref` values in compiled code, which
+    /// are pointers to these boxed entries, keyed by function body and
+    /// environment. Entries are never removed, so that the pointers stay valid
+    /// as long as the instance lives.
+    funcrefs: RefCell<HashMap<(usize, usize), Box<VMCallerCheckedAnyfunc>>>,
+
     /// Hosts can store arbitrary per-instance information here.
     host_state: Box<dyn Any>,
 
+    /// The interrupt flags checked by the compiled code of this instance.
+    interrupts: Arc<VMInterrupts>,
+
+    /// Handler run when the epoch deadline of the instance is reached.
+    epoch_deadline_callback: Cell<Option<Box<EpochDeadlineCallback>>>,
+
     /// Handler run when `SIGBUS`, `SIGFPE`, `SIGILL`, or `SIGSEGV` are caught by the instance thread.
     pub(crate) signal_handler: Cell<Option<Box<SignalHandler>>>,
 
@@ -368,6 +390,51 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
     }
 
+    /// Return a pointer to the pointer to the `VMInterrupts`.
+    fn interrupts_ptr(&self) -> *mut *const VMInterrupts {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_interrupts()) }
+    }
+
+    /// Return the interrupt flags checked by the compiled code of this
+    /// instance.
+    pub(crate) fn interrupts(&self) -> &VMInterrupts {
+        &self.interrupts
+    }
+
+    /// Return a pointer to the epoch deadline of this instance.
+    fn epoch_deadline_ptr(&self) -> *mut u64 {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_epoch_deadline()) }
+    }
+
+    /// Sets the epoch deadline `delta` epochs after the current one.
+    pub(crate) fn set_epoch_deadline(&self, delta: u64) {
+        let deadline = self.interrupts.epoch().saturating_add(delta);
+        unsafe {
+            *self.epoch_deadline_ptr() = deadline;
+        }
+    }
+
+    /// Handles the epoch deadline being reached, by extending it with the
+    /// epoch deadline callback, or trapping if there is none or if it
+    /// declines to.
+    pub(crate) fn epoch_deadline_reached(&self) -> Result<(), Trap> {
+        // The deadline may have been extended since it was checked.
+        if self.interrupts.epoch() < unsafe { *self.epoch_deadline_ptr() } {
+            return Ok(());
+        }
+
+        let mut callback = self.epoch_deadline_callback.take();
+        let delta = callback.as_mut().and_then(|callback| callback());
+        self.epoch_deadline_callback.set(callback);
+        match delta {
+            Some(delta) => {
+                self.set_epoch_deadline(delta);
+                Ok(())
+            }
+            None => Err(Trap::new_from_runtime(TrapCode::EpochDeadline)),
+        }
+    }
+
     /// Return a reference to the vmctx used by compiled wasm code.
     fn vmctx(&self) -> &VMContext {
         &self.vmctx
@@ -413,13 +480,13 @@ impl Instance {
         };
 
         // Make the call.
-        unsafe {
+        self.interrupts.with_stack_limit(|| unsafe {
             catch_traps(callee_vmctx, || {
                 mem::transmute::<*const VMFunctionBody, unsafe extern "C" fn(VMFunctionEnvironment)>(
                     callee_address,
                 )(callee_vmctx)
             })
-        }
+        })
     }
 
     /// Return the offset from the vmctx pointer to its containing `Instance`.
@@ -629,6 +696,130 @@ impl Instance {
         // dropping a non-passive element is a no-op (not a trap).
     }
 
+    /// Returns the `funcref` value of `anyfunc`, which is a pointer to an
+    /// equal `VMCallerCheckedAnyfunc` owned by this instance, or null if
+    /// `anyfunc` is the null reference.
+    fn intern_funcref(&self, anyfunc: VMCallerCheckedAnyfunc) -> *const VMCallerCheckedAnyfunc {
+        if anyfunc.func_ptr.is_null() {
+            return ptr::null();
+        }
+        let key = (anyfunc.func_ptr as usize, unsafe {
+            anyfunc.vmctx.host_env as usize
+        });
+        let mut funcrefs = self.funcrefs.borrow_mut();
+        let funcref = funcrefs.entry(key).or_insert_with(|| Box::new(anyfunc));
+        &**funcref as *const _
+    }
+
+    /// Returns the `VMCallerCheckedAnyfunc` a `funcref` value points to.
+    ///
+    /// # Safety
+    /// `funcref` must be null or point to a valid `VMCallerCheckedAnyfunc`.
+    unsafe fn funcref_to_anyfunc(funcref: *const VMCallerCheckedAnyfunc) -> VMCallerCheckedAnyfunc {
+        if funcref.is_null() {
+            VMCallerCheckedAnyfunc::default()
+        } else {
+            (*funcref).clone()
+        }
+    }
+
+    /// The `ref.func` operation: returns the `funcref` value of a function.
+    pub(crate) fn func_ref(&self, index: FunctionIndex) -> *const VMCallerCheckedAnyfunc {
+        self.intern_funcref(self.get_caller_checked_anyfunc(index))
+    }
+
+    /// The `table.get` operation for tables of `funcref`s.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the index is out of bounds.
+    pub(crate) fn table_get_funcref(
+        &self,
+        table_index: TableIndex,
+        index: u32,
+    ) -> Result<*const VMCallerCheckedAnyfunc, Trap> {
+        let anyfunc = self
+            .get_table(table_index)
+            .get(index)
+            .ok_or_else(|| Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds))?;
+        Ok(self.intern_funcref(anyfunc))
+    }
+
+    /// The `table.set` operation for tables of `funcref`s.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the index is out of bounds.
+    ///
+    /// # Safety
+    /// `value` must be null or point to a valid `VMCallerCheckedAnyfunc`.
+    pub(crate) unsafe fn table_set_funcref(
+        &self,
+        table_index: TableIndex,
+        index: u32,
+        value: *const VMCallerCheckedAnyfunc,
+    ) -> Result<(), Trap> {
+        self.get_table(table_index)
+            .set(index, Self::funcref_to_anyfunc(value))
+    }
+
+    /// The `table.grow` operation for tables of `funcref`s, filling the new
+    /// elements with `init_value`.
+    ///
+    /// Returns `None` if the table can't be grown by the specified amount of
+    /// elements, otherwise returns the previous size of the table.
+    ///
+    /// # Safety
+    /// `init_value` must be null or point to a valid `VMCallerCheckedAnyfunc`.
+    pub(crate) unsafe fn table_grow_funcref(
+        &self,
+        table_index: TableIndex,
+        delta: u32,
+        init_value: *const VMCallerCheckedAnyfunc,
+    ) -> Option<u32> {
+        let table = self.get_table(table_index);
+        let old_size = table.grow(delta)?;
+        let anyfunc = Self::funcref_to_anyfunc(init_value);
+        for index in old_size..old_size + delta {
+            table
+                .set(index, anyfunc.clone())
+                .expect("should never panic because the table was just grown");
+        }
+        Some(old_size)
+    }
+
+    /// The `table.fill` operation for tables of `funcref`s.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the range within the table is out of bounds.
+    ///
+    /// # Safety
+    /// `value` must be null or point to a valid `VMCallerCheckedAnyfunc`.
+    pub(crate) unsafe fn table_fill_funcref(
+        &self,
+        table_index: TableIndex,
+        dst: u32,
+        value: *const VMCallerCheckedAnyfunc,
+        len: u32,
+    ) -> Result<(), Trap> {
+        // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-table-fill
+
+        let table = self.get_table(table_index);
+        if dst.checked_add(len).map_or(true, |n| n > table.size()) {
+            return Err(Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds));
+        }
+
+        let anyfunc = Self::funcref_to_anyfunc(value);
+        for index in dst..dst + len {
+            table
+                .set(index, anyfunc.clone())
+                .expect("should never panic because we already did the bounds check above");
+        }
+
+        Ok(())
+    }
+
     /// Do a `memory.copy` for a locally defined memory.
     ///
     /// # Errors
@@ -638,9 +829,9 @@ impl Instance {
     pub(crate) fn local_memory_copy(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
 
@@ -653,9 +844,9 @@ impl Instance {
     pub(crate) fn imported_memory_copy(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         let memory = unsafe { import.definition.as_ref() };
@@ -663,6 +854,27 @@ impl Instance {
         unsafe { memory.memory_copy(dst, src, len) }
     }
 
+    /// Perform a `memory.copy` between two different memories, which can be
+    /// either locally defined or imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source or destination ranges are out of
+    /// bounds.
+    pub(crate) fn memory_copy_between(
+        &self,
+        dst_index: MemoryIndex,
+        src_index: MemoryIndex,
+        dst: u64,
+        src: u64,
+        len: u64,
+    ) -> Result<(), Trap> {
+        let dst_memory = self.get_memory(dst_index);
+        let src_memory = self.get_memory(src_index);
+        // The following memory copy is not synchronized and is not atomic:
+        unsafe { dst_memory.memory_copy_from(&src_memory, dst, src, len) }
+    }
+
     /// Perform the `memory.fill` operation on a locally defined memory.
     ///
     /// # Errors
@@ -671,9 +883,9 @@ impl Instance {
     pub(crate) fn local_memory_fill(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let memory = self.memory(memory_index);
         // The following memory fill is not synchronized and is not atomic:
@@ -688,9 +900,9 @@ impl Instance {
     pub(crate) fn imported_memory_fill(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         let memory = unsafe { import.definition.as_ref() };
@@ -709,7 +921,7 @@ impl Instance {
         &self,
         memory_index: MemoryIndex,
         data_index: DataIndex,
-        dst: u32,
+        dst: u64,
         src: u32,
         len: u32,
     ) -> Result<(), Trap> {
@@ -726,8 +938,8 @@ impl Instance {
             .checked_add(len)
             .map_or(true, |n| n as usize > data.len())
             || dst
-                .checked_add(len)
-                .map_or(true, |m| m > memory.current_length)
+                .checked_add(u64::from(len))
+                .map_or(true, |m| m > memory.current_length as u64)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -749,6 +961,119 @@ impl Instance {
         passive_data[data_index] = None;
     }
 
+    /// Get a memory by index regardless of whether it is locally-defined or
+    /// imported.
+    fn get_memory_object(&self, memory_index: MemoryIndex) -> &dyn Memory {
+        if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
+            self.memories[local_memory_index].as_ref()
+        } else {
+            self.imported_memory(memory_index).from.as_ref()
+        }
+    }
+
+    /// Returns the host address of the `size`-byte atomic location at `addr`
+    /// in the memory at `memory_index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the location is out of bounds or
+    /// misaligned.
+    fn atomic_location(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        size: u64,
+    ) -> Result<usize, Trap> {
+        let memory = self.get_memory(memory_index);
+        if addr
+            .checked_add(size)
+            .map_or(true, |end| end > memory.current_length as u64)
+        {
+            return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
+        }
+        if addr % size != 0 {
+            return Err(Trap::new_from_runtime(TrapCode::UnalignedAtomic));
+        }
+        Ok(memory.base as usize + addr as usize)
+    }
+
+    /// Perform a `memory.atomic.wait32` or `memory.atomic.wait64`, with
+    /// `load` reading the value at the given host address.
+    ///
+    /// Returns 0 when woken up by a notification, 1 when the value didn't
+    /// match `expected`, and 2 when `timeout` nanoseconds expired. A negative
+    /// timeout never expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the location is out of bounds or
+    /// misaligned, or when the memory isn't shared.
+    pub(crate) fn memory_atomic_wait<T: PartialEq>(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        expected: T,
+        timeout: i64,
+        load: impl FnOnce(usize) -> T,
+    ) -> Result<u32, Trap> {
+        // https://webassembly.github.io/threads/core/exec/instructions.html#exec-atomic-wait
+
+        let location = self.atomic_location(memory_index, addr, mem::size_of::<T>() as u64)?;
+        if !self.get_memory_object(memory_index).ty().shared {
+            return Err(Trap::new_from_runtime(TrapCode::AtomicWaitNonSharedMemory));
+        }
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_nanos);
+        let result = ParkingSpot::global().wait(location, || load(location) == expected, timeout);
+        Ok(result as u32)
+    }
+
+    /// Perform a `memory.atomic.notify`, waking up at most `count` waiters.
+    ///
+    /// Returns the number of waiters that were woken up.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the location is out of bounds or
+    /// misaligned.
+    pub(crate) fn memory_atomic_notify(
+        &self,
+        memory_index: MemoryIndex,
+        addr: u64,
+        count: u32,
+    ) -> Result<u32, Trap> {
+        // https://webassembly.github.io/threads/core/exec/instructions.html#exec-atomic-notify
+
+        let location = self.atomic_location(memory_index, addr, 4)?;
+        if !self.get_memory_object(memory_index).ty().shared {
+            // Nothing can wait on an unshared memory.
+            return Ok(0);
+        }
+        Ok(ParkingSpot::global().notify(location, count))
+    }
+
+    /// Create a new exception thrown with the tag at `tag_index`, carrying
+    /// `payload`.
+    pub(crate) fn exception_new(&self, tag_index: TagIndex, payload: Box<[u128]>) -> VMException {
+        VMException::new(self.tags[tag_index].clone(), payload)
+    }
+
+    /// Returns the number of values carried by the exceptions thrown with
+    /// the tag at `tag_index`.
+    pub(crate) fn tag_arity(&self, tag_index: TagIndex) -> usize {
+        self.tags[tag_index].ty().params().len()
+    }
+
+    /// Returns whether `exception` was thrown with the tag at `tag_index`.
+    pub(crate) fn exception_matches(&self, exception: &VMException, tag_index: TagIndex) -> bool {
+        exception.is(&self.tags[tag_index])
+    }
+
+    /// Get the trampoline calling functions of the given signature from the
+    /// host.
+    pub(crate) fn function_call_trampoline(&self, sig_index: SignatureIndex) -> VMTrampoline {
+        self.function_call_trampolines[sig_index]
+    }
+
     /// Get a table by index regardless of whether it is locally-defined or an
     /// imported, foreign table.
     pub(crate) fn get_table(&self, table_index: TableIndex) -> &dyn Table {
@@ -817,6 +1142,7 @@ impl InstanceHandle {
         vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
         host_state: Box<dyn Any>,
         imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
+        interrupts: Arc<VMInterrupts>,
     ) -> Result<Self, Trap> {
         let vmctx_globals = finished_globals
             .values()
@@ -830,6 +1156,21 @@ impl InstanceHandle {
                 .map(|data| Some(data.clone()))
                 .collect(),
         );
+        // Imported tags come first, then every local tag gets a fresh
+        // identity.
+        let tags = imports
+            .tags
+            .values()
+            .cloned()
+            .chain(
+                module
+                    .tags
+                    .keys()
+                    .skip(module.num_imported_tags)
+                    .map(|index| Arc::new(Tag::new(module.tag_type(index)))),
+            )
+            .collect::<PrimaryMap<TagIndex, _>>()
+            .into_boxed_slice();
 
         let handle = {
             let offsets = allocator.offsets().clone();
@@ -840,11 +1181,15 @@ impl InstanceHandle {
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
+                tags,
                 functions: finished_functions,
                 function_call_trampolines: finished_function_call_trampolines,
                 passive_elements: Default::default(),
                 passive_data,
+                funcrefs: Default::default(),
                 host_state,
+                interrupts,
+                epoch_deadline_callback: Cell::new(None),
                 signal_handler: Cell::new(None),
                 imported_function_envs,
                 vmctx: VMContext {},
@@ -895,6 +1240,12 @@ impl InstanceHandle {
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        ptr::write(
+            instance.interrupts_ptr(),
+            &*instance.interrupts as *const VMInterrupts,
+        );
+        // There is no epoch deadline until one is set.
+        ptr::write(instance.epoch_deadline_ptr(), u64::MAX);
 
         // Ensure that our signal handlers are ready for action.
         init_traps();
@@ -914,12 +1265,16 @@ impl InstanceHandle {
 
     /// Finishes the instantiation process started by `Instance::new`.
     ///
+    /// The local memories with an image in `memory_images`, created from
+    /// the same `data_initializers`, are initialized by mapping it.
+    ///
     /// # Safety
     ///
     /// Only safe to call immediately after instantiation.
     pub unsafe fn finish_instantiation(
         &self,
         data_initializers: &[DataInitializer<'_>],
+        memory_images: Option<&MemoryImages>,
     ) -> Result<(), Trap> {
         let instance = self.instance().as_ref();
         check_table_init_bounds(instance)?;
@@ -927,7 +1282,7 @@ impl InstanceHandle {
 
         // Apply the initializers.
         initialize_tables(instance)?;
-        initialize_memories(instance, data_initializers)?;
+        initialize_memories(instance, data_initializers, memory_images)?;
 
         // The WebAssembly spec specifies that the start function is
         // invoked automatically at instantiation time.
@@ -963,13 +1318,24 @@ impl InstanceHandle {
     }
 
     /// Lookup an export with the given name.
+    ///
+    /// Exported modules and instances aren't known to the VM, so they are
+    /// never found.
     pub fn lookup(&self, field: &str) -> Option<VMExport> {
         let export = self.module_ref().exports.get(field)?;
+        if let ExportIndex::Module(_) | ExportIndex::Instance(_) = export {
+            return None;
+        }
 
         Some(self.lookup_by_declaration(&export))
     }
 
     /// Lookup an export with the given export declaration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the export is a module or an instance, which are handled
+    /// by the embedder.
     // TODO: maybe EngineExport
     pub fn lookup_by_declaration(&self, export: &ExportIndex) -> VMExport {
         let instance = self.instance().clone();
@@ -1050,6 +1416,14 @@ impl InstanceHandle {
                 }
                 .into()
             }
+            ExportIndex::Tag(index) => VMExportTag {
+                from: instance_ref.tags[*index].clone(),
+                instance_ref: Some(instance),
+            }
+            .into(),
+            ExportIndex::Module(_) | ExportIndex::Instance(_) => {
+                panic!("modules and instances are not exported by the VM")
+            }
         }
     }
 
@@ -1128,6 +1502,28 @@ impl InstanceHandle {
         self.instance().as_ref().get_local_table(index)
     }
 
+    /// Sets the epoch deadline of this instance `delta` epochs after the
+    /// current one.
+    pub fn set_epoch_deadline(&self, delta: u64) {
+        self.instance().as_ref().set_epoch_deadline(delta)
+    }
+
+    /// Sets the handler run when the epoch deadline of this instance is
+    /// reached.
+    ///
+    /// The handler returns the number of epochs to extend the deadline by,
+    /// counted from the current epoch, or `None` to trap with
+    /// `TrapCode::EpochDeadline`.
+    pub fn set_epoch_deadline_callback<F>(&self, callback: F)
+    where
+        F: 'static + FnMut() -> Option<u64> + Send,
+    {
+        self.instance()
+            .as_ref()
+            .epoch_deadline_callback
+            .set(Some(Box::new(callback)));
+    }
+
     /// Initializes the host environments.
     ///
     /// # Safety
@@ -1165,6 +1561,9 @@ impl InstanceHandle {
     }
 }
 
+/// A handler run when the epoch deadline of an instance is reached.
+pub type EpochDeadlineCallback = dyn FnMut() -> Option<u64> + Send;
+
 cfg_if::cfg_if! {
     if #[cfg(unix)] {
         pub type SignalHandler = dyn Fn(libc::c_int, *const libc::siginfo_t, *const libc::c_void) -> bool;
@@ -1213,14 +1612,20 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
     let mut start = init.location.offset;
 
     if let Some(base) = init.location.base {
-        let val = unsafe {
+        let global = unsafe {
             if let Some(def_index) = instance.module.local_global_index(base) {
-                instance.global(def_index).to_u32()
+                instance.global(def_index)
             } else {
-                instance.imported_global(base).definition.as_ref().to_u32()
+                instance.imported_global(base).definition.as_ref().clone()
             }
         };
-        start += usize::try_from(val).unwrap();
+        // 64-bit memories are initialized at `i64` offsets.
+        let val = if instance.module.memories[init.location.memory_index].memory64 {
+            global.to_u64()
+        } else {
+            u64::from(global.to_u32())
+        };
+        start = start.saturating_add(usize::try_from(val).unwrap_or(usize::MAX));
     }
 
     start
@@ -1241,7 +1646,7 @@ unsafe fn get_memory_slice<'instance>(
         let import = instance.imported_memory(init.location.memory_index);
         *import.definition.as_ref()
     };
-    slice::from_raw_parts_mut(memory.base, memory.current_length.try_into().unwrap())
+    slice::from_raw_parts_mut(memory.base, memory.current_length)
 }
 
 fn check_memory_init_bounds(
@@ -1252,7 +1657,8 @@ fn check_memory_init_bounds(
         let start = get_memory_init_start(init, instance);
         unsafe {
             let mem_slice = get_memory_slice(init, instance);
-            if mem_slice.get_mut(start..start + init.data.len()).is_none() {
+            let end = start.checked_add(init.data.len());
+            if end.and_then(|end| mem_slice.get_mut(start..end)).is_none() {
                 return Err(Trap::new_from_runtime(TrapCode::HeapSetterOutOfBounds));
             }
         }
@@ -1334,14 +1740,35 @@ fn initialize_passive_elements(instance: &Instance) {
 fn initialize_memories(
     instance: &Instance,
     data_initializers: &[DataInitializer<'_>],
+    memory_images: Option<&MemoryImages>,
 ) -> Result<(), Trap> {
+    // The memories mapping their image get all their data from it.
+    let mut mapped = HashSet::new();
+    if let Some(memory_images) = memory_images {
+        for (index, memory) in instance.memories.iter() {
+            let image = match memory_images.get(index) {
+                Some(image) => image,
+                None => continue,
+            };
+            if memory
+                .map_image(image)
+                .map_err(|error| Trap::new_from_user(Box::new(error)))?
+            {
+                mapped.insert(instance.module.memory_index(index));
+            }
+        }
+    }
+
     for init in data_initializers {
+        if mapped.contains(&init.location.memory_index) {
+            continue;
+        }
         let memory = instance.get_memory(init.location.memory_index);
 
         let start = get_memory_init_start(init, instance);
         if start
             .checked_add(init.data.len())
-            .map_or(true, |end| end > memory.current_length.try_into().unwrap())
+            .map_or(true, |end| end > memory.current_length)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -1,3 +1,4 @@
+use super::allocator::InstanceStorage;
 use super::Instance;
 use std::alloc::Layout;
 use std::ptr::{self, NonNull};
@@ -40,6 +41,9 @@ pub struct InstanceRef {
     /// The layout of `Instance` (which can vary).
     instance_layout: Layout,
 
+    /// Where the memory of `Instance` comes from.
+    storage: InstanceStorage,
+
     /// The `Instance` itself. It must be the last field of
     /// `InstanceRef` since `Instance` is dyamically-sized.
     ///
@@ -64,10 +68,15 @@ impl InstanceRef {
     /// and correctly initialized pointer to `Instance`. See
     /// [`InstanceAllocator`] for an example of how to correctly use
     /// this API.
-    pub(super) unsafe fn new(instance: NonNull<Instance>, instance_layout: Layout) -> Self {
+    pub(super) unsafe fn new(
+        instance: NonNull<Instance>,
+        instance_layout: Layout,
+        storage: InstanceStorage,
+    ) -> Self {
         Self {
             strong: Arc::new(atomic::AtomicUsize::new(1)),
             instance_layout,
+            storage,
             instance,
         }
     }
@@ -88,7 +97,8 @@ impl InstanceRef {
         let instance_ptr = self.instance.as_ptr();
 
         ptr::drop_in_place(instance_ptr);
-        std::alloc::dealloc(instance_ptr as *mut u8, self.instance_layout);
+        self.storage
+            .free(self.instance.cast(), self.instance_layout);
     }
 
     /// Get the number of strong references pointing to this
@@ -156,6 +166,7 @@ impl Clone for InstanceRef {
         Self {
             strong: self.strong.clone(),
             instance_layout: self.instance_layout,
+            storage: self.storage.clone(),
             instance: self.instance.clone(),
         }
     }
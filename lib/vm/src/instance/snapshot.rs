@@ -0,0 +1,328 @@
+//! Snapshots of the state of instances.
+//!
+//! An [`InstanceSnapshot`] captures what a running instance has made of
+//! its local memories, mutable globals, tables and passive segments, so
+//! that a new instance of the same module can be restored to that state
+//! instead of being initialized, for instance to resume a pre-initialized
+//! module.
+
+use super::{Instance, InstanceHandle};
+use crate::memory::MemoryError;
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMGlobalDefinition};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::slice;
+use thiserror::Error;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{
+    DataIndex, ElemIndex, FunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    Pages, Type,
+};
+
+/// The granularity at which zeroes are left out of memory snapshots.
+const MEMORY_CHUNK_SIZE: usize = 0x1000;
+
+/// An error while taking or restoring an [`InstanceSnapshot`].
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    /// The state of the instance can't be captured.
+    #[error("The instance can't be captured: {0}")]
+    Unsupported(String),
+    /// The snapshot doesn't fit the module it is restored for.
+    #[error("The snapshot doesn't match the module: {0}")]
+    Incompatible(String),
+    /// A memory couldn't grow to the size it has in the snapshot.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+}
+
+/// The state of an instance, as captured by [`InstanceHandle::snapshot`].
+///
+/// The state of imported memories, globals and tables belongs to the
+/// instance or host defining them, and isn't part of the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceSnapshot {
+    memories: Vec<MemorySnapshot>,
+    globals: Vec<GlobalSnapshot>,
+    tables: Vec<TableSnapshot>,
+    dropped_elements: Vec<u32>,
+    dropped_data: Vec<u32>,
+}
+
+/// The contents of a local memory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MemorySnapshot {
+    /// The size of the memory in pages.
+    pages: u32,
+    /// The offsets and bytes of the parts of the memory that aren't zeroes.
+    segments: Vec<(u64, Vec<u8>)>,
+}
+
+/// The value of a mutable local global.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct GlobalSnapshot {
+    index: u32,
+    value: [u8; 16],
+}
+
+/// The elements of a local table, as indices of the functions of the
+/// module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TableSnapshot {
+    elements: Vec<Option<u32>>,
+}
+
+impl InstanceHandle {
+    /// Captures the state of the instance.
+    ///
+    /// Tables can only be captured if their elements are null or
+    /// functions of the module, local or imported.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, SnapshotError> {
+        let instance = self.instance().as_ref();
+        let module = &instance.module;
+
+        let memories = instance
+            .memories
+            .keys()
+            .map(|index| snapshot_memory(instance, index))
+            .collect();
+
+        let globals = instance
+            .globals
+            .keys()
+            .filter_map(|index| {
+                let ty = module.globals[module.global_index(index)];
+                if !ty.mutability.is_mutable() {
+                    return None;
+                }
+                Some(match ty.ty {
+                    Type::ExternRef | Type::FuncRef => Err(SnapshotError::Unsupported(format!(
+                        "the global {} is a reference",
+                        index.index()
+                    ))),
+                    _ => Ok(GlobalSnapshot {
+                        index: index.as_u32(),
+                        value: instance.global(index).to_bytes(),
+                    }),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        // The functions that table elements may refer to, keyed as the
+        // `funcref` values of the instance.
+        let functions = module
+            .functions
+            .keys()
+            .map(|index| {
+                (
+                    funcref_key(&instance.get_caller_checked_anyfunc(index)),
+                    index,
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let tables = instance
+            .tables
+            .iter()
+            .map(|(table_index, table)| {
+                let elements = (0..table.size())
+                    .map(|index| {
+                        let anyfunc = table.get(index).unwrap_or_default();
+                        if anyfunc.func_ptr.is_null() {
+                            return Ok(None);
+                        }
+                        match functions.get(&funcref_key(&anyfunc)) {
+                            Some(function) => Ok(Some(function.as_u32())),
+                            None => Err(SnapshotError::Unsupported(format!(
+                                "the element {} of the table {} is a function of another instance",
+                                index,
+                                table_index.index()
+                            ))),
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(TableSnapshot { elements })
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+
+        let dropped_elements = instance
+            .passive_elements
+            .borrow()
+            .iter()
+            .filter(|(_, segment)| segment.is_none())
+            .map(|(index, _)| index.as_u32())
+            .collect();
+        let dropped_data = instance
+            .passive_data
+            .borrow()
+            .iter()
+            .filter(|(_, segment)| segment.is_none())
+            .map(|(index, _)| index.as_u32())
+            .collect();
+
+        Ok(InstanceSnapshot {
+            memories,
+            globals,
+            tables,
+            dropped_elements,
+            dropped_data,
+        })
+    }
+
+    /// Restores the state captured by `snapshot` in this instance, in
+    /// place of its initialization by `finish_instantiation`.
+    ///
+    /// # Safety
+    ///
+    /// Only safe to call immediately after instantiation, instead of
+    /// `finish_instantiation`.
+    pub unsafe fn restore_snapshot(
+        &self,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<(), SnapshotError> {
+        let instance = self.instance().as_ref();
+        let module = &instance.module;
+
+        if snapshot.memories.len() != instance.memories.len() {
+            return Err(SnapshotError::Incompatible(format!(
+                "the module has {} local memories, not {}",
+                instance.memories.len(),
+                snapshot.memories.len()
+            )));
+        }
+        for (index, memory_snapshot) in snapshot.memories.iter().enumerate() {
+            let index = LocalMemoryIndex::new(index);
+            let memory = &instance.memories[index];
+            let pages = Pages(memory_snapshot.pages);
+            let delta = pages.0.checked_sub(memory.size().0).ok_or_else(|| {
+                SnapshotError::Incompatible(format!(
+                    "the memory {} can't shrink to {} pages",
+                    index.index(),
+                    pages.0
+                ))
+            })?;
+            memory.grow(Pages(delta))?;
+            let definition = instance.memory(index);
+            let contents = slice::from_raw_parts_mut(definition.base, definition.current_length);
+            for (offset, data) in &memory_snapshot.segments {
+                let segment = usize::try_from(*offset)
+                    .ok()
+                    .and_then(|start| contents.get_mut(start..start.checked_add(data.len())?))
+                    .ok_or_else(|| {
+                        SnapshotError::Incompatible(format!(
+                            "the memory {} is too small for its contents",
+                            index.index()
+                        ))
+                    })?;
+                segment.copy_from_slice(data);
+            }
+        }
+
+        for global in &snapshot.globals {
+            let index = LocalGlobalIndex::from_u32(global.index);
+            let ty = match instance.globals.get(index) {
+                Some(_) => module.globals[module.global_index(index)],
+                None => {
+                    return Err(SnapshotError::Incompatible(format!(
+                        "the module has no local global {}",
+                        global.index
+                    )))
+                }
+            };
+            if !ty.mutability.is_mutable() {
+                return Err(SnapshotError::Incompatible(format!(
+                    "the global {} is immutable",
+                    global.index
+                )));
+            }
+            let mut value = VMGlobalDefinition::new();
+            *value.as_bytes_mut() = global.value;
+            instance.set_global(index, &value);
+        }
+
+        if snapshot.tables.len() != instance.tables.len() {
+            return Err(SnapshotError::Incompatible(format!(
+                "the module has {} local tables, not {}",
+                instance.tables.len(),
+                snapshot.tables.len()
+            )));
+        }
+        for (index, table_snapshot) in snapshot.tables.iter().enumerate() {
+            let index = LocalTableIndex::new(index);
+            let table = &instance.tables[index];
+            let incompatible = |reason: &str| {
+                SnapshotError::Incompatible(format!("the table {} {}", index.index(), reason))
+            };
+            let size = u32::try_from(table_snapshot.elements.len())
+                .map_err(|_| incompatible("is too large"))?;
+            let delta = size
+                .checked_sub(table.size())
+                .ok_or_else(|| incompatible("can't shrink"))?;
+            table
+                .grow(delta)
+                .ok_or_else(|| incompatible("can't grow to its size in the snapshot"))?;
+            for (i, element) in table_snapshot.elements.iter().enumerate() {
+                let anyfunc = match element {
+                    Some(function) if (*function as usize) < module.functions.len() => {
+                        instance.get_caller_checked_anyfunc(FunctionIndex::from_u32(*function))
+                    }
+                    Some(function) => {
+                        return Err(SnapshotError::Incompatible(format!(
+                            "the module has no function {}",
+                            function
+                        )))
+                    }
+                    None => VMCallerCheckedAnyfunc::default(),
+                };
+                table
+                    .set(i as u32, anyfunc)
+                    .map_err(|_| incompatible("is too small for its elements"))?;
+            }
+        }
+
+        let mut passive_elements = instance.passive_elements.borrow_mut();
+        for index in &snapshot.dropped_elements {
+            if let Some(segment) = passive_elements.get_mut(ElemIndex::from_u32(*index)) {
+                *segment = None;
+            }
+        }
+        let mut passive_data = instance.passive_data.borrow_mut();
+        for index in &snapshot.dropped_data {
+            if let Some(segment) = passive_data.get_mut(DataIndex::from_u32(*index)) {
+                *segment = None;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Captures the parts of the local memory `index` that aren't zeroes.
+fn snapshot_memory(instance: &Instance, index: LocalMemoryIndex) -> MemorySnapshot {
+    let definition = instance.memory(index);
+    let contents = unsafe { slice::from_raw_parts(definition.base, definition.current_length) };
+    let mut segments: Vec<(u64, Vec<u8>)> = Vec::new();
+    let mut end = 0;
+    for (i, chunk) in contents.chunks(MEMORY_CHUNK_SIZE).enumerate() {
+        if chunk.iter().all(|byte| *byte == 0) {
+            continue;
+        }
+        let offset = i * MEMORY_CHUNK_SIZE;
+        match segments.last_mut() {
+            // Consecutive chunks are merged into a single segment.
+            Some((_, data)) if end == offset => data.extend_from_slice(chunk),
+            _ => segments.push((offset as u64, chunk.to_vec())),
+        }
+        end = offset + chunk.len();
+    }
+    MemorySnapshot {
+        pages: instance.memories[index].size().0,
+        segments,
+    }
+}
+
+/// The function body and environment of a `funcref` value.
+fn funcref_key(anyfunc: &VMCallerCheckedAnyfunc) -> (usize, usize) {
+    (anyfunc.func_ptr as usize, unsafe { anyfunc.vmctx.vmctx }
+        as usize)
+}
@@ -21,15 +21,21 @@
 )]
 
 mod export;
+mod fiber;
 mod global;
 mod imports;
 mod instance;
 mod memory;
+mod memory_image;
 mod mmap;
 mod module;
+mod parking_spot;
+mod pool;
 mod probestack;
+mod resource_limiter;
 mod sig_registry;
 mod table;
+mod tag;
 mod trap;
 mod vmcontext;
 mod vmoffsets;
@@ -37,23 +43,32 @@ mod vmoffsets;
 pub mod libcalls;
 
 pub use crate::export::*;
+pub use crate::fiber::{
+    suspend_current_fiber, Fiber, FiberError, FiberStack, DEFAULT_FIBER_STACK_SIZE,
+    FIBERS_SUPPORTED,
+};
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
-    ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
+    EpochDeadlineCallback, ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator,
+    InstanceHandle, InstanceSnapshot, SnapshotError,
 };
 pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
+pub use crate::memory_image::{MemoryImage, MemoryImages};
 pub use crate::mmap::Mmap;
-pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
+pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo, ModuleInitializer};
+pub use crate::pool::{InstancePool, PoolingLimits};
 pub use crate::probestack::PROBESTACK;
+pub use crate::resource_limiter::{LimitedMemory, LimitedTable, ResourceLimiter};
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{LinearTable, Table, TableStyle};
+pub use crate::tag::{Tag, VMException};
 pub use crate::trap::*;
 pub use crate::vmcontext::{
     VMBuiltinFunctionIndex, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext,
     VMFunctionBody, VMFunctionEnvironment, VMFunctionImport, VMFunctionKind, VMGlobalDefinition,
-    VMGlobalImport, VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition,
-    VMTableImport, VMTrampoline,
+    VMGlobalImport, VMInterrupts, VMMemoryDefinition, VMMemoryImport, VMSharedSignatureIndex,
+    VMTableDefinition, VMTableImport, VMTrampoline,
 };
 pub use crate::vmoffsets::{TargetSharedSignatureIndex, VMOffsets};
 
@@ -36,11 +36,19 @@
 //!   ```
 
 use crate::probestack::PROBESTACK;
-use crate::trap::{raise_lib_trap, Trap, TrapCode};
-use crate::vmcontext::VMContext;
+use crate::tag::VMException;
+use crate::trap::{catch_traps, raise_lib_trap, raise_user_trap, resume_panic, Trap, TrapCode};
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMContext, VMFunctionBody, VMFunctionEnvironment};
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use wasmer_types::{DataIndex, ElemIndex, LocalMemoryIndex, MemoryIndex, TableIndex};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use wasmer_types::{
+    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, MemoryIndex, SignatureIndex, TableIndex,
+    TagIndex,
+};
 
 /// Implementation of f32.ceil
 #[no_mangle]
@@ -258,6 +266,305 @@ pub unsafe extern "C" fn wasmer_elem_drop(vmctx: *mut VMContext, elem_index: u32
     instance.elem_drop(elem_index);
 }
 
+/// Implementation of `table.size`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_table_size(vmctx: *mut VMContext, table_index: u32) -> u32 {
+    let table_index = TableIndex::from_u32(table_index);
+    let instance = (&*vmctx).instance();
+    instance.get_table(table_index).size()
+}
+
+/// Implementation of `table.get`.
+///
+/// Returns the `funcref` stored at `elem_index`, as a pointer to a
+/// `VMCallerCheckedAnyfunc` owned by the instance, or null.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_table_get(
+    vmctx: *mut VMContext,
+    table_index: u32,
+    elem_index: u32,
+) -> *const VMCallerCheckedAnyfunc {
+    let result = {
+        let table_index = TableIndex::from_u32(table_index);
+        let instance = (&*vmctx).instance();
+        instance.table_get_funcref(table_index, elem_index)
+    };
+    match result {
+        Ok(funcref) => funcref,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `table.set`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `value` must be null or point to
+/// a valid `VMCallerCheckedAnyfunc`.
+pub unsafe extern "C" fn wasmer_table_set(
+    vmctx: *mut VMContext,
+    table_index: u32,
+    elem_index: u32,
+    value: *const VMCallerCheckedAnyfunc,
+) {
+    let result = {
+        let table_index = TableIndex::from_u32(table_index);
+        let instance = (&*vmctx).instance();
+        instance.table_set_funcref(table_index, elem_index, value)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `table.grow`.
+///
+/// Returns the previous size of the table, or `u32::MAX` if it can't be
+/// grown.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `init_value` must be null or
+/// point to a valid `VMCallerCheckedAnyfunc`.
+pub unsafe extern "C" fn wasmer_table_grow(
+    vmctx: *mut VMContext,
+    init_value: *const VMCallerCheckedAnyfunc,
+    delta: u32,
+    table_index: u32,
+) -> u32 {
+    let table_index = TableIndex::from_u32(table_index);
+    let instance = (&*vmctx).instance();
+    instance
+        .table_grow_funcref(table_index, delta, init_value)
+        .unwrap_or(u32::max_value())
+}
+
+/// Implementation of `table.fill`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `value` must be null or point to
+/// a valid `VMCallerCheckedAnyfunc`.
+pub unsafe extern "C" fn wasmer_table_fill(
+    vmctx: *mut VMContext,
+    table_index: u32,
+    dst: u32,
+    value: *const VMCallerCheckedAnyfunc,
+    len: u32,
+) {
+    let result = {
+        let table_index = TableIndex::from_u32(table_index);
+        let instance = (&*vmctx).instance();
+        instance.table_fill_funcref(table_index, dst, value, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `ref.func`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_func_ref(
+    vmctx: *mut VMContext,
+    function_index: u32,
+) -> *const VMCallerCheckedAnyfunc {
+    let function_index = FunctionIndex::from_u32(function_index);
+    let instance = (&*vmctx).instance();
+    instance.func_ref(function_index)
+}
+
+/// Implementation of `memory.atomic.wait32`, for both locally defined and
+/// imported memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_wait32(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    expected: u32,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, expected, timeout, |location| {
+            (*(location as *const AtomicU32)).load(Ordering::SeqCst)
+        })
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.wait64`, for both locally defined and
+/// imported memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_wait64(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    expected: u64,
+    timeout: i64,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_wait(memory_index, addr, expected, timeout, |location| {
+            (*(location as *const AtomicU64)).load(Ordering::SeqCst)
+        })
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `memory.atomic.notify`, for both locally defined and
+/// imported memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_atomic_notify(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    addr: u64,
+    count: u32,
+) -> u32 {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_atomic_notify(memory_index, addr, count)
+    };
+    match result {
+        Ok(result) => result,
+        Err(trap) => raise_lib_trap(trap),
+    }
+}
+
+/// Implementation of `throw`: creates the exception thrown with the tag at
+/// `tag_index`, reading its values from `payload`.
+///
+/// The returned exception is owned by the caller, which must hand it over to
+/// `wasmer_exception_raise` or `wasmer_exception_take_payload`.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `payload` must point to one
+/// 128-bit slot per parameter of the tag type.
+pub unsafe extern "C" fn wasmer_exception_new(
+    vmctx: *mut VMContext,
+    tag_index: u32,
+    payload: *const u128,
+) -> *mut VMException {
+    let tag_index = TagIndex::from_u32(tag_index);
+    let instance = (&*vmctx).instance();
+    let payload = slice::from_raw_parts(payload, instance.tag_arity(tag_index));
+    let exception = instance.exception_new(tag_index, payload.into());
+    Box::into_raw(Box::new(exception))
+}
+
+/// Unwinds the stack with `exception` up to the closest `wasmer_invoke`
+/// frame, or back to the host if there is none.
+///
+/// # Safety
+///
+/// `exception` must have been returned by `wasmer_exception_new` or
+/// `wasmer_invoke`, and not have been consumed yet.
+pub unsafe extern "C" fn wasmer_exception_raise(exception: *mut VMException) -> ! {
+    let exception = *Box::from_raw(exception);
+    raise_user_trap(Box::new(exception))
+}
+
+/// Calls `callee` with the values stored in `values`, catching the exception
+/// it may throw.
+///
+/// Returns a null pointer when the call returned normally, its results being
+/// stored in `values`. Otherwise, returns the exception, owned by the caller.
+/// Any other trap continues unwinding.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, `callee` must be a function of the
+/// signature at `sig_index` and `values` must be large enough to hold both
+/// its parameters and results.
+pub unsafe extern "C" fn wasmer_invoke(
+    vmctx: *mut VMContext,
+    sig_index: u32,
+    callee: *const VMFunctionBody,
+    callee_vmctx: *mut VMContext,
+    values: *mut u128,
+) -> *mut VMException {
+    let result = {
+        let sig_index = SignatureIndex::from_u32(sig_index);
+        let trampoline = (&*vmctx).instance().function_call_trampoline(sig_index);
+        // The caller's context is used to catch the traps, as the callee's
+        // may be a host function environment.
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            catch_traps(VMFunctionEnvironment { vmctx }, || {
+                trampoline(callee_vmctx, callee, values)
+            })
+        }))
+    };
+    match result {
+        Ok(Ok(())) => ptr::null_mut(),
+        Ok(Err(Trap::User(error))) => match VMException::from_error(&*error) {
+            Some(exception) => Box::into_raw(Box::new(exception)),
+            None => raise_user_trap(error),
+        },
+        Ok(Err(trap)) => raise_lib_trap(trap),
+        Err(payload) => resume_panic(payload),
+    }
+}
+
+/// Implementation of `catch`: returns 1 if `exception` was thrown with the
+/// tag at `tag_index`, 0 otherwise.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null, and `exception` must be owned by the
+/// caller.
+pub unsafe extern "C" fn wasmer_exception_matches(
+    vmctx: *mut VMContext,
+    exception: *const VMException,
+    tag_index: u32,
+) -> u32 {
+    let tag_index = TagIndex::from_u32(tag_index);
+    let instance = (&*vmctx).instance();
+    instance.exception_matches(&*exception, tag_index) as u32
+}
+
+/// Stores the values carried by `exception` in `values`, and frees the
+/// exception.
+///
+/// # Safety
+///
+/// `exception` must be owned by the caller, and `values` must be large
+/// enough to hold its payload.
+pub unsafe extern "C" fn wasmer_exception_take_payload(
+    exception: *mut VMException,
+    values: *mut u128,
+) {
+    let exception = Box::from_raw(exception);
+    let payload = exception.payload();
+    ptr::copy_nonoverlapping(payload.as_ptr(), values, payload.len());
+}
+
 /// Implementation of `memory.copy` for locally defined memories.
 ///
 /// # Safety
@@ -273,7 +580,7 @@ pub unsafe extern "C" fn wasmer_local_memory_copy(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.local_memory_copy(memory_index, dst, src, len)
+        instance.local_memory_copy(memory_index, dst.into(), src.into(), len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -295,7 +602,7 @@ pub unsafe extern "C" fn wasmer_imported_memory_copy(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.imported_memory_copy(memory_index, dst, src, len)
+        instance.imported_memory_copy(memory_index, dst.into(), src.into(), len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -317,7 +624,7 @@ pub unsafe extern "C" fn wasmer_memory_fill(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.local_memory_fill(memory_index, dst, val, len)
+        instance.local_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -339,7 +646,7 @@ pub unsafe extern "C" fn wasmer_imported_memory_fill(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (&*vmctx).instance();
-        instance.imported_memory_fill(memory_index, dst, val, len)
+        instance.imported_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -358,6 +665,118 @@ pub unsafe extern "C" fn wasmer_memory_init(
     dst: u32,
     src: u32,
     len: u32,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let data_index = DataIndex::from_u32(data_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_init(memory_index, data_index, dst.into(), src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_local_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.local_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_imported_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.imported_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.local_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_imported_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (&*vmctx).instance();
+        instance.imported_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.init` for 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory64_init(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    data_index: u32,
+    dst: u64,
+    src: u32,
+    len: u32,
 ) {
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
@@ -370,6 +789,33 @@ pub unsafe extern "C" fn wasmer_memory_init(
     }
 }
 
+/// Implementation of `memory.copy` between two different memories.
+///
+/// The addresses and length are zero-extended by the caller when one of the
+/// memories is a 32-bit memory.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_memory_copy_between(
+    vmctx: *mut VMContext,
+    dst_memory_index: u32,
+    src_memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let dst_memory_index = MemoryIndex::from_u32(dst_memory_index);
+        let src_memory_index = MemoryIndex::from_u32(src_memory_index);
+        let instance = (&*vmctx).instance();
+        instance.memory_copy_between(dst_memory_index, src_memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
 /// Implementation of `data.drop`.
 ///
 /// # Safety
@@ -381,6 +827,38 @@ pub unsafe extern "C" fn wasmer_data_drop(vmctx: *mut VMContext, data_index: u32
     instance.data_drop(data_index)
 }
 
+/// Implementation of the interrupt checks made on function entry and loop
+/// headers, called when the interrupt flag is set.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_interrupt(vmctx: *mut VMContext) {
+    let interrupted = {
+        let instance = (&*vmctx).instance();
+        instance.interrupts().take_interrupt()
+    };
+    if interrupted {
+        raise_lib_trap(Trap::new_from_runtime(TrapCode::Interrupt));
+    }
+}
+
+/// Implementation of the epoch checks made on function entry and loop
+/// headers, called when the epoch deadline of the instance is reached.
+///
+/// # Safety
+///
+/// `vmctx` must be valid and not null.
+pub unsafe extern "C" fn wasmer_epoch_deadline(vmctx: *mut VMContext) {
+    let result = {
+        let instance = (&*vmctx).instance();
+        instance.epoch_deadline_reached()
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
 /// Implementation for raising a trap
 ///
 /// # Safety
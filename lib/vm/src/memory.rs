@@ -5,6 +5,7 @@
 //!
 //! `LinearMemory` is to WebAssembly linear memories what `Table` is to WebAssembly tables.
 
+use crate::memory_image::MemoryImage;
 use crate::mmap::Mmap;
 use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::assert_ge;
@@ -113,6 +114,15 @@ pub trait Memory: fmt::Debug + Send + Sync {
     ///
     /// The pointer returned in [`VMMemoryDefinition`] must be valid for the lifetime of this memory.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
+
+    /// Maps `image` copy-on-write over the beginning of the memory, in
+    /// place of its current contents.
+    ///
+    /// Returns `Ok(false)`, leaving the memory untouched, if the memory
+    /// doesn't support memory images.
+    fn map_image(&self, _image: &MemoryImage) -> Result<bool, MemoryError> {
+        Ok(false)
+    }
 }
 
 /// A linear memory instance.
@@ -205,18 +215,19 @@ impl LinearMemory {
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
     ) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
+        if memory.minimum > memory.max_pages() {
             return Err(MemoryError::MinimumMemoryTooLarge {
                 min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
+                max_allowed: memory.max_pages(),
             });
         }
-        // `maximum` cannot be set to more than `65536` pages.
+        // `maximum` cannot be set to more than `65536` pages for 32-bit
+        // memories.
         if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
+            if max > memory.max_pages() {
                 return Err(MemoryError::MaximumMemoryTooLarge {
                     max_requested: max,
-                    max_allowed: Pages::max_value(),
+                    max_allowed: memory.max_pages(),
                 });
             }
             if max < memory.minimum {
@@ -228,6 +239,20 @@ impl LinearMemory {
                 });
             }
         }
+        // Shared memories are accessed concurrently by instances running on
+        // other threads, so their base address can never change.
+        if memory.shared {
+            if memory.maximum.is_none() {
+                return Err(MemoryError::InvalidMemory {
+                    reason: "shared memories must have a maximum size".to_string(),
+                });
+            }
+            if let MemoryStyle::Dynamic { .. } = style {
+                return Err(MemoryError::InvalidMemory {
+                    reason: "shared memories must use a static memory style".to_string(),
+                });
+            }
+        }
 
         let offset_guard_bytes = style.offset_guard_size() as usize;
 
@@ -259,7 +284,7 @@ impl LinearMemory {
         };
 
         let base_ptr = mmap.alloc.as_mut_ptr();
-        let mem_length = memory.minimum.bytes().0.try_into().unwrap();
+        let mem_length = memory.minimum.bytes().0;
         Ok(Self {
             mmap: Mutex::new(mmap),
             maximum: memory.maximum,
@@ -334,14 +359,16 @@ impl Memory for LinearMemory {
             return Ok(mmap.size);
         }
 
-        let new_pages = mmap
-            .size
-            .checked_add(delta)
-            .ok_or(MemoryError::CouldNotGrow {
-                current: mmap.size,
-                attempted_delta: delta,
-            })?;
         let prev_pages = mmap.size;
+        let new_pages = match prev_pages.0.checked_add(delta.0) {
+            Some(new_pages) => Pages(new_pages),
+            None => {
+                return Err(MemoryError::CouldNotGrow {
+                    current: prev_pages,
+                    attempted_delta: delta,
+                })
+            }
+        };
 
         if let Some(maximum) = self.maximum {
             if new_pages > maximum {
@@ -355,7 +382,7 @@ impl Memory for LinearMemory {
         // Wasm linear memories are never allowed to grow beyond what is
         // indexable. If the memory has no maximum, enforce the greatest
         // limit here.
-        if new_pages >= Pages::max_value() {
+        if new_pages >= self.memory.max_pages() {
             // Linear memory size would exceed the index range.
             return Err(MemoryError::CouldNotGrow {
                 current: mmap.size,
@@ -399,7 +426,7 @@ impl Memory for LinearMemory {
         unsafe {
             let mut md_ptr = self.get_vm_memory_definition();
             let md = md_ptr.as_mut();
-            md.current_length = new_pages.bytes().0.try_into().unwrap();
+            md.current_length = new_pages.bytes().0;
             md.base = mmap.alloc.as_mut_ptr() as _;
         }
 
@@ -411,4 +438,16 @@ impl Memory for LinearMemory {
         let _mmap_guard = self.mmap.lock().unwrap();
         unsafe { self.get_vm_memory_definition() }
     }
+
+    /// Maps `image` copy-on-write over the beginning of the memory.
+    fn map_image(&self, image: &MemoryImage) -> Result<bool, MemoryError> {
+        let mut mmap_guard = self.mmap.lock().unwrap();
+        let mmap = mmap_guard.borrow_mut();
+        if image.len() > mmap.size.bytes().0 {
+            return Ok(false);
+        }
+        // The image is unmapped along with the rest of the allocation.
+        unsafe { image.map_at(mmap.alloc.as_mut_ptr()) }.map_err(MemoryError::Region)?;
+        Ok(true)
+    }
 }
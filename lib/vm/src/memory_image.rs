@@ -0,0 +1,174 @@
+//! Copy-on-write images of the initial contents of linear memories.
+//!
+//! When all the data initializers of a local memory are at constant
+//! offsets within its minimum size, the contents they give the memory
+//! don't depend on the instance. A [`MemoryImage`] holds these contents in
+//! an in-memory file, which is then mapped copy-on-write over the memories
+//! of new instances instead of copying the data into each of them.
+//!
+//! Images are only supported on Linux, where they are backed by a `memfd`.
+//! Elsewhere, or for memories that can't have an image, the data
+//! initializers are applied by copying as usual.
+
+use crate::module::ModuleInfo;
+use std::fs::File;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{LocalMemoryIndex, OwnedDataInitializer};
+
+/// The initial contents of a linear memory, mappable copy-on-write.
+#[derive(Debug)]
+pub struct MemoryImage {
+    file: File,
+    /// The length of the image in bytes, a native page-size multiple.
+    len: usize,
+}
+
+impl MemoryImage {
+    /// Creates an image of the `segments`, pairs of offsets and data,
+    /// applied in order to a zeroed memory.
+    ///
+    /// Returns `None` if images aren't supported on this platform, or if
+    /// the image can't be created.
+    #[cfg(target_os = "linux")]
+    pub fn new<'a>(segments: impl IntoIterator<Item = (usize, &'a [u8])> + Clone) -> Option<Self> {
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::FromRawFd;
+
+        let end = segments
+            .clone()
+            .into_iter()
+            .map(|(offset, data)| offset.checked_add(data.len()))
+            .try_fold(0, |end, segment_end| Some(end.max(segment_end?)))?;
+        let len = crate::mmap::round_up_to_page_size(end, region::page::size());
+        if len == 0 {
+            return None;
+        }
+
+        let name = b"wasm-memory-image\0";
+        let fd =
+            unsafe { libc::memfd_create(name.as_ptr() as *const libc::c_char, libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return None;
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        // The file is sparse: the pages no segment touches are read as
+        // zeroes without taking any memory.
+        file.set_len(len as u64).ok()?;
+        for (offset, data) in segments {
+            file.write_all_at(data, offset as u64).ok()?;
+        }
+        Some(Self { file, len })
+    }
+
+    /// Creates an image of the `segments`, pairs of offsets and data,
+    /// applied in order to a zeroed memory.
+    ///
+    /// Returns `None` if images aren't supported on this platform, or if
+    /// the image can't be created.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new<'a>(_segments: impl IntoIterator<Item = (usize, &'a [u8])> + Clone) -> Option<Self> {
+        None
+    }
+
+    /// Returns the length of the image in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the image is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maps the image copy-on-write at `base`, readable and writable,
+    /// replacing the pages mapped there.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be page-aligned, and the `self.len()` bytes from `base`
+    /// must be memory mapped by the caller, which then owns the mapping of
+    /// the image.
+    #[cfg(unix)]
+    pub unsafe fn map_at(&self, base: *mut u8) -> Result<(), String> {
+        use std::os::unix::io::AsRawFd;
+
+        let ptr = libc::mmap(
+            base as *mut libc::c_void,
+            self.len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_FIXED,
+            self.file.as_raw_fd(),
+            0,
+        );
+        if ptr as isize == -1_isize {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Maps the image copy-on-write at `base`, readable and writable,
+    /// replacing the pages mapped there.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be page-aligned, and the `self.len()` bytes from `base`
+    /// must be memory mapped by the caller, which then owns the mapping of
+    /// the image.
+    #[cfg(not(unix))]
+    pub unsafe fn map_at(&self, _base: *mut u8) -> Result<(), String> {
+        Err("memory images aren't supported on this platform".to_string())
+    }
+}
+
+/// The images of the local memories of a module.
+#[derive(Debug, Default)]
+pub struct MemoryImages {
+    images: PrimaryMap<LocalMemoryIndex, Option<MemoryImage>>,
+}
+
+impl MemoryImages {
+    /// Creates the images of the local memories of `module` that can have
+    /// one, given its `data_initializers`.
+    ///
+    /// A memory can have an image if it is initialized by at least one
+    /// non-empty data initializer, and if all its data initializers are at constant
+    /// offsets and within its minimum size.
+    pub fn new(module: &ModuleInfo, data_initializers: &[OwnedDataInitializer]) -> Self {
+        let images = (0..module.memories.len() - module.num_imported_memories)
+            .map(|index| {
+                let index = module.memory_index(LocalMemoryIndex::new(index));
+                let initializers = data_initializers
+                    .iter()
+                    .filter(|init| init.location.memory_index == index);
+                // Copying nothing is cheaper than mapping an empty image.
+                if initializers.clone().all(|init| init.data.is_empty()) {
+                    return None;
+                }
+                let minimum = module.memories[index].minimum.bytes().0;
+                let in_bounds = initializers.clone().all(|init| {
+                    init.location.base.is_none()
+                        && init
+                            .location
+                            .offset
+                            .checked_add(init.data.len())
+                            .map_or(false, |end| end <= minimum)
+                });
+                if !in_bounds {
+                    return None;
+                }
+                MemoryImage::new(initializers.map(|init| (init.location.offset, &*init.data)))
+            })
+            .collect();
+        Self { images }
+    }
+
+    /// Returns the image of the local memory `index`, if it has one.
+    pub fn get(&self, index: LocalMemoryIndex) -> Option<&MemoryImage> {
+        self.images.get(index).and_then(Option::as_ref)
+    }
+
+    /// Returns whether no memory has an image.
+    pub fn is_empty(&self) -> bool {
+        self.images.values().all(Option::is_none)
+    }
+}
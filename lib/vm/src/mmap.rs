@@ -12,7 +12,7 @@ use std::ptr;
 use std::slice;
 
 /// Round `size` up to the nearest multiple of `page_size`.
-fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
+pub(crate) fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
     (size + (page_size - 1)) & !(page_size - 1)
 }
 
@@ -182,7 +182,6 @@ impl Mmap {
     /// Make the memory starting at `start` and extending for `len` bytes accessible.
     /// `start` and `len` must be native page-size multiples and describe a range within
     /// `self`'s reserved memory.
-    #[cfg(not(target_os = "windows"))]
     pub fn make_accessible(&mut self, start: usize, len: usize) -> Result<(), String> {
         let page_size = region::page::size();
         assert_eq!(start & (page_size - 1), 0);
@@ -190,25 +189,38 @@ impl Mmap {
         assert_lt!(len, self.len);
         assert_lt!(start, self.len - len);
 
+        self.commit(start, len)
+    }
+
+    /// Like `make_accessible`, but the range may also extend to the end of
+    /// `self`'s reserved memory, as the last slot of an `InstancePool` does.
+    pub(crate) fn make_accessible_to_end(
+        &mut self,
+        start: usize,
+        len: usize,
+    ) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        self.commit(start, len)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn commit(&mut self, start: usize, len: usize) -> Result<(), String> {
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
         unsafe { region::protect(ptr.add(start), len, region::Protection::READ_WRITE) }
             .map_err(|e| e.to_string())
     }
 
-    /// Make the memory starting at `start` and extending for `len` bytes accessible.
-    /// `start` and `len` must be native page-size multiples and describe a range within
-    /// `self`'s reserved memory.
     #[cfg(target_os = "windows")]
-    pub fn make_accessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+    fn commit(&mut self, start: usize, len: usize) -> Result<(), String> {
         use winapi::ctypes::c_void;
         use winapi::um::memoryapi::VirtualAlloc;
         use winapi::um::winnt::{MEM_COMMIT, PAGE_READWRITE};
-        let page_size = region::page::size();
-        assert_eq!(start & (page_size - 1), 0);
-        assert_eq!(len & (page_size - 1), 0);
-        assert_lt!(len, self.len);
-        assert_lt!(start, self.len - len);
 
         // Commit the accessible size.
         let ptr = self.ptr as *const u8;
@@ -228,6 +240,90 @@ impl Mmap {
         Ok(())
     }
 
+    /// Reset the memory starting at `start` and extending for `len` bytes to
+    /// zeroes and make it inaccessible again, giving its pages back to the
+    /// OS. `start` and `len` must be native page-size multiples and describe
+    /// a range within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        let ptr = unsafe { (self.ptr as *mut u8).add(start) };
+        // On Linux, `MADV_DONTNEED` zeroes private anonymous mappings. Other
+        // systems don't guarantee it, so the range is mapped anew there.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            if libc::madvise(ptr as *mut libc::c_void, len, libc::MADV_DONTNEED) != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            region::protect(ptr, len, region::Protection::NONE).map_err(|e| e.to_string())
+        }
+        #[cfg(not(target_os = "linux"))]
+        unsafe {
+            map_anew(ptr, len)
+        }
+    }
+
+    /// Reset the memory starting at `start` and extending for `len` bytes to
+    /// zeroes and make it inaccessible again, like [`Mmap::decommit`], by
+    /// mapping it anew. Unlike `MADV_DONTNEED`, this also replaces the files
+    /// mapped over the range, such as memory images, rather than resetting
+    /// them to their contents.
+    #[cfg(not(target_os = "windows"))]
+    pub fn remap(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        unsafe { map_anew((self.ptr as *mut u8).add(start), len) }
+    }
+
+    /// Reset the memory starting at `start` and extending for `len` bytes to
+    /// zeroes and make it inaccessible again, giving its pages back to the
+    /// OS. `start` and `len` must be native page-size multiples and describe
+    /// a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Reset the memory starting at `start` and extending for `len` bytes to
+    /// zeroes and make it inaccessible again, like [`Mmap::decommit`]. Files
+    /// are never mapped over the reserved memory on Windows, so this is the
+    /// same.
+    #[cfg(target_os = "windows")]
+    pub fn remap(&mut self, start: usize, len: usize) -> Result<(), String> {
+        self.decommit(start, len)
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -288,6 +384,23 @@ impl MemoryUsage for Mmap {
     }
 }
 
+/// Maps the `len` bytes from `ptr` anew, zeroed and inaccessible.
+#[cfg(not(target_os = "windows"))]
+unsafe fn map_anew(ptr: *mut u8, len: usize) -> Result<(), String> {
+    let new_ptr = libc::mmap(
+        ptr as *mut libc::c_void,
+        len,
+        libc::PROT_NONE,
+        libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+        -1,
+        0,
+    );
+    if new_ptr as isize == -1_isize {
+        return Err(io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
 fn _assert() {
     fn _assert_send_sync<T: Send + Sync>() {}
     _assert_send_sync::<Mmap>();
@@ -304,4 +417,34 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    fn test_make_accessible_to_end() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::accessible_reserved(0, 2 * page_size).unwrap();
+        mmap.make_accessible_to_end(page_size, page_size).unwrap();
+        mmap.as_mut_slice()[2 * page_size - 1] = 1;
+        mmap.make_accessible_to_end(0, 2 * page_size).unwrap();
+        mmap.as_mut_slice()[0] = 1;
+    }
+
+    #[test]
+    fn test_decommit_and_remap_zero_the_memory() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::accessible_reserved(2 * page_size, 2 * page_size).unwrap();
+        mmap.as_mut_slice()[0] = 1;
+        mmap.as_mut_slice()[page_size] = 1;
+        mmap.decommit(0, page_size).unwrap();
+        mmap.remap(page_size, page_size).unwrap();
+        mmap.make_accessible_to_end(0, 2 * page_size).unwrap();
+        assert!(mmap.as_slice().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_make_accessible_keeps_the_end_reserved() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::accessible_reserved(0, 2 * page_size).unwrap();
+        let _ = mmap.make_accessible(page_size, page_size);
+    }
 }
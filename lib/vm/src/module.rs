@@ -14,9 +14,10 @@ use std::sync::Arc;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex,
-    FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, LocalFunctionIndex,
-    LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType, SignatureIndex,
-    TableIndex, TableInitializer, TableType,
+    FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, InstanceIndex,
+    InstanceType, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    LocalTagIndex, MemoryIndex, MemoryType, ModuleIndex, ModuleType, SignatureIndex, TableIndex,
+    TableInitializer, TableType, TagIndex, TagType,
 };
 
 #[derive(Debug, Clone)]
@@ -39,6 +40,52 @@ impl Default for ModuleId {
     }
 }
 
+/// A step needed to instantiate a module that uses the module linking
+/// proposal, before its own functions, tables, memories and globals are
+/// created.
+///
+/// The initializers are listed in the order they appear in the module, so
+/// each of them only refers to imports and to entities defined by the
+/// initializers before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ModuleInitializer {
+    /// A module defined in the module section, given as its binary.
+    DefineModule {
+        /// The index of the module in the module index space.
+        index: ModuleIndex,
+        /// The binary of the nested module.
+        binary: Arc<[u8]>,
+    },
+
+    /// An instance created by instantiating a module.
+    Instantiate {
+        /// The index of the new instance in the instance index space.
+        index: InstanceIndex,
+        /// The module being instantiated.
+        module: ModuleIndex,
+        /// The entities passed to the module, with the import names they are
+        /// given for.
+        args: Vec<(String, ExportIndex)>,
+    },
+
+    /// An export of an instance, aliased into one of the index spaces of
+    /// this module.
+    ///
+    /// Aliased functions, tables, memories, globals and tags are imports as
+    /// far as the VM is concerned: they're given to the `Resolver` with
+    /// `import_idx` as their index.
+    AliasInstanceExport {
+        /// The instance whose export is aliased.
+        instance: InstanceIndex,
+        /// The name of the aliased export.
+        export: String,
+        /// The index of the alias.
+        index: ImportIndex,
+        /// The position of the alias among the imports of the module.
+        import_idx: u32,
+    },
+}
+
 /// A translated WebAssembly module, excluding the function bodies and
 /// memory initializers.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +143,22 @@ pub struct ModuleInfo {
     /// WebAssembly global variables (imported and local).
     pub globals: PrimaryMap<GlobalIndex, GlobalType>,
 
+    /// WebAssembly exception tags (imported and local), with the signature
+    /// describing the values carried by their exceptions.
+    pub tags: PrimaryMap<TagIndex, SignatureIndex>,
+
+    /// WebAssembly modules (imported, nested and aliased), for the module
+    /// linking proposal.
+    pub modules: PrimaryMap<ModuleIndex, ModuleType>,
+
+    /// WebAssembly instances (imported, instantiated and aliased), for the
+    /// module linking proposal.
+    pub instances: PrimaryMap<InstanceIndex, InstanceType>,
+
+    /// The steps that define the nested modules, instances and aliases of
+    /// the module, for the module linking proposal.
+    pub initializers: Vec<ModuleInitializer>,
+
     /// Custom sections in the module.
     pub custom_sections: IndexMap<String, CustomSectionIndex>,
 
@@ -113,6 +176,9 @@ pub struct ModuleInfo {
 
     /// Number of imported globals in the module.
     pub num_imported_globals: usize,
+
+    /// Number of imported exception tags in the module.
+    pub num_imported_tags: usize,
 }
 
 impl ModuleInfo {
@@ -134,10 +200,15 @@ impl ModuleInfo {
             tables: PrimaryMap::new(),
             memories: PrimaryMap::new(),
             globals: PrimaryMap::new(),
+            tags: PrimaryMap::new(),
+            modules: PrimaryMap::new(),
+            instances: PrimaryMap::new(),
+            initializers: Vec::new(),
             num_imported_functions: 0,
             num_imported_tables: 0,
             num_imported_memories: 0,
             num_imported_globals: 0,
+            num_imported_tags: 0,
             custom_sections: IndexMap::new(),
             custom_sections_data: PrimaryMap::new(),
         }
@@ -184,6 +255,9 @@ impl ModuleInfo {
                     let global_type = self.globals.get(*i).unwrap();
                     ExternType::Global(*global_type)
                 }
+                ExportIndex::Tag(i) => ExternType::Tag(self.tag_type(*i)),
+                ExportIndex::Module(i) => ExternType::Module(self.modules[*i].clone()),
+                ExportIndex::Instance(i) => ExternType::Instance(self.instances[*i].clone()),
             };
             ExportType::new(name, extern_type)
         });
@@ -217,6 +291,9 @@ impl ModuleInfo {
                         let global_type = self.globals.get(*i).unwrap();
                         ExternType::Global(*global_type)
                     }
+                    ImportIndex::Tag(i) => ExternType::Tag(self.tag_type(*i)),
+                    ImportIndex::Module(i) => ExternType::Module(self.modules[*i].clone()),
+                    ImportIndex::Instance(i) => ExternType::Instance(self.instances[*i].clone()),
                 };
                 ImportType::new(module, field, extern_type)
             });
@@ -313,6 +390,37 @@ impl ModuleInfo {
         index.index() < self.num_imported_globals
     }
 
+    /// Convert a `LocalTagIndex` into a `TagIndex`.
+    pub fn tag_index(&self, local_tag: LocalTagIndex) -> TagIndex {
+        TagIndex::new(self.num_imported_tags + local_tag.index())
+    }
+
+    /// Convert a `TagIndex` into a `LocalTagIndex`. Returns None if the
+    /// index is an imported tag.
+    pub fn local_tag_index(&self, tag: TagIndex) -> Option<LocalTagIndex> {
+        tag.index()
+            .checked_sub(self.num_imported_tags)
+            .map(LocalTagIndex::new)
+    }
+
+    /// Test whether the given tag index is for an imported tag.
+    pub fn is_imported_tag(&self, index: TagIndex) -> bool {
+        index.index() < self.num_imported_tags
+    }
+
+    /// Get the type of the given exception tag.
+    pub fn tag_type(&self, index: TagIndex) -> TagType {
+        let signature = self.tags[index];
+        TagType::new(self.signatures[signature].params())
+    }
+
+    /// Test whether instantiating the module involves the module linking
+    /// proposal, that is whether it imports modules or instances, or has
+    /// initializers.
+    pub fn uses_module_linking(&self) -> bool {
+        !self.modules.is_empty() || !self.instances.is_empty() || !self.initializers.is_empty()
+    }
+
     /// Get the Module name
     pub fn name(&self) -> String {
         match self.name {
@@ -382,6 +490,13 @@ impl<I: Iterator<Item = ExportType> + Sized> ExportsIterator<I> {
             _ => None,
         })
     }
+    /// Get only the tags
+    pub fn tags(self) -> impl Iterator<Item = ExportType<TagType>> + Sized {
+        self.iter.filter_map(|extern_| match extern_.ty() {
+            ExternType::Tag(ty) => Some(ExportType::new(extern_.name(), ty.clone())),
+            _ => None,
+        })
+    }
 }
 
 impl<I: Iterator<Item = ExportType> + Sized> Iterator for ExportsIterator<I> {
@@ -438,6 +553,17 @@ impl<I: Iterator<Item = ImportType> + Sized> ImportsIterator<I> {
             _ => None,
         })
     }
+    /// Get only the tags
+    pub fn tags(self) -> impl Iterator<Item = ImportType<TagType>> + Sized {
+        self.iter.filter_map(|extern_| match extern_.ty() {
+            ExternType::Tag(ty) => Some(ImportType::new(
+                extern_.module(),
+                extern_.name(),
+                ty.clone(),
+            )),
+            _ => None,
+        })
+    }
 }
 
 impl<I: Iterator<Item = ImportType> + Sized> Iterator for ImportsIterator<I> {
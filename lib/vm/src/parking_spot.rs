@@ -0,0 +1,172 @@
+//! Futex-style waiting and notification for `memory.atomic.wait32`,
+//! `memory.atomic.wait64` and `memory.atomic.notify`.
+//!
+//! Waiters are keyed by the host address of the value they wait on. Shared
+//! memories never move, so the same address designates the same location in
+//! every instance the memory is imported into.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The result of a wait operation, as returned to WebAssembly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub(crate) enum WaitResult {
+    /// The waiter was woken up by a notification.
+    Ok = 0,
+    /// The loaded value didn't match the expected value.
+    Mismatch = 1,
+    /// The timeout expired before the waiter was notified.
+    TimedOut = 2,
+}
+
+#[derive(Default)]
+struct Spot {
+    /// Used to wake up the threads waiting on this spot.
+    condvar: Arc<Condvar>,
+    /// The number of threads waiting on this spot.
+    num_waiters: u32,
+    /// The number of waiters that have been notified but haven't woken up yet.
+    to_unpark: u32,
+}
+
+/// A set of waiting queues, keyed by address.
+#[derive(Default)]
+pub(crate) struct ParkingSpot {
+    spots: Mutex<HashMap<usize, Spot>>,
+}
+
+lazy_static::lazy_static! {
+    /// The parking spot shared by all the memories of the process.
+    static ref PARKING_SPOT: ParkingSpot = ParkingSpot::default();
+}
+
+impl ParkingSpot {
+    /// Returns the parking spot shared by all the memories of the process.
+    pub(crate) fn global() -> &'static Self {
+        &PARKING_SPOT
+    }
+
+    /// Parks the current thread on `addr` until it is notified or `timeout`
+    /// expires, if `validate` returns `true`.
+    ///
+    /// `validate` runs while holding the lock used by `notify`, so that a
+    /// notification can't be missed between the check and the wait.
+    pub(crate) fn wait(
+        &self,
+        addr: usize,
+        validate: impl FnOnce() -> bool,
+        timeout: Option<Duration>,
+    ) -> WaitResult {
+        let mut spots = self.spots.lock().unwrap();
+        if !validate() {
+            return WaitResult::Mismatch;
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let condvar = {
+            let spot = spots.entry(addr).or_default();
+            spot.num_waiters += 1;
+            spot.condvar.clone()
+        };
+
+        loop {
+            let spot = spots.get_mut(&addr).unwrap();
+            if spot.to_unpark > 0 {
+                spot.to_unpark -= 1;
+                Self::leave(&mut spots, addr);
+                return WaitResult::Ok;
+            }
+            match deadline {
+                None => spots = condvar.wait(spots).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        Self::leave(&mut spots, addr);
+                        return WaitResult::TimedOut;
+                    }
+                    spots = condvar.wait_timeout(spots, deadline - now).unwrap().0;
+                }
+            }
+        }
+    }
+
+    /// Wakes up at most `count` threads parked on `addr`, and returns the
+    /// number of threads that were woken up.
+    pub(crate) fn notify(&self, addr: usize, count: u32) -> u32 {
+        let mut spots = self.spots.lock().unwrap();
+        match spots.get_mut(&addr) {
+            Some(spot) => {
+                let count = count.min(spot.num_waiters - spot.to_unpark);
+                if count > 0 {
+                    spot.to_unpark += count;
+                    spot.condvar.notify_all();
+                }
+                count
+            }
+            None => 0,
+        }
+    }
+
+    /// Removes the current thread from the waiters of `addr`.
+    fn leave(spots: &mut HashMap<usize, Spot>, addr: usize) {
+        let spot = spots.get_mut(&addr).unwrap();
+        spot.num_waiters -= 1;
+        if spot.num_waiters == 0 {
+            spots.remove(&addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+
+    #[test]
+    fn wait_mismatch_and_timeout() {
+        let spot = ParkingSpot::default();
+        assert_eq!(spot.wait(0, || false, None), WaitResult::Mismatch);
+        assert_eq!(
+            spot.wait(0, || true, Some(Duration::from_millis(1))),
+            WaitResult::TimedOut
+        );
+        assert_eq!(spot.notify(0, 1), 0);
+    }
+
+    #[test]
+    fn notify_wakes_up_waiters() {
+        let spot = Arc::new(ParkingSpot::default());
+        let parked = Arc::new(AtomicU32::new(0));
+        let threads = (0..2)
+            .map(|_| {
+                let spot = spot.clone();
+                let parked = parked.clone();
+                thread::spawn(move || {
+                    spot.wait(
+                        8,
+                        || {
+                            parked.fetch_add(1, Ordering::SeqCst);
+                            true
+                        },
+                        None,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut woken = 0;
+        while woken < 2 {
+            if parked.load(Ordering::SeqCst) == 2 {
+                woken += spot.notify(8, 1);
+            }
+            thread::yield_now();
+        }
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), WaitResult::Ok);
+        }
+        assert_eq!(spot.notify(8, 1), 0);
+    }
+}
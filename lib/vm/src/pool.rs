@@ -0,0 +1,587 @@
+//! Pooling allocation of instances, memories and tables.
+//!
+//! An [`InstancePool`] reserves the address space of a fixed number of
+//! instances, linear memories and tables up front. Instantiating a module
+//! then takes free slots from the pool instead of allocating, and dropping
+//! the instance resets its slots and gives them back to the pool.
+
+use crate::memory::{Memory, MemoryError, MemoryStyle};
+use crate::memory_image::MemoryImage;
+use crate::mmap::{round_up_to_page_size, Mmap};
+use crate::table::{Table, TableStyle};
+use crate::trap::{Trap, TrapCode};
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMMemoryDefinition, VMTableDefinition};
+use std::alloc::Layout;
+use std::cmp::min;
+use std::convert::TryFrom;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer_types::{Bytes, MemoryType, Pages, TableType, Type as ValType};
+
+/// The limits of an [`InstancePool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolingLimits {
+    /// The number of instances that can be alive at once.
+    pub instances: u32,
+
+    /// The maximum size in bytes of an instance, including its `VMContext`.
+    pub instance_size: usize,
+
+    /// The number of local memories that can be alive at once, across all
+    /// the instances.
+    pub memories: u32,
+
+    /// The maximum number of pages of each memory.
+    pub memory_pages: Pages,
+
+    /// The number of local tables that can be alive at once, across all
+    /// the instances.
+    pub tables: u32,
+
+    /// The maximum number of elements of each table.
+    pub table_elements: u32,
+}
+
+impl Default for PoolingLimits {
+    fn default() -> Self {
+        Self {
+            instances: 1000,
+            instance_size: 0x10_0000,
+            memories: 1000,
+            memory_pages: Pages(160),
+            tables: 1000,
+            table_elements: 10_000,
+        }
+    }
+}
+
+/// Slots of the same size in a single reservation of address space.
+///
+/// Slots are inaccessible while free. Taking one makes its beginning
+/// accessible, and giving it back resets it to zeroes and makes it
+/// inaccessible again.
+#[derive(Debug)]
+struct SlotPool {
+    /// The address of the first slot.
+    base: usize,
+    slot_size: usize,
+    count: usize,
+    /// What the slots hold, for error messages.
+    kind: &'static str,
+    state: Mutex<SlotPoolState>,
+}
+
+#[derive(Debug)]
+struct SlotPoolState {
+    mmap: Mmap,
+    /// The free slots, the most recently freed one last.
+    free: Vec<usize>,
+}
+
+impl SlotPool {
+    fn new(kind: &'static str, count: u32, slot_size: usize) -> Result<Self, String> {
+        let count = usize::try_from(count).unwrap();
+        let slot_size = round_up_to_page_size(slot_size, region::page::size());
+        let len = slot_size
+            .checked_mul(count)
+            .ok_or_else(|| format!("the {} slots don't fit in the address space", kind))?;
+        let mut mmap = Mmap::accessible_reserved(0, len)?;
+        Ok(Self {
+            base: mmap.as_mut_ptr() as usize,
+            slot_size,
+            count,
+            kind,
+            state: Mutex::new(SlotPoolState {
+                mmap,
+                free: (0..count).rev().collect(),
+            }),
+        })
+    }
+
+    /// Takes a free slot, making its first `accessible` bytes accessible.
+    fn take(&self, accessible: usize) -> Result<usize, String> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.free.pop().ok_or_else(|| {
+            format!(
+                "all the {} {} slots of the pool are in use",
+                self.count, self.kind
+            )
+        })?;
+        if let Err(message) = self.make_accessible(&mut state, index, 0, accessible) {
+            state.free.push(index);
+            return Err(message);
+        }
+        Ok(index)
+    }
+
+    /// Makes `len` more bytes from `start` in the slot `index` accessible.
+    fn grow(&self, index: usize, start: usize, len: usize) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        self.make_accessible(&mut state, index, start, len)
+    }
+
+    fn make_accessible(
+        &self,
+        state: &mut SlotPoolState,
+        index: usize,
+        start: usize,
+        len: usize,
+    ) -> Result<(), String> {
+        let page_size = region::page::size();
+        let end = round_up_to_page_size(start + len, page_size);
+        let start = start & !(page_size - 1);
+        assert!(end <= self.slot_size);
+        if end == start {
+            return Ok(());
+        }
+        state
+            .mmap
+            .make_accessible_to_end(index * self.slot_size + start, end - start)
+    }
+
+    /// Gives the slot `index`, of which the first `used` bytes may have been
+    /// made accessible, back to the pool.
+    ///
+    /// The first `mapped` bytes, which have a file such as a memory image
+    /// mapped over them, are mapped anew rather than just decommitted.
+    fn give_back(&self, index: usize, used: usize, mapped: usize) {
+        let used = min(
+            round_up_to_page_size(used, region::page::size()),
+            self.slot_size,
+        );
+        let mapped = min(mapped, used);
+        let start = index * self.slot_size;
+        let mut state = self.state.lock().unwrap();
+        let reset = state
+            .mmap
+            .remap(start, mapped)
+            .and_then(|()| state.mmap.decommit(start + mapped, used - mapped));
+        // A slot that can't be reset is leaked rather than reused.
+        if reset.is_ok() {
+            state.free.push(index);
+        }
+    }
+
+    fn slot_ptr(&self, index: usize) -> *mut u8 {
+        (self.base + index * self.slot_size) as *mut u8
+    }
+}
+
+/// A pool of preallocated instances, linear memories and tables.
+///
+/// Each memory slot reserves the address space the memory styles of the
+/// tunables using the pool require, so that memories never move: a memory
+/// can grow in place up to its maximum, within `memory_pages`.
+#[derive(Debug)]
+pub struct InstancePool {
+    limits: PoolingLimits,
+    instances: SlotPool,
+    memories: SlotPool,
+    tables: SlotPool,
+}
+
+impl InstancePool {
+    /// Reserves the address space of a pool with the given `limits`, with
+    /// `memory_reservation` bytes for each memory, guard pages included.
+    pub fn new(limits: PoolingLimits, memory_reservation: usize) -> Result<Self, String> {
+        if limits.memory_pages.bytes().0 > memory_reservation {
+            return Err(format!(
+                "memories of {} pages don't fit in a reservation of {} bytes",
+                limits.memory_pages.0, memory_reservation
+            ));
+        }
+        let table_size = usize::try_from(limits.table_elements)
+            .ok()
+            .and_then(|elements| elements.checked_mul(mem::size_of::<VMCallerCheckedAnyfunc>()))
+            .ok_or_else(|| "the tables don't fit in the address space".to_string())?;
+        Ok(Self {
+            limits,
+            instances: SlotPool::new("instance", limits.instances, limits.instance_size)?,
+            memories: SlotPool::new("memory", limits.memories, memory_reservation)?,
+            tables: SlotPool::new("table", limits.tables, table_size)?,
+        })
+    }
+
+    /// Returns the limits of the pool.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.limits
+    }
+
+    /// Takes an instance slot for an instance of the given `layout`,
+    /// returning its index and its address.
+    pub(crate) fn take_instance(&self, layout: Layout) -> Result<(usize, NonNull<u8>), String> {
+        if layout.size() > self.limits.instance_size {
+            return Err(format!(
+                "the instance takes {} bytes, more than the {} bytes of the slots of the pool",
+                layout.size(),
+                self.limits.instance_size
+            ));
+        }
+        assert!(layout.align() <= region::page::size());
+        let index = self.instances.take(layout.size())?;
+        Ok((index, NonNull::new(self.instances.slot_ptr(index)).unwrap()))
+    }
+
+    /// Gives the instance slot `index`, holding an instance of the given
+    /// `layout`, back to the pool.
+    ///
+    /// # Safety
+    ///
+    /// The instance in the slot must have been dropped.
+    pub(crate) unsafe fn give_back_instance(&self, index: usize, layout: Layout) {
+        self.instances.give_back(index, layout.size(), 0);
+    }
+
+    /// Creates a local memory in a memory slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_memory_location` must point to a valid location in VM memory.
+    pub unsafe fn create_memory(
+        self: &Arc<Self>,
+        memory: &MemoryType,
+        style: &MemoryStyle,
+        vm_memory_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn Memory>, MemoryError> {
+        let offset_guard_size = usize::try_from(style.offset_guard_size()).unwrap();
+        // Static memories must have the address space of their bound, and
+        // dynamic ones can't move, so they can only grow up to the
+        // reservation.
+        let bound = match style {
+            MemoryStyle::Static { bound, .. } => Some(bound.bytes().0),
+            MemoryStyle::Dynamic { .. } => None,
+        };
+        let capacity = match self.memories.slot_size.checked_sub(offset_guard_size) {
+            Some(capacity) if bound.map_or(true, |bound| bound <= capacity) => capacity,
+            _ => {
+                return Err(MemoryError::InvalidMemory {
+                    reason: "its memory style doesn't fit in the slots of the instance pool"
+                        .to_string(),
+                })
+            }
+        };
+        let capacity = Pages::try_from(Bytes(capacity)).unwrap_or_else(|_| Pages::max_value());
+        let maximum = memory
+            .maximum
+            .unwrap_or_else(|| memory.max_pages())
+            .min(memory.max_pages())
+            .min(self.limits.memory_pages)
+            .min(capacity);
+        if memory.minimum > maximum {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: memory.minimum,
+                max_allowed: maximum,
+            });
+        }
+
+        let index = self
+            .memories
+            .take(memory.minimum.bytes().0)
+            .map_err(MemoryError::Region)?;
+        let mut definition = vm_memory_location;
+        *definition.as_mut() = VMMemoryDefinition {
+            base: self.memories.slot_ptr(index),
+            current_length: memory.minimum.bytes().0,
+        };
+        Ok(Arc::new(PooledMemory {
+            pool: self.clone(),
+            index,
+            size: Mutex::new(memory.minimum),
+            image_len: AtomicUsize::new(0),
+            maximum,
+            memory: *memory,
+            style: style.clone(),
+            vm_memory_definition: vm_memory_location,
+        }))
+    }
+
+    /// Creates a local table in a table slot of the pool.
+    ///
+    /// # Safety
+    /// - `vm_table_location` must point to a valid location in VM memory.
+    pub unsafe fn create_table(
+        self: &Arc<Self>,
+        table: &TableType,
+        style: &TableStyle,
+        vm_table_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn Table>, String> {
+        match table.ty {
+            ValType::FuncRef => (),
+            ty => return Err(format!("tables of types other than anyfunc ({})", ty)),
+        };
+        let maximum = table.maximum.map_or(self.limits.table_elements, |maximum| {
+            min(maximum, self.limits.table_elements)
+        });
+        if table.minimum > maximum {
+            return Err(format!(
+                "Table minimum ({}) is larger than the maximum allowed ({})",
+                table.minimum, maximum
+            ));
+        }
+
+        let index = self.tables.take(elements_size(table.minimum))?;
+        let base = self.tables.slot_ptr(index) as *mut VMCallerCheckedAnyfunc;
+        for i in 0..table.minimum as usize {
+            base.add(i).write(VMCallerCheckedAnyfunc::default());
+        }
+        let mut definition = vm_table_location;
+        *definition.as_mut() = VMTableDefinition {
+            base: base as _,
+            current_elements: table.minimum,
+        };
+        Ok(Arc::new(PooledTable {
+            pool: self.clone(),
+            index,
+            size: Mutex::new(table.minimum),
+            maximum,
+            table: *table,
+            style: style.clone(),
+            vm_table_definition: vm_table_location,
+        }))
+    }
+}
+
+/// The size in bytes of `elements` table elements.
+fn elements_size(elements: u32) -> usize {
+    elements as usize * mem::size_of::<VMCallerCheckedAnyfunc>()
+}
+
+/// A linear memory in a memory slot of an [`InstancePool`].
+#[derive(Debug)]
+struct PooledMemory {
+    pool: Arc<InstancePool>,
+    index: usize,
+    /// The current size, locked while growing.
+    size: Mutex<Pages>,
+    /// The length of the memory image mapped over the beginning of the
+    /// slot, if any.
+    image_len: AtomicUsize,
+    maximum: Pages,
+    memory: MemoryType,
+    style: MemoryStyle,
+    vm_memory_definition: NonNull<VMMemoryDefinition>,
+}
+
+/// This is correct for the same reasons as for `LinearMemory`.
+unsafe impl Send for PooledMemory {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledMemory {}
+
+impl Memory for PooledMemory {
+    fn ty(&self) -> &MemoryType {
+        &self.memory
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        &self.style
+    }
+
+    fn size(&self) -> Pages {
+        *self.size.lock().unwrap()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut size = self.size.lock().unwrap();
+        let prev_pages = *size;
+        let new_pages = match prev_pages.0.checked_add(delta.0) {
+            Some(new_pages) if Pages(new_pages) <= self.maximum => Pages(new_pages),
+            _ => {
+                return Err(MemoryError::CouldNotGrow {
+                    current: prev_pages,
+                    attempted_delta: delta,
+                })
+            }
+        };
+        self.pool
+            .memories
+            .grow(self.index, prev_pages.bytes().0, delta.bytes().0)
+            .map_err(MemoryError::Region)?;
+        *size = new_pages;
+        unsafe {
+            let mut definition = self.vm_memory_definition;
+            definition.as_mut().current_length = new_pages.bytes().0;
+        }
+        Ok(prev_pages)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.vm_memory_definition
+    }
+
+    fn map_image(&self, image: &MemoryImage) -> Result<bool, MemoryError> {
+        let size = self.size.lock().unwrap();
+        if image.len() > size.bytes().0 {
+            return Ok(false);
+        }
+        unsafe { image.map_at(self.pool.memories.slot_ptr(self.index)) }
+            .map_err(MemoryError::Region)?;
+        // Giving the slot back maps it anew there, which unmaps the image.
+        self.image_len.store(image.len(), Ordering::SeqCst);
+        Ok(true)
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        let size = *self.size.get_mut().unwrap();
+        let image_len = *self.image_len.get_mut();
+        self.pool
+            .memories
+            .give_back(self.index, size.bytes().0, image_len);
+    }
+}
+
+/// A table in a table slot of an [`InstancePool`].
+#[derive(Debug)]
+struct PooledTable {
+    pool: Arc<InstancePool>,
+    index: usize,
+    /// The current number of elements, locked while accessing them.
+    size: Mutex<u32>,
+    maximum: u32,
+    table: TableType,
+    style: TableStyle,
+    vm_table_definition: NonNull<VMTableDefinition>,
+}
+
+/// This is correct because there is no thread-specific data tied to this type.
+unsafe impl Send for PooledTable {}
+/// This is correct because all internal mutability is protected by a mutex.
+unsafe impl Sync for PooledTable {}
+
+impl PooledTable {
+    fn elements(&self) -> *mut VMCallerCheckedAnyfunc {
+        self.pool.tables.slot_ptr(self.index) as *mut VMCallerCheckedAnyfunc
+    }
+}
+
+impl Table for PooledTable {
+    fn style(&self) -> &TableStyle {
+        &self.style
+    }
+
+    fn ty(&self) -> &TableType {
+        &self.table
+    }
+
+    fn size(&self) -> u32 {
+        *self.size.lock().unwrap()
+    }
+
+    fn grow(&self, delta: u32) -> Option<u32> {
+        let mut size = self.size.lock().unwrap();
+        let prev_size = *size;
+        let new_size = prev_size.checked_add(delta)?;
+        if new_size > self.maximum {
+            return None;
+        }
+        self.pool
+            .tables
+            .grow(self.index, elements_size(prev_size), elements_size(delta))
+            .ok()?;
+        let elements = self.elements();
+        for i in prev_size..new_size {
+            unsafe {
+                elements
+                    .add(i as usize)
+                    .write(VMCallerCheckedAnyfunc::default())
+            };
+        }
+        *size = new_size;
+        unsafe {
+            let mut definition = self.vm_table_definition;
+            definition.as_mut().current_elements = new_size;
+        }
+        Some(prev_size)
+    }
+
+    fn get(&self, index: u32) -> Option<VMCallerCheckedAnyfunc> {
+        let size = self.size.lock().unwrap();
+        if index >= *size {
+            return None;
+        }
+        Some(unsafe { (*self.elements().add(index as usize)).clone() })
+    }
+
+    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap> {
+        let size = self.size.lock().unwrap();
+        if index >= *size {
+            return Err(Trap::new_from_runtime(TrapCode::TableAccessOutOfBounds));
+        }
+        unsafe { *self.elements().add(index as usize) = func };
+        Ok(())
+    }
+
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.vm_table_definition
+    }
+}
+
+impl Drop for PooledTable {
+    fn drop(&mut self) {
+        let size = *self.size.get_mut().unwrap();
+        self.pool
+            .tables
+            .give_back(self.index, elements_size(size), 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> PoolingLimits {
+        PoolingLimits {
+            instances: 2,
+            instance_size: 0x1000,
+            memories: 1,
+            memory_pages: Pages(2),
+            tables: 1,
+            table_elements: 10,
+        }
+    }
+
+    fn memory_definition() -> NonNull<VMMemoryDefinition> {
+        NonNull::from(Box::leak(Box::new(VMMemoryDefinition {
+            base: std::ptr::null_mut(),
+            current_length: 0,
+        })))
+    }
+
+    #[test]
+    fn memory_slots_are_reset_and_reused() {
+        let pool = Arc::new(InstancePool::new(limits(), 0x4_0000).unwrap());
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0x1_0000,
+        };
+        let ty = MemoryType::new(1, None, false);
+        let location = memory_definition();
+
+        let memory = unsafe { pool.create_memory(&ty, &style, location) }.unwrap();
+        let base = unsafe { location.as_ref().base };
+        unsafe { *base = 42 };
+        assert_eq!(memory.grow(Pages(1)).unwrap(), Pages(1));
+        assert_eq!(unsafe { location.as_ref().current_length }, 0x2_0000);
+        // The memory can't grow beyond `memory_pages`, and there are no
+        // memory slots left.
+        assert!(memory.grow(Pages(1)).is_err());
+        assert!(unsafe { pool.create_memory(&ty, &style, memory_definition()) }.is_err());
+
+        drop(memory);
+        let _memory = unsafe { pool.create_memory(&ty, &style, location) }.unwrap();
+        assert_eq!(unsafe { location.as_ref().base }, base);
+        assert_eq!(unsafe { *base }, 0);
+    }
+
+    #[test]
+    fn memories_must_fit_in_slots() {
+        let pool = Arc::new(InstancePool::new(limits(), 0x4_0000).unwrap());
+        let style = MemoryStyle::Static {
+            bound: Pages(0x1_0000),
+            offset_guard_size: 0,
+        };
+        let ty = MemoryType::new(1, None, false);
+        assert!(unsafe { pool.create_memory(&ty, &style, memory_definition()) }.is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Limiting the resources used by memories and tables.
+//!
+//! A [`ResourceLimiter`] is consulted before a memory or a table is
+//! created, and whenever one wrapped in a [`LimitedMemory`] or a
+//! [`LimitedTable`] grows, and can deny it. It is also told when they are dropped, so that it can keep
+//! track of the resources in use across many instances.
+
+use crate::memory::{Memory, MemoryError, MemoryStyle};
+use crate::memory_image::MemoryImage;
+use crate::table::{Table, TableStyle};
+use crate::trap::Trap;
+use crate::vmcontext::{VMCallerCheckedAnyfunc, VMMemoryDefinition, VMTableDefinition};
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use wasmer_types::{MemoryType, Pages, TableType};
+
+/// Hooks deciding whether memories and tables can be created and grow.
+///
+/// Creating a memory or a table counts as growing it from zero to its
+/// initial size. If it then fails to be created, the limiter is told that
+/// it failed to grow.
+pub trait ResourceLimiter: Send + Sync {
+    /// Returns whether a memory can grow from `current` to `desired`
+    /// pages, `maximum` being the maximum of its type.
+    fn memory_growing(&self, current: Pages, desired: Pages, maximum: Option<Pages>) -> bool;
+
+    /// Called when a memory fails to grow after `memory_growing` allowed
+    /// it.
+    fn memory_grow_failed(&self, _error: &MemoryError) {}
+
+    /// Called when a memory of `size` pages is dropped.
+    fn memory_dropped(&self, _size: Pages) {}
+
+    /// Returns whether a table can grow from `current` to `desired`
+    /// elements, `maximum` being the maximum of its type.
+    fn table_growing(&self, current: u32, desired: u32, maximum: Option<u32>) -> bool;
+
+    /// Called when a table fails to grow after `table_growing` allowed it.
+    fn table_grow_failed(&self) {}
+
+    /// Called when a table of `size` elements is dropped.
+    fn table_dropped(&self, _size: u32) {}
+}
+
+/// A memory whose growth is limited by a [`ResourceLimiter`].
+pub struct LimitedMemory {
+    memory: Arc<dyn Memory>,
+    limiter: Arc<dyn ResourceLimiter>,
+    /// The size of the memory, locked while growing so that the limiter
+    /// sees the growths one at a time.
+    size: Mutex<Pages>,
+}
+
+impl LimitedMemory {
+    /// Asks `limiter` whether a memory of type `ty` can be created, before
+    /// it is allocated.
+    pub fn check_creation(
+        limiter: &dyn ResourceLimiter,
+        ty: &MemoryType,
+    ) -> Result<(), MemoryError> {
+        if !limiter.memory_growing(Pages(0), ty.minimum, ty.maximum) {
+            return Err(MemoryError::Generic(
+                "the resource limiter denied the creation of the memory".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Wraps the just created `memory`, whose creation `limiter` allowed.
+    pub fn new(memory: Arc<dyn Memory>, limiter: Arc<dyn ResourceLimiter>) -> Self {
+        let size = memory.ty().minimum;
+        Self {
+            memory,
+            limiter,
+            size: Mutex::new(size),
+        }
+    }
+}
+
+impl fmt::Debug for LimitedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LimitedMemory")
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+impl Memory for LimitedMemory {
+    fn ty(&self) -> &MemoryType {
+        self.memory.ty()
+    }
+
+    fn style(&self) -> &MemoryStyle {
+        self.memory.style()
+    }
+
+    fn size(&self) -> Pages {
+        self.memory.size()
+    }
+
+    fn grow(&self, delta: Pages) -> Result<Pages, MemoryError> {
+        let mut size = self.size.lock().unwrap();
+        let current = *size;
+        let desired = match current.0.checked_add(delta.0) {
+            Some(desired) if delta.0 > 0 => Pages(desired),
+            // Growing by nothing, or beyond what pages can count, is left
+            // to the memory.
+            _ => return self.memory.grow(delta),
+        };
+        if !self
+            .limiter
+            .memory_growing(current, desired, self.memory.ty().maximum)
+        {
+            return Err(MemoryError::CouldNotGrow {
+                current,
+                attempted_delta: delta,
+            });
+        }
+        let previous = self.memory.grow(delta).map_err(|error| {
+            self.limiter.memory_grow_failed(&error);
+            error
+        })?;
+        *size = desired;
+        Ok(previous)
+    }
+
+    fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
+        self.memory.vmmemory()
+    }
+
+    fn map_image(&self, image: &MemoryImage) -> Result<bool, MemoryError> {
+        self.memory.map_image(image)
+    }
+}
+
+impl Drop for LimitedMemory {
+    fn drop(&mut self) {
+        self.limiter.memory_dropped(*self.size.get_mut().unwrap());
+    }
+}
+
+/// A table whose growth is limited by a [`ResourceLimiter`].
+pub struct LimitedTable {
+    table: Arc<dyn Table>,
+    limiter: Arc<dyn ResourceLimiter>,
+    /// The size of the table, locked while growing.
+    size: Mutex<u32>,
+}
+
+impl LimitedTable {
+    /// Asks `limiter` whether a table of type `ty` can be created, before
+    /// it is allocated.
+    pub fn check_creation(limiter: &dyn ResourceLimiter, ty: &TableType) -> Result<(), String> {
+        if !limiter.table_growing(0, ty.minimum, ty.maximum) {
+            return Err("the resource limiter denied the creation of the table".to_string());
+        }
+        Ok(())
+    }
+
+    /// Wraps the just created `table`, whose creation `limiter` allowed.
+    pub fn new(table: Arc<dyn Table>, limiter: Arc<dyn ResourceLimiter>) -> Self {
+        let size = table.ty().minimum;
+        Self {
+            table,
+            limiter,
+            size: Mutex::new(size),
+        }
+    }
+}
+
+impl fmt::Debug for LimitedTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LimitedTable")
+            .field("table", &self.table)
+            .finish()
+    }
+}
+
+impl Table for LimitedTable {
+    fn style(&self) -> &TableStyle {
+        self.table.style()
+    }
+
+    fn ty(&self) -> &TableType {
+        self.table.ty()
+    }
+
+    fn size(&self) -> u32 {
+        self.table.size()
+    }
+
+    fn grow(&self, delta: u32) -> Option<u32> {
+        let mut size = self.size.lock().unwrap();
+        let current = *size;
+        let desired = match current.checked_add(delta) {
+            Some(desired) if delta > 0 => desired,
+            _ => return self.table.grow(delta),
+        };
+        if !self
+            .limiter
+            .table_growing(current, desired, self.table.ty().maximum)
+        {
+            return None;
+        }
+        let previous = self.table.grow(delta);
+        match previous {
+            Some(_) => *size = desired,
+            None => self.limiter.table_grow_failed(),
+        }
+        previous
+    }
+
+    fn get(&self, index: u32) -> Option<VMCallerCheckedAnyfunc> {
+        self.table.get(index)
+    }
+
+    fn set(&self, index: u32, func: VMCallerCheckedAnyfunc) -> Result<(), Trap> {
+        self.table.set(index, func)
+    }
+
+    fn vmtable(&self) -> NonNull<VMTableDefinition> {
+        self.table.vmtable()
+    }
+
+    fn copy(
+        &self,
+        src_table: &dyn Table,
+        dst_index: u32,
+        src_index: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        self.table.copy(src_table, dst_index, src_index, len)
+    }
+}
+
+impl Drop for LimitedTable {
+    fn drop(&mut self) {
+        self.limiter.table_dropped(*self.size.get_mut().unwrap());
+    }
+}
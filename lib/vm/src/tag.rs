@@ -0,0 +1,110 @@
+//! Exception tags, and the exceptions thrown with them.
+//!
+//! Exceptions are thrown and caught with the trap machinery rather than by
+//! unwinding the frames of the compiled code with the unwind registries of
+//! the engines: Cranelift can't generate landing pads, and relying on the
+//! traps lets both compilers share the same runtime support. Only the calls
+//! made within a `try` block pay for it, as they go through
+//! `wasmer_invoke`; `benches/exceptions.rs` measures how much.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use wasmer_types::TagType;
+
+/// An exception tag instance.
+///
+/// Tags are compared by identity: an exception thrown with a tag is only
+/// caught by the `catch` clauses referring to the very same tag, be it
+/// defined by the module or imported.
+#[derive(Debug)]
+pub struct Tag {
+    ty: TagType,
+}
+
+impl Tag {
+    /// Create a new tag from a [`TagType`].
+    pub fn new(ty: TagType) -> Self {
+        Self { ty }
+    }
+
+    /// Get the type of the tag.
+    pub fn ty(&self) -> &TagType {
+        &self.ty
+    }
+}
+
+/// A WebAssembly exception, as thrown by `throw`.
+///
+/// Exceptions are propagated to their handler as user traps, so an exception
+/// that isn't caught by WebAssembly code ends up in the `RuntimeError`
+/// returned to the host.
+#[derive(Debug, Clone)]
+pub struct VMException {
+    inner: Arc<VMExceptionInner>,
+}
+
+#[derive(Debug)]
+struct VMExceptionInner {
+    tag: Arc<Tag>,
+    /// The values carried by the exception, each one stored in a 128-bit
+    /// slot, like the values passed to trampolines.
+    payload: Box<[u128]>,
+}
+
+impl VMException {
+    /// Create a new exception with the given tag and payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload doesn't have one value per parameter of the
+    /// tag type.
+    pub fn new(tag: Arc<Tag>, payload: Box<[u128]>) -> Self {
+        assert_eq!(
+            tag.ty().params().len(),
+            payload.len(),
+            "the exception payload doesn't match its tag"
+        );
+        Self {
+            inner: Arc::new(VMExceptionInner { tag, payload }),
+        }
+    }
+
+    /// Get the tag of the exception.
+    pub fn tag(&self) -> &Arc<Tag> {
+        &self.inner.tag
+    }
+
+    /// Get the values carried by the exception.
+    pub fn payload(&self) -> &[u128] {
+        &self.inner.payload
+    }
+
+    /// Returns whether the exception was thrown with `tag`.
+    pub fn is(&self, tag: &Arc<Tag>) -> bool {
+        Arc::ptr_eq(&self.inner.tag, tag)
+    }
+
+    /// Find the exception carried by `error` or one of its sources, if any.
+    ///
+    /// This is how exceptions thrown by host functions are recognized, as
+    /// they are usually wrapped in other errors on their way to WebAssembly.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Option<Self> {
+        let mut error = Some(error);
+        while let Some(e) = error {
+            if let Some(exception) = e.downcast_ref::<Self>() {
+                return Some(exception.clone());
+            }
+            error = e.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for VMException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uncaught exception with tag {}", self.inner.tag.ty())
+    }
+}
+
+impl Error for VMException {}
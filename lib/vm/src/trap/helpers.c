@@ -1,7 +1,13 @@
 // This file contains partial code from other sources.
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
+// The ucontext routines are only declared with `_XOPEN_SOURCE` on macOS.
+#if defined(__APPLE__)
+#define _XOPEN_SOURCE 700
+#endif
+
 #include <setjmp.h>
+#include <stddef.h>
 
 int RegisterSetjmp(
     void **buf_storage,
@@ -20,3 +26,63 @@ void Unwind(void *JmpBuf) {
   jmp_buf *buf = (jmp_buf*) JmpBuf;
   longjmp(*buf, 1);
 }
+
+// Fibers are implemented with the ucontext routines, on the platforms
+// providing them.
+#if defined(__APPLE__) || defined(__FreeBSD__) || \
+    (defined(__linux__) && defined(__GLIBC__))
+#include <ucontext.h>
+
+typedef struct {
+  ucontext_t fiber;
+  // The context that resumed the fiber, and to which it switches back when
+  // it suspends or returns.
+  ucontext_t parent;
+  void (*entry)(void*);
+  void *payload;
+} WasmerFiber;
+
+// `makecontext` can only portably pass `int` arguments, so the fiber being
+// started is passed on the side.
+static _Thread_local WasmerFiber *StartingFiber;
+
+static void FiberStart(void) {
+  WasmerFiber *fiber = StartingFiber;
+  fiber->entry(fiber->payload);
+  // Returning switches to `uc_link`, the parent context.
+}
+
+size_t WasmerFiberSize(void) {
+  return sizeof(WasmerFiber);
+}
+
+int WasmerFiberInit(
+    void *storage,
+    void *stack,
+    size_t stack_size,
+    void (*entry)(void*),
+    void *payload) {
+  WasmerFiber *fiber = (WasmerFiber*) storage;
+  if (getcontext(&fiber->fiber) != 0) {
+    return -1;
+  }
+  fiber->fiber.uc_stack.ss_sp = stack;
+  fiber->fiber.uc_stack.ss_size = stack_size;
+  fiber->fiber.uc_link = &fiber->parent;
+  fiber->entry = entry;
+  fiber->payload = payload;
+  makecontext(&fiber->fiber, FiberStart, 0);
+  return 0;
+}
+
+int WasmerFiberResume(void *storage) {
+  WasmerFiber *fiber = (WasmerFiber*) storage;
+  StartingFiber = fiber;
+  return swapcontext(&fiber->parent, &fiber->fiber);
+}
+
+int WasmerFiberSuspend(void *storage) {
+  WasmerFiber *fiber = (WasmerFiber*) storage;
+  return swapcontext(&fiber->fiber, &fiber->parent);
+}
+#endif
@@ -73,6 +73,12 @@ pub enum TrapCode {
 
     /// A trap indicating that the runtime was unable to allocate sufficient memory.
     VMOutOfMemory = 15,
+
+    /// An atomic wait was attempted on a memory that isn't shared.
+    AtomicWaitNonSharedMemory = 16,
+
+    /// The epoch deadline of the instance was reached.
+    EpochDeadline = 17,
     // /// A user-defined trap code.
     // User(u16),
 }
@@ -97,6 +103,8 @@ impl TrapCode {
             Self::Interrupt => "interrupt",
             Self::UnalignedAtomic => "unaligned atomic access",
             Self::VMOutOfMemory => "out of memory",
+            Self::AtomicWaitNonSharedMemory => "atomic wait on non-shared memory",
+            Self::EpochDeadline => "epoch deadline reached",
             // Self::User(_) => unreachable!(),
         }
     }
@@ -121,6 +129,8 @@ impl Display for TrapCode {
             Self::Interrupt => "interrupt",
             Self::UnalignedAtomic => "unalign_atom",
             Self::VMOutOfMemory => "oom",
+            Self::AtomicWaitNonSharedMemory => "atomic_wait_unshared",
+            Self::EpochDeadline => "epoch_deadline",
             // User(x) => return write!(f, "user{}", x),
         };
         f.write_str(identifier)
@@ -149,6 +159,8 @@ impl FromStr for TrapCode {
             "interrupt" => Ok(Interrupt),
             "unalign_atom" => Ok(UnalignedAtomic),
             "oom" => Ok(VMOutOfMemory),
+            "atomic_wait_unshared" => Ok(AtomicWaitNonSharedMemory),
+            "epoch_deadline" => Ok(EpochDeadline),
             // _ if s.starts_with("user") => s[4..].parse().map(User).map_err(|_| ()),
             _ => Err(()),
         }
@@ -160,7 +172,7 @@ mod tests {
     use super::*;
 
     // Everything but user-defined codes.
-    const CODES: [TrapCode; 15] = [
+    const CODES: [TrapCode; 17] = [
         TrapCode::StackOverflow,
         TrapCode::HeapSetterOutOfBounds,
         TrapCode::HeapAccessOutOfBounds,
@@ -176,6 +188,8 @@ mod tests {
         TrapCode::UnreachableCodeReached,
         TrapCode::Interrupt,
         TrapCode::UnalignedAtomic,
+        TrapCode::AtomicWaitNonSharedMemory,
+        TrapCode::EpochDeadline,
     ];
 
     #[test]
@@ -121,7 +121,11 @@ cfg_if::cfg_if! {
                     // The stack and its guard page covers the
                     // range [stackaddr - guard pages .. stackaddr + stacksize).
                     // We assume the guard page is 1 page, and pages are 4KiB (or 16KiB in Apple Silicon)
-                    if stackaddr - region::page::size() <= addr && addr < stackaddr + stacksize {
+                    // WebAssembly code running on a fiber overflows into the
+                    // guard page of the fiber stack instead.
+                    if stackaddr - region::page::size() <= addr && addr < stackaddr + stacksize
+                        || crate::fiber::is_fiber_guard_page(addr)
+                    {
                         Some(TrapCode::StackOverflow)
                     } else {
                         Some(TrapCode::HeapAccessOutOfBounds)
@@ -737,7 +741,11 @@ impl Drop for CallThreadState {
 // happen which requires us to read some contextual state to figure out what to
 // do with the trap. This `tls` module is used to persist that information from
 // the caller to the trap site.
-mod tls {
+//
+// Calls in wasm running on a fiber may be suspended on a thread and resumed on
+// another one, so the thread local is looked up again after `closure` returns,
+// and the fiber saves and restores it with `replace` when switching stacks.
+pub(crate) mod tls {
     use super::CallThreadState;
     use std::cell::Cell;
     use std::ptr;
@@ -748,18 +756,26 @@ mod tls {
     /// execution of `closure` any call to `with` will yield `ptr`, unless this
     /// is recursively called again.
     pub fn set<R>(ptr: &CallThreadState, closure: impl FnOnce() -> R) -> R {
-        struct Reset<'a, T: Copy>(&'a Cell<T>, T);
+        struct Reset(*const CallThreadState);
 
-        impl<T: Copy> Drop for Reset<'_, T> {
+        impl Drop for Reset {
             fn drop(&mut self) {
-                self.0.set(self.1);
+                replace(self.0);
             }
         }
 
-        PTR.with(|p| {
-            let _r = Reset(p, p.replace(ptr));
-            closure()
-        })
+        let _r = Reset(replace(ptr));
+        closure()
+    }
+
+    /// Replaces the pointer configured with `set` above, returning the
+    /// previous one.
+    ///
+    /// This is never inlined so that the address of the thread local is not
+    /// reused across a fiber switch.
+    #[inline(never)]
+    pub fn replace(ptr: *const CallThreadState) -> *const CallThreadState {
+        PTR.with(|p| p.replace(ptr))
     }
 
     /// Returns the last pointer configured with `set` above. Panics if `set`
@@ -779,7 +795,7 @@ mod tls {
 /// and registering our own alternate stack that is large enough and has a guard
 /// page.
 #[cfg(unix)]
-fn setup_unix_sigaltstack() -> Result<(), Trap> {
+pub(crate) fn setup_unix_sigaltstack() -> Result<(), Trap> {
     use std::cell::RefCell;
     use std::ptr::null_mut;
 
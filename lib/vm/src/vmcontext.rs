@@ -11,9 +11,11 @@ use crate::table::Table;
 use crate::trap::{Trap, TrapCode};
 use loupe::MemoryUsage;
 use std::any::Any;
+use std::cell::RefCell;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::u32;
 
@@ -323,7 +325,7 @@ pub struct VMMemoryDefinition {
     pub base: *mut u8,
 
     /// The current logical size of this linear memory in bytes.
-    pub current_length: u32,
+    pub current_length: usize,
 }
 
 /// # Safety
@@ -348,14 +350,14 @@ impl VMMemoryDefinition {
     /// # Safety
     /// The memory is not copied atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
-    pub(crate) unsafe fn memory_copy(&self, dst: u32, src: u32, len: u32) -> Result<(), Trap> {
+    pub(crate) unsafe fn memory_copy(&self, dst: u64, src: u64, len: u64) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
         if src
             .checked_add(len)
-            .map_or(true, |n| n > self.current_length)
+            .map_or(true, |n| n > self.current_length as u64)
             || dst
                 .checked_add(len)
-                .map_or(true, |m| m > self.current_length)
+                .map_or(true, |m| m > self.current_length as u64)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -372,6 +374,46 @@ impl VMMemoryDefinition {
         Ok(())
     }
 
+    /// Do an unsynchronized, non-atomic `memory.copy` from `src_memory` into
+    /// this memory. Both memories may be the same.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when the source or destination ranges are out of
+    /// bounds.
+    ///
+    /// # Safety
+    /// The memory is not copied atomically and is not synchronized: it's the
+    /// caller's responsibility to synchronize.
+    pub(crate) unsafe fn memory_copy_from(
+        &self,
+        src_memory: &Self,
+        dst: u64,
+        src: u64,
+        len: u64,
+    ) -> Result<(), Trap> {
+        if src
+            .checked_add(len)
+            .map_or(true, |n| n > src_memory.current_length as u64)
+            || dst
+                .checked_add(len)
+                .map_or(true, |m| m > self.current_length as u64)
+        {
+            return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
+        }
+
+        let dst = usize::try_from(dst).unwrap();
+        let src = usize::try_from(src).unwrap();
+
+        // Bounds and casts are checked above, by this point we know that
+        // everything is safe.
+        let dst = self.base.add(dst);
+        let src = src_memory.base.add(src);
+        ptr::copy(src, dst, len as usize);
+
+        Ok(())
+    }
+
     /// Perform the `memory.fill` operation for the memory in an unsynchronized,
     /// non-atomic way.
     ///
@@ -382,10 +424,10 @@ impl VMMemoryDefinition {
     /// # Safety
     /// The memory is not filled atomically and is not synchronized: it's the
     /// caller's responsibility to synchronize.
-    pub(crate) unsafe fn memory_fill(&self, dst: u32, val: u32, len: u32) -> Result<(), Trap> {
+    pub(crate) unsafe fn memory_fill(&self, dst: u64, val: u32, len: u64) -> Result<(), Trap> {
         if dst
             .checked_add(len)
-            .map_or(true, |m| m > self.current_length)
+            .map_or(true, |m| m > self.current_length as u64)
         {
             return Err(Trap::new_from_runtime(TrapCode::HeapAccessOutOfBounds));
         }
@@ -425,12 +467,10 @@ mod test_vmmemory_definition {
             offset_of!(VMMemoryDefinition, current_length),
             usize::from(offsets.vmmemory_definition_current_length())
         );
-        /* TODO: Assert that the size of `current_length` matches.
         assert_eq!(
-            size_of::<VMMemoryDefinition::current_length>(),
+            size_of::<usize>(),
             usize::from(offsets.size_of_vmmemory_definition_current_length())
         );
-        */
     }
 }
 
@@ -805,6 +845,243 @@ impl Default for VMCallerCheckedAnyfunc {
     }
 }
 
+/// The flags used to interrupt the WebAssembly code running in a set of
+/// instances, possibly from another thread, and the limit of the stack
+/// this code may use.
+///
+/// Every `VMContext` points to the `VMInterrupts` it checks, on function
+/// entry and on loop headers.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct VMInterrupts {
+    /// Non-zero when the WebAssembly code must trap with
+    /// `TrapCode::Interrupt` as soon as possible.
+    pub interrupt: AtomicUsize,
+
+    /// The current epoch, compared against the epoch deadline of each
+    /// instance.
+    pub epoch: AtomicU64,
+
+    /// The lowest address the stack pointer may reach on entry to a
+    /// WebAssembly function, below which it traps with
+    /// `TrapCode::StackOverflow`. Zero when there is no limit.
+    ///
+    /// This is maintained by [`VMInterrupts::with_stack_limit`].
+    pub stack_limit: AtomicUsize,
+
+    // The fields below aren't accessed by compiled code.
+    /// The number of bytes of stack WebAssembly code may use from the
+    /// point where the host calls into it. Zero when there is no limit.
+    max_wasm_stack: usize,
+
+    /// The number of threads running WebAssembly code through these
+    /// interrupts.
+    running_threads: AtomicUsize,
+}
+
+thread_local! {
+    /// The `VMInterrupts` of the calls into WebAssembly code in progress on
+    /// this thread that set up a stack limit, outermost first.
+    static ACTIVATIONS: RefCell<Vec<*const VMInterrupts>> = RefCell::new(Vec::new());
+}
+
+#[cfg(test)]
+mod test_vminterrupts {
+    use super::VMInterrupts;
+    use crate::{ModuleInfo, VMOffsets};
+    use memoffset::offset_of;
+    use std::mem::size_of;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn check_vminterrupts_offsets() {
+        let module = ModuleInfo::new();
+        let offsets = VMOffsets::new(size_of::<*mut u8>() as u8, &module);
+        assert_eq!(
+            offset_of!(VMInterrupts, interrupt),
+            usize::from(offsets.vminterrupts_interrupt())
+        );
+        assert_eq!(
+            offset_of!(VMInterrupts, epoch),
+            usize::from(offsets.vminterrupts_epoch())
+        );
+        assert_eq!(
+            offset_of!(VMInterrupts, stack_limit),
+            usize::from(offsets.vminterrupts_stack_limit())
+        );
+        assert_eq!(
+            offset_of!(VMInterrupts, stack_limit) + size_of::<usize>(),
+            usize::from(offsets.size_of_vminterrupts())
+        );
+    }
+
+    #[test]
+    fn stack_limit_of_nested_calls() {
+        let interrupts = VMInterrupts::with_max_wasm_stack(0x1000);
+        assert_eq!(interrupts.stack_limit(), 0);
+        interrupts.with_stack_limit(|| {
+            let limit = interrupts.stack_limit();
+            assert_ne!(limit, 0);
+            // A call back into WebAssembly code from the host keeps the
+            // limit of the outer call.
+            interrupts.with_stack_limit(|| assert_eq!(interrupts.stack_limit(), limit));
+            assert_eq!(interrupts.stack_limit(), limit);
+        });
+        assert_eq!(interrupts.stack_limit(), 0);
+
+        // Calls through other interrupts set their own limit.
+        let other = VMInterrupts::with_max_wasm_stack(0x2000);
+        interrupts.with_stack_limit(|| {
+            let limit = interrupts.stack_limit();
+            other.with_stack_limit(|| {
+                assert_eq!(interrupts.stack_limit(), limit);
+                assert_ne!(other.stack_limit(), 0);
+                assert!(other.stack_limit() < limit);
+            });
+            assert_eq!(other.stack_limit(), 0);
+        });
+    }
+
+    #[test]
+    fn stack_limit_of_concurrent_calls() {
+        const THREADS: usize = 4;
+        let interrupts = Arc::new(VMInterrupts::with_max_wasm_stack(0x1000));
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let threads = (0..THREADS)
+            .map(|_| {
+                let interrupts = interrupts.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    interrupts.with_stack_limit(|| {
+                        // Each thread may have set its own limit before the
+                        // other ones started, but none applies while they
+                        // all run.
+                        barrier.wait();
+                        assert_eq!(interrupts.stack_limit(), 0);
+                        barrier.wait();
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(interrupts.stack_limit(), 0);
+
+        // The limit applies again once a single thread runs.
+        interrupts.with_stack_limit(|| assert_ne!(interrupts.stack_limit(), 0));
+        assert_eq!(interrupts.stack_limit(), 0);
+    }
+
+    #[test]
+    fn stack_limit_stays_lifted_until_the_outer_call_ends() {
+        let interrupts = Arc::new(VMInterrupts::with_max_wasm_stack(0x1000));
+        interrupts.with_stack_limit(|| {
+            assert_ne!(interrupts.stack_limit(), 0);
+            let other = interrupts.clone();
+            std::thread::spawn(move || {
+                other.with_stack_limit(|| assert_eq!(other.stack_limit(), 0));
+            })
+            .join()
+            .unwrap();
+            assert_eq!(interrupts.stack_limit(), 0);
+        });
+        interrupts.with_stack_limit(|| assert_ne!(interrupts.stack_limit(), 0));
+    }
+}
+
+impl VMInterrupts {
+    /// Requests the WebAssembly code running, or the next one to run if none
+    /// is, to trap with `TrapCode::Interrupt`.
+    pub fn interrupt(&self) {
+        self.interrupt.store(1, Ordering::SeqCst);
+    }
+
+    /// Clears the pending interrupt, returning whether there was one.
+    pub fn take_interrupt(&self) -> bool {
+        self.interrupt.swap(0, Ordering::SeqCst) != 0
+    }
+
+    /// Returns the current epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// Increments the current epoch, returning the new one.
+    pub fn increment_epoch(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Creates a new `VMInterrupts` limiting the stack WebAssembly code
+    /// may use to `max_wasm_stack` bytes, or not limiting it if zero.
+    pub fn with_max_wasm_stack(max_wasm_stack: usize) -> Self {
+        Self {
+            max_wasm_stack,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the current stack limit, zero when there is none.
+    pub fn stack_limit(&self) -> usize {
+        self.stack_limit.load(Ordering::SeqCst)
+    }
+
+    /// Runs `f`, which calls into WebAssembly code, with the stack limit set
+    /// `max_wasm_stack` bytes below the current stack pointer.
+    ///
+    /// Calls back into WebAssembly code from the host functions it calls
+    /// keep the limit of the outermost call on their thread, so that the
+    /// depth WebAssembly code can reach doesn't depend on where the host
+    /// calls it from. As the limit is shared by all the instances using
+    /// these interrupts, it is lifted while several threads run their
+    /// WebAssembly code, whose overflows are then caught by the guard pages
+    /// of their native stacks, until the next call made while no other
+    /// thread runs any.
+    pub fn with_stack_limit<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Reset<'a>(&'a VMInterrupts, usize);
+
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                ACTIVATIONS.with(|activations| activations.borrow_mut().pop());
+                // Another thread may have lifted the limit already.
+                let _ = self.0.stack_limit.compare_exchange(
+                    self.1,
+                    0,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+                self.0.running_threads.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        if self.max_wasm_stack == 0 {
+            return f();
+        }
+        let this = self as *const Self;
+        if ACTIVATIONS.with(|activations| activations.borrow().contains(&this)) {
+            return f();
+        }
+        let marker = 0u8;
+        let limit = (&marker as *const u8 as usize).saturating_sub(self.max_wasm_stack);
+        ACTIVATIONS.with(|activations| activations.borrow_mut().push(this));
+        let _reset = Reset(self, limit);
+        if self.running_threads.fetch_add(1, Ordering::SeqCst) == 0 {
+            let _ = self
+                .stack_limit
+                .compare_exchange(0, limit, Ordering::SeqCst, Ordering::SeqCst);
+            // Lift the limit again if another thread started running
+            // WebAssembly code in the meantime, as it may have found its
+            // own limit lifted before this one was set.
+            if self.running_threads.load(Ordering::SeqCst) != 1 {
+                self.stack_limit.store(0, Ordering::SeqCst);
+            }
+        } else {
+            self.stack_limit.store(0, Ordering::SeqCst);
+        }
+        f()
+    }
+}
+
 /// An index type for builtin functions.
 #[derive(Copy, Clone, Debug)]
 pub struct VMBuiltinFunctionIndex(u32);
@@ -867,9 +1144,105 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_raise_trap_index() -> Self {
         Self(13)
     }
+    /// Returns an index for wasm's `table.size` instruction.
+    pub const fn get_table_size_index() -> Self {
+        Self(14)
+    }
+    /// Returns an index for wasm's `table.get` instruction.
+    pub const fn get_table_get_index() -> Self {
+        Self(15)
+    }
+    /// Returns an index for wasm's `table.set` instruction.
+    pub const fn get_table_set_index() -> Self {
+        Self(16)
+    }
+    /// Returns an index for wasm's `table.grow` instruction.
+    pub const fn get_table_grow_index() -> Self {
+        Self(17)
+    }
+    /// Returns an index for wasm's `table.fill` instruction.
+    pub const fn get_table_fill_index() -> Self {
+        Self(18)
+    }
+    /// Returns an index for wasm's `ref.func` instruction.
+    pub const fn get_func_ref_index() -> Self {
+        Self(19)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait32` instruction.
+    pub const fn get_memory_atomic_wait32_index() -> Self {
+        Self(20)
+    }
+    /// Returns an index for wasm's `memory.atomic.wait64` instruction.
+    pub const fn get_memory_atomic_wait64_index() -> Self {
+        Self(21)
+    }
+    /// Returns an index for wasm's `memory.atomic.notify` instruction.
+    pub const fn get_memory_atomic_notify_index() -> Self {
+        Self(22)
+    }
+    /// Returns an index for creating the exception thrown by wasm's `throw`
+    /// instruction.
+    pub const fn get_exception_new_index() -> Self {
+        Self(23)
+    }
+    /// Returns an index for raising an exception not caught by the function
+    /// throwing it.
+    pub const fn get_exception_raise_index() -> Self {
+        Self(24)
+    }
+    /// Returns an index for calling a function from a `try` block.
+    pub const fn get_invoke_index() -> Self {
+        Self(25)
+    }
+    /// Returns an index for matching an exception against wasm's `catch`
+    /// clauses.
+    pub const fn get_exception_matches_index() -> Self {
+        Self(26)
+    }
+    /// Returns an index for reading the values of a caught exception.
+    pub const fn get_exception_take_payload_index() -> Self {
+        Self(27)
+    }
+    /// Returns an index for wasm's `memory.copy` for locally defined 64-bit
+    /// memories.
+    pub const fn get_local_memory64_copy_index() -> Self {
+        Self(28)
+    }
+    /// Returns an index for wasm's `memory.copy` for imported 64-bit memories.
+    pub const fn get_imported_memory64_copy_index() -> Self {
+        Self(29)
+    }
+    /// Returns an index for wasm's `memory.fill` for locally defined 64-bit
+    /// memories.
+    pub const fn get_memory64_fill_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for wasm's `memory.fill` for imported 64-bit memories.
+    pub const fn get_imported_memory64_fill_index() -> Self {
+        Self(31)
+    }
+    /// Returns an index for wasm's `memory.init` instruction on 64-bit
+    /// memories.
+    pub const fn get_memory64_init_index() -> Self {
+        Self(32)
+    }
+    /// Returns an index for wasm's `memory.copy` between two different
+    /// memories.
+    pub const fn get_memory_copy_between_index() -> Self {
+        Self(33)
+    }
+    /// Returns an index for trapping when the WebAssembly code has been
+    /// interrupted.
+    pub const fn get_interrupt_index() -> Self {
+        Self(34)
+    }
+    /// Returns an index for handling a reached epoch deadline.
+    pub const fn get_epoch_deadline_index() -> Self {
+        Self(35)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        14
+        36
     }
 
     /// Return the index as an u32 number.
@@ -927,6 +1300,49 @@ impl VMBuiltinFunctionsArray {
             wasmer_data_drop as usize;
         ptrs[VMBuiltinFunctionIndex::get_raise_trap_index().index() as usize] =
             wasmer_raise_trap as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_size_index().index() as usize] =
+            wasmer_table_size as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_get_index().index() as usize] =
+            wasmer_table_get as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_set_index().index() as usize] =
+            wasmer_table_set as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_grow_index().index() as usize] =
+            wasmer_table_grow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_table_fill_index().index() as usize] =
+            wasmer_table_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_func_ref_index().index() as usize] =
+            wasmer_func_ref as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait32_index().index() as usize] =
+            wasmer_memory_atomic_wait32 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_wait64_index().index() as usize] =
+            wasmer_memory_atomic_wait64 as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_atomic_notify_index().index() as usize] =
+            wasmer_memory_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_new_index().index() as usize] =
+            wasmer_exception_new as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_raise_index().index() as usize] =
+            wasmer_exception_raise as usize;
+        ptrs[VMBuiltinFunctionIndex::get_invoke_index().index() as usize] = wasmer_invoke as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_matches_index().index() as usize] =
+            wasmer_exception_matches as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_take_payload_index().index() as usize] =
+            wasmer_exception_take_payload as usize;
+        ptrs[VMBuiltinFunctionIndex::get_local_memory64_copy_index().index() as usize] =
+            wasmer_local_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_copy_index().index() as usize] =
+            wasmer_imported_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_fill_index().index() as usize] =
+            wasmer_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_fill_index().index() as usize] =
+            wasmer_imported_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_init_index().index() as usize] =
+            wasmer_memory64_init as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory_copy_between_index().index() as usize] =
+            wasmer_memory_copy_between as usize;
+        ptrs[VMBuiltinFunctionIndex::get_interrupt_index().index() as usize] =
+            wasmer_interrupt as usize;
+        ptrs[VMBuiltinFunctionIndex::get_epoch_deadline_index().index() as usize] =
+            wasmer_epoch_deadline as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
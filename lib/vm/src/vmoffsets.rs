@@ -242,7 +242,7 @@ impl VMOffsets {
 
     /// The size of the `current_length` field.
     pub const fn size_of_vmmemory_definition_current_length(&self) -> u8 {
-        4
+        self.pointer_size
     }
 
     /// Return the size of [`VMMemoryDefinition`].
@@ -334,6 +334,34 @@ impl VMOffsets {
     }
 }
 
+/// Offsets for [`VMInterrupts`].
+///
+/// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+impl VMOffsets {
+    /// The offset of the `interrupt` field.
+    pub const fn vminterrupts_interrupt(&self) -> u8 {
+        0
+    }
+
+    /// The offset of the `epoch` field.
+    pub const fn vminterrupts_epoch(&self) -> u8 {
+        8
+    }
+
+    /// The offset of the `stack_limit` field.
+    pub const fn vminterrupts_stack_limit(&self) -> u8 {
+        16
+    }
+
+    /// Return the size of the fields of [`VMInterrupts`] accessed by
+    /// compiled code.
+    ///
+    /// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+    pub const fn size_of_vminterrupts(&self) -> u8 {
+        16 + self.pointer_size
+    }
+}
+
 /// Offsets for [`VMContext`].
 ///
 /// [`VMContext`]: crate::vmcontext::VMContext
@@ -435,10 +463,10 @@ impl VMOffsets {
             .unwrap()
     }
 
-    /// Return the size of the [`VMContext`] allocation.
+    /// The offset of the pointer to the [`VMInterrupts`].
     ///
-    /// [`VMContext`]: crate::vmcontext::VMContext
-    pub fn size_of_vmctx(&self) -> u32 {
+    /// [`VMInterrupts`]: crate::vmcontext::VMInterrupts
+    pub fn vmctx_interrupts(&self) -> u32 {
         self.vmctx_builtin_functions_begin()
             .checked_add(
                 VMBuiltinFunctionIndex::builtin_functions_total_number()
@@ -448,6 +476,22 @@ impl VMOffsets {
             .unwrap()
     }
 
+    /// The offset of the epoch deadline of the instance.
+    pub fn vmctx_epoch_deadline(&self) -> u32 {
+        let offset = self
+            .vmctx_interrupts()
+            .checked_add(u32::from(self.pointer_size))
+            .unwrap();
+        align(offset, 8)
+    }
+
+    /// Return the size of the [`VMContext`] allocation.
+    ///
+    /// [`VMContext`]: crate::vmcontext::VMContext
+    pub fn size_of_vmctx(&self) -> u32 {
+        self.vmctx_epoch_deadline().checked_add(8).unwrap()
+    }
+
     /// Return the offset to [`VMSharedSignatureIndex`] index `index`.
     ///
     /// [`VMSharedSignatureIndex`]: crate::vmcontext::VMSharedSignatureIndex
@@ -22,7 +22,8 @@ mod utils;
 use crate::syscalls::*;
 
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder,
+    DirEntry, Fd, FileSystem, HostFileSystem, MemFile, MemFileSystem, OpenOptions, Pipe, Stderr,
+    Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiSocket, WasiState, WasiStateBuilder,
     WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
@@ -93,6 +94,10 @@ impl WasiEnv {
     }
 
     /// Get a reference to the memory
+    ///
+    /// This is the memory the module exports as `memory`. Modules with
+    /// several memories must export the one WASI should operate on under
+    /// that name.
     pub fn memory(&self) -> &Memory {
         self.memory_ref()
             .expect("Memory should be set on `WasiEnv` first")
@@ -1,9 +1,12 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{WasiFile, WasiFs, WasiFsError, WasiState};
-use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
+use crate::state::{FileSystem, WasiFile, WasiFs, WasiFsError, WasiSocket, WasiState};
+use crate::syscalls::types::{
+    __wasi_rights_t, __WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO,
+};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Creates an empty [`WasiStateBuilder`].
@@ -37,6 +40,7 @@ pub struct WasiStateBuilder {
     args: Vec<Vec<u8>>,
     envs: Vec<(Vec<u8>, Vec<u8>)>,
     preopens: Vec<PreopenedDir>,
+    preopened_sockets: Vec<(WasiSocket, __wasi_rights_t)>,
     #[allow(clippy::type_complexity)]
     setup_fs_fn: Option<Box<dyn Fn(&mut WasiFs) -> Result<(), String> + Send>>,
     stdout_override: Option<Box<dyn WasiFile>>,
@@ -50,6 +54,7 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("args", &self.args)
             .field("envs", &self.envs)
             .field("preopens", &self.preopens)
+            .field("preopened_sockets", &self.preopened_sockets)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
@@ -258,6 +263,43 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Hand a socket of the host to the WASI program, with the given
+    /// `rights`.
+    ///
+    /// The program can receive and send data with `fd_read` and `fd_write`,
+    /// or `sock_recv` and `sock_send`, given the `__WASI_RIGHT_FD_READ`
+    /// and `__WASI_RIGHT_FD_WRITE` rights, and shut the connection down
+    /// with `sock_shutdown` given the `__WASI_RIGHT_SOCK_SHUTDOWN` right.
+    ///
+    /// Preopened sockets get the file descriptors following the preopened
+    /// directories, in the order they were added.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use std::net::TcpStream;
+    /// # use wasmer_wasi::types::*;
+    /// # use wasmer_wasi::{WasiState, WasiStateCreationError};
+    /// # fn main() -> Result<(), WasiStateCreationError> {
+    /// let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+    /// WasiState::new("program_name")
+    ///    .preopen_socket(
+    ///        stream,
+    ///        __WASI_RIGHT_FD_READ | __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_SOCK_SHUTDOWN,
+    ///    )
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preopen_socket<S>(&mut self, socket: S, rights: __wasi_rights_t) -> &mut Self
+    where
+        S: Into<WasiSocket>,
+    {
+        self.preopened_sockets.push((socket.into(), rights));
+
+        self
+    }
+
     /// Overwrite the default WASI `stdout`, if you want to hold on to the
     /// original `stdout` use [`WasiFs::swap_file`] after building.
     pub fn stdout(&mut self, new_file: Box<dyn WasiFile>) -> &mut Self {
@@ -381,6 +423,11 @@ impl WasiStateBuilder {
                 .swap_file(__WASI_STDERR_FILENO, stderr_override)
                 .map_err(WasiStateCreationError::WasiFsError)?;
         }
+        for (socket, rights) in self.preopened_sockets.drain(..) {
+            wasi_fs
+                .preopen_socket(socket, rights)
+                .map_err(|e| WasiStateCreationError::WasiFsError(WasiFsError::from_wasi_err(e)))?;
+        }
         if let Some(f) = &self.setup_fs_fn {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
@@ -419,6 +466,7 @@ pub struct PreopenDirBuilder {
     read: bool,
     write: bool,
     create: bool,
+    filesystem: Option<Arc<dyn FileSystem>>,
 }
 
 /// The built version of `PreopenDirBuilder`
@@ -429,6 +477,7 @@ pub(crate) struct PreopenedDir {
     pub(crate) read: bool,
     pub(crate) write: bool,
     pub(crate) create: bool,
+    pub(crate) filesystem: Option<Arc<dyn FileSystem>>,
 }
 
 impl PreopenDirBuilder {
@@ -484,6 +533,17 @@ impl PreopenDirBuilder {
         self
     }
 
+    /// Mount the preopened directory from `filesystem` instead of the
+    /// file system of the host
+    ///
+    /// The path given to [`PreopenDirBuilder::directory`] is then a path
+    /// within `filesystem`.
+    pub fn filesystem(&mut self, filesystem: Arc<dyn FileSystem>) -> &mut Self {
+        self.filesystem = Some(filesystem);
+
+        self
+    }
+
     pub(crate) fn build(&self) -> Result<PreopenedDir, WasiStateCreationError> {
         // ensure at least one is set
         if !(self.read || self.write || self.create) {
@@ -497,7 +557,11 @@ impl PreopenDirBuilder {
         }
         let path = self.path.clone().unwrap();
 
-        if !path.exists() {
+        let exists = match &self.filesystem {
+            Some(filesystem) => filesystem.metadata(&path).is_ok(),
+            None => path.exists(),
+        };
+        if !exists {
             return Err(WasiStateCreationError::PreopenedDirectoryNotFound(path));
         }
         if let Some(alias) = &self.alias {
@@ -510,6 +574,7 @@ impl PreopenDirBuilder {
             read: self.read,
             write: self.write,
             create: self.create,
+            filesystem: self.filesystem.clone(),
         })
     }
 }
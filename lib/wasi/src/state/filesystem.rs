@@ -0,0 +1,203 @@
+//! Filesystems backing the preopened directories of the WASI filesystem.
+//!
+//! Every file and directory the WASI program sees outside of the virtual
+//! root lives in a [`FileSystem`]. By default this is the file system of the
+//! host, but each preopened directory can be mounted from its own
+//! implementation, for instance to serve the program from memory or from
+//! an archive.
+
+use crate::state::{host_file_type_to_wasi_file_type, HostFile, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The index of a filesystem in [`WasiFs::filesystems`](crate::WasiFs::filesystems).
+pub type FileSystemIndex = usize;
+
+/// The index of the [`HostFileSystem`], which backs the preopened
+/// directories that don't mount a filesystem of their own.
+pub const HOST_FILESYSTEM: FileSystemIndex = 0;
+
+/// The operations the WASI filesystem needs from the storage backing it.
+///
+/// Paths are absolute paths within the filesystem: they are made of the
+/// path a directory is preopened at, followed by the path the WASI program
+/// used relative to it. Implementations don't have to sandbox them, the
+/// WASI filesystem only hands out paths within preopened directories.
+///
+/// Like [`WasiFile`], implementations must be serializable so that the
+/// [`WasiState`](crate::WasiState) can be frozen.
+#[typetag::serde(tag = "type")]
+pub trait FileSystem: fmt::Debug + Send + Sync + 'static {
+    /// Opens the file at `path`.
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError>;
+
+    /// Returns the entries of the directory at `path`, without `.` and `..`.
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError>;
+
+    /// Returns the metadata of the file at `path`, following symlinks.
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError>;
+
+    /// Returns the metadata of the file at `path`, without following a
+    /// symlink at `path` itself.
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError>;
+
+    /// Returns the value of the symlink at `path`.
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError>;
+
+    /// Creates an empty directory at `path`.
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Removes the empty directory at `path`.
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Moves the file or directory at `from` to `to`.
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError>;
+
+    /// Removes the file or symlink at `path`.
+    fn unlink(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Creates a symlink at `path` whose value is `target`.
+    fn symlink(&self, target: &Path, path: &Path) -> Result<(), WasiFsError>;
+}
+
+/// How [`FileSystem::open`] opens a file, as in [`std::fs::OpenOptions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    /// Creates the file if it doesn't exist.
+    pub create: bool,
+    /// Creates the file, failing if it exists.
+    pub create_new: bool,
+}
+
+/// An entry of a directory, as returned by [`FileSystem::read_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub file_type: __wasi_filetype_t,
+}
+
+/// The file system of the host.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct HostFileSystem;
+
+#[typetag::serde]
+impl FileSystem for HostFileSystem {
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new)
+            .open(path)?;
+        Ok(Box::new(HostFile::new(
+            file,
+            path.to_path_buf(),
+            options.read,
+            options.write,
+            options.append,
+        )))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    file_type: host_file_type(entry.file_type()?),
+                })
+            })
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        Ok(host_metadata_to_filestat(&path.metadata()?))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        Ok(host_metadata_to_filestat(&path.symlink_metadata()?))
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        fs::read_link(path).map_err(Into::into)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::create_dir(path).map_err(Into::into)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_dir(path).map_err(Into::into)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        fs::rename(from, to).map_err(Into::into)
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_file(path).map_err(Into::into)
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, target: &Path, path: &Path) -> Result<(), WasiFsError> {
+        std::os::unix::fs::symlink(target, path).map_err(Into::into)
+    }
+
+    #[cfg(windows)]
+    fn symlink(&self, target: &Path, path: &Path) -> Result<(), WasiFsError> {
+        std::os::windows::fs::symlink_file(target, path).map_err(Into::into)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn symlink(&self, _target: &Path, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+}
+
+/// Converts the metadata of a file of the host.
+fn host_metadata_to_filestat(metadata: &fs::Metadata) -> __wasi_filestat_t {
+    let nanos = |time: std::io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|time| time.as_nanos() as __wasi_timestamp_t)
+            .unwrap_or(0)
+    };
+    __wasi_filestat_t {
+        st_filetype: host_file_type(metadata.file_type()),
+        st_size: metadata.len(),
+        st_atim: nanos(metadata.accessed()),
+        st_mtim: nanos(metadata.modified()),
+        st_ctim: nanos(metadata.created()),
+        ..__wasi_filestat_t::default()
+    }
+}
+
+/// Converts the type of a file of the host, including the special files
+/// [`host_file_type_to_wasi_file_type`] doesn't know about.
+fn host_file_type(file_type: fs::FileType) -> __wasi_filetype_t {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_char_device() {
+            return __WASI_FILETYPE_CHARACTER_DEVICE;
+        } else if file_type.is_block_device() {
+            return __WASI_FILETYPE_BLOCK_DEVICE;
+        } else if file_type.is_socket() {
+            // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
+            // a `__WASI_FILETYPE_SOCKET_DGRAM`?
+            return __WASI_FILETYPE_SOCKET_STREAM;
+        }
+    }
+    // FIFOs don't fit any other type, so they're unknown
+    host_file_type_to_wasi_file_type(file_type)
+}
@@ -0,0 +1,844 @@
+//! A filesystem living entirely in memory.
+//!
+//! It lets WASI programs create, write and remove files without ever
+//! touching the disk of the host, for instance in tests or when running
+//! programs of different tenants side by side.
+
+use crate::state::{DirEntry, FileSystem, OpenOptions, WasiFile, WasiFsError, MAX_SYMLINKS};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// A [`FileSystem`] keeping its directories, files and symlinks in memory.
+///
+/// Clones of a `MemFileSystem` share the same files, so the host can keep
+/// one around to prepare the files of a WASI program and to look at what
+/// it wrote:
+///
+/// ```
+/// # use std::io::Read;
+/// # use std::path::Path;
+/// # use std::sync::Arc;
+/// # use wasmer_wasi::{FileSystem, MemFileSystem, OpenOptions, WasiState, WasiStateCreationError};
+/// # fn main() -> Result<(), WasiStateCreationError> {
+/// let filesystem = MemFileSystem::new();
+/// WasiState::new("program_name")
+///    .preopen(|p| {
+///        p.directory("/")
+///            .alias("tmp")
+///            .read(true)
+///            .write(true)
+///            .create(true)
+///            .filesystem(Arc::new(filesystem.clone()))
+///    })?
+///    .build()?;
+///
+/// // once the program ran
+/// let options = OpenOptions {
+///     read: true,
+///     ..OpenOptions::default()
+/// };
+/// if let Ok(mut file) = filesystem.open(Path::new("/output.txt"), &options) {
+///     let mut output = String::new();
+///     file.read_to_string(&mut output).unwrap();
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// When the [`WasiState`](crate::WasiState) is frozen, the files are
+/// serialized along with it. The files opened by the WASI program are
+/// serialized with their own copy of their contents, which doesn't stay in
+/// sync with the filesystem once unfrozen.
+///
+/// The files can't grow past [`MemFileSystem::DEFAULT_MAX_FILE_SIZE`]
+/// bytes, or the size given to [`MemFileSystem::with_max_file_size`]:
+/// writes and resizes going further fail with `__WASI_EFBIG` instead of
+/// letting the WASI program exhaust the memory of the host. Likewise, all
+/// the files together can't take more than
+/// [`MemFileSystem::DEFAULT_MAX_SIZE`] bytes, or the size given to
+/// [`MemFileSystem::with_max_size`], past which they fail with
+/// `__WASI_ENOSPC`. The contents of the removed files stop counting once
+/// the last file opening them is closed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemFileSystem {
+    root: Arc<Mutex<Node>>,
+    budget: Arc<Mutex<Budget>>,
+    max_file_size: u64,
+}
+
+impl MemFileSystem {
+    /// The maximum size of a file, in bytes, unless configured otherwise.
+    pub const DEFAULT_MAX_FILE_SIZE: u64 = 1 << 30;
+
+    /// The maximum size of all the files together, in bytes, unless
+    /// configured otherwise.
+    pub const DEFAULT_MAX_SIZE: u64 = 1 << 30;
+
+    /// Creates a filesystem containing an empty root directory, `/`.
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(Mutex::new(Node::Directory(Directory::new()))),
+            budget: Arc::new(Mutex::new(Budget {
+                max_size: Self::DEFAULT_MAX_SIZE,
+                used: 0,
+            })),
+            max_file_size: Self::DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+
+    /// Sets the maximum size, in bytes, of the files opened through this
+    /// filesystem.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of all the files of this filesystem
+    /// together, for its clones as well.
+    pub fn with_max_size(self, max_size: u64) -> Self {
+        self.budget.lock().unwrap().max_size = max_size;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Node> {
+        self.root.lock().unwrap()
+    }
+}
+
+impl Default for MemFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for MemFileSystem {
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let mut root = self.lock();
+        let names = resolve(&root, path, true)?;
+        let data = match get(&root, &names) {
+            Ok(_) if options.create_new => return Err(WasiFsError::AlreadyExists),
+            Ok(Node::File(data)) => {
+                let data = data.clone();
+                if options.truncate {
+                    let mut data = data.lock().unwrap();
+                    data.resize(0, self.max_file_size)?;
+                    data.times.modified = now();
+                }
+                data
+            }
+            Ok(_) => return Err(WasiFsError::NotAFile),
+            Err(WasiFsError::EntityNotFound) if options.create || options.create_new => {
+                let (parent, name) = parent_mut(&mut root, &names)?;
+                let data = Arc::new(Mutex::new(FileData {
+                    bytes: Vec::new(),
+                    times: Times::now(),
+                    budget: self.budget.clone(),
+                }));
+                parent.insert(name, Node::File(data.clone()));
+                data
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Box::new(MemFile {
+            data,
+            cursor: 0,
+            max_size: self.max_file_size,
+            read: options.read,
+            write: options.write,
+            append: options.append,
+        }))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let root = self.lock();
+        let names = resolve(&root, path, true)?;
+        match get(&root, &names)? {
+            Node::Directory(directory) => Ok(directory
+                .entries
+                .iter()
+                .map(|(name, node)| DirEntry {
+                    name: name.clone(),
+                    file_type: node.file_type(),
+                })
+                .collect()),
+            _ => Err(WasiFsError::BaseNotDirectory),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        let root = self.lock();
+        let names = resolve(&root, path, true)?;
+        Ok(get(&root, &names)?.stat())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        let root = self.lock();
+        let names = resolve(&root, path, false)?;
+        Ok(get(&root, &names)?.stat())
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        let root = self.lock();
+        let names = resolve(&root, path, false)?;
+        match get(&root, &names)? {
+            Node::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(WasiFsError::InvalidInput),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut root = self.lock();
+        let names = resolve(&root, path, false)?;
+        let (parent, name) = parent_mut(&mut root, &names)?;
+        if parent.entries.contains_key(&name) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        parent.insert(name, Node::Directory(Directory::new()));
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut root = self.lock();
+        let names = resolve(&root, path, false)?;
+        match get(&root, &names)? {
+            Node::Directory(directory) if !directory.entries.is_empty() => {
+                return Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY))
+            }
+            Node::Directory(_) => (),
+            _ => return Err(WasiFsError::BaseNotDirectory),
+        }
+        let (parent, name) = parent_mut(&mut root, &names)?;
+        parent.remove(&name);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        let mut root = self.lock();
+        let from = resolve(&root, from, false)?;
+        let to = resolve(&root, to, false)?;
+        let source_is_dir = matches!(get(&root, &from)?, Node::Directory(_));
+        if from == to {
+            return Ok(());
+        }
+        if to.starts_with(&from) {
+            // a directory can't be moved inside of itself
+            return Err(WasiFsError::InvalidInput);
+        }
+        match get(&root, &to) {
+            Ok(Node::Directory(directory)) if source_is_dir => {
+                if !directory.entries.is_empty() {
+                    return Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY));
+                }
+            }
+            Ok(Node::Directory(_)) => return Err(WasiFsError::UnknownError(__WASI_EISDIR)),
+            Ok(_) if source_is_dir => return Err(WasiFsError::BaseNotDirectory),
+            Ok(_) | Err(WasiFsError::EntityNotFound) => (),
+            Err(e) => return Err(e),
+        }
+        // make sure that the target's parent exists before taking the source out
+        parent_mut(&mut root, &to)?;
+
+        let (parent, name) = parent_mut(&mut root, &from)?;
+        let node = parent.remove(&name).unwrap();
+        let (parent, name) = parent_mut(&mut root, &to)?;
+        parent.insert(name, node);
+        Ok(())
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut root = self.lock();
+        let names = resolve(&root, path, false)?;
+        if let Node::Directory(_) = get(&root, &names)? {
+            return Err(WasiFsError::UnknownError(__WASI_EISDIR));
+        }
+        let (parent, name) = parent_mut(&mut root, &names)?;
+        parent.remove(&name);
+        Ok(())
+    }
+
+    fn symlink(&self, target: &Path, path: &Path) -> Result<(), WasiFsError> {
+        let mut root = self.lock();
+        let names = resolve(&root, path, false)?;
+        let (parent, name) = parent_mut(&mut root, &names)?;
+        if parent.entries.contains_key(&name) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        parent.insert(
+            name,
+            Node::Symlink {
+                target: target.to_path_buf(),
+                times: Times::now(),
+            },
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Node {
+    /// The contents of a file are shared with the [`MemFile`]s opening it.
+    File(Arc<Mutex<FileData>>),
+    Directory(Directory),
+    Symlink {
+        target: PathBuf,
+        times: Times,
+    },
+}
+
+impl Node {
+    fn file_type(&self) -> __wasi_filetype_t {
+        match self {
+            Node::File(_) => __WASI_FILETYPE_REGULAR_FILE,
+            Node::Directory(_) => __WASI_FILETYPE_DIRECTORY,
+            Node::Symlink { .. } => __WASI_FILETYPE_SYMBOLIC_LINK,
+        }
+    }
+
+    fn stat(&self) -> __wasi_filestat_t {
+        let (size, times) = match self {
+            Node::File(data) => {
+                let data = data.lock().unwrap();
+                (data.bytes.len() as u64, data.times)
+            }
+            Node::Directory(directory) => (0, directory.times),
+            Node::Symlink { target, times } => (target.to_string_lossy().len() as u64, *times),
+        };
+        __wasi_filestat_t {
+            st_filetype: self.file_type(),
+            st_size: size,
+            st_atim: times.accessed,
+            st_mtim: times.modified,
+            st_ctim: times.created,
+            ..__wasi_filestat_t::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Directory {
+    entries: BTreeMap<String, Node>,
+    times: Times,
+}
+
+impl Directory {
+    fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            times: Times::now(),
+        }
+    }
+
+    fn insert(&mut self, name: String, node: Node) {
+        self.entries.insert(name, node);
+        self.times.modified = now();
+    }
+
+    fn remove(&mut self, name: &str) -> Option<Node> {
+        let node = self.entries.remove(name);
+        self.times.modified = now();
+        node
+    }
+}
+
+/// The bytes the files of a [`MemFileSystem`] can allocate, and those they
+/// did.
+#[derive(Debug, Serialize, Deserialize)]
+struct Budget {
+    max_size: u64,
+    used: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileData {
+    bytes: Vec<u8>,
+    times: Times,
+    /// The budget of the filesystem, charged with the capacity of `bytes`.
+    budget: Arc<Mutex<Budget>>,
+}
+
+impl FileData {
+    /// Resizes the contents of the file to `len` bytes, failing with
+    /// `__WASI_EFBIG` past `max_size` and with `__WASI_ENOSPC` past the
+    /// budget of the filesystem.
+    fn resize(&mut self, len: u64, max_size: u64) -> Result<(), WasiFsError> {
+        let len = match usize::try_from(len) {
+            Ok(len) if len as u64 <= max_size => len,
+            _ => return Err(WasiFsError::UnknownError(__WASI_EFBIG)),
+        };
+        let mut budget = self.budget.lock().unwrap();
+        let old_capacity = self.bytes.capacity();
+        if len > old_capacity {
+            let available = budget.max_size.saturating_sub(budget.used);
+            if (len - old_capacity) as u64 > available {
+                return Err(WasiFsError::UnknownError(__WASI_ENOSPC));
+            }
+            // grow geometrically like `Vec::reserve` does, without going past
+            // the maximum size of the file nor the budget
+            let capacity = cmp::max(len as u64, old_capacity as u64 * 2);
+            let capacity = cmp::min(capacity, max_size);
+            let capacity = cmp::min(capacity, (old_capacity as u64).saturating_add(available));
+            self.bytes
+                .reserve_exact(capacity as usize - self.bytes.len());
+        }
+        if len < self.bytes.len() {
+            self.bytes.truncate(len);
+            self.bytes.shrink_to_fit();
+        } else {
+            self.bytes.resize(len, 0);
+        }
+        budget.used =
+            (budget.used + self.bytes.capacity() as u64).saturating_sub(old_capacity as u64);
+        Ok(())
+    }
+}
+
+impl Drop for FileData {
+    fn drop(&mut self) {
+        let mut budget = self.budget.lock().unwrap();
+        budget.used = budget.used.saturating_sub(self.bytes.capacity() as u64);
+    }
+}
+
+/// The timestamps of a node, in nanoseconds since the UNIX epoch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Times {
+    accessed: __wasi_timestamp_t,
+    modified: __wasi_timestamp_t,
+    created: __wasi_timestamp_t,
+}
+
+impl Times {
+    fn now() -> Self {
+        let now = now();
+        Self {
+            accessed: now,
+            modified: now,
+            created: now,
+        }
+    }
+}
+
+fn now() -> __wasi_timestamp_t {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|time| time.as_nanos() as __wasi_timestamp_t)
+        .unwrap_or(0)
+}
+
+/// Splits `path` into the names it's made of, keeping `..` but dropping
+/// the root and `.`.
+fn split(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_string()),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => None,
+        })
+        .collect()
+}
+
+/// Resolves `path` into the names of the node it points to from the root,
+/// following the symlinks along the way, and the one `path` ends with if
+/// `follow_last` is set.
+fn resolve(root: &Node, path: &Path, follow_last: bool) -> Result<Vec<String>, WasiFsError> {
+    let mut remaining = split(path);
+    remaining.reverse();
+    let mut resolved: Vec<String> = Vec::new();
+    let mut symlinks = 0;
+    while let Some(name) = remaining.pop() {
+        if name == ".." {
+            resolved.pop();
+            continue;
+        }
+        resolved.push(name);
+        if let Ok(Node::Symlink { target, .. }) = get(root, &resolved) {
+            if remaining.is_empty() && !follow_last {
+                break;
+            }
+            symlinks += 1;
+            if symlinks > MAX_SYMLINKS {
+                return Err(WasiFsError::UnknownError(__WASI_ELOOP));
+            }
+            resolved.pop();
+            if target.is_absolute() {
+                resolved.clear();
+            }
+            remaining.extend(split(target).into_iter().rev());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Returns the node at the end of the resolved `names`.
+fn get<'a>(mut node: &'a Node, names: &[String]) -> Result<&'a Node, WasiFsError> {
+    for name in names {
+        node = match node {
+            Node::Directory(directory) => directory
+                .entries
+                .get(name)
+                .ok_or(WasiFsError::EntityNotFound)?,
+            _ => return Err(WasiFsError::BaseNotDirectory),
+        };
+    }
+    Ok(node)
+}
+
+/// Returns the directory containing the resolved `names` and the name of
+/// the entry in it.
+fn parent_mut<'a>(
+    mut node: &'a mut Node,
+    names: &[String],
+) -> Result<(&'a mut Directory, String), WasiFsError> {
+    let (name, parent_names) = names.split_last().ok_or(WasiFsError::InvalidInput)?;
+    for parent_name in parent_names {
+        node = match node {
+            Node::Directory(directory) => directory
+                .entries
+                .get_mut(parent_name)
+                .ok_or(WasiFsError::EntityNotFound)?,
+            _ => return Err(WasiFsError::BaseNotDirectory),
+        };
+    }
+    match node {
+        Node::Directory(directory) => Ok((directory, name.clone())),
+        _ => Err(WasiFsError::BaseNotDirectory),
+    }
+}
+
+/// A file of a [`MemFileSystem`], as opened by [`FileSystem::open`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemFile {
+    data: Arc<Mutex<FileData>>,
+    cursor: u64,
+    max_size: u64,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl MemFile {
+    fn data(&self) -> MutexGuard<'_, FileData> {
+        self.data.lock().unwrap()
+    }
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.read {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the file wasn't opened for reading",
+            ));
+        }
+        let mut data = self.data();
+        data.times.accessed = now();
+        let start = std::cmp::min(self.cursor as usize, data.bytes.len());
+        let amt = std::cmp::min(buf.len(), data.bytes.len() - start);
+        buf[..amt].copy_from_slice(&data.bytes[start..start + amt]);
+        drop(data);
+        self.cursor += amt as u64;
+        Ok(amt)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.write {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the file wasn't opened for writing",
+            ));
+        }
+        // lock the field rather than `self` as the cursor moves while locked
+        let mut data = self.data.lock().unwrap();
+        if self.append {
+            self.cursor = data.bytes.len() as u64;
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let end = self.cursor.saturating_add(buf.len() as u64);
+        if (data.bytes.len() as u64) < end {
+            data.resize(end, self.max_size)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+        let start = self.cursor as usize;
+        data.bytes[start..start + buf.len()].copy_from_slice(buf);
+        data.times.modified = now();
+        drop(data);
+        self.cursor = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            io::SeekFrom::Start(offset) => {
+                self.cursor = offset;
+                return Ok(offset);
+            }
+            io::SeekFrom::End(offset) => (self.size(), offset),
+            io::SeekFrom::Current(offset) => (self.cursor, offset),
+        };
+        let cursor = if offset < 0 {
+            // `wrapping_neg` keeps `i64::MIN` as is, which is 2^63 as a `u64`
+            base.checked_sub(offset.wrapping_neg() as u64)
+        } else {
+            base.checked_add(offset as u64)
+        };
+        self.cursor = cursor.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.cursor)
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for MemFile {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        self.data().times.accessed
+    }
+
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        self.data().times.modified
+    }
+
+    fn created_time(&self) -> __wasi_timestamp_t {
+        self.data().times.created
+    }
+
+    fn set_last_accessed(&self, last_accessed: __wasi_timestamp_t) {
+        self.data().times.accessed = last_accessed;
+    }
+
+    fn set_last_modified(&self, last_modified: __wasi_timestamp_t) {
+        self.data().times.modified = last_modified;
+    }
+
+    fn set_created_time(&self, created_time: __wasi_timestamp_t) {
+        self.data().times.created = created_time;
+    }
+
+    fn size(&self) -> u64 {
+        self.data().bytes.len() as u64
+    }
+
+    fn set_len(&mut self, new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        let max_size = self.max_size;
+        let mut data = self.data();
+        data.resize(new_size, max_size)?;
+        data.times.modified = now();
+        Ok(())
+    }
+
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        // the entry of the file is removed by `MemFileSystem::unlink`, the
+        // contents are dropped with the last open file
+        Ok(())
+    }
+
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        let len = self.data().bytes.len() as u64;
+        Ok(len.saturating_sub(self.cursor) as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_file(filesystem: &MemFileSystem, path: &str, contents: &[u8]) {
+        let options = OpenOptions {
+            write: true,
+            create: true,
+            truncate: true,
+            ..OpenOptions::default()
+        };
+        let mut file = filesystem.open(Path::new(path), &options).unwrap();
+        file.write_all(contents).unwrap();
+    }
+
+    fn read_file(filesystem: &MemFileSystem, path: &str) -> Result<Vec<u8>, WasiFsError> {
+        let options = OpenOptions {
+            read: true,
+            ..OpenOptions::default()
+        };
+        let mut file = filesystem.open(Path::new(path), &options)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        Ok(contents)
+    }
+
+    #[test]
+    fn files_are_shared_between_clones() {
+        let filesystem = MemFileSystem::new();
+        filesystem.create_dir(Path::new("/dir")).unwrap();
+        write_file(&filesystem.clone(), "/dir/file", b"contents");
+        assert_eq!(read_file(&filesystem, "/dir/file").unwrap(), b"contents");
+        assert_eq!(
+            filesystem.metadata(Path::new("/dir/file")).unwrap().st_size,
+            8
+        );
+        assert_eq!(
+            filesystem.read_dir(Path::new("/dir")).unwrap(),
+            vec![DirEntry {
+                name: "file".to_string(),
+                file_type: __WASI_FILETYPE_REGULAR_FILE,
+            }]
+        );
+    }
+
+    #[test]
+    fn rename_replaces_files_and_moves_directories() {
+        let filesystem = MemFileSystem::new();
+        filesystem.create_dir(Path::new("/a")).unwrap();
+        filesystem.create_dir(Path::new("/b")).unwrap();
+        write_file(&filesystem, "/a/old", b"old");
+        write_file(&filesystem, "/a/new", b"new");
+
+        filesystem
+            .rename(Path::new("/a/new"), Path::new("/a/old"))
+            .unwrap();
+        assert_eq!(read_file(&filesystem, "/a/old").unwrap(), b"new");
+        assert!(read_file(&filesystem, "/a/new").is_err());
+
+        assert_eq!(
+            filesystem.rename(Path::new("/a"), Path::new("/a/inner")),
+            Err(WasiFsError::InvalidInput)
+        );
+        filesystem.rename(Path::new("/a"), Path::new("/b")).unwrap();
+        assert_eq!(read_file(&filesystem, "/b/old").unwrap(), b"new");
+        assert_eq!(
+            filesystem.metadata(Path::new("/a")),
+            Err(WasiFsError::EntityNotFound)
+        );
+    }
+
+    #[test]
+    fn symlinks_are_followed() {
+        let filesystem = MemFileSystem::new();
+        filesystem.create_dir(Path::new("/dir")).unwrap();
+        write_file(&filesystem, "/dir/file", b"contents");
+        filesystem
+            .symlink(Path::new("dir/file"), Path::new("/link"))
+            .unwrap();
+        filesystem
+            .symlink(Path::new("/loop"), Path::new("/loop"))
+            .unwrap();
+
+        assert_eq!(read_file(&filesystem, "/link").unwrap(), b"contents");
+        assert_eq!(
+            filesystem
+                .symlink_metadata(Path::new("/link"))
+                .unwrap()
+                .st_filetype,
+            __WASI_FILETYPE_SYMBOLIC_LINK
+        );
+        assert_eq!(
+            filesystem.read_link(Path::new("/link")).unwrap(),
+            PathBuf::from("dir/file")
+        );
+        assert_eq!(
+            filesystem.metadata(Path::new("/loop")),
+            Err(WasiFsError::UnknownError(__WASI_ELOOP))
+        );
+        filesystem.unlink(Path::new("/link")).unwrap();
+        assert_eq!(read_file(&filesystem, "/dir/file").unwrap(), b"contents");
+    }
+
+    #[test]
+    fn files_cannot_grow_past_the_maximum_size() {
+        let filesystem = MemFileSystem::new().with_max_file_size(16);
+        let options = OpenOptions {
+            read: true,
+            write: true,
+            create: true,
+            ..OpenOptions::default()
+        };
+        let mut file = filesystem.open(Path::new("/file"), &options).unwrap();
+
+        assert_eq!(
+            file.set_len(u64::MAX),
+            Err(WasiFsError::UnknownError(__WASI_EFBIG))
+        );
+        assert_eq!(
+            file.set_len(17),
+            Err(WasiFsError::UnknownError(__WASI_EFBIG))
+        );
+        file.set_len(16).unwrap();
+        assert_eq!(file.size(), 16);
+
+        file.seek(io::SeekFrom::Start(u64::MAX - 1)).unwrap();
+        let error = file.write(b"contents").unwrap_err();
+        assert_eq!(
+            WasiFsError::from(error),
+            WasiFsError::UnknownError(__WASI_EFBIG)
+        );
+        file.seek(io::SeekFrom::Start(12)).unwrap();
+        let error = file.write(b"contents").unwrap_err();
+        assert_eq!(
+            WasiFsError::from(error),
+            WasiFsError::UnknownError(__WASI_EFBIG)
+        );
+        assert_eq!(file.size(), 16);
+
+        file.seek(io::SeekFrom::Start(8)).unwrap();
+        file.write_all(b"contents").unwrap();
+        assert_eq!(&read_file(&filesystem, "/file").unwrap()[8..], b"contents");
+    }
+
+    #[test]
+    fn files_cannot_grow_past_the_maximum_size_of_the_filesystem() {
+        let filesystem = MemFileSystem::new().with_max_size(16);
+        let options = OpenOptions {
+            write: true,
+            create: true,
+            truncate: true,
+            ..OpenOptions::default()
+        };
+        write_file(&filesystem, "/a", b"0123456789");
+
+        let mut file = filesystem.open(Path::new("/b"), &options).unwrap();
+        let error = file.write(b"0123456789").unwrap_err();
+        assert_eq!(
+            WasiFsError::from(error),
+            WasiFsError::UnknownError(__WASI_ENOSPC)
+        );
+        file.write_all(b"012345").unwrap();
+        assert_eq!(
+            file.set_len(7),
+            Err(WasiFsError::UnknownError(__WASI_ENOSPC))
+        );
+
+        // the contents of a truncated file are released
+        filesystem.open(Path::new("/a"), &options).unwrap();
+        file.write_all(b"0123456789").unwrap();
+
+        // so are those of a removed file, once it's closed
+        filesystem.unlink(Path::new("/b")).unwrap();
+        let mut other_file = filesystem.open(Path::new("/c"), &options).unwrap();
+        let error = other_file.write(b"0").unwrap_err();
+        assert_eq!(
+            WasiFsError::from(error),
+            WasiFsError::UnknownError(__WASI_ENOSPC)
+        );
+        drop(file);
+        other_file.write_all(b"0123456789abcdef").unwrap();
+    }
+}
@@ -16,9 +16,15 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod filesystem;
+mod mem_fs;
+mod socket;
 mod types;
 
 pub use self::builder::*;
+pub use self::filesystem::*;
+pub use self::mem_fs::*;
+pub use self::socket::*;
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -26,12 +32,11 @@ pub use generational_arena::Index as Inode;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::{
-    borrow::Borrow,
     cell::Cell,
     fs,
     io::Write,
-    path::{Path, PathBuf},
-    time::SystemTime,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
 };
 use tracing::debug;
 
@@ -73,9 +78,12 @@ pub enum Kind {
     File {
         /// the open file, if it's open
         handle: Option<Box<dyn WasiFile>>,
-        /// The path on the host system where the file is located
+        /// The path of the file in its filesystem
         /// This is deprecated and will be removed soon
         path: PathBuf,
+        /// The filesystem the file is in
+        #[serde(default)]
+        filesystem: FileSystemIndex,
         /// Marks the file as a special file that only one `fd` can exist for
         /// This is useful when dealing with host-provided special files that
         /// should be looked up by path
@@ -85,9 +93,11 @@ pub enum Kind {
     Dir {
         /// Parent directory
         parent: Option<Inode>,
-        /// The path on the host system where the directory is located
-        // TODO: wrap it like WasiFile
+        /// The path of the directory in its filesystem
         path: PathBuf,
+        /// The filesystem the directory is in
+        #[serde(default)]
+        filesystem: FileSystemIndex,
         /// The entries of a directory are lazily filled.
         entries: HashMap<String, Inode>,
     },
@@ -109,7 +119,7 @@ pub enum Kind {
         base_po_dir: __wasi_fd_t,
         /// The path to the symlink from the `base_po_dir`
         path_to_symlink: PathBuf,
-        /// the value of the symlink as a relative path
+        /// the value of the symlink
         relative_path: PathBuf,
     },
     Buffer {
@@ -163,6 +173,9 @@ pub struct WasiFs {
     inode_counter: Cell<u64>,
     /// for fds still open after the file has been deleted
     pub orphan_fds: HashMap<Inode, InodeVal>,
+    /// The filesystems the preopened directories are mounted from, the
+    /// host's being at [`HOST_FILESYSTEM`]
+    pub filesystems: Vec<Arc<dyn FileSystem>>,
 }
 
 impl WasiFs {
@@ -194,6 +207,7 @@ impl WasiFs {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: dir.clone(),
+                    filesystem: HOST_FILESYSTEM,
                     entries: Default::default(),
                 }
             } else {
@@ -247,6 +261,7 @@ impl WasiFs {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: real_dir.clone(),
+                    filesystem: HOST_FILESYSTEM,
                     entries: Default::default(),
                 }
             } else {
@@ -299,6 +314,7 @@ impl WasiFs {
             read,
             write,
             create,
+            filesystem,
         } in preopens
         {
             debug!(
@@ -306,18 +322,37 @@ impl WasiFs {
                 &path.to_string_lossy(),
                 &alias
             );
-            let cur_dir_metadata = path.metadata().map_err(|e| {
-                format!(
-                    "Could not get metadata for file {:?}: {}",
-                    path,
-                    e.to_string()
-                )
-            })?;
+            let filesystem = match filesystem {
+                // the preopened directories of a filesystem share its index,
+                // so that entries can be renamed from one to the other
+                Some(filesystem) => match wasi_fs
+                    .filesystems
+                    .iter()
+                    .position(|mounted| Arc::ptr_eq(mounted, filesystem))
+                {
+                    Some(index) => index,
+                    None => {
+                        wasi_fs.filesystems.push(filesystem.clone());
+                        wasi_fs.filesystems.len() - 1
+                    }
+                },
+                None => HOST_FILESYSTEM,
+            };
+            let cur_dir_metadata = wasi_fs.filesystems[filesystem]
+                .metadata(path)
+                .map_err(|e| {
+                    format!(
+                        "Could not get metadata for file {:?}: {}",
+                        path,
+                        e.to_string()
+                    )
+                })?;
 
-            let kind = if cur_dir_metadata.is_dir() {
+            let kind = if cur_dir_metadata.st_filetype == __WASI_FILETYPE_DIRECTORY {
                 Kind::Dir {
                     parent: Some(root_inode),
                     path: path.clone(),
+                    filesystem,
                     entries: Default::default(),
                 }
             } else {
@@ -364,7 +399,8 @@ impl WasiFs {
                         | __WASI_RIGHT_PATH_CREATE_FILE
                         | __WASI_RIGHT_PATH_LINK_TARGET
                         | __WASI_RIGHT_PATH_OPEN
-                        | __WASI_RIGHT_PATH_RENAME_TARGET;
+                        | __WASI_RIGHT_PATH_RENAME_TARGET
+                        | __WASI_RIGHT_PATH_SYMLINK;
                 }
 
                 rights
@@ -428,6 +464,7 @@ impl WasiFs {
             next_fd: Cell::new(3),
             inode_counter: Cell::new(1024),
             orphan_fds: HashMap::new(),
+            filesystems: vec![Arc::new(HostFileSystem)],
         };
         wasi_fs.create_stdin();
         wasi_fs.create_stdout();
@@ -569,6 +606,7 @@ impl WasiFs {
                     let kind = Kind::Dir {
                         parent: Some(cur_inode),
                         path: PathBuf::from(""),
+                        filesystem: HOST_FILESYSTEM,
                         entries: HashMap::new(),
                     };
 
@@ -631,6 +669,7 @@ impl WasiFs {
                 let kind = Kind::File {
                     handle: Some(file),
                     path: PathBuf::from(""),
+                    filesystem: HOST_FILESYSTEM,
                     fd: Some(self.next_fd.get()),
                 };
 
@@ -717,246 +756,223 @@ impl WasiFs {
     /// `.` and `..`) and resolving symlinks (while preventing infinite
     /// loops/stack overflows).
     ///
-    /// TODO: expand upon exactly what the state of the returned value is,
-    /// explaining lazy-loading from the real file system and synchronizing
-    /// between them.
+    /// Directories, files and symlinks found along the way are loaded from
+    /// their filesystem and cached in the entries of their parent directory.
+    ///
+    /// The lookup never leaves the preopened directories: `..` stops at the
+    /// root, relative symlinks are resolved like any other path from the
+    /// directory containing them, and absolute symlinks only when they point
+    /// inside of a preopened directory. Symlinks are followed everywhere but
+    /// in the last component when `follow_symlinks` isn't set.
     ///
     /// This is where a lot of the magic happens, be very careful when editing
     /// this code.
-    ///
-    /// TODO: write more tests for this code
     fn get_inode_at_path_inner(
         &mut self,
         base: __wasi_fd_t,
         path: &str,
-        mut symlink_count: u32,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
-        if symlink_count > MAX_SYMLINKS {
-            return Err(__WASI_EMLINK);
+        let mut cur_inode = self.get_fd(base)?.inode;
+        let path = Path::new(path);
+        // the paths of the WASI program are relative to its directories
+        if path.has_root() && !matches!(self.inodes[cur_inode].kind, Kind::Root { .. }) {
+            return Err(__WASI_ENOTCAPABLE);
         }
-
-        let base_dir = self.get_fd(base)?;
-        let path: &Path = Path::new(path);
-
-        let mut cur_inode = base_dir.inode;
-        let n_components = path.components().count();
-        // TODO: rights checks
-        'path_iter: for (i, component) in path.components().enumerate() {
-            // used to terminate symlink resolution properly
-            let last_component = i + 1 == n_components;
-            // for each component traverse file structure
-            // loading inodes as necessary
-            'symlink_resolution: while symlink_count < MAX_SYMLINKS {
-                match &mut self.inodes[cur_inode].kind {
-                    Kind::Buffer { .. } => unimplemented!("state::get_inode_at_path for buffers"),
+        // the components left to resolve, last one first, which the values of
+        // the symlinks are pushed onto as they are followed
+        let mut components = Self::path_components(path);
+        let mut symlink_count = 0;
+
+        while let Some(component) = components.pop() {
+            if component == ".." {
+                cur_inode = match &self.inodes[cur_inode].kind {
+                    // the root's parent is the root
+                    Kind::Root { .. } => cur_inode,
                     Kind::Dir {
-                        ref mut entries,
-                        ref path,
-                        ref parent,
+                        parent: Some(parent),
                         ..
-                    } => {
-                        match component.as_os_str().to_string_lossy().borrow() {
-                            ".." => {
-                                if let Some(p) = parent {
-                                    cur_inode = *p;
-                                    continue 'path_iter;
-                                } else {
-                                    return Err(__WASI_EACCES);
-                                }
-                            }
-                            "." => continue 'path_iter,
-                            _ => (),
-                        }
-                        // used for full resolution of symlinks
-                        let mut loop_for_symlink = false;
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = *entry;
-                        } else {
-                            let file = {
-                                let mut cd = path.clone();
-                                cd.push(component);
-                                cd
-                            };
-                            let metadata = file.symlink_metadata().ok().ok_or(__WASI_EINVAL)?;
-                            let file_type = metadata.file_type();
-                            // we want to insert newly opened dirs and files, but not transient symlinks
-                            // TODO: explain why (think about this deeply when well rested)
-                            let mut should_insert = false;
-
-                            let kind = if file_type.is_dir() {
-                                should_insert = true;
-                                // load DIR
-                                Kind::Dir {
-                                    parent: Some(cur_inode),
-                                    path: file.clone(),
-                                    entries: Default::default(),
-                                }
-                            } else if file_type.is_file() {
-                                should_insert = true;
-                                // load file
-                                Kind::File {
-                                    handle: None,
-                                    path: file.clone(),
-                                    fd: None,
-                                }
-                            } else if file_type.is_symlink() {
-                                let link_value = file.read_link().ok().ok_or(__WASI_EIO)?;
-                                debug!("attempting to decompose path {:?}", link_value);
-
-                                let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
-                                    self.path_into_pre_open_and_relative_path(&file)?
-                                } else {
-                                    unimplemented!("Absolute symlinks are not yet supported");
-                                };
-                                loop_for_symlink = true;
-                                symlink_count += 1;
-                                Kind::Symlink {
-                                    base_po_dir: pre_open_dir_fd,
-                                    path_to_symlink: relative_path,
-                                    relative_path: link_value,
-                                }
-                            } else {
-                                #[cfg(unix)]
-                                {
-                                    use std::os::unix::fs::FileTypeExt;
-                                    let file_type: __wasi_filetype_t = if file_type.is_char_device()
-                                    {
-                                        __WASI_FILETYPE_CHARACTER_DEVICE
-                                    } else if file_type.is_block_device() {
-                                        __WASI_FILETYPE_BLOCK_DEVICE
-                                    } else if file_type.is_fifo() {
-                                        // FIFO doesn't seem to fit any other type, so unknown
-                                        __WASI_FILETYPE_UNKNOWN
-                                    } else if file_type.is_socket() {
-                                        // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
-                                        // a `__WASI_FILETYPE_SOCKET_DGRAM`?
-                                        __WASI_FILETYPE_SOCKET_STREAM
-                                    } else {
-                                        unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
-                                    };
-
-                                    let kind = Kind::File {
-                                        handle: None,
-                                        path: file.clone(),
-                                        fd: None,
-                                    };
-                                    let new_inode = self.create_inode_with_stat(
-                                        kind,
-                                        false,
-                                        file.to_string_lossy().to_string(),
-                                        __wasi_filestat_t {
-                                            st_filetype: file_type,
-                                            ..__wasi_filestat_t::default()
-                                        },
-                                    );
-                                    if let Kind::Dir {
-                                        ref mut entries, ..
-                                    } = &mut self.inodes[cur_inode].kind
-                                    {
-                                        entries.insert(
-                                            component.as_os_str().to_string_lossy().to_string(),
-                                            new_inode,
-                                        );
-                                    } else {
-                                        unreachable!(
-                                            "Attempted to insert special device into non-directory"
-                                        );
-                                    }
-                                    // perhaps just continue with symlink resolution and return at the end
-                                    return Ok(new_inode);
-                                }
-                                #[cfg(not(unix))]
-                                unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
-                            };
-
-                            let new_inode =
-                                self.create_inode(kind, false, file.to_string_lossy().to_string())?;
-                            if should_insert {
-                                if let Kind::Dir {
-                                    ref mut entries, ..
-                                } = &mut self.inodes[cur_inode].kind
-                                {
-                                    entries.insert(
-                                        component.as_os_str().to_string_lossy().to_string(),
-                                        new_inode,
-                                    );
-                                }
-                            }
-                            cur_inode = new_inode;
+                    } => *parent,
+                    Kind::Dir { parent: None, .. } => return Err(__WASI_EACCES),
+                    _ => return Err(__WASI_ENOTDIR),
+                };
+                continue;
+            }
 
-                            if loop_for_symlink && follow_symlinks {
-                                debug!("Following symlink to {:?}", cur_inode);
-                                continue 'symlink_resolution;
-                            }
-                        }
-                    }
-                    Kind::Root { entries } => {
-                        match component.as_os_str().to_string_lossy().borrow() {
-                            // the root's parent is the root
-                            ".." => continue 'path_iter,
-                            // the root's current directory is the root
-                            "." => continue 'path_iter,
-                            _ => (),
-                        }
+            let entry = match &self.inodes[cur_inode].kind {
+                Kind::Dir { entries, .. } => entries.get(&component).copied(),
+                Kind::Root { entries } => Some(*entries.get(&component).ok_or(__WASI_ENOENT)?),
+                Kind::File { .. } | Kind::Buffer { .. } | Kind::Symlink { .. } => {
+                    return Err(__WASI_ENOTDIR)
+                }
+            };
+            let entry = match entry {
+                Some(entry) => entry,
+                None => self.load_entry(cur_inode, &component)?,
+            };
 
-                        if let Some(entry) =
-                            entries.get(component.as_os_str().to_string_lossy().as_ref())
-                        {
-                            cur_inode = *entry;
-                        } else {
-                            return Err(__WASI_EINVAL);
-                        }
+            let is_last = components.is_empty();
+            match &self.inodes[entry].kind {
+                Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    relative_path,
+                } if !is_last || follow_symlinks => {
+                    symlink_count += 1;
+                    if symlink_count > MAX_SYMLINKS {
+                        return Err(__WASI_ELOOP);
                     }
-                    Kind::File { .. } => {
-                        return Err(__WASI_ENOTDIR);
-                    }
-                    Kind::Symlink {
-                        base_po_dir,
-                        path_to_symlink,
-                        relative_path,
-                    } => {
-                        let new_base_dir = *base_po_dir;
-                        // allocate to reborrow mutabily to recur
-                        let new_path = {
-                            /*if let Kind::Root { .. } = self.inodes[base_po_dir].kind {
-                                assert!(false, "symlinks should never be relative to the root");
-                            }*/
-                            let mut base = path_to_symlink.clone();
-                            // remove the symlink file itself from the path, leaving just the path from the base
-                            // to the dir containing the symlink
-                            base.pop();
-                            base.push(relative_path);
-                            base.to_string_lossy().to_string()
-                        };
-                        debug!("Following symlink recursively");
-                        let symlink_inode = self.get_inode_at_path_inner(
-                            new_base_dir,
-                            &new_path,
-                            symlink_count + 1,
-                            follow_symlinks,
-                        )?;
-                        cur_inode = symlink_inode;
-                        // if we're at the very end and we found a file, then we're done
-                        // TODO: figure out if this should also happen for directories?
-                        if let Kind::File { .. } = &self.inodes[cur_inode].kind {
-                            // check if on last step
-                            if last_component {
-                                break 'symlink_resolution;
-                            }
-                        }
-                        continue 'symlink_resolution;
+                    debug!(
+                        "Following symlink {:?} to {:?}",
+                        path_to_symlink, relative_path
+                    );
+                    if relative_path.is_absolute() {
+                        let (filesystem, _) = self
+                            .symlink_path(*base_po_dir, path_to_symlink)
+                            .ok_or(__WASI_EIO)?;
+                        // absolute symlinks can't lead out of the preopened directories
+                        let (po_fd, rest) = self
+                            .path_into_pre_open_and_relative_path(filesystem, relative_path)
+                            .map_err(|_| __WASI_ENOTCAPABLE)?;
+                        components.extend(Self::path_components(&rest));
+                        cur_inode = self.fd_map[&po_fd].inode;
+                    } else {
+                        components.extend(Self::path_components(relative_path));
                     }
                 }
-                break 'symlink_resolution;
+                _ => cur_inode = entry,
             }
         }
 
         Ok(cur_inode)
     }
 
+    /// Returns the components of `path` in the order in which
+    /// [`WasiFs::get_inode_at_path_inner`] resolves them, last one first.
+    fn path_components(path: &Path) -> Vec<String> {
+        path.components()
+            .rev()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                Component::ParentDir => Some("..".to_string()),
+                Component::CurDir | Component::RootDir | Component::Prefix(_) => None,
+            })
+            .collect()
+    }
+
+    /// Loads the entry `name` of the directory `dir_inode` from its
+    /// filesystem, caching it in the entries of the directory.
+    fn load_entry(&mut self, dir_inode: Inode, name: &str) -> Result<Inode, __wasi_errno_t> {
+        let (file, filesystem) = match &self.inodes[dir_inode].kind {
+            Kind::Dir {
+                path, filesystem, ..
+            } => (path.join(name), *filesystem),
+            _ => return Err(__WASI_ENOTDIR),
+        };
+        let metadata = self.filesystems[filesystem]
+            .symlink_metadata(&file)
+            .map_err(WasiFsError::into_wasi_err)?;
+
+        let kind = match metadata.st_filetype {
+            __WASI_FILETYPE_DIRECTORY => Kind::Dir {
+                parent: Some(dir_inode),
+                path: file.clone(),
+                filesystem,
+                entries: Default::default(),
+            },
+            __WASI_FILETYPE_SYMBOLIC_LINK => {
+                let link_value = self.filesystems[filesystem]
+                    .read_link(&file)
+                    .map_err(WasiFsError::into_wasi_err)?;
+                let (base_po_dir, path_to_symlink) =
+                    self.path_into_pre_open_and_relative_path(filesystem, &file)?;
+                Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    relative_path: link_value,
+                }
+            }
+            // regular files, character and block devices, sockets and
+            // anything else the filesystem can't describe
+            _ => Kind::File {
+                handle: None,
+                path: file.clone(),
+                filesystem,
+                fd: None,
+            },
+        };
+        let new_inode =
+            self.create_inode_with_stat(kind, false, file.to_string_lossy().into_owned(), metadata);
+        if let Kind::Dir { entries, .. } = &mut self.inodes[dir_inode].kind {
+            entries.insert(name.to_string(), new_inode);
+        }
+        Ok(new_inode)
+    }
+
+    /// Returns the filesystem and the path in it of a symlink.
+    pub(crate) fn symlink_path(
+        &self,
+        base_po_dir: __wasi_fd_t,
+        path_to_symlink: &Path,
+    ) -> Option<(FileSystemIndex, PathBuf)> {
+        let base_po_inode = self.fd_map.get(&base_po_dir)?.inode;
+        match &self.inodes[base_po_inode].kind {
+            Kind::Dir {
+                path, filesystem, ..
+            } => Some((*filesystem, path.join(path_to_symlink))),
+            _ => None,
+        }
+    }
+
+    /// Updates the paths of `inode`, and of the entries cached under it, after
+    /// it was moved from `from` to `to` in its filesystem.
+    pub(crate) fn rebase_paths(&mut self, inode: Inode, from: &Path, to: &Path) {
+        let rebase = |path: &Path| match path.strip_prefix(from) {
+            Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+            Ok(rest) => to.join(rest),
+            Err(_) => path.to_path_buf(),
+        };
+        let mut inodes = vec![inode];
+        while let Some(inode) = inodes.pop() {
+            let symlink = match &mut self.inodes[inode].kind {
+                Kind::Dir { path, entries, .. } => {
+                    *path = rebase(path);
+                    inodes.extend(entries.values());
+                    None
+                }
+                Kind::File { path, fd: None, .. } => {
+                    *path = rebase(path);
+                    None
+                }
+                Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    ..
+                } => Some((*base_po_dir, path_to_symlink.clone())),
+                Kind::File { .. } | Kind::Buffer { .. } | Kind::Root { .. } => None,
+            };
+            let moved_symlink = symlink
+                .and_then(|(base_po_dir, path_to_symlink)| {
+                    self.symlink_path(base_po_dir, &path_to_symlink)
+                })
+                .and_then(|(filesystem, path)| {
+                    self.path_into_pre_open_and_relative_path(filesystem, &rebase(&path))
+                        .ok()
+                });
+            if let Some((new_base_po_dir, new_path_to_symlink)) = moved_symlink {
+                if let Kind::Symlink {
+                    base_po_dir,
+                    path_to_symlink,
+                    ..
+                } = &mut self.inodes[inode].kind
+                {
+                    *base_po_dir = new_base_po_dir;
+                    *path_to_symlink = new_path_to_symlink;
+                }
+            }
+        }
+    }
+
     /// Splits a path into the first preopened directory that is a parent of it,
     /// if such a preopened directory exists, and the rest of the path.
     ///
@@ -965,16 +981,23 @@ impl WasiFs {
     ///
     /// TODO: evaluate users of this function and explain why this behavior is
     /// not the same as libpreopen or update its behavior to be the same.
-    fn path_into_pre_open_and_relative_path(
+    pub(crate) fn path_into_pre_open_and_relative_path(
         &self,
+        filesystem: FileSystemIndex,
         path: &Path,
     ) -> Result<(__wasi_fd_t, PathBuf), __wasi_errno_t> {
         // for each preopened directory
         for po_fd in &self.preopen_fds {
             let po_inode = self.fd_map[po_fd].inode;
             let po_path = match &self.inodes[po_inode].kind {
+                // only the preopened directories of the same filesystem can contain `path`
+                Kind::Dir {
+                    filesystem: po_filesystem,
+                    ..
+                } if *po_filesystem != filesystem => continue,
                 Kind::Dir { path, .. } => &**path,
-                Kind::Root { .. } => Path::new("/"),
+                // the root isn't a directory of any filesystem
+                Kind::Root { .. } => continue,
                 _ => unreachable!("Preopened FD that's not a directory or the root"),
             };
             // stem path based on it
@@ -1020,55 +1043,28 @@ impl WasiFs {
         Ok(out)
     }
 
-    /// finds the number of directories between the fd and the inode if they're connected
-    /// expects inode to point to a directory
-    pub(crate) fn path_depth_from_fd(
-        &self,
-        fd: __wasi_fd_t,
-        inode: Inode,
-    ) -> Result<usize, __wasi_errno_t> {
-        let mut counter = 0;
-        let base_fd = self.get_fd(fd)?;
-        let base_inode = base_fd.inode;
-        let mut cur_inode = inode;
-
-        while cur_inode != base_inode {
-            counter += 1;
-            match &self.inodes[cur_inode].kind {
-                Kind::Dir { parent, .. } => {
-                    if let Some(p) = parent {
-                        cur_inode = *p;
-                    }
-                }
-                _ => return Err(__WASI_EINVAL),
-            }
-        }
-
-        Ok(counter)
-    }
-
     /// gets a host file from a base directory and a path
     /// this function ensures the fs remains sandboxed
-    // NOTE: follow symlinks is super weird right now
-    // even if it's false, it still follows symlinks, just not the last
-    // symlink so
-    // This will be resolved when we have tests asserting the correct behavior
+    ///
+    /// Symlinks are always followed, except for the last component of the
+    /// path when `follow_symlinks` isn't set.
     pub(crate) fn get_inode_at_path(
         &mut self,
         base: __wasi_fd_t,
         path: &str,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
-        self.get_inode_at_path_inner(base, path, 0, follow_symlinks)
+        self.get_inode_at_path_inner(base, path, follow_symlinks)
     }
 
     /// Returns the parent Dir or Root that the file at a given path is in and the file name
     /// stripped off
+    ///
+    /// The parent being a directory, symlinks are followed all the way to it.
     pub(crate) fn get_parent_inode_at_path(
         &mut self,
         base: __wasi_fd_t,
         path: &Path,
-        follow_symlinks: bool,
     ) -> Result<(Inode, String), __wasi_errno_t> {
         let mut parent_dir = std::path::PathBuf::new();
         let mut components = path.components().rev();
@@ -1081,7 +1077,7 @@ impl WasiFs {
         for comp in components.rev() {
             parent_dir.push(comp);
         }
-        self.get_inode_at_path(base, &parent_dir.to_string_lossy(), follow_symlinks)
+        self.get_inode_at_path(base, &parent_dir.to_string_lossy(), true)
             .map(|v| (v, new_entity_name))
     }
 
@@ -1147,7 +1143,11 @@ impl WasiFs {
         debug!("fdstat: {:?}", fd);
 
         Ok(__wasi_fdstat_t {
-            fs_filetype: match self.inodes[fd.inode].kind {
+            fs_filetype: match &self.inodes[fd.inode].kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } if handle.downcast_ref::<WasiSocket>().is_some() => __WASI_FILETYPE_SOCKET_STREAM,
                 Kind::File { .. } => __WASI_FILETYPE_REGULAR_FILE,
                 Kind::Dir { .. } => __WASI_FILETYPE_DIRECTORY,
                 Kind::Symlink { .. } => __WASI_FILETYPE_SYMBOLIC_LINK,
@@ -1212,7 +1212,7 @@ impl WasiFs {
                     }
                     // TODO: verify this behavior
                     Kind::Dir { .. } => return Err(__WASI_EISDIR),
-                    Kind::Symlink { .. } => unimplemented!("WasiFs::flush Kind::Symlink"),
+                    Kind::Symlink { .. } => return Err(__WASI_EBADF),
                     Kind::Buffer { .. } => (),
                     _ => return Err(__WASI_EIO),
                 }
@@ -1315,6 +1315,55 @@ impl WasiFs {
         })
     }
 
+    /// Hands `socket` to the WASI program, under a new fd with the given
+    /// `rights`.
+    pub(crate) fn preopen_socket(
+        &mut self,
+        socket: WasiSocket,
+        rights: __wasi_rights_t,
+    ) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        let stat = __wasi_filestat_t {
+            st_filetype: __WASI_FILETYPE_SOCKET_STREAM,
+            st_ino: self.get_next_inode_index(),
+            ..__wasi_filestat_t::default()
+        };
+        let kind = Kind::File {
+            fd: Some(self.next_fd.get()),
+            handle: Some(Box::new(socket)),
+            path: "".into(),
+            filesystem: HOST_FILESYSTEM,
+        };
+        let inode = self.inodes.insert(InodeVal {
+            stat,
+            is_preopened: false,
+            name: "socket".to_string(),
+            kind,
+        });
+        self.create_fd(rights, 0, 0, Fd::READ | Fd::WRITE, inode)
+    }
+
+    /// Returns the socket behind `fd`, if it has the given `rights`.
+    pub(crate) fn get_socket_mut(
+        &mut self,
+        fd: __wasi_fd_t,
+        rights: __wasi_rights_t,
+    ) -> Result<&mut WasiSocket, __wasi_errno_t> {
+        let fd = self.fd_map.get(&fd).ok_or(__WASI_EBADF)?;
+        let inode = fd.inode;
+        let socket = match &mut self.inodes[inode].kind {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.downcast_mut::<WasiSocket>(),
+            _ => None,
+        }
+        .ok_or(__WASI_ENOTSOCK)?;
+        if fd.rights & rights != rights {
+            return Err(__WASI_ENOTCAPABLE);
+        }
+        Ok(socket)
+    }
+
     fn create_stdout(&mut self) {
         self.create_std_dev_inner(
             Box::new(Stdout),
@@ -1360,6 +1409,7 @@ impl WasiFs {
             fd: Some(raw_fd),
             handle: Some(handle),
             path: "".into(),
+            filesystem: HOST_FILESYSTEM,
         };
         let inode = self.inodes.insert(InodeVal {
             stat,
@@ -1382,73 +1432,41 @@ impl WasiFs {
     }
 
     pub fn get_stat_for_kind(&self, kind: &Kind) -> Option<__wasi_filestat_t> {
-        let md = match kind {
-            Kind::File { handle, path, .. } => match handle {
-                Some(wf) => {
-                    return Some(__wasi_filestat_t {
-                        st_filetype: __WASI_FILETYPE_REGULAR_FILE,
-                        st_size: wf.size(),
-                        st_atim: wf.last_accessed(),
-                        st_mtim: wf.last_modified(),
-                        st_ctim: wf.created_time(),
-
-                        ..__wasi_filestat_t::default()
-                    })
-                }
-                None => path.metadata().ok()?,
+        match kind {
+            Kind::File {
+                handle,
+                path,
+                filesystem,
+                ..
+            } => match handle {
+                Some(wf) => Some(__wasi_filestat_t {
+                    st_filetype: if wf.downcast_ref::<WasiSocket>().is_some() {
+                        __WASI_FILETYPE_SOCKET_STREAM
+                    } else {
+                        __WASI_FILETYPE_REGULAR_FILE
+                    },
+                    st_size: wf.size(),
+                    st_atim: wf.last_accessed(),
+                    st_mtim: wf.last_modified(),
+                    st_ctim: wf.created_time(),
+
+                    ..__wasi_filestat_t::default()
+                }),
+                None => self.filesystems[*filesystem].metadata(path).ok(),
             },
-            Kind::Dir { path, .. } => path.metadata().ok()?,
+            Kind::Dir {
+                path, filesystem, ..
+            } => self.filesystems[*filesystem].metadata(path).ok(),
             Kind::Symlink {
                 base_po_dir,
                 path_to_symlink,
                 ..
             } => {
-                let base_po_inode = &self.fd_map[base_po_dir].inode;
-                let base_po_inode_v = &self.inodes[*base_po_inode];
-                match &base_po_inode_v.kind {
-                    Kind::Root { .. } => {
-                        path_to_symlink.clone().symlink_metadata().ok()?
-                    }
-                    Kind::Dir { path, .. } => {
-                        let mut real_path = path.clone();
-                        // PHASE 1: ignore all possible symlinks in `relative_path`
-                        // TODO: walk the segments of `relative_path` via the entries of the Dir
-                        //       use helper function to avoid duplicating this logic (walking this will require
-                        //       &self to be &mut sel
-                        // TODO: adjust size of symlink, too
-                        //      for all paths adjusted think about this
-                        real_path.push(path_to_symlink);
-                        real_path.symlink_metadata().ok()?
-                    }
-                    // if this triggers, there's a bug in the symlink code
-                    _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
-                }
+                let (filesystem, path) = self.symlink_path(*base_po_dir, path_to_symlink)?;
+                self.filesystems[filesystem].symlink_metadata(&path).ok()
             }
-            _ => return None,
-        };
-        Some(__wasi_filestat_t {
-            st_filetype: host_file_type_to_wasi_file_type(md.file_type()),
-            st_size: md.len(),
-            st_atim: md
-                .accessed()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_mtim: md
-                .modified()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_ctim: md
-                .created()
-                .ok()
-                .and_then(|ct| ct.duration_since(SystemTime::UNIX_EPOCH).ok())
-                .map(|ct| ct.as_nanos() as u64)
-                .unwrap_or(0),
-            ..__wasi_filestat_t::default()
-        })
+            _ => None,
+        }
     }
 
     /// Closes an open FD, handling all details such as FD being preopen
@@ -0,0 +1,229 @@
+//! Sockets of the host handed to the WASI program.
+//!
+//! WASI programs can't create sockets themselves, they can only use the
+//! ones preopened for them with [`WasiStateBuilder::preopen_socket`].
+//!
+//! [`WasiStateBuilder::preopen_socket`]: crate::WasiStateBuilder::preopen_socket
+
+use crate::state::{WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Seek, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// A socket of the host, as seen by the WASI program.
+///
+/// Sockets can't be serialized: a [`WasiState`](crate::WasiState) which
+/// is frozen and unfrozen gets back disconnected sockets, on which the
+/// socket operations fail with `__WASI_ENOTCONN`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WasiSocket {
+    #[serde(skip)]
+    socket: Option<HostSocket>,
+}
+
+#[derive(Debug)]
+enum HostSocket {
+    TcpListener(TcpListener),
+    TcpStream(TcpStream),
+    #[cfg(unix)]
+    UnixListener(UnixListener),
+    #[cfg(unix)]
+    UnixStream(UnixStream),
+}
+
+impl From<TcpListener> for WasiSocket {
+    fn from(listener: TcpListener) -> Self {
+        Self::from_host(HostSocket::TcpListener(listener))
+    }
+}
+
+impl From<TcpStream> for WasiSocket {
+    fn from(stream: TcpStream) -> Self {
+        Self::from_host(HostSocket::TcpStream(stream))
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixListener> for WasiSocket {
+    fn from(listener: UnixListener) -> Self {
+        Self::from_host(HostSocket::UnixListener(listener))
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for WasiSocket {
+    fn from(stream: UnixStream) -> Self {
+        Self::from_host(HostSocket::UnixStream(stream))
+    }
+}
+
+impl WasiSocket {
+    fn from_host(socket: HostSocket) -> Self {
+        Self {
+            socket: Some(socket),
+        }
+    }
+
+    /// Receives data into `buf`, leaving it in the socket if `peek` is set.
+    /// Unless `wait_all` is set, returns as soon as some data is received.
+    pub(crate) fn recv(
+        &mut self,
+        buf: &mut [u8],
+        peek: bool,
+        wait_all: bool,
+    ) -> Result<usize, WasiFsError> {
+        if peek {
+            return match &self.socket {
+                Some(HostSocket::TcpStream(stream)) => Ok(stream.peek(buf)?),
+                #[cfg(unix)]
+                Some(HostSocket::UnixStream(stream)) => {
+                    use std::os::unix::io::AsRawFd;
+                    let result = unsafe {
+                        libc::recv(
+                            stream.as_raw_fd(),
+                            buf.as_mut_ptr() as *mut libc::c_void,
+                            buf.len(),
+                            libc::MSG_PEEK,
+                        )
+                    };
+                    if result < 0 {
+                        Err(io::Error::last_os_error().into())
+                    } else {
+                        Ok(result as usize)
+                    }
+                }
+                _ => Err(WasiFsError::NotConnected),
+            };
+        }
+        let mut received = 0;
+        while received < buf.len() {
+            let amt = self.read(&mut buf[received..])?;
+            received += amt;
+            if amt == 0 || !wait_all {
+                break;
+            }
+        }
+        Ok(received)
+    }
+
+    /// Sends the data of `buf`, returning how much of it was sent.
+    pub(crate) fn send(&mut self, buf: &[u8]) -> Result<usize, WasiFsError> {
+        Ok(self.write(buf)?)
+    }
+
+    /// Shuts down the reading and/or writing halves of the connection.
+    pub(crate) fn shutdown(&self, how: Shutdown) -> Result<(), WasiFsError> {
+        match &self.socket {
+            Some(HostSocket::TcpStream(stream)) => Ok(stream.shutdown(how)?),
+            #[cfg(unix)]
+            Some(HostSocket::UnixStream(stream)) => Ok(stream.shutdown(how)?),
+            _ => Err(WasiFsError::NotConnected),
+        }
+    }
+
+    fn not_connected() -> io::Error {
+        io::Error::new(io::ErrorKind::NotConnected, "the socket isn't connected")
+    }
+}
+
+impl Read for WasiSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.socket {
+            Some(HostSocket::TcpStream(stream)) => stream.read(buf),
+            #[cfg(unix)]
+            Some(HostSocket::UnixStream(stream)) => stream.read(buf),
+            _ => Err(Self::not_connected()),
+        }
+    }
+}
+
+impl Write for WasiSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.socket {
+            Some(HostSocket::TcpStream(stream)) => stream.write(buf),
+            #[cfg(unix)]
+            Some(HostSocket::UnixStream(stream)) => stream.write(buf),
+            _ => Err(Self::not_connected()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.socket {
+            Some(HostSocket::TcpStream(stream)) => stream.flush(),
+            #[cfg(unix)]
+            Some(HostSocket::UnixStream(stream)) => stream.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Seek for WasiSocket {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek in a socket",
+        ))
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for WasiSocket {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        0
+    }
+
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        0
+    }
+
+    fn created_time(&self) -> __wasi_timestamp_t {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        let host_fd = self.get_raw_fd().ok_or(WasiFsError::NotConnected)?;
+        let mut bytes_found = 0 as libc::c_int;
+        let result = unsafe { libc::ioctl(host_fd, libc::FIONREAD, &mut bytes_found) };
+        if result < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(bytes_found as usize)
+    }
+
+    #[cfg(not(unix))]
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Err(WasiFsError::UnknownError(__WASI_ENOTSUP))
+    }
+
+    #[cfg(unix)]
+    fn get_raw_fd(&self) -> Option<i32> {
+        use std::os::unix::io::AsRawFd;
+        match self.socket.as_ref()? {
+            HostSocket::TcpListener(listener) => Some(listener.as_raw_fd()),
+            HostSocket::TcpStream(stream) => Some(stream.as_raw_fd()),
+            HostSocket::UnixListener(listener) => Some(listener.as_raw_fd()),
+            HostSocket::UnixStream(stream) => Some(stream.as_raw_fd()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn get_raw_fd(&self) -> Option<i32> {
+        None
+    }
+}
@@ -17,9 +17,8 @@ use self::types::*;
 use crate::{
     ptr::{Array, WasmPtr},
     state::{
-        self, host_file_type_to_wasi_file_type, iterate_poll_events, poll, Fd, HostFile, Inode,
-        InodeVal, Kind, PollEvent, PollEventBuilder, WasiFile, WasiFsError, WasiState,
-        MAX_SYMLINKS,
+        self, iterate_poll_events, poll, Fd, Inode, InodeVal, Kind, OpenOptions, PollEvent,
+        PollEventBuilder, WasiFile, WasiFsError, WasiState, MAX_SYMLINKS,
     },
     WasiEnv, WasiError,
 };
@@ -27,6 +26,7 @@ use std::borrow::Borrow;
 use std::cell::Cell;
 use std::convert::{Infallible, TryInto};
 use std::io::{self, Read, Seek, Write};
+use std::net::Shutdown;
 use tracing::{debug, trace};
 use wasmer::{Memory, RuntimeError, Value};
 
@@ -52,7 +52,7 @@ fn write_bytes_inner<T: Write>(
         let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
         write_loc
             .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
+            .map_err(|e| WasiFsError::from(e).into_wasi_err())?;
 
         // TODO: handle failure more accurately
         bytes_written += iov_inner.buf_len;
@@ -690,7 +690,7 @@ pub fn fd_pread(
                     }
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pread"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
                     wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, iov_cells))
                 }
@@ -845,7 +845,7 @@ pub fn fd_pwrite(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pwrite"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => wasi_try!(write_bytes(
                     &mut buffer[(offset as usize)..],
                     memory,
@@ -921,7 +921,7 @@ pub fn fd_read(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
                     wasi_try!(read_bytes(&buffer[offset..], memory, iovs_arr_cell))
                 }
@@ -975,22 +975,29 @@ pub fn fd_readdir(
     let mut buf_idx = 0;
 
     let entries: Vec<(String, u8, u64)> = match &state.fs.inodes[working_dir.inode].kind {
-        Kind::Dir { path, entries, .. } => {
+        Kind::Dir {
+            path,
+            filesystem,
+            entries,
+            ..
+        } => {
             // TODO: refactor this code
             // we need to support multiple calls,
             // simple and obviously correct implementation for now:
             // maintain consistent order via lexacographic sorting
-            let fs_info = wasi_try!(wasi_try!(std::fs::read_dir(path).map_err(|_| __WASI_EIO))
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| __WASI_EIO));
-            let mut entry_vec = wasi_try!(fs_info
+            let fs_info = wasi_try!(state.fs.filesystems[*filesystem]
+                .read_dir(path)
+                .map_err(WasiFsError::into_wasi_err));
+            let mut entry_vec = fs_info
                 .into_iter()
-                .map(|entry| Ok((
-                    entry.file_name().to_string_lossy().to_string(),
-                    host_file_type_to_wasi_file_type(entry.file_type().map_err(|_| __WASI_EIO)?),
-                    0, // TODO: inode
-                )))
-                .collect::<Result<Vec<(String, u8, u64)>, _>>());
+                .map(|entry| {
+                    (
+                        entry.name,
+                        entry.file_type,
+                        0, // TODO: inode
+                    )
+                })
+                .collect::<Vec<(String, u8, u64)>>();
             entry_vec.extend(
                 entries
                     .iter()
@@ -1135,9 +1142,7 @@ pub fn fd_seek(
                         return __WASI_EINVAL;
                     }
                 }
-                Kind::Symlink { .. } => {
-                    unimplemented!("wasi::fd_seek not implemented for symlinks")
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Dir { .. } | Kind::Root { .. } => {
                     // TODO: check this
                     return __WASI_EINVAL;
@@ -1299,7 +1304,7 @@ pub fn fd_write(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_write"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
                     wasi_try!(write_bytes(&mut buffer[offset..], memory, iovs_arr_cell))
                 }
@@ -1374,6 +1379,7 @@ pub fn path_create_directory(
             Kind::Dir {
                 ref mut entries,
                 path,
+                filesystem,
                 parent,
             } => {
                 match comp.borrow() {
@@ -1392,14 +1398,20 @@ pub fn path_create_directory(
                     let mut adjusted_path = path.clone();
                     // TODO: double check this doesn't risk breaking the sandbox
                     adjusted_path.push(comp);
-                    if adjusted_path.exists() && !adjusted_path.is_dir() {
-                        return __WASI_ENOTDIR;
-                    } else if !adjusted_path.exists() {
-                        wasi_try!(std::fs::create_dir(&adjusted_path).ok(), __WASI_EIO);
+                    let filesystem = *filesystem;
+                    match state.fs.filesystems[filesystem].metadata(&adjusted_path) {
+                        Ok(stat) if stat.st_filetype != __WASI_FILETYPE_DIRECTORY => {
+                            return __WASI_ENOTDIR;
+                        }
+                        Ok(_) => (),
+                        Err(_) => wasi_try!(state.fs.filesystems[filesystem]
+                            .create_dir(&adjusted_path)
+                            .map_err(WasiFsError::into_wasi_err)),
                     }
                     let kind = Kind::Dir {
                         parent: Some(cur_dir_inode),
                         path: adjusted_path,
+                        filesystem,
                         entries: Default::default(),
                     };
                     let new_inode = wasi_try!(state.fs.create_inode(kind, false, comp.to_string()));
@@ -1617,9 +1629,7 @@ pub fn path_link(
     ));
     let target_path_arg = std::path::PathBuf::from(new_path_str);
     let (target_parent_inode, new_entry_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(new_fd, &target_path_arg, false));
+        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, &target_path_arg));
 
     if state.fs.inodes[source_inode].stat.st_nlink == __wasi_linkcount_t::max_value() {
         return __WASI_EMLINK;
@@ -1718,12 +1728,15 @@ pub fn path_open(
     let adjusted_rights = /*fs_rights_base &*/ working_dir_rights_inheriting;
     let inode = if let Ok(inode) = maybe_inode {
         // Happy path, we found the file we're trying to open
-        match &mut state.fs.inodes[inode].kind {
+        let wasi_fs = &mut state.fs;
+        match &mut wasi_fs.inodes[inode].kind {
             Kind::File {
                 ref mut handle,
                 path,
+                filesystem,
                 fd,
             } => {
+                let filesystem = &wasi_fs.filesystems[*filesystem];
                 if let Some(special_fd) = fd {
                     // short circuit if we're dealing with a special file
                     assert!(handle.is_some());
@@ -1733,10 +1746,9 @@ pub fn path_open(
                 if o_flags & __WASI_O_DIRECTORY != 0 {
                     return __WASI_ENOTDIR;
                 }
-                if o_flags & __WASI_O_EXCL != 0 && path.exists() {
+                if o_flags & __WASI_O_EXCL != 0 && filesystem.metadata(path).is_ok() {
                     return __WASI_EEXIST;
                 }
-                let mut open_options = std::fs::OpenOptions::new();
                 let write_permission = adjusted_rights & __WASI_RIGHT_FD_WRITE != 0;
                 // append, truncate, and create all require the permission to write
                 let (append_permission, truncate_permission, create_permission) =
@@ -1749,13 +1761,15 @@ pub fn path_open(
                     } else {
                         (false, false, false)
                     };
-                let open_options = open_options
-                    .read(true)
+                let open_options = OpenOptions {
+                    read: true,
                     // TODO: ensure these rights are actually valid given parent, etc.
-                    .write(write_permission)
-                    .create(create_permission)
-                    .append(append_permission)
-                    .truncate(truncate_permission);
+                    write: write_permission,
+                    create: create_permission,
+                    append: append_permission,
+                    truncate: truncate_permission,
+                    create_new: false,
+                };
                 open_flags |= Fd::READ;
                 if adjusted_rights & __WASI_RIGHT_FD_WRITE != 0 {
                     open_flags |= Fd::WRITE;
@@ -1766,85 +1780,85 @@ pub fn path_open(
                 if o_flags & __WASI_O_TRUNC != 0 {
                     open_flags |= Fd::TRUNCATE;
                 }
-                *handle = Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&path).map_err(|_| __WASI_EIO)),
-                    path.to_path_buf(),
-                    true,
-                    adjusted_rights & __WASI_RIGHT_FD_WRITE != 0,
-                    false,
-                )));
+                *handle = Some(wasi_try!(filesystem
+                    .open(path, &open_options)
+                    .map_err(WasiFsError::into_wasi_err)));
             }
-            Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
-            Kind::Dir { .. } | Kind::Root { .. } => {
-                // TODO: adjust these to be correct
-                if o_flags & __WASI_O_EXCL != 0 && path_arg.exists() {
+            Kind::Buffer { buffer } => {
+                if o_flags & __WASI_O_DIRECTORY != 0 {
+                    return __WASI_ENOTDIR;
+                }
+                if o_flags & __WASI_O_EXCL != 0 {
                     return __WASI_EEXIST;
                 }
+                open_flags |= Fd::READ;
+                if adjusted_rights & __WASI_RIGHT_FD_WRITE != 0 {
+                    open_flags |= Fd::WRITE;
+                    if o_flags & __WASI_O_TRUNC != 0 {
+                        buffer.clear();
+                        open_flags |= Fd::TRUNCATE;
+                    }
+                }
             }
-            Kind::Symlink {
-                base_po_dir,
-                path_to_symlink,
-                relative_path,
-            } => {
-                // I think this should return an error (because symlinks should be resolved away by the path traversal)
-                // TODO: investigate this
-                unimplemented!("SYMLINKS IN PATH_OPEN");
+            Kind::Dir { .. } | Kind::Root { .. } => {
+                if o_flags & __WASI_O_EXCL != 0 {
+                    return __WASI_EEXIST;
+                }
             }
+            // symlinks are only left unresolved when they aren't to be followed
+            Kind::Symlink { .. } => return __WASI_ELOOP,
         }
         inode
     } else {
         // less-happy path, we have to try to create the file
         debug!("Maybe creating file");
-        if o_flags & __WASI_O_CREAT != 0 {
+        if o_flags & __WASI_O_CREAT != 0 && maybe_inode == Err(__WASI_ENOENT) {
             if o_flags & __WASI_O_DIRECTORY != 0 {
                 return __WASI_ENOTDIR;
             }
             debug!("Creating file");
             // strip end file name
 
-            let (parent_inode, new_entity_name) = wasi_try!(state.fs.get_parent_inode_at_path(
-                dirfd,
-                &path_arg,
-                dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0
-            ));
-            let new_file_host_path = match &state.fs.inodes[parent_inode].kind {
-                Kind::Dir { path, .. } => {
+            let (parent_inode, new_entity_name) =
+                wasi_try!(state.fs.get_parent_inode_at_path(dirfd, &path_arg));
+            let (new_file_path, filesystem) = match &state.fs.inodes[parent_inode].kind {
+                Kind::Dir {
+                    path, filesystem, ..
+                } => {
                     let mut new_path = path.clone();
                     new_path.push(&new_entity_name);
-                    new_path
+                    (new_path, *filesystem)
                 }
                 Kind::Root { .. } => return __WASI_EACCES,
                 _ => return __WASI_EINVAL,
             };
-            // once we got the data we need from the parent, we lookup the host file
+            // once we got the data we need from the parent, we create the file in its filesystem
             // todo: extra check that opening with write access is okay
             let handle = {
-                let mut open_options = std::fs::OpenOptions::new();
-                let open_options = open_options
-                    .read(true)
-                    .append(fs_flags & __WASI_FDFLAG_APPEND != 0)
+                let open_options = OpenOptions {
+                    read: true,
+                    append: fs_flags & __WASI_FDFLAG_APPEND != 0,
                     // TODO: ensure these rights are actually valid given parent, etc.
                     // write access is required for creating a file
-                    .write(true)
-                    .create_new(true);
+                    write: true,
+                    create_new: true,
+                    ..OpenOptions::default()
+                };
                 open_flags |= Fd::READ | Fd::WRITE | Fd::CREATE | Fd::TRUNCATE;
 
-                Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&new_file_host_path).map_err(|e| {
+                Some(wasi_try!(state.fs.filesystems[filesystem]
+                    .open(&new_file_path, &open_options)
+                    .map_err(|e| {
                         debug!("Error opening file {}", e);
-                        __WASI_EIO
-                    })),
-                    new_file_host_path.clone(),
-                    true,
-                    true,
-                    true,
-                )) as Box<dyn WasiFile>)
+                        e.into_wasi_err()
+                    })))
             };
 
             let new_inode = {
                 let kind = Kind::File {
                     handle,
-                    path: new_file_host_path,
+                    path: new_file_path,
+                    filesystem,
                     fd: None,
                 };
                 wasi_try!(state.fs.create_inode(kind, false, new_entity_name.clone()))
@@ -1986,19 +2000,26 @@ pub fn path_remove_directory(
     let path_str = unsafe { get_input_str!(memory, path, path_len) };
 
     let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
-    let (parent_inode, childs_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+    let (parent_inode, childs_name) = wasi_try!(state
+        .fs
+        .get_parent_inode_at_path(fd, std::path::Path::new(path_str)));
 
-    let host_path_to_remove = match &state.fs.inodes[inode].kind {
-        Kind::Dir { entries, path, .. } => {
+    let (path_to_remove, filesystem) = match &state.fs.inodes[inode].kind {
+        Kind::Dir {
+            entries,
+            path,
+            filesystem,
+            ..
+        } => {
             if !entries.is_empty()
-                || wasi_try!(std::fs::read_dir(path).ok(), __WASI_EIO).count() != 0
+                || !wasi_try!(state.fs.filesystems[*filesystem]
+                    .read_dir(path)
+                    .map_err(WasiFsError::into_wasi_err))
+                .is_empty()
             {
                 return __WASI_ENOTEMPTY;
             }
-            path.clone()
+            (path.clone(), *filesystem)
         }
         Kind::Root { .. } => return __WASI_EACCES,
         _ => return __WASI_ENOTDIR,
@@ -2018,7 +2039,7 @@ pub fn path_remove_directory(
         ),
     }
 
-    if std::fs::remove_dir(path_str).is_err() {
+    if let Err(e) = state.fs.filesystems[filesystem].remove_dir(&path_to_remove) {
         // reinsert to prevent FS from being in bad state
         if let Kind::Dir {
             ref mut entries, ..
@@ -2026,8 +2047,7 @@ pub fn path_remove_directory(
         {
             entries.insert(childs_name, inode);
         }
-        // TODO: more intelligently return error value by inspecting returned error value
-        return __WASI_EIO;
+        return e.into_wasi_err();
     }
 
     __WASI_ESUCCESS
@@ -2080,67 +2100,128 @@ pub fn path_rename(
     }
 
     let (source_parent_inode, source_entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(old_fd, source_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(old_fd, source_path));
     let (target_parent_inode, target_entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, target_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(new_fd, target_path));
+    if [&source_entry_name, &target_entry_name]
+        .iter()
+        .any(|name| *name == "." || *name == "..")
+    {
+        return __WASI_EINVAL;
+    }
 
-    let host_adjusted_target_path = match &state.fs.inodes[target_parent_inode].kind {
-        Kind::Dir { entries, path, .. } => {
-            if entries.contains_key(&target_entry_name) {
-                return __WASI_EEXIST;
-            }
-            let mut out_path = path.clone();
-            out_path.push(target_path);
-            out_path
-        }
+    let (adjusted_target_path, target_filesystem) = match &state.fs.inodes[target_parent_inode].kind
+    {
+        Kind::Dir {
+            path, filesystem, ..
+        } => (path.join(&target_entry_name), *filesystem),
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
         Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
             unreachable!("Fatal internal logic error: parent of inode is not a directory")
         }
     };
-    let source_entry = match &mut state.fs.inodes[source_parent_inode].kind {
-        Kind::Dir { entries, .. } => wasi_try!(entries.remove(&source_entry_name), __WASI_EINVAL),
-        Kind::Root { .. } => return __WASI_ENOTCAPABLE,
-        Kind::Symlink { .. } | Kind::File { .. } | Kind::Buffer { .. } => {
-            unreachable!("Fatal internal logic error: parent of inode is not a directory")
-        }
+    if let Kind::Root { .. } = &state.fs.inodes[source_parent_inode].kind {
+        return __WASI_ENOTCAPABLE;
+    }
+
+    // the entries are loaded in their parent directories if they aren't yet
+    let source_entry = wasi_try!(state.fs.get_inode_at_path(old_fd, source_str, false));
+    let target_entry = match state.fs.get_inode_at_path(new_fd, target_str, false) {
+        Ok(inode) => Some(inode),
+        Err(__WASI_ENOENT) => None,
+        Err(e) => return e,
     };
+    // renaming a file to itself, or to another of its hard links, does nothing
+    if target_entry == Some(source_entry) {
+        return __WASI_ESUCCESS;
+    }
 
-    match &mut state.fs.inodes[source_entry].kind {
+    let source = match &state.fs.inodes[source_entry].kind {
         Kind::File {
-            handle,
-            ref mut path,
+            fd: Some(_),
+            handle: Some(_),
             ..
-        } => {
-            let result = if let Some(h) = handle {
-                h.rename_file(&host_adjusted_target_path)
-                    .map_err(|e| e.into_wasi_err())
-            } else {
-                let out =
-                    std::fs::rename(&path, &host_adjusted_target_path).map_err(|_| __WASI_EIO);
-                *path = host_adjusted_target_path;
-                out
-            };
-            // if the above operation failed we have to revert the previous change and then fail
-            if let Err(e) = result {
-                if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
-                    entries.insert(source_entry_name, source_entry);
-                    return e;
-                }
+        }
+        | Kind::Buffer { .. } => None,
+        Kind::File {
+            path, filesystem, ..
+        }
+        | Kind::Dir {
+            path, filesystem, ..
+        } => Some((*filesystem, path.clone())),
+        Kind::Symlink {
+            base_po_dir,
+            path_to_symlink,
+            ..
+        } => Some(wasi_try!(state
+            .fs
+            .symlink_path(*base_po_dir, path_to_symlink)
+            .ok_or(__WASI_EIO))),
+        Kind::Root { .. } => return __WASI_ENOTCAPABLE,
+    };
+    match source {
+        Some((filesystem, _)) if filesystem != target_filesystem => return __WASI_EXDEV,
+        Some((filesystem, source_path)) => {
+            // the filesystem replaces the target, or refuses to, the way POSIX does
+            wasi_try!(state.fs.filesystems[filesystem]
+                .rename(&source_path, &adjusted_target_path)
+                .map_err(WasiFsError::into_wasi_err));
+            state
+                .fs
+                .rebase_paths(source_entry, &source_path, &adjusted_target_path);
+        }
+        // the files which aren't in a filesystem can't replace the ones which are
+        None if target_entry.is_some() => return __WASI_EEXIST,
+        None => {
+            if let Kind::File {
+                handle: Some(handle),
+                ..
+            } = &mut state.fs.inodes[source_entry].kind
+            {
+                // special files are only known by their handle
+                wasi_try!(handle
+                    .rename_file(&adjusted_target_path)
+                    .map_err(WasiFsError::into_wasi_err));
             }
         }
-        Kind::Dir { path, .. } => unimplemented!("wasi::path_rename on Directories"),
-        Kind::Buffer { .. } => {}
-        Kind::Symlink { .. } => {}
-        Kind::Root { .. } => unreachable!("The root can not be moved"),
     }
 
+    if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
+        entries.remove(&source_entry_name);
+    }
     if let Kind::Dir { entries, .. } = &mut state.fs.inodes[target_parent_inode].kind {
-        let result = entries.insert(target_entry_name, source_entry);
-        assert!(
-            result.is_none(),
-            "Fatal error: race condition on filesystem detected or internal logic error"
-        );
+        entries.insert(target_entry_name, source_entry);
+    }
+    if let Kind::Dir { parent, .. } = &mut state.fs.inodes[source_entry].kind {
+        *parent = Some(target_parent_inode);
+    }
+
+    if let Some(replaced_inode) = target_entry {
+        let is_last_link = match &mut state.fs.inodes[replaced_inode] {
+            InodeVal {
+                kind: Kind::Dir { .. },
+                ..
+            } => true,
+            InodeVal { stat, .. } => {
+                stat.st_nlink = stat.st_nlink.saturating_sub(1);
+                stat.st_nlink == 0
+            }
+        };
+        if is_last_link {
+            // make the replaced entry an orphan fd if an fd still uses it
+            let fd_is_orphaned = state
+                .fs
+                .fd_map
+                .values()
+                .any(|fd| fd.inode == replaced_inode);
+            let replaced_inode_val = unsafe { state.fs.remove_inode(replaced_inode) };
+            if let (true, Some(replaced_inode_val)) = (fd_is_orphaned, replaced_inode_val) {
+                state
+                    .fs
+                    .orphan_fds
+                    .insert(replaced_inode, replaced_inode_val);
+            }
+        }
     }
 
     __WASI_ESUCCESS
@@ -2176,48 +2257,50 @@ pub fn path_symlink(
         return __WASI_EACCES;
     }
 
-    // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
+    // the symlinks are resolved inside of the preopened directories, an absolute
+    // value would be a path of the host
     let old_path_path = std::path::Path::new(old_path_str);
-    let (source_inode, _) = wasi_try!(state.fs.get_parent_inode_at_path(fd, old_path_path, true));
-    let depth = wasi_try!(state.fs.path_depth_from_fd(fd, source_inode)) - 1;
+    if old_path_path.has_root() {
+        return __WASI_ENOTCAPABLE;
+    }
 
     let new_path_path = std::path::Path::new(new_path_str);
     let (target_parent_inode, entry_name) =
-        wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path, true));
+        wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path));
 
     // short circuit if anything is wrong, before we create an inode
-    match &state.fs.inodes[target_parent_inode].kind {
-        Kind::Dir { entries, .. } => {
+    let (symlink_path, filesystem) = match &state.fs.inodes[target_parent_inode].kind {
+        Kind::Dir {
+            entries,
+            path,
+            filesystem,
+            ..
+        } => {
             if entries.contains_key(&entry_name) {
                 return __WASI_EEXIST;
             }
+            (path.join(&entry_name), *filesystem)
         }
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
         Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => {
             unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
         }
-    }
+    };
+    debug!("Symlinking {:?} to {}", symlink_path, old_path_str);
 
-    let mut source_path = std::path::Path::new(old_path_str);
-    let mut relative_path = std::path::PathBuf::new();
-    for _ in 0..depth {
-        relative_path.push("..");
-    }
-    relative_path.push(source_path);
-    debug!(
-        "Symlinking {} to {}",
-        new_path_str,
-        relative_path.to_string_lossy()
-    );
+    wasi_try!(state.fs.filesystems[filesystem]
+        .symlink(old_path_path, &symlink_path)
+        .map_err(WasiFsError::into_wasi_err));
 
+    let (base_po_dir, path_to_symlink) = wasi_try!(state
+        .fs
+        .path_into_pre_open_and_relative_path(filesystem, &symlink_path));
     let kind = Kind::Symlink {
-        base_po_dir: fd,
-        path_to_symlink: std::path::PathBuf::from(new_path_str),
-        relative_path,
+        base_po_dir,
+        path_to_symlink,
+        relative_path: old_path_path.to_path_buf(),
     };
-    let new_inode = state
-        .fs
-        .create_inode_with_default_stat(kind, false, entry_name.clone());
+    let new_inode = wasi_try!(state.fs.create_inode(kind, false, entry_name.clone()));
 
     if let Kind::Dir {
         ref mut entries, ..
@@ -2255,10 +2338,9 @@ pub fn path_unlink_file(
     debug!("Requested file: {}", path_str);
 
     let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
-    let (parent_inode, childs_name) =
-        wasi_try!(state
-            .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+    let (parent_inode, childs_name) = wasi_try!(state
+        .fs
+        .get_parent_inode_at_path(fd, std::path::Path::new(path_str)));
 
     let removed_inode = match &mut state.fs.inodes[parent_inode].kind {
         Kind::Dir {
@@ -2278,30 +2360,46 @@ pub fn path_unlink_file(
 
     state.fs.inodes[removed_inode].stat.st_nlink -= 1;
     if state.fs.inodes[removed_inode].stat.st_nlink == 0 {
-        match &mut state.fs.inodes[removed_inode].kind {
-            Kind::File { handle, path, .. } => {
-                if let Some(h) = handle {
+        let wasi_fs = &mut state.fs;
+        match &mut wasi_fs.inodes[removed_inode].kind {
+            Kind::File {
+                handle,
+                path,
+                filesystem,
+                fd,
+            } => {
+                if let (Some(_), Some(h)) = (fd, handle) {
+                    // special files are only known by their handle
                     wasi_try!(h.unlink().map_err(WasiFsError::into_wasi_err));
                 } else {
-                    // File is closed
-                    // problem with the abstraction, we can't call unlink because there's no handle
-                    // TODO: replace this code
-                    wasi_try!(std::fs::remove_file(path).map_err(|_| __WASI_EIO));
+                    wasi_try!(wasi_fs.filesystems[*filesystem]
+                        .unlink(path)
+                        .map_err(WasiFsError::into_wasi_err));
                 }
             }
             Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-            Kind::Symlink { .. } => {
-                // TODO: actually delete real symlinks and do nothing for virtual symlinks
+            Kind::Symlink {
+                base_po_dir,
+                path_to_symlink,
+                ..
+            } => {
+                let (base_po_dir, path_to_symlink) = (*base_po_dir, path_to_symlink.clone());
+                let (filesystem, path) = wasi_try!(wasi_fs
+                    .symlink_path(base_po_dir, &path_to_symlink)
+                    .ok_or(__WASI_EIO));
+                wasi_try!(wasi_fs.filesystems[filesystem]
+                    .unlink(&path)
+                    .map_err(WasiFsError::into_wasi_err));
             }
-            _ => unimplemented!("wasi::path_unlink_file for Buffer"),
+            Kind::Buffer { .. } => (),
         }
         // TODO: test this on Windows and actually make it portable
         // make the file an orphan fd if the fd is still open
-        let fd_is_orphaned = if let Kind::File { handle, .. } = &state.fs.inodes[removed_inode].kind
-        {
-            handle.is_some()
-        } else {
-            false
+        let fd_is_orphaned = match &state.fs.inodes[removed_inode].kind {
+            Kind::File { handle, .. } => handle.is_some(),
+            // buffers have no handle, they live on as long as an fd uses them
+            Kind::Buffer { .. } => state.fs.fd_map.values().any(|fd| fd.inode == removed_inode),
+            _ => false,
         };
         let removed_inode_val = unsafe { state.fs.remove_inode(removed_inode) };
         assert!(
@@ -2561,6 +2659,22 @@ pub fn sched_yield(env: &WasiEnv) -> __wasi_errno_t {
     __WASI_ESUCCESS
 }
 
+/// ### `sock_recv()`
+/// Receive a message from a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to receive from
+/// - `__wasi_iovec_t *ri_data`
+///     The vectors where the received data will be stored
+/// - `u32 ri_data_len`
+///     The number of vectors
+/// - `__wasi_riflags_t ri_flags`
+///     Whether to peek at the data and to wait until the vectors are filled
+/// Output:
+/// - `u32 *ro_datalen`
+///     The number of bytes received
+/// - `__wasi_roflags_t *ro_flags`
+///     Whether the data was truncated
 pub fn sock_recv(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2570,9 +2684,59 @@ pub fn sock_recv(
     ro_datalen: WasmPtr<u32>,
     ro_flags: WasmPtr<__wasi_roflags_t>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_recv");
-    unimplemented!("wasi::sock_recv")
+    debug!("wasi::sock_recv: fd={}", sock);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let iovs_arr_cell = wasi_try!(ri_data.deref(memory, 0, ri_data_len));
+    let ro_datalen_cell = wasi_try!(ro_datalen.deref(memory));
+    let ro_flags_cell = wasi_try!(ro_flags.deref(memory));
+    let socket = wasi_try!(state.fs.get_socket_mut(sock, __WASI_RIGHT_FD_READ));
+
+    // receive everything at once so that peeking doesn't fill every vector
+    // with the same data
+    let mut iovs = Vec::with_capacity(iovs_arr_cell.len());
+    for iov in iovs_arr_cell {
+        let iov_inner = iov.get();
+        iovs.push(wasi_try!(iov_inner.buf.deref(memory, 0, iov_inner.buf_len)));
+    }
+    let mut data = vec![0; iovs.iter().map(|iov| iov.len()).sum()];
+    let received = wasi_try!(socket
+        .recv(
+            &mut data,
+            ri_flags & __WASI_SOCK_RECV_PEEK != 0,
+            ri_flags & __WASI_SOCK_RECV_WAITALL != 0,
+        )
+        .map_err(WasiFsError::into_wasi_err));
+    let mut received_data = &data[..received];
+    for iov in iovs {
+        let (iov_data, rest) =
+            received_data.split_at(std::cmp::min(iov.len(), received_data.len()));
+        for (cell, byte) in iov.iter().zip(iov_data) {
+            cell.set(*byte);
+        }
+        received_data = rest;
+    }
+
+    ro_datalen_cell.set(received as u32);
+    // only datagrams can be truncated
+    ro_flags_cell.set(0);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_send()`
+/// Send a message on a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to send on
+/// - `const __wasi_ciovec_t *si_data`
+///     The vectors of the data to send
+/// - `u32 si_data_len`
+///     The number of vectors
+/// - `__wasi_siflags_t si_flags`
+///     Unused, no flags are defined
+/// Output:
+/// - `u32 *so_datalen`
+///     The number of bytes sent
 pub fn sock_send(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2581,10 +2745,44 @@ pub fn sock_send(
     si_flags: __wasi_siflags_t,
     so_datalen: WasmPtr<u32>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_send");
-    unimplemented!("wasi::sock_send")
+    debug!("wasi::sock_send: fd={}", sock);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let iovs_arr_cell = wasi_try!(si_data.deref(memory, 0, si_data_len));
+    let so_datalen_cell = wasi_try!(so_datalen.deref(memory));
+    let socket = wasi_try!(state.fs.get_socket_mut(sock, __WASI_RIGHT_FD_WRITE));
+
+    let mut data = Vec::new();
+    for iov in iovs_arr_cell {
+        let iov_inner = iov.get();
+        let bytes = wasi_try!(iov_inner.buf.deref(memory, 0, iov_inner.buf_len));
+        data.extend(bytes.iter().map(|b_cell| b_cell.get()));
+    }
+    let sent = wasi_try!(socket.send(&data).map_err(WasiFsError::into_wasi_err));
+
+    so_datalen_cell.set(sent as u32);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_shutdown()`
+/// Shut down the receiving and/or sending halves of a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to shut down
+/// - `__wasi_sdflags_t how`
+///     `__WASI_SHUT_RD` and/or `__WASI_SHUT_WR`
 pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
-    debug!("wasi::sock_shutdown");
-    unimplemented!("wasi::sock_shutdown")
+    debug!("wasi::sock_shutdown: fd={}, how={}", sock, how);
+    let mut state = env.state();
+    let socket = wasi_try!(state.fs.get_socket_mut(sock, __WASI_RIGHT_SOCK_SHUTDOWN));
+
+    let how = match how {
+        __WASI_SHUT_RD => Shutdown::Read,
+        __WASI_SHUT_WR => Shutdown::Write,
+        both if both == __WASI_SHUT_RD | __WASI_SHUT_WR => Shutdown::Both,
+        _ => return __WASI_EINVAL,
+    };
+    wasi_try!(socket.shutdown(how).map_err(WasiFsError::into_wasi_err));
+
+    __WASI_ESUCCESS
 }
@@ -218,7 +218,7 @@ impl Features {
     /// appropriate WebAssembly modules.
     ///
     /// This feature gates support for linear memory of sizes larger than
-    /// 2^32 bits.
+    /// 2^32 bytes.
     ///
     /// This is `false` by default.
     ///
@@ -227,6 +227,25 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether the WebAssembly exception handling proposal will
+    /// be enabled.
+    ///
+    /// The [WebAssembly exception handling proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature gates exception tags and the `try`, `catch`, `throw`
+    /// and `rethrow` instructions.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    pub fn exceptions(&mut self, enable: bool) -> &mut Self {
+        self.exceptions = enable;
+        self
+    }
 }
 
 impl Default for Features {
@@ -4,7 +4,7 @@ use crate::lib::std::fmt;
 use crate::lib::std::format;
 use crate::lib::std::string::{String, ToString};
 use crate::lib::std::vec::Vec;
-use crate::units::Pages;
+use crate::units::{Pages, WASM64_MAX_PAGES};
 use crate::values::Value;
 use loupe::MemoryUsage;
 
@@ -116,6 +116,12 @@ pub enum ExternType {
     Table(TableType),
     /// This external type is the type of a WebAssembly memory.
     Memory(MemoryType),
+    /// This external type is the type of a WebAssembly exception tag.
+    Tag(TagType),
+    /// This external type is the type of a WebAssembly instance.
+    Instance(InstanceType),
+    /// This external type is the type of a WebAssembly module.
+    Module(ModuleType),
 }
 
 fn is_global_compatible(exported: GlobalType, imported: GlobalType) -> bool {
@@ -162,11 +168,13 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
         minimum: exported_minimum,
         maximum: exported_maximum,
         shared: exported_shared,
+        memory64: exported_memory64,
     } = exported;
     let MemoryType {
         minimum: imported_minimum,
         maximum: imported_maximum,
         shared: imported_shared,
+        memory64: imported_memory64,
     } = imported;
 
     imported_minimum <= exported_minimum
@@ -174,6 +182,25 @@ fn is_memory_compatible(exported: &MemoryType, imported: &MemoryType) -> bool {
             || (!exported_maximum.is_none()
                 && imported_maximum.unwrap() >= exported_maximum.unwrap()))
         && exported_shared == imported_shared
+        && exported_memory64 == imported_memory64
+}
+
+fn is_instance_compatible(exported: &InstanceType, imported: &InstanceType) -> bool {
+    imported.exports().all(|imported_export| {
+        exported
+            .export(imported_export.name())
+            .map_or(false, |ty| ty.is_compatible_with(imported_export.ty()))
+    })
+}
+
+fn is_module_compatible(exported: &ModuleType, imported: &ModuleType) -> bool {
+    // The module may import less than what the importer will provide, and
+    // export more than what the importer will use.
+    exported.imports().all(|exported_import| {
+        imported
+            .import(exported_import.module(), exported_import.name())
+            .map_or(false, |ty| ty.is_compatible_with(exported_import.ty()))
+    }) && is_instance_compatible(&exported.exports, &imported.exports)
 }
 
 macro_rules! accessors {
@@ -206,6 +233,9 @@ impl ExternType {
         (Global(GlobalType) global unwrap_global)
         (Table(TableType) table unwrap_table)
         (Memory(MemoryType) memory unwrap_memory)
+        (Tag(TagType) tag unwrap_tag)
+        (Instance(InstanceType) instance unwrap_instance)
+        (Module(ModuleType) module unwrap_module)
     }
     /// Check if two externs are compatible
     pub fn is_compatible_with(&self, other: &Self) -> bool {
@@ -214,6 +244,9 @@ impl ExternType {
             (Self::Global(a), Self::Global(b)) => is_global_compatible(*a, *b),
             (Self::Table(a), Self::Table(b)) => is_table_compatible(a, b),
             (Self::Memory(a), Self::Memory(b)) => is_memory_compatible(a, b),
+            (Self::Tag(a), Self::Tag(b)) => a == b,
+            (Self::Instance(a), Self::Instance(b)) => is_instance_compatible(a, b),
+            (Self::Module(a), Self::Module(b)) => is_module_compatible(a, b),
             // The rest of possibilities, are not compatible
             _ => false,
         }
@@ -489,6 +522,8 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// Whether the memory is indexed with 64-bit addresses.
+    pub memory64: bool,
 }
 
 impl MemoryType {
@@ -502,6 +537,39 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            memory64: false,
+        }
+    }
+
+    /// Creates a new descriptor for a 64-bit WebAssembly memory given the
+    /// specified limits of the memory.
+    pub fn new64<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>, shared: bool) -> Self
+    where
+        IntoPages: Into<Pages>,
+    {
+        Self {
+            memory64: true,
+            ..Self::new(minimum, maximum, shared)
+        }
+    }
+
+    /// Returns the type of the addresses used to index this memory,
+    /// either `i32` or `i64`.
+    pub fn index_type(&self) -> Type {
+        if self.memory64 {
+            Type::I64
+        } else {
+            Type::I32
+        }
+    }
+
+    /// Returns the largest number of pages this kind of memory can
+    /// ever have, regardless of its declared maximum.
+    pub fn max_pages(&self) -> Pages {
+        if self.memory64 {
+            Pages(WASM64_MAX_PAGES)
+        } else {
+            Pages::max_value()
         }
     }
 }
@@ -510,11 +578,146 @@ impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)?;
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{} ({:?}..)", shared, self.minimum)?;
+        }
+        if self.memory64 {
+            write!(f, " i64")?;
+        }
+        Ok(())
+    }
+}
+
+// Tag Types
+
+/// A descriptor for a WebAssembly exception tag.
+///
+/// Tags identify the exceptions thrown by `throw` and matched by `catch`, and
+/// describe the values carried by those exceptions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct TagType {
+    /// The types of the values carried by exceptions with this tag.
+    params: Box<[Type]>,
+}
+
+impl TagType {
+    /// Creates a new tag descriptor for exceptions carrying values of the
+    /// given types.
+    pub fn new<Params>(params: Params) -> Self
+    where
+        Params: Into<Box<[Type]>>,
+    {
+        Self {
+            params: params.into(),
+        }
+    }
+
+    /// The types of the values carried by exceptions with this tag.
+    pub fn params(&self) -> &[Type] {
+        &self.params
+    }
+}
+
+impl fmt::Display for TagType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "[{}]", params)
+    }
+}
+
+// Instance and Module Types
+
+/// A descriptor for a WebAssembly instance, as defined by the module linking
+/// proposal.
+///
+/// An instance type lists the exports that an instance provides.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct InstanceType {
+    exports: Box<[ExportType]>,
+}
+
+impl InstanceType {
+    /// Creates a new instance descriptor with the given exports.
+    pub fn new<Exports>(exports: Exports) -> Self
+    where
+        Exports: Into<Box<[ExportType]>>,
+    {
+        Self {
+            exports: exports.into(),
         }
     }
+
+    /// The exports of the instance.
+    pub fn exports(&self) -> impl Iterator<Item = &ExportType> {
+        self.exports.iter()
+    }
+
+    /// The type of the export with the given name, if any.
+    pub fn export(&self, name: &str) -> Option<&ExternType> {
+        self.exports
+            .iter()
+            .find(|export| export.name() == name)
+            .map(ExportType::ty)
+    }
+}
+
+/// A descriptor for a WebAssembly module, as defined by the module linking
+/// proposal.
+///
+/// A module type lists the imports that a module needs to be instantiated,
+/// and the exports of the instances it creates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ModuleType {
+    imports: Box<[ImportType]>,
+    exports: InstanceType,
+}
+
+impl ModuleType {
+    /// Creates a new module descriptor with the given imports and exports.
+    pub fn new<Imports, Exports>(imports: Imports, exports: Exports) -> Self
+    where
+        Imports: Into<Box<[ImportType]>>,
+        Exports: Into<Box<[ExportType]>>,
+    {
+        Self {
+            imports: imports.into(),
+            exports: InstanceType::new(exports),
+        }
+    }
+
+    /// The imports of the module.
+    pub fn imports(&self) -> impl Iterator<Item = &ImportType> {
+        self.imports.iter()
+    }
+
+    /// The type of the import with the given module and field names, if any.
+    ///
+    /// Single-level imports have an empty field name.
+    pub fn import(&self, module: &str, name: &str) -> Option<&ExternType> {
+        self.imports
+            .iter()
+            .find(|import| import.module() == module && import.name() == name)
+            .map(ImportType::ty)
+    }
+
+    /// The exports of the module.
+    pub fn exports(&self) -> impl Iterator<Item = &ExportType> {
+        self.exports.exports()
+    }
+
+    /// The type of the instances created by this module.
+    pub fn instance_type(&self) -> &InstanceType {
+        &self.exports
+    }
 }
 
 // Import Types
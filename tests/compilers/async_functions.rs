@@ -0,0 +1,196 @@
+//! Testing asynchronous host functions, and calling WebAssembly code
+//! asynchronously.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use wasmer::*;
+
+/// A future which is pending `count` times before being ready.
+struct Yield {
+    count: usize,
+}
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.count == 0 {
+            return Poll::Ready(());
+        }
+        self.count -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+/// Polls `future` until it is ready, returning its output and how many
+/// times it was polled.
+fn run<F: Future>(future: F) -> (F::Output, usize) {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return (output, polls);
+        }
+    }
+}
+
+const WAT: &str = r#"
+    (module
+        (import "env" "sleep" (func $sleep (param i32) (result i32)))
+        (func (export "sleep_plus_one") (param i32) (result i32)
+            (i32.add (call $sleep (local.get 0)) (i32.const 1)))
+        (func (export "sleep_then_trap") (param i32)
+            (drop (call $sleep (local.get 0)))
+            unreachable))
+"#;
+
+fn instantiate(store: &Store) -> Result<Instance> {
+    let module = Module::new(store, WAT)?;
+    let sleep = Function::new_async(
+        store,
+        FunctionType::new(vec![Type::I32], vec![Type::I32]),
+        |args| {
+            let count = args[0].unwrap_i32();
+            async move {
+                Yield {
+                    count: count as usize,
+                }
+                .await;
+                Ok(vec![Value::I32(count * 2)])
+            }
+        },
+    );
+    let import_object = imports! {
+        "env" => {
+            "sleep" => sleep,
+        },
+    };
+    Ok(Instance::new(&module, &import_object)?)
+}
+
+#[test]
+fn async_host_function_suspends_the_call() -> Result<()> {
+    let store = get_store(false);
+    let instance = instantiate(&store)?;
+    let sleep_plus_one = instance
+        .exports
+        .get_native_function::<i32, i32>("sleep_plus_one")?;
+
+    let (result, polls) = run(sleep_plus_one.call_async(3));
+    assert_eq!(result?, 7);
+    assert_eq!(polls, 4);
+    Ok(())
+}
+
+#[test]
+fn async_call_traps() -> Result<()> {
+    let store = get_store(false);
+    let instance = instantiate(&store)?;
+    let sleep_then_trap = instance
+        .exports
+        .get_native_function::<i32, ()>("sleep_then_trap")?;
+
+    let (result, _) = run(sleep_then_trap.call_async(2));
+    let e = result.unwrap_err();
+    assert!(e.message().contains("unreachable"));
+
+    // The trap doesn't break calls on the thread's own stack.
+    let sleep_plus_one = instance
+        .exports
+        .get_native_function::<i32, i32>("sleep_plus_one")?;
+    assert_eq!(run(sleep_plus_one.call_async(0)).0?, 1);
+    Ok(())
+}
+
+#[test]
+fn async_host_function_called_synchronously() -> Result<()> {
+    let store = get_store(false);
+    let instance = instantiate(&store)?;
+    let sleep_plus_one = instance
+        .exports
+        .get_native_function::<i32, i32>("sleep_plus_one")?;
+
+    let e = sleep_plus_one.call(1).unwrap_err();
+    assert_eq!(
+        e.message(),
+        "async host functions can only be called through `call_async`"
+    );
+    Ok(())
+}
+
+#[test]
+fn async_call_cancelled_on_drop() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, WAT)?;
+    let finished = Arc::new(AtomicBool::new(false));
+    let sleep = Function::new_async(
+        &store,
+        FunctionType::new(vec![Type::I32], vec![Type::I32]),
+        {
+            let finished = finished.clone();
+            move |_| {
+                let finished = finished.clone();
+                async move {
+                    Yield { count: usize::MAX }.await;
+                    finished.store(true, Ordering::SeqCst);
+                    Ok(vec![Value::I32(0)])
+                }
+            }
+        },
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "sleep" => sleep,
+            },
+        },
+    )?;
+    let sleep_plus_one = instance
+        .exports
+        .get_native_function::<i32, i32>("sleep_plus_one")?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = sleep_plus_one.call_async(0);
+    assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+    assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+    drop(future);
+    assert!(!finished.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[test]
+fn async_call_stack_overflow() -> Result<()> {
+    let store = get_store(false);
+    let wat = r#"
+        (module
+            (func $run (export "run") (call $run)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run_func = instance.exports.get_native_function::<(), ()>("run")?;
+
+    // The guard page of the fiber stack is recognized as such.
+    let e = run(run_func.call_async()).0.unwrap_err();
+    assert!(e.message().contains("call stack exhausted"));
+    Ok(())
+}
@@ -0,0 +1,142 @@
+//! Testing the exception handling proposal, with exceptions crossing
+//! the boundary between WebAssembly and the host.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use wasmer::*;
+
+fn get_instance(store: &Store, tag: &Tag) -> Result<Instance> {
+    let wat = r#"
+        (import "env" "tag" (event $host (param i32)))
+        (import "env" "throw" (func $throw_host (param i32)))
+        (event $e (export "e") (param i32))
+        (event $other (param i64))
+
+        (func $throw (param i32)
+            local.get 0
+            throw $e)
+
+        (func (export "catch-local") (param i32) (result i32)
+            try (result i32)
+                local.get 0
+                throw $e
+            catch $e
+                i32.const 1
+                i32.add
+            end)
+
+        (func (export "catch-call") (param i32) (result i32)
+            try (result i32)
+                local.get 0
+                call $throw
+                i32.const 0
+            catch $other
+                i32.wrap_i64
+            catch $e
+            end)
+
+        (func (export "catch-host") (param i32) (result i32)
+            try (result i32)
+                local.get 0
+                call $throw_host
+                i32.const 0
+            catch $host
+            end)
+
+        (func (export "rethrow") (param i32) (result i32)
+            try (result i32)
+                try
+                    local.get 0
+                    call $throw
+                catch $e
+                    drop
+                    rethrow 0
+                end
+                i32.const 0
+            catch $e
+                i32.const 2
+                i32.mul
+            end)
+
+        (func (export "uncaught") (param i32)
+            try
+                local.get 0
+                call $throw
+            catch $other
+                drop
+            end)
+    "#;
+
+    let module = Module::new(store, wat)?;
+    let throw_type = FunctionType::new(vec![Type::I32], vec![]);
+    let throw_tag = tag.clone();
+    let throw = Function::new(store, &throw_type, move |args| {
+        Err(Exception::new(&throw_tag, args)?.into())
+    });
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "tag" => tag.clone(),
+                "throw" => throw,
+            },
+        },
+    )?;
+    Ok(instance)
+}
+
+// Singlepass doesn't support the exception handling proposal.
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn catch_exceptions() -> Result<()> {
+    let store = get_store_enabling(|features| features.exceptions(true));
+    let tag = Tag::new(&store, TagType::new(vec![Type::I32]));
+    let instance = get_instance(&store, &tag)?;
+
+    let catch_local = instance
+        .exports
+        .get_native_function::<i32, i32>("catch-local")?;
+    assert_eq!(catch_local.call(41)?, 42);
+
+    let catch_call = instance
+        .exports
+        .get_native_function::<i32, i32>("catch-call")?;
+    assert_eq!(catch_call.call(7)?, 7);
+
+    let catch_host = instance
+        .exports
+        .get_native_function::<i32, i32>("catch-host")?;
+    assert_eq!(catch_host.call(13)?, 13);
+
+    let rethrow = instance
+        .exports
+        .get_native_function::<i32, i32>("rethrow")?;
+    assert_eq!(rethrow.call(21)?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn uncaught_exception() -> Result<()> {
+    let store = get_store_enabling(|features| features.exceptions(true));
+    let tag = Tag::new(&store, TagType::new(vec![Type::I32]));
+    let instance = get_instance(&store, &tag)?;
+
+    let uncaught = instance.exports.get_function("uncaught")?;
+    let error = uncaught.call(&[Val::I32(5)]).unwrap_err();
+    let exception = Exception::from_runtime_error(&store, error).unwrap();
+    assert!(exception.tag().same(instance.exports.get_tag("e")?));
+    assert!(!exception.tag().same(&tag));
+    assert_eq!(exception.payload(), vec![Val::I32(5)]);
+
+    Ok(())
+}
+
+#[test]
+fn exception_payload_type_mismatch() {
+    let store = get_store_enabling(|features| features.exceptions(true));
+    let tag = Tag::new(&store, TagType::new(vec![Type::I32]));
+    assert!(Exception::new(&tag, &[Val::I64(0)]).is_err());
+    assert!(Exception::new(&tag, &[]).is_err());
+}
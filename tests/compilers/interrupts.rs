@@ -0,0 +1,120 @@
+//! Testing the interruption of running WebAssembly code, and epoch
+//! deadlines.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wasmer::*;
+
+const LOOP_WAT: &str = r#"
+    (module
+        (func (export "loop_forever")
+            (loop $l (br $l)))
+        (func (export "answer") (result i32)
+            (i32.const 42)))
+"#;
+
+#[test]
+fn interrupt_before_call() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, LOOP_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    instance.interrupt_handle().interrupt();
+    let answer = instance.exports.get_native_function::<(), i32>("answer")?;
+    let e = answer.call().unwrap_err();
+    assert_eq!(e.message(), "interrupt");
+
+    // The interrupt is consumed by the trap it caused.
+    assert_eq!(answer.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn interrupt_running_loop() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, LOOP_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let handle = store.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    let loop_forever = instance
+        .exports
+        .get_native_function::<(), ()>("loop_forever")?;
+    let e = loop_forever.call().unwrap_err();
+    assert_eq!(e.message(), "interrupt");
+
+    interrupter.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn epoch_deadline_reached() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, LOOP_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let answer = instance.exports.get_native_function::<(), i32>("answer")?;
+
+    instance.set_epoch_deadline(1);
+    assert_eq!(answer.call()?, 42);
+
+    store.increment_epoch();
+    let e = answer.call().unwrap_err();
+    assert_eq!(e.message(), "epoch deadline reached");
+
+    instance.set_epoch_deadline(1);
+    assert_eq!(answer.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn epoch_deadline_callback() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, LOOP_WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let extensions = Arc::new(AtomicUsize::new(0));
+    instance.set_epoch_deadline(1);
+    instance.set_epoch_deadline_callback({
+        let extensions = extensions.clone();
+        move || {
+            if extensions.fetch_add(1, Ordering::SeqCst) < 3 {
+                Some(1)
+            } else {
+                None
+            }
+        }
+    });
+
+    let done = Arc::new(AtomicBool::new(false));
+    let handle = store.interrupt_handle();
+    let ticker = thread::spawn({
+        let done = done.clone();
+        move || {
+            while !done.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+                handle.increment_epoch();
+            }
+        }
+    });
+
+    let loop_forever = instance
+        .exports
+        .get_native_function::<(), ()>("loop_forever")?;
+    let e = loop_forever.call().unwrap_err();
+    assert_eq!(e.message(), "epoch deadline reached");
+    assert_eq!(extensions.load(Ordering::SeqCst), 4);
+
+    done.store(true, Ordering::SeqCst);
+    ticker.join().unwrap();
+    Ok(())
+}
@@ -4,15 +4,29 @@
 //! implementation, such as: singlepass, cranelift or llvm depending
 //! on what's available on the target.
 
+mod async_functions;
+mod exceptions;
 mod imports;
+mod interrupts;
+mod memory64;
+mod memory_images;
 mod metering;
 mod middlewares;
+mod module_linking;
+mod multi_memory;
 mod multi_value_imports;
 mod native_functions;
+mod pooling;
+mod resource_limiter;
 mod serialize;
+mod simd;
+mod snapshot;
+mod threads;
 mod traps;
 mod utils;
 mod wasi;
+mod wasi_fs;
+mod wasi_sock;
 mod wast;
 
 pub use crate::utils::get_compiler;
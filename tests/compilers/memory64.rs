@@ -0,0 +1,89 @@
+//! Testing memories indexed with 64-bit addresses.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use wasmer::*;
+
+fn get_instance(store: &Store) -> Result<Instance> {
+    let wat = r#"
+        (memory (export "memory") i64 1 16)
+        (data (i64.const 8) "\2a")
+
+        (func (export "load") (param i64) (result i32)
+            (i32.load8_u offset=8 (local.get 0)))
+
+        (func (export "store") (param i64) (param i32)
+            (i32.store (local.get 0) (local.get 1)))
+
+        (func (export "size") (result i64)
+            (memory.size))
+
+        (func (export "grow") (param i64) (result i64)
+            (memory.grow (local.get 0)))
+    "#;
+
+    let module = Module::new(store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    Ok(instance)
+}
+
+// Singlepass doesn't support 64-bit memories.
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn memory64_load_store() -> Result<()> {
+    let store = get_store_enabling(|features| features.memory64(true));
+    let instance = get_instance(&store)?;
+
+    let memory = instance.exports.get_memory("memory")?;
+    assert!(memory.ty().memory64);
+    assert_eq!(memory.ty().index_type(), Type::I64);
+
+    let load = instance.exports.get_native_function::<i64, i32>("load")?;
+    let store_fn = instance
+        .exports
+        .get_native_function::<(i64, i32), ()>("store")?;
+    assert_eq!(load.call(0)?, 42);
+    store_fn.call(16, 7)?;
+    assert_eq!(load.call(8)?, 7);
+
+    // Addresses beyond 4GiB must not wrap around to the start of the memory.
+    assert!(load.call(0x1_0000_0000).is_err());
+    assert!(store_fn.call(0x1_0000_0000, 0).is_err());
+    // Neither should the address plus the offset.
+    assert!(load.call(-4).is_err());
+    assert!(load.call(0xffff - 8).is_ok());
+    assert!(load.call(0x1_0000 - 8).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(feature = "test-singlepass", ignore)]
+fn memory64_size_grow() -> Result<()> {
+    let store = get_store_enabling(|features| features.memory64(true));
+    let instance = get_instance(&store)?;
+
+    let size = instance.exports.get_native_function::<(), i64>("size")?;
+    let grow = instance.exports.get_native_function::<i64, i64>("grow")?;
+    assert_eq!(size.call()?, 1);
+    assert_eq!(grow.call(2)?, 1);
+    assert_eq!(size.call()?, 3);
+
+    // Deltas that don't fit in 32 bits are rejected without being truncated.
+    assert_eq!(grow.call(0x1_0000_0001)?, -1);
+    assert_eq!(grow.call(16)?, -1);
+    assert_eq!(size.call()?, 3);
+
+    Ok(())
+}
+
+#[test]
+fn memory64_host_memory() -> Result<()> {
+    let store = get_store_enabling(|features| features.memory64(true));
+    let memory = Memory::new(&store, MemoryType::new64(1, Some(4), false))?;
+    assert_eq!(memory.size(), Pages(1));
+    assert_eq!(memory.grow(3)?, Pages(1));
+    assert_eq!(memory.data_size(), 4 * WASM_PAGE_SIZE as u64);
+    assert!(memory.grow(1).is_err());
+    Ok(())
+}
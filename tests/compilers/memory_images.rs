@@ -0,0 +1,168 @@
+//! Testing the initialization of memories from copy-on-write memory
+//! images.
+
+use crate::utils::{get_engine, get_store};
+use anyhow::Result;
+use wasmer::*;
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 2)
+        (data (i32.const 0) "abcd")
+        (data (i32.const 0x10000) "efgh")
+        (data (i32.const 2) "XY")
+        (func (export "load") (param i32) (result i32)
+            (i32.load (local.get 0)))
+        (func (export "store") (param i32 i32)
+            (i32.store (local.get 0) (local.get 1))))
+"#;
+
+fn load(instance: &Instance, offset: i32) -> Result<i32> {
+    let load = instance.exports.get_native_function::<i32, i32>("load")?;
+    Ok(load.call(offset)?)
+}
+
+fn store_value(instance: &Instance, offset: i32, value: i32) -> Result<()> {
+    let store_value = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store")?;
+    Ok(store_value.call(offset, value)?)
+}
+
+#[test]
+fn memory_images_initialize_memories() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, WAT)?;
+    assert_eq!(
+        module.artifact().has_memory_image(),
+        cfg!(target_os = "linux")
+    );
+
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    assert_eq!(load(&first, 0)?, i32::from_le_bytes(*b"abXY"));
+    assert_eq!(load(&first, 0x10000)?, i32::from_le_bytes(*b"efgh"));
+    assert_eq!(load(&first, 0x1000)?, 0);
+
+    // Writes only change the memory of their own instance.
+    store_value(&first, 0, 42)?;
+    store_value(&first, 0x1000, 43)?;
+    assert_eq!(load(&first, 0)?, 42);
+    assert_eq!(load(&second, 0)?, i32::from_le_bytes(*b"abXY"));
+    assert_eq!(load(&second, 0x1000)?, 0);
+    let third = Instance::new(&module, &imports! {})?;
+    assert_eq!(load(&third, 0)?, i32::from_le_bytes(*b"abXY"));
+
+    // The contents of the memory are kept when it grows.
+    let memory = first.exports.get_memory("memory")?;
+    memory.grow(10)?;
+    assert_eq!(load(&first, 0)?, 42);
+    assert_eq!(load(&first, 0x10000)?, i32::from_le_bytes(*b"efgh"));
+    Ok(())
+}
+
+#[test]
+fn memory_images_need_constant_offsets_within_the_minimum() -> Result<()> {
+    let store = get_store(false);
+    let global_offset = Module::new(
+        &store,
+        r#"
+        (module
+            (import "env" "offset" (global i32))
+            (memory 1)
+            (data (global.get 0) "abcd"))
+        "#,
+    )?;
+    assert!(!global_offset.artifact().has_memory_image());
+
+    let out_of_bounds = Module::new(
+        &store,
+        r#"
+        (module
+            (memory 1)
+            (data (i32.const 0) "abcd")
+            (data (i32.const 0xfffe) "efgh"))
+        "#,
+    )?;
+    assert!(!out_of_bounds.artifact().has_memory_image());
+    assert!(Instance::new(&out_of_bounds, &imports! {}).is_err());
+    Ok(())
+}
+
+#[test]
+fn memory_images_need_data() -> Result<()> {
+    let store = get_store(false);
+    let no_data = Module::new(&store, "(module (memory 1))")?;
+    assert!(!no_data.artifact().has_memory_image());
+
+    let empty_data = Module::new(&store, r#"(module (memory 1) (data (i32.const 0) ""))"#)?;
+    assert!(!empty_data.artifact().has_memory_image());
+    Ok(())
+}
+
+#[test]
+fn memory_images_can_be_disabled() -> Result<()> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables.memory_images = false;
+    let store = Store::new_with_tunables(&engine, tunables);
+    let module = Module::new(&store, WAT)?;
+
+    // The data is copied instead.
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    assert_eq!(load(&first, 0)?, i32::from_le_bytes(*b"abXY"));
+    assert_eq!(load(&first, 0x10000)?, i32::from_le_bytes(*b"efgh"));
+    store_value(&first, 0, 42)?;
+    assert_eq!(load(&second, 0)?, i32::from_le_bytes(*b"abXY"));
+    Ok(())
+}
+
+#[test]
+fn pooled_memories_from_images_are_reset() -> Result<()> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables
+        .enable_pooling(PoolingLimits {
+            instances: 1,
+            memories: 1,
+            memory_pages: Pages(2),
+            ..PoolingLimits::default()
+        })
+        .map_err(anyhow::Error::msg)?;
+    let store = Store::new_with_tunables(&engine, tunables);
+    let module = Module::new(&store, WAT)?;
+    let empty = Module::new(
+        &store,
+        r#"
+        (module
+            (memory 2)
+            (func (export "load") (param i32) (result i32)
+                (i32.load (local.get 0)))
+            (func (export "store") (param i32 i32)
+                (i32.store (local.get 0) (local.get 1))))
+        "#,
+    )?;
+
+    let instance = Instance::new(&module, &imports! {})?;
+    store_value(&instance, 0x1000, 42)?;
+    drop(instance);
+
+    // The memory slot doesn't keep anything from the image.
+    let instance = Instance::new(&empty, &imports! {})?;
+    assert_eq!(load(&instance, 0)?, 0);
+    assert_eq!(load(&instance, 0x1000)?, 0);
+    assert_eq!(load(&instance, 0x10000)?, 0);
+    store_value(&instance, 0x2000, 44)?;
+    drop(instance);
+
+    // Nor from the memories without an image.
+    let instance = Instance::new(&empty, &imports! {})?;
+    assert_eq!(load(&instance, 0x2000)?, 0);
+    drop(instance);
+
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(load(&instance, 0)?, i32::from_le_bytes(*b"abXY"));
+    assert_eq!(load(&instance, 0x1000)?, 0);
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! Testing modules that define, import and export modules and instances.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn module_linking_nested_instantiation() -> Result<()> {
+    let store = get_store_enabling(|features| features.module_linking(true));
+    let wat = r#"
+        (module
+            (import "env" "ten" (global $ten i32))
+            (module $inner
+                (import "base" (global $base i32))
+                (func (export "add") (param i32) (result i32)
+                    (i32.add (local.get 0) (global.get $base))))
+            (instance $i (instantiate $inner (import "base" (global $ten))))
+            (alias $i "add" (func $add))
+
+            (func (export "add_twice") (param i32) (result i32)
+                (call $add (call $add (local.get 0))))
+            (export "inner" (instance $i)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let import_object = imports! {
+        "env" => {
+            "ten" => Global::new(&store, Value::I32(10)),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+
+    let add_twice = instance
+        .exports
+        .get_native_function::<i32, i32>("add_twice")?;
+    assert_eq!(add_twice.call(1)?, 21);
+
+    let inner = instance.exports.get_instance("inner")?;
+    let add = inner.exports.get_native_function::<i32, i32>("add")?;
+    assert_eq!(add.call(1)?, 11);
+
+    Ok(())
+}
+
+#[test]
+fn module_linking_host_instance_and_module() -> Result<()> {
+    let store = get_store_enabling(|features| features.module_linking(true));
+    let provider = Module::new(
+        &store,
+        r#"
+        (func (export "answer") (result i32)
+            (i32.const 42))
+        "#,
+    )?;
+    let provider_instance = Instance::new(&provider, &imports! {})?;
+
+    let wat = r#"
+        (import "provider" (instance $p
+            (export "answer" (func (result i32)))))
+        (import "provider_module" (module $m
+            (export "answer" (func (result i32)))))
+        (alias $p "answer" (func $answer))
+        (instance $fresh (instantiate $m))
+
+        (func (export "answer") (result i32)
+            (call $answer))
+        (export "fresh" (instance $fresh))
+        (export "provider_module" (module $m))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let import_object = imports! {
+        "provider" => {
+            "" => Extern::Instance(provider_instance),
+        },
+        "provider_module" => {
+            "" => Extern::Module(provider),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+
+    let answer = instance.exports.get_native_function::<(), i32>("answer")?;
+    assert_eq!(answer.call()?, 42);
+
+    let fresh = instance.exports.get_instance("fresh")?;
+    let answer = fresh.exports.get_native_function::<(), i32>("answer")?;
+    assert_eq!(answer.call()?, 42);
+
+    let exported = instance.exports.get_module("provider_module")?;
+    assert!(exported
+        .ty()
+        .exports()
+        .any(|export| export.name() == "answer"));
+
+    Ok(())
+}
+
+#[test]
+fn module_linking_incompatible_instance_import() -> Result<()> {
+    let store = get_store_enabling(|features| features.module_linking(true));
+    let provider = Module::new(&store, r#"(global (export "g") i32 (i32.const 0))"#)?;
+    let provider_instance = Instance::new(&provider, &imports! {})?;
+
+    let wat = r#"
+        (import "provider" (instance
+            (export "answer" (func (result i32)))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let import_object = imports! {
+        "provider" => {
+            "" => Extern::Instance(provider_instance),
+        },
+    };
+    assert!(matches!(
+        Instance::new(&module, &import_object),
+        Err(InstantiationError::Link(LinkError::Import(..)))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Testing modules that define and import several memories.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use wasmer::*;
+
+#[test]
+fn multi_memory_exports() -> Result<()> {
+    let store = get_store_enabling(|features| features.multi_memory(true));
+    let wat = r#"
+        (memory $m0 (export "mem0") 1)
+        (memory $m1 (export "mem1") 2 3)
+        (data (memory $m0) (i32.const 0) "\01")
+        (data (memory $m1) (i32.const 0) "\02")
+
+        (func (export "load0") (param i32) (result i32)
+            (i32.load8_u (memory $m0) (local.get 0)))
+        (func (export "load1") (param i32) (result i32)
+            (i32.load8_u (memory $m1) (local.get 0)))
+        (func (export "store1") (param i32 i32)
+            (i32.store8 (memory $m1) (local.get 0) (local.get 1)))
+        (func (export "size1") (result i32)
+            (memory.size $m1))
+        (func (export "grow1") (param i32) (result i32)
+            (memory.grow $m1 (local.get 0)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let mem0 = instance.exports.get_memory("mem0")?;
+    let mem1 = instance.exports.get_memory("mem1")?;
+    assert_eq!(mem0.size(), Pages(1));
+    assert_eq!(mem1.size(), Pages(2));
+    assert_eq!(mem1.ty().maximum, Some(Pages(3)));
+
+    let load0 = instance.exports.get_native_function::<i32, i32>("load0")?;
+    let load1 = instance.exports.get_native_function::<i32, i32>("load1")?;
+    let store1 = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store1")?;
+    assert_eq!(load0.call(0)?, 1);
+    assert_eq!(load1.call(0)?, 2);
+
+    store1.call(0x1_0000, 3)?;
+    assert_eq!(load1.call(0x1_0000)?, 3);
+    assert_eq!(mem1.view::<u8>()[0x1_0000].get(), 3);
+    // The second memory is larger, so this is out of bounds only for the first.
+    assert!(load0.call(0x1_0000).is_err());
+
+    let size1 = instance.exports.get_native_function::<(), i32>("size1")?;
+    let grow1 = instance.exports.get_native_function::<i32, i32>("grow1")?;
+    assert_eq!(grow1.call(1)?, 2);
+    assert_eq!(size1.call()?, 3);
+    assert_eq!(grow1.call(1)?, -1);
+    assert_eq!(mem0.size(), Pages(1));
+    assert_eq!(mem1.size(), Pages(3));
+
+    Ok(())
+}
+
+#[test]
+fn multi_memory_imported_and_local() -> Result<()> {
+    let store = get_store_enabling(|features| features.multi_memory(true));
+    let wat = r#"
+        (import "env" "memory" (memory $imported 1))
+        (memory $local (export "local") 1)
+
+        (func (export "store") (param i32 i32)
+            (i32.store8 (memory $imported) (local.get 0) (local.get 1))
+            (i32.store8 (memory $local) (local.get 0) (i32.add (local.get 1) (i32.const 1))))
+        (func (export "grow_local") (param i32) (result i32)
+            (memory.grow $local (local.get 0)))
+        (func (export "size_local") (result i32)
+            (memory.size $local))
+        (func (export "size_imported") (result i32)
+            (memory.size $imported))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let imported = Memory::new(&store, MemoryType::new(1, None, false))?;
+    let import_object = imports! {
+        "env" => {
+            "memory" => imported.clone(),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let local = instance.exports.get_memory("local")?;
+
+    let store_fn = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store")?;
+    store_fn.call(8, 10)?;
+    assert_eq!(imported.view::<u8>()[8].get(), 10);
+    assert_eq!(local.view::<u8>()[8].get(), 11);
+
+    let grow_local = instance
+        .exports
+        .get_native_function::<i32, i32>("grow_local")?;
+    let size_local = instance
+        .exports
+        .get_native_function::<(), i32>("size_local")?;
+    let size_imported = instance
+        .exports
+        .get_native_function::<(), i32>("size_imported")?;
+    assert_eq!(grow_local.call(2)?, 1);
+    assert_eq!(size_local.call()?, 3);
+    assert_eq!(size_imported.call()?, 1);
+    assert_eq!(imported.size(), Pages(1));
+
+    Ok(())
+}
+
+// Singlepass and LLVM don't implement `memory.copy`.
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn multi_memory_copy() -> Result<()> {
+    let store = get_store_enabling(|features| features.multi_memory(true));
+    let wat = r#"
+        (memory $m0 (export "mem0") 1)
+        (memory $m1 (export "mem1") 1)
+        (data (memory $m0) (i32.const 0) "hello")
+
+        (func (export "copy") (param i32 i32 i32)
+            (memory.copy $m1 $m0 (local.get 0) (local.get 1) (local.get 2)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let mem0 = instance.exports.get_memory("mem0")?;
+    let mem1 = instance.exports.get_memory("mem1")?;
+
+    let copy = instance
+        .exports
+        .get_native_function::<(i32, i32, i32), ()>("copy")?;
+    copy.call(16, 0, 5)?;
+    let copied = mem1.view::<u8>()[16..21]
+        .iter()
+        .map(|cell| cell.get())
+        .collect::<Vec<u8>>();
+    assert_eq!(copied, b"hello");
+    assert_eq!(mem0.view::<u8>()[16].get(), 0);
+
+    // The bounds are checked against each memory separately.
+    assert!(copy.call(0x1_0000 - 4, 0, 5).is_err());
+    assert!(copy.call(0, 0x1_0000 - 4, 5).is_err());
+
+    Ok(())
+}
@@ -37,7 +37,7 @@ macro_rules! mvr_test {
             }
 
             #[test]
-            #[cfg_attr(any(feature = "test-cranelift", feature="test-singlepass"), ignore)]
+            #[cfg_attr(feature = "test-cranelift", ignore)]
             fn native() -> anyhow::Result<()> {
                 let store = get_store(false);
                 let module = get_module(&store)?;
@@ -63,7 +63,6 @@ macro_rules! mvr_test {
             }
 
             #[test]
-            #[cfg_attr(feature="test-singlepass", ignore)]
             fn dynamic() -> anyhow::Result<()> {
                 let store = get_store(false);
                 let module = get_module(&store)?;
@@ -0,0 +1,87 @@
+//! Testing the allocation of instances from an instance pool.
+
+use crate::utils::get_engine;
+use anyhow::Result;
+use wasmer::*;
+
+fn get_pooling_store(limits: PoolingLimits) -> Result<Store> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables
+        .enable_pooling(limits)
+        .map_err(anyhow::Error::msg)?;
+    Ok(Store::new_with_tunables(&engine, tunables))
+}
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (table (export "table") 1 funcref)
+        (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0)))
+        (func (export "load") (result i32)
+            (i32.load (i32.const 0)))
+        (func (export "store") (param i32)
+            (i32.store (i32.const 0) (local.get 0))))
+"#;
+
+fn limits() -> PoolingLimits {
+    PoolingLimits {
+        instances: 2,
+        memories: 2,
+        memory_pages: Pages(3),
+        tables: 2,
+        table_elements: 10,
+        ..PoolingLimits::default()
+    }
+}
+
+#[test]
+fn pooled_instances_are_limited() -> Result<()> {
+    let store = get_pooling_store(limits())?;
+    let module = Module::new(&store, WAT)?;
+
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    assert!(Instance::new(&module, &imports! {}).is_err());
+
+    // Dropping an instance gives its slots back to the pool.
+    drop(first);
+    let _third = Instance::new(&module, &imports! {})?;
+    drop(second);
+    let _fourth = Instance::new(&module, &imports! {})?;
+    Ok(())
+}
+
+#[test]
+fn pooled_memories_are_reset() -> Result<()> {
+    let store = get_pooling_store(limits())?;
+    let module = Module::new(&store, WAT)?;
+
+    for _ in 0..4 {
+        let instance = Instance::new(&module, &imports! {})?;
+        let load = instance.exports.get_native_function::<(), i32>("load")?;
+        let store_value = instance.exports.get_native_function::<i32, ()>("store")?;
+        assert_eq!(load.call()?, 0);
+        store_value.call(42)?;
+        assert_eq!(load.call()?, 42);
+    }
+    Ok(())
+}
+
+#[test]
+fn pooled_memories_grow_within_the_limits() -> Result<()> {
+    let store = get_pooling_store(limits())?;
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+
+    assert_eq!(grow.call(2)?, 1);
+    assert_eq!(instance.exports.get_memory("memory")?.size(), Pages(3));
+    assert_eq!(grow.call(1)?, -1);
+
+    let table = instance.exports.get_table("table")?;
+    assert_eq!(table.grow(9, Val::ExternRef(ExternRef::Null))?, 1);
+    assert!(table.grow(1, Val::ExternRef(ExternRef::Null)).is_err());
+    Ok(())
+}
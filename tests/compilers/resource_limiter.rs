@@ -0,0 +1,191 @@
+//! Testing the limits put by a `ResourceLimiter` on the memories and
+//! tables of a store.
+
+use crate::utils::{get_engine, get_store};
+use anyhow::Result;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use wasmer::*;
+
+/// Limits the total number of pages and table elements of a store.
+struct TotalLimiter {
+    max_pages: u32,
+    max_elements: u32,
+    pages: AtomicU32,
+    elements: AtomicU32,
+}
+
+impl TotalLimiter {
+    fn new(max_pages: u32, max_elements: u32) -> Arc<Self> {
+        Arc::new(Self {
+            max_pages,
+            max_elements,
+            pages: AtomicU32::new(0),
+            elements: AtomicU32::new(0),
+        })
+    }
+}
+
+impl ResourceLimiter for TotalLimiter {
+    fn memory_growing(&self, current: Pages, desired: Pages, _maximum: Option<Pages>) -> bool {
+        let delta = desired.0 - current.0;
+        self.pages
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pages| {
+                Some(pages + delta).filter(|pages| *pages <= self.max_pages)
+            })
+            .is_ok()
+    }
+
+    fn memory_grow_failed(&self, _error: &MemoryError) {
+        panic!("the memories aren't expected to fail to grow");
+    }
+
+    fn memory_dropped(&self, size: Pages) {
+        self.pages.fetch_sub(size.0, Ordering::SeqCst);
+    }
+
+    fn table_growing(&self, current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        let delta = desired - current;
+        self.elements
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |elements| {
+                Some(elements + delta).filter(|elements| *elements <= self.max_elements)
+            })
+            .is_ok()
+    }
+
+    fn table_dropped(&self, size: u32) {
+        self.elements.fetch_sub(size, Ordering::SeqCst);
+    }
+}
+
+const WAT: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (table (export "table") 1 funcref)
+        (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0))))
+"#;
+
+#[test]
+fn limiter_denies_growth() -> Result<()> {
+    let limiter = TotalLimiter::new(3, 10);
+    let store = get_store(false);
+    store.set_resource_limiter(limiter.clone());
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 1);
+
+    let grow = instance.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(2)?, 1);
+    assert_eq!(grow.call(1)?, -1);
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.size(), Pages(3));
+    assert!(memory.grow(1).is_err());
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 3);
+
+    // The memory is dropped with the last reference to the instance.
+    drop(grow);
+    drop(instance);
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 0);
+    Ok(())
+}
+
+#[test]
+fn limiter_enforces_store_wide_totals() -> Result<()> {
+    let limiter = TotalLimiter::new(3, 10);
+    let store = get_store(false);
+    store.set_resource_limiter(limiter.clone());
+    let module = Module::new(&store, WAT)?;
+
+    let first = Instance::new(&module, &imports! {})?;
+    let second = Instance::new(&module, &imports! {})?;
+    let host = Memory::new(&store, MemoryType::new(1, None, false))?;
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 3);
+
+    // The store has no pages left for another instance, or to grow.
+    assert!(Instance::new(&module, &imports! {}).is_err());
+    assert!(Memory::new(&store, MemoryType::new(1, None, false)).is_err());
+    assert!(host.grow(1).is_err());
+
+    drop(first);
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 2);
+    let grow = second.exports.get_native_function::<i32, i32>("grow")?;
+    assert_eq!(grow.call(1)?, 1);
+    assert_eq!(grow.call(1)?, -1);
+    Ok(())
+}
+
+#[test]
+fn limiter_denies_table_growth() -> Result<()> {
+    let limiter = TotalLimiter::new(10, 4);
+    let store = get_store(false);
+    store.set_resource_limiter(limiter.clone());
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let table = instance.exports.get_table("table")?;
+    assert_eq!(limiter.elements.load(Ordering::SeqCst), 1);
+
+    let function = Val::FuncRef(Function::new_native(&store, || {}));
+    assert_eq!(table.grow(2, function.clone())?, 1);
+    assert!(table.grow(2, function.clone()).is_err());
+    assert_eq!(table.size(), 3);
+
+    let host = Table::new(
+        &store,
+        TableType::new(ValType::FuncRef, 1, None),
+        function.clone(),
+    )?;
+    assert_eq!(limiter.elements.load(Ordering::SeqCst), 4);
+    assert!(host.grow(1, function.clone()).is_err());
+
+    drop(instance);
+    drop(host);
+    assert_eq!(limiter.elements.load(Ordering::SeqCst), 0);
+    Ok(())
+}
+
+#[test]
+fn limiter_applies_to_modules_created_before() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, WAT)?;
+    let limiter = TotalLimiter::new(1, 10);
+    store.set_resource_limiter(limiter.clone());
+
+    let instance = Instance::new(&module, &imports! {})?;
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 1);
+    assert!(Instance::new(&module, &imports! {}).is_err());
+    drop(instance);
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 0);
+    Ok(())
+}
+
+#[test]
+fn limiter_is_consulted_before_allocating() -> Result<()> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables
+        .enable_pooling(PoolingLimits {
+            instances: 2,
+            memories: 1,
+            tables: 2,
+            memory_pages: Pages(1),
+            ..PoolingLimits::default()
+        })
+        .map_err(anyhow::Error::msg)?;
+    let store = Store::new_with_tunables(&engine, tunables);
+    let limiter = TotalLimiter::new(1, 10);
+    store.set_resource_limiter(limiter.clone());
+    let module = Module::new(&store, WAT)?;
+
+    // The limiter denies the second memory before the pool runs out of
+    // memory slots.
+    let _instance = Instance::new(&module, &imports! {})?;
+    let error = Instance::new(&module, &imports! {}).unwrap_err();
+    assert!(
+        error.to_string().contains("resource limiter"),
+        "unexpected error: {}",
+        error
+    );
+    assert_eq!(limiter.pages.load(Ordering::SeqCst), 1);
+    Ok(())
+}
@@ -0,0 +1,450 @@
+//! Testing the SIMD operators that were added to the proposal after its
+//! spec tests were vendored in `tests/wast/spec/proposals/simd`.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use wasmer::*;
+
+/// Packs the lanes of a `v128`, of `lane_bits` bits each, the first lane
+/// being the least significant one.
+fn pack(lanes: &[u128], lane_bits: u32) -> u128 {
+    let mask = u128::MAX >> (128 - lane_bits);
+    lanes
+        .iter()
+        .rev()
+        .fold(0, |v128, lane| (v128 << lane_bits) | (lane & mask))
+}
+
+fn i8x16(lanes: [i8; 16]) -> u128 {
+    pack(&lanes.iter().map(|&l| l as u128).collect::<Vec<_>>(), 8)
+}
+
+fn i16x8(lanes: [i16; 8]) -> u128 {
+    pack(&lanes.iter().map(|&l| l as u128).collect::<Vec<_>>(), 16)
+}
+
+fn i32x4(lanes: [i32; 4]) -> u128 {
+    pack(&lanes.iter().map(|&l| l as u128).collect::<Vec<_>>(), 32)
+}
+
+fn i64x2(lanes: [i64; 2]) -> u128 {
+    pack(&lanes.iter().map(|&l| l as u128).collect::<Vec<_>>(), 64)
+}
+
+fn f32x4(lanes: [f32; 4]) -> u128 {
+    pack(
+        &lanes
+            .iter()
+            .map(|l| l.to_bits() as u128)
+            .collect::<Vec<_>>(),
+        32,
+    )
+}
+
+fn f64x2(lanes: [f64; 2]) -> u128 {
+    pack(
+        &lanes
+            .iter()
+            .map(|l| l.to_bits() as u128)
+            .collect::<Vec<_>>(),
+        64,
+    )
+}
+
+fn get_instance(store: &Store) -> Result<Instance> {
+    let wat = r#"
+        (memory (export "memory") 1)
+        (data (i32.const 0) "\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10")
+
+        (func $a8 (result v128)
+            (v128.const i8x16 -1 2 -128 127 5 -6 7 8 100 -100 127 -128 0 1 -2 3))
+        (func $b8 (result v128)
+            (v128.const i8x16 -1 3 -128 127 -5 6 7 8 100 100 -128 -128 9 1 -2 -3))
+        (func $a16 (result v128)
+            (v128.const i16x8 -1 2 -32768 32767 300 -300 7 8))
+        (func $b16 (result v128)
+            (v128.const i16x8 -1 3 -32768 32767 300 300 -7 8))
+        (func $a32 (result v128)
+            (v128.const i32x4 -1 0x7fffffff -2147483648 3))
+        (func $b32 (result v128)
+            (v128.const i32x4 -1 0x7fffffff 2 -3))
+
+        (func (export "i16x8.extmul_low_i8x16_s") (result v128)
+            (i16x8.extmul_low_i8x16_s (call $a8) (call $b8)))
+        (func (export "i16x8.extmul_high_i8x16_s") (result v128)
+            (i16x8.extmul_high_i8x16_s (call $a8) (call $b8)))
+        (func (export "i16x8.extmul_low_i8x16_u") (result v128)
+            (i16x8.extmul_low_i8x16_u (call $a8) (call $b8)))
+        (func (export "i16x8.extmul_high_i8x16_u") (result v128)
+            (i16x8.extmul_high_i8x16_u (call $a8) (call $b8)))
+        (func (export "i32x4.extmul_low_i16x8_s") (result v128)
+            (i32x4.extmul_low_i16x8_s (call $a16) (call $b16)))
+        (func (export "i32x4.extmul_high_i16x8_s") (result v128)
+            (i32x4.extmul_high_i16x8_s (call $a16) (call $b16)))
+        (func (export "i32x4.extmul_low_i16x8_u") (result v128)
+            (i32x4.extmul_low_i16x8_u (call $a16) (call $b16)))
+        (func (export "i32x4.extmul_high_i16x8_u") (result v128)
+            (i32x4.extmul_high_i16x8_u (call $a16) (call $b16)))
+        (func (export "i64x2.extmul_low_i32x4_s") (result v128)
+            (i64x2.extmul_low_i32x4_s (call $a32) (call $b32)))
+        (func (export "i64x2.extmul_high_i32x4_s") (result v128)
+            (i64x2.extmul_high_i32x4_s (call $a32) (call $b32)))
+        (func (export "i64x2.extmul_low_i32x4_u") (result v128)
+            (i64x2.extmul_low_i32x4_u (call $a32) (call $b32)))
+        (func (export "i64x2.extmul_high_i32x4_u") (result v128)
+            (i64x2.extmul_high_i32x4_u (call $a32) (call $b32)))
+
+        (func (export "i64x2.extend_low_i32x4_s") (result v128)
+            (i64x2.extend_low_i32x4_s (call $a32)))
+        (func (export "i64x2.extend_high_i32x4_s") (result v128)
+            (i64x2.extend_high_i32x4_s (call $a32)))
+        (func (export "i64x2.extend_low_i32x4_u") (result v128)
+            (i64x2.extend_low_i32x4_u (call $a32)))
+        (func (export "i64x2.extend_high_i32x4_u") (result v128)
+            (i64x2.extend_high_i32x4_u (call $a32)))
+
+        (func (export "i8x16.bitmask") (result i32)
+            (i8x16.bitmask (call $a8)))
+        (func (export "i16x8.bitmask") (result i32)
+            (i16x8.bitmask (call $a16)))
+        (func (export "i32x4.bitmask") (result i32)
+            (i32x4.bitmask (call $a32)))
+        (func (export "i64x2.bitmask") (result i32)
+            (i64x2.bitmask (v128.const i64x2 -1 1)))
+        (func (export "i64x2.all_true") (param i64 i64) (result i32)
+            (i64x2.all_true
+                (i64x2.replace_lane 1
+                    (i64x2.splat (local.get 0))
+                    (local.get 1))))
+        (func (export "i64x2.eq") (result v128)
+            (i64x2.eq (v128.const i64x2 1 2) (v128.const i64x2 1 3)))
+        (func (export "i64x2.ne") (result v128)
+            (i64x2.ne (v128.const i64x2 1 2) (v128.const i64x2 1 3)))
+
+        (func (export "i32x4.dot_i16x8_s") (result v128)
+            (i32x4.dot_i16x8_s (call $a16) (call $b16)))
+        (func (export "i16x8.q15mulr_sat_s") (result v128)
+            (i16x8.q15mulr_sat_s
+                (v128.const i16x8 16384 -32768 -32768 32767 100 -100 1 0)
+                (v128.const i16x8 16384 -32768 16384 32767 200 200 1 5)))
+
+        (func (export "f32x4.pmin") (result v128)
+            (f32x4.pmin
+                (v128.const f32x4 1 -0 nan 3)
+                (v128.const f32x4 2 0 1 nan)))
+        (func (export "f32x4.pmax") (result v128)
+            (f32x4.pmax
+                (v128.const f32x4 1 -0 nan 3)
+                (v128.const f32x4 2 0 1 nan)))
+        (func (export "f64x2.pmin") (result v128)
+            (f64x2.pmin (v128.const f64x2 1 -0) (v128.const f64x2 -1 0)))
+        (func (export "f64x2.pmax") (result v128)
+            (f64x2.pmax (v128.const f64x2 1 -0) (v128.const f64x2 -1 0)))
+
+        (func (export "f32x4.demote_f64x2_zero") (result v128)
+            (f32x4.demote_f64x2_zero (v128.const f64x2 1.5 -2.25)))
+        (func (export "f64x2.promote_low_f32x4") (result v128)
+            (f64x2.promote_low_f32x4 (v128.const f32x4 1.5 -2.25 7 8)))
+        (func (export "f64x2.convert_low_i32x4_s") (result v128)
+            (f64x2.convert_low_i32x4_s (call $a32)))
+        (func (export "f64x2.convert_low_i32x4_u") (result v128)
+            (f64x2.convert_low_i32x4_u (call $a32)))
+        (func (export "i32x4.trunc_sat_f64x2_s_zero") (result v128)
+            (i32x4.trunc_sat_f64x2_s_zero (v128.const f64x2 -1.5 1e10)))
+        (func (export "i32x4.trunc_sat_f64x2_u_zero") (result v128)
+            (i32x4.trunc_sat_f64x2_u_zero (v128.const f64x2 nan 1e10)))
+
+        (func (export "v128.load32_zero") (result v128)
+            (v128.load32_zero (i32.const 4)))
+        (func (export "v128.load64_zero") (result v128)
+            (v128.load64_zero (i32.const 8)))
+        (func (export "v128.load8_lane") (result v128)
+            (v128.load8_lane 15 (i32.const 1) (v128.const i64x2 0 0)))
+        (func (export "v128.load16_lane") (result v128)
+            (v128.load16_lane 3 (i32.const 2) (v128.const i64x2 -1 -1)))
+        (func (export "v128.load32_lane") (result v128)
+            (v128.load32_lane 1 (i32.const 0) (v128.const i64x2 0 0)))
+        (func (export "v128.load64_lane") (result v128)
+            (v128.load64_lane 1 (i32.const 8) (v128.const i64x2 0 0)))
+        (func (export "v128.store8_lane")
+            (v128.store8_lane 5 (i32.const 32)
+                (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)))
+        (func (export "v128.store16_lane")
+            (v128.store16_lane 7 (i32.const 40)
+                (v128.const i16x8 0 0 0 0 0 0 0 0x1234)))
+        (func (export "v128.store32_lane")
+            (v128.store32_lane 2 (i32.const 48)
+                (v128.const i32x4 0 0 0x01020304 0)))
+        (func (export "v128.store64_lane")
+            (v128.store64_lane 1 (i32.const 56)
+                (v128.const i64x2 0 0x0102030405060708)))
+    "#;
+
+    let module = Module::new(store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    Ok(instance)
+}
+
+fn call_v128(instance: &Instance, name: &str) -> Result<u128> {
+    let results = instance.exports.get_function(name)?.call(&[])?;
+    Ok(results[0].unwrap_v128())
+}
+
+fn call_i32(instance: &Instance, name: &str, params: &[Val]) -> Result<i32> {
+    let results = instance.exports.get_function(name)?.call(params)?;
+    Ok(results[0].unwrap_i32())
+}
+
+// Only Cranelift lowers all of these operators for now.
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_extmul() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_v128(&instance, "i16x8.extmul_low_i8x16_s")?,
+        i16x8([1, 6, 16384, 16129, -25, -36, 49, 64])
+    );
+    assert_eq!(
+        call_v128(&instance, "i16x8.extmul_high_i8x16_s")?,
+        i16x8([10000, -10000, -16256, 16384, 0, 1, 4, -9])
+    );
+    assert_eq!(
+        call_v128(&instance, "i16x8.extmul_low_i8x16_u")?,
+        i16x8([-511, 6, 16384, 16129, 1255, 1500, 49, 64])
+    );
+    assert_eq!(
+        call_v128(&instance, "i16x8.extmul_high_i8x16_u")?,
+        i16x8([10000, 15600, 16256, 16384, 0, 1, -1020, 759])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.extmul_low_i16x8_s")?,
+        i32x4([1, 6, 1073741824, 1073676289])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.extmul_high_i16x8_s")?,
+        i32x4([90000, -90000, -49, 64])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.extmul_low_i16x8_u")?,
+        i32x4([-131071, 6, 1073741824, 1073676289])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.extmul_high_i16x8_u")?,
+        i32x4([90000, 19570800, 458703, 64])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extmul_low_i32x4_s")?,
+        i64x2([1, 4611686014132420609])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extmul_high_i32x4_s")?,
+        i64x2([-4294967296, -9])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extmul_low_i32x4_u")?,
+        i64x2([-8589934591, 4611686014132420609])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extmul_high_i32x4_u")?,
+        i64x2([4294967296, 12884901879])
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_extend() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_v128(&instance, "i64x2.extend_low_i32x4_s")?,
+        i64x2([-1, 2147483647])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extend_high_i32x4_s")?,
+        i64x2([-2147483648, 3])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extend_low_i32x4_u")?,
+        i64x2([4294967295, 2147483647])
+    );
+    assert_eq!(
+        call_v128(&instance, "i64x2.extend_high_i32x4_u")?,
+        i64x2([2147483648, 3])
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_lane_tests() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_i32(&instance, "i8x16.bitmask", &[])?,
+        0b0100_1010_0010_0101
+    );
+    assert_eq!(call_i32(&instance, "i16x8.bitmask", &[])?, 0b0010_0101);
+    assert_eq!(call_i32(&instance, "i32x4.bitmask", &[])?, 0b0101);
+    assert_eq!(call_i32(&instance, "i64x2.bitmask", &[])?, 0b01);
+    assert_eq!(
+        call_i32(&instance, "i64x2.all_true", &[Val::I64(1), Val::I64(-1)])?,
+        1
+    );
+    assert_eq!(
+        call_i32(&instance, "i64x2.all_true", &[Val::I64(1), Val::I64(0)])?,
+        0
+    );
+    assert_eq!(
+        call_i32(&instance, "i64x2.all_true", &[Val::I64(0), Val::I64(1)])?,
+        0
+    );
+    assert_eq!(call_v128(&instance, "i64x2.eq")?, i64x2([-1, 0]));
+    assert_eq!(call_v128(&instance, "i64x2.ne")?, i64x2([0, -1]));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_integer_arithmetic() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_v128(&instance, "i32x4.dot_i16x8_s")?,
+        i32x4([7, 2147418113, 0, 15])
+    );
+    assert_eq!(
+        call_v128(&instance, "i16x8.q15mulr_sat_s")?,
+        i16x8([8192, 32767, -16384, 32766, 1, -1, 0, 0])
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_pseudo_min_max() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    // The pseudo-minimum and maximum return their first operand unless the
+    // second one compares strictly less (or greater), NaNs included.
+    assert_eq!(
+        call_v128(&instance, "f32x4.pmin")?,
+        f32x4([1.0, -0.0, f32::NAN, 3.0])
+    );
+    assert_eq!(
+        call_v128(&instance, "f32x4.pmax")?,
+        f32x4([2.0, -0.0, f32::NAN, 3.0])
+    );
+    assert_eq!(call_v128(&instance, "f64x2.pmin")?, f64x2([-1.0, -0.0]));
+    assert_eq!(call_v128(&instance, "f64x2.pmax")?, f64x2([1.0, -0.0]));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_conversions() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_v128(&instance, "f32x4.demote_f64x2_zero")?,
+        f32x4([1.5, -2.25, 0.0, 0.0])
+    );
+    assert_eq!(
+        call_v128(&instance, "f64x2.promote_low_f32x4")?,
+        f64x2([1.5, -2.25])
+    );
+    assert_eq!(
+        call_v128(&instance, "f64x2.convert_low_i32x4_s")?,
+        f64x2([-1.0, 2147483647.0])
+    );
+    assert_eq!(
+        call_v128(&instance, "f64x2.convert_low_i32x4_u")?,
+        f64x2([4294967295.0, 2147483647.0])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.trunc_sat_f64x2_s_zero")?,
+        i32x4([-1, 2147483647, 0, 0])
+    );
+    assert_eq!(
+        call_v128(&instance, "i32x4.trunc_sat_f64x2_u_zero")?,
+        i32x4([0, -1, 0, 0])
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_load_zero_and_lane() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    assert_eq!(
+        call_v128(&instance, "v128.load32_zero")?,
+        i32x4([0x08070605, 0, 0, 0])
+    );
+    assert_eq!(
+        call_v128(&instance, "v128.load64_zero")?,
+        i64x2([0x100f0e0d0c0b0a09, 0])
+    );
+    assert_eq!(
+        call_v128(&instance, "v128.load8_lane")?,
+        i8x16([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2])
+    );
+    assert_eq!(
+        call_v128(&instance, "v128.load16_lane")?,
+        i16x8([-1, -1, -1, 0x0403, -1, -1, -1, -1])
+    );
+    assert_eq!(
+        call_v128(&instance, "v128.load32_lane")?,
+        i32x4([0, 0x04030201, 0, 0])
+    );
+    assert_eq!(
+        call_v128(&instance, "v128.load64_lane")?,
+        i64x2([0, 0x100f0e0d0c0b0a09])
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(any(feature = "test-singlepass", feature = "test-llvm"), ignore)]
+fn simd_store_lane() -> Result<()> {
+    let store = get_store_enabling(|features| features.simd(true));
+    let instance = get_instance(&store)?;
+
+    for name in &[
+        "v128.store8_lane",
+        "v128.store16_lane",
+        "v128.store32_lane",
+        "v128.store64_lane",
+    ] {
+        instance.exports.get_function(name)?.call(&[])?;
+    }
+
+    let memory = instance.exports.get_memory("memory")?;
+    let bytes = |start: usize, end: usize| {
+        memory.view::<u8>()[start..end]
+            .iter()
+            .map(|b| b.get())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(bytes(32, 34), [5, 0]);
+    assert_eq!(bytes(40, 43), [0x34, 0x12, 0]);
+    assert_eq!(bytes(48, 53), [4, 3, 2, 1, 0]);
+    assert_eq!(bytes(56, 65), [8, 7, 6, 5, 4, 3, 2, 1, 0]);
+
+    Ok(())
+}
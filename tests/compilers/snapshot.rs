@@ -0,0 +1,127 @@
+//! Testing snapshots of instances, and their restoration.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use wasmer::*;
+
+const WAT: &str = r#"
+    (module
+        (import "env" "get" (func $get (result i32)))
+        (export "get" (func $get))
+        (memory (export "memory") 1)
+        (table (export "table") 2 funcref)
+        (global $counter (export "counter") (mut i32) (i32.const 0))
+        (global $step (export "step") i32 (i32.const 1))
+        (data (i32.const 0) "abcd")
+        (elem (i32.const 0) $increment)
+        (func $increment (export "increment")
+            (global.set $counter (i32.add (global.get $counter) (global.get $step))))
+        (func (export "init")
+            (i32.store (i32.const 0x1_0004) (call $get))
+            (i32.store (i32.const 0x2_0000) (i32.const 0x6867_6665)))
+        (func (export "call") (param i32) (result i32)
+            (call_indirect (result i32) (local.get 0)))
+        (func (export "load") (param i32) (result i32)
+            (i32.load (local.get 0)))
+        (start $increment))
+"#;
+
+fn imports(store: &Store, value: i32) -> ImportObject {
+    imports! {
+        "env" => {
+            "get" => Function::new(
+                store,
+                FunctionType::new(vec![], vec![Type::I32]),
+                move |_| Ok(vec![Value::I32(value)]),
+            ),
+        },
+    }
+}
+
+fn load(instance: &Instance, offset: i32) -> Result<i32> {
+    let load = instance.exports.get_native_function::<i32, i32>("load")?;
+    Ok(load.call(offset)?)
+}
+
+fn counter(instance: &Instance) -> Result<Value> {
+    Ok(instance.exports.get_global("counter")?.get())
+}
+
+#[test]
+fn snapshot_and_restore() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports(&store, 42))?;
+    instance.exports.get_memory("memory")?.grow(2)?;
+    instance
+        .exports
+        .get_native_function::<(), ()>("init")?
+        .call()?;
+    let get = instance.exports.get_function("get")?.clone();
+    instance
+        .exports
+        .get_table("table")?
+        .set(1, Val::FuncRef(get))?;
+    instance
+        .exports
+        .get_native_function::<(), ()>("increment")?
+        .call()?;
+
+    let snapshot = instance.snapshot()?;
+    let bytes = bincode::serialize(&snapshot)?;
+    let snapshot: InstanceSnapshot = bincode::deserialize(&bytes)?;
+    drop(instance);
+
+    // The start function isn't run again, and the imports are new.
+    let restored = Instance::restore(&module, &snapshot, &imports(&store, 7))?;
+    assert_eq!(counter(&restored)?, Value::I32(2));
+    assert_eq!(restored.exports.get_memory("memory")?.size(), Pages(3));
+    assert_eq!(load(&restored, 0)?, i32::from_le_bytes(*b"abcd"));
+    assert_eq!(load(&restored, 0x1_0004)?, 42);
+    assert_eq!(load(&restored, 0x2_0000)?, i32::from_le_bytes(*b"efgh"));
+    assert_eq!(load(&restored, 0x1_0000)?, 0);
+
+    let call = restored.exports.get_native_function::<i32, i32>("call")?;
+    assert_eq!(call.call(1)?, 7);
+    assert!(call.call(0).is_err());
+
+    assert_eq!(restored.snapshot()?, snapshot);
+    Ok(())
+}
+
+#[test]
+fn restore_mismatched_snapshot() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports(&store, 0))?;
+    let snapshot = instance.snapshot()?;
+
+    let other = Module::new(&store, "(module)")?;
+    assert!(Instance::restore(&other, &snapshot, &imports! {}).is_err());
+
+    let smaller = Module::new(&store, "(module (memory 2))")?;
+    let snapshot = Instance::new(&smaller, &imports! {})?.snapshot()?;
+    let larger = Module::new(&store, "(module (memory 3))")?;
+    assert!(Instance::restore(&larger, &snapshot, &imports! {}).is_err());
+    Ok(())
+}
+
+#[test]
+fn snapshot_foreign_function() -> Result<()> {
+    let store = get_store(false);
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+            (table (export "table") 1 funcref))
+        "#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let function = Function::new_native(&store, || {});
+    instance
+        .exports
+        .get_table("table")?
+        .set(0, Val::FuncRef(function))?;
+    assert!(instance.snapshot().is_err());
+    Ok(())
+}
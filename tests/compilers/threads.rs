@@ -0,0 +1,83 @@
+//! Testing shared memories imported into instances running on
+//! different threads.
+
+use crate::utils::get_store_enabling;
+use anyhow::Result;
+use std::thread;
+use wasmer::*;
+
+fn get_module(store: &Store) -> Result<Module> {
+    let wat = r#"
+        (import "env" "memory" (memory 1 1 shared))
+
+        (func (export "wait") (param $addr i32) (result i32)
+            (memory.atomic.wait32 (local.get $addr) (i32.const 0) (i64.const -1)))
+
+        (func (export "notify") (param $addr i32) (result i32)
+            (memory.atomic.notify (local.get $addr) (i32.const 1)))
+
+        (func (export "store") (param $addr i32) (param $value i32)
+            (i32.atomic.store (local.get $addr) (local.get $value)))
+    "#;
+
+    let module = Module::new(&store, &wat)?;
+    Ok(module)
+}
+
+// Singlepass doesn't lower atomic wait and notify on aarch64.
+#[test]
+#[cfg_attr(all(feature = "test-singlepass", target_arch = "aarch64"), ignore)]
+fn shared_memory_wait_notify() -> Result<()> {
+    let store = get_store_enabling(|features| features.threads(true));
+    let module = get_module(&store)?;
+    let memory = Memory::new(&store, MemoryType::new(1, Some(1), true))?;
+
+    let waiters = (0..2)
+        .map(|_| {
+            let module = module.clone();
+            let memory = memory.clone();
+            thread::spawn(move || -> Result<i32> {
+                let instance = Instance::new(
+                    &module,
+                    &imports! {
+                        "env" => {
+                            "memory" => memory,
+                        },
+                    },
+                )?;
+                let wait = instance.exports.get_native_function::<i32, i32>("wait")?;
+                Ok(wait.call(8)?)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "memory" => memory.clone(),
+            },
+        },
+    )?;
+    let notify = instance.exports.get_native_function::<i32, i32>("notify")?;
+    let store = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("store")?;
+
+    // The waiters may not be parked yet, so keep notifying until both
+    // have been woken up.
+    let mut woken = 0;
+    while woken < 2 {
+        woken += notify.call(8)?;
+        thread::yield_now();
+    }
+    for waiter in waiters {
+        assert_eq!(waiter.join().unwrap()?, 0);
+    }
+
+    // The stores of one instance are visible through the memory object.
+    store.call(8, 42)?;
+    assert_eq!(memory.view::<u32>()[2].get(), 42);
+
+    Ok(())
+}
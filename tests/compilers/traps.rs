@@ -1,4 +1,4 @@
-use crate::utils::get_store;
+use crate::utils::{get_engine, get_store};
 use anyhow::Result;
 use std::panic::{self, AssertUnwindSafe};
 use wasmer::*;
@@ -125,6 +125,91 @@ fn test_trap_trace_cb() -> Result<()> {
     Ok(())
 }
 
+/// Runs a function recursing until the stack overflows with at most
+/// `max_wasm_stack` bytes of stack, and returns the depth it reached.
+fn stack_overflow_depth(max_wasm_stack: usize) -> Result<i32> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables.max_wasm_stack = Some(max_wasm_stack);
+    let store = Store::new_with_tunables(&engine, tunables);
+    let wat = r#"
+        (module
+            (global $depth (export "depth") (mut i32) (i32.const 0))
+            (func $run (export "run")
+                (global.set $depth (i32.add (global.get $depth) (i32.const 1)))
+                (call $run)))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let run_func = instance.exports.get_native_function::<(), ()>("run")?;
+
+    let e = run_func.call().unwrap_err();
+    assert!(e.message().contains("call stack exhausted"));
+    Ok(instance.exports.get_global("depth")?.get().unwrap_i32())
+}
+
+#[test]
+fn test_trap_max_wasm_stack() -> Result<()> {
+    // Each frame takes at least a return address.
+    let depth = stack_overflow_depth(0x1_0000)?;
+    assert!(depth > 0 && depth <= 0x1_0000 / 8);
+
+    // The depth doesn't depend on the stack of the calling thread.
+    assert_eq!(stack_overflow_depth(0x1_0000)?, depth);
+    let thread_depth = std::thread::Builder::new()
+        .stack_size(0x8_0000)
+        .spawn(|| stack_overflow_depth(0x1_0000).unwrap())?
+        .join()
+        .unwrap();
+    assert_eq!(thread_depth, depth);
+
+    assert!(stack_overflow_depth(0x2_0000)? > depth);
+    Ok(())
+}
+
+#[test]
+fn test_trap_max_wasm_stack_concurrent_callers() -> Result<()> {
+    let engine = get_engine(false);
+    let mut tunables = BaseTunables::for_target(engine.target());
+    tunables.max_wasm_stack = Some(0x1_0000);
+    let store = Store::new_with_tunables(&engine, tunables);
+    let wat = r#"
+        (module
+            (func $recurse (export "recurse") (param i32) (result i32)
+                (if (result i32) (i32.eqz (local.get 0))
+                    (then (i32.const 0))
+                    (else
+                        (i32.add
+                            (i32.const 1)
+                            (call $recurse (i32.sub (local.get 0) (i32.const 1))))))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let recurse = instance
+        .exports
+        .get_native_function::<i32, i32>("recurse")?;
+
+    // The stack limit of a thread never applies to the others.
+    let threads = (0..4)
+        .map(|_| {
+            let recurse = recurse.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    assert_eq!(recurse.call(100).unwrap(), 100);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // And it applies again once they are done.
+    let e = recurse.call(0x1_0000).unwrap_err();
+    assert!(e.message().contains("call stack exhausted"));
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(
     any(
@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use wasmer::{ModuleMiddleware, Store};
+use wasmer::{Features, ModuleMiddleware, Store};
 use wasmer_compiler::CompilerConfig;
 use wasmer_engine::Engine;
 #[cfg(feature = "test-jit")]
@@ -50,6 +50,23 @@ pub fn get_store(canonicalize_nans: bool) -> Store {
     Store::new(&get_engine(canonicalize_nans))
 }
 
+pub fn get_store_with_features(features: Features) -> Store {
+    let compiler_config = get_compiler(false);
+    #[cfg(feature = "test-jit")]
+    let engine = JIT::new(compiler_config).features(features).engine();
+    #[cfg(feature = "test-native")]
+    let engine = Native::new(compiler_config).features(features).engine();
+    Store::new(&engine)
+}
+
+/// Returns a store with the default features plus the ones `enable` turns
+/// on, e.g. `get_store_enabling(|features| features.simd(true))`.
+pub fn get_store_enabling(enable: impl FnOnce(&mut Features) -> &mut Features) -> Store {
+    let mut features = Features::default();
+    enable(&mut features);
+    get_store_with_features(features)
+}
+
 pub fn get_store_with_middlewares<I: Iterator<Item = Arc<dyn ModuleMiddleware>>>(
     middlewares: I,
 ) -> Store {
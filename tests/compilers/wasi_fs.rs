@@ -0,0 +1,573 @@
+#![cfg(all(feature = "compiler", feature = "engine", feature = "wasi"))]
+
+//! Testing the preopened directories mounted from a custom `FileSystem`,
+//! and from the in-memory `MemFileSystem`.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmer::*;
+use wasmer_wasi::types::*;
+use wasmer_wasi::{
+    DirEntry, FileSystem, HostFileSystem, MemFileSystem, OpenOptions, WasiFile, WasiFsError,
+    WasiState, ALL_RIGHTS,
+};
+
+/// Serves the paths of the filesystem from a directory of the host.
+#[derive(Debug, Serialize, Deserialize)]
+struct RootedFileSystem {
+    root: PathBuf,
+}
+
+impl RootedFileSystem {
+    fn host_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for RootedFileSystem {
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        HostFileSystem.open(&self.host_path(path), options)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        HostFileSystem.read_dir(&self.host_path(path))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        HostFileSystem.metadata(&self.host_path(path))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<__wasi_filestat_t, WasiFsError> {
+        HostFileSystem.symlink_metadata(&self.host_path(path))
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        HostFileSystem.read_link(&self.host_path(path))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        HostFileSystem.create_dir(&self.host_path(path))
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        HostFileSystem.remove_dir(&self.host_path(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        HostFileSystem.rename(&self.host_path(from), &self.host_path(to))
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), WasiFsError> {
+        HostFileSystem.unlink(&self.host_path(path))
+    }
+
+    fn symlink(&self, target: &Path, path: &Path) -> Result<(), WasiFsError> {
+        HostFileSystem.symlink(target, &self.host_path(path))
+    }
+}
+
+/// Re-exports the WASI syscalls so that the tests can call them directly.
+const WAT: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_remove_directory" (func $path_remove_directory (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_readlink" (func $path_readlink (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_unlink_file" (func $path_unlink_file (param i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (export "path_open" (func $path_open))
+        (export "fd_read" (func $fd_read))
+        (export "fd_write" (func $fd_write))
+        (export "fd_close" (func $fd_close))
+        (export "path_create_directory" (func $path_create_directory))
+        (export "path_remove_directory" (func $path_remove_directory))
+        (export "path_readlink" (func $path_readlink))
+        (export "path_rename" (func $path_rename))
+        (export "path_symlink" (func $path_symlink))
+        (export "path_unlink_file" (func $path_unlink_file)))
+"#;
+
+/// The first preopened directory, after the standard streams and the root.
+const PREOPEN_FD: i32 = 4;
+
+// Where the arguments of the syscalls are put in the memory.
+const IOVEC: i32 = 0;
+const RESULT: i32 = 16;
+const PATH: i32 = 1024;
+const OTHER_PATH: i32 = 2048;
+const BUFFER: i32 = 4096;
+
+struct Guest {
+    instance: Instance,
+}
+
+impl Guest {
+    /// Instantiates the module with the root of `filesystem` mounted at `/data`.
+    fn new(filesystem: Arc<dyn FileSystem>) -> Result<Self> {
+        Self::with_preopens(filesystem, &[("/", "data")])
+    }
+
+    /// Instantiates the module with the directories of `filesystem` mounted
+    /// at their aliases, from `PREOPEN_FD` on.
+    fn with_preopens(
+        filesystem: Arc<dyn FileSystem>,
+        directories: &[(&str, &str)],
+    ) -> Result<Self> {
+        let store = get_store(false);
+        let module = Module::new(&store, WAT)?;
+        let mut state = WasiState::new("wasi_fs");
+        for (directory, alias) in directories {
+            state.preopen(|p| {
+                p.directory(directory)
+                    .alias(alias)
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .filesystem(filesystem.clone())
+            })?;
+        }
+        let mut wasi_env = state.finalize()?;
+        let import_object = wasi_env.import_object(&module)?;
+        let instance = Instance::new(&module, &import_object)?;
+        Ok(Self { instance })
+    }
+
+    fn write_memory(&self, offset: i32, bytes: &[u8]) -> Result<()> {
+        let view = self.instance.exports.get_memory("memory")?.view::<u8>();
+        for (cell, byte) in view[offset as usize..].iter().zip(bytes) {
+            cell.set(*byte);
+        }
+        Ok(())
+    }
+
+    fn read_memory(&self, offset: i32, len: usize) -> Result<Vec<u8>> {
+        let view = self.instance.exports.get_memory("memory")?.view::<u8>();
+        Ok(view[offset as usize..offset as usize + len]
+            .iter()
+            .map(|cell| cell.get())
+            .collect())
+    }
+
+    fn read_u32(&self, offset: i32) -> Result<u32> {
+        let bytes = self.read_memory(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Calls the syscall `name`, returning its errno.
+    fn call(&self, name: &str, params: &[Value]) -> Result<__wasi_errno_t> {
+        let results = self.instance.exports.get_function(name)?.call(params)?;
+        Ok(results[0].unwrap_i32() as __wasi_errno_t)
+    }
+
+    fn path_open(&self, path: &str, o_flags: __wasi_oflags_t) -> Result<i32> {
+        let errno = self.try_path_open(path, 0, o_flags)?;
+        assert_eq!(errno, __WASI_ESUCCESS, "path_open({:?})", path);
+        Ok(self.read_u32(RESULT)? as i32)
+    }
+
+    /// Calls `path_open`, returning its errno and leaving the fd at `RESULT`.
+    fn try_path_open(
+        &self,
+        path: &str,
+        dirflags: __wasi_lookupflags_t,
+        o_flags: __wasi_oflags_t,
+    ) -> Result<__wasi_errno_t> {
+        self.write_memory(PATH, path.as_bytes())?;
+        self.call(
+            "path_open",
+            &[
+                Value::I32(PREOPEN_FD),
+                Value::I32(dirflags as i32),
+                Value::I32(PATH),
+                Value::I32(path.len() as i32),
+                Value::I32(o_flags as i32),
+                Value::I64(ALL_RIGHTS as i64),
+                Value::I64(ALL_RIGHTS as i64),
+                Value::I32(0),
+                Value::I32(RESULT),
+            ],
+        )
+    }
+
+    fn write(&self, fd: i32, bytes: &[u8]) -> Result<()> {
+        self.write_memory(BUFFER, bytes)?;
+        self.write_memory(IOVEC, &BUFFER.to_le_bytes())?;
+        self.write_memory(IOVEC + 4, &(bytes.len() as u32).to_le_bytes())?;
+        let errno = self.call(
+            "fd_write",
+            &[
+                Value::I32(fd),
+                Value::I32(IOVEC),
+                Value::I32(1),
+                Value::I32(RESULT),
+            ],
+        )?;
+        assert_eq!(errno, __WASI_ESUCCESS);
+        assert_eq!(self.read_u32(RESULT)? as usize, bytes.len());
+        Ok(())
+    }
+
+    fn read(&self, fd: i32) -> Result<Vec<u8>> {
+        self.write_memory(IOVEC, &BUFFER.to_le_bytes())?;
+        self.write_memory(IOVEC + 4, &1024u32.to_le_bytes())?;
+        let errno = self.call(
+            "fd_read",
+            &[
+                Value::I32(fd),
+                Value::I32(IOVEC),
+                Value::I32(1),
+                Value::I32(RESULT),
+            ],
+        )?;
+        assert_eq!(errno, __WASI_ESUCCESS);
+        let len = self.read_u32(RESULT)? as usize;
+        self.read_memory(BUFFER, len)
+    }
+
+    fn close(&self, fd: i32) -> Result<()> {
+        assert_eq!(self.call("fd_close", &[Value::I32(fd)])?, __WASI_ESUCCESS);
+        Ok(())
+    }
+
+    /// Calls a syscall taking a directory and a path.
+    fn call_with_path(&self, name: &str, path: &str) -> Result<__wasi_errno_t> {
+        self.write_memory(PATH, path.as_bytes())?;
+        self.call(
+            name,
+            &[
+                Value::I32(PREOPEN_FD),
+                Value::I32(PATH),
+                Value::I32(path.len() as i32),
+            ],
+        )
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<__wasi_errno_t> {
+        self.write_memory(PATH, target.as_bytes())?;
+        self.write_memory(OTHER_PATH, path.as_bytes())?;
+        self.call(
+            "path_symlink",
+            &[
+                Value::I32(PATH),
+                Value::I32(target.len() as i32),
+                Value::I32(PREOPEN_FD),
+                Value::I32(OTHER_PATH),
+                Value::I32(path.len() as i32),
+            ],
+        )
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.write_memory(PATH, path.as_bytes())?;
+        let errno = self.call(
+            "path_readlink",
+            &[
+                Value::I32(PREOPEN_FD),
+                Value::I32(PATH),
+                Value::I32(path.len() as i32),
+                Value::I32(BUFFER),
+                Value::I32(1024),
+                Value::I32(RESULT),
+            ],
+        )?;
+        assert_eq!(errno, __WASI_ESUCCESS);
+        let len = self.read_u32(RESULT)? as usize;
+        Ok(String::from_utf8(self.read_memory(BUFFER, len)?)?)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<__wasi_errno_t> {
+        self.rename_at(PREOPEN_FD, from, PREOPEN_FD, to)
+    }
+
+    fn rename_at(&self, from_fd: i32, from: &str, to_fd: i32, to: &str) -> Result<__wasi_errno_t> {
+        self.write_memory(PATH, from.as_bytes())?;
+        self.write_memory(OTHER_PATH, to.as_bytes())?;
+        self.call(
+            "path_rename",
+            &[
+                Value::I32(from_fd),
+                Value::I32(PATH),
+                Value::I32(from.len() as i32),
+                Value::I32(to_fd),
+                Value::I32(OTHER_PATH),
+                Value::I32(to.len() as i32),
+            ],
+        )
+    }
+}
+
+#[test]
+fn preopen_reads_and_writes_custom_filesystem() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    fs::write(root.path().join("from_host.txt"), "hello from the host")?;
+    let guest = Guest::new(Arc::new(RootedFileSystem {
+        root: root.path().to_path_buf(),
+    }))?;
+
+    let fd = guest.path_open("from_host.txt", 0)?;
+    assert_eq!(guest.read(fd)?, b"hello from the host");
+    guest.close(fd)?;
+
+    let fd = guest.path_open("from_guest.txt", __WASI_O_CREAT)?;
+    guest.write(fd, b"hello from the guest")?;
+    guest.close(fd)?;
+    assert_eq!(
+        fs::read_to_string(root.path().join("from_guest.txt"))?,
+        "hello from the guest"
+    );
+    Ok(())
+}
+
+#[test]
+fn preopen_manages_entries_of_custom_filesystem() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    let guest = Guest::new(Arc::new(RootedFileSystem {
+        root: root.path().to_path_buf(),
+    }))?;
+
+    assert_eq!(
+        guest.call_with_path("path_create_directory", "dir")?,
+        __WASI_ESUCCESS
+    );
+    assert!(root.path().join("dir").is_dir());
+
+    let fd = guest.path_open("dir/old", __WASI_O_CREAT)?;
+    guest.close(fd)?;
+    assert_eq!(guest.rename("dir/old", "dir/new")?, __WASI_ESUCCESS);
+    assert!(!root.path().join("dir/old").exists());
+    assert!(root.path().join("dir/new").is_file());
+
+    assert_eq!(
+        guest.call_with_path("path_remove_directory", "dir")?,
+        __WASI_ENOTEMPTY
+    );
+    assert_eq!(
+        guest.call_with_path("path_unlink_file", "dir/new")?,
+        __WASI_ESUCCESS
+    );
+    assert!(!root.path().join("dir/new").exists());
+    assert_eq!(
+        guest.call_with_path("path_remove_directory", "dir")?,
+        __WASI_ESUCCESS
+    );
+    assert!(!root.path().join("dir").exists());
+    Ok(())
+}
+
+#[test]
+fn preopen_reads_and_writes_memory_filesystem() -> Result<()> {
+    let filesystem = MemFileSystem::new();
+    let guest = Guest::new(Arc::new(filesystem.clone()))?;
+
+    let fd = guest.path_open("from_guest.txt", __WASI_O_CREAT)?;
+    guest.write(fd, b"hello from the guest")?;
+    guest.close(fd)?;
+
+    let options = OpenOptions {
+        read: true,
+        ..OpenOptions::default()
+    };
+    let mut contents = String::new();
+    filesystem
+        .open(Path::new("/from_guest.txt"), &options)?
+        .read_to_string(&mut contents)?;
+    assert_eq!(contents, "hello from the guest");
+
+    let fd = guest.path_open("from_guest.txt", __WASI_O_TRUNC)?;
+    assert_eq!(guest.read(fd)?, b"");
+    guest.close(fd)?;
+    Ok(())
+}
+
+#[test]
+fn preopen_manages_entries_of_memory_filesystem() -> Result<()> {
+    let filesystem = MemFileSystem::new();
+    let guest = Guest::new(Arc::new(filesystem.clone()))?;
+
+    assert_eq!(
+        guest.call_with_path("path_create_directory", "dir")?,
+        __WASI_ESUCCESS
+    );
+    let fd = guest.path_open("dir/old", __WASI_O_CREAT)?;
+    guest.write(fd, b"contents")?;
+    guest.close(fd)?;
+    assert_eq!(guest.rename("dir/old", "dir/new")?, __WASI_ESUCCESS);
+    assert_eq!(
+        filesystem.read_dir(Path::new("/dir"))?,
+        vec![DirEntry {
+            name: "new".to_string(),
+            file_type: __WASI_FILETYPE_REGULAR_FILE,
+        }]
+    );
+    assert_eq!(filesystem.metadata(Path::new("/dir/new"))?.st_size, 8);
+
+    assert_eq!(
+        guest.call_with_path("path_remove_directory", "dir")?,
+        __WASI_ENOTEMPTY
+    );
+    assert_eq!(
+        guest.call_with_path("path_unlink_file", "dir/new")?,
+        __WASI_ESUCCESS
+    );
+    assert_eq!(
+        guest.call_with_path("path_remove_directory", "dir")?,
+        __WASI_ESUCCESS
+    );
+    assert_eq!(filesystem.read_dir(Path::new("/"))?, vec![]);
+    Ok(())
+}
+
+/// Creates `/dir/file` in a new `MemFileSystem`.
+fn memory_filesystem_with_file() -> Result<MemFileSystem> {
+    let filesystem = MemFileSystem::new();
+    filesystem.create_dir(Path::new("/dir"))?;
+    let options = OpenOptions {
+        write: true,
+        create: true,
+        ..OpenOptions::default()
+    };
+    filesystem
+        .open(Path::new("/dir/file"), &options)?
+        .write_all(b"contents")?;
+    Ok(filesystem)
+}
+
+#[test]
+fn symlinks_are_followed_inside_preopen() -> Result<()> {
+    let filesystem = memory_filesystem_with_file()?;
+    filesystem.symlink(Path::new("dir/file"), Path::new("/relative"))?;
+    filesystem.symlink(Path::new("/dir/file"), Path::new("/absolute"))?;
+    filesystem.symlink(Path::new("../dir"), Path::new("/dir/parent"))?;
+    let guest = Guest::new(Arc::new(filesystem))?;
+
+    for path in &["relative", "absolute", "dir/parent/file"] {
+        let errno = guest.try_path_open(path, __WASI_LOOKUP_SYMLINK_FOLLOW, 0)?;
+        assert_eq!(errno, __WASI_ESUCCESS, "path_open({:?})", path);
+        let fd = guest.read_u32(RESULT)? as i32;
+        assert_eq!(guest.read(fd)?, b"contents");
+        guest.close(fd)?;
+    }
+    // the symlinks leading to the last component are always followed
+    let fd = guest.path_open("dir/parent/parent/file", 0)?;
+    guest.close(fd)?;
+    assert_eq!(guest.try_path_open("relative", 0, 0)?, __WASI_ELOOP);
+    Ok(())
+}
+
+#[test]
+fn symlinks_cannot_escape_preopen() -> Result<()> {
+    let filesystem = memory_filesystem_with_file()?;
+    filesystem.create_dir(Path::new("/sandbox"))?;
+    filesystem.symlink(Path::new("/dir/file"), Path::new("/sandbox/absolute"))?;
+    filesystem.symlink(Path::new("../dir/file"), Path::new("/sandbox/relative"))?;
+    filesystem.symlink(Path::new("loop"), Path::new("/sandbox/loop"))?;
+    let guest = Guest::with_preopens(Arc::new(filesystem), &[("/sandbox", "data")])?;
+
+    let follow = __WASI_LOOKUP_SYMLINK_FOLLOW;
+    assert_eq!(
+        guest.try_path_open("absolute", follow, 0)?,
+        __WASI_ENOTCAPABLE
+    );
+    // `..` leads from the preopened directory to the root of the preopened directories
+    assert_eq!(guest.try_path_open("relative", follow, 0)?, __WASI_ENOENT);
+    assert_eq!(guest.try_path_open("loop", follow, 0)?, __WASI_ELOOP);
+    assert_eq!(guest.try_path_open("loop/file", 0, 0)?, __WASI_ELOOP);
+    Ok(())
+}
+
+#[test]
+fn guest_manages_symlinks() -> Result<()> {
+    let filesystem = memory_filesystem_with_file()?;
+    let guest = Guest::new(Arc::new(filesystem.clone()))?;
+
+    assert_eq!(guest.symlink("dir/file", "link")?, __WASI_ESUCCESS);
+    assert_eq!(
+        filesystem.read_link(Path::new("/link"))?,
+        Path::new("dir/file")
+    );
+    assert_eq!(guest.readlink("link")?, "dir/file");
+    assert_eq!(guest.symlink("dir", "link")?, __WASI_EEXIST);
+    assert_eq!(guest.symlink("/dir/file", "absolute")?, __WASI_ENOTCAPABLE);
+
+    let errno = guest.try_path_open("link", __WASI_LOOKUP_SYMLINK_FOLLOW, 0)?;
+    assert_eq!(errno, __WASI_ESUCCESS);
+    let fd = guest.read_u32(RESULT)? as i32;
+    assert_eq!(guest.read(fd)?, b"contents");
+    guest.close(fd)?;
+
+    assert_eq!(guest.rename("link", "dir/link")?, __WASI_ESUCCESS);
+    assert_eq!(
+        filesystem.read_link(Path::new("/dir/link"))?,
+        Path::new("dir/file")
+    );
+    assert_eq!(
+        guest.call_with_path("path_unlink_file", "dir/link")?,
+        __WASI_ESUCCESS
+    );
+    assert!(filesystem.symlink_metadata(Path::new("/dir/link")).is_err());
+    assert!(filesystem.metadata(Path::new("/dir/file")).is_ok());
+    Ok(())
+}
+
+#[test]
+fn rename_moves_directories_across_preopens() -> Result<()> {
+    let filesystem = memory_filesystem_with_file()?;
+    filesystem.create_dir(Path::new("/a"))?;
+    filesystem.create_dir(Path::new("/b"))?;
+    filesystem.rename(Path::new("/dir"), Path::new("/a/dir"))?;
+    let guest = Guest::with_preopens(Arc::new(filesystem.clone()), &[("/a", "a"), ("/b", "b")])?;
+
+    // cache the directory and its entries before moving them
+    let open_fd = guest.path_open("dir/file", 0)?;
+    assert_eq!(
+        guest.rename_at(PREOPEN_FD, "dir", PREOPEN_FD + 1, "moved")?,
+        __WASI_ESUCCESS
+    );
+    assert!(filesystem.metadata(Path::new("/a/dir")).is_err());
+    assert_eq!(filesystem.metadata(Path::new("/b/moved/file"))?.st_size, 8);
+    assert_eq!(guest.read(open_fd)?, b"contents");
+    guest.close(open_fd)?;
+
+    assert_eq!(guest.try_path_open("dir/file", 0, 0)?, __WASI_ENOENT);
+    let fd = guest.path_open("../b/moved/../moved/file", 0)?;
+    assert_eq!(guest.read(fd)?, b"contents");
+    guest.close(fd)?;
+    Ok(())
+}
+
+#[test]
+fn rename_replaces_targets_like_posix() -> Result<()> {
+    let filesystem = memory_filesystem_with_file()?;
+    filesystem.create_dir(Path::new("/empty"))?;
+    let guest = Guest::new(Arc::new(filesystem.clone()))?;
+
+    let fd = guest.path_open("replaced", __WASI_O_CREAT)?;
+    guest.write(fd, b"replaced")?;
+    guest.close(fd)?;
+    assert_eq!(guest.rename("dir/file", "replaced")?, __WASI_ESUCCESS);
+    let fd = guest.path_open("replaced", 0)?;
+    assert_eq!(guest.read(fd)?, b"contents");
+    guest.close(fd)?;
+    assert_eq!(guest.try_path_open("dir/file", 0, 0)?, __WASI_ENOENT);
+
+    assert_eq!(guest.rename("replaced", "dir")?, __WASI_EISDIR);
+    assert_eq!(guest.rename("dir", "replaced")?, __WASI_ENOTDIR);
+    assert_eq!(guest.rename("dir", "dir/inner")?, __WASI_EINVAL);
+    assert_eq!(guest.rename("replaced", "dir/file")?, __WASI_ESUCCESS);
+    assert_eq!(guest.rename("empty", "dir")?, __WASI_ENOTEMPTY);
+    assert_eq!(guest.rename("dir", "empty")?, __WASI_ESUCCESS);
+    assert_eq!(filesystem.metadata(Path::new("/empty/file"))?.st_size, 8);
+    assert!(filesystem.metadata(Path::new("/dir")).is_err());
+    Ok(())
+}
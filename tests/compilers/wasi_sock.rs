@@ -0,0 +1,216 @@
+#![cfg(all(feature = "compiler", feature = "engine", feature = "wasi"))]
+
+//! Testing the sockets preopened for WASI programs against loopback
+//! connections.
+
+use crate::utils::get_store;
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use wasmer::*;
+use wasmer_wasi::types::*;
+use wasmer_wasi::{WasiSocket, WasiState};
+
+/// Re-exports the WASI socket syscalls so that the tests can call them
+/// directly.
+const WAT: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "sock_shutdown" (func $sock_shutdown (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (export "sock_recv" (func $sock_recv))
+        (export "sock_send" (func $sock_send))
+        (export "sock_shutdown" (func $sock_shutdown)))
+"#;
+
+/// The first preopened socket, after the standard streams and the root.
+const SOCKET_FD: i32 = 4;
+
+const SOCKET_RIGHTS: __wasi_rights_t =
+    __WASI_RIGHT_FD_READ | __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_SOCK_SHUTDOWN;
+
+// Where the arguments of the syscalls are put in the memory.
+const IOVECS: i32 = 0;
+const RESULT: i32 = 32;
+const FLAGS: i32 = 40;
+const BUFFER: i32 = 4096;
+
+struct Guest {
+    instance: Instance,
+}
+
+impl Guest {
+    /// Instantiates the module with `socket` preopened at `SOCKET_FD`.
+    fn new(socket: impl Into<WasiSocket>, rights: __wasi_rights_t) -> Result<Self> {
+        let store = get_store(false);
+        let module = Module::new(&store, WAT)?;
+        let mut wasi_env = WasiState::new("wasi_sock")
+            .preopen_socket(socket, rights)
+            .finalize()?;
+        let import_object = wasi_env.import_object(&module)?;
+        let instance = Instance::new(&module, &import_object)?;
+        Ok(Self { instance })
+    }
+
+    fn write_memory(&self, offset: i32, bytes: &[u8]) -> Result<()> {
+        let view = self.instance.exports.get_memory("memory")?.view::<u8>();
+        for (cell, byte) in view[offset as usize..].iter().zip(bytes) {
+            cell.set(*byte);
+        }
+        Ok(())
+    }
+
+    fn read_memory(&self, offset: i32, len: usize) -> Result<Vec<u8>> {
+        let view = self.instance.exports.get_memory("memory")?.view::<u8>();
+        Ok(view[offset as usize..offset as usize + len]
+            .iter()
+            .map(|cell| cell.get())
+            .collect())
+    }
+
+    fn read_u32(&self, offset: i32) -> Result<u32> {
+        let bytes = self.read_memory(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Points the iovecs at consecutive buffers of the given lengths.
+    fn write_iovecs(&self, lens: &[u32]) -> Result<()> {
+        let mut buf = BUFFER as u32;
+        for (i, len) in lens.iter().enumerate() {
+            let iovec = IOVECS + 8 * i as i32;
+            self.write_memory(iovec, &buf.to_le_bytes())?;
+            self.write_memory(iovec + 4, &len.to_le_bytes())?;
+            buf += len;
+        }
+        Ok(())
+    }
+
+    /// Calls the syscall `name`, returning its errno.
+    fn call(&self, name: &str, params: &[Value]) -> Result<__wasi_errno_t> {
+        let results = self.instance.exports.get_function(name)?.call(params)?;
+        Ok(results[0].unwrap_i32() as __wasi_errno_t)
+    }
+
+    /// Receives data into buffers of the given lengths.
+    fn recv(&self, fd: i32, lens: &[u32], flags: __wasi_riflags_t) -> Result<Vec<u8>> {
+        self.write_iovecs(lens)?;
+        let errno = self.call(
+            "sock_recv",
+            &[
+                Value::I32(fd),
+                Value::I32(IOVECS),
+                Value::I32(lens.len() as i32),
+                Value::I32(flags as i32),
+                Value::I32(RESULT),
+                Value::I32(FLAGS),
+            ],
+        )?;
+        assert_eq!(errno, __WASI_ESUCCESS);
+        let len = self.read_u32(RESULT)? as usize;
+        self.read_memory(BUFFER, len)
+    }
+
+    fn send(&self, fd: i32, bytes: &[u8]) -> Result<__wasi_errno_t> {
+        self.write_memory(BUFFER, bytes)?;
+        self.write_iovecs(&[bytes.len() as u32])?;
+        let errno = self.call(
+            "sock_send",
+            &[
+                Value::I32(fd),
+                Value::I32(IOVECS),
+                Value::I32(1),
+                Value::I32(0),
+                Value::I32(RESULT),
+            ],
+        )?;
+        if errno == __WASI_ESUCCESS {
+            assert_eq!(self.read_u32(RESULT)? as usize, bytes.len());
+        }
+        Ok(errno)
+    }
+
+    fn shutdown(&self, fd: i32, how: __wasi_sdflags_t) -> Result<__wasi_errno_t> {
+        self.call("sock_shutdown", &[Value::I32(fd), Value::I32(how as i32)])
+    }
+}
+
+/// Connects a client to a loopback listener, returning both ends.
+fn tcp_pair() -> Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let client = TcpStream::connect(listener.local_addr()?)?;
+    let (server, _) = listener.accept()?;
+    Ok((client, server))
+}
+
+#[test]
+fn tcp_stream_sends_and_receives() -> Result<()> {
+    let (mut client, server) = tcp_pair()?;
+    let guest = Guest::new(server, SOCKET_RIGHTS)?;
+
+    client.write_all(b"hello from the host")?;
+    let peeked = guest.recv(SOCKET_FD, &[19], __WASI_SOCK_RECV_PEEK)?;
+    assert_eq!(peeked, b"hello from the host");
+    let received = guest.recv(SOCKET_FD, &[5, 14], __WASI_SOCK_RECV_WAITALL)?;
+    assert_eq!(received, b"hello from the host");
+
+    assert_eq!(
+        guest.send(SOCKET_FD, b"hello from the guest")?,
+        __WASI_ESUCCESS
+    );
+    assert_eq!(guest.shutdown(SOCKET_FD, __WASI_SHUT_WR)?, __WASI_ESUCCESS);
+    let mut sent = String::new();
+    client.read_to_string(&mut sent)?;
+    assert_eq!(sent, "hello from the guest");
+    Ok(())
+}
+
+#[test]
+fn socket_rights_are_checked() -> Result<()> {
+    let (_client, server) = tcp_pair()?;
+    let guest = Guest::new(server, __WASI_RIGHT_FD_READ)?;
+
+    assert_eq!(guest.send(SOCKET_FD, b"denied")?, __WASI_ENOTCAPABLE);
+    assert_eq!(
+        guest.shutdown(SOCKET_FD, __WASI_SHUT_RD | __WASI_SHUT_WR)?,
+        __WASI_ENOTCAPABLE
+    );
+    Ok(())
+}
+
+#[test]
+fn socket_syscalls_reject_other_fds() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let guest = Guest::new(listener, SOCKET_RIGHTS)?;
+
+    assert_eq!(guest.send(SOCKET_FD, b"listener")?, __WASI_ENOTCONN);
+    assert_eq!(guest.shutdown(SOCKET_FD, __WASI_SHUT_RD)?, __WASI_ENOTCONN);
+    assert_eq!(
+        guest.send(__WASI_STDOUT_FILENO as i32, b"stdout")?,
+        __WASI_ENOTSOCK
+    );
+    assert_eq!(guest.shutdown(SOCKET_FD + 1, __WASI_SHUT_RD)?, __WASI_EBADF);
+    assert_eq!(guest.shutdown(SOCKET_FD, 0)?, __WASI_EINVAL);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_stream_sends_and_receives() -> Result<()> {
+    use std::os::unix::net::UnixStream;
+
+    let (mut host, guest_end) = UnixStream::pair()?;
+    let guest = Guest::new(guest_end, SOCKET_RIGHTS)?;
+
+    host.write_all(b"ping")?;
+    assert_eq!(guest.recv(SOCKET_FD, &[4], __WASI_SOCK_RECV_PEEK)?, b"ping");
+    assert_eq!(guest.recv(SOCKET_FD, &[16], 0)?, b"ping");
+    assert_eq!(guest.send(SOCKET_FD, b"pong")?, __WASI_ESUCCESS);
+    let mut reply = [0; 4];
+    host.read_exact(&mut reply)?;
+    assert_eq!(&reply, b"pong");
+
+    assert_eq!(guest.shutdown(SOCKET_FD, __WASI_SHUT_WR)?, __WASI_ESUCCESS);
+    assert_eq!(host.read(&mut reply)?, 0);
+    Ok(())
+}
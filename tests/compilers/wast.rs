@@ -49,14 +49,23 @@ pub fn run_wast(wast_path: &str, compiler: &str) -> anyhow::Result<()> {
     let mut features = Features::default();
     let is_bulkmemory = wast_path.contains("bulk-memory");
     let is_simd = wast_path.contains("simd");
+    let is_reference_types = wast_path.contains("reference-types");
+    let is_threads = wast_path.contains("threads");
+    let is_tail_call = wast_path.contains("tail-call");
     if is_bulkmemory {
         features.bulk_memory(true);
     }
     if is_simd {
         features.simd(true);
     }
-    if cfg!(feature = "test-singlepass") {
-        features.multi_value(false);
+    if is_reference_types {
+        features.reference_types(true);
+    }
+    if is_threads {
+        features.threads(true);
+    }
+    if is_tail_call {
+        features.tail_call(true);
     }
     let store = get_store(features, try_nan_canonicalization);
     let mut wast = Wast::new_with_spectest(store);
@@ -85,13 +94,6 @@ pub fn run_wast(wast_path: &str, compiler: &str) -> anyhow::Result<()> {
             "Validation error: Invalid var_u32",
         ]);
     }
-    if compiler == "singlepass" {
-        // We don't support multivalue yet in singlepass
-        wast.allow_instantiation_failures(&[
-            "Validation error: invalid result arity: func type returns multiple values",
-            "Validation error: blocks, loops, and ifs accept no parameters when multi-value is not enabled",
-        ]);
-    }
     wast.fail_fast = false;
     let path = Path::new(wast_path);
     wast.run_file(path)
@@ -0,0 +1,175 @@
+;; `table.fill` on funcref tables, ported from the reference-types spec test
+;; `table_fill.wast`, which still uses `anyref` and `ref.host` values at the
+;; boundary of the module. Here the function returning `n` stands for
+;; `(ref.host n)`, and `0` stands for `(ref.null)`.
+
+(module
+  (type $host (func (result i32)))
+  (table $t 10 funcref)
+  (table $hosts 7 funcref)
+  (elem (table $hosts) (i32.const 1) func $h1 $h2 $h3 $h4 $h5 $h6)
+
+  (func $h1 (result i32) (i32.const 1))
+  (func $h2 (result i32) (i32.const 2))
+  (func $h3 (result i32) (i32.const 3))
+  (func $h4 (result i32) (i32.const 4))
+  (func $h5 (result i32) (i32.const 5))
+  (func $h6 (result i32) (i32.const 6))
+
+  (func $ref (param $n i32) (result funcref)
+    (table.get $hosts (local.get $n))
+  )
+
+  (func (export "fill") (param $i i32) (param $r i32) (param $n i32)
+    (table.fill $t (local.get $i) (call $ref (local.get $r)) (local.get $n))
+  )
+
+  (func (export "get") (param $i i32) (result i32)
+    (if (result i32) (ref.is_null (table.get $t (local.get $i)))
+      (then (i32.const 0))
+      (else (call_indirect $t (type $host) (local.get $i)))
+    )
+  )
+)
+
+(assert_return (invoke "get" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 2)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 3)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 5)) (i32.const 0))
+
+(assert_return (invoke "fill" (i32.const 2) (i32.const 1) (i32.const 3)))
+(assert_return (invoke "get" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 2)) (i32.const 1))
+(assert_return (invoke "get" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 1))
+(assert_return (invoke "get" (i32.const 5)) (i32.const 0))
+
+(assert_return (invoke "fill" (i32.const 4) (i32.const 2) (i32.const 2)))
+(assert_return (invoke "get" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 2))
+(assert_return (invoke "get" (i32.const 5)) (i32.const 2))
+(assert_return (invoke "get" (i32.const 6)) (i32.const 0))
+
+(assert_return (invoke "fill" (i32.const 4) (i32.const 3) (i32.const 0)))
+(assert_return (invoke "get" (i32.const 3)) (i32.const 1))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 2))
+(assert_return (invoke "get" (i32.const 5)) (i32.const 2))
+
+(assert_return (invoke "fill" (i32.const 8) (i32.const 4) (i32.const 2)))
+(assert_return (invoke "get" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 8)) (i32.const 4))
+(assert_return (invoke "get" (i32.const 9)) (i32.const 4))
+
+(assert_return (invoke "fill" (i32.const 9) (i32.const 0) (i32.const 1)))
+(assert_return (invoke "get" (i32.const 8)) (i32.const 4))
+(assert_return (invoke "get" (i32.const 9)) (i32.const 0))
+
+(assert_return (invoke "fill" (i32.const 10) (i32.const 5) (i32.const 0)))
+(assert_return (invoke "get" (i32.const 9)) (i32.const 0))
+
+(assert_trap
+  (invoke "fill" (i32.const 8) (i32.const 6) (i32.const 3))
+  "out of bounds"
+)
+(assert_return (invoke "get" (i32.const 7)) (i32.const 0))
+(assert_return (invoke "get" (i32.const 8)) (i32.const 4))
+(assert_return (invoke "get" (i32.const 9)) (i32.const 0))
+
+(assert_trap
+  (invoke "fill" (i32.const 11) (i32.const 0) (i32.const 0))
+  "out of bounds"
+)
+
+(assert_trap
+  (invoke "fill" (i32.const 11) (i32.const 0) (i32.const 10))
+  "out of bounds"
+)
+
+
+;; Type errors
+
+(assert_invalid
+  (module
+    (table $t 10 funcref)
+    (func $type-index-value-length-empty-vs-i32-i32
+      (table.fill $t)
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 10 funcref)
+    (func $type-index-empty-vs-i32
+      (table.fill $t (ref.null func) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 10 funcref)
+    (func $type-value-empty-vs
+      (table.fill $t (i32.const 1) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 10 funcref)
+    (func $type-length-empty-vs-i32
+      (table.fill $t (i32.const 1) (ref.null func))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-index-f32-vs-i32
+      (table.fill $t (f32.const 1) (ref.null func) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-value-vs-funcref (param $r externref)
+      (table.fill $t (i32.const 1) (local.get $r) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-length-f32-vs-i32
+      (table.fill $t (i32.const 1) (ref.null func) (f32.const 1))
+    )
+  )
+  "type mismatch"
+)
+
+(assert_invalid
+  (module
+    (table $t1 1 externref)
+    (table $t2 1 funcref)
+    (func $type-value-externref-vs-funcref-multi (param $r externref)
+      (table.fill $t2 (i32.const 0) (local.get $r) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+
+(assert_invalid
+  (module
+    (table $t 1 funcref)
+    (func $type-result-empty-vs-num (result i32)
+      (table.fill $t (i32.const 0) (ref.null func) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
@@ -0,0 +1,199 @@
+;; `table.grow` on funcref tables, ported from the reference-types spec test
+;; `table_grow.wast`, which still uses `anyref` and `ref.host` values at the
+;; boundary of the module. Here the function returning `n` stands for
+;; `(ref.host n)`, and `0` stands for `(ref.null)`.
+
+(module
+  (type $host (func (result i32)))
+  (table $t 0 funcref)
+  (table $hosts 5 funcref)
+  (elem (table $hosts) (i32.const 1) func $h1 $h2 $h3 $h4)
+
+  (func $h1 (result i32) (i32.const 1))
+  (func $h2 (result i32) (i32.const 2))
+  (func $h3 (result i32) (i32.const 3))
+  (func $h4 (result i32) (i32.const 4))
+
+  (func $ref (param $n i32) (result funcref)
+    (table.get $hosts (local.get $n))
+  )
+
+  (func (export "get") (param $i i32) (result i32)
+    (if (result i32) (ref.is_null (table.get $t (local.get $i)))
+      (then (i32.const 0))
+      (else (call_indirect $t (type $host) (local.get $i)))
+    )
+  )
+  (func (export "set") (param $i i32) (param $r i32)
+    (table.set $t (local.get $i) (call $ref (local.get $r)))
+  )
+
+  (func (export "grow") (param $sz i32) (param $init i32) (result i32)
+    (table.grow $t (call $ref (local.get $init)) (local.get $sz))
+  )
+  (func (export "size") (result i32) (table.size $t))
+)
+
+(assert_return (invoke "size") (i32.const 0))
+(assert_trap (invoke "set" (i32.const 0) (i32.const 2)) "out of bounds table access")
+(assert_trap (invoke "get" (i32.const 0)) "out of bounds table access")
+
+(assert_return (invoke "grow" (i32.const 1) (i32.const 0)) (i32.const 0))
+(assert_return (invoke "size") (i32.const 1))
+(assert_return (invoke "get" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "set" (i32.const 0) (i32.const 2)))
+(assert_return (invoke "get" (i32.const 0)) (i32.const 2))
+(assert_trap (invoke "set" (i32.const 1) (i32.const 2)) "out of bounds table access")
+(assert_trap (invoke "get" (i32.const 1)) "out of bounds table access")
+
+(assert_return (invoke "grow" (i32.const 4) (i32.const 3)) (i32.const 1))
+(assert_return (invoke "size") (i32.const 5))
+(assert_return (invoke "get" (i32.const 0)) (i32.const 2))
+(assert_return (invoke "set" (i32.const 0) (i32.const 2)))
+(assert_return (invoke "get" (i32.const 0)) (i32.const 2))
+(assert_return (invoke "get" (i32.const 1)) (i32.const 3))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 3))
+(assert_return (invoke "set" (i32.const 4) (i32.const 4)))
+(assert_return (invoke "get" (i32.const 4)) (i32.const 4))
+(assert_trap (invoke "set" (i32.const 5) (i32.const 2)) "out of bounds table access")
+(assert_trap (invoke "get" (i32.const 5)) "out of bounds table access")
+
+
+;; Reject growing to size outside i32 value range
+(module
+  (table $t 0x10 funcref)
+  (elem (i32.const 0) $f)
+  (func $f (export "grow") (result i32)
+    (table.grow $t (ref.func $f) (i32.const 0xffff_fff0))
+  )
+)
+
+(assert_return (invoke "grow") (i32.const -1))
+
+
+(module
+  (table $t 0 funcref)
+  (func (export "grow") (param i32) (result i32)
+    (table.grow $t (ref.null func) (local.get 0))
+  )
+)
+
+(assert_return (invoke "grow" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "grow" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "grow" (i32.const 2)) (i32.const 1))
+(assert_return (invoke "grow" (i32.const 800)) (i32.const 3))
+
+
+(module
+  (table $t 0 10 funcref)
+  (func (export "grow") (param i32) (result i32)
+    (table.grow $t (ref.null func) (local.get 0))
+  )
+)
+
+(assert_return (invoke "grow" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "grow" (i32.const 2)) (i32.const 2))
+(assert_return (invoke "grow" (i32.const 6)) (i32.const 4))
+(assert_return (invoke "grow" (i32.const 0)) (i32.const 10))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const -1))
+(assert_return (invoke "grow" (i32.const 0x10000)) (i32.const -1))
+
+
+(module
+  (table $t 10 funcref)
+  ;; Declares `$check-table-null` for `ref.func` without storing it in `$t`.
+  (table $declare 1 funcref)
+  (elem (table $declare) (i32.const 0) func $check-table-null)
+  (func (export "grow") (param i32) (result i32)
+    (table.grow $t (ref.null func) (local.get 0))
+  )
+  (func $check-table-null (export "check-table-null") (param i32 i32) (result i32)
+    (local funcref)
+    (local.set 2 (ref.func $check-table-null))
+    (block
+      (loop
+        (local.set 2 (table.get $t (local.get 0)))
+        (br_if 1 (i32.eqz (ref.is_null (local.get 2))))
+        (br_if 1 (i32.ge_u (local.get 0) (local.get 1)))
+        (local.set 0 (i32.add (local.get 0) (i32.const 1)))
+        (br_if 0 (i32.le_u (local.get 0) (local.get 1)))
+      )
+    )
+    (ref.is_null (local.get 2))
+  )
+)
+
+(assert_return (invoke "check-table-null" (i32.const 0) (i32.const 9)) (i32.const 1))
+(assert_return (invoke "grow" (i32.const 10)) (i32.const 10))
+(assert_return (invoke "check-table-null" (i32.const 0) (i32.const 19)) (i32.const 1))
+
+
+;; Type errors
+
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-init-size-empty-vs-i32-funcref (result i32)
+      (table.grow $t)
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-size-empty-vs-i32 (result i32)
+      (table.grow $t (ref.null func))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-init-empty-vs-funcref (result i32)
+      (table.grow $t (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-size-f32-vs-i32 (result i32)
+      (table.grow $t (ref.null func) (f32.const 1))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 0 funcref)
+    (func $type-init-externref-vs-funcref (param $r externref) (result i32)
+      (table.grow $t (local.get $r) (i32.const 1))
+    )
+  )
+  "type mismatch"
+)
+
+(assert_invalid
+  (module
+    (table $t 1 funcref)
+    (func $type-result-i32-vs-empty
+      (table.grow $t (ref.null func) (i32.const 0))
+    )
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module
+    (table $t 1 funcref)
+    (func $type-result-i32-vs-f32 (result f32)
+      (table.grow $t (ref.null func) (i32.const 0))
+    )
+  )
+  "type mismatch"
+)
@@ -0,0 +1,64 @@
+;; Table operations on funcref tables, using only `i32` values at the
+;; boundary of the module.
+
+(module
+  (type $ret-i32 (func (result i32)))
+  (table $t 2 funcref)
+  (elem (i32.const 0) $one $two)
+
+  (func $one (result i32) (i32.const 1))
+  (func $two (result i32) (i32.const 2))
+
+  (func (export "size") (result i32)
+    (table.size $t))
+  (func (export "is-null") (param $i i32) (result i32)
+    (ref.is_null (table.get $t (local.get $i))))
+  (func (export "call") (param $i i32) (result i32)
+    (call_indirect $t (type $ret-i32) (local.get $i)))
+  (func (export "set-one") (param $i i32)
+    (table.set $t (local.get $i) (ref.func $one)))
+  (func (export "set-null") (param $i i32)
+    (table.set $t (local.get $i) (ref.null func)))
+  (func (export "copy") (param $dst i32) (param $src i32)
+    (table.set $t (local.get $dst) (table.get $t (local.get $src))))
+  (func (export "grow") (param $delta i32) (result i32)
+    (table.grow $t (table.get $t (i32.const 1)) (local.get $delta)))
+  (func (export "grow-null") (param $delta i32) (result i32)
+    (table.grow $t (ref.null func) (local.get $delta)))
+  (func (export "fill") (param $dst i32) (param $len i32)
+    (table.fill $t (local.get $dst) (ref.func $two) (local.get $len)))
+  (func (export "select-null") (param $c i32) (result i32)
+    (ref.is_null
+      (select (result funcref) (ref.func $one) (ref.null func) (local.get $c))))
+)
+
+(assert_return (invoke "size") (i32.const 2))
+(assert_return (invoke "is-null" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "call" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 2))
+(assert_trap (invoke "is-null" (i32.const 2)) "out of bounds table access")
+
+(invoke "set-null" (i32.const 1))
+(assert_return (invoke "is-null" (i32.const 1)) (i32.const 1))
+(assert_trap (invoke "call" (i32.const 1)) "uninitialized element")
+
+(invoke "copy" (i32.const 1) (i32.const 0))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 1))
+
+(assert_return (invoke "grow" (i32.const 3)) (i32.const 2))
+(assert_return (invoke "size") (i32.const 5))
+(assert_return (invoke "call" (i32.const 4)) (i32.const 1))
+(assert_return (invoke "grow-null" (i32.const 1)) (i32.const 5))
+(assert_return (invoke "is-null" (i32.const 5)) (i32.const 1))
+
+(invoke "fill" (i32.const 2) (i32.const 4))
+(assert_return (invoke "call" (i32.const 2)) (i32.const 2))
+(assert_return (invoke "call" (i32.const 5)) (i32.const 2))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 1))
+(assert_trap (invoke "fill" (i32.const 4) (i32.const 3)) "out of bounds table access")
+
+(invoke "set-one" (i32.const 5))
+(assert_return (invoke "call" (i32.const 5)) (i32.const 1))
+
+(assert_return (invoke "select-null" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "select-null" (i32.const 0)) (i32.const 1))
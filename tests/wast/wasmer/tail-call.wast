@@ -0,0 +1,90 @@
+;; Tail calls to the function itself run in constant stack space, and start
+;; over with the locals that aren't parameters zeroed.
+(module
+  (func $count (export "count") (param i64) (result i64)
+    (local i64)
+    (if (i64.ne (local.get 1) (i64.const 0)) (then (unreachable)))
+    (local.set 1 (i64.const 1))
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (local.get 0))
+      (else (return_call $count (i64.sub (local.get 0) (i64.const 1))))
+    )
+  )
+
+  ;; Rotates the last three parameters as many times as the first one says.
+  ;; The results are returned through memory on some targets.
+  (func $rotate (export "rotate") (param i32 i32 i32 i32) (result i32 i32 i32)
+    (if (result i32 i32 i32) (i32.eqz (local.get 0))
+      (then (local.get 1) (local.get 2) (local.get 3))
+      (else
+        (return_call $rotate
+          (i32.sub (local.get 0) (i32.const 1))
+          (local.get 2) (local.get 3) (local.get 1)
+        )
+      )
+    )
+  )
+
+  ;; The tail call leaves the enclosing blocks and loops.
+  (func $sum (export "sum") (param i64 i64) (result i64)
+    (block $done
+      (loop $again
+        (br_if $done (i64.eqz (local.get 0)))
+        (return_call $sum
+          (i64.sub (local.get 0) (i64.const 1))
+          (i64.add (local.get 1) (local.get 0))
+        )
+      )
+    )
+    (local.get 1)
+  )
+)
+
+(assert_return (invoke "count" (i64.const 0)) (i64.const 0))
+(assert_return (invoke "count" (i64.const 1_000_000)) (i64.const 0))
+
+(assert_return
+  (invoke "rotate" (i32.const 4) (i32.const 1) (i32.const 2) (i32.const 3))
+  (i32.const 2) (i32.const 3) (i32.const 1)
+)
+(assert_return
+  (invoke "rotate" (i32.const 100_000) (i32.const 1) (i32.const 2) (i32.const 3))
+  (i32.const 2) (i32.const 3) (i32.const 1)
+)
+
+(assert_return (invoke "sum" (i64.const 1_000_000) (i64.const 0)) (i64.const 500000500000))
+
+;; The other tail calls can't be guaranteed to run in constant stack space,
+;; so they are rejected.
+(assert_invalid
+  (module
+    (func $a (export "a") (result i32) (return_call $b))
+    (func $b (result i32) (i32.const 1))
+  )
+  "tail call to function 1 from another function"
+)
+(assert_invalid
+  (module
+    (import "spectest" "print_i32" (func $print_i32 (param i32)))
+    (func (export "call-import") (return_call $print_i32 (i32.const 42)))
+  )
+  "tail call to function 0 from another function"
+)
+(assert_invalid
+  (module
+    (type $i64-i64 (func (param i64) (result i64)))
+    (table funcref (elem $count))
+    (func $count (export "count") (param i64) (result i64)
+      (if (result i64) (i64.eqz (local.get 0))
+        (then (local.get 0))
+        (else
+          (return_call_indirect (type $i64-i64)
+            (i64.sub (local.get 0) (i64.const 1))
+            (i32.const 0)
+          )
+        )
+      )
+    )
+  )
+  "indirect tail call"
+)
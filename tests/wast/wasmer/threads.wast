@@ -0,0 +1,63 @@
+;; Atomic wait and notify on a shared memory, from a single thread.
+
+(module
+  (memory 1 1 shared)
+
+  (func (export "init") (param $addr i32) (param $value i64)
+    (i64.atomic.store (local.get $addr) (local.get $value)))
+
+  (func (export "wait32") (param $addr i32) (param $expected i32) (param $timeout i64) (result i32)
+    (memory.atomic.wait32 (local.get $addr) (local.get $expected) (local.get $timeout)))
+
+  (func (export "wait64") (param $addr i32) (param $expected i64) (param $timeout i64) (result i32)
+    (memory.atomic.wait64 (local.get $addr) (local.get $expected) (local.get $timeout)))
+
+  (func (export "wait32-offset") (param $addr i32) (result i32)
+    (memory.atomic.wait32 offset=65532 (local.get $addr) (i32.const 0) (i64.const 0)))
+
+  (func (export "notify") (param $addr i32) (param $count i32) (result i32)
+    (memory.atomic.notify (local.get $addr) (local.get $count)))
+
+  (func (export "notify-offset") (param $addr i32) (result i32)
+    (memory.atomic.notify offset=65536 (local.get $addr) (i32.const 1)))
+)
+
+(invoke "init" (i32.const 8) (i64.const 0x1_0000_0002))
+
+;; The value doesn't match.
+(assert_return (invoke "wait32" (i32.const 8) (i32.const 1) (i64.const -1)) (i32.const 1))
+(assert_return (invoke "wait64" (i32.const 8) (i64.const 2) (i64.const -1)) (i32.const 1))
+
+;; The value matches, but nobody notifies us.
+(assert_return (invoke "wait32" (i32.const 8) (i32.const 2) (i64.const 0)) (i32.const 2))
+(assert_return (invoke "wait64" (i32.const 8) (i64.const 0x1_0000_0002) (i64.const 1000)) (i32.const 2))
+(assert_return (invoke "wait32-offset" (i32.const 0)) (i32.const 2))
+
+;; Nobody is waiting.
+(assert_return (invoke "notify" (i32.const 8) (i32.const 1)) (i32.const 0))
+(assert_return (invoke "notify" (i32.const 0) (i32.const 0)) (i32.const 0))
+
+(assert_trap (invoke "wait32" (i32.const 65536) (i32.const 0) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "wait64" (i32.const 65532) (i64.const 0) (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "wait32-offset" (i32.const 4)) "out of bounds memory access")
+(assert_trap (invoke "notify" (i32.const 65536) (i32.const 1)) "out of bounds memory access")
+(assert_trap (invoke "notify-offset" (i32.const 0)) "out of bounds memory access")
+(assert_trap (invoke "notify-offset" (i32.const -1)) "out of bounds memory access")
+
+(assert_trap (invoke "wait32" (i32.const 2) (i32.const 0) (i64.const 0)) "unaligned atomic access")
+(assert_trap (invoke "wait64" (i32.const 4) (i64.const 0) (i64.const 0)) "unaligned atomic access")
+(assert_trap (invoke "notify" (i32.const 1) (i32.const 1)) "unaligned atomic access")
+
+;; Waiting on an unshared memory traps, but notifying it wakes up nobody.
+(module
+  (memory 1 1)
+
+  (func (export "wait32") (result i32)
+    (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const 0)))
+
+  (func (export "notify") (result i32)
+    (memory.atomic.notify (i32.const 0) (i32.const 1)))
+)
+
+(assert_trap (invoke "wait32") "atomic wait on non-shared memory")
+(assert_return (invoke "notify") (i32.const 0))